@@ -0,0 +1,212 @@
+// battle-core/benches/serialization.rs
+//
+// JSON vs binary (binary.rs - MessagePack and bincode frames) encoding of the
+// WASM-boundary payloads for a 1000-unit battle: one busy TickResult and the
+// full get_results snapshot.
+// Also compares simulate_tick with simulate_tick_delta over a 5000-unit
+// standoff where most ships hold position and only get nudged by collision
+// avoidance.
+// Encoded sizes are printed before the timings:
+//   cargo bench --bench serialization
+
+use battle_core::battle_unit::{BattleUnit, Weapon};
+use battle_core::binary;
+use battle_core::rng::SimulationMode;
+use battle_core::config::BattleConfig;
+use battle_core::simulator::{BattleSimulator, DeltaTickResult, TickResult};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+const DT: f32 = 0.05;
+const UNITS: usize = 1000;
+const STANDOFF_UNITS: usize = 5000;
+const STANDOFF_WARMUP_TICKS: usize = 100;
+const STANDOFF_TICKS: usize = 200;
+
+/// Two AI fleets closing on each other - every tick has movement and hits
+fn fleets(count: usize) -> Vec<BattleUnit> {
+    let mut seed: u32 = 12345;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as f32 / (1u32 << 24) as f32
+    };
+    let side = (count as f32 / 2.0).cbrt() * 60.0;
+
+    (0..count)
+        .map(|i| {
+            let faction = (i % 2) as u32 + 1;
+            let offset = if faction == 1 { -300.0 } else { 300.0 };
+            BattleUnit {
+                id: i as u32 + 1,
+                faction_id: faction,
+                pos_x: offset + next() * side,
+                pos_y: next() * side,
+                pos_z: next() * side,
+                max_hp: 1.0e6,
+                hp: 1.0e6,
+                max_shield: 500.0,
+                shield: 500.0,
+                is_ship: true,
+                ai_controlled: true,
+                view_range: 2000.0,
+                max_speed: 40.0,
+                weapons: vec![Weapon {
+                    tag: "LASER".to_string(),
+                    dps: 20.0,
+                    cooldown: 0.5,
+                    max_range: 400.0,
+                    optimal_range: 300.0,
+                    last_fired: 1.0,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// The simulator a few seconds in, plus its busiest recent tick
+fn battle() -> (BattleSimulator, TickResult) {
+    let mut sim = BattleSimulator::new(fleets(UNITS), 1000.0);
+    let mut busiest = sim.simulate_tick(DT, 1000.0);
+    for i in 1..60 {
+        let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+        let size = |r: &TickResult| r.moved.len() + r.damaged.len() + r.weapons_fired.len();
+        if size(&result) > size(&busiest) {
+            busiest = result;
+        }
+    }
+    (sim, busiest)
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let (sim, tick) = battle();
+    let units = sim.get_units();
+
+    println!(
+        "TickResult ({} moved, {} damaged, {} fired): json {} bytes, binary {} bytes, bincode {} bytes",
+        tick.moved.len(), tick.damaged.len(), tick.weapons_fired.len(),
+        serde_json::to_vec(&tick).unwrap().len(),
+        binary::encode(&tick).unwrap().len(),
+        binary::encode_frame(&tick).unwrap().len(),
+    );
+    println!(
+        "get_results ({} units): json {} bytes, binary {} bytes",
+        units.len(),
+        serde_json::to_vec(units).unwrap().len(),
+        binary::encode(units).unwrap().len(),
+    );
+
+    let mut group = c.benchmark_group("encode_tick_result");
+    group.bench_function("json", |b| b.iter(|| black_box(serde_json::to_string(&tick).unwrap())));
+    group.bench_function("binary", |b| b.iter(|| black_box(binary::encode(&tick).unwrap())));
+    group.bench_function("bincode", |b| b.iter(|| black_box(binary::encode_frame(&tick).unwrap())));
+    group.finish();
+
+    let mut group = c.benchmark_group("encode_results");
+    group.sample_size(20);
+    group.bench_function("json", |b| b.iter(|| black_box(serde_json::to_string(units).unwrap())));
+    group.bench_function("binary", |b| b.iter(|| black_box(binary::encode(units).unwrap())));
+    group.finish();
+}
+
+/// Two loose walls facing each other inside weapon range - everyone is in a
+/// fight but only drifts as collision avoidance evens out the spacing
+fn standoff() -> BattleSimulator {
+    let mut seed: u32 = 777;
+    let mut jitter = move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        ((seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5) * 3.0
+    };
+    let per_row = 50;
+    let units = (0..STANDOFF_UNITS)
+        .map(|i| {
+            let faction = (i % 2) as u32 + 1;
+            let slot = i / 2;
+            BattleUnit {
+                id: i as u32 + 1,
+                faction_id: faction,
+                pos_x: if faction == 1 { 0.0 } else { 1100.0 } + jitter(),
+                pos_y: (slot / per_row) as f32 * 11.0 + jitter(),
+                pos_z: (slot % per_row) as f32 * 11.0 + jitter(),
+                max_hp: 1.0e6,
+                hp: 1.0e6,
+                max_shield: 100.0,
+                shield: 100.0,
+                shield_regen: 100.0,
+                is_ship: true,
+                ai_controlled: true,
+                view_range: 2000.0,
+                max_speed: 20.0,
+                weapons: vec![Weapon {
+                    tag: "LASER".to_string(),
+                    dps: 1.0,
+                    cooldown: 1.0,
+                    max_range: 1500.0,
+                    optimal_range: 1400.0,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        })
+        .collect();
+    let mut sim = BattleSimulator::new(units, 1000.0);
+    sim.set_config(BattleConfig {
+        collision_avoidance: true,
+        emit_all_positions_every_n_ticks: 50,
+        mode: SimulationMode::Deterministic { seed: 7 },
+        ..Default::default()
+    }).unwrap();
+    sim
+}
+
+/// JSON bytes over STANDOFF_TICKS ticks once the lines have mostly settled,
+/// plain vs delta results
+fn bench_delta(c: &mut Criterion) {
+    let time = |i: usize| 1000.0 + i as f64 * DT as f64;
+    let mut full_sim = standoff();
+    let mut delta_sim = standoff();
+    for i in 0..STANDOFF_WARMUP_TICKS {
+        full_sim.simulate_tick(DT, time(i));
+        delta_sim.simulate_tick_delta(DT, time(i));
+    }
+
+    let (mut full_bytes, mut delta_bytes) = (0, 0);
+    let (mut full_entries, mut delta_entries) = (0, 0);
+    for i in STANDOFF_WARMUP_TICKS..STANDOFF_WARMUP_TICKS + STANDOFF_TICKS {
+        let full = full_sim.simulate_tick(DT, time(i));
+        let DeltaTickResult(delta) = delta_sim.simulate_tick_delta(DT, time(i));
+        full_bytes += serde_json::to_vec(&full).unwrap().len();
+        delta_bytes += serde_json::to_vec(&delta).unwrap().len();
+        full_entries += full.moved.len() + full.damaged.len();
+        delta_entries += delta.moved.len() + delta.damaged.len();
+    }
+    println!(
+        "{} ticks, {} units: full {} bytes ({} moved+damaged), delta {} bytes ({}) - {:.0}% smaller",
+        STANDOFF_TICKS, STANDOFF_UNITS, full_bytes, full_entries, delta_bytes, delta_entries,
+        100.0 * (1.0 - delta_bytes as f64 / full_bytes as f64),
+    );
+
+    let mut group = c.benchmark_group("standoff_tick");
+    group.sample_size(10);
+    let mut i = STANDOFF_WARMUP_TICKS + STANDOFF_TICKS;
+    group.bench_function("full", |b| b.iter(|| {
+        i += 1;
+        let result = full_sim.simulate_tick(DT, time(i));
+        let json = black_box(serde_json::to_string(&result).unwrap());
+        full_sim.recycle_result(result);
+        json
+    }));
+    let mut i = STANDOFF_WARMUP_TICKS + STANDOFF_TICKS;
+    group.bench_function("delta", |b| b.iter(|| {
+        i += 1;
+        let DeltaTickResult(result) = delta_sim.simulate_tick_delta(DT, time(i));
+        let json = black_box(serde_json::to_string(&result).unwrap());
+        delta_sim.recycle_result(result);
+        json
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_delta);
+criterion_main!(benches);
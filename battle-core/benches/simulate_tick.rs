@@ -0,0 +1,138 @@
+// battle-core/benches/simulate_tick.rs
+//
+// Full simulate_tick cost for large two-faction battles. Compare serial and
+// rayon builds (speedup depends on core count):
+//   cargo bench --bench simulate_tick
+//   cargo bench --bench simulate_tick --features parallel
+//
+// targeting_phase clears every target first so the tick is dominated by the
+// targeting read phase (find_best_target for all 10000 units).
+//
+// simulate_tick_threads (parallel build only) runs the 5000-unit tick on
+// rayon pools of 1, 2 and 4 threads, so the speedup shows in one run.
+// Measured so far - a 1-core sandbox, where extra threads can only cost:
+//   1t: [3.01 ms 3.27 ms 3.52 ms]
+//   2t: [2.99 ms 3.37 ms 3.91 ms]
+//   4t: [3.50 ms 3.74 ms 3.94 ms]
+// The multi-core numbers haven't been taken yet - run it on a 4+ core machine
+// and record them here before relying on the parallel feature.
+
+use battle_core::battle_unit::{BattleUnit, Weapon};
+use battle_core::simulator::BattleSimulator;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+
+const DT: f32 = 0.05;
+
+/// Two fleets facing each other, close enough that everyone has a target.
+/// Huge hull so nobody dies mid-benchmark.
+fn fleets(count: usize) -> Vec<BattleUnit> {
+    let mut seed: u32 = 12345;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as f32 / (1u32 << 24) as f32
+    };
+    let side = (count as f32 / 2.0).cbrt() * 60.0;
+
+    (0..count)
+        .map(|i| {
+            let faction = (i % 2) as u32 + 1;
+            let offset = if faction == 1 { -200.0 } else { 200.0 };
+            BattleUnit {
+                id: i as u32 + 1,
+                faction_id: faction,
+                pos_x: offset + next() * side,
+                pos_y: next() * side,
+                pos_z: next() * side,
+                max_hp: 1.0e9,
+                hp: 1.0e9,
+                is_ship: true,
+                weapons: vec![Weapon {
+                    tag: "LASER".to_string(),
+                    cooldown: 0.5,
+                    max_range: 1000.0,
+                    optimal_range: 800.0,
+                    last_fired: 1.0,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Battle with targets already acquired, and the time of its last tick
+fn warmed_up(count: usize) -> (BattleSimulator, f64) {
+    let mut sim = BattleSimulator::new(fleets(count), 1000.0);
+    let mut time = 1000.0;
+    for _ in 0..5 {
+        time += DT as f64;
+        sim.simulate_tick(DT, time);
+    }
+    (sim, time)
+}
+
+fn bench_tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulate_tick");
+    group.sample_size(10);
+    let label = if cfg!(feature = "parallel") { "parallel" } else { "serial" };
+
+    for &count in &[5000usize, 10000] {
+        let (mut sim, mut time) = warmed_up(count);
+        group.bench_function(BenchmarkId::new(label, count), |b| {
+            b.iter(|| {
+                time += DT as f64;
+                black_box(sim.simulate_tick(DT, time))
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// The parallel build on rayon pools of 1, 2 and 4 threads - the speedup
+/// in one run, on a machine with that many cores
+#[cfg(feature = "parallel")]
+fn bench_threads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulate_tick_threads");
+    group.sample_size(10);
+
+    for threads in [1, 2, 4] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let (mut sim, mut time) = warmed_up(5000);
+        group.bench_function(BenchmarkId::new(format!("{}t", threads), 5000), |b| {
+            b.iter(|| {
+                time += DT as f64;
+                pool.install(|| black_box(sim.simulate_tick(DT, time)))
+            });
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(not(feature = "parallel"))]
+fn bench_threads(_: &mut Criterion) {}
+
+fn bench_targeting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("targeting_phase");
+    group.sample_size(10);
+    let label = if cfg!(feature = "parallel") { "parallel" } else { "serial" };
+
+    let count = 10000;
+    let mut sim = BattleSimulator::new(fleets(count), 1000.0);
+    let mut time = 1000.0;
+
+    group.bench_function(BenchmarkId::new(label, count), |b| {
+        b.iter(|| {
+            sim.force_retarget_all();
+            time += DT as f64;
+            black_box(sim.simulate_tick(DT, time))
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_tick, bench_targeting, bench_threads);
+criterion_main!(benches);
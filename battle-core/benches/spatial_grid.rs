@@ -0,0 +1,116 @@
+// battle-core/benches/spatial_grid.rs
+//
+// Fixed 100.0 cell size vs density-tuned cell size
+// Each iteration rebuilds the grid and runs one weapon-range query per unit,
+// matching what simulate_tick does in its grid + targeting phases.
+//
+// Also get_nearby (whole cells) vs get_in_radius (exact sphere) for typical
+// weapon ranges; the false-positive rate get_in_radius removes is printed.
+//
+// get_in_radius_dense packs many units per cell so the distance loop dominates;
+// compare runs with and without the SSE path:
+//   cargo bench --bench spatial_grid -- dense
+//   cargo bench --bench spatial_grid --features simd -- dense
+//
+// Run: cargo bench --bench spatial_grid
+
+use battle_core::spatial_grid::SpatialGrid;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+const QUERY_RANGE: f32 = 300.0;
+
+/// Deterministic positions spread through a sphere-ish cube of `radius`
+fn positions(count: usize, radius: f32) -> Vec<(f32, f32, f32)> {
+    let mut seed: u32 = 12345;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+    };
+    (0..count).map(|_| (next() * radius, next() * radius, next() * radius)).collect()
+}
+
+fn build_and_query(grid: &mut SpatialGrid, points: &[(f32, f32, f32)]) -> usize {
+    grid.clear();
+    for (idx, &(x, y, z)) in points.iter().enumerate() {
+        grid.insert(idx, x, y, z);
+    }
+    points.iter()
+        .map(|&(x, y, z)| grid.get_nearby(x, y, z, QUERY_RANGE).len())
+        .sum()
+}
+
+fn bench_cell_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grid_build_and_query");
+
+    for &count in &[100usize, 1000, 10000] {
+        let radius = 2000.0 * (count as f32 / 100.0).cbrt();
+        let points = positions(count, radius);
+
+        group.bench_with_input(BenchmarkId::new("fixed_100", count), &points, |b, points| {
+            let mut grid = SpatialGrid::new(100.0);
+            b.iter(|| black_box(build_and_query(&mut grid, points)));
+        });
+
+        group.bench_with_input(BenchmarkId::new("auto_tuned", count), &points, |b, points| {
+            let mut grid = SpatialGrid::with_auto_cell_size(count, radius);
+            b.iter(|| black_box(build_and_query(&mut grid, points)));
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_in_radius(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nearby_vs_in_radius");
+    let count = 1000;
+    let radius = 2000.0 * (count as f32 / 100.0).cbrt();
+    let points = positions(count, radius);
+
+    let mut grid = SpatialGrid::with_auto_cell_size(count, radius);
+    for (idx, &(x, y, z)) in points.iter().enumerate() {
+        grid.insert(idx, x, y, z);
+    }
+
+    for &range in &[100.0f32, 300.0, 1000.0] {
+        let nearby: usize = points.iter().map(|&(x, y, z)| grid.get_nearby(x, y, z, range).len()).sum();
+        let exact: usize = points.iter().map(|&(x, y, z)| grid.get_in_radius(x, y, z, range).len()).sum();
+        eprintln!(
+            "range {:>6.0}: get_nearby {} candidates, get_in_radius {} ({:.1}% false positives removed)",
+            range, nearby, exact, 100.0 * (nearby - exact) as f64 / nearby.max(1) as f64
+        );
+
+        group.bench_with_input(BenchmarkId::new("get_nearby", range as u32), &range, |b, &range| {
+            b.iter(|| points.iter().map(|&(x, y, z)| black_box(grid.get_nearby(x, y, z, range)).len()).sum::<usize>());
+        });
+
+        group.bench_with_input(BenchmarkId::new("get_in_radius", range as u32), &range, |b, &range| {
+            b.iter(|| points.iter().map(|&(x, y, z)| black_box(grid.get_in_radius(x, y, z, range)).len()).sum::<usize>());
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_in_radius_dense(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_in_radius_dense");
+    let label = if cfg!(feature = "simd") { "simd" } else { "scalar" };
+
+    for &count in &[1000usize, 10000] {
+        let points = positions(count, 1000.0);
+        let mut grid = SpatialGrid::new(500.0);
+        for (idx, &(x, y, z)) in points.iter().enumerate() {
+            grid.insert(idx, x, y, z);
+        }
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new(label, count), &points, |b, points| {
+            b.iter(|| points.iter().map(|&(x, y, z)| black_box(grid.get_in_radius(x, y, z, QUERY_RANGE)).len()).sum::<usize>());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_cell_size, bench_in_radius, bench_in_radius_dense);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+// battle-core/benches/spatial_index.rs
+//
+// SpatialGrid vs Octree on sparse 10000-unit battlefields: fleets clustered
+// tightly but spread across a huge volume, the case a uniform grid handles worst.
+// Each iteration rebuilds the index and runs one weapon-range query per unit.
+//
+// Run: cargo bench --bench spatial_index
+
+use battle_core::octree::Octree;
+use battle_core::spatial_grid::SpatialGrid;
+use battle_core::spatial_index::SpatialIndex;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+
+const UNIT_COUNT: usize = 10000;
+const CLUSTER_RADIUS: f32 = 500.0;
+const QUERY_RANGE: f32 = 300.0;
+
+/// Deterministic positions: `clusters` fleets scattered through a cube of `spread`
+fn sparse_positions(clusters: usize, spread: f32) -> Vec<(f32, f32, f32)> {
+    let mut seed: u32 = 12345;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+    };
+
+    let centers: Vec<(f32, f32, f32)> = (0..clusters)
+        .map(|_| (next() * spread, next() * spread, next() * spread))
+        .collect();
+
+    (0..UNIT_COUNT)
+        .map(|i| {
+            let c = centers[i % clusters];
+            (
+                c.0 + next() * CLUSTER_RADIUS,
+                c.1 + next() * CLUSTER_RADIUS,
+                c.2 + next() * CLUSTER_RADIUS,
+            )
+        })
+        .collect()
+}
+
+fn build_and_query(index: &mut impl SpatialIndex, points: &[(f32, f32, f32)]) -> usize {
+    index.clear();
+    for (idx, &(x, y, z)) in points.iter().enumerate() {
+        index.insert(idx, x, y, z);
+    }
+    points.iter()
+        .map(|&(x, y, z)| index.query_range(x, y, z, QUERY_RANGE).len())
+        .sum()
+}
+
+fn bench_sparse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sparse_10000_build_and_query");
+    group.sample_size(20);
+
+    for &(clusters, spread) in &[(10usize, 50_000.0f32), (50, 200_000.0), (200, 1_000_000.0)] {
+        let points = sparse_positions(clusters, spread);
+        let label = format!("{}x{}k", clusters, spread as u32 / 1000);
+
+        group.bench_with_input(BenchmarkId::new("grid_auto", &label), &points, |b, points| {
+            let mut grid = SpatialGrid::with_auto_cell_size(UNIT_COUNT, spread);
+            b.iter(|| black_box(build_and_query(&mut grid, points)));
+        });
+
+        group.bench_with_input(BenchmarkId::new("grid_fixed_100", &label), &points, |b, points| {
+            let mut grid = SpatialGrid::new(100.0);
+            b.iter(|| black_box(build_and_query(&mut grid, points)));
+        });
+
+        group.bench_with_input(BenchmarkId::new("octree", &label), &points, |b, points| {
+            let mut tree = Octree::new();
+            b.iter(|| black_box(build_and_query(&mut tree, points)));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_sparse);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+// battle-core/benches/tick_allocations.rs
+//
+// Heap allocations per simulate_tick for a steady-state 1000-unit battle,
+// counted with DHAT. Not a criterion bench - it prints a table:
+//   cargo bench --bench tick_allocations
+//
+// Ticks reuse the simulator's TickBuffers when the result is handed back with
+// recycle_result (as the WASM wrapper does), grid queries included. After the
+// first tick, ticks without shots allocate nothing; each shot still allocates
+// its WeaponFired.weapon_type String and the combat log's copy of the event
+// (plus a log line at log level Debug+).
+
+use battle_core::battle_unit::{BattleUnit, Weapon};
+use battle_core::simulator::BattleSimulator;
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+const DT: f32 = 0.05;
+const UNITS: usize = 1000;
+const TICKS: u64 = 200;
+
+/// Two fleets in range of each other with hulls big enough that nobody dies
+fn fleets(count: usize) -> Vec<BattleUnit> {
+    let mut seed: u32 = 12345;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as f32 / (1u32 << 24) as f32
+    };
+    let side = (count as f32 / 2.0).cbrt() * 60.0;
+
+    (0..count)
+        .map(|i| {
+            let faction = (i % 2) as u32 + 1;
+            let offset = if faction == 1 { -200.0 } else { 200.0 };
+            BattleUnit {
+                id: i as u32 + 1,
+                faction_id: faction,
+                pos_x: offset + next() * side,
+                pos_y: next() * side,
+                pos_z: next() * side,
+                max_hp: 1.0e9,
+                hp: 1.0e9,
+                is_ship: true,
+                weapons: vec![Weapon {
+                    tag: "LASER".to_string(),
+                    cooldown: 0.5,
+                    max_range: 1000.0,
+                    optimal_range: 800.0,
+                    last_fired: 1.0,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn main() {
+    let _profiler = dhat::Profiler::builder().testing().build();
+
+    let mut sim = BattleSimulator::new(fleets(UNITS), 1000.0);
+
+    println!("{:>6} {:>12} {:>12} {:>8}", "tick", "allocs", "bytes", "shots");
+    let mut steady_allocs = 0u64;
+    let mut steady_shots = 0u64;
+    for i in 0..TICKS {
+        let before = dhat::HeapStats::get();
+        let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+        let after = dhat::HeapStats::get();
+
+        let allocs = after.total_blocks - before.total_blocks;
+        let bytes = after.total_bytes - before.total_bytes;
+        let shots = result.weapons_fired.len() as u64;
+        sim.recycle_result(result);
+
+        if i < 5 || i % 20 == 0 {
+            println!("{:>6} {:>12} {:>12} {:>8}", i + 1, allocs, bytes, shots);
+        }
+        if i >= TICKS / 2 {
+            steady_allocs += allocs;
+            steady_shots += shots;
+        }
+    }
+
+    let ticks = (TICKS - TICKS / 2) as f64;
+    println!(
+        "steady state (last {} ticks): {:.1} allocs/tick, {:.1} shots/tick",
+        ticks,
+        steady_allocs as f64 / ticks,
+        steady_shots as f64 / ticks
+    );
+}
@@ -5,18 +5,484 @@
 // 2. Added unit_type string for special handling
 // 3. Added sequence support to Weapon struct
 // 4. Added view_range for detection
+// 5. Added retreat_hp_fraction / retreating / withdrawn for morale-style retreats
+// 6. Added waypoints / current_waypoint for simulator-driven patrols
+// 7. Added ai_controlled flag for simulator-driven combat movement
+// 8. Added retreat_target rally point (retreat_hp_threshold / is_retreating accepted as aliases)
+// 9. Added collision radius (derived from is_station when not sent)
+// 10. Added orbit_mode / orbit_angle for circling targets at optimal range
+// 11. Added ammo / magazine_size / reload_time to Weapon (absent = unlimited)
+// 12. Added lock_time / target_acquired_time - weapons hold fire until a new target is locked
+// 13. Added max_offensive_range() / is_armed() - point defense doesn't count as offensive
+// 14. Added status effects (slow / shield disrupt / burn) and Weapon.applies_effect
+// 15. Added Weapon.independent_targeting for turrets that pick their own targets
+// 16. Added repair weapons (Weapon.is_repair / repairs_shield), repair() and healing_done
+// 17. Added Weapon.is_disabled - disabled weapons never fire (auto or manual)
+// 18. take_damage() returns the damage absorbed (overkill isn't counted) and
+//     ignores dead units
+// 19. Added target_locked - host-pinned targets the AI won't override
+// 20. Added energy capacitor (max_energy / energy / energy_regen) and Weapon.energy_cost
+//     - absent fields leave the system off
+// 21. Added Weapon.sequence_offset - per-weapon phase into the fire sequence
+//     (derived from unit id + weapon index when not sent)
+// 22. Added shots_fired / shots_hit / kills and stats() (CombatStats)
+// 23. Added class (default "generic") for the targeting PriorityTable
+// 24. effects is always serialized (bincode replays need every field present)
+// 25. normalize() draws cooldown jitter from the simulator's BattleRng
+// 26. Added Weapon.shield_pierce / shield_damage_bonus and take_damage_split()
+// 27. Added signature_radius and Weapon.tracking for hit chance (0 = always hit),
+//     external_speed / external_move_time and speed()
+// 28. Added morale (morale / morale_regen_rate / morale_radius /
+//     morale_loss_per_death, is_commander) - shaken units lose accuracy,
+//     broken units retreat
+// 29. Added veterancy (experience / veterancy_level / xp_per_damage /
+//     xp_per_kill), gain_experience() and veterancy() (Veterancy)
+// 30. Added weapon_stats (WeaponStats per weapon, parallel to weapons) and
+//     weapon_stats_by_tag()
+// 31. Added carriers (hangar_capacity / hangar_contents) and is_in_hangar -
+//     docked units are off the battlefield until launched
+// 32. Added state (UnitState: active / destroyed / withdrawn) - alive and
+//     withdrawn are kept as serialized mirrors of it (set_state()) and only
+//     read by normalize() for JSON that has no state
+// 33. Added Weapon supply (ammo_per_shot / ammo_remaining / ammo_capacity) on
+//     top of the magazine, and resupply units (resupply_rate / resupply_range)
+// 34. Added surrender (can_surrender / surrender_hp_threshold / is_surrendered) -
+//     surrendered units stay alive but are out of the battle for good
+// 35. Added disabling (disable_threshold / repair_rate / is_disabled /
+//     quiet_ticks) - disabled units only take siege fire and self-repair
+//     back online; on_battlefield() covers units out of the fight but present
+// 36. Added ship_class (ShipClass, default frigate; stations are always
+//     station) for per-class weapon restrictions
+// 37. Added drone hangars (hangar: DroneHangar) - the simulator builds drones
+//     from a template and recovers them; drones carry carrier_id
+// 38. Added move_order - a destination the simulator moves the unit to at
+//     max_speed, ahead of target chasing and waypoints
+// 39. Added group_id (squads sharing a focus target) and stance (Stance)
+// 40. armor and Weapon.target_armor_max are ArmorClass (names or legacy tier
+//     numbers in JSON); flat_armor overrides the per-hit hull reduction
+// 41. Added BattleUnitBuilder (BattleUnit::builder()) - build() rejects a
+//     missing id / faction or max_hp <= 0
+// 42. Added reactor power (power_shield / power_weapons / power_engines, a
+//     third each by default) - 3x a system's share scales shield regen,
+//     weapon damage and max_speed; set_power() / auto_balance_power()
+// 43. Added self-destruct charges (self_destruct_damage / _radius /
+//     _trigger_radius) - detonate() destroys the unit, the simulator applies
+//     the blast
+// 44. Added stealth_modifier - scales the distance enemies detect the unit
+//     at; sensor_range() is view_range or weapon range, whichever is longer
+// 45. Added Weapon.am_intercept_chance / saturation_factor - point defense
+//     shooting down incoming missiles
+// 46. alive / withdrawn are no longer stored - JSON gets them worked out
+//     from state on the way out, and reads them on the way in for units
+//     without state (see the Serialize / Deserialize impls)
+// 47. Weapon.reloading_until is an Option - a reload finishing at time 0
+//     no longer reads as "not reloading"
 
-use serde::{Deserialize, Serialize};
-use getrandom::getrandom;
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::rng::BattleRng;
+use crate::weapons::WeaponCategory;
+use crate::status_effect::{EffectSpec, StatusEffect, StatusEffectKind};
+use crate::log_at;
+
+/// Morale below this halves accuracy (accuracy_multiplier)
+pub const MORALE_SHAKEN: f32 = 50.0;
+/// Morale below this makes the unit retreat until it's back to MORALE_SHAKEN
+pub const MORALE_BROKEN: f32 = 25.0;
+pub const MAX_MORALE: f32 = 100.0;
+
+/// Each system's share of reactor power by default - stock stats
+pub const BALANCED_POWER: f32 = 1.0 / 3.0;
+/// How far power fractions may sum from 1.0
+pub const POWER_SUM_TOLERANCE: f32 = 0.01;
+/// Hull fraction below which auto_balance_power feeds the shields
+pub const LOW_HULL_POWER_FRACTION: f32 = 0.3;
+
+/// Whether a unit is still in the fight
+///
+/// Withdrawn units left without being destroyed (retreat, removal) - they
+/// aren't targetable and don't count as losses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum UnitState {
+    #[default]
+    Active,
+    Destroyed,
+    Withdrawn,
+}
+
+/// Hull class - the simulator can restrict which weapons each class carries
+/// (see weapons::is_weapon_allowed)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ShipClass {
+    Fighter,
+    Corvette,
+    #[default]
+    Frigate,
+    Destroyer,
+    Cruiser,
+    Battleship,
+    Carrier,
+    Station,
+}
+
+impl std::str::FromStr for ShipClass {
+    type Err = String;
+
+    /// Class from its JSON name, case-insensitive ("Destroyer", "destroyer")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let class = match s.to_ascii_lowercase().as_str() {
+            "fighter" => ShipClass::Fighter,
+            "corvette" => ShipClass::Corvette,
+            "frigate" => ShipClass::Frigate,
+            "destroyer" => ShipClass::Destroyer,
+            "cruiser" => ShipClass::Cruiser,
+            "battleship" => ShipClass::Battleship,
+            "carrier" => ShipClass::Carrier,
+            "station" => ShipClass::Station,
+            _ => return Err(format!("unknown ship class '{}'", s)),
+        };
+        Ok(class)
+    }
+}
+
+/// How freely a unit engages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Stance {
+    /// Fire at will and close on the target
+    #[default]
+    Aggressive,
+    /// Fire at will but hold ground - the simulator never steers it towards
+    /// its target (move orders and waypoints still apply)
+    Defensive,
+    /// Only manually_fire shots - repair weapons keep working
+    HoldFire,
+}
+
+impl std::str::FromStr for Stance {
+    type Err = String;
+
+    /// Stance from its JSON name, case-insensitive ("hold_fire", "HoldFire")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stance = match s.to_ascii_lowercase().replace('_', "").as_str() {
+            "aggressive" => Stance::Aggressive,
+            "defensive" => Stance::Defensive,
+            "holdfire" => Stance::HoldFire,
+            _ => return Err(format!("unknown stance '{}'", s)),
+        };
+        Ok(stance)
+    }
+}
+
+/// Armor tier - weapons lose damage against tiers above their
+/// target_armor_max (see weapons::calculate_armor_effectiveness)
+///
+/// JSON: "none" / "light" / "medium" / "heavy" / "super" (any case), or the
+/// legacy tier number 0-4. Numbers outside that are clamped and logged.
+/// Binary formats that aren't self-describing (bincode) use the tier number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ArmorClass {
+    #[default]
+    None,
+    Light,
+    Medium,
+    Heavy,
+    Super,
+}
+
+impl ArmorClass {
+    const ALL: [ArmorClass; 5] = [ArmorClass::None, ArmorClass::Light, ArmorClass::Medium, ArmorClass::Heavy, ArmorClass::Super];
+
+    /// 0 (None) to 4 (Super)
+    pub fn tier(self) -> u8 {
+        self as u8
+    }
+
+    /// Tier from a number - clamped to 0-4 and rounded, with an error logged
+    /// when the value wasn't a whole tier already
+    pub fn from_tier(value: f64) -> Self {
+        let tier = if value.is_nan() { 0.0 } else { value.round().clamp(0.0, 4.0) };
+        if tier != value {
+            log_at!(Error, "[Unit] Armor {} is not a tier (0-4), using {}", value, tier);
+        }
+        Self::ALL[tier as usize]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ArmorClass::None => "none",
+            ArmorClass::Light => "light",
+            ArmorClass::Medium => "medium",
+            ArmorClass::Heavy => "heavy",
+            ArmorClass::Super => "super",
+        }
+    }
+}
+
+impl std::str::FromStr for ArmorClass {
+    type Err = String;
+
+    /// Armor class from its name, case-insensitive ("heavy", "Heavy")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter()
+            .find(|class| class.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown armor class '{}'", s))
+    }
+}
+
+impl Serialize for ArmorClass {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(self.name())
+        } else {
+            serializer.serialize_u8(self.tier())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ArmorClass {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ArmorVisitor;
+
+        impl serde::de::Visitor<'_> for ArmorVisitor {
+            type Value = ArmorClass;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "an armor class name or tier number")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<ArmorClass, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<ArmorClass, E> {
+                Ok(ArmorClass::from_tier(v as f64))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<ArmorClass, E> {
+                Ok(ArmorClass::from_tier(v as f64))
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<ArmorClass, E> {
+                Ok(ArmorClass::from_tier(v))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(ArmorVisitor)
+        } else {
+            deserializer.deserialize_u8(ArmorVisitor)
+        }
+    }
+}
+
+/// Written as a name; read from a name or a legacy tier number
+#[cfg(feature = "typegen")]
+impl schemars::JsonSchema for ArmorClass {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "ArmorClass".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let names: Vec<&str> = Self::ALL.iter().map(|class| class.name()).collect();
+        if generator.contract().is_serialize() {
+            return schemars::json_schema!({ "type": "string", "enum": names });
+        }
+        schemars::json_schema!({
+            "anyOf": [
+                { "type": "string", "enum": names },
+                { "type": "integer", "minimum": 0, "maximum": 4 }
+            ]
+        })
+    }
+}
+
+/// Unit ids from here up are reserved for drones built by carriers - host
+/// units may only use them for drones (carrier_id set), e.g. from a snapshot
+pub const DRONE_ID_BASE: u32 = 0xF000_0000;
+
+/// Default ticks a carrier goes without enemies before recalling its drones
+/// 100 ticks = 5 seconds at 20 ticks/sec
+pub const DEFAULT_DRONE_RECOVER_TICKS: u32 = 100;
+
+/// Drones a carrier builds and launches itself (see BattleSimulator's hangar
+/// pass)
+///
+/// A store of its own, apart from hangar_capacity / hangar_contents: drones
+/// waiting in `count` (built or recovered) don't take up hangar_capacity,
+/// which only limits units docked with dock_fighter - a drone docked that
+/// way counts like any other unit.
+///
+/// JSON: { "template": { ...BattleUnit... }, "count": 6, "launch_interval": 1.5 }
+/// - the rest is optional or kept by the simulator.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct DroneHangar {
+    /// Unit every drone is built from - faction, player, id and position are
+    /// filled in at launch, view_range raised to the carrier's
+    pub template: Box<BattleUnit>,
+    /// Drones ready to launch - recovered drones go back into it, a
+    /// destroyed one is gone for good
+    pub count: u32,
+    /// Seconds between launches
+    pub launch_interval: f32,
+    /// Ticks without an enemy in view before launched drones are recalled
+    /// (0 = the first tick without one)
+    #[serde(default = "default_recover_ticks")]
+    pub recover_after_ticks: u32,
+    /// Simulated time of the last launch
+    #[serde(default)]
+    pub last_launch: f64,
+    /// Ticks in a row with no enemy in view
+    #[serde(default)]
+    pub idle_ticks: u32,
+}
+
+/// Experience needed for veterancy levels 1-5
+pub const VETERANCY_THRESHOLDS: [f32; 5] = [100.0, 250.0, 500.0, 1000.0, 2000.0];
+
+/// How long (seconds) the speed implied by an external position update counts
+/// towards speed() after it arrived
+pub const EXTERNAL_SPEED_WINDOW: f64 = 1.0;
+
+/// Stable pseudo-random sequence phase for a unit's weapon
+#[inline]
+fn sequence_phase(unit_id: u32, weapon_idx: usize) -> u32 {
+    // splitmix64 finalizer - neighbouring ids land on unrelated phases
+    let mut h = ((unit_id as u64) << 32) | weapon_idx as u64;
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (h ^ (h >> 31)) as u32
+}
+
+/// serde default for BattleUnit.class
+fn generic_class() -> String {
+    "generic".to_string()
+}
+
+/// serde default for Weapon.shield_damage_bonus
+fn no_bonus() -> f32 {
+    1.0
+}
+
+/// serde default for BattleUnit.energy - normalize() clamps it to max_energy
+fn full_energy() -> f32 {
+    f32::MAX
+}
+
+fn default_recover_ticks() -> u32 {
+    DEFAULT_DRONE_RECOVER_TICKS
+}
+
+/// Why set_power refused a split
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerError {
+    /// A fraction outside 0..=1 (or not a number)
+    OutOfRange(f32),
+    /// Fractions that don't add up to 1.0
+    BadSum(f32),
+}
+
+impl fmt::Display for PowerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerError::OutOfRange(fraction) => write!(f, "power fraction {} is outside 0..=1", fraction),
+            PowerError::BadSum(sum) => write!(f, "power fractions sum to {}, not 1.0", sum),
+        }
+    }
+}
+
+impl std::error::Error for PowerError {}
+
+/// Check a shield / weapons / engines split (see BattleUnit::set_power)
+pub fn check_power(shield: f32, weapons: f32, engines: f32) -> Result<(), PowerError> {
+    if let Some(&bad) = [shield, weapons, engines].iter().find(|f| !(0.0..=1.0).contains(*f)) {
+        return Err(PowerError::OutOfRange(bad));
+    }
+    let sum = shield + weapons + engines;
+    if (sum - 1.0).abs() > POWER_SUM_TOLERANCE {
+        return Err(PowerError::BadSum(sum));
+    }
+    Ok(())
+}
+
+fn balanced_power() -> f32 {
+    BALANCED_POWER
+}
+
+fn no_stealth() -> f32 {
+    1.0
+}
+
+fn full_morale() -> f32 {
+    MAX_MORALE
+}
+
+fn default_xp_per_damage() -> f32 {
+    0.1
+}
+
+fn default_xp_per_kill() -> f32 {
+    50.0
+}
+
+/// Damage headed for one unit, split by how it meets the shield
+///
+/// Several hits on the same target in a tick are summed (+=) and applied once.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DamageSplit {
+    /// Raw damage that has to get through the shield first
+    pub shielded: f32,
+    /// What `shielded` is worth against shields (shield_damage_bonus applied)
+    pub vs_shield: f32,
+    /// Raw damage that skips the shield (armor still applies)
+    pub piercing: f32,
+}
+
+impl DamageSplit {
+    pub fn new(damage: f32, shield_pierce: f32, shield_damage_bonus: f32) -> Self {
+        let piercing = damage * shield_pierce.clamp(0.0, 1.0);
+        let shielded = damage - piercing;
+        DamageSplit {
+            shielded,
+            vs_shield: shielded * shield_damage_bonus.max(0.0),
+            piercing,
+        }
+    }
+
+    #[inline]
+    pub fn total(&self) -> f32 {
+        self.shielded + self.piercing
+    }
+}
+
+impl std::ops::AddAssign for DamageSplit {
+    fn add_assign(&mut self, other: Self) {
+        self.shielded += other.shielded;
+        self.vs_shield += other.vs_shield;
+        self.piercing += other.piercing;
+    }
+}
 
 /// Memory-optimized battle unit
 /// 
 /// Uses flat primitives for cache efficiency
 /// ~250 bytes per unit in Rust
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema), schemars(transform = liveness_properties))]
 pub struct BattleUnit {
     // Identity
     pub id: u32,
+    #[cfg_attr(feature = "typegen", schemars(description = "Units of different factions fight unless allied"))]
     pub faction_id: u32,
     pub player_id: Option<u32>,
     
@@ -25,9 +491,35 @@ pub struct BattleUnit {
     pub hp: f32,
     pub max_shield: f32,
     pub shield: f32,
-    pub armor: f32,           // 0=None, 1=Light, 2=Medium, 3=Heavy, 4=Super
+    pub armor: ArmorClass,
+    /// Hull damage taken off every hit (None = 0.5 per armor tier)
+    #[serde(default)]
+    pub flat_armor: Option<f32>,
     pub shield_regen: f32,
     
+    // Capacitor (max_energy 0 = no energy system, weapons are free)
+    #[serde(default)]
+    pub max_energy: f32,
+    #[serde(default = "full_energy")]
+    pub energy: f32,               // Absent = starts full (clamped in normalize)
+    #[serde(default)]
+    pub energy_regen: f32,         // Per second
+
+    // Reactor power - shares for shields / weapons / engines, summing to 1
+    // (see set_power). Each system runs at 3x its share: a third = stock stats
+    #[serde(default = "balanced_power")]
+    pub power_shield: f32,
+    #[serde(default = "balanced_power")]
+    pub power_weapons: f32,
+    #[serde(default = "balanced_power")]
+    pub power_engines: f32,
+
+    // Resupply (resupply_rate 0 = not a supply unit)
+    #[serde(default)]
+    pub resupply_rate: f32,        // Supply per second handed to the most depleted ally in range
+    #[serde(default)]
+    pub resupply_range: f32,
+    
     // Position (flat for cache efficiency)
     pub pos_x: f32,
     pub pos_y: f32,
@@ -38,14 +530,28 @@ pub struct BattleUnit {
     pub vel_y: f32,
     pub vel_z: f32,
     pub max_speed: f32,
+    #[serde(default)]
+    pub radius: f32,          // Collision radius (0 = derive: station 50, ship 10)
+    #[serde(default)]
+    pub signature_radius: f32,     // Size to weapon tracking (0 = always hit, see weapons::hit_chance)
+    #[serde(default)]
+    pub external_speed: f32,       // Speed implied by the last external position update
+    #[serde(default)]
+    pub external_move_time: f64,   // Simulator time of that update
     
     // Weapons
     pub weapons: Vec<Weapon>,
+    #[cfg_attr(feature = "typegen", schemars(description = "Longest weapon max_range - derived from weapons when the simulator normalizes the unit"))]
     pub max_weapon_range: f32,
     
     // ✅ NEW: Unit type info for targeting priority
     #[serde(default)]
     pub unit_type: String,
+    #[serde(default = "generic_class")]
+    #[cfg_attr(feature = "typegen", schemars(description = "Key into the targeting PriorityTable"))]
+    pub class: String,             // Key into the targeting PriorityTable
+    #[serde(default)]
+    pub ship_class: ShipClass,     // Hull class for weapon restrictions (stations: always Station)
     #[serde(default)]
     pub is_ship: bool,
     #[serde(default)]
@@ -53,24 +559,356 @@ pub struct BattleUnit {
     #[serde(default)]
     pub has_weapons: bool,
     #[serde(default)]
+    #[cfg_attr(feature = "typegen", schemars(description = "Sensor range (weapon range when that's longer) - enemies are detected and targets stay valid inside it"))]
     pub view_range: f32,
+    #[serde(default = "no_stealth")]
+    #[cfg_attr(feature = "typegen", schemars(description = "Scales the distance enemies detect this unit at (0.5 = half their sensor range)"))]
+    pub stealth_modifier: f32,
+    #[serde(default)]
+    #[cfg_attr(feature = "typegen", schemars(description = "NPC / offline unit - the simulator moves it itself"))]
+    pub ai_controlled: bool,       // NPC/offline - simulator moves this unit
+    #[serde(default)]
+    pub orbit_mode: bool,          // Circle the target at optimal range instead of stopping
+    #[serde(default)]
+    pub orbit_angle: f32,          // Current orbit angle (radians, XZ plane)
     
     // Combat state
     pub target_id: Option<u32>,
-    pub alive: bool,
+    #[serde(default)]
+    pub state: UnitState,
+    #[serde(default)]
+    pub lock_time: f32,               // Seconds after acquiring a target before weapons may fire
+    #[serde(default)]
+    pub target_acquired_time: f64,    // When target_id last changed (seconds since epoch)
+    #[serde(default)]
+    pub target_locked: bool,          // Target pinned by the host - kept until it dies
+    
+    // Retreat behavior
+    #[serde(default, alias = "retreat_hp_threshold")]
+    pub retreat_hp_fraction: f32,  // Retreat when hp < max_hp * fraction (0 = never)
+    #[serde(default, alias = "is_retreating")]
+    pub retreating: bool,
+    #[serde(default)]
+    pub retreat_target: Option<(f32, f32, f32)>,  // Rally point (None = away from nearest enemy)
+
+    // Surrender - permanent, unlike retreating; the unit stays where it is
+    #[serde(default)]
+    pub can_surrender: bool,
+    #[serde(default)]
+    pub surrender_hp_threshold: f32,  // Surrender when hp / max_hp drops below this
+    #[serde(default)]
+    pub is_surrendered: bool,         // Can't move, fire or be targeted
+
+    // Disabling - shut down instead of destroyed, repairs itself once left alone
+    #[serde(default)]
+    pub disable_threshold: f32,       // Disabled when hp / max_hp drops below this (0 = never)
+    #[serde(default)]
+    pub repair_rate: f32,             // Hp per second while disabled and no enemy ship is near
+    #[serde(default)]
+    pub is_disabled: bool,            // Can't fire; only siege weapons may target it
+    #[serde(default)]
+    pub quiet_ticks: u32,             // Ticks in a row without an enemy ship near (while disabled)
+
+    // Morale (morale_loss_per_death 0 = allied deaths don't matter)
+    #[serde(default = "full_morale")]
+    pub morale: f32,               // 0-100
+    #[serde(default)]
+    pub morale_regen_rate: f32,    // Per second
+    #[serde(default)]
+    pub morale_radius: f32,        // Allied deaths / commanders within this count
+    #[serde(default)]
+    pub morale_loss_per_death: f32,
+    #[serde(default)]
+    pub is_commander: bool,        // Steadies allies that have it within their morale_radius
+
+    // Carriers - the host keeps hangar_contents and is_in_hangar consistent
+    // when sending units; launch/dock calls keep them in sync after that.
+    // hangar_capacity only limits docked units - drones in `hangar` are
+    // counted there (see DroneHangar)
+    #[serde(default)]
+    pub hangar_capacity: u32,
+    #[serde(default)]
+    pub hangar_contents: Vec<u32>,  // Docked unit ids
+    #[serde(default)]
+    pub is_in_hangar: bool,         // Docked in a carrier - can't act or be targeted
+    #[serde(default)]
+    pub hangar: Option<DroneHangar>,  // Drones this carrier builds itself
+    #[serde(default)]
+    pub carrier_id: Option<u32>,    // Carrier that built this drone
+
+    // Self-destruct (self_destruct_damage 0 = no charge) - detonates on
+    // trigger_self_destruct, or once an enemy is within the trigger radius
+    #[serde(default)]
+    pub self_destruct_damage: f32,  // Dealt to every unit in the blast
+    #[serde(default)]
+    pub self_destruct_radius: f32,
+    #[serde(default)]
+    pub self_destruct_trigger_radius: f32,  // 0 = only on command (or caught in another blast)
+    
+    // Waypoint navigation (used when the unit has no target)
+    #[serde(default)]
+    pub waypoints: Vec<(f32, f32, f32)>,
+    #[serde(default)]
+    pub current_waypoint: usize,   // Index into waypoints, wraps for patrol loops
+    #[serde(default)]
+    pub move_order: Option<(f32, f32, f32)>,  // Ordered destination, cleared on arrival
+    #[serde(default)]
+    pub group_id: Option<u32>,     // Squad - members share the group's focus target
+    #[serde(default)]
+    pub stance: Stance,
+    
+    // Status effects (debuffs from weapon hits)
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
     
     // Stats tracking
     pub damage_dealt: f32,
     pub damage_taken: f32,
+    #[serde(default)]
+    pub healing_done: f32,
+    #[serde(default)]
+    pub shots_fired: u32,
+    #[serde(default)]
+    pub shots_hit: u32,            // Shots that did damage (not wasted on a dead target)
+    #[serde(default)]
+    pub kills: u32,
+    #[serde(default)]
+    pub weapon_stats: Vec<WeaponStats>,  // Same order as weapons (see weapon_stats_mut)
+
+    // Veterancy - levels are permanent stat boosts (see gain_experience)
+    #[serde(default)]
+    pub experience: f32,
+    #[serde(default)]
+    pub veterancy_level: u8,       // 0-5, bonuses already in the stats sent
+    #[serde(default = "default_xp_per_damage")]
+    pub xp_per_damage: f32,        // Experience per point of damage dealt
+    #[serde(default = "default_xp_per_kill")]
+    pub xp_per_kill: f32,
+}
+
+// JSON (and MessagePack) readers from before state existed look at alive /
+// withdrawn, so they're written next to it, worked out from state; units
+// without state get it from them on the way in - picked out of the map as
+// the derived impl reads it (flatten would buffer every unit first). Binary
+// formats (bincode replays) only carry state.
+
+/// BattleUnit's own fields plus the derived flags
+#[derive(Serialize)]
+struct LivenessOut<'a> {
+    #[serde(flatten, serialize_with = "BattleUnit::serialize")]
+    unit: &'a BattleUnit,
+    alive: bool,
+    withdrawn: bool,
+}
+
+/// A unit's map with alive / withdrawn taken out along the way
+struct LivenessIn<A> {
+    map: A,
+    alive: Option<bool>,
+    withdrawn: Option<bool>,
+}
+
+/// A key of LivenessIn's map - a BattleUnit field, or one of the flags with
+/// the field seed handed back for the next key
+enum LivenessKey<V, K> {
+    Field(V),
+    Alive(K),
+    Withdrawn(K),
+}
+
+struct LivenessKeySeed<K>(K);
+
+impl<'de, K: DeserializeSeed<'de>> DeserializeSeed<'de> for LivenessKeySeed<K> {
+    type Value = LivenessKey<K::Value, K>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de, K: DeserializeSeed<'de>> Visitor<'de> for LivenessKeySeed<K> {
+    type Value = LivenessKey<K::Value, K>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a field name")
+    }
+
+    fn visit_str<E: de::Error>(self, key: &str) -> Result<Self::Value, E> {
+        match key {
+            "alive" => Ok(LivenessKey::Alive(self.0)),
+            "withdrawn" => Ok(LivenessKey::Withdrawn(self.0)),
+            _ => self.0.deserialize(key.into_deserializer()).map(LivenessKey::Field),
+        }
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for LivenessIn<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, mut seed: K) -> Result<Option<K::Value>, A::Error> {
+        loop {
+            match self.map.next_key_seed(LivenessKeySeed(seed))? {
+                None => return Ok(None),
+                Some(LivenessKey::Field(key)) => return Ok(Some(key)),
+                Some(LivenessKey::Alive(next)) => {
+                    self.alive = Some(self.map.next_value()?);
+                    seed = next;
+                }
+                Some(LivenessKey::Withdrawn(next)) => {
+                    self.withdrawn = Some(self.map.next_value()?);
+                    seed = next;
+                }
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        self.map.next_value_seed(seed)
+    }
+}
+
+struct BattleUnitVisitor;
+
+impl<'de> Visitor<'de> for BattleUnitVisitor {
+    type Value = BattleUnit;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a unit object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<BattleUnit, A::Error> {
+        let mut map = LivenessIn { map, alive: None, withdrawn: None };
+        let mut unit = BattleUnit::deserialize(MapAccessDeserializer::new(&mut map))?;
+        if unit.state == UnitState::Active {
+            if map.alive == Some(false) {
+                unit.state = UnitState::Destroyed;
+            } else if map.withdrawn == Some(true) {
+                unit.state = UnitState::Withdrawn;
+            }
+        }
+        Ok(unit)
+    }
+}
+
+impl Serialize for BattleUnit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return BattleUnit::serialize(self, serializer);
+        }
+        LivenessOut { unit: self, alive: self.is_alive(), withdrawn: self.is_withdrawn() }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BattleUnit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            return BattleUnit::deserialize(deserializer);
+        }
+        deserializer.deserialize_map(BattleUnitVisitor)
+    }
+}
+
+/// alive / withdrawn in the schema - optional on the way in
+#[cfg(feature = "typegen")]
+fn liveness_properties(schema: &mut schemars::Schema) {
+    let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) else {
+        return;
+    };
+    properties.insert("alive".into(), serde_json::json!({
+        "description": "state isn't destroyed - read for units without state",
+        "type": "boolean",
+    }));
+    properties.insert("withdrawn".into(), serde_json::json!({
+        "description": "state is withdrawn - read for units without state",
+        "type": "boolean",
+    }));
+}
+
+/// Counters for one weapon (or every weapon with one tag, once merged)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct WeaponStats {
+    pub shots_fired: u32,
+    /// Shots that did damage
+    pub shots_hit: u32,
+    pub damage_dealt: f32,
+    pub kills: u32,
+}
+
+impl WeaponStats {
+    pub fn add(&mut self, other: &WeaponStats) {
+        self.shots_fired += other.shots_fired;
+        self.shots_hit += other.shots_hit;
+        self.damage_dealt += other.damage_dealt;
+        self.kills += other.kills;
+    }
+}
+
+/// One weapon's magazine and supply (get_weapon_ammo)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeaponAmmo {
+    pub tag: String,
+    /// Rounds in the magazine (None = unlimited)
+    pub ammo: Option<u32>,
+    pub magazine_size: u32,
+    /// Supply left, per shot and capacity (ammo_per_shot 0 = unlimited)
+    pub ammo_remaining: f32,
+    pub ammo_per_shot: f32,
+    pub ammo_capacity: f32,
+}
+
+/// A unit's veterancy progress (get_unit_veterancy)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Veterancy {
+    pub level: u8,
+    pub experience: f32,
+    /// Experience the next level needs (None at the top level)
+    pub next_level_xp: Option<f32>,
+}
+
+/// Combat statistics for one unit or a whole faction
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CombatStats {
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub kills: u32,
+    /// shots_hit / shots_fired (0 before the first shot)
+    pub accuracy: f32,
+}
+
+impl CombatStats {
+    /// Add another unit's numbers (accuracy is recomputed from the totals)
+    pub fn add(&mut self, other: &CombatStats) {
+        self.damage_dealt += other.damage_dealt;
+        self.damage_taken += other.damage_taken;
+        self.shots_fired += other.shots_fired;
+        self.shots_hit += other.shots_hit;
+        self.kills += other.kills;
+        self.update_accuracy();
+    }
+
+    fn update_accuracy(&mut self) {
+        self.accuracy = if self.shots_fired > 0 {
+            self.shots_hit as f32 / self.shots_fired as f32
+        } else {
+            0.0
+        };
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
 pub struct Weapon {
     pub tag: String,
     
     // Damage
+    #[cfg_attr(feature = "typegen", schemars(description = "Damage per second", extend("exclusiveMinimum" = 0)))]
     pub dps: f32,              // Damage per second (already converted from per-minute)
+    #[cfg_attr(feature = "typegen", schemars(description = "Shots per second"))]
     pub fire_rate: f32,        // Shots per second
+    #[cfg_attr(feature = "typegen", schemars(description = "Seconds between shots"))]
     pub cooldown: f32,         // Seconds between shots
     
     // Range
@@ -78,36 +916,195 @@ pub struct Weapon {
     pub optimal_range: f32,
     
     // Targeting
-    pub target_armor_max: f32, // Max armor this weapon is effective against
+    #[cfg_attr(feature = "typegen", schemars(description = "Heaviest armor class hit at full damage"))]
+    pub target_armor_max: ArmorClass, // Max armor this weapon is effective against
     
     // ✅ NEW: Sequence firing
     #[serde(default)]
     pub sequence: Vec<bool>,   // Fire pattern (true = fire, false = pause)
     #[serde(default)]
     pub sequence_index: usize,
+    #[serde(default)]
+    pub sequence_offset: Option<u32>,  // Phase into the sequence (None = derived in normalize)
     
     // ✅ NEW: Projectile info
     #[serde(default)]
     pub projectile_speed: f32,
     
+    // Ammunition (None = unlimited)
+    #[serde(default)]
+    pub ammo: Option<u32>,     // Rounds left in the magazine (or total if no magazine)
+    #[serde(default)]
+    pub magazine_size: u32,    // Refill amount after reloading (0 = no reloads)
+    #[serde(default)]
+    pub reload_time: f32,      // Seconds to reload an empty magazine
+
+    // Supply (ammo_per_shot 0 = unlimited) - drawn on every shot as well as
+    // the magazine, only refilled by resupply units
+    #[serde(default)]
+    pub ammo_per_shot: f32,
+    #[serde(default)]
+    pub ammo_remaining: f32,
+    #[serde(default)]
+    pub ammo_capacity: f32,    // Resupply fills up to this (0 = the starting ammo_remaining)
+    
     // Timing
     pub last_fired: f64,
+    #[serde(default)]
+    pub reloading_until: Option<f64>,  // Reload completes at this time (None = not reloading)
+    #[serde(default)]
+    pub applies_effect: Option<EffectSpec>,  // Debuff attached to the target on hit
+    #[serde(default)]
+    pub independent_targeting: bool,  // Pick own target (nearest in range) instead of the unit's
+    #[serde(default)]
+    pub is_repair: bool,       // Restores the most-damaged ally in range instead of hitting enemies
+    #[serde(default)]
+    pub repairs_shield: bool,  // Repair left over after a full hull goes into the shield
+    #[serde(default)]
+    pub energy_cost: f32,      // Capacitor energy per shot (ignored if the unit has no capacitor)
+    #[serde(default)]
+    pub is_disabled: bool,     // Knocked out / offline - holds fire until re-enabled
+    #[serde(default)]
+    pub shield_pierce: f32,    // Fraction of damage (0-1) that skips the shield straight to hull
+    #[serde(default = "no_bonus")]
+    pub shield_damage_bonus: f32,  // Multiplier on damage dealt to shields
+    #[serde(default)]
+    pub tracking: f32,         // Angular speed (rad/s) it follows a reference-size target at (0 = always hit)
+    #[serde(default)]
+    #[cfg_attr(feature = "typegen", schemars(description = "Point defense: chance (0-1) to shoot down an incoming missile"))]
+    pub am_intercept_chance: f32,
+    #[serde(default)]
+    #[cfg_attr(feature = "typegen", schemars(description = "Point defense: how much each extra missile arriving at once dilutes am_intercept_chance"))]
+    pub saturation_factor: f32,
+    #[serde(default)]
+    #[cfg_attr(feature = "typegen", schemars(description = "Set from the simulator's tag registry when the unit joins"))]
+    pub category: WeaponCategory,
 }
 
-impl Default for Weapon {
-    fn default() -> Self {
-        Weapon {
-            tag: String::new(),
-            dps: 10.0,
-            fire_rate: 1.0,
-            cooldown: 1.0,
-            max_range: 100.0,
-            optimal_range: 50.0,
-            target_armor_max: 0.0,
-            sequence: Vec::new(),
-            sequence_index: 0,
-            projectile_speed: 100.0,
-            last_fired: 0.0,
+impl Default for Weapon {
+    fn default() -> Self {
+        Weapon {
+            tag: String::new(),
+            dps: 10.0,
+            fire_rate: 1.0,
+            cooldown: 1.0,
+            max_range: 100.0,
+            optimal_range: 50.0,
+            target_armor_max: ArmorClass::None,
+            sequence: Vec::new(),
+            sequence_index: 0,
+            sequence_offset: None,
+            projectile_speed: 100.0,
+            ammo: None,
+            magazine_size: 0,
+            reload_time: 0.0,
+            ammo_per_shot: 0.0,
+            ammo_remaining: 0.0,
+            ammo_capacity: 0.0,
+            last_fired: 0.0,
+            reloading_until: None,
+            applies_effect: None,
+            independent_targeting: false,
+            is_repair: false,
+            repairs_shield: false,
+            energy_cost: 0.0,
+            is_disabled: false,
+            shield_pierce: 0.0,
+            shield_damage_bonus: 1.0,
+            tracking: 0.0,
+            am_intercept_chance: 0.0,
+            saturation_factor: 0.0,
+            category: WeaponCategory::Kinetic,
+        }
+    }
+}
+
+impl Weapon {
+    /// Check if the weapon has a round to fire (magazine and supply)
+    #[inline]
+    pub fn has_ammo(&self) -> bool {
+        self.ammo != Some(0) && self.has_supply()
+    }
+
+    /// Check if there's supply for another shot
+    #[inline]
+    pub fn has_supply(&self) -> bool {
+        self.ammo_remaining >= self.ammo_per_shot
+    }
+
+    pub fn ammo_status(&self) -> WeaponAmmo {
+        WeaponAmmo {
+            tag: self.tag.clone(),
+            ammo: self.ammo,
+            magazine_size: self.magazine_size,
+            ammo_remaining: self.ammo_remaining,
+            ammo_per_shot: self.ammo_per_shot,
+            ammo_capacity: self.ammo_capacity,
+        }
+    }
+
+    /// Fraction of ammo_capacity left (None if the weapon doesn't use supply)
+    #[inline]
+    pub fn supply_fraction(&self) -> Option<f32> {
+        (self.ammo_per_shot > 0.0 && self.ammo_capacity > 0.0).then(|| self.ammo_remaining / self.ammo_capacity)
+    }
+
+    /// Earliest time the weapon can fire again (f64::MAX if permanently empty
+    /// or disabled - a resupply can make an empty weapon ready again)
+    #[inline]
+    pub fn ready_time(&self) -> f64 {
+        if self.is_disabled || !self.has_supply() {
+            f64::MAX
+        } else if self.has_ammo() {
+            self.last_fired + self.cooldown as f64
+        } else if let Some(reloaded) = self.reloading_until {
+            reloaded.max(self.last_fired + self.cooldown as f64)
+        } else {
+            f64::MAX
+        }
+    }
+
+    /// Spend one round and ammo_per_shot supply, starting a reload if that
+    /// emptied the magazine
+    #[inline]
+    pub fn consume_ammo(&mut self, current_time: f64) {
+        if self.ammo_per_shot > 0.0 {
+            self.ammo_remaining = (self.ammo_remaining - self.ammo_per_shot).max(0.0);
+        }
+        if let Some(ammo) = self.ammo.as_mut() {
+            *ammo = ammo.saturating_sub(1);
+            if *ammo == 0 && self.magazine_size > 0 {
+                self.reloading_until = Some(current_time + self.reload_time as f64);
+            }
+        }
+    }
+
+    /// Give back the round and supply of a shot that never landed - a
+    /// magazine that's already reloading is left to the reload
+    pub fn refund_ammo(&mut self) {
+        if self.ammo_per_shot > 0.0 {
+            self.ammo_remaining += self.ammo_per_shot;
+            if self.ammo_capacity > 0.0 {
+                self.ammo_remaining = self.ammo_remaining.min(self.ammo_capacity);
+            }
+        }
+        if self.reloading_until.is_some() {
+            return;
+        }
+        if let Some(ammo) = self.ammo.as_mut() {
+            *ammo += 1;
+            if self.magazine_size > 0 {
+                *ammo = (*ammo).min(self.magazine_size);
+            }
+        }
+    }
+
+    /// Refill the magazine once the reload has finished
+    #[inline]
+    pub fn update_reload(&mut self, current_time: f64) {
+        if self.reloading_until.is_some_and(|reloaded| current_time >= reloaded) {
+            self.ammo = Some(self.magazine_size);
+            self.reloading_until = None;
         }
     }
 }
@@ -130,7 +1127,7 @@ impl BattleUnit {
         let dist = (dx * dx + dy * dy + dz * dz).sqrt();
         
         if dist > 0.0 {
-            let factor = self.max_speed / dist;
+            let factor = self.effective_max_speed() / dist;
             self.vel_x = dx * factor;
             self.vel_y = dy * factor;
             self.vel_z = dz * factor;
@@ -146,7 +1143,7 @@ impl BattleUnit {
         let dist = (dx * dx + dy * dy + dz * dz).sqrt();
         
         if dist > 0.0 {
-            let factor = self.max_speed / dist;
+            let factor = self.effective_max_speed() / dist;
             self.vel_x = dx * factor;
             self.vel_y = dy * factor;
             self.vel_z = dz * factor;
@@ -164,44 +1161,176 @@ impl BattleUnit {
     /// Regenerate shields
     #[inline]
     pub fn regen_shield(&mut self, dt: f32) {
-        if self.shield < self.max_shield && self.shield_regen > 0.0 {
-            self.shield = (self.shield + self.shield_regen * dt).min(self.max_shield);
+        if self.has_effect(StatusEffectKind::ShieldDisrupt) {
+            return;
+        }
+        let regen = self.effective_shield_regen();
+        if self.shield < self.max_shield && regen > 0.0 {
+            self.shield = (self.shield + regen * dt).min(self.max_shield);
+        }
+    }
+
+    /// Shield regen paid for from the capacitor (1 energy per shield point)
+    pub fn regen_shield_from_energy(&mut self, dt: f32) {
+        if self.max_energy <= 0.0 {
+            self.regen_shield(dt);
+            return;
+        }
+        if self.has_effect(StatusEffectKind::ShieldDisrupt) {
+            return;
+        }
+        let regen = self.effective_shield_regen();
+        if self.shield < self.max_shield && regen > 0.0 {
+            let amount = (regen * dt).min(self.max_shield - self.shield).min(self.energy);
+            self.shield += amount;
+            self.energy -= amount;
+        }
+    }
+
+    /// Recharge the capacitor
+    #[inline]
+    pub fn regen_energy(&mut self, dt: f32) {
+        if self.energy < self.max_energy && self.energy_regen > 0.0 {
+            self.energy = (self.energy + self.energy_regen * dt).min(self.max_energy);
+        }
+    }
+
+    /// Check if the capacitor can pay for a shot (always true without one)
+    #[inline]
+    pub fn has_energy_for(&self, cost: f32) -> bool {
+        self.max_energy <= 0.0 || self.energy >= cost
+    }
+
+    /// Pay for a shot from the capacitor (no-op without one)
+    #[inline]
+    pub fn spend_energy(&mut self, cost: f32) {
+        if self.max_energy > 0.0 {
+            self.energy = (self.energy - cost).max(0.0);
         }
     }
 
     /// Take damage - optimized for batch processing
     /// 
     /// Damage flows: Shield -> Hull (with armor reduction)
+    /// Returns how much of `damage` was absorbed - all of it unless the unit
+    /// died, then only what it took to kill (dead units absorb nothing)
     #[inline]
-    pub fn take_damage(&mut self, damage: f32) {
-        self.damage_taken += damage;
-        
-        // Shields absorb damage first
-        if self.shield > 0.0 {
-            if damage <= self.shield {
-                self.shield -= damage;
-                return;
+    pub fn take_damage(&mut self, damage: f32) -> f32 {
+        self.take_damage_split(DamageSplit::new(damage, 0.0, 1.0))
+    }
+
+    /// take_damage for damage carrying shield modifiers
+    ///
+    /// The shielded part hits the shield (worth `vs_shield` there), whatever
+    /// gets through joins the piercing part on the hull, and armor applies once
+    /// to that hull total. Returns raw damage absorbed like take_damage.
+    pub fn take_damage_split(&mut self, hit: DamageSplit) -> f32 {
+        if !self.is_alive() {
+            return 0.0;
+        }
+        let damage = hit.total();
+        let hp_before = self.hp;
+        let armor_reduction = self.flat_armor.unwrap_or(self.armor.tier() as f32 * 0.5);
+
+        // Shields absorb the shielded part first - bonus scales what it's worth there
+        let mut hull_damage = hit.piercing;
+        let mut shield_cost = 0.0;  // Raw damage the shield soaked up
+        if hit.shielded > 0.0 {
+            if self.shield <= 0.0 {
+                hull_damage += hit.shielded;
+            } else if hit.vs_shield <= self.shield {
+                self.shield -= hit.vs_shield;
+                shield_cost = hit.shielded;
             } else {
-                let remaining = damage - self.shield;
+                let ratio = hit.shielded / hit.vs_shield;
+                shield_cost = self.shield * ratio;
+                hull_damage += hit.shielded - shield_cost;
                 self.shield = 0.0;
-                
-                // Apply remaining to hull with armor reduction
-                // Armor reduces hull damage by 0.5 per point
-                let armor_reduction = self.armor * 0.5;
-                let actual_damage = (remaining - armor_reduction).max(1.0);
-                self.hp -= actual_damage;
             }
-        } else {
-            // Direct hull damage with armor reduction
-            let armor_reduction = self.armor * 0.5;
-            let actual_damage = (damage - armor_reduction).max(1.0);
-            self.hp -= actual_damage;
         }
-        
-        if self.hp <= 0.0 {
+
+        if hull_damage <= 0.0 {
+            self.damage_taken += damage;
+            return damage;
+        }
+
+        // Armor reduces hull damage by a flat amount per hit
+        self.hp -= (hull_damage - armor_reduction).max(1.0);
+
+        // Past the kill only what it took to get through shield, hull and armor counts
+        let absorbed = if self.hp <= 0.0 {
             self.hp = 0.0;
-            self.alive = false;
+            self.set_state(UnitState::Destroyed);
+            damage.min(shield_cost + hp_before + armor_reduction)
+        } else {
+            damage
+        };
+        self.damage_taken += absorbed;
+        absorbed
+    }
+
+    /// Repair - restores hull up to max_hp, then shield if include_shield
+    ///
+    /// Returns the amount actually restored. Dead units can't be repaired.
+    #[inline]
+    pub fn repair(&mut self, amount: f32, include_shield: bool) -> f32 {
+        if !self.is_alive() || amount <= 0.0 {
+            return 0.0;
+        }
+
+        let hull = amount.min((self.max_hp - self.hp).max(0.0));
+        self.hp += hull;
+
+        let mut shield = 0.0;
+        if include_shield {
+            shield = (amount - hull).min((self.max_shield - self.shield).max(0.0));
+            self.shield += shield;
         }
+
+        hull + shield
+    }
+
+    /// Fraction of hull (plus shield if include_shield) missing - 0 = nothing to repair
+    #[inline]
+    pub fn repair_need(&self, include_shield: bool) -> f32 {
+        let (mut missing, mut max) = ((self.max_hp - self.hp).max(0.0), self.max_hp);
+        if include_shield {
+            missing += (self.max_shield - self.shield).max(0.0);
+            max += self.max_shield;
+        }
+        if max > 0.0 { missing / max } else { 0.0 }
+    }
+
+    /// Lowest supply_fraction among the unit's weapons that aren't full (None
+    /// = nothing to resupply)
+    pub fn supply_need(&self) -> Option<f32> {
+        self.weapons.iter()
+            .filter_map(|w| w.supply_fraction())
+            .filter(|&fraction| fraction < 1.0)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    /// Hand out supply, emptiest weapon first, each up to its ammo_capacity
+    ///
+    /// Returns the amount actually taken.
+    pub fn resupply(&mut self, mut amount: f32) -> f32 {
+        let offered = amount;
+        while amount > 0.0 {
+            let Some(weapon) = self.weapons.iter_mut()
+                .filter(|w| w.supply_fraction().is_some_and(|f| f < 1.0))
+                .min_by(|a, b| a.supply_fraction().unwrap().total_cmp(&b.supply_fraction().unwrap())) else {
+                break;
+            };
+            let missing = weapon.ammo_capacity - weapon.ammo_remaining;
+            if amount >= missing {
+                weapon.ammo_remaining = weapon.ammo_capacity;
+                amount -= missing;
+            } else {
+                weapon.ammo_remaining += amount;
+                amount = 0.0;
+            }
+        }
+        offered - amount
     }
 
     /// Calculate distance squared (faster - no sqrt)
@@ -219,44 +1348,369 @@ impl BattleUnit {
         self.distance_sq(other).sqrt()
     }
 
+    /// How fast the unit is moving - its own velocity, or for externally moved
+    /// units the speed of the last position update if it's recent
+    pub fn speed(&self, current_time: f64) -> f32 {
+        let own = (self.vel_x * self.vel_x + self.vel_y * self.vel_y + self.vel_z * self.vel_z).sqrt();
+        if current_time - self.external_move_time <= EXTERNAL_SPEED_WINDOW {
+            own.max(self.external_speed)
+        } else {
+            own
+        }
+    }
+
     /// Check if this unit can attack (has weapons)
     #[inline]
     pub fn can_attack(&self) -> bool {
         self.has_weapons && !self.weapons.is_empty()
     }
 
+    /// Longest range this unit can actually hit a ship (or station) from
+    ///
+    /// Point defense can't fire at units, repair weapons only hit allies and
+    /// siege weapons only hit stations, so none of them count. Falls back to
+    /// max_weapon_range when the weapon list wasn't sent.
+    pub fn max_offensive_range(&self, against_station: bool) -> f32 {
+        if self.weapons.is_empty() {
+            return self.max_weapon_range;
+        }
+        self.weapons.iter()
+            .filter(|w| w.category != WeaponCategory::PointDefense && !w.is_repair && (against_station || w.category != WeaponCategory::Siege))
+            .map(|w| w.max_range)
+            .fold(0.0f32, |a, b| a.max(b))
+    }
+
+    /// Longest siege weapon range (0 = can't finish off disabled stations)
+    pub fn max_siege_range(&self) -> f32 {
+        self.weapons.iter()
+            .filter(|w| w.category == WeaponCategory::Siege)
+            .map(|w| w.max_range)
+            .fold(0.0f32, |a, b| a.max(b))
+    }
+
+    /// Check if this unit carries weapons that threaten other units (not just point defense / repair)
+    #[inline]
+    pub fn is_armed(&self) -> bool {
+        self.has_weapons
+            && (self.weapons.is_empty() || self.weapons.iter().any(|w| w.category != WeaponCategory::PointDefense && !w.is_repair))
+    }
+
+    /// Check if this unit hasn't been destroyed (withdrawn units still count)
+    #[inline]
+    pub fn is_alive(&self) -> bool {
+        self.state != UnitState::Destroyed
+    }
+
+    #[inline]
+    pub fn is_withdrawn(&self) -> bool {
+        self.state == UnitState::Withdrawn
+    }
+
+    /// Distance this unit detects enemies at before their stealth_modifier -
+    /// view_range, or its weapon range if that's longer
+    #[inline]
+    pub fn sensor_range(&self) -> f32 {
+        self.view_range.max(self.max_weapon_range)
+    }
+
+    /// Carries a self-destruct charge
+    #[inline]
+    pub fn can_self_destruct(&self) -> bool {
+        self.self_destruct_damage > 0.0
+    }
+
+    /// Blow up - destroyed on the spot, hull and shield gone. The blast is
+    /// the simulator's job
+    pub fn detonate(&mut self) {
+        self.hp = 0.0;
+        self.shield = 0.0;
+        self.set_state(UnitState::Destroyed);
+    }
+
+    #[inline]
+    pub fn set_state(&mut self, state: UnitState) {
+        self.state = state;
+    }
+
+    /// Check if this unit is physically present (active and not docked in a
+    /// carrier) - surrendered and disabled units included
+    #[inline]
+    pub fn on_battlefield(&self) -> bool {
+        self.state == UnitState::Active && !self.is_in_hangar
+    }
+
+    /// Check if this unit is still taking part (on the battlefield and not
+    /// surrendered or disabled)
+    #[inline]
+    pub fn in_battle(&self) -> bool {
+        self.on_battlefield() && !self.is_surrendered && !self.is_disabled
+    }
+
     /// Check if this unit is a valid combat target
     #[inline]
     pub fn is_valid_target(&self) -> bool {
-        self.alive
+        self.in_battle()
+    }
+
+    /// Check if siege weapons may shoot this unit - valid targets plus
+    /// disabled units, which they can finish off
+    #[inline]
+    pub fn is_siege_target(&self) -> bool {
+        self.is_valid_target() || (self.is_disabled && self.on_battlefield() && !self.is_surrendered)
+    }
+
+    /// Check if this unit is still fighting (not retreating or withdrawn)
+    #[inline]
+    pub fn is_engaged(&self) -> bool {
+        self.in_battle() && !self.retreating
+    }
+
+    /// Check if a carrier has room for another unit
+    #[inline]
+    pub fn has_hangar_space(&self) -> bool {
+        (self.hangar_contents.len() as u32) < self.hangar_capacity
+    }
+
+    /// Change target, restarting the lock timer if it's a different target
+    pub fn set_target(&mut self, target_id: Option<u32>, current_time: f64) {
+        if self.target_id != target_id {
+            self.target_id = target_id;
+            self.target_acquired_time = current_time;
+        }
+    }
+
+    /// Drop the target, including a host-pinned one (target died / left)
+    #[inline]
+    pub fn clear_target(&mut self) {
+        self.target_id = None;
+        self.target_locked = false;
+    }
+
+    /// Check if the current target is still being locked (weapons hold fire)
+    #[inline]
+    pub fn is_locking(&self, current_time: f64) -> bool {
+        self.lock_time > 0.0 && current_time < self.target_acquired_time + self.lock_time as f64
+    }
+
+    /// Add experience, levelling up as thresholds are crossed
+    ///
+    /// Each level permanently adds 5% dps to every weapon, 3% off their
+    /// cooldowns and 2% max_hp (healed by the same amount). Returns the new
+    /// level if it changed.
+    pub fn gain_experience(&mut self, xp: f32) -> Option<u8> {
+        self.experience += xp;
+        let start = self.veterancy_level;
+        while let Some(&threshold) = VETERANCY_THRESHOLDS.get(self.veterancy_level as usize) {
+            if self.experience < threshold {
+                break;
+            }
+            self.veterancy_level += 1;
+            for weapon in self.weapons.iter_mut() {
+                weapon.dps *= 1.05;
+                weapon.cooldown *= 0.97;
+            }
+            let bonus_hp = self.max_hp * 0.02;
+            self.max_hp += bonus_hp;
+            if self.is_alive() {
+                self.hp += bonus_hp;
+            }
+        }
+        (self.veterancy_level != start).then_some(self.veterancy_level)
+    }
+
+    /// Level and experience towards the next one
+    pub fn veterancy(&self) -> Veterancy {
+        Veterancy {
+            level: self.veterancy_level,
+            experience: self.experience,
+            next_level_xp: VETERANCY_THRESHOLDS.get(self.veterancy_level as usize).copied(),
+        }
+    }
+
+    /// Combat statistics so far
+    pub fn stats(&self) -> CombatStats {
+        let mut stats = CombatStats {
+            damage_dealt: self.damage_dealt,
+            damage_taken: self.damage_taken,
+            shots_fired: self.shots_fired,
+            shots_hit: self.shots_hit,
+            kills: self.kills,
+            accuracy: 0.0,
+        };
+        stats.update_accuracy();
+        stats
+    }
+
+    /// Counters for weapons[weapon_idx], growing weapon_stats to match weapons
+    #[inline]
+    pub fn weapon_stats_mut(&mut self, weapon_idx: usize) -> &mut WeaponStats {
+        if self.weapon_stats.len() <= weapon_idx {
+            self.weapon_stats.resize(self.weapons.len().max(weapon_idx + 1), WeaponStats::default());
+        }
+        &mut self.weapon_stats[weapon_idx]
+    }
+
+    /// Weapon counters merged by tag (two "LASER"s report as one)
+    pub fn weapon_stats_by_tag(&self) -> BTreeMap<String, WeaponStats> {
+        let mut by_tag: BTreeMap<String, WeaponStats> = BTreeMap::new();
+        for (weapon, stats) in self.weapons.iter().zip(&self.weapon_stats) {
+            by_tag.entry(weapon.tag.clone()).or_default().add(stats);
+        }
+        by_tag
+    }
+
+    /// Check if an effect of this kind is on the unit
+    #[inline]
+    pub fn has_effect(&self, kind: StatusEffectKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    /// Attach an effect - refreshes rather than stacks
+    ///
+    /// A second effect of the same kind extends the existing one to the later
+    /// expiry and keeps the stronger magnitude; the latest applier gets credit.
+    pub fn apply_effect(&mut self, effect: StatusEffect) {
+        if let Some(existing) = self.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            existing.expires_at_tick = existing.expires_at_tick.max(effect.expires_at_tick);
+            existing.magnitude = existing.magnitude.max(effect.magnitude);
+            existing.source_id = effect.source_id;
+        } else {
+            self.effects.push(effect);
+        }
+    }
+
+    /// Drop effects that have run out. Returns true if any were removed.
+    pub fn expire_effects(&mut self, tick: u64) -> bool {
+        let before = self.effects.len();
+        self.effects.retain(|e| e.is_active(tick));
+        self.effects.len() != before
+    }
+
+    /// max_speed after engine power and slows
+    #[inline]
+    pub fn effective_max_speed(&self) -> f32 {
+        let slow = self.effects.iter()
+            .filter(|e| e.kind == StatusEffectKind::SpeedSlow)
+            .map(|e| e.magnitude)
+            .fold(0.0f32, f32::max);
+        self.max_speed * self.power_engines * 3.0 * (1.0 - slow.clamp(0.0, 1.0))
+    }
+
+    /// shield_regen after shield power
+    #[inline]
+    pub fn effective_shield_regen(&self) -> f32 {
+        self.shield_regen * self.power_shield * 3.0
+    }
+
+    /// Weapon damage multiplier from weapon power (1.0 at a third)
+    #[inline]
+    pub fn effective_damage_multiplier(&self) -> f32 {
+        self.power_weapons * 3.0
+    }
+
+    /// Split reactor power - each fraction in 0..=1, summing to 1.0 (within
+    /// POWER_SUM_TOLERANCE). Stored as given; nothing changes on error
+    pub fn set_power(&mut self, shield: f32, weapons: f32, engines: f32) -> Result<(), PowerError> {
+        check_power(shield, weapons, engines)?;
+        self.power_shield = shield;
+        self.power_weapons = weapons;
+        self.power_engines = engines;
+        Ok(())
+    }
+
+    /// Power split an AI captain would pick - shields when the hull is low
+    /// (and there are shields to feed), weapons in combat, else balanced
+    pub fn auto_balance_power(&mut self, in_combat: bool) {
+        let low_hull = self.max_shield > 0.0 && self.hp < self.max_hp * LOW_HULL_POWER_FRACTION;
+        let (shield, weapons, engines) = if low_hull {
+            (0.5, 0.3, 0.2)
+        } else if in_combat {
+            (0.25, 0.5, 0.25)
+        } else {
+            (BALANCED_POWER, BALANCED_POWER, BALANCED_POWER)
+        };
+        self.power_shield = shield;
+        self.power_weapons = weapons;
+        self.power_engines = engines;
+    }
+
+    /// Check if hull has dropped below the retreat threshold or morale broke
+    ///
+    /// Broken units retreat regardless of retreat_hp_fraction and keep going
+    /// until morale recovers to MORALE_SHAKEN.
+    #[inline]
+    pub fn should_retreat(&self) -> bool {
+        let morale_floor = if self.retreating { MORALE_SHAKEN } else { MORALE_BROKEN };
+        (self.retreat_hp_fraction > 0.0 && self.hp < self.max_hp * self.retreat_hp_fraction)
+            || self.morale < morale_floor
+    }
+
+    /// Check if an alive unit has been beaten down far enough to give up
+    #[inline]
+    pub fn should_surrender(&self) -> bool {
+        self.can_surrender && self.max_hp > 0.0 && self.hp / self.max_hp < self.surrender_hp_threshold
+    }
+
+    /// Check if a unit still in the fight is damaged enough to shut down
+    #[inline]
+    pub fn should_disable(&self) -> bool {
+        self.disable_threshold > 0.0 && self.max_hp > 0.0 && self.hp / self.max_hp < self.disable_threshold
+    }
+
+    /// Check if a disabled unit has repaired enough to come back online -
+    /// twice its disable threshold, so the next hit doesn't knock it straight
+    /// back out
+    #[inline]
+    pub fn can_reactivate(&self) -> bool {
+        self.hp >= self.max_hp * (self.disable_threshold * 2.0).min(1.0)
+    }
+
+    /// Hit chance multiplier from morale - shaken units shoot half as well
+    #[inline]
+    pub fn accuracy_multiplier(&self) -> f32 {
+        if self.morale < MORALE_SHAKEN { 0.5 } else { 1.0 }
     }
 
     /// Normalize unit data after deserialization
     /// Computes derived fields if they weren't sent by the game server
-    pub fn normalize(&mut self, current_time: f64) {
+    pub fn normalize(&mut self, current_time: f64, rng: &mut BattleRng) {
         // Randomize weapon cooldowns so ships don't all fire at the same time
         for (i, weapon) in self.weapons.iter_mut().enumerate() {
             if weapon.last_fired == 0.0 && weapon.cooldown > 0.0 {
-                // Get random bytes and convert to float 0.0-1.0
-                let mut buf = [0u8; 4];
-                if getrandom(&mut buf).is_ok() {
-                    let random_frac = (u32::from_le_bytes(buf) as f64) / (u32::MAX as f64);
-
-                    // Set last_fired to a random point in the past within cooldown period
-                    // This staggers when each weapon becomes ready
-                    weapon.last_fired = current_time - (random_frac * weapon.cooldown as f64);
-
-                    // Debug log for first few weapons
-                    if i < 3 {
-                        crate::log(&format!(
-                            "[Normalize] Unit {} weapon {} ({}): cooldown={:.1}s, random={:.2}, last_fired={:.2}",
-                            self.id, i, weapon.tag, weapon.cooldown, random_frac, weapon.last_fired
-                        ));
-                    }
+                let random_frac = rng.next_f64();
+
+                // Set last_fired to a random point in the past within cooldown period
+                // This staggers when each weapon becomes ready
+                weapon.last_fired = current_time - (random_frac * weapon.cooldown as f64);
+
+                // Debug log for first few weapons
+                if i < 3 {
+                    log_at!(Trace,
+                        "[Normalize] Unit {} weapon {} ({}): cooldown={:.1}s, random={:.2}, last_fired={:.2}",
+                        self.id, i, weapon.tag, weapon.cooldown, random_frac, weapon.last_fired
+                    );
                 }
             }
         }
 
+        // Stagger fire sequences so identical weapons don't all fire on the same ticks
+        for (i, weapon) in self.weapons.iter_mut().enumerate() {
+            if !weapon.sequence.is_empty() && weapon.sequence_offset.is_none() {
+                weapon.sequence_offset = Some(sequence_phase(self.id, i));
+            }
+        }
+
+        // Absent supply capacity = what the weapon started with
+        for weapon in self.weapons.iter_mut() {
+            if weapon.ammo_per_shot > 0.0 && weapon.ammo_capacity <= 0.0 {
+                weapon.ammo_capacity = weapon.ammo_remaining;
+            }
+        }
+
+        if self.is_station {
+            self.ship_class = ShipClass::Station;
+        }
+
         // Compute has_weapons from weapons array if not set
         if !self.has_weapons && !self.weapons.is_empty() {
             self.has_weapons = true;
@@ -279,6 +1733,19 @@ impl BattleUnit {
                 self.is_ship = true;
             }
         }
+
+        // Absent energy starts full; never above the cap
+        self.energy = self.energy.clamp(0.0, self.max_energy.max(0.0));
+
+        // Derive collision radius from unit type if not set
+        if self.radius <= 0.0 {
+            self.radius = if self.is_station { 50.0 } else { 10.0 };
+        }
+
+        // Nothing left of the hull - whatever state says
+        if self.state == UnitState::Active && self.hp <= 0.0 {
+            self.state = UnitState::Destroyed;
+        }
     }
 }
 
@@ -292,8 +1759,17 @@ impl Default for BattleUnit {
             hp: 100.0,
             max_shield: 0.0,
             shield: 0.0,
-            armor: 0.0,
+            armor: ArmorClass::None,
+            flat_armor: None,
             shield_regen: 0.0,
+            max_energy: 0.0,
+            energy: 0.0,
+            energy_regen: 0.0,
+            power_shield: BALANCED_POWER,
+            power_weapons: BALANCED_POWER,
+            power_engines: BALANCED_POWER,
+            resupply_rate: 0.0,
+            resupply_range: 0.0,
             pos_x: 0.0,
             pos_y: 0.0,
             pos_z: 0.0,
@@ -301,17 +1777,228 @@ impl Default for BattleUnit {
             vel_y: 0.0,
             vel_z: 0.0,
             max_speed: 10.0,
+            radius: 0.0,
+            signature_radius: 0.0,
+            external_speed: 0.0,
+            external_move_time: 0.0,
             weapons: Vec::new(),
             max_weapon_range: 0.0,
             unit_type: String::new(),
+            class: generic_class(),
+            ship_class: ShipClass::Frigate,
             is_ship: false,
             is_station: false,
             has_weapons: false,
             view_range: 100.0,
+            stealth_modifier: 1.0,
+            ai_controlled: false,
+            orbit_mode: false,
+            orbit_angle: 0.0,
             target_id: None,
-            alive: true,
+            state: UnitState::Active,
+            lock_time: 0.0,
+            target_acquired_time: 0.0,
+            target_locked: false,
+            retreat_hp_fraction: 0.0,
+            retreating: false,
+            retreat_target: None,
+            can_surrender: false,
+            surrender_hp_threshold: 0.0,
+            is_surrendered: false,
+            disable_threshold: 0.0,
+            repair_rate: 0.0,
+            is_disabled: false,
+            quiet_ticks: 0,
+            morale: MAX_MORALE,
+            morale_regen_rate: 0.0,
+            morale_radius: 0.0,
+            morale_loss_per_death: 0.0,
+            is_commander: false,
+            hangar_capacity: 0,
+            hangar_contents: Vec::new(),
+            is_in_hangar: false,
+            hangar: None,
+            carrier_id: None,
+            self_destruct_damage: 0.0,
+            self_destruct_radius: 0.0,
+            self_destruct_trigger_radius: 0.0,
+            waypoints: Vec::new(),
+            current_waypoint: 0,
+            move_order: None,
+            group_id: None,
+            stance: Stance::Aggressive,
+            effects: Vec::new(),
             damage_dealt: 0.0,
             damage_taken: 0.0,
+            healing_done: 0.0,
+            shots_fired: 0,
+            shots_hit: 0,
+            kills: 0,
+            weapon_stats: Vec::new(),
+            experience: 0.0,
+            veterancy_level: 0,
+            xp_per_damage: default_xp_per_damage(),
+            xp_per_kill: default_xp_per_kill(),
+        }
+    }
+}
+
+/// Why BattleUnitBuilder::build refused
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuildError {
+    /// id was never set (0 isn't a unit id)
+    MissingId,
+    /// faction was never set (0 isn't a faction id)
+    MissingFaction,
+    /// max_hp of zero or less - the unit would start dead
+    InvalidMaxHp(f32),
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::MissingId => write!(f, "unit id not set"),
+            BuildError::MissingFaction => write!(f, "faction not set"),
+            BuildError::InvalidMaxHp(hp) => write!(f, "max_hp {} must be above zero", hp),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl BattleUnit {
+    pub fn builder() -> BattleUnitBuilder {
+        BattleUnitBuilder::default()
+    }
+}
+
+/// Chainable BattleUnit construction - anything not set keeps
+/// BattleUnit::default()
+///
+/// build() fills in has_weapons and max_weapon_range from the weapons the
+/// way normalize() does, unless max_weapon_range was set.
+#[derive(Debug, Clone, Default)]
+pub struct BattleUnitBuilder {
+    unit: BattleUnit,
+    max_weapon_range: Option<f32>,
+}
+
+impl BattleUnitBuilder {
+    pub fn id(mut self, id: u32) -> Self {
+        self.unit.id = id;
+        self
+    }
+
+    pub fn faction(mut self, faction_id: u32) -> Self {
+        self.unit.faction_id = faction_id;
+        self
+    }
+
+    pub fn player(mut self, player_id: u32) -> Self {
+        self.unit.player_id = Some(player_id);
+        self
+    }
+
+    /// Starts at full hull
+    pub fn hp(mut self, max_hp: f32) -> Self {
+        self.unit.max_hp = max_hp;
+        self.unit.hp = max_hp;
+        self
+    }
+
+    pub fn shield(mut self, current: f32, max: f32) -> Self {
+        self.unit.shield = current;
+        self.unit.max_shield = max;
+        self
+    }
+
+    pub fn armor(mut self, armor: ArmorClass) -> Self {
+        self.unit.armor = armor;
+        self
+    }
+
+    pub fn position(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.unit.pos_x = x;
+        self.unit.pos_y = y;
+        self.unit.pos_z = z;
+        self
+    }
+
+    pub fn max_speed(mut self, max_speed: f32) -> Self {
+        self.unit.max_speed = max_speed;
+        self
+    }
+
+    pub fn weapons(mut self, weapons: Vec<Weapon>) -> Self {
+        self.unit.weapons = weapons;
+        self
+    }
+
+    /// Add one weapon to those already set
+    pub fn weapon(mut self, weapon: Weapon) -> Self {
+        self.unit.weapons.push(weapon);
+        self
+    }
+
+    pub fn view_range(mut self, view_range: f32) -> Self {
+        self.unit.view_range = view_range;
+        self
+    }
+
+    /// Detected at `modifier` x an enemy's sensor range (1 = no stealth)
+    pub fn stealth(mut self, modifier: f32) -> Self {
+        self.unit.stealth_modifier = modifier;
+        self
+    }
+
+    /// Targeting PriorityTable class ("bomber", "station", ...)
+    pub fn class(mut self, class: &str) -> Self {
+        self.unit.class = class.to_string();
+        self
+    }
+
+    /// Self-destruct charge - `trigger_radius` 0 only detonates on command
+    pub fn self_destruct(mut self, damage: f32, radius: f32, trigger_radius: f32) -> Self {
+        self.unit.self_destruct_damage = damage;
+        self.unit.self_destruct_radius = radius;
+        self.unit.self_destruct_trigger_radius = trigger_radius;
+        self
+    }
+
+    /// Override the range derived from the weapons
+    pub fn max_weapon_range(mut self, range: f32) -> Self {
+        self.max_weapon_range = Some(range);
+        self
+    }
+
+    pub fn as_ship(mut self) -> Self {
+        self.unit.is_ship = true;
+        self.unit.is_station = false;
+        self
+    }
+
+    pub fn as_station(mut self) -> Self {
+        self.unit.is_station = true;
+        self.unit.is_ship = false;
+        self
+    }
+
+    pub fn build(self) -> Result<BattleUnit, BuildError> {
+        let mut unit = self.unit;
+        if unit.id == 0 {
+            return Err(BuildError::MissingId);
         }
+        if unit.faction_id == 0 {
+            return Err(BuildError::MissingFaction);
+        }
+        if unit.max_hp.is_nan() || unit.max_hp <= 0.0 {
+            return Err(BuildError::InvalidMaxHp(unit.max_hp));
+        }
+
+        unit.has_weapons |= !unit.weapons.is_empty();
+        unit.max_weapon_range = self.max_weapon_range.unwrap_or_else(|| {
+            unit.weapons.iter().map(|w| w.max_range).fold(0.0f32, f32::max)
+        });
+        Ok(unit)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,43 @@
+// battle-core/src/bin/generate-types.rs
+//
+// Regenerate types/battle-core.d.ts and types/battle-units.schema.json (see
+// typegen.rs):
+//
+//   cargo run --features typegen --bin generate-types
+//   cargo run --features typegen --bin generate-types -- --check   # fail if stale
+
+use std::path::Path;
+use std::process::ExitCode;
+use battle_core::typegen;
+
+fn main() -> ExitCode {
+    let check = std::env::args().skip(1).any(|arg| arg == "--check");
+    let mut ok = true;
+
+    for (file, generated) in typegen::generated_files() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+        if check {
+            let committed = std::fs::read_to_string(&path).unwrap_or_default();
+            if committed != generated {
+                eprintln!("{} is stale - run cargo run --features typegen --bin generate-types", file);
+                ok = false;
+            } else {
+                println!("{} is up to date", file);
+            }
+            continue;
+        }
+
+        let written = path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, generated));
+        match written {
+            Ok(()) => println!("wrote {}", file),
+            Err(e) => {
+                eprintln!("failed to write {}: {}", path.display(), e);
+                ok = false;
+            }
+        }
+    }
+
+    if ok { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
@@ -0,0 +1,184 @@
+// battle-core/src/binary.rs
+//
+// Binary encoding for the big WASM-boundary payloads (tick results, unit
+// snapshots) - serde_json dominates tick time in large battles.
+//
+// Layout:
+//   byte 0    BINARY_FORMAT_VERSION
+//   bytes 1.. MessagePack, structs as maps with the same keys as the JSON
+//
+// Decoding on the JS side (@msgpack/msgpack):
+//   const { decode } = require('@msgpack/msgpack');
+//   function decodeBattleBinary(bytes) {
+//     if (bytes[0] !== 1) throw new Error(`Unknown battle binary version ${bytes[0]}`);
+//     return decode(bytes.subarray(1));
+//   }
+// The decoded objects match what JSON.parse gives for the JSON methods.
+//
+// Bincode frames (simulate_tick_binary) are smaller still but positional:
+//   bytes 0..4  u32 LE length of the body
+//   bytes 4..   bincode (varint integers and lengths, little-endian f32/f64),
+//               fields in declaration order, Option = 0/1 tag byte then value
+// so the reader needs the Rust struct layout - no version byte, the reader
+// has to be regenerated whenever TickResult (or anything in it) changes.
+
+use std::fmt;
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// First byte of every encoded payload - bump when the layout changes
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum BinaryError {
+    Encode(rmp_serde::encode::Error),
+    Decode(rmp_serde::decode::Error),
+    Bincode(bincode::Error),
+    /// Frame length prefix doesn't match the data
+    BadFrame,
+    /// Empty input or a version byte this build doesn't know
+    UnsupportedVersion(Option<u8>),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::Encode(e) => write!(f, "encode failed: {}", e),
+            BinaryError::Decode(e) => write!(f, "decode failed: {}", e),
+            BinaryError::Bincode(e) => write!(f, "bincode failed: {}", e),
+            BinaryError::BadFrame => write!(f, "frame length doesn't match the data"),
+            BinaryError::UnsupportedVersion(Some(v)) => write!(f, "unsupported format version {}", v),
+            BinaryError::UnsupportedVersion(None) => write!(f, "empty input"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+/// Version byte + MessagePack
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, BinaryError> {
+    let mut bytes = vec![BINARY_FORMAT_VERSION];
+    // Human-readable so optional fields are left out exactly like the JSON
+    let mut serializer = rmp_serde::Serializer::new(&mut bytes).with_struct_map().with_human_readable();
+    value.serialize(&mut serializer).map_err(BinaryError::Encode)?;
+    Ok(bytes)
+}
+
+/// Inverse of encode (native tools and tests - JS decodes it itself)
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, BinaryError> {
+    match bytes.split_first() {
+        Some((&BINARY_FORMAT_VERSION, body)) => {
+            // Human-readable to match encode (ArmorClass is a name there)
+            let mut deserializer = rmp_serde::Deserializer::from_read_ref(body).with_human_readable();
+            T::deserialize(&mut deserializer).map_err(BinaryError::Decode)
+        }
+        Some((&version, _)) => Err(BinaryError::UnsupportedVersion(Some(version))),
+        None => Err(BinaryError::UnsupportedVersion(None)),
+    }
+}
+
+/// Length-prefixed bincode frame
+pub fn encode_frame<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, BinaryError> {
+    let options = bincode::DefaultOptions::new();
+    let len = options.serialized_size(value).map_err(BinaryError::Bincode)?;
+    let len = u32::try_from(len).map_err(|_| BinaryError::BadFrame)?;
+    let mut bytes = Vec::with_capacity(4 + len as usize);
+    bytes.extend_from_slice(&len.to_le_bytes());
+    options.serialize_into(&mut bytes, value).map_err(BinaryError::Bincode)?;
+    Ok(bytes)
+}
+
+/// Inverse of encode_frame - expects exactly one frame
+pub fn decode_frame<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, BinaryError> {
+    let (len, body) = bytes.split_first_chunk::<4>().ok_or(BinaryError::BadFrame)?;
+    if u32::from_le_bytes(*len) as usize != body.len() {
+        return Err(BinaryError::BadFrame);
+    }
+    bincode::DefaultOptions::new().deserialize(body).map_err(BinaryError::Bincode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::{BattleUnit, Weapon};
+    use crate::simulator::{BattleSimulator, TickResult};
+
+    fn as_json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip_matches_json() {
+        let units: Vec<BattleUnit> = (1..=6).map(|id| BattleUnit::builder()
+            .id(id)
+            .faction(id % 2 + 1)
+            .position(id as f32 * 20.0, 0.0, 0.0)
+            .hp(50.0)
+            .as_ship()
+            .weapons(vec![Weapon {
+                ammo: if id == 1 { Some(5) } else { None },
+                ..Weapon::builder().tag("LASER").dps(40.0).range(50.0, 200.0).last_fired(900.0).build()
+            }])
+            .build()
+            .unwrap()
+        ).collect();
+        let mut sim = BattleSimulator::new(units, 1000.0);
+
+        let mut saw_damage = false;
+        for i in 0..40 {
+            let result = sim.simulate_tick(0.05, 1000.0 + i as f64 * 0.05);
+            saw_damage |= !result.damaged.is_empty();
+            let decoded: TickResult = decode(&encode(&result).unwrap()).unwrap();
+            assert_eq!(as_json(&decoded), as_json(&result));
+        }
+        assert!(saw_damage);
+
+        let results = sim.get_results();
+        let bytes = encode(&results).unwrap();
+        assert_eq!(bytes[0], BINARY_FORMAT_VERSION);
+        let decoded: Vec<BattleUnit> = decode(&bytes).unwrap();
+        assert_eq!(as_json(&decoded), as_json(&results));
+        assert!(bytes.len() < serde_json::to_vec(&results).unwrap().len());
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let units: Vec<BattleUnit> = (1..=4).map(|id| BattleUnit::builder()
+            .id(id)
+            .faction(id % 2 + 1)
+            .position(id as f32 * 20.0, 0.0, 0.0)
+            .as_ship()
+            .weapons(vec![Weapon {
+                ammo: if id == 1 { Some(5) } else { None },
+                ..Weapon::builder().tag("LASER").range(50.0, 200.0).last_fired(900.0).build()
+            }])
+            .build()
+            .unwrap()
+        ).collect();
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let result = sim.simulate_tick(0.05, 1000.0);
+        assert!(result.weapons_fired.iter().any(|f| f.ammo_remaining == Some(4)));
+        assert!(result.weapons_fired.iter().any(|f| f.ammo_remaining.is_none()));
+
+        let bytes = encode_frame(&result).unwrap();
+        assert_eq!(u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize, bytes.len() - 4);
+        let decoded: TickResult = decode_frame(&bytes).unwrap();
+        assert_eq!(decoded.weapons_fired, result.weapons_fired);
+        assert_eq!(as_json(&decoded), as_json(&result));
+        assert!(bytes.len() < serde_json::to_vec(&result).unwrap().len());
+
+        assert!(matches!(decode_frame::<TickResult>(&bytes[..bytes.len() - 1]), Err(BinaryError::BadFrame)));
+        // JSON still drops ammoRemaining for unlimited weapons
+        let unlimited = result.weapons_fired.iter().find(|f| f.ammo_remaining.is_none()).unwrap();
+        assert!(!serde_json::to_string(unlimited).unwrap().contains("ammoRemaining"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_version() {
+        let mut bytes = encode(&vec![1u32, 2, 3]).unwrap();
+        bytes[0] = 99;
+        assert!(matches!(decode::<Vec<u32>>(&bytes), Err(BinaryError::UnsupportedVersion(Some(99)))));
+        assert!(matches!(decode::<Vec<u32>>(&[]), Err(BinaryError::UnsupportedVersion(None))));
+    }
+}
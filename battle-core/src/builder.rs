@@ -0,0 +1,324 @@
+// battle-core/src/builder.rs
+//
+// SimulationBuilder - a whole battle scenario (units, objectives,
+// reinforcement waves, resource nodes, config, seed) assembled in one chain
+// and checked once in build(), instead of a constructor followed by a string
+// of setters that can each fail on their own.
+//
+//   let sim = SimulationBuilder::new()
+//       .add_faction_units(1, attackers)
+//       .add_faction_units(2, defenders)
+//       .set_objective(1, BattleObjective::KillUnit(900))
+//       .set_seed(7)
+//       .build()?;
+
+use std::collections::BTreeSet;
+use std::fmt;
+use crate::battle_unit::BattleUnit;
+use crate::config::BattleConfig;
+use crate::objectives::BattleObjective;
+use crate::resources::ResourceNode;
+use crate::rng::SimulationMode;
+use crate::simulator::{BattleSimulator, ReinforcementWave};
+use crate::spatial_index::SpatialIndex;
+use crate::validation::ValidationError;
+
+/// Why SimulationBuilder::build refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// Fewer than two factions bring an armed unit (at the start or in a
+    /// wave) - nobody would ever fight. Lists the ones that do.
+    NotEnoughArmedFactions(Vec<u32>),
+    /// A unit or wave the simulator won't accept (see validation.rs)
+    InvalidUnit(ValidationError),
+    /// Two resource nodes share this id
+    DuplicateResourceNode(u32),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::NotEnoughArmedFactions(factions) => {
+                write!(f, "need armed units in at least 2 factions, found {:?}", factions)
+            }
+            BuildError::InvalidUnit(e) => write!(f, "invalid unit: {}", e),
+            BuildError::DuplicateResourceNode(id) => write!(f, "resource node {}: duplicate id", id),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<ValidationError> for BuildError {
+    fn from(e: ValidationError) -> Self {
+        BuildError::InvalidUnit(e)
+    }
+}
+
+/// Chainable battle scenario - nothing is checked until build()
+#[derive(Debug, Clone, Default)]
+pub struct SimulationBuilder {
+    units: Vec<BattleUnit>,
+    objectives: Vec<(u32, BattleObjective)>,
+    waves: Vec<ReinforcementWave>,
+    resource_nodes: Vec<ResourceNode>,
+    config: BattleConfig,
+    seed: Option<u64>,
+    start_time: f64,
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_unit(mut self, unit: BattleUnit) -> Self {
+        self.units.push(unit);
+        self
+    }
+
+    /// Add units fighting for `faction_id` (overrides their own faction_id)
+    pub fn add_faction_units(mut self, faction_id: u32, units: Vec<BattleUnit>) -> Self {
+        self.units.extend(units.into_iter().map(|unit| BattleUnit { faction_id, ..unit }));
+        self
+    }
+
+    /// Add a win condition for a faction - a faction may have several
+    pub fn set_objective(mut self, faction_id: u32, objective: BattleObjective) -> Self {
+        self.objectives.push((faction_id, objective));
+        self
+    }
+
+    pub fn add_reinforcement_wave(mut self, wave: ReinforcementWave) -> Self {
+        self.waves.push(wave);
+        self
+    }
+
+    pub fn add_resource_node(mut self, node: ResourceNode) -> Self {
+        self.resource_nodes.push(node);
+        self
+    }
+
+    /// Replace the config (a seed from set_seed still wins over its mode)
+    pub fn with_config(mut self, config: BattleConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Deterministic random rolls from this seed
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Host clock the battle starts at (seconds, like simulate_tick's
+    /// current_time) - 0 unless set
+    pub fn start_time(mut self, current_time: f64) -> Self {
+        self.start_time = current_time;
+        self
+    }
+
+    pub fn config(&self) -> &BattleConfig {
+        &self.config
+    }
+
+    /// Factions with at least one armed unit, at the start or in a wave
+    fn armed_factions(&self) -> BTreeSet<u32> {
+        let starting = self.units.iter().filter(|u| u.has_weapons || !u.weapons.is_empty()).map(|u| u.faction_id);
+        let arriving = self.waves.iter().flat_map(|wave| {
+            wave.units.iter()
+                .filter(|u| u.has_weapons || !u.weapons.is_empty())
+                .map(move |u| wave.faction_id.unwrap_or(u.faction_id))
+        });
+        starting.chain(arriving).collect()
+    }
+
+    /// Validate the scenario and build a simulator on the default spatial grid
+    pub fn build(self) -> Result<BattleSimulator, BuildError> {
+        let grid = self.config().spatial_grid();
+        self.build_with_index(grid)
+    }
+
+    /// build() with the given (empty) spatial index
+    pub fn build_with_index<I: SpatialIndex>(self, grid: I) -> Result<BattleSimulator<I>, BuildError> {
+        let armed = self.armed_factions();
+        if armed.len() < 2 {
+            return Err(BuildError::NotEnoughArmedFactions(armed.into_iter().collect()));
+        }
+        let mut node_ids = BTreeSet::new();
+        if let Some(node) = self.resource_nodes.iter().find(|n| !node_ids.insert(n.id)) {
+            return Err(BuildError::DuplicateResourceNode(node.id));
+        }
+
+        let mut config = self.config;
+        if let Some(seed) = self.seed {
+            config.mode = SimulationMode::Deterministic { seed };
+        }
+        let mut sim = BattleSimulator::try_with_config(self.units, self.start_time, grid, config)?;
+        if !self.objectives.is_empty() {
+            sim.set_objectives(self.objectives);
+        }
+        for wave in self.waves {
+            sim.schedule_wave(wave)?;
+        }
+        for node in self.resource_nodes {
+            sim.add_resource_node(node);
+        }
+        Ok(sim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::Weapon;
+    use crate::simulator::CompletionReason;
+
+    const DT: f32 = 0.05;
+
+    fn ship(id: u32, x: f32, z: f32) -> BattleUnit {
+        BattleUnit::builder()
+            .id(id)
+            .faction(1)
+            .position(x, 0.0, z)
+            .hp(200.0)
+            .max_speed(50.0)
+            .as_ship()
+            .weapons(vec![Weapon::builder().tag("LASER").dps(20.0).range(80.0, 100.0).build()])
+            .build()
+            .unwrap()
+    }
+
+    fn fleet(first_id: u32, count: u32, x: f32) -> Vec<BattleUnit> {
+        (0..count).map(|i| ship(first_id + i, x, i as f32 * 5.0)).collect()
+    }
+
+    fn station(id: u32, x: f32) -> BattleUnit {
+        BattleUnit::builder()
+            .id(id)
+            .faction(1)
+            .position(x, 0.0, 0.0)
+            .hp(2000.0)
+            .as_station()
+            .weapons(vec![Weapon::builder().tag("LASER").dps(10.0).range(80.0, 100.0).build()])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_equal_fleets_fight_to_a_finish() {
+        let mut sim = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 5, 0.0))
+            .add_faction_units(2, fleet(100, 5, 60.0))
+            .set_seed(1)
+            .build()
+            .unwrap();
+        assert_eq!(sim.get_active_factions(), vec![1, 2]);
+
+        let report = sim.run_to_completion(DT, 5000);
+        assert_eq!(report.reason, CompletionReason::Elimination);
+        assert!(report.winner.is_some());
+    }
+
+    #[test]
+    fn test_station_assault_with_reinforcements() {
+        let mut sim = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 6, 0.0))
+            .add_unit(BattleUnit { faction_id: 2, ..station(900, 70.0) })
+            .set_objective(1, BattleObjective::KillUnit(900))
+            .add_reinforcement_wave(ReinforcementWave {
+                arrival_tick: 20,
+                units: fleet(200, 3, 0.0),
+                faction_id: Some(1),
+                spawn: Some((-40.0, 0.0, 0.0)),
+            })
+            .set_seed(2)
+            .build()
+            .unwrap();
+        assert_eq!(sim.pending_reinforcements(), 3);
+
+        let report = sim.run_to_completion(DT, 20000);
+        assert_eq!(report.reason, CompletionReason::Objective);
+        assert_eq!(report.winner, Some(1));
+        assert!(sim.get_unit(200).is_some());
+    }
+
+    #[test]
+    fn test_three_way_battle() {
+        let mut sim = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 4, 0.0))
+            .add_faction_units(2, fleet(100, 4, 60.0))
+            .add_faction_units(3, fleet(200, 4, 30.0).into_iter().map(|u| BattleUnit { pos_y: 50.0, ..u }).collect())
+            .add_resource_node(ResourceNode {
+                id: 1,
+                pos_x: 30.0,
+                pos_y: 20.0,
+                pos_z: 0.0,
+                control_radius: 30.0,
+                resource_per_tick: 1.0,
+                controlled_by: None,
+            })
+            .set_seed(3)
+            .build()
+            .unwrap();
+        assert_eq!(sim.get_active_factions(), vec![1, 2, 3]);
+        assert_eq!(sim.resource_nodes().len(), 1);
+
+        let report = sim.run_to_completion(DT, 10000);
+        assert_eq!(report.reason, CompletionReason::Elimination);
+        assert_eq!(sim.get_active_factions().len(), 1);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        // One side unarmed - an unarmed station doesn't count
+        let unarmed = BattleUnit { faction_id: 2, weapons: Vec::new(), has_weapons: false, ..station(900, 70.0) };
+        let err = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 2, 0.0))
+            .add_unit(unarmed.clone())
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err, BuildError::NotEnoughArmedFactions(vec![1]));
+
+        // ...but an armed wave for it does
+        let sim = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 2, 0.0))
+            .add_unit(unarmed)
+            .add_reinforcement_wave(ReinforcementWave { arrival_tick: 10, units: fleet(50, 1, 70.0), faction_id: Some(2), spawn: None })
+            .build();
+        assert!(sim.is_ok());
+
+        let err = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 2, 0.0))
+            .add_faction_units(2, fleet(2, 2, 60.0))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err, BuildError::InvalidUnit(ValidationError::DuplicateId { id: 2 }));
+
+        let node = ResourceNode { id: 4, pos_x: 0.0, pos_y: 0.0, pos_z: 0.0, control_radius: 10.0, resource_per_tick: 1.0, controlled_by: None };
+        let err = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 2, 0.0))
+            .add_faction_units(2, fleet(10, 2, 60.0))
+            .add_resource_node(node.clone())
+            .add_resource_node(node)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err, BuildError::DuplicateResourceNode(4));
+    }
+
+    #[test]
+    fn test_seed_wins_over_config_mode() {
+        let sim = SimulationBuilder::new()
+            .add_faction_units(1, fleet(1, 2, 0.0))
+            .add_faction_units(2, fleet(10, 2, 60.0))
+            .set_seed(9)
+            .with_config(BattleConfig { stalemate_ticks: Some(50), ..Default::default() })
+            .build()
+            .unwrap();
+        assert_eq!(sim.config().mode, SimulationMode::Deterministic { seed: 9 });
+        assert_eq!(sim.config().stalemate_ticks, Some(50));
+    }
+}
@@ -0,0 +1,128 @@
+// battle-core/src/combat_log.rs
+//
+// Bounded history of battle events for after-action views. The simulator
+// appends as it goes; once full the oldest events are dropped.
+
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use crate::simulator::WeaponFired;
+
+/// Default number of events kept (BattleConfig.combat_log_capacity)
+pub const DEFAULT_COMBAT_LOG_CAPACITY: usize = 10_000;
+
+/// One battle event - serialized as {"type": "weapon_fired", "data": {...}}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum CombatLogEntry {
+    WeaponFired(WeaponFired),
+    /// Damage credited to the attacker (None for burns whose source is gone)
+    DamageDealt { attacker: Option<u32>, target: u32, damage: f32 },
+    UnitDestroyed(u32),
+    TargetAcquired { unit_id: u32, target_id: u32 },
+    ShieldBroken(u32),
+    /// Every unit of the faction withdrew (surrender_faction)
+    FactionSurrendered(u32),
+}
+
+/// A logged event with the tick it happened on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub entry: CombatLogEntry,
+}
+
+/// Ring buffer of the most recent events (capacity 0 = logging off)
+#[derive(Debug, Clone)]
+pub struct CombatLog {
+    events: VecDeque<LoggedEvent>,
+    capacity: usize,
+}
+
+impl Default for CombatLog {
+    fn default() -> Self {
+        CombatLog::with_capacity(DEFAULT_COMBAT_LOG_CAPACITY)
+    }
+}
+
+impl CombatLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        CombatLog { events: VecDeque::new(), capacity }
+    }
+
+    /// Check if events are being recorded
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Change the capacity, dropping the oldest events if it shrank
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.events.len() > capacity {
+            self.events.pop_front();
+        }
+    }
+
+    /// Append an event, dropping the oldest when full
+    pub fn push(&mut self, tick: u64, entry: CombatLogEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(LoggedEvent { tick, entry });
+    }
+
+    /// Up to max_entries events from since_tick on, oldest first
+    pub fn since(&self, since_tick: u64, max_entries: usize) -> Vec<&LoggedEvent> {
+        // Events are in tick order, so skip straight to the first match
+        let start = self.events.partition_point(|e| e.tick < since_tick);
+        self.events.range(start..).take(max_entries).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drops_oldest_when_full() {
+        let mut log = CombatLog::with_capacity(3);
+        for tick in 1..=5 {
+            log.push(tick, CombatLogEntry::UnitDestroyed(tick as u32));
+        }
+        assert_eq!(log.len(), 3);
+        let ticks: Vec<u64> = log.since(0, 10).iter().map(|e| e.tick).collect();
+        assert_eq!(ticks, vec![3, 4, 5]);
+        assert_eq!(log.since(4, 1).len(), 1);
+        assert_eq!(log.since(4, 1)[0].tick, 4);
+
+        log.set_capacity(0);
+        log.push(6, CombatLogEntry::ShieldBroken(1));
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn test_serializes_tagged() {
+        let event = LoggedEvent {
+            tick: 7,
+            entry: CombatLogEntry::TargetAcquired { unit_id: 1, target_id: 2 },
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"tick":7,"type":"target_acquired","data":{"unit_id":1,"target_id":2}}"#);
+    }
+}
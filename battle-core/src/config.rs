@@ -0,0 +1,370 @@
+// battle-core/src/config.rs
+//
+// BattleConfig - every simulator tunable in one place. Pass it to
+// BattleSimulator::new_with_config() (or with_config() for a custom spatial
+// index), or replace it mid-battle with set_config(). From JS it's a JSON
+// object; missing fields take the defaults below.
+//
+// A few fields only matter when the simulator is built (grid_cell_size,
+// battlefield_radius size the spatial grid); the rest are read every tick.
+//
+// Durations are real time (*_secs) and turned into tick counts for tick_rate
+// when the config is applied (tick_counts()), so a battle run at 10 or 30 Hz
+// still stalemates after the same simulated minute. tick_rate has to match
+// the host's loop: it implies a fixed dt of 1 / tick_rate, which is what
+// step() advances by and what simulate_tick's dt is expected to be. The
+// *_ticks fields override a duration with an exact tick count. validate()
+// rejects a tick_rate that can't give a dt.
+
+use crate::combat_log::DEFAULT_COMBAT_LOG_CAPACITY;
+use crate::logging::LogLevel;
+use crate::objectives::VictoryCondition;
+use crate::profiling::DEFAULT_PROFILE_WINDOW;
+use crate::rng::SimulationMode;
+use crate::simulator::BattleBounds;
+use crate::spatial_grid::{SpatialGrid, DEFAULT_CELL_SIZE};
+use crate::targeting::PriorityTable;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How often to re-evaluate targets (seconds)
+pub const DEFAULT_RETARGET_INTERVAL_SECS: f32 = 1.0;
+
+/// Morale per second each allied commander in range adds
+pub const DEFAULT_COMMANDER_MORALE_REGEN: f32 = 5.0;
+
+/// Ticks per second - one tick is 0.05s
+pub const DEFAULT_TICK_RATE: f32 = 20.0;
+
+/// Moves shorter than this are left out of delta results
+pub const DEFAULT_POSITION_EPSILON: f32 = 0.01;
+
+/// Default seconds without combat before declaring stalemate
+pub const DEFAULT_STALEMATE_SECS: f32 = 60.0;
+
+/// Seconds after movement before entering idle mode
+pub const DEFAULT_IDLE_MOVEMENT_SECS: f32 = 2.0;
+
+/// How often to re-tune grid cell size when auto_tune_grid is set (seconds)
+pub const DEFAULT_GRID_TUNE_INTERVAL_SECS: f32 = 10.0;
+
+/// Overlap resolution passes per tick for simulator-moved units
+pub const DEFAULT_SEPARATION_ITERATIONS: usize = 3;
+
+/// Default fraction closer a same-priority candidate must be to steal a valid target
+pub const DEFAULT_RETARGET_SWITCH_MARGIN: f32 = 0.2;
+
+/// Default distance an enemy ship has to keep from a disabled unit for it to self-repair
+pub const DEFAULT_DISABLED_REPAIR_RADIUS: f32 = 1000.0;
+
+/// Default seconds a disabled unit has to be left alone before it self-repairs
+pub const DEFAULT_DISABLED_REPAIR_DELAY_SECS: f32 = 5.0;
+
+/// Default distance from every enemy at which a retreating unit has withdrawn
+pub const DEFAULT_RETREAT_DISENGAGE_DISTANCE: f32 = 1000.0;
+
+/// External position updates longer than this clear the unit's target
+pub const DEFAULT_SIGNIFICANT_MOVEMENT_THRESHOLD: f32 = 0.1;
+
+/// Default distance from a dead target within which a guided shot picks a new one
+pub const DEFAULT_MISSILE_REACQUIRE_RADIUS: f32 = 100.0;
+
+/// Default self-destructs one trigger may set off in a chain (blasts catching
+/// other charged units) within a tick
+pub const DEFAULT_SELF_DESTRUCT_CHAIN_LIMIT: u32 = 8;
+
+/// Default cap on the ticks one simulate_ticks / simulate_until_end call runs
+pub const DEFAULT_MAX_FAST_FORWARD_TICKS: u32 = 10_000;
+
+/// Spacing of the spiral reinforcement units are spread along around a
+/// wave's spawn point
+pub const DEFAULT_REINFORCEMENT_SPACING: f32 = 20.0;
+
+/// Simulator tunables - set from JS via set_config() / new_with_config()
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BattleConfig {
+    /// Let the simulator steer retreating units even if they aren't ai_controlled
+    /// When false, player units only move via external position updates
+    pub ai_movement: bool,
+    /// A retreating unit farther than this from every enemy is marked withdrawn
+    pub retreat_disengage_distance: f32,
+    /// Steer simulator-moved units away from nearby allies
+    pub collision_avoidance: bool,
+    /// Allies closer than this repel each other
+    pub separation_radius: f32,
+    /// Repulsion strength (fraction of max_speed at full overlap)
+    pub separation_strength: f32,
+    /// Overlap resolution passes per tick for simulator-moved units
+    pub separation_iterations: usize,
+    /// Periodic retargeting only replaces a valid target with a same-priority
+    /// candidate at least this fraction closer (0.2 = 20%)
+    pub retarget_switch_margin: f32,
+    /// Seconds between periodic target re-evaluations
+    pub retarget_interval_secs: f32,
+    /// Exact ticks between them instead (None = from retarget_interval_secs)
+    pub retarget_interval: Option<u64>,
+    /// Same-priority candidates look up to this fraction farther, by a fixed
+    /// amount per attacker / target pair, spreading a fleet's fire over
+    /// near-equidistant enemies (0 = nearest wins)
+    pub target_spread_factor: f32,
+    /// Spatial grid cell size when the simulator is built (0 = size it to
+    /// the unit count and spread)
+    pub grid_cell_size: f32,
+    /// Radius the grid is sized for when grid_cell_size is 0 (0 = measure
+    /// it from unit positions)
+    pub battlefield_radius: f32,
+    /// Periodically re-tune the spatial grid cell size to current unit density
+    /// (never with a fixed grid_cell_size)
+    pub auto_tune_grid: bool,
+    /// Seconds between auto_tune_grid re-tunes
+    pub grid_tune_interval_secs: f32,
+    /// Exact ticks between them instead (None = from grid_tune_interval_secs)
+    pub grid_tune_interval: Option<u64>,
+    /// Arena edge - external positions are clamped to it and the simulator
+    /// never moves units outside (None = unbounded)
+    pub bounds: Option<BattleBounds>,
+    /// Every N ticks TickResult.moved lists every unit on the battlefield so
+    /// clients that dropped packets can resync (0 = only units that moved)
+    pub emit_all_positions_every_n_ticks: u32,
+    /// Shield regen draws from the capacitor (units without one regen for free)
+    pub shield_regen_uses_energy: bool,
+    /// Events kept in the combat log (0 = don't record)
+    pub combat_log_capacity: usize,
+    /// Per-class target scores, consulted before the built-in ship/station rules
+    pub priority_table: PriorityTable,
+    /// Random rolls from entropy (default) or a fixed seed
+    pub mode: SimulationMode,
+    /// simulate_tick_delta leaves out units that moved less than this since
+    /// the position it last reported for them
+    pub position_epsilon: f32,
+    /// External position updates that move a unit farther than this clear
+    /// its (unlocked) target so it re-acquires from the new spot
+    pub significant_movement_threshold: f32,
+    /// Ticks per second the host runs the battle at - step() advances by
+    /// 1 / tick_rate, and the *_secs durations are counted in these ticks
+    #[serde(alias = "ticks_per_second")]
+    pub tick_rate: f32,
+    /// Seconds after the last movement before the battle may go idle
+    pub idle_movement_secs: f32,
+    /// Exact ticks instead (None = from idle_movement_secs)
+    pub idle_movement_ticks: Option<u64>,
+    /// Disabled units only self-repair with no enemy ship this close...
+    pub disabled_repair_radius: f32,
+    /// ...for this many seconds in a row
+    pub disabled_repair_delay_secs: f32,
+    /// Exact ticks instead (None = from disabled_repair_delay_secs)
+    pub disabled_repair_delay_ticks: Option<u32>,
+    /// Seconds without combat before the battle ends as a stalemate
+    pub stalemate_secs: f32,
+    /// Exact ticks instead (None = from stalemate_secs)
+    pub stalemate_ticks: Option<u64>,
+    /// What wins the battle besides elimination (see get_victory_state);
+    /// stalemate still ends it while unmet
+    pub victory: VictoryCondition,
+    /// Morale per second each allied commander in range adds
+    pub commander_morale_regen: f32,
+    /// Spiral spacing of reinforcements around a wave's spawn point
+    pub reinforcement_spacing: f32,
+    /// WeaponFired carries attacker / target positions and miss offsets
+    pub include_fire_positions: bool,
+    /// Guided shots (missiles, rockets, nukes) whose target dies before they
+    /// land turn on the nearest enemy this close to it, or fizzle (0 = always
+    /// fizzle). Nukes only turn on stations
+    pub missile_reacquire_radius: f32,
+    /// Fizzled guided shots give their round and supply back
+    pub refund_fizzled_ammo: bool,
+    /// Self-destruct blasts hurt allies (and the unit's own faction) too -
+    /// off, they only hit enemies and only set off enemy charges
+    pub self_destruct_friendly_fire: bool,
+    /// Links a chain of self-destructs may run to in one tick - charged units
+    /// caught in a blast past it just take the damage
+    pub self_destruct_chain_limit: u32,
+    /// Most ticks simulate_ticks / simulate_until_end run per call - longer
+    /// requests are cut short with a warning
+    pub max_fast_forward_ticks: u32,
+    /// Battles with fewer units than this run the parallel phases serially
+    /// (parallel builds only, 0 = always split)
+    pub parallel_threshold: usize,
+    /// Crate-wide log level to switch to (None leaves it alone - the level
+    /// is shared by every simulator in the process)
+    pub log_level: Option<LogLevel>,
+    /// Record a replay from construction (see start_recording / export_replay)
+    pub record_replay: bool,
+    /// Time each phase of simulate_tick (see profile_stats)
+    pub profiling: bool,
+    /// Recent ticks the profile's averages and maxima cover
+    pub profile_window: usize,
+}
+
+impl Default for BattleConfig {
+    fn default() -> Self {
+        BattleConfig {
+            ai_movement: false,
+            retreat_disengage_distance: DEFAULT_RETREAT_DISENGAGE_DISTANCE,
+            collision_avoidance: false,
+            separation_radius: 10.0,
+            separation_strength: 1.0,
+            separation_iterations: DEFAULT_SEPARATION_ITERATIONS,
+            retarget_switch_margin: DEFAULT_RETARGET_SWITCH_MARGIN,
+            retarget_interval_secs: DEFAULT_RETARGET_INTERVAL_SECS,
+            retarget_interval: None,
+            target_spread_factor: 0.0,
+            grid_cell_size: 0.0,
+            battlefield_radius: 0.0,
+            auto_tune_grid: false,
+            grid_tune_interval_secs: DEFAULT_GRID_TUNE_INTERVAL_SECS,
+            grid_tune_interval: None,
+            bounds: None,
+            emit_all_positions_every_n_ticks: 0,
+            shield_regen_uses_energy: false,
+            combat_log_capacity: DEFAULT_COMBAT_LOG_CAPACITY,
+            priority_table: PriorityTable::default(),
+            mode: SimulationMode::Stochastic,
+            position_epsilon: DEFAULT_POSITION_EPSILON,
+            significant_movement_threshold: DEFAULT_SIGNIFICANT_MOVEMENT_THRESHOLD,
+            tick_rate: DEFAULT_TICK_RATE,
+            idle_movement_secs: DEFAULT_IDLE_MOVEMENT_SECS,
+            idle_movement_ticks: None,
+            disabled_repair_radius: DEFAULT_DISABLED_REPAIR_RADIUS,
+            disabled_repair_delay_secs: DEFAULT_DISABLED_REPAIR_DELAY_SECS,
+            disabled_repair_delay_ticks: None,
+            stalemate_secs: DEFAULT_STALEMATE_SECS,
+            stalemate_ticks: None,
+            victory: VictoryCondition::Elimination,
+            commander_morale_regen: DEFAULT_COMMANDER_MORALE_REGEN,
+            reinforcement_spacing: DEFAULT_REINFORCEMENT_SPACING,
+            include_fire_positions: true,
+            missile_reacquire_radius: DEFAULT_MISSILE_REACQUIRE_RADIUS,
+            refund_fizzled_ammo: false,
+            self_destruct_friendly_fire: true,
+            self_destruct_chain_limit: DEFAULT_SELF_DESTRUCT_CHAIN_LIMIT,
+            max_fast_forward_ticks: DEFAULT_MAX_FAST_FORWARD_TICKS,
+            parallel_threshold: 0,
+            log_level: None,
+            record_replay: false,
+            profiling: false,
+            profile_window: DEFAULT_PROFILE_WINDOW,
+        }
+    }
+}
+
+/// The config's durations in ticks at its tick_rate (BattleConfig::tick_counts)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickCounts {
+    /// Ticks in one second - periodic debug logging
+    pub second: u64,
+    pub retarget_interval: u64,
+    pub grid_tune_interval: u64,
+    pub idle_movement: u64,
+    pub disabled_repair_delay: u32,
+    pub stalemate: u64,
+}
+
+/// Why a config was refused (BattleConfig::validate)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// tick_rate has to be finite and above 0 - 1 / tick_rate is the dt
+    TickRate(f32),
+    /// A replay is being recorded - the mode has to stay deterministic
+    StochasticWhileRecording,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::TickRate(rate) => write!(f, "tick_rate {} must be a finite number above 0", rate),
+            ConfigError::StochasticWhileRecording => write!(f, "mode must stay deterministic while a replay is recorded"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl BattleConfig {
+    /// Check the fields the simulator can't run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.tick_rate.is_finite() || self.tick_rate <= 0.0 {
+            return Err(ConfigError::TickRate(self.tick_rate));
+        }
+        Ok(())
+    }
+
+    /// Tick length (seconds) step() advances by - the dt the *_secs
+    /// durations assume
+    pub fn fixed_dt(&self) -> f32 {
+        1.0 / self.tick_rate
+    }
+
+    /// Whole ticks closest to `secs` at tick_rate
+    pub fn ticks_for(&self, secs: f32) -> u64 {
+        (secs * self.tick_rate).round().max(0.0) as u64
+    }
+
+    /// Every duration in ticks - a *_ticks override wins over its *_secs
+    pub fn tick_counts(&self) -> TickCounts {
+        TickCounts {
+            second: self.ticks_for(1.0).max(1),
+            retarget_interval: self.retarget_interval.unwrap_or_else(|| self.ticks_for(self.retarget_interval_secs)),
+            grid_tune_interval: self.grid_tune_interval.unwrap_or_else(|| self.ticks_for(self.grid_tune_interval_secs)),
+            idle_movement: self.idle_movement_ticks.unwrap_or_else(|| self.ticks_for(self.idle_movement_secs)),
+            disabled_repair_delay: self.disabled_repair_delay_ticks
+                .unwrap_or_else(|| self.ticks_for(self.disabled_repair_delay_secs).min(u32::MAX as u64) as u32),
+            stalemate: self.stalemate_ticks.unwrap_or_else(|| self.ticks_for(self.stalemate_secs)),
+        }
+    }
+
+    /// Empty spatial grid for a new simulator - grid_cell_size, or the
+    /// default until construction tunes it
+    pub fn spatial_grid(&self) -> SpatialGrid {
+        SpatialGrid::new(if self.grid_cell_size > 0.0 { self.grid_cell_size } else { DEFAULT_CELL_SIZE })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_defaults_and_overrides() {
+        let config: BattleConfig = serde_json::from_str(
+            r#"{ "tick_rate": 10, "retarget_interval": 5, "grid_cell_size": 250, "log_level": "debug" }"#
+        ).unwrap();
+        assert_eq!(config.retarget_interval, Some(5));
+        assert!((config.fixed_dt() - 0.1).abs() < 1e-6);
+        // Durations follow the tick rate unless given in ticks
+        let ticks = config.tick_counts();
+        assert_eq!((ticks.retarget_interval, ticks.stalemate, ticks.idle_movement, ticks.second), (5, 600, 20, 10));
+        assert_eq!(config.spatial_grid().cell_size(), 250.0);
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        // Everything else keeps its default
+        assert_eq!(config.stalemate_secs, DEFAULT_STALEMATE_SECS);
+        assert_eq!(config.target_spread_factor, 0.0);
+        assert_eq!(config.victory, VictoryCondition::Elimination);
+
+        let config: BattleConfig = serde_json::from_str(
+            r#"{ "victory": { "any": [{ "destroy_unit": 7 }, { "survive_ticks": { "faction_id": 2, "ticks": 600 } }] } }"#
+        ).unwrap();
+        assert_eq!(config.victory.to_string(), "any(destroy_unit(7), survive_ticks(2, 600))");
+
+        let config = BattleConfig::default();
+        assert!((config.fixed_dt() - 0.05).abs() < 1e-6);
+        let ticks = config.tick_counts();
+        assert_eq!((ticks.retarget_interval, ticks.stalemate, ticks.disabled_repair_delay), (20, 1200, 100));
+        let config: BattleConfig = serde_json::from_str(r#"{ "ticks_per_second": 30, "stalemate_ticks": 50 }"#).unwrap();
+        assert_eq!((config.tick_counts().retarget_interval, config.tick_counts().stalemate), (30, 50));
+        assert_eq!(config.spatial_grid().cell_size(), DEFAULT_CELL_SIZE);
+        assert_eq!(config.log_level, None);
+    }
+
+    #[test]
+    fn test_validate_tick_rate() {
+        assert_eq!(BattleConfig::default().validate(), Ok(()));
+        for tick_rate in [0.0, -20.0, f32::INFINITY, f32::NAN] {
+            let config = BattleConfig { tick_rate, ..Default::default() };
+            assert!(matches!(config.validate(), Err(ConfigError::TickRate(_))), "{}", tick_rate);
+        }
+        let config: BattleConfig = serde_json::from_str(r#"{ "tick_rate": 0 }"#).unwrap();
+        assert_eq!(config.validate(), Err(ConfigError::TickRate(0.0)));
+    }
+}
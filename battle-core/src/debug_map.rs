@@ -0,0 +1,114 @@
+// battle-core/src/debug_map.rs
+//
+// ASCII top-down view of the battlefield (the X/Z plane) for chasing
+// targeting and position sync bugs without a game client. Debug builds only.
+//
+// The map is centred on the origin, +z at the top. Each cell is one of:
+//   A-Z  armed ship, by faction (1 = A, 2 = B, ... wrapping after Z)
+//   a-z  unarmed ship
+//   S    station
+//   2-9  that many units in the cell (+ for 10 or more)
+//   .    empty
+//
+// Units off the edge of the map, destroyed, withdrawn or docked are left out.
+
+use crate::battle_unit::BattleUnit;
+
+/// World units per character cell when none is given
+pub const DEFAULT_DEBUG_MAP_SCALE: f32 = 100.0;
+
+/// Map size render_debug_map draws (characters)
+pub const DEBUG_MAP_WIDTH: usize = 80;
+pub const DEBUG_MAP_HEIGHT: usize = 40;
+
+/// Letter for one unit on its own in a cell
+fn unit_glyph(unit: &BattleUnit) -> char {
+    if unit.is_station {
+        return 'S';
+    }
+    let letter = (b'A' + ((unit.faction_id as u64 + 25) % 26) as u8) as char;
+    if unit.is_armed() { letter } else { letter.to_ascii_lowercase() }
+}
+
+/// Draw `units` on a width x height grid of `scale` world units per cell -
+/// height lines of width characters, each ending in '\n'
+pub fn render_ascii(units: &[BattleUnit], width: usize, height: usize, scale: f32) -> String {
+    // (count, glyph of the first unit) per cell, row 0 at the top
+    let mut cells: Vec<(u32, char)> = vec![(0, '.'); width * height];
+    for unit in units.iter().filter(|u| u.on_battlefield()) {
+        let col = (unit.pos_x / scale + width as f32 / 2.0).floor();
+        let row = (height as f32 / 2.0 - unit.pos_z / scale).floor();
+        if !(0.0..width as f32).contains(&col) || !(0.0..height as f32).contains(&row) {
+            continue;
+        }
+        let cell = &mut cells[row as usize * width + col as usize];
+        if cell.0 == 0 {
+            cell.1 = unit_glyph(unit);
+        }
+        cell.0 += 1;
+    }
+
+    let mut map = String::with_capacity((width + 1) * height);
+    for row in cells.chunks(width.max(1)).take(height) {
+        map.extend(row.iter().map(|&(count, glyph)| match count {
+            0 | 1 => glyph,
+            2..=9 => char::from_digit(count, 10).unwrap(),
+            _ => '+',
+        }));
+        map.push('\n');
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::Weapon;
+    use crate::simulator::BattleSimulator;
+
+    fn unit(id: u32, faction: u32, x: f32, z: f32) -> BattleUnit {
+        BattleUnit::builder()
+            .id(id)
+            .faction(faction)
+            .position(x, 0.0, z)
+            .hp(100.0)
+            .as_ship()
+            .weapons(vec![Weapon::builder().tag("LASER").dps(10.0).range(80.0, 100.0).build()])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render_glyphs() {
+        let units = vec![
+            unit(1, 1, -40.0, 15.0),
+            unit(2, 2, 15.0, -15.0),
+            BattleUnit { has_weapons: false, weapons: Vec::new(), ..unit(3, 2, 35.0, -15.0) },
+            BattleUnit { is_station: true, ..unit(4, 1, 0.0, 0.0) },
+            unit(5, 2, 21.0, 5.0),
+            unit(6, 2, 29.0, 1.0),
+            unit(7, 1, 21.0, 9.0),
+            // Off the map
+            unit(8, 1, 500.0, 0.0),
+        ];
+
+        let map = render_ascii(&units, 10, 4, 10.0);
+        assert_eq!(map, concat!(
+            ".A........\n",
+            ".......3..\n",
+            ".....S....\n",
+            "......B.b.\n",
+        ));
+    }
+
+    #[test]
+    fn test_simulator_map_skips_destroyed_units() {
+        let mut sim = BattleSimulator::new(vec![unit(1, 1, 0.0, 0.0), unit(2, 3, 10.0, 0.0)], 0.0);
+        assert_eq!(sim.render_ascii(3, 1, 10.0), ".AC\n");
+
+        sim.set_debug_map_scale(20.0);
+        assert_eq!(sim.debug_map_scale(), 20.0);
+        sim.remove_unit(2);
+        assert_eq!(sim.render_ascii(3, 1, sim.debug_map_scale()), ".A.\n");
+    }
+}
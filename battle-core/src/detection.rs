@@ -0,0 +1,162 @@
+// battle-core/src/detection.rs
+//
+// Sensor contacts. Each tick the simulator works out which hostile units
+// every faction can see: those within one of its units' sensor_range()
+// scaled by the target's stealth_modifier. A faction's units share what
+// they see, and targeting only picks from that set - a stealthy unit can
+// close in unseen until it's inside someone's scaled range.
+//
+// A contact a faction already had stays detected while it keeps firing, so
+// a unit can't shoot from just outside sensor range and stay hidden.
+//
+// The sets are flags by unit index (the targeting searches have indices,
+// and the pass marks every candidate of every spotter, so no hashing in
+// the hot loop). They're turned into sorted ids once per tick to report
+// contacts gained and lost (TickResult.contactsDetected / contactsLost).
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// A faction picking up or losing sight of an enemy unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct SensorContact {
+    pub faction_id: u32,
+    pub unit_id: u32,
+}
+
+/// Hostile units each faction can see
+#[derive(Debug, Clone, Default)]
+pub struct Detection {
+    /// faction -> detected flag per unit index, this tick
+    flags: HashMap<u32, Vec<bool>>,
+    /// faction -> sorted ids it detects, this tick and last
+    seen: HashMap<u32, Vec<u32>>,
+    previous: HashMap<u32, Vec<u32>>,
+    /// Units that fired last tick, sorted
+    firing: Vec<u32>,
+    units: usize,
+}
+
+impl Detection {
+    /// Start a pass over `units` units - everything undetected, last tick's
+    /// contacts kept for changes() (buffers keep their capacity)
+    pub fn begin_tick(&mut self, units: usize) {
+        self.units = units;
+        std::mem::swap(&mut self.seen, &mut self.previous);
+        for flags in self.flags.values_mut() {
+            flags.clear();
+            flags.resize(units, false);
+        }
+    }
+
+    /// Detected flags of faction_id, to mark units in
+    pub fn flags_mut(&mut self, faction_id: u32) -> &mut [bool] {
+        let units = self.units;
+        self.flags.entry(faction_id).or_insert_with(|| vec![false; units])
+    }
+
+    /// Enemies faction_id can see this tick, by unit index
+    #[inline]
+    pub fn detected_by(&self, faction_id: u32) -> &[bool] {
+        self.flags.get(&faction_id).map_or(&[], Vec::as_slice)
+    }
+
+    #[inline]
+    pub fn is_detected(&self, faction_id: u32, idx: usize) -> bool {
+        self.detected_by(faction_id).get(idx).copied().unwrap_or(false)
+    }
+
+    /// Units that fired this tick - they stay detected next tick by factions
+    /// that already see them
+    pub fn set_firing(&mut self, attacker_ids: impl IntoIterator<Item = u32>) {
+        self.firing.clear();
+        self.firing.extend(attacker_ids);
+        self.firing.sort_unstable();
+        self.firing.dedup();
+    }
+
+    /// End the pass: last tick's contacts that fired since stay detected
+    /// (`index_of` finds those still on the battlefield), then this tick's
+    /// ids are collected (`id_of` an index)
+    pub fn finish(&mut self, index_of: impl Fn(u32) -> Option<usize>, id_of: impl Fn(usize) -> u32) {
+        if !self.firing.is_empty() {
+            let units = self.units;
+            for (&faction_id, previous) in &self.previous {
+                for &unit_id in previous.iter().filter(|id| self.firing.binary_search(id).is_ok()) {
+                    if let Some(idx) = index_of(unit_id) {
+                        self.flags.entry(faction_id).or_insert_with(|| vec![false; units])[idx] = true;
+                    }
+                }
+            }
+        }
+
+        for seen in self.seen.values_mut() {
+            seen.clear();
+        }
+        for (&faction_id, flags) in &self.flags {
+            let seen = self.seen.entry(faction_id).or_default();
+            seen.extend(flags.iter().enumerate().filter(|(_, &detected)| detected).map(|(idx, _)| id_of(idx)));
+            seen.sort_unstable();
+        }
+    }
+
+    /// Contacts gained and lost since last tick, sorted. Lost contacts are
+    /// only listed while `present` - a destroyed unit isn't a lost contact
+    pub fn changes(&self, detected: &mut Vec<SensorContact>, lost: &mut Vec<SensorContact>, present: impl Fn(u32) -> bool) {
+        detected.clear();
+        lost.clear();
+        diff(&self.seen, &self.previous, detected, |_| true);
+        diff(&self.previous, &self.seen, lost, present);
+        detected.sort_unstable();
+        lost.sort_unstable();
+    }
+}
+
+/// Contacts in `a` and not in `b` for which `keep` holds
+fn diff(a: &HashMap<u32, Vec<u32>>, b: &HashMap<u32, Vec<u32>>, out: &mut Vec<SensorContact>, keep: impl Fn(u32) -> bool) {
+    for (&faction_id, seen) in a {
+        let other = b.get(&faction_id).map_or(&[][..], Vec::as_slice);
+        out.extend(seen.iter()
+            .filter(|&&unit_id| other.binary_search(&unit_id).is_err() && keep(unit_id))
+            .map(|&unit_id| SensorContact { faction_id, unit_id }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contact_changes() {
+        // Unit index i has id 20 + i
+        let (index_of, id_of) = (|id: u32| id.checked_sub(20).map(|i| i as usize), |idx: usize| idx as u32 + 20);
+        let mut detection = Detection::default();
+        detection.begin_tick(4);
+        detection.flags_mut(1)[0] = true;
+        detection.flags_mut(1)[1] = true;
+        detection.flags_mut(2)[3] = true;
+        detection.finish(index_of, id_of);
+        let (mut detected, mut lost) = (Vec::new(), Vec::new());
+        detection.changes(&mut detected, &mut lost, |_| true);
+        assert_eq!(detected.len(), 3);
+        assert_eq!(detected[0], SensorContact { faction_id: 1, unit_id: 20 });
+
+        // 21 slips away, 20 is destroyed, 22 shows up
+        detection.begin_tick(4);
+        detection.flags_mut(1)[2] = true;
+        detection.flags_mut(2)[3] = true;
+        detection.finish(index_of, id_of);
+        detection.changes(&mut detected, &mut lost, |id| id != 20);
+        assert_eq!(detected, vec![SensorContact { faction_id: 1, unit_id: 22 }]);
+        assert_eq!(lost, vec![SensorContact { faction_id: 1, unit_id: 21 }]);
+        assert_eq!(detection.detected_by(1), &[false, false, true, false]);
+        assert!(detection.detected_by(3).is_empty() && !detection.is_detected(3, 0));
+
+        // Out of sensor range but still shooting - kept
+        detection.set_firing([22, 23]);
+        detection.begin_tick(4);
+        detection.finish(index_of, id_of);
+        assert!(detection.is_detected(1, 2) && detection.is_detected(2, 3) && !detection.is_detected(2, 2));
+    }
+}
@@ -0,0 +1,233 @@
+// battle-core/src/error.rs
+//
+// Errors returned across the WASM boundary. Each one reaches JS as a plain
+// object with a stable `code` to switch on, the variant's fields, and a
+// human-readable `message`:
+//   { "code": "UNIT_NOT_FOUND", "unit_id": 42, "message": "unit 42 not found" }
+
+use std::fmt;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use wasm_bindgen::JsValue;
+use crate::builder::BuildError;
+use crate::templates::TemplateError;
+use crate::validation::ValidationError;
+
+#[derive(Debug)]
+pub enum BattleError {
+    /// JSON argument that didn't parse - field names the argument
+    JsonParse { field: String, source: serde_json::Error },
+    /// No unit with this id on the battlefield
+    UnitNotFound(u32),
+    /// The call doesn't make sense in the simulator's current state
+    InvalidState(String),
+    /// The unit carries no weapon with this tag
+    WeaponNotFound { unit_id: u32, tag: String },
+    /// Point outside the battlefield bounds
+    OutOfBounds { x: f32, y: f32, z: f32 },
+    /// Unit data the simulator won't accept (see validation.rs)
+    InvalidUnit(ValidationError),
+    /// Argument that parsed but isn't a known value (index type, stance, ...)
+    InvalidArgument { field: String, message: String },
+    /// A result couldn't be serialized or encoded
+    Encode { what: String, message: String },
+    /// Units JSON that breaks the schema or payload rules (see schema.rs) -
+    /// one "path: message" per problem
+    SchemaValidation { errors: Vec<String> },
+}
+
+impl BattleError {
+    /// map_err adapter for serde_json::from_str on the argument `field`
+    pub fn parse(field: &str) -> impl FnOnce(serde_json::Error) -> BattleError + '_ {
+        move |source| BattleError::JsonParse { field: field.to_string(), source }
+    }
+
+    /// map_err adapter for serializing / encoding `what`
+    pub fn encode<E: fmt::Display>(what: &str) -> impl FnOnce(E) -> BattleError + '_ {
+        move |e| BattleError::Encode { what: what.to_string(), message: e.to_string() }
+    }
+
+    /// map_err adapter for a rejected value of the argument `field`
+    pub fn invalid<E: fmt::Display>(field: &str) -> impl FnOnce(E) -> BattleError + '_ {
+        move |e| BattleError::InvalidArgument { field: field.to_string(), message: e.to_string() }
+    }
+
+    /// Stable identifier JS can switch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            BattleError::JsonParse { .. } => "JSON_PARSE",
+            BattleError::UnitNotFound(_) => "UNIT_NOT_FOUND",
+            BattleError::InvalidState(_) => "INVALID_STATE",
+            BattleError::WeaponNotFound { .. } => "WEAPON_NOT_FOUND",
+            BattleError::OutOfBounds { .. } => "OUT_OF_BOUNDS",
+            BattleError::InvalidUnit(_) => "INVALID_UNIT",
+            BattleError::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            BattleError::Encode { .. } => "ENCODE",
+            BattleError::SchemaValidation { .. } => "SCHEMA_VALIDATION",
+        }
+    }
+}
+
+impl fmt::Display for BattleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BattleError::JsonParse { field, source } => write!(f, "failed to parse {}: {}", field, source),
+            BattleError::UnitNotFound(id) => write!(f, "unit {} not found", id),
+            BattleError::InvalidState(message) => write!(f, "{}", message),
+            BattleError::WeaponNotFound { unit_id, tag } => write!(f, "unit {} has no {} weapon", unit_id, tag),
+            BattleError::OutOfBounds { x, y, z } => write!(f, "({}, {}, {}) is outside the battlefield bounds", x, y, z),
+            BattleError::InvalidUnit(e) => write!(f, "invalid unit: {}", e),
+            BattleError::InvalidArgument { field, message } => write!(f, "invalid {}: {}", field, message),
+            BattleError::Encode { what, message } => write!(f, "failed to encode {}: {}", what, message),
+            BattleError::SchemaValidation { errors } => write!(f, "invalid units: {}", errors.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for BattleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BattleError::JsonParse { source, .. } => Some(source),
+            BattleError::InvalidUnit(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for BattleError {
+    fn from(source: serde_json::Error) -> Self {
+        BattleError::JsonParse { field: "json".to_string(), source }
+    }
+}
+
+impl From<ValidationError> for BattleError {
+    fn from(e: ValidationError) -> Self {
+        BattleError::InvalidUnit(e)
+    }
+}
+
+impl From<BuildError> for BattleError {
+    fn from(e: BuildError) -> Self {
+        match e {
+            BuildError::InvalidUnit(e) => BattleError::InvalidUnit(e),
+            e => BattleError::InvalidArgument { field: "scenario".to_string(), message: e.to_string() },
+        }
+    }
+}
+
+impl From<TemplateError> for BattleError {
+    fn from(e: TemplateError) -> Self {
+        match e {
+            TemplateError::InvalidUnit(e) => BattleError::InvalidUnit(e),
+            e => BattleError::InvalidArgument { field: "template_id".to_string(), message: e.to_string() },
+        }
+    }
+}
+
+/// The object Serialize below writes
+#[cfg(feature = "typegen")]
+impl schemars::JsonSchema for BattleError {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "BattleError".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Thrown by the WASM methods - switch on code; the other fields depend on it",
+            "type": "object",
+            "required": ["code", "message"],
+            "properties": {
+                "code": {
+                    "type": "string",
+                    "enum": [
+                        "JSON_PARSE", "UNIT_NOT_FOUND", "INVALID_STATE", "WEAPON_NOT_FOUND", "OUT_OF_BOUNDS",
+                        "INVALID_UNIT", "INVALID_ARGUMENT", "ENCODE", "SCHEMA_VALIDATION"
+                    ]
+                },
+                "message": { "type": "string" },
+                "field": { "type": "string", "description": "JSON_PARSE, INVALID_ARGUMENT: the argument" },
+                "unit_id": { "type": "integer", "description": "UNIT_NOT_FOUND, WEAPON_NOT_FOUND" },
+                "tag": { "type": "string", "description": "WEAPON_NOT_FOUND" },
+                "x": { "type": "number", "description": "OUT_OF_BOUNDS" },
+                "y": { "type": "number", "description": "OUT_OF_BOUNDS" },
+                "z": { "type": "number", "description": "OUT_OF_BOUNDS" },
+                "problem": { "type": "object", "description": "INVALID_UNIT: { kind, id, ... } (see validation.rs)" },
+                "what": { "type": "string", "description": "ENCODE" },
+                "errors": { "type": "array", "items": { "type": "string" }, "description": "SCHEMA_VALIDATION: \"path: message\" per problem" }
+            }
+        })
+    }
+}
+
+/// The JS object: code, the variant's fields, message
+impl Serialize for BattleError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", self.code())?;
+        match self {
+            BattleError::JsonParse { field, .. } => map.serialize_entry("field", field)?,
+            BattleError::UnitNotFound(id) => map.serialize_entry("unit_id", id)?,
+            BattleError::InvalidState(_) => {}
+            BattleError::WeaponNotFound { unit_id, tag } => {
+                map.serialize_entry("unit_id", unit_id)?;
+                map.serialize_entry("tag", tag)?;
+            }
+            BattleError::OutOfBounds { x, y, z } => {
+                map.serialize_entry("x", x)?;
+                map.serialize_entry("y", y)?;
+                map.serialize_entry("z", z)?;
+            }
+            BattleError::InvalidUnit(e) => map.serialize_entry("problem", e)?,
+            BattleError::InvalidArgument { field, .. } => map.serialize_entry("field", field)?,
+            BattleError::Encode { what, .. } => map.serialize_entry("what", what)?,
+            BattleError::SchemaValidation { errors } => map.serialize_entry("errors", errors)?,
+        }
+        map.serialize_entry("message", &self.to_string())?;
+        map.end()
+    }
+}
+
+impl From<BattleError> for JsValue {
+    fn from(error: BattleError) -> Self {
+        let json = serde_json::to_string(&error).unwrap_or_else(|_| error.to_string());
+        js_sys::JSON::parse(&json).unwrap_or_else(|_| JsValue::from_str(&json))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_js_payload() {
+        let json = |e: BattleError| serde_json::to_value(&e).unwrap();
+
+        assert_eq!(
+            json(BattleError::UnitNotFound(42)),
+            serde_json::json!({ "code": "UNIT_NOT_FOUND", "unit_id": 42, "message": "unit 42 not found" })
+        );
+        assert_eq!(
+            json(BattleError::WeaponNotFound { unit_id: 7, tag: "LASER".to_string() })["tag"],
+            "LASER"
+        );
+        assert_eq!(
+            json(ValidationError::DuplicateId { id: 3 }.into()),
+            serde_json::json!({
+                "code": "INVALID_UNIT",
+                "problem": { "kind": "duplicate_id", "id": 3 },
+                "message": "invalid unit: unit 3: duplicate id",
+            })
+        );
+
+        let parse = serde_json::from_str::<Vec<u32>>("[1,").map_err(BattleError::parse("units")).unwrap_err();
+        assert_eq!(parse.code(), "JSON_PARSE");
+        assert!(std::error::Error::source(&parse).is_some());
+        let payload = json(parse);
+        assert_eq!(payload["field"], "units");
+        assert!(payload["message"].as_str().unwrap().starts_with("failed to parse units: "));
+
+        let errors = vec!["/0/hp: \"x\" is not of type \"number\"".to_string(), "/1/id: duplicate id 1".to_string()];
+        let payload = json(BattleError::SchemaValidation { errors });
+        assert_eq!(payload["code"], "SCHEMA_VALIDATION");
+        assert_eq!(payload["errors"][1], "/1/id: duplicate id 1");
+    }
+}
@@ -0,0 +1,184 @@
+// battle-core/src/factions.rs
+//
+// Faction hostility. By default every faction is hostile to every other one
+// (the original `faction_id !=` behavior); pairs can be marked allied.
+//
+// Per-faction tuning (FactionConfig): damage / shield regen / speed
+// multipliers and faction-exclusive special weapons.
+
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::weapons::tag_starts_with;
+
+/// Alliance table - factions not listed as allied are hostile
+#[derive(Debug, Clone, Default)]
+pub struct FactionRelations {
+    /// Allied pairs stored as (low, high)
+    allied: HashSet<(u32, u32)>,
+}
+
+#[inline]
+fn pair(a: u32, b: u32) -> (u32, u32) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+impl FactionRelations {
+    /// Mark a pair allied (or hostile again) - returns true if anything changed
+    pub fn set_allied(&mut self, a: u32, b: u32, allied: bool) -> bool {
+        if a == b {
+            return false; // A faction is always allied with itself
+        }
+        if allied {
+            self.allied.insert(pair(a, b))
+        } else {
+            self.allied.remove(&pair(a, b))
+        }
+    }
+
+    /// Check if two factions are on the same side (a faction is allied with itself)
+    #[inline]
+    pub fn is_allied(&self, a: u32, b: u32) -> bool {
+        a == b || (!self.allied.is_empty() && self.allied.contains(&pair(a, b)))
+    }
+
+    /// Check if units of these factions fight each other
+    #[inline]
+    pub fn is_hostile(&self, a: u32, b: u32) -> bool {
+        !self.is_allied(a, b)
+    }
+
+    /// Check if any pair is allied (everyone hostile otherwise)
+    #[inline]
+    pub fn has_alliances(&self) -> bool {
+        !self.allied.is_empty()
+    }
+
+    /// All allied pairs as (low, high), sorted
+    pub fn alliances(&self) -> Vec<(u32, u32)> {
+        let mut pairs: Vec<(u32, u32)> = self.allied.iter().copied().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    /// Check if any two of these factions are hostile to each other
+    pub fn any_hostile(&self, factions: &[u32]) -> bool {
+        factions.iter().enumerate().any(|(i, &a)| {
+            factions[i + 1..].iter().any(|&b| self.is_hostile(a, b))
+        })
+    }
+}
+
+/// Per-faction balance - factions without one fight at 1x
+///
+/// JSON: { "faction_id": 2, "damage_multiplier": 1.1, "special_weapon_tags": ["PLASMA"] }
+/// - the multipliers default to 1, the tags to none.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct FactionConfig {
+    pub faction_id: u32,
+    /// Scales the damage of every shot the faction's units fire
+    #[serde(default = "one")]
+    pub damage_multiplier: f32,
+    #[serde(default = "one")]
+    pub shield_regen_multiplier: f32,
+    /// Scales max_speed of simulator-moved units
+    #[serde(default = "one")]
+    pub speed_multiplier: f32,
+    /// Weapon tag prefixes only this faction may fire - other factions'
+    /// units carrying them hold fire with them
+    #[serde(default)]
+    pub special_weapon_tags: Vec<String>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl FactionConfig {
+    pub fn new(faction_id: u32) -> Self {
+        FactionConfig {
+            faction_id,
+            damage_multiplier: 1.0,
+            shield_regen_multiplier: 1.0,
+            speed_multiplier: 1.0,
+            special_weapon_tags: Vec::new(),
+        }
+    }
+
+    /// Check if the weapon tag is one of this faction's special weapons
+    pub fn is_special(&self, weapon_tag: &str) -> bool {
+        self.special_weapon_tags.iter().any(|prefix| tag_starts_with(weapon_tag, prefix))
+    }
+}
+
+/// FactionConfig by faction id
+pub type FactionConfigs = HashMap<u32, FactionConfig>;
+
+/// Damage multiplier of a faction (1 without a config)
+#[inline]
+pub fn damage_multiplier(configs: &FactionConfigs, faction_id: u32) -> f32 {
+    configs.get(&faction_id).map_or(1.0, |c| c.damage_multiplier)
+}
+
+/// Shield regen multiplier of a faction (1 without a config)
+#[inline]
+pub fn shield_regen_multiplier(configs: &FactionConfigs, faction_id: u32) -> f32 {
+    configs.get(&faction_id).map_or(1.0, |c| c.shield_regen_multiplier)
+}
+
+/// Speed multiplier of a faction (1 without a config)
+#[inline]
+pub fn speed_multiplier(configs: &FactionConfigs, faction_id: u32) -> f32 {
+    configs.get(&faction_id).map_or(1.0, |c| c.speed_multiplier)
+}
+
+/// Check if a unit of this faction may fire the weapon - false if another
+/// faction claims it as a special weapon
+#[inline]
+pub fn may_fire(configs: &FactionConfigs, faction_id: u32, weapon_tag: &str) -> bool {
+    configs.is_empty() || !configs.values().any(|c| c.faction_id != faction_id && c.is_special(weapon_tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_everyone_hostile() {
+        let relations = FactionRelations::default();
+        assert!(relations.is_hostile(1, 2));
+        assert!(!relations.is_hostile(3, 3));
+        assert!(relations.any_hostile(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_alliances_are_symmetric() {
+        let mut relations = FactionRelations::default();
+        assert!(relations.set_allied(3, 2, true));
+        assert!(!relations.set_allied(2, 3, true));
+        assert!(relations.is_allied(2, 3) && relations.is_allied(3, 2));
+        assert!(!relations.any_hostile(&[2, 3]));
+        assert!(relations.any_hostile(&[1, 2, 3]));
+        assert_eq!(relations.alliances(), vec![(2, 3)]);
+
+        assert!(relations.set_allied(2, 3, false));
+        assert!(relations.is_hostile(2, 3));
+        assert!(!relations.set_allied(1, 1, true));
+    }
+
+    #[test]
+    fn test_faction_config_defaults_and_special_weapons() {
+        let config: FactionConfig = serde_json::from_str(
+            r#"{ "faction_id": 2, "damage_multiplier": 1.1, "special_weapon_tags": ["plasma"] }"#
+        ).unwrap();
+        assert_eq!(config.shield_regen_multiplier, 1.0);
+        assert_eq!(config.speed_multiplier, 1.0);
+
+        let configs: FactionConfigs = [(2, config)].into_iter().collect();
+        assert_eq!(damage_multiplier(&configs, 2), 1.1);
+        assert_eq!(damage_multiplier(&configs, 1), 1.0);
+        assert!(may_fire(&configs, 2, "PLASMA_LANCE"));
+        assert!(!may_fire(&configs, 1, "PLASMA_LANCE"));
+        assert!(may_fire(&configs, 1, "LASER"));
+    }
+}
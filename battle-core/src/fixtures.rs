@@ -0,0 +1,46 @@
+// battle-core/src/fixtures.rs
+//
+// Ready-made units for tests and native callers. Each returns a builder
+// preset to a typical unit of its kind, so callers only chain what matters
+// to them - build() still derives has_weapons / max_weapon_range:
+//
+//   let escort = fixtures::ship(1, 1).position(0.0, 0.0, 50.0).build()?;
+//   let depot = fixtures::station(900, 2).weapons(Vec::new()).build()?;
+
+use crate::battle_unit::{BattleUnit, BattleUnitBuilder, Weapon};
+
+/// Armed ship - 500 hp, speed 50, one 20 dps laser
+pub fn ship(id: u32, faction_id: u32) -> BattleUnitBuilder {
+    BattleUnit::builder()
+        .id(id)
+        .faction(faction_id)
+        .hp(500.0)
+        .max_speed(50.0)
+        .view_range(150.0)
+        .as_ship()
+        .weapon(Weapon::laser(20.0))
+}
+
+/// Unarmed ship - 300 hp, speed 30
+pub fn freighter(id: u32, faction_id: u32) -> BattleUnitBuilder {
+    BattleUnit::builder()
+        .id(id)
+        .faction(faction_id)
+        .hp(300.0)
+        .max_speed(30.0)
+        .view_range(150.0)
+        .as_ship()
+}
+
+/// Armed station - 5000 hp, a missile battery and point defense, doesn't move
+pub fn station(id: u32, faction_id: u32) -> BattleUnitBuilder {
+    BattleUnit::builder()
+        .id(id)
+        .faction(faction_id)
+        .hp(5000.0)
+        .max_speed(0.0)
+        .view_range(250.0)
+        .as_station()
+        .weapon(Weapon::missile(50.0))
+        .weapon(Weapon::point_defense(10.0))
+}
@@ -5,29 +5,158 @@
 // 2. Added force_retarget() - force units to re-evaluate targets
 // 3. Added update_single_unit_position() - update a single unit's position
 // 4. ✅ NEW: Added is_idle() and get_idle_info() for idle mode optimization
+// 5. Added set_spatial_index_type() - switch between grid and octree
+// 6. Added get_out_of_bounds_units() - debug helper for battlefield bounds
+// 7. simulate_tick() hands the result back to the simulator for buffer reuse
+// 8. Added add_units_batch() and remove_unit() for bulk / admin operations
+// 9. Added set_log_level() - all logging goes through log_at! (default Info)
+// 10. Added pause_battle() / resume_battle() / is_paused()
+// 11. Added get_unit() / get_units_by_faction() - serialize without cloning every unit
+// 12. Added set_alliances() / set_factions_allied() - allied factions don't fight
+// 13. Added query_units_in_radius() - AoE / proximity / fog-of-war queries
+// 14. Added run_to_completion() - auto-resolve without per-tick round trips
+// 15. Added manually_fire_weapon() - player-activated weapons
+// 16. Added lock_target() / unlock_target() - pin a target against AI retargeting
+// 17. Added set_faction_alliance() / are_allies()
+// 18. Added get_unit_stats() / get_faction_stats()
+// 19. Added get_tick() / get_ticks_since_combat() / get_stalemate_threshold() / is_stalemate()
+// 20. Added get_combat_log() / clear_combat_log()
+// 21. Added set_priority_table()
+// 22. Added start_recording() / stop_recording() / export_replay(), and
+//     from_replay() / step_replay() for playback
+// 23. Added simulate_tick_bin() / get_results_bin() / get_units_by_faction_bin() -
+//     versioned MessagePack (binary.rs) alongside the JSON methods
+// 24. Added new_deterministic() - seeded constructor for reproducible battles
+// 25. Added simulate_tick_binary() - length-prefixed bincode TickResult
+// 26. Added schedule_reinforcements() / get_pending_reinforcements()
+// 27. Added simulate_tick_delta() - leaves out unchanged units (DeltaTickResult)
+// 28. Added pause() / resume() / step() - time spent paused doesn't count
+//     towards cooldowns; pause_battle() / resume_battle() kept as aliases
+// 29. Added get_unit_veterancy()
+// 30. Added get_weapon_stats(); run_to_completion() report includes
+//     per-tag weapon totals
+// 31. Added launch_fighter() / dock_fighter() for carriers
+// 32. remove_unit() withdraws the unit instead of killing it
+// 33. Added get_weapon_ammo()
+// 34. Constructors, add_unit / add_units_batch and schedule_reinforcements
+//     reject invalid units (validation.rs); added validate_units_json()
+// 35. Added get_surrendered_units()
+// 36. Added set_class_weapon_restrictions()
+// 37. Added set_objectives() / get_completed_objectives()
+// 38. Added add_resource_node() / remove_resource_node() / get_resource_nodes()
+//     / get_faction_resources()
+// 39. Added issue_move_order() / issue_move_orders() / clear_orders() -
+//     the simulator moves ordered units itself
+// 40. Added schedule_reinforcement_wave() - waves with a faction and spawn point
+// 41. Added assign_group() / set_group_target() / set_group_stance() / clear_group()
+// 42. Added set_stalemate_threshold()
+// 43. Added get_grid_stats() / debug_query_nearby() - spatial grid diagnostics
+// 44. Errors are BattleError (error.rs) - JS gets { code, ..., message } objects
+// 45. Added new_with_config(); set_config() takes config::BattleConfig
+// 46. Added surrender_faction()
+// 47. Added WasmSimulationBuilder - whole scenarios checked in one build()
+// 48. The .d.ts has interfaces for the JSON payloads (typescript.rs)
+// 49. Added set_faction_config() - per-faction multipliers and special weapons
+// 50. Added register_template() / add_unit_from_template()
+// 51. Added get_profile_stats() - per-phase tick timing (config.profiling)
+// 52. Added render_debug_map() / set_debug_map_scale() (debug builds only)
+// 53. Constructors check units JSON against a schema (schema.rs) first;
+//     validate_units_json() returns "path: message" strings
+// 54. fixtures.rs - preset unit builders for tests and native callers
+// 55. types/battle-core.d.ts generated from JsonSchema derives (feature
+//     typegen, typegen.rs)
+// 56. Added get_victory_state() - how config.victory was met
+// 57. Added register_weapon_tag_prefix() / get_weapon_tags() - weapon categories
+// 58. Added set_unit_power() - shield / weapons / engines power split
+// 59. Added get_average_tick_duration_us() - tick results carry version and
+//     tickDurationUs
+// 60. Added trigger_self_destruct() - blasts are reported in selfDestructed
+// 61. Added simulate_n_ticks() / simulate_until_end() - fast-forward on the
+//     host clock
+// 62. Detection and stealth (detection.rs, BattleUnit.stealth_modifier) -
+//     tick results list contactsDetected / contactsLost
+// 63. Added snapshot_battle() / restore_battle() / get_initial_snapshot() -
+//     roll back for lag compensation
+// 64. Replays keep the config, alliances and player commands; config.record_replay
+//     records from construction; added replay_step() (alias of step_replay())
+// 65. get_victory_state() also reports the objective that decided the battle
+// 66. Configs with a tick_rate that isn't finite and above 0 are refused
+//     (INVALID_ARGUMENT, field "config")
+// 67. Replays record every host input that changes the battle; recording
+//     needs a deterministic battle, and set_config() can't switch a recorded
+//     one to stochastic
+// 68. The .d.ts payload interfaces and the units schema are the generated
+//     types/ files (typegen.rs) - no hand-written copies
 
-mod spatial_grid;
-mod battle_unit;
-mod simulator;
-mod targeting;
-mod weapons;
-mod movement;
+pub mod logging;
+pub mod spatial_grid;
+pub mod spatial_index;
+pub mod octree;
+pub mod battle_unit;
+pub mod simulator;
+pub mod targeting;
+pub mod detection;
+pub mod weapons;
+pub mod movement;
+pub mod status_effect;
+pub mod factions;
+pub mod combat_log;
+pub mod replay;
+pub mod binary;
+pub mod rng;
+pub mod validation;
+pub mod objectives;
+pub mod resources;
+pub mod error;
+pub mod config;
+pub mod builder;
+pub mod typescript;
+pub mod templates;
+pub mod profiling;
+pub mod schema;
+pub mod fixtures;
+#[cfg(feature = "typegen")]
+pub mod typegen;
+#[cfg(debug_assertions)]
+pub mod debug_map;
 
 use wasm_bindgen::prelude::*;
-use simulator::BattleSimulator;
-use battle_unit::BattleUnit;
-use serde_json;
+use error::BattleError;
+use simulator::{BattleSimulator, BattleSnapshot, DeltaTickResult, ReinforcementWave};
+use config::BattleConfig;
+use builder::SimulationBuilder;
+use battle_unit::{BattleUnit, ShipClass, Stance};
+use weapons::WeaponCategory;
+use spatial_index::AnySpatialIndex;
+use targeting::PriorityTable;
+use replay::ReplayRecorder;
+use rng::SimulationMode;
+use logging::LogLevel;
+use objectives::BattleObjective;
+use resources::ResourceNode;
+use factions::FactionConfig;
+use templates::UnitTemplate;
+use profiling::{Phase, PhaseTimer};
 use serde::{Deserialize, Serialize};
 
 // JS console binding that works in both browser and Node.js
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     pub fn log(s: &str);
 }
 
+/// Native builds (cargo test) have no JS console - logging is a no-op
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(_s: &str) {
+    #[cfg(test)]
+    logging::LINES_LOGGED.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+}
+
 /// Position update for syncing external movement
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
 pub struct PositionUpdate {
     pub id: u32,
     pub x: f32,
@@ -37,10 +166,49 @@ pub struct PositionUpdate {
     pub clear_target: bool,  // If true, clear the unit's current target
 }
 
+/// Destination for a simulator-driven move (see issue_move_orders)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveOrder {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Group membership change (see assign_group) - null groupId leaves the group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupAssignment {
+    #[serde(rename = "unitId")]
+    pub unit_id: u32,
+    #[serde(rename = "groupId")]
+    pub group_id: Option<u32>,
+}
+
 /// WASM-exported battle simulator
 #[wasm_bindgen]
 pub struct WasmBattleSimulator {
-    simulator: BattleSimulator,
+    simulator: BattleSimulator<AnySpatialIndex>,
+}
+
+impl WasmBattleSimulator {
+    /// Why manually_fire refused - a missing unit or weapon, else the
+    /// weapon just can't fire right now
+    fn manual_fire_error(&self, attacker_id: u32, target_id: u32, weapon_tag: &str) -> BattleError {
+        let on_field = |id| self.simulator.get_unit(id).filter(|u| u.in_battle());
+        let Some(attacker) = on_field(attacker_id) else {
+            return BattleError::UnitNotFound(attacker_id);
+        };
+        if on_field(target_id).is_none() {
+            return BattleError::UnitNotFound(target_id);
+        }
+        if !attacker.weapons.iter().any(|w| w.tag == weapon_tag) {
+            return BattleError::WeaponNotFound { unit_id: attacker_id, tag: weapon_tag.to_string() };
+        }
+        BattleError::InvalidState(format!(
+            "unit {} can't fire {} at {} (disabled, not ready, out of range or not hostile)",
+            attacker_id, weapon_tag, target_id
+        ))
+    }
 }
 
 #[wasm_bindgen]
@@ -48,50 +216,524 @@ impl WasmBattleSimulator {
     /// Create new simulator from JSON units
     /// current_time should be Date.now() / 1000 (seconds since epoch)
     #[wasm_bindgen(constructor)]
-    pub fn new(units_json: &str, current_time: f64) -> Result<WasmBattleSimulator, JsValue> {
-        let units: Vec<BattleUnit> = serde_json::from_str(units_json)
-            .map_err(|e| JsValue::from_str(&format!("Failed to parse units: {}", e)))?;
+    pub fn new(units_json: &str, current_time: f64) -> Result<WasmBattleSimulator, BattleError> {
+        let units = schema::parse_units(units_json)?;
+
+        let simulator = BattleSimulator::try_with_config(units, current_time, AnySpatialIndex::default(), BattleConfig::default())
+            .map_err(BattleError::from)?;
+        Ok(WasmBattleSimulator { simulator })
+    }
+
+    /// Create a simulator with a full config in place from the start - takes
+    /// JSON (missing fields use defaults), see config::BattleConfig
+    #[wasm_bindgen]
+    pub fn new_with_config(units_json: &str, current_time: f64, config_json: &str) -> Result<WasmBattleSimulator, BattleError> {
+        let units = schema::parse_units(units_json)?;
+        let config: BattleConfig = serde_json::from_str(config_json)
+            .map_err(BattleError::parse("config"))?;
+        config.validate()
+            .map_err(BattleError::invalid("config"))?;
+
+        let grid = AnySpatialIndex::Grid(config.spatial_grid());
+        let simulator = BattleSimulator::try_with_config(units, current_time, grid, config)
+            .map_err(BattleError::from)?;
+        Ok(WasmBattleSimulator { simulator })
+    }
+
+    /// Create a simulator whose random rolls come from `seed` - the same units,
+    /// seed and inputs always play out the same battle. seed is a BigInt in JS.
+    #[wasm_bindgen]
+    pub fn new_deterministic(units_json: &str, current_time: f64, seed: u64) -> Result<WasmBattleSimulator, BattleError> {
+        let units = schema::parse_units(units_json)?;
+
+        let config = BattleConfig {
+            mode: SimulationMode::Deterministic { seed },
+            ..Default::default()
+        };
+        let simulator = BattleSimulator::try_with_config(units, current_time, AnySpatialIndex::default(), config)
+            .map_err(BattleError::from)?;
+        Ok(WasmBattleSimulator { simulator })
+    }
 
+    /// Check a units JSON array the way the constructors do, without building
+    /// a simulator - returns a JSON array of problems ([] = valid), e.g.
+    /// ["/3/weapons/0/dps: 0 is less than or equal to the minimum of 0", "/5/id: duplicate id 7"]
+    #[wasm_bindgen]
+    pub fn validate_units_json(units_json: &str) -> Result<String, BattleError> {
+        let value: serde_json::Value = serde_json::from_str(units_json)
+            .map_err(BattleError::parse("units"))?;
+
+        let mut problems = schema::units_payload_errors(&value);
+        if problems.is_empty() {
+            let units: Vec<BattleUnit> = serde_json::from_value(value)
+                .map_err(BattleError::parse("units"))?;
+            problems.extend(validation::validate_units(&units, []).iter().map(|e| e.to_string()));
+        }
+        serde_json::to_string(&problems)
+            .map_err(BattleError::encode("problems"))
+    }
+
+    /// Create a simulator that plays back an export_replay() with the config
+    /// it was recorded under - step it with step_replay()
+    #[wasm_bindgen]
+    pub fn from_replay(data: &[u8]) -> Result<WasmBattleSimulator, BattleError> {
+        let replay = ReplayRecorder::import(data)
+            .map_err(BattleError::invalid("replay"))?;
+        replay.config.validate()
+            .map_err(BattleError::invalid("config"))?;
+
+        let grid = AnySpatialIndex::Grid(replay.config.spatial_grid());
         Ok(WasmBattleSimulator {
-            simulator: BattleSimulator::new(units, current_time),
+            simulator: BattleSimulator::from_replay(replay, grid),
         })
     }
 
+    /// Replace simulator config - takes JSON (missing fields use defaults)
+    /// { ai_movement, retreat_disengage_distance, bounds, ... } - see BattleConfig
+    #[wasm_bindgen]
+    pub fn set_config(&mut self, config_json: &str) -> Result<(), BattleError> {
+        let config: BattleConfig = serde_json::from_str(config_json)
+            .map_err(BattleError::parse("config"))?;
+
+        self.simulator.set_config(config)
+            .map_err(BattleError::invalid("config"))
+    }
+
+    /// Set class-based target priorities: {"bomber": {"station": 200}, ...}
+    #[wasm_bindgen]
+    pub fn set_priority_table(&mut self, table_json: &str) -> Result<(), BattleError> {
+        let table: PriorityTable = serde_json::from_str(table_json)
+            .map_err(BattleError::parse("priority table"))?;
+
+        self.simulator.set_priority_table(table);
+        Ok(())
+    }
+
+    /// Switch spatial index - "grid" (default) or "octree" for sparse, very large battlefields
+    #[wasm_bindgen]
+    pub fn set_spatial_index_type(&mut self, type_name: &str) -> Result<(), BattleError> {
+        let index = AnySpatialIndex::from_name(type_name)
+            .ok_or_else(|| BattleError::InvalidArgument {
+                field: "spatial index type".to_string(),
+                message: format!("unknown type {}", type_name),
+            })?;
+
+        self.simulator.set_spatial_index(index);
+        Ok(())
+    }
+
+    /// Set log verbosity - "off", "error", "warn", "info" (default), "debug" or "trace"
+    /// The level is shared by every simulator in this WASM instance
+    #[wasm_bindgen]
+    pub fn set_log_level(&mut self, level: &str) -> Result<(), BattleError> {
+        let level = LogLevel::from_name(level)
+            .ok_or_else(|| BattleError::InvalidArgument {
+                field: "log level".to_string(),
+                message: format!("unknown level {}", level),
+            })?;
+
+        logging::set_log_level(level);
+        Ok(())
+    }
+
+    /// Replace faction alliances - takes JSON array of [a, b] pairs
+    /// Call right after construction or mid-battle (targets between changed pairs are cleared)
+    #[wasm_bindgen]
+    pub fn set_alliances(&mut self, pairs_json: &str) -> Result<(), BattleError> {
+        let pairs: Vec<(u32, u32)> = serde_json::from_str(pairs_json)
+            .map_err(BattleError::parse("alliances"))?;
+
+        self.simulator.set_alliances(&pairs);
+        Ok(())
+    }
+
+    /// Set a faction's balance - JSON FactionConfig, e.g. {"faction_id": 2,
+    /// "damage_multiplier": 1.1, "special_weapon_tags": ["PLASMA"]}; missing
+    /// multipliers are 1
+    #[wasm_bindgen]
+    pub fn set_faction_config(&mut self, config_json: &str) -> Result<(), BattleError> {
+        let config: FactionConfig = serde_json::from_str(config_json)
+            .map_err(BattleError::parse("faction config"))?;
+
+        self.simulator.set_faction_config(config);
+        Ok(())
+    }
+
+    /// Limit a ship class ("fighter", "destroyer", ...) to weapons whose tags
+    /// start with one of a JSON array of prefixes, e.g. ["LASER", "PR"] -
+    /// null lifts it. Weapons already carried that break it are disabled.
+    #[wasm_bindgen]
+    pub fn set_class_weapon_restrictions(&mut self, class: &str, allowed_tags_json: &str) -> Result<(), BattleError> {
+        let class: ShipClass = class.parse()
+            .map_err(BattleError::invalid("class"))?;
+        let allowed: Option<Vec<String>> = serde_json::from_str(allowed_tags_json)
+            .map_err(BattleError::parse("allowed tags"))?;
+
+        self.simulator.set_class_weapon_restrictions(class, allowed);
+        Ok(())
+    }
+
+    /// Weapons whose tag starts with `prefix` (case-insensitive, longest
+    /// prefix wins) are `category` - "point_defense", "siege", "kinetic",
+    /// "energy", "explosive", "tractor", "ion" or "emp". Re-categorizes the
+    /// weapons of units already in the battle.
+    #[wasm_bindgen]
+    pub fn register_weapon_tag_prefix(&mut self, prefix: &str, category: &str) -> Result<(), BattleError> {
+        if prefix.is_empty() {
+            return Err(BattleError::InvalidArgument {
+                field: "prefix".to_string(),
+                message: "must not be empty".to_string(),
+            });
+        }
+        let category: WeaponCategory = category.parse()
+            .map_err(BattleError::invalid("category"))?;
+
+        self.simulator.register_weapon_tag_prefix(prefix, category);
+        Ok(())
+    }
+
+    /// Registered weapon tag prefixes - JSON object of prefix -> category
+    #[wasm_bindgen]
+    pub fn get_weapon_tags(&self) -> Result<String, BattleError> {
+        let prefixes: std::collections::BTreeMap<&String, &WeaponCategory> = self.simulator.weapon_tags().prefixes.iter().collect();
+        serde_json::to_string(&prefixes)
+            .map_err(BattleError::encode("weapon tags"))
+    }
+
+    /// Set win conditions from a JSON array of [faction_id, objective] pairs,
+    /// e.g. [[1, "eliminate_all"], [2, {"survive_for": 1200}]] - the battle
+    /// ends when any of them is completed. [] goes back to elimination.
+    #[wasm_bindgen]
+    pub fn set_objectives(&mut self, objectives_json: &str) -> Result<(), BattleError> {
+        let objectives: Vec<(u32, BattleObjective)> = serde_json::from_str(objectives_json)
+            .map_err(BattleError::parse("objectives"))?;
+
+        self.simulator.set_objectives(objectives);
+        Ok(())
+    }
+
+    /// The objective or config.victory that decided the battle - JSON
+    /// { winner, condition, tick }, or null while neither has been met (always
+    /// null for plain elimination)
+    #[wasm_bindgen]
+    pub fn get_victory_state(&self) -> Result<String, BattleError> {
+        serde_json::to_string(&self.simulator.victory_state())
+            .map_err(BattleError::encode("victory state"))
+    }
+
+    /// Objectives completed so far - JSON array of [faction_id, objective]
+    #[wasm_bindgen]
+    pub fn get_completed_objectives(&self) -> Result<String, BattleError> {
+        serde_json::to_string(self.simulator.completed_objectives())
+            .map_err(BattleError::encode("objectives"))
+    }
+
+    /// Add a capturable resource node from JSON, e.g. {"id": 1, "pos_x": 0,
+    /// "pos_y": 0, "pos_z": 0, "control_radius": 500, "resource_per_tick": 1}
+    #[wasm_bindgen]
+    pub fn add_resource_node(&mut self, node_json: &str) -> Result<(), BattleError> {
+        let node: ResourceNode = serde_json::from_str(node_json)
+            .map_err(BattleError::parse("resource node"))?;
+
+        let id = node.id;
+        if !self.simulator.add_resource_node(node) {
+            return Err(BattleError::InvalidState(format!("resource node {} already exists", id)));
+        }
+        Ok(())
+    }
+
+    /// Remove a resource node - returns false if there was none with that id
+    #[wasm_bindgen]
+    pub fn remove_resource_node(&mut self, node_id: u32) -> bool {
+        self.simulator.remove_resource_node(node_id)
+    }
+
+    /// Get resource nodes and who controls them - returns JSON array
+    #[wasm_bindgen]
+    pub fn get_resource_nodes(&self) -> Result<String, BattleError> {
+        serde_json::to_string(self.simulator.resource_nodes())
+            .map_err(BattleError::encode("resource nodes"))
+    }
+
+    /// Get resources earned per faction - returns JSON object (faction id -> total)
+    #[wasm_bindgen]
+    pub fn get_faction_resources(&self) -> Result<String, BattleError> {
+        serde_json::to_string(self.simulator.faction_resources())
+            .map_err(BattleError::encode("faction resources"))
+    }
+
+    /// Mark two factions allied (or hostile again)
+    #[wasm_bindgen]
+    pub fn set_factions_allied(&mut self, faction_a: u32, faction_b: u32, allied: bool) {
+        self.simulator.set_factions_allied(faction_a, faction_b, allied);
+    }
+
+    /// Same as set_factions_allied
+    #[wasm_bindgen]
+    pub fn set_faction_alliance(&mut self, faction_a: u32, faction_b: u32, allied: bool) {
+        self.simulator.set_factions_allied(faction_a, faction_b, allied);
+    }
+
+    /// Check if two factions are allied (a faction is always allied with itself)
+    #[wasm_bindgen]
+    pub fn are_allies(&self, faction_a: u32, faction_b: u32) -> bool {
+        self.simulator.are_allies(faction_a, faction_b)
+    }
+
+    /// Configure ally separation steering for simulator-moved units
+    #[wasm_bindgen]
+    pub fn set_collision_avoidance(&mut self, enabled: bool, radius: f32, strength: f32) {
+        self.simulator.set_collision_avoidance(enabled, radius, strength);
+    }
+
     /// Simulate one tick - returns JSON
     #[wasm_bindgen]
-    pub fn simulate_tick(&mut self, dt: f32, current_time: f64) -> Result<String, JsValue> {
+    pub fn simulate_tick(&mut self, dt: f32, current_time: f64) -> Result<String, BattleError> {
         let result = self.simulator.simulate_tick(dt, current_time);
-        
-        serde_json::to_string(&result)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
+
+        let mut timer = PhaseTimer::start(self.simulator.config().profiling);
+        let json = serde_json::to_string(&result)
+            .map_err(BattleError::encode("result"));
+        self.simulator.lap_phase(&mut timer, Phase::Serialize);
+        self.simulator.recycle_result(result);
+        json
+    }
+
+    /// simulate_tick without units that barely moved or whose hp/shield didn't
+    /// change since the last delta tick - same JSON shape as simulate_tick
+    #[wasm_bindgen]
+    pub fn simulate_tick_delta(&mut self, dt: f32, current_time: f64) -> Result<String, BattleError> {
+        let DeltaTickResult(result) = self.simulator.simulate_tick_delta(dt, current_time);
+
+        let mut timer = PhaseTimer::start(self.simulator.config().profiling);
+        let json = serde_json::to_string(&result)
+            .map_err(BattleError::encode("result"));
+        self.simulator.lap_phase(&mut timer, Phase::Serialize);
+        self.simulator.recycle_result(result);
+        json
+    }
+
+    /// simulate_tick with a binary result (see binary.rs for the layout)
+    #[wasm_bindgen]
+    pub fn simulate_tick_bin(&mut self, dt: f32, current_time: f64) -> Result<Vec<u8>, BattleError> {
+        let result = self.simulator.simulate_tick(dt, current_time);
+
+        let mut timer = PhaseTimer::start(self.simulator.config().profiling);
+        let bytes = binary::encode(&result)
+            .map_err(BattleError::encode("result"));
+        self.simulator.lap_phase(&mut timer, Phase::Serialize);
+        self.simulator.recycle_result(result);
+        bytes
+    }
+
+    /// simulate_tick as a length-prefixed bincode frame - smallest payload, but
+    /// the reader needs the TickResult layout (see binary.rs)
+    #[wasm_bindgen]
+    pub fn simulate_tick_binary(&mut self, dt: f32, current_time: f64) -> Result<Vec<u8>, BattleError> {
+        let result = self.simulator.simulate_tick(dt, current_time);
+
+        let mut timer = PhaseTimer::start(self.simulator.config().profiling);
+        let bytes = binary::encode_frame(&result)
+            .map_err(BattleError::encode("result"));
+        self.simulator.lap_phase(&mut timer, Phase::Serialize);
+        self.simulator.recycle_result(result);
+        bytes
+    }
+
+    /// Simulate n ticks, the i-th at start_time + i * dt - returns a JSON array
+    /// of the tick results. n is capped at config.max_fast_forward_ticks
+    #[wasm_bindgen]
+    pub fn simulate_n_ticks(&mut self, n: u32, dt: f32, start_time: f64) -> Result<String, BattleError> {
+        let results = self.simulator.simulate_ticks(n, dt, start_time);
+
+        let mut timer = PhaseTimer::start(self.simulator.config().profiling);
+        let json = serde_json::to_string(&results)
+            .map_err(BattleError::encode("results"));
+        self.simulator.lap_phase(&mut timer, Phase::Serialize);
+        for result in results {
+            self.simulator.recycle_result(result);
+        }
+        json
+    }
+
+    /// simulate_n_ticks until the battle ends, at most max_ticks - returns the
+    /// JSON outcome (winner, reason, ticks, survivor count)
+    #[wasm_bindgen]
+    pub fn simulate_until_end(&mut self, max_ticks: u32, dt: f32, start_time: f64) -> Result<String, BattleError> {
+        let outcome = self.simulator.simulate_until_end(max_ticks, dt, start_time);
+        serde_json::to_string(&outcome)
+            .map_err(BattleError::encode("outcome"))
+    }
+
+    /// Simulate until the battle ends or max_ticks have run - returns a JSON
+    /// report (winner, ticks, reason, survivors, per-faction counts, weapon
+    /// totals by tag)
+    #[wasm_bindgen]
+    pub fn run_to_completion(&mut self, dt: f32, max_ticks: u32) -> Result<String, BattleError> {
+        let report = self.simulator.run_to_completion(dt, max_ticks);
+        serde_json::to_string(&report)
+            .map_err(BattleError::encode("report"))
+    }
+
+    /// Fire a specific weapon at a target, bypassing the targeting AI - returns
+    /// the WeaponFired JSON. Damage lands (and the shot is reported) next tick.
+    #[wasm_bindgen]
+    pub fn manually_fire_weapon(&mut self, attacker_id: u32, target_id: u32, weapon_tag: &str, current_time: f64) -> Result<String, BattleError> {
+        let Some(fired) = self.simulator.manually_fire(attacker_id, target_id, weapon_tag, current_time) else {
+            return Err(self.manual_fire_error(attacker_id, target_id, weapon_tag));
+        };
+        serde_json::to_string(&fired)
+            .map_err(BattleError::encode("shot"))
+    }
+
+    /// Pause the battle - simulate_tick returns empty results and the tick
+    /// counter stays put until resumed. Position updates still apply.
+    #[wasm_bindgen]
+    pub fn pause(&mut self) {
+        self.simulator.pause();
+    }
+
+    /// Resume a paused battle - picks up where it stopped, so weapons don't
+    /// come off cooldown for the time spent paused
+    #[wasm_bindgen]
+    pub fn resume(&mut self) {
+        self.simulator.resume();
+    }
+
+    /// Run exactly one tick of 1 / the configured tick_rate, even while paused -
+    /// returns the tick result JSON
+    #[wasm_bindgen]
+    pub fn step(&mut self) -> Result<String, BattleError> {
+        let result = self.simulator.step();
+
+        let mut timer = PhaseTimer::start(self.simulator.config().profiling);
+        let json = serde_json::to_string(&result)
+            .map_err(BattleError::encode("result"));
+        self.simulator.lap_phase(&mut timer, Phase::Serialize);
+        self.simulator.recycle_result(result);
+        json
+    }
+
+    /// Same as pause()
+    #[wasm_bindgen]
+    pub fn pause_battle(&mut self) {
+        self.simulator.pause();
+    }
+
+    /// Same as resume()
+    #[wasm_bindgen]
+    pub fn resume_battle(&mut self) {
+        self.simulator.resume();
+    }
+
+    /// Check if the battle is paused
+    #[wasm_bindgen]
+    pub fn is_paused(&self) -> bool {
+        self.simulator.is_paused()
     }
 
     /// Add unit mid-battle - takes JSON
     /// current_time should be Date.now() / 1000 (seconds since epoch)
     #[wasm_bindgen]
-    pub fn add_unit(&mut self, unit_json: &str, current_time: f64) -> Result<(), JsValue> {
+    pub fn add_unit(&mut self, unit_json: &str, current_time: f64) -> Result<(), BattleError> {
         let unit: BattleUnit = serde_json::from_str(unit_json)
-            .map_err(|e| JsValue::from_str(&format!("Failed to parse unit: {}", e)))?;
+            .map_err(BattleError::parse("unit"))?;
+
+        self.simulator.add_unit(unit, current_time)
+            .map_err(BattleError::from)
+    }
+
+    /// Add many units at once - takes JSON array, returns number added
+    /// The spatial grid is rebuilt once for the whole batch
+    #[wasm_bindgen]
+    pub fn add_units_batch(&mut self, units_json: &str, current_time: f64) -> Result<u32, BattleError> {
+        let units: Vec<BattleUnit> = serde_json::from_str(units_json)
+            .map_err(BattleError::parse("units"))?;
+
+        self.simulator.add_units(units, current_time)
+            .map_err(BattleError::from)
+    }
 
-        self.simulator.add_unit(unit, current_time);
+    /// Store a unit type - JSON { "template_id": "fighter", "base_unit": { ...unit... } }
+    /// Registering the same template_id again replaces it
+    #[wasm_bindgen]
+    pub fn register_template(&mut self, template_json: &str) -> Result<(), BattleError> {
+        let template: UnitTemplate = serde_json::from_str(template_json)
+            .map_err(BattleError::parse("template"))?;
+
+        self.simulator.register_template(template);
         Ok(())
     }
 
+    /// Add a copy of a registered template with its own id, faction and
+    /// position - no unit JSON to parse, for spawning many identical units
+    #[wasm_bindgen]
+    pub fn add_unit_from_template(&mut self, template_id: &str, id: u32, faction_id: u32, x: f32, y: f32, z: f32) -> Result<(), BattleError> {
+        self.simulator.add_unit_from_template(template_id, id, faction_id, x, y, z)
+            .map_err(BattleError::from)
+    }
+
+    /// Queue a JSON array of units to join at `tick` (a past tick means the next one)
+    /// They're listed in the tick result's `spawned` when they arrive
+    #[wasm_bindgen]
+    pub fn schedule_reinforcements(&mut self, tick: f64, units_json: &str) -> Result<(), BattleError> {
+        let units: Vec<BattleUnit> = serde_json::from_str(units_json)
+            .map_err(BattleError::parse("units"))?;
+
+        self.simulator.schedule_reinforcements(tick.max(0.0) as u64, units)
+            .map_err(BattleError::from)
+    }
+
+    /// Queue a JSON reinforcement wave, e.g. {"arrival_tick": 600, "units": [...],
+    /// "faction_id": 2, "spawn": [0, 0, 5000]} - faction_id and spawn are
+    /// optional. Arrivals are listed per faction in reinforcementsArrived
+    #[wasm_bindgen]
+    pub fn schedule_reinforcement_wave(&mut self, wave_json: &str) -> Result<(), BattleError> {
+        let wave: ReinforcementWave = serde_json::from_str(wave_json)
+            .map_err(BattleError::parse("reinforcement wave"))?;
+
+        self.simulator.schedule_wave(wave)
+            .map_err(BattleError::from)
+    }
+
+    /// Number of scheduled units that haven't arrived yet
+    #[wasm_bindgen]
+    pub fn get_pending_reinforcements(&self) -> u32 {
+        self.simulator.pending_reinforcements() as u32
+    }
+
+    /// Remove a unit (admin removal, not a combat death) - it counts as withdrawn
+    /// and shows up in the next tick's `withdrawn`
+    /// Returns false if no unit on the battlefield has this id
+    #[wasm_bindgen]
+    pub fn remove_unit(&mut self, unit_id: u32) -> bool {
+        self.simulator.remove_unit(unit_id)
+    }
+
+    /// Concede for a whole faction - its units are withdrawn (not destroyed)
+    /// and show up in the next tick's `withdrawn`; is_battle_ended() /
+    /// get_winner() reflect it right away
+    /// Returns false if the faction has no unit left in the battle
+    #[wasm_bindgen]
+    pub fn surrender_faction(&mut self, faction_id: u32) -> bool {
+        self.simulator.surrender_faction(faction_id)
+    }
+
     /// ✅ NEW: Update multiple unit positions from external source (player movement)
     /// Takes JSON array of PositionUpdate objects
     /// Returns number of units updated
     #[wasm_bindgen]
-    pub fn update_unit_positions(&mut self, positions_json: &str) -> Result<u32, JsValue> {
+    pub fn update_unit_positions(&mut self, positions_json: &str) -> Result<u32, BattleError> {
         let updates: Vec<PositionUpdate> = serde_json::from_str(positions_json)
-            .map_err(|e| JsValue::from_str(&format!("Failed to parse position updates: {}", e)))?;
+            .map_err(BattleError::parse("position updates"))?;
         
         let count = self.simulator.update_positions(&updates);
         
         if !updates.is_empty() {
-            log(&format!(
+            log_at!(Debug,
                 "[WASM] Updated {} unit positions from external source",
                 count
-            ));
+            );
         }
         
         Ok(count)
@@ -99,9 +741,16 @@ impl WasmBattleSimulator {
 
     /// ✅ NEW: Update a single unit's position
     /// Useful for real-time movement sync
+    /// Positions outside the configured bounds are clamped to the edge
     #[wasm_bindgen]
     pub fn update_single_unit_position(&mut self, unit_id: u32, x: f32, y: f32, z: f32, clear_target: bool) -> bool {
-        self.simulator.update_single_position(unit_id, x, y, z, clear_target)
+        self.simulator.update_single_position(unit_id, x, y, z, clear_target).is_some()
+    }
+
+    /// Ids of units outside the configured battlefield bounds (debug helper)
+    #[wasm_bindgen]
+    pub fn get_out_of_bounds_units(&self) -> Vec<u32> {
+        self.simulator.get_out_of_bounds_units()
     }
 
     /// ✅ NEW: Force all units to re-evaluate their targets
@@ -117,34 +766,441 @@ impl WasmBattleSimulator {
         self.simulator.force_retarget_unit(unit_id)
     }
 
+    /// Pin a unit's target so retargeting, position updates and force_retarget
+    /// leave it alone until the target dies
+    #[wasm_bindgen]
+    pub fn lock_target(&mut self, unit_id: u32, target_id: u32) -> bool {
+        self.simulator.lock_target(unit_id, target_id)
+    }
+
+    /// Launch a unit docked in a carrier's hangar - it appears next to the
+    /// carrier and is listed in the next tick's `launched`
+    #[wasm_bindgen]
+    pub fn launch_fighter(&mut self, carrier_id: u32, fighter_id: u32, current_time: f64) -> Result<(), BattleError> {
+        self.simulator.launch_fighter(carrier_id, fighter_id, current_time)
+            .map_err(|e| BattleError::InvalidState(format!("launch failed: {}", e)))
+    }
+
+    /// Dock a unit in a carrier of its faction - it can't act or be targeted
+    /// until launched (or the carrier dies)
+    #[wasm_bindgen]
+    pub fn dock_fighter(&mut self, carrier_id: u32, fighter_id: u32) -> Result<(), BattleError> {
+        self.simulator.dock_fighter(carrier_id, fighter_id)
+            .map_err(|e| BattleError::InvalidState(format!("dock failed: {}", e)))
+    }
+
+    /// Release a target pinned with lock_target
+    #[wasm_bindgen]
+    pub fn unlock_target(&mut self, unit_id: u32) -> bool {
+        self.simulator.unlock_target(unit_id)
+    }
+
+    /// Set patrol waypoints for a unit - takes JSON array of [x, y, z]
+    /// The simulator moves the unit between them whenever it has no target
+    #[wasm_bindgen]
+    pub fn set_unit_waypoints(&mut self, unit_id: u32, waypoints_json: &str) -> Result<(), BattleError> {
+        let waypoints: Vec<(f32, f32, f32)> = serde_json::from_str(waypoints_json)
+            .map_err(BattleError::parse("waypoints"))?;
+
+        if self.simulator.set_unit_waypoints(unit_id, waypoints) {
+            Ok(())
+        } else {
+            Err(BattleError::UnitNotFound(unit_id))
+        }
+    }
+
+    /// Order a unit to move to a point at its max_speed - the simulator moves
+    /// it each tick (reported in moved) until it arrives. An external position
+    /// update cancels the order. Returns false if unit not found
+    #[wasm_bindgen]
+    pub fn issue_move_order(&mut self, unit_id: u32, x: f32, y: f32, z: f32) -> bool {
+        self.simulator.issue_move_order(unit_id, x, y, z)
+    }
+
+    /// Order several units at once - takes JSON array of {id, x, y, z}
+    /// Returns number of units ordered
+    #[wasm_bindgen]
+    pub fn issue_move_orders(&mut self, orders_json: &str) -> Result<u32, BattleError> {
+        let orders: Vec<MoveOrder> = serde_json::from_str(orders_json)
+            .map_err(BattleError::parse("move orders"))?;
+
+        Ok(self.simulator.issue_move_orders(&orders))
+    }
+
+    /// Cancel a unit's move order and stop it - returns false if unit not found
+    #[wasm_bindgen]
+    pub fn clear_orders(&mut self, unit_id: u32) -> bool {
+        self.simulator.clear_orders(unit_id)
+    }
+
+    /// Put units in groups - takes JSON array of {unitId, groupId} (null
+    /// groupId takes the unit out of its group)
+    /// Returns number of units found
+    #[wasm_bindgen]
+    pub fn assign_group(&mut self, assignments_json: &str) -> Result<u32, BattleError> {
+        let assignments: Vec<GroupAssignment> = serde_json::from_str(assignments_json)
+            .map_err(BattleError::parse("group assignments"))?;
+
+        Ok(self.simulator.assign_groups(&assignments))
+    }
+
+    /// Give a group a focus target - members take it whenever it's in range
+    /// Returns number of members retargeted (0 if the target isn't valid)
+    #[wasm_bindgen]
+    pub fn set_group_target(&mut self, group_id: u32, target_id: u32) -> u32 {
+        self.simulator.set_group_target(group_id, target_id)
+    }
+
+    /// Set a group's stance: "aggressive", "defensive" or "hold_fire"
+    /// Returns number of members
+    #[wasm_bindgen]
+    pub fn set_group_stance(&mut self, group_id: u32, stance: &str) -> Result<u32, BattleError> {
+        let stance: Stance = stance.parse()
+            .map_err(BattleError::invalid("stance"))?;
+
+        Ok(self.simulator.set_group_stance(group_id, stance))
+    }
+
+    /// Disband a group - returns number of members it had
+    #[wasm_bindgen]
+    pub fn clear_group(&mut self, group_id: u32) -> u32 {
+        self.simulator.clear_group(group_id)
+    }
+
+    /// Clear a unit's waypoints - returns false if unit not found
+    #[wasm_bindgen]
+    pub fn clear_unit_waypoints(&mut self, unit_id: u32) -> bool {
+        self.simulator.clear_unit_waypoints(unit_id)
+    }
+
+    /// Toggle orbit movement - simulator-moved units circle their target
+    /// at optimal range instead of stopping
+    #[wasm_bindgen]
+    pub fn set_unit_orbit_mode(&mut self, unit_id: u32, enabled: bool) -> bool {
+        self.simulator.set_unit_orbit_mode(unit_id, enabled)
+    }
+
+    /// Split a unit's reactor power - fractions for shields, weapons and
+    /// engines, each 0-1 and summing to 1.0 (+-0.01). Each system runs at 3x
+    /// its share, so a third each is stock. ai_controlled units pick their
+    /// own split every tick
+    #[wasm_bindgen]
+    pub fn set_unit_power(&mut self, unit_id: u32, shield_frac: f32, weapon_frac: f32, engine_frac: f32) -> Result<(), BattleError> {
+        if self.simulator.set_unit_power(unit_id, shield_frac, weapon_frac, engine_frac)
+            .map_err(BattleError::invalid("power"))?
+        {
+            Ok(())
+        } else {
+            Err(BattleError::UnitNotFound(unit_id))
+        }
+    }
+
+    /// Blow a unit up at the start of the next tick - everything within its
+    /// self_destruct_radius takes self_destruct_damage, and charged units
+    /// caught in the blast go off too (config.self_destruct_chain_limit)
+    #[wasm_bindgen]
+    pub fn trigger_self_destruct(&mut self, unit_id: u32) -> Result<(), BattleError> {
+        if self.simulator.trigger_self_destruct(unit_id) {
+            return Ok(());
+        }
+        match self.simulator.get_unit(unit_id) {
+            Some(unit) if unit.in_battle() => Err(BattleError::InvalidArgument {
+                field: "unit_id".to_string(),
+                message: format!("unit {} has no self-destruct charge", unit_id),
+            }),
+            _ => Err(BattleError::UnitNotFound(unit_id)),
+        }
+    }
+
+    /// Set the hull fraction below which a unit retreats (0 = never)
+    #[wasm_bindgen]
+    pub fn set_retreat_threshold(&mut self, unit_id: u32, fraction: f32) -> bool {
+        self.simulator.set_retreat_threshold(unit_id, fraction)
+    }
+
+    /// Set the rally point a retreating unit flees towards
+    #[wasm_bindgen]
+    pub fn set_retreat_target(&mut self, unit_id: u32, x: f32, y: f32, z: f32) -> bool {
+        self.simulator.set_retreat_target(unit_id, x, y, z)
+    }
+
     /// Check if battle ended
     #[wasm_bindgen]
     pub fn is_battle_ended(&self) -> bool {
         self.simulator.is_battle_ended()
     }
 
-    /// Get active factions - returns JSON array
+    /// Current tick number (f64 - JS numbers are exact up to 2^53)
+    #[wasm_bindgen]
+    pub fn get_tick(&self) -> f64 {
+        self.simulator.tick() as f64
+    }
+
+    /// Ticks since anything fired at an enemy or took damage
+    #[wasm_bindgen]
+    pub fn get_ticks_since_combat(&self) -> f64 {
+        self.simulator.ticks_since_combat() as f64
+    }
+
+    /// Ticks without combat before the battle ends as a stalemate
+    #[wasm_bindgen]
+    pub fn get_stalemate_threshold(&self) -> f64 {
+        self.simulator.stalemate_threshold() as f64
+    }
+
+    /// Change the ticks without combat before the battle ends as a stalemate -
+    /// tick results carry stalemateWarning once half of it has passed
+    #[wasm_bindgen]
+    pub fn set_stalemate_threshold(&mut self, ticks: f64) {
+        self.simulator.set_stalemate_threshold(ticks.max(0.0) as u64);
+    }
+
+    /// Check if the battle has stalled (ends it - see is_battle_ended)
+    #[wasm_bindgen]
+    pub fn is_stalemate(&self) -> bool {
+        self.simulator.is_stalemate()
+    }
+
+    /// Combat log events from since_tick on, oldest first, at most max_entries -
+    /// returns JSON [{ tick, type, data }]
+    #[wasm_bindgen]
+    pub fn get_combat_log(&self, since_tick: f64, max_entries: u32) -> Result<String, BattleError> {
+        let events = self.simulator.combat_log().since(since_tick as u64, max_entries as usize);
+        serde_json::to_string(&events)
+            .map_err(BattleError::encode("combat log"))
+    }
+
+    /// Drop every recorded combat log event
+    #[wasm_bindgen]
+    pub fn clear_combat_log(&mut self) {
+        self.simulator.clear_combat_log();
+    }
+
+    /// Start recording a replay - only before the first tick of a deterministic
+    /// battle, returns false otherwise (config.record_replay starts it at
+    /// construction)
+    #[wasm_bindgen]
+    pub fn start_recording(&mut self) -> bool {
+        self.simulator.start_recording()
+    }
+
+    /// Stop recording (what was recorded can still be exported)
+    #[wasm_bindgen]
+    pub fn stop_recording(&mut self) {
+        self.simulator.stop_recording();
+    }
+
+    /// Recorded replay as bincode bytes (Uint8Array on the JS side)
+    #[wasm_bindgen]
+    pub fn export_replay(&self) -> Result<Vec<u8>, BattleError> {
+        self.simulator.export_replay()
+            .map_err(BattleError::encode("replay"))
+    }
+
+    /// Play the next recorded tick - returns TickResult JSON, or "null" when the replay is done
+    #[wasm_bindgen]
+    pub fn step_replay(&mut self) -> Result<String, BattleError> {
+        let Some(result) = self.simulator.step_replay() else {
+            return Ok("null".to_string());
+        };
+        let json = serde_json::to_string(&result)
+            .map_err(BattleError::encode("result"));
+        self.simulator.recycle_result(result);
+        json
+    }
+
+    /// Same as step_replay
+    #[wasm_bindgen]
+    pub fn replay_step(&mut self) -> Result<String, BattleError> {
+        self.step_replay()
+    }
+
+    /// Recorded ticks left to play back
+    #[wasm_bindgen]
+    pub fn replay_ticks_remaining(&self) -> u32 {
+        self.simulator.replay_ticks_remaining() as u32
+    }
+
+    /// Get ids of surrendered units - returns JSON array
+    #[wasm_bindgen]
+    pub fn get_surrendered_units(&self) -> Result<String, BattleError> {
+        serde_json::to_string(&self.simulator.get_surrendered_units())
+            .map_err(BattleError::encode("surrendered units"))
+    }
+
+    /// Get active factions (surrendered units don't count) - returns JSON array
     #[wasm_bindgen]
-    pub fn get_active_factions(&self) -> Result<String, JsValue> {
+    pub fn get_active_factions(&self) -> Result<String, BattleError> {
         let factions = self.simulator.get_active_factions();
         serde_json::to_string(&factions)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize factions: {}", e)))
+            .map_err(BattleError::encode("factions"))
     }
 
     /// Get battle results - returns JSON
     #[wasm_bindgen]
-    pub fn get_results(&self) -> Result<String, JsValue> {
+    pub fn get_results(&self) -> Result<String, BattleError> {
         let results = self.simulator.get_results();
         serde_json::to_string(&results)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize results: {}", e)))
+            .map_err(BattleError::encode("results"))
+    }
+
+    /// get_results as binary (see binary.rs for the layout)
+    #[wasm_bindgen]
+    pub fn get_results_bin(&self) -> Result<Vec<u8>, BattleError> {
+        binary::encode(self.simulator.get_units())
+            .map_err(BattleError::encode("results"))
+    }
+
+    /// Get one unit (alive or dead) - returns JSON
+    #[wasm_bindgen]
+    pub fn get_unit(&self, unit_id: u32) -> Result<String, BattleError> {
+        let unit = self.simulator.get_unit(unit_id)
+            .ok_or(BattleError::UnitNotFound(unit_id))?;
+
+        serde_json::to_string(unit)
+            .map_err(BattleError::encode("unit"))
+    }
+
+    /// Get all units of a faction (alive or dead) - returns JSON array
+    #[wasm_bindgen]
+    pub fn get_units_by_faction(&self, faction_id: u32) -> Result<String, BattleError> {
+        let units: Vec<&BattleUnit> = self.simulator.get_units_by_faction(faction_id).collect();
+        serde_json::to_string(&units)
+            .map_err(BattleError::encode("units"))
+    }
+
+    /// get_units_by_faction as binary (see binary.rs for the layout)
+    #[wasm_bindgen]
+    pub fn get_units_by_faction_bin(&self, faction_id: u32) -> Result<Vec<u8>, BattleError> {
+        let units: Vec<&BattleUnit> = self.simulator.get_units_by_faction(faction_id).collect();
+        binary::encode(&units)
+            .map_err(BattleError::encode("units"))
+    }
+
+    /// Combat stats for one unit - returns JSON
+    /// { damage_dealt, damage_taken, shots_fired, shots_hit, kills, accuracy }
+    #[wasm_bindgen]
+    pub fn get_unit_stats(&self, unit_id: u32) -> Result<String, BattleError> {
+        let stats = self.simulator.get_unit_stats(unit_id)
+            .ok_or(BattleError::UnitNotFound(unit_id))?;
+
+        serde_json::to_string(&stats)
+            .map_err(BattleError::encode("stats"))
+    }
+
+    /// Veterancy for one unit - returns JSON { level, experience, next_level_xp }
+    /// (next_level_xp is null at the top level)
+    #[wasm_bindgen]
+    pub fn get_unit_veterancy(&self, unit_id: u32) -> Result<String, BattleError> {
+        let veterancy = self.simulator.get_unit_veterancy(unit_id)
+            .ok_or(BattleError::UnitNotFound(unit_id))?;
+
+        serde_json::to_string(&veterancy)
+            .map_err(BattleError::encode("veterancy"))
+    }
+
+    /// Ammo for each of a unit's weapons - returns JSON array
+    /// [{ tag, ammo, magazine_size, ammo_remaining, ammo_per_shot, ammo_capacity }]
+    /// (ammo null = unlimited magazine, ammo_per_shot 0 = unlimited supply)
+    #[wasm_bindgen]
+    pub fn get_weapon_ammo(&self, unit_id: u32) -> Result<String, BattleError> {
+        let ammo = self.simulator.get_weapon_ammo(unit_id)
+            .ok_or(BattleError::UnitNotFound(unit_id))?;
+
+        serde_json::to_string(&ammo)
+            .map_err(BattleError::encode("ammo"))
+    }
+
+    /// Combat stats summed over a faction's units (alive or dead) - same JSON
+    /// shape as get_unit_stats
+    #[wasm_bindgen]
+    pub fn get_faction_stats(&self, faction_id: u32) -> Result<String, BattleError> {
+        let stats = self.simulator.get_faction_stats(faction_id);
+        serde_json::to_string(&stats)
+            .map_err(BattleError::encode("stats"))
+    }
+
+    /// Per-weapon stats - returns JSON keyed by unit id then weapon tag:
+    /// { "12": { "LASER": { shots_fired, shots_hit, damage_dealt, kills } } }
+    /// Units that never fired are left out; weapons sharing a tag are summed.
+    #[wasm_bindgen]
+    pub fn get_weapon_stats(&self) -> Result<String, BattleError> {
+        serde_json::to_string(&self.simulator.get_weapon_stats())
+            .map_err(BattleError::encode("weapon stats"))
+    }
+
+    /// Units within radius of a point (boundary included), nearest first - returns JSON
+    /// [{ id, faction_id, hp, shield, pos_x, pos_y, pos_z, distance }]
+    #[wasm_bindgen]
+    pub fn query_units_in_radius(
+        &self,
+        x: f32,
+        y: f32,
+        z: f32,
+        radius: f32,
+        faction_filter: Option<u32>,
+    ) -> Result<String, BattleError> {
+        let found = self.simulator.query_units_in_radius(x, y, z, radius, faction_filter);
+        serde_json::to_string(&found)
+            .map_err(BattleError::encode("units"))
+    }
+
+    /// Spatial grid diagnostics - returns JSON { cell_count, total_entries,
+    /// min_per_cell, avg_per_cell, max_per_cell, cell_size, max_query_range,
+    /// units_beyond_query_range }. Errors when the octree index is in use.
+    #[wasm_bindgen]
+    pub fn get_grid_stats(&self) -> Result<String, BattleError> {
+        let stats = self.simulator.grid_stats()
+            .ok_or_else(|| BattleError::InvalidState("grid stats need the grid spatial index".to_string()))?;
+        serde_json::to_string(&stats)
+            .map_err(BattleError::encode("grid stats"))
+    }
+
+    /// Unit ids the spatial index returns for a neighbour query, sorted - returns JSON [id, ...]
+    /// Unfiltered grid candidates: compare against a brute-force distance check
+    #[wasm_bindgen]
+    pub fn debug_query_nearby(&self, x: f32, y: f32, z: f32, range: f32) -> Result<String, BattleError> {
+        serde_json::to_string(&self.simulator.debug_query_nearby(x, y, z, range))
+            .map_err(BattleError::encode("unit ids"))
+    }
+
+    /// ASCII top-down map of the X/Z plane, centred on the origin - armed
+    /// ships as their faction letter (A-Z), unarmed lowercase, stations S,
+    /// counts where units share a cell. scale is world units per character
+    /// (0 = the set_debug_map_scale default). Debug builds only.
+    #[cfg(debug_assertions)]
+    #[wasm_bindgen]
+    pub fn render_debug_map(&self, scale: f32) -> Result<String, BattleError> {
+        let scale = if scale == 0.0 { self.simulator.debug_map_scale() } else { scale };
+        if !(scale > 0.0 && scale.is_finite()) {
+            return Err(BattleError::InvalidArgument {
+                field: "scale".to_string(),
+                message: format!("must be positive, got {}", scale),
+            });
+        }
+        Ok(self.simulator.render_ascii(debug_map::DEBUG_MAP_WIDTH, debug_map::DEBUG_MAP_HEIGHT, scale))
+    }
+
+    /// Default world units per character for render_debug_map. Debug builds only.
+    #[cfg(debug_assertions)]
+    #[wasm_bindgen]
+    pub fn set_debug_map_scale(&mut self, units_per_cell: f32) -> Result<(), BattleError> {
+        if !(units_per_cell > 0.0 && units_per_cell.is_finite()) {
+            return Err(BattleError::InvalidArgument {
+                field: "scale".to_string(),
+                message: format!("must be positive, got {}", units_per_cell),
+            });
+        }
+        self.simulator.set_debug_map_scale(units_per_cell);
+        Ok(())
     }
 
     /// ✅ NEW: Get current unit positions - useful for debugging
     #[wasm_bindgen]
-    pub fn get_unit_positions(&self) -> Result<String, JsValue> {
+    pub fn get_unit_positions(&self) -> Result<String, BattleError> {
         let positions: Vec<PositionUpdate> = self.simulator.get_units()
             .iter()
-            .filter(|u| u.alive)
+            .filter(|u| u.is_alive())
             .map(|u| PositionUpdate {
                 id: u.id,
                 x: u.pos_x,
@@ -155,7 +1211,7 @@ impl WasmBattleSimulator {
             .collect();
         
         serde_json::to_string(&positions)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize positions: {}", e)))
+            .map_err(BattleError::encode("positions"))
     }
 
     // =========================================================================
@@ -178,9 +1234,158 @@ impl WasmBattleSimulator {
     /// Get detailed idle info - returns JSON
     /// { isIdle, ticksSinceMovement, nextWeaponReadyTime, idleTickCount }
     #[wasm_bindgen]
-    pub fn get_idle_info(&self, current_time: f64) -> Result<String, JsValue> {
+    pub fn get_idle_info(&self, current_time: f64) -> Result<String, BattleError> {
         let info = self.simulator.get_idle_info(current_time);
         serde_json::to_string(&info)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize idle info: {}", e)))
+            .map_err(BattleError::encode("idle info"))
+    }
+
+    /// Per-phase tick timing while config.profiling is on - returns JSON
+    /// { ticks, window, phases: [{ phase, total_ms, samples, avg_ms, max_ms }] }
+    /// with averages and maxima over the last profile_window ticks
+    #[wasm_bindgen]
+    pub fn get_profile_stats(&self) -> Result<String, BattleError> {
+        serde_json::to_string(&self.simulator.profile_stats())
+            .map_err(BattleError::encode("profile stats"))
+    }
+
+    /// Clear profiling totals and samples
+    #[wasm_bindgen]
+    pub fn reset_profile_stats(&mut self) {
+        self.simulator.reset_profile();
+    }
+
+    /// Mean compute time of the last 20 ticks in microseconds (profiling or
+    /// not) - each tick's own is TickResult.tickDurationUs
+    #[wasm_bindgen]
+    pub fn get_average_tick_duration_us(&self) -> u32 {
+        self.simulator.average_tick_duration_us()
+    }
+
+    /// Save the battle state - returns snapshot JSON for restore_battle (a
+    /// copy of every unit, so not something to call every tick in a big battle)
+    #[wasm_bindgen]
+    pub fn snapshot_battle(&self) -> Result<String, BattleError> {
+        serde_json::to_string(&self.simulator.snapshot())
+            .map_err(BattleError::encode("snapshot"))
     }
-}
\ No newline at end of file
+
+    /// Roll the battle back to a snapshot_battle() / get_initial_snapshot()
+    /// JSON, then re-simulate from its time to catch up
+    #[wasm_bindgen]
+    pub fn restore_battle(&mut self, snapshot_json: &str) -> Result<(), BattleError> {
+        let snapshot: BattleSnapshot = serde_json::from_str(snapshot_json)
+            .map_err(BattleError::parse("snapshot"))?;
+        if let Some(problem) = validation::validate_units(&snapshot.units, []).into_iter().next() {
+            return Err(problem.into());
+        }
+        self.simulator.restore(snapshot);
+        Ok(())
+    }
+
+    /// The battle as it was constructed (tick 0) - snapshot JSON
+    #[wasm_bindgen]
+    pub fn get_initial_snapshot(&self) -> Result<String, BattleError> {
+        serde_json::to_string(self.simulator.initial_snapshot())
+            .map_err(BattleError::encode("snapshot"))
+    }
+}
+
+/// WASM-exported scenario builder - JSON in, like the simulator methods
+///
+/// const builder = new WasmSimulationBuilder();
+/// builder.add_faction_units(1, fleetJson);
+/// builder.set_objective(1, '{ "kill_unit": 900 }');
+/// const sim = builder.build(Date.now() / 1000);
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmSimulationBuilder {
+    builder: SimulationBuilder,
+}
+
+impl WasmSimulationBuilder {
+    /// Apply one chained SimulationBuilder call in place
+    fn update(&mut self, f: impl FnOnce(SimulationBuilder) -> SimulationBuilder) {
+        self.builder = f(std::mem::take(&mut self.builder));
+    }
+}
+
+#[wasm_bindgen]
+impl WasmSimulationBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmSimulationBuilder {
+        WasmSimulationBuilder::default()
+    }
+
+    /// Add one unit - takes a unit JSON object
+    #[wasm_bindgen]
+    pub fn add_unit(&mut self, unit_json: &str) -> Result<(), BattleError> {
+        let unit: BattleUnit = serde_json::from_str(unit_json)
+            .map_err(BattleError::parse("unit"))?;
+        self.update(|b| b.add_unit(unit));
+        Ok(())
+    }
+
+    /// Add a JSON array of units fighting for faction_id
+    #[wasm_bindgen]
+    pub fn add_faction_units(&mut self, faction_id: u32, units_json: &str) -> Result<(), BattleError> {
+        let units: Vec<BattleUnit> = serde_json::from_str(units_json)
+            .map_err(BattleError::parse("units"))?;
+        self.update(|b| b.add_faction_units(faction_id, units));
+        Ok(())
+    }
+
+    /// Add a win condition for a faction - see BattleObjective for the JSON
+    #[wasm_bindgen]
+    pub fn set_objective(&mut self, faction_id: u32, objective_json: &str) -> Result<(), BattleError> {
+        let objective: BattleObjective = serde_json::from_str(objective_json)
+            .map_err(BattleError::parse("objective"))?;
+        self.update(|b| b.set_objective(faction_id, objective));
+        Ok(())
+    }
+
+    /// { arrival_tick, units, faction_id?, spawn? } - see schedule_reinforcement_wave
+    #[wasm_bindgen]
+    pub fn add_reinforcement_wave(&mut self, wave_json: &str) -> Result<(), BattleError> {
+        let wave: ReinforcementWave = serde_json::from_str(wave_json)
+            .map_err(BattleError::parse("reinforcement wave"))?;
+        self.update(|b| b.add_reinforcement_wave(wave));
+        Ok(())
+    }
+
+    /// { id, pos_x, pos_y, pos_z, control_radius, resource_per_tick }
+    #[wasm_bindgen]
+    pub fn add_resource_node(&mut self, node_json: &str) -> Result<(), BattleError> {
+        let node: ResourceNode = serde_json::from_str(node_json)
+            .map_err(BattleError::parse("resource node"))?;
+        self.update(|b| b.add_resource_node(node));
+        Ok(())
+    }
+
+    /// Replace the config - JSON, missing fields use defaults
+    #[wasm_bindgen]
+    pub fn with_config(&mut self, config_json: &str) -> Result<(), BattleError> {
+        let config: BattleConfig = serde_json::from_str(config_json)
+            .map_err(BattleError::parse("config"))?;
+        config.validate()
+            .map_err(BattleError::invalid("config"))?;
+        self.update(|b| b.with_config(config));
+        Ok(())
+    }
+
+    /// Deterministic random rolls from seed (a BigInt in JS)
+    #[wasm_bindgen]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.update(|b| b.set_seed(seed));
+    }
+
+    /// Validate and build the simulator - current_time is Date.now() / 1000.
+    /// The builder is left empty either way.
+    #[wasm_bindgen]
+    pub fn build(&mut self, current_time: f64) -> Result<WasmBattleSimulator, BattleError> {
+        let builder = std::mem::take(&mut self.builder).start_time(current_time);
+        let grid = AnySpatialIndex::Grid(builder.config().spatial_grid());
+        let simulator = builder.build_with_index(grid)?;
+        Ok(WasmBattleSimulator { simulator })
+    }
+}
@@ -0,0 +1,154 @@
+// battle-core/src/logging.rs
+//
+// Crate-wide log level. Every log line goes through log_at!, which checks the
+// level before formatting - lines below the threshold cost one atomic load.
+//
+// The level is global (shared by every simulator in the process), so it can be
+// read from free functions like try_fire_weapon without threading state.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Log verbosity, lowest to highest
+///
+/// JSON: "off" / "error" / "warn" / "info" / "debug" / "trace"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum LogLevel {
+    #[serde(alias = "none")]
+    Off = 0,
+    Error = 1,
+    /// Something the host should look at that didn't stop the battle (slow ticks)
+    #[serde(alias = "warning")]
+    Warn = 2,
+    /// Battle events: target changes, destroys, retreats, idle transitions
+    Info = 3,
+    /// Per-shot and periodic per-tick summaries
+    Debug = 4,
+    /// Per-weapon detail (cooldown / range misses, falloff, armor)
+    Trace = 5,
+}
+
+impl LogLevel {
+    /// Parse "off" / "error" / "warn" / "info" / "debug" / "trace" (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" | "none" => Some(LogLevel::Off),
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LogLevel::Off,
+            1 => LogLevel::Error,
+            2 => LogLevel::Warn,
+            3 => LogLevel::Info,
+            4 => LogLevel::Debug,
+            _ => LogLevel::Trace,
+        }
+    }
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Lines passed to the native log() sink (tests only - there's no console natively)
+#[cfg(test)]
+pub(crate) static LINES_LOGGED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Set the crate-wide log level
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Current crate-wide log level (Info by default)
+pub fn log_level() -> LogLevel {
+    LogLevel::from_u8(LOG_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Check if lines at this level are logged
+#[inline]
+pub fn enabled(level: LogLevel) -> bool {
+    level != LogLevel::Off && level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Log a formatted line at a level - the format! only runs when the level is enabled
+///
+/// `log_at!(Debug, "[Weapon] Unit {} fired", id)`
+#[macro_export]
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::$level) {
+            $crate::log(&format!($($arg)*));
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::{BattleUnit, Weapon};
+    use crate::simulator::BattleSimulator;
+    use std::fmt;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Display impl that counts how often it gets formatted
+    struct Counted<'a>(&'a AtomicUsize);
+
+    impl fmt::Display for Counted<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            write!(f, "x")
+        }
+    }
+
+    #[test]
+    fn test_level_names() {
+        assert_eq!(LogLevel::from_name("TRACE"), Some(LogLevel::Trace));
+        assert_eq!(LogLevel::from_name("off"), Some(LogLevel::Off));
+        assert_eq!(LogLevel::from_name("Warning"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::from_name("verbose"), None);
+    }
+
+    #[test]
+    fn test_off_builds_no_strings() {
+        let formatted = AtomicUsize::new(0);
+
+        set_log_level(LogLevel::Off);
+        let before = LINES_LOGGED.load(Ordering::Relaxed);
+
+        log_at!(Error, "{}", Counted(&formatted));
+        let units: Vec<BattleUnit> = (0..20)
+            .map(|i| BattleUnit::builder()
+                .id(i + 1)
+                .faction(i % 2 + 1)
+                .position((i % 2) as f32 * 50.0, i as f32, 0.0)
+                .as_ship()
+                .weapons(vec![Weapon::builder().tag("LASER").range(50.0, 100.0).build()])
+                .build()
+                .unwrap())
+            .collect();
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let mut shots = 0;
+        for i in 0..100 {
+            shots += sim.simulate_tick(0.05, 1000.0 + i as f64 * 0.05).weapons_fired.len();
+        }
+
+        let lines = LINES_LOGGED.load(Ordering::Relaxed) - before;
+        set_log_level(LogLevel::Info);
+
+        assert!(shots > 0);
+        assert_eq!(formatted.load(Ordering::Relaxed), 0);
+        assert_eq!(lines, 0);
+
+        // Sanity check the sink itself
+        log_at!(Info, "{}", Counted(&formatted));
+        assert_eq!(formatted.load(Ordering::Relaxed), 1);
+    }
+}
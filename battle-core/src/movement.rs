@@ -1,24 +1,94 @@
 use crate::battle_unit::BattleUnit;
+use crate::spatial_index::SpatialIndex;
+use crate::weapons::WeaponCategory;
+
+/// Distance at which a waypoint counts as reached
+pub const WAYPOINT_ARRIVAL_RADIUS: f32 = 5.0;
+/// Distance at which a move order counts as done
+pub const MOVE_ORDER_EPSILON: f32 = 0.01;
+
+/// Preferred engagement distance - optimal range of the longest-range
+/// offensive weapon (point defense can't shoot ships, so it's ignored)
+pub fn engagement_range(unit: &BattleUnit) -> f32 {
+    unit.weapons.iter()
+        .filter(|w| w.category != WeaponCategory::PointDefense)
+        .max_by(|a, b| a.max_range.total_cmp(&b.max_range))
+        .map(|w| w.optimal_range)
+        .unwrap_or(0.0)
+}
+
+/// Repulsion velocity pushing a unit away from allies closer than `radius`
+///
+/// Each ally contributes up to `strength * max_speed`, scaled linearly by
+/// how deep inside the radius it is. Exactly stacked allies are ignored
+/// (no direction to push in) - collision separation handles those.
+/// `nearby` is scratch space for the grid query.
+pub fn separation_force(
+    unit: &BattleUnit,
+    all_units: &[BattleUnit],
+    grid: &impl SpatialIndex,
+    radius: f32,
+    strength: f32,
+    nearby: &mut Vec<usize>,
+) -> (f32, f32, f32) {
+    let mut force = (0.0, 0.0, 0.0);
+    if radius <= 0.0 || strength <= 0.0 {
+        return force;
+    }
+
+    grid.get_nearby_into(unit.pos_x, unit.pos_y, unit.pos_z, radius, nearby);
+    for &idx in nearby.iter() {
+        let ally = &all_units[idx];
+        if ally.id == unit.id || ally.faction_id != unit.faction_id || !ally.in_battle() {
+            continue;
+        }
+
+        let dx = unit.pos_x - ally.pos_x;
+        let dy = unit.pos_y - ally.pos_y;
+        let dz = unit.pos_z - ally.pos_z;
+        let dist = (dx * dx + dy * dy + dz * dz).sqrt();
+        if dist <= 1e-4 || dist >= radius {
+            continue;
+        }
+
+        let push = (radius - dist) / radius * strength * unit.effective_max_speed() / dist;
+        force.0 += dx * push;
+        force.1 += dy * push;
+        force.2 += dz * push;
+    }
+
+    force
+}
 
 /// Update unit movement based on target
+///
+/// A move order comes first (see follow_move_order); with no target, units
+/// follow their waypoints (if any) or stand still.
+/// `separation` (see separation_force) is added to the desired velocity and
+/// the result clamped to max_speed (after engine power and slows).
 pub fn update_movement(
     unit: &mut BattleUnit,
     target: Option<&BattleUnit>,
+    separation: (f32, f32, f32),
     dt: f32,
 ) {
-    if !unit.alive {
+    if !unit.is_alive() {
         return;
     }
 
-    if let Some(target) = target {
+    if unit.move_order.is_some() {
+        follow_move_order(unit, dt);
+    } else if let Some(target) = target {
         let dist = unit.distance(target);
-        let optimal_range = if !unit.weapons.is_empty() {
-            unit.weapons[0].optimal_range
-        } else {
-            0.0
-        };
+        let optimal_range = engagement_range(unit);
 
-        if dist > optimal_range {
+        if unit.orbit_mode && optimal_range > 0.0 && dist <= unit.max_weapon_range {
+            // Circle the target at optimal range while firing
+            unit.orbit_angle += (unit.effective_max_speed() / optimal_range) * dt;
+            let orbit_x = target.pos_x + unit.orbit_angle.cos() * optimal_range;
+            let orbit_z = target.pos_z + unit.orbit_angle.sin() * optimal_range;
+            unit.move_towards(orbit_x, target.pos_y, orbit_z);
+        } else if dist > optimal_range {
             // Move towards target
             unit.move_towards(target.pos_x, target.pos_y, target.pos_z);
         } else if dist < optimal_range * 0.8 {
@@ -28,7 +98,7 @@ pub fn update_movement(
             let dz = unit.pos_z - target.pos_z;
             let dist = (dx * dx + dy * dy + dz * dz).sqrt();
             if dist > 0.0 {
-                let factor = unit.max_speed / dist;
+                let factor = unit.effective_max_speed() / dist;
                 unit.vel_x = dx * factor;
                 unit.vel_y = dy * factor;
                 unit.vel_z = dz * factor;
@@ -37,8 +107,252 @@ pub fn update_movement(
             // At optimal range, stop
             unit.stop();
         }
+    } else if !unit.waypoints.is_empty() {
+        follow_waypoints(unit);
+    } else {
+        unit.stop();
+    }
+
+    if separation != (0.0, 0.0, 0.0) {
+        unit.vel_x += separation.0;
+        unit.vel_y += separation.1;
+        unit.vel_z += separation.2;
+
+        let speed = (unit.vel_x * unit.vel_x + unit.vel_y * unit.vel_y + unit.vel_z * unit.vel_z).sqrt();
+        let max_speed = unit.effective_max_speed();
+        if speed > max_speed {
+            let factor = max_speed / speed;
+            unit.vel_x *= factor;
+            unit.vel_y *= factor;
+            unit.vel_z *= factor;
+        }
     }
 
     // Update position
     unit.update_position(dt);
+
+    if let Some((x, y, z)) = unit.move_order {
+        let (dx, dy, dz) = (x - unit.pos_x, y - unit.pos_y, z - unit.pos_z);
+        if dx * dx + dy * dy + dz * dz <= MOVE_ORDER_EPSILON * MOVE_ORDER_EPSILON {
+            unit.move_order = None;
+        }
+    }
+}
+
+/// Move a retreating unit towards its rally point, or directly away from
+/// the nearest enemy if it has none
+pub fn update_retreat(unit: &mut BattleUnit, nearest_enemy: (f32, f32, f32), dt: f32) {
+    if !unit.is_alive() {
+        return;
+    }
+
+    match unit.retreat_target {
+        Some((x, y, z)) => {
+            let dx = x - unit.pos_x;
+            let dy = y - unit.pos_y;
+            let dz = z - unit.pos_z;
+            if dx * dx + dy * dy + dz * dz <= WAYPOINT_ARRIVAL_RADIUS * WAYPOINT_ARRIVAL_RADIUS {
+                unit.stop();
+            } else {
+                unit.move_towards(x, y, z);
+            }
+        }
+        None => {
+            let (x, y, z) = nearest_enemy;
+            unit.move_away(x, y, z);
+        }
+    }
+
+    unit.update_position(dt);
+}
+
+/// Head for the ordered destination at max_speed, slowing on the last step
+/// so it lands on the point rather than past it
+fn follow_move_order(unit: &mut BattleUnit, dt: f32) {
+    let Some((x, y, z)) = unit.move_order else {
+        return;
+    };
+    let (dx, dy, dz) = (x - unit.pos_x, y - unit.pos_y, z - unit.pos_z);
+    let dist = (dx * dx + dy * dy + dz * dz).sqrt();
+    if dist <= MOVE_ORDER_EPSILON {
+        unit.stop();
+        return;
+    }
+
+    unit.move_towards(x, y, z);
+    let step = unit.effective_max_speed() * dt;
+    if step > dist && dt > 0.0 {
+        unit.vel_x = dx / dt;
+        unit.vel_y = dy / dt;
+        unit.vel_z = dz / dt;
+    }
+}
+
+/// Steer towards the current waypoint, advancing (and wrapping) on arrival
+fn follow_waypoints(unit: &mut BattleUnit) {
+    let len = unit.waypoints.len();
+    let mut idx = unit.current_waypoint % len;
+
+    if waypoint_dist_sq(unit, idx) <= WAYPOINT_ARRIVAL_RADIUS * WAYPOINT_ARRIVAL_RADIUS {
+        idx = (idx + 1) % len;
+    }
+    unit.current_waypoint = idx;
+
+    // Single waypoint reached - hold position
+    if waypoint_dist_sq(unit, idx) <= WAYPOINT_ARRIVAL_RADIUS * WAYPOINT_ARRIVAL_RADIUS {
+        unit.stop();
+        return;
+    }
+
+    let (x, y, z) = unit.waypoints[idx];
+    unit.move_towards(x, y, z);
+}
+
+#[inline]
+fn waypoint_dist_sq(unit: &BattleUnit, idx: usize) -> f32 {
+    let (x, y, z) = unit.waypoints[idx];
+    let dx = x - unit.pos_x;
+    let dy = y - unit.pos_y;
+    let dz = z - unit.pos_z;
+    dx * dx + dy * dy + dz * dz
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::Weapon;
+    use crate::spatial_grid::SpatialGrid;
+
+    const NO_SEPARATION: (f32, f32, f32) = (0.0, 0.0, 0.0);
+
+    fn make_patrol(waypoints: Vec<(f32, f32, f32)>) -> BattleUnit {
+        BattleUnit {
+            waypoints,
+            ..BattleUnit::builder().id(1).faction(1).max_speed(10.0).build().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_engagement_range_uses_longest_offensive_weapon() {
+        let unit = BattleUnit::builder()
+            .id(1)
+            .faction(1)
+            .weapons(vec![
+                Weapon::builder().tag("1").range(300.0, 400.0).as_point_defense().build(),
+                Weapon::builder().tag("LASER").range(50.0, 80.0).build(),
+                Weapon::builder().tag("RAIL").range(150.0, 200.0).build(),
+            ])
+            .build()
+            .unwrap();
+
+        assert_eq!(engagement_range(&unit), 150.0);
+    }
+
+    #[test]
+    fn test_no_target_no_waypoints_stops() {
+        let mut unit = make_patrol(Vec::new());
+        unit.vel_x = 10.0;
+
+        update_movement(&mut unit, None, NO_SEPARATION, 0.1);
+
+        assert_eq!(unit.vel_x, 0.0);
+        assert_eq!(unit.pos_x, 0.0);
+    }
+
+    #[test]
+    fn test_retreat_prefers_rally_point() {
+        let mut unit = make_patrol(Vec::new());
+        unit.retreat_target = Some((0.0, 0.0, 100.0));
+
+        update_retreat(&mut unit, (-50.0, 0.0, 0.0), 0.1);
+        assert!(unit.vel_z > 0.0);
+        assert_eq!(unit.vel_x, 0.0);
+
+        unit.retreat_target = None;
+        update_retreat(&mut unit, (-50.0, 0.0, 0.0), 0.1);
+        assert!(unit.vel_x > 0.0);
+    }
+
+    #[test]
+    fn test_orbit_mode_circles_target() {
+        let mut unit = BattleUnit {
+            orbit_mode: true,
+            ..BattleUnit::builder()
+                .id(1)
+                .faction(1)
+                .max_speed(10.0)
+                .position(50.0, 0.0, 0.0)
+                .weapons(vec![Weapon::builder().tag("LASER").range(50.0, 80.0).build()])
+                .build()
+                .unwrap()
+        };
+        let target = BattleUnit::builder().id(2).faction(2).build().unwrap();
+
+        // Without orbit the unit would sit still at optimal range
+        for _ in 0..100 {
+            update_movement(&mut unit, Some(&target), NO_SEPARATION, 0.1);
+            let dist = unit.distance(&target);
+            assert!((45.0..=55.0).contains(&dist), "drifted to {}", dist);
+        }
+
+        assert!((unit.orbit_angle - 2.0).abs() < 1e-3);
+        assert!(unit.pos_z.abs() > 1.0);
+    }
+
+    #[test]
+    fn test_separation_pushes_allies_apart() {
+        let unit = make_patrol(Vec::new());
+        let ally = BattleUnit::builder().id(2).faction(1).position(4.0, 0.0, 0.0).build().unwrap();
+        let enemy = BattleUnit::builder().id(3).faction(2).position(-4.0, 0.0, 0.0).build().unwrap();
+        let units = vec![unit.clone(), ally, enemy];
+        let mut grid = SpatialGrid::new(100.0);
+        for (idx, u) in units.iter().enumerate() {
+            grid.insert(idx, u.pos_x, u.pos_y, u.pos_z);
+        }
+
+        // Only the ally repels: (10 - 4) / 10 * 2.0 * 10 speed = 12 along -x
+        let force = separation_force(&unit, &units, &grid, 10.0, 2.0, &mut Vec::new());
+        assert!((force.0 + 12.0).abs() < 1e-4);
+
+        // Combined velocity is clamped to max_speed
+        let mut unit = unit;
+        update_movement(&mut unit, None, force, 0.1);
+        assert!((unit.vel_x + 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_waypoint_advancement() {
+        let mut unit = make_patrol(vec![(20.0, 0.0, 0.0), (20.0, 0.0, 20.0)]);
+
+        // 10 units/sec - reaches first waypoint radius after ~1.5s
+        for _ in 0..20 {
+            update_movement(&mut unit, None, NO_SEPARATION, 0.1);
+        }
+
+        assert_eq!(unit.current_waypoint, 1);
+        assert!(unit.vel_z > 0.0);
+    }
+
+    #[test]
+    fn test_waypoint_patrol_wraps() {
+        let mut unit = make_patrol(vec![(10.0, 0.0, 0.0), (0.0, 0.0, 0.0)]);
+        unit.current_waypoint = 1;
+
+        // Already at the last waypoint - wraps back to the first
+        update_movement(&mut unit, None, NO_SEPARATION, 0.1);
+
+        assert_eq!(unit.current_waypoint, 0);
+        assert!(unit.pos_x > 0.0);
+    }
+
+    #[test]
+    fn test_target_overrides_waypoints() {
+        let mut unit = make_patrol(vec![(100.0, 0.0, 0.0)]);
+        let target = BattleUnit::builder().id(2).faction(2).position(-100.0, 0.0, 0.0).build().unwrap();
+
+        update_movement(&mut unit, Some(&target), NO_SEPARATION, 0.1);
+
+        assert!(unit.vel_x < 0.0);
+        assert_eq!(unit.current_waypoint, 0);
+    }
 }
@@ -0,0 +1,393 @@
+// battle-core/src/objectives.rs
+//
+// Win conditions. Without objectives a battle ends when no hostile factions
+// are left; with them it ends as soon as any faction completes its own
+// objective, and that faction wins.
+//
+// Objectives are checked once per tick after combat. ControlZone counts
+// consecutive ticks held, so its progress lives next to the objective in
+// ObjectiveProgress rather than in the objective itself.
+//
+// VictoryCondition (config.victory) is the battle-wide form: each leaf is
+// an objective checked with the same is_met, and the winner follows from
+// what was met - the flagship's enemies, the faction that held out.
+// Whichever is met first, a faction's objective or the condition, becomes
+// the battle's VictoryState. Elimination and stalemate still end the
+// battle while neither is.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::battle_unit::{BattleUnit, UnitState};
+use crate::factions::FactionRelations;
+
+/// What a faction has to do to win
+///
+/// JSON: "eliminate_all", { "kill_unit": 42 }, { "survive_for": 1200 },
+/// { "destroy_all_stations": 2 },
+/// { "control_zone": { "x": 0, "y": 0, "z": 0, "radius": 500, "min_ticks": 200 } }
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BattleObjective {
+    /// No hostile unit left in the fight
+    EliminateAll,
+    /// This unit is destroyed (retreating or surrendering doesn't count)
+    KillUnit(u32),
+    /// Still have a unit in the fight at this tick
+    SurviveFor(u64),
+    /// Every station of this faction is destroyed (a faction that never had
+    /// one can't lose this way)
+    DestroyAllStations(u32),
+    /// Be the only side with units inside the sphere for min_ticks ticks in a row
+    ControlZone { x: f32, y: f32, z: f32, radius: f32, min_ticks: u64 },
+}
+
+impl fmt::Display for BattleObjective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BattleObjective::EliminateAll => write!(f, "eliminate_all"),
+            BattleObjective::KillUnit(id) => write!(f, "kill_unit({})", id),
+            BattleObjective::SurviveFor(ticks) => write!(f, "survive_for({})", ticks),
+            BattleObjective::DestroyAllStations(faction_id) => write!(f, "destroy_all_stations({})", faction_id),
+            BattleObjective::ControlZone { x, y, z, radius, min_ticks } => {
+                write!(f, "control_zone({}, {}, {}, r={}, {} ticks)", x, y, z, radius, min_ticks)
+            }
+        }
+    }
+}
+
+/// Per-objective state kept by the simulator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct ObjectiveProgress {
+    /// Consecutive ticks the zone has been held (ControlZone only)
+    pub held_ticks: u64,
+    pub completed: bool,
+}
+
+#[inline]
+fn has_units(units: &[BattleUnit], faction_id: u32) -> bool {
+    units.iter().any(|u| u.faction_id == faction_id && u.in_battle())
+}
+
+impl BattleObjective {
+    /// Whether faction_id alone holds the zone right now - it has a unit in
+    /// the fight inside it and no hostile one is. False for other objectives.
+    pub fn zone_held(&self, faction_id: u32, units: &[BattleUnit], relations: &FactionRelations) -> bool {
+        let BattleObjective::ControlZone { x, y, z, radius, .. } = *self else {
+            return false;
+        };
+        let radius_sq = radius * radius;
+        let mut present = false;
+        for unit in units.iter().filter(|u| u.in_battle()) {
+            let (dx, dy, dz) = (unit.pos_x - x, unit.pos_y - y, unit.pos_z - z);
+            if dx * dx + dy * dy + dz * dz > radius_sq {
+                continue;
+            }
+            if relations.is_hostile(faction_id, unit.faction_id) {
+                return false;
+            }
+            present |= unit.faction_id == faction_id;
+        }
+        present
+    }
+
+    /// Check the objective for faction_id at `tick`
+    ///
+    /// `arriving` are factions with reinforcements still on the way - they
+    /// haven't been eliminated. KillUnit and DestroyAllStations are the same
+    /// for every faction. Call update() first for ControlZone so held_ticks
+    /// is current.
+    pub fn is_met(
+        &self,
+        faction_id: u32,
+        units: &[BattleUnit],
+        relations: &FactionRelations,
+        arriving: &[u32],
+        tick: u64,
+        progress: &ObjectiveProgress,
+    ) -> bool {
+        match *self {
+            BattleObjective::EliminateAll => {
+                has_units(units, faction_id)
+                    && !units.iter().any(|u| u.in_battle() && relations.is_hostile(faction_id, u.faction_id))
+                    && !arriving.iter().any(|&f| relations.is_hostile(faction_id, f))
+            }
+            BattleObjective::KillUnit(id) => {
+                units.iter().any(|u| u.id == id && u.state == UnitState::Destroyed)
+            }
+            BattleObjective::SurviveFor(ticks) => tick >= ticks && has_units(units, faction_id),
+            BattleObjective::DestroyAllStations(loser) => {
+                let mut stations = units.iter().filter(|u| u.faction_id == loser && u.is_station).peekable();
+                stations.peek().is_some() && stations.all(|u| u.state == UnitState::Destroyed)
+            }
+            BattleObjective::ControlZone { min_ticks, .. } => progress.held_ticks >= min_ticks,
+        }
+    }
+
+    /// Advance per-tick progress (zone hold streaks)
+    pub fn update(&self, faction_id: u32, units: &[BattleUnit], relations: &FactionRelations, progress: &mut ObjectiveProgress) {
+        if matches!(self, BattleObjective::ControlZone { .. }) {
+            if self.zone_held(faction_id, units, relations) {
+                progress.held_ticks += 1;
+            } else {
+                progress.held_ticks = 0;
+            }
+        }
+    }
+}
+
+/// Battle-wide end condition (config.victory) - see objective() for the
+/// objective behind each leaf
+///
+/// JSON: "elimination", { "destroy_unit": 7 }, { "destroy_all_stations": 2 },
+/// { "survive_ticks": { "faction_id": 2, "ticks": 6000 } },
+/// { "any": [...] } (the first one met decides), { "all": [...] } (every one
+/// met - the winner they all name, no winner if they disagree)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VictoryCondition {
+    /// No hostile factions left - the default, same as having no condition
+    #[default]
+    Elimination,
+    /// This unit is destroyed - its faction's strongest enemy wins
+    DestroyUnit(u32),
+    /// Every station of this faction is destroyed (a faction that never had
+    /// one can't lose this way) - its strongest enemy wins
+    DestroyAllStations(u32),
+    /// faction_id still has a unit in the fight at this tick - it wins
+    SurviveTicks { faction_id: u32, ticks: u64 },
+    Any(Vec<VictoryCondition>),
+    All(Vec<VictoryCondition>),
+}
+
+impl fmt::Display for VictoryCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter<'_>, name: &str, conditions: &[VictoryCondition]| {
+            let inner: Vec<String> = conditions.iter().map(ToString::to_string).collect();
+            write!(f, "{}({})", name, inner.join(", "))
+        };
+        match self {
+            VictoryCondition::Elimination => write!(f, "elimination"),
+            VictoryCondition::DestroyUnit(id) => write!(f, "destroy_unit({})", id),
+            VictoryCondition::DestroyAllStations(faction_id) => write!(f, "destroy_all_stations({})", faction_id),
+            VictoryCondition::SurviveTicks { faction_id, ticks } => write!(f, "survive_ticks({}, {})", faction_id, ticks),
+            VictoryCondition::Any(conditions) => list(f, "any", conditions),
+            VictoryCondition::All(conditions) => list(f, "all", conditions),
+        }
+    }
+}
+
+/// How an objective or config.victory decided the battle (get_victory_state)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct VictoryState {
+    /// None when nobody is left to claim it
+    pub winner: Option<u32>,
+    /// The objective or condition that was met, e.g. "destroy_unit(7)"
+    pub condition: String,
+    pub tick: u64,
+}
+
+/// Faction in the fight with the most units, lowest id on a tie
+fn largest_faction(units: &[BattleUnit], eligible: impl Fn(u32) -> bool) -> Option<u32> {
+    let mut counts: Vec<(u32, usize)> = Vec::new();
+    for unit in units.iter().filter(|u| u.in_battle() && eligible(u.faction_id)) {
+        match counts.iter_mut().find(|(faction_id, _)| *faction_id == unit.faction_id) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((unit.faction_id, 1)),
+        }
+    }
+    counts.into_iter()
+        .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+        .map(|(faction_id, _)| faction_id)
+}
+
+impl VictoryCondition {
+    /// The objective a leaf stands for - None for Any / All
+    pub fn objective(&self) -> Option<BattleObjective> {
+        match *self {
+            VictoryCondition::Elimination => Some(BattleObjective::EliminateAll),
+            VictoryCondition::DestroyUnit(id) => Some(BattleObjective::KillUnit(id)),
+            VictoryCondition::DestroyAllStations(faction_id) => Some(BattleObjective::DestroyAllStations(faction_id)),
+            VictoryCondition::SurviveTicks { ticks, .. } => Some(BattleObjective::SurviveFor(ticks)),
+            VictoryCondition::Any(_) | VictoryCondition::All(_) => None,
+        }
+    }
+
+    /// Some((winner, condition met)) once the condition holds at `tick` - for
+    /// Any, the member that was met. `arriving` as for is_met.
+    pub fn check(
+        &self,
+        units: &[BattleUnit],
+        relations: &FactionRelations,
+        arriving: &[u32],
+        tick: u64,
+    ) -> Option<(Option<u32>, String)> {
+        let conditions = match self {
+            VictoryCondition::Any(conditions) => {
+                return conditions.iter().find_map(|c| c.check(units, relations, arriving, tick));
+            }
+            VictoryCondition::All(conditions) => conditions,
+            _ => {
+                let winner = self.check_leaf(units, relations, arriving, tick)?;
+                return Some((winner, self.to_string()));
+            }
+        };
+        let winners: Vec<Option<u32>> = conditions.iter()
+            .map(|c| c.check(units, relations, arriving, tick).map(|(winner, _)| winner))
+            .collect::<Option<_>>()?;
+        let first = *winners.first()?;
+        let winner = if winners.iter().all(|&w| w == first) { first } else { None };
+        Some((winner, self.to_string()))
+    }
+
+    /// Some(winner) once a leaf's objective is met
+    fn check_leaf(&self, units: &[BattleUnit], relations: &FactionRelations, arriving: &[u32], tick: u64) -> Option<Option<u32>> {
+        let objective = self.objective()?;
+        let met = |faction_id| objective.is_met(faction_id, units, relations, arriving, tick, &ObjectiveProgress::default());
+        // Another faction's loss - its strongest enemy wins
+        let beaten = |loser: u32| largest_faction(units, |f| relations.is_hostile(f, loser));
+        match *self {
+            VictoryCondition::Elimination => {
+                // Every side still in the fight (or on the way) is unopposed
+                let mut factions: Vec<u32> = units.iter()
+                    .filter(|u| u.in_battle())
+                    .map(|u| u.faction_id)
+                    .chain(arriving.iter().copied())
+                    .collect();
+                factions.sort_unstable();
+                factions.dedup();
+                factions.iter().all(|&f| met(f)).then(|| largest_faction(units, |_| true))
+            }
+            VictoryCondition::DestroyUnit(id) => {
+                let loser = units.iter().find(|u| u.id == id)?.faction_id;
+                met(loser).then(|| beaten(loser))
+            }
+            VictoryCondition::DestroyAllStations(loser) => met(loser).then(|| beaten(loser)),
+            VictoryCondition::SurviveTicks { faction_id, .. } => met(faction_id).then_some(Some(faction_id)),
+            VictoryCondition::Any(_) | VictoryCondition::All(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(id: u32, faction_id: u32, x: f32) -> BattleUnit {
+        BattleUnit::builder().id(id).faction(faction_id).hp(10.0).position(x, 0.0, 0.0).build().unwrap()
+    }
+
+    fn check(objective: &BattleObjective, faction_id: u32, units: &[BattleUnit], tick: u64) -> bool {
+        objective.is_met(faction_id, units, &FactionRelations::default(), &[], tick, &ObjectiveProgress::default())
+    }
+
+    #[test]
+    fn test_eliminate_all() {
+        let objective = BattleObjective::EliminateAll;
+        let mut units = vec![unit(1, 1, 0.0), unit(2, 2, 100.0)];
+        assert!(!check(&objective, 1, &units, 1));
+        units[1].state = UnitState::Destroyed;
+        assert!(check(&objective, 1, &units, 1));
+        // A faction that's gone itself hasn't eliminated anyone
+        assert!(!check(&objective, 2, &units, 1));
+        // Surrendered enemies are out of the fight
+        units[1].state = UnitState::Active;
+        units[1].is_surrendered = true;
+        assert!(check(&objective, 1, &units, 1));
+
+        // Allies don't need to be eliminated
+        let mut relations = FactionRelations::default();
+        relations.set_allied(1, 3, true);
+        let units = vec![unit(1, 1, 0.0), unit(3, 3, 100.0)];
+        assert!(objective.is_met(1, &units, &relations, &[], 1, &ObjectiveProgress::default()));
+        // ...but enemy reinforcements on the way do
+        assert!(!objective.is_met(1, &units, &relations, &[2], 1, &ObjectiveProgress::default()));
+        assert!(objective.is_met(1, &units, &relations, &[3], 1, &ObjectiveProgress::default()));
+    }
+
+    #[test]
+    fn test_kill_unit() {
+        let objective = BattleObjective::KillUnit(2);
+        let mut units = vec![unit(1, 1, 0.0), unit(2, 2, 100.0)];
+        assert!(!check(&objective, 1, &units, 1));
+        units[1].state = UnitState::Withdrawn;
+        assert!(!check(&objective, 1, &units, 1));
+        units[1].state = UnitState::Destroyed;
+        assert!(check(&objective, 1, &units, 1));
+        assert_eq!(objective.to_string(), "kill_unit(2)");
+    }
+
+    #[test]
+    fn test_survive_for() {
+        let objective = BattleObjective::SurviveFor(100);
+        let mut units = vec![unit(1, 1, 0.0), unit(2, 2, 100.0)];
+        assert!(!check(&objective, 1, &units, 99));
+        assert!(check(&objective, 1, &units, 100));
+        units[0].state = UnitState::Destroyed;
+        assert!(!check(&objective, 1, &units, 100));
+    }
+
+    #[test]
+    fn test_control_zone() {
+        let objective: BattleObjective = serde_json::from_str(
+            r#"{"control_zone": {"x": 0, "y": 0, "z": 0, "radius": 50, "min_ticks": 3}}"#
+        ).unwrap();
+        let relations = FactionRelations::default();
+        let mut units = vec![unit(1, 1, 10.0), unit(2, 2, 40.0)];
+        let mut progress = ObjectiveProgress::default();
+
+        // Contested - nobody holds it
+        objective.update(1, &units, &relations, &mut progress);
+        assert_eq!(progress.held_ticks, 0);
+
+        units[1].pos_x = 60.0;
+        for _ in 0..2 {
+            objective.update(1, &units, &relations, &mut progress);
+        }
+        assert!(!objective.is_met(1, &units, &relations, &[], 3, &progress));
+        // Leaving resets the streak
+        units[0].pos_x = 70.0;
+        objective.update(1, &units, &relations, &mut progress);
+        assert_eq!(progress.held_ticks, 0);
+
+        units[0].pos_x = 0.0;
+        for _ in 0..3 {
+            objective.update(1, &units, &relations, &mut progress);
+        }
+        assert!(objective.is_met(1, &units, &relations, &[], 6, &progress));
+        assert!(!objective.zone_held(2, &units, &relations));
+    }
+
+    #[test]
+    fn test_victory_conditions() {
+        let relations = FactionRelations::default();
+        let mut units = vec![unit(1, 1, 0.0), unit(2, 1, 10.0), unit(3, 2, 100.0), unit(4, 2, 110.0)];
+        units[3].is_station = true;
+
+        let stations = VictoryCondition::DestroyAllStations(2);
+        assert_eq!(stations.check(&units, &relations, &[], 1), None);
+        // Faction 1 never had a station to lose
+        assert_eq!(VictoryCondition::DestroyAllStations(1).check(&units, &relations, &[], 1), None);
+        units[3].state = UnitState::Destroyed;
+        assert_eq!(stations.check(&units, &relations, &[], 1), Some((Some(1), "destroy_all_stations(2)".to_string())));
+
+        assert_eq!(stations.objective(), Some(BattleObjective::DestroyAllStations(2)));
+
+        // All - every member met, and they have to agree on the winner
+        let all = |survivor| VictoryCondition::All(vec![stations.clone(), VictoryCondition::SurviveTicks { faction_id: survivor, ticks: 50 }]);
+        assert_eq!(all(1).check(&units, &relations, &[], 49), None);
+        assert_eq!(all(1).check(&units, &relations, &[], 50).map(|(winner, _)| winner), Some(Some(1)));
+        // Faction 2 held out but lost its stations to faction 1 - met, nobody wins
+        assert_eq!(
+            all(2).check(&units, &relations, &[], 50),
+            Some((None, "all(destroy_all_stations(2), survive_ticks(2, 50))".to_string()))
+        );
+        assert_eq!(VictoryCondition::All(Vec::new()).check(&units, &relations, &[], 50), None);
+
+        // Elimination waits for reinforcements on the way
+        units[2].state = UnitState::Destroyed;
+        assert_eq!(VictoryCondition::Elimination.check(&units, &relations, &[2], 1), None);
+        assert_eq!(VictoryCondition::Elimination.check(&units, &relations, &[], 1).map(|(winner, _)| winner), Some(Some(1)));
+    }
+}
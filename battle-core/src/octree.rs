@@ -0,0 +1,326 @@
+// battle-core/src/octree.rs
+//
+// Octree spatial index for sparse, very large battlefields.
+// A uniform grid either wastes memory on tiny cells or scans crowded cells
+// when fleets are spread far apart; the octree only subdivides where units are.
+
+use crate::spatial_index::SpatialIndex;
+
+/// Maximum subdivision depth below the root
+pub const MAX_DEPTH: u32 = 8;
+
+/// Units a leaf holds before it splits
+pub const NODE_CAPACITY: usize = 4;
+
+/// Root half-size on first insert - the root doubles to cover outliers
+const INITIAL_HALF_SIZE: f32 = 1024.0;
+
+type Entry = (usize, f32, f32, f32);
+
+#[derive(Debug, Clone)]
+struct Node {
+    center: (f32, f32, f32),
+    half_size: f32,
+    depth: u32,
+    entries: Vec<Entry>,
+    children: Option<Box<[Node; 8]>>,
+}
+
+impl Node {
+    fn new(center: (f32, f32, f32), half_size: f32, depth: u32) -> Self {
+        Self {
+            center,
+            half_size,
+            depth,
+            entries: Vec::new(),
+            children: None,
+        }
+    }
+
+    #[inline]
+    fn contains(&self, x: f32, y: f32, z: f32) -> bool {
+        (x - self.center.0).abs() <= self.half_size
+            && (y - self.center.1).abs() <= self.half_size
+            && (z - self.center.2).abs() <= self.half_size
+    }
+
+    /// Child index for a position: one bit per axis, set on the positive side
+    #[inline]
+    fn octant(&self, x: f32, y: f32, z: f32) -> usize {
+        (x >= self.center.0) as usize
+            | ((y >= self.center.1) as usize) << 1
+            | ((z >= self.center.2) as usize) << 2
+    }
+
+    /// Squared distance from a point to this node's bounding box (0 if inside)
+    #[inline]
+    fn box_dist_sq(&self, x: f32, y: f32, z: f32) -> f32 {
+        let dx = ((x - self.center.0).abs() - self.half_size).max(0.0);
+        let dy = ((y - self.center.1).abs() - self.half_size).max(0.0);
+        let dz = ((z - self.center.2).abs() - self.half_size).max(0.0);
+        dx * dx + dy * dy + dz * dz
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        let octant = self.octant(entry.1, entry.2, entry.3);
+        if let Some(children) = &mut self.children {
+            children[octant].insert(entry);
+            return;
+        }
+
+        self.entries.push(entry);
+        if self.entries.len() > NODE_CAPACITY && self.depth < MAX_DEPTH {
+            self.split();
+        }
+    }
+
+    fn split(&mut self) {
+        let quarter = self.half_size * 0.5;
+        let (cx, cy, cz) = self.center;
+        let depth = self.depth + 1;
+        let children: [Node; 8] = std::array::from_fn(|i| {
+            let sign = |bit: usize| if i & bit != 0 { quarter } else { -quarter };
+            Node::new((cx + sign(1), cy + sign(2), cz + sign(4)), quarter, depth)
+        });
+        self.children = Some(Box::new(children));
+
+        for entry in std::mem::take(&mut self.entries) {
+            self.insert(entry);
+        }
+    }
+
+    fn remove(&mut self, index: usize, x: f32, y: f32, z: f32) -> bool {
+        let octant = self.octant(x, y, z);
+        if let Some(children) = &mut self.children {
+            return children[octant].remove(index, x, y, z);
+        }
+
+        let before = self.entries.len();
+        self.entries.retain(|&(idx, ..)| idx != index);
+        self.entries.len() != before
+    }
+
+    fn query(&self, x: f32, y: f32, z: f32, range_sq: f32, visit: &mut impl FnMut(usize, f32)) {
+        if self.box_dist_sq(x, y, z) > range_sq {
+            return;
+        }
+
+        for &(idx, ux, uy, uz) in &self.entries {
+            let dist_sq = (ux - x).powi(2) + (uy - y).powi(2) + (uz - z).powi(2);
+            if dist_sq <= range_sq {
+                visit(idx, dist_sq);
+            }
+        }
+
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.query(x, y, z, range_sq, visit);
+            }
+        }
+    }
+
+    fn collect(&self, out: &mut Vec<Entry>) {
+        out.extend_from_slice(&self.entries);
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.collect(out);
+            }
+        }
+    }
+}
+
+/// Sparse octree over unit positions
+///
+/// Bounds are not fixed up front: the root is centred on the first unit and
+/// doubles in size whenever a unit lands outside it.
+#[derive(Debug, Clone, Default)]
+pub struct Octree {
+    root: Option<Node>,
+    len: usize,
+}
+
+impl Octree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert unit - O(log n); non-finite positions are ignored
+    pub fn insert(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+            return;
+        }
+
+        let root = self.root.get_or_insert_with(|| Node::new((x, y, z), INITIAL_HALF_SIZE, 0));
+        if !root.contains(x, y, z) {
+            self.grow_to(x, y, z);
+        }
+
+        if let Some(root) = &mut self.root {
+            root.insert((index, x, y, z));
+            self.len += 1;
+        }
+    }
+
+    /// Units within range, with squared distance
+    pub fn query_range(&self, x: f32, y: f32, z: f32, range: f32) -> Vec<(usize, f32)> {
+        let mut result = Vec::new();
+        self.query_range_into(x, y, z, range, &mut result);
+        result
+    }
+
+    /// query_range into `out` (cleared first), reusing its allocation
+    pub fn query_range_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<(usize, f32)>) {
+        out.clear();
+        if let Some(root) = &self.root {
+            root.query(x, y, z, range * range, &mut |idx, dist_sq| out.push((idx, dist_sq)));
+        }
+    }
+
+    /// Indices within range into `out` (cleared first)
+    pub fn get_nearby_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<usize>) {
+        out.clear();
+        if let Some(root) = &self.root {
+            root.query(x, y, z, range * range, &mut |idx, _| out.push(idx));
+        }
+    }
+
+    /// Remove unit previously inserted at this position
+    pub fn remove(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        if let Some(root) = &mut self.root {
+            if root.contains(x, y, z) && root.remove(index, x, y, z) {
+                self.len -= 1;
+            }
+        }
+    }
+
+    /// Drop every node
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Double the root towards a point until it fits, then re-insert everything
+    fn grow_to(&mut self, x: f32, y: f32, z: f32) {
+        let Some(old) = self.root.take() else {
+            return;
+        };
+
+        let (mut center, mut half_size) = (old.center, old.half_size);
+        loop {
+            let step = |c: f32, p: f32| if p >= c { c + half_size } else { c - half_size };
+            center = (step(center.0, x), step(center.1, y), step(center.2, z));
+            half_size *= 2.0;
+            if (x - center.0).abs() <= half_size
+                && (y - center.1).abs() <= half_size
+                && (z - center.2).abs() <= half_size
+            {
+                break;
+            }
+        }
+
+        let mut entries = Vec::with_capacity(self.len);
+        old.collect(&mut entries);
+
+        let mut root = Node::new(center, half_size, 0);
+        for entry in entries {
+            root.insert(entry);
+        }
+        self.root = Some(root);
+    }
+}
+
+impl SpatialIndex for Octree {
+    fn insert(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        Octree::insert(self, index, x, y, z);
+    }
+
+    fn query_range_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<(usize, f32)>) {
+        Octree::query_range_into(self, x, y, z, range, out);
+    }
+
+    fn get_nearby_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<usize>) {
+        Octree::get_nearby_into(self, x, y, z, range, out);
+    }
+
+    fn remove(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        Octree::remove(self, index, x, y, z);
+    }
+
+    fn clear(&mut self) {
+        Octree::clear(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut hits: Vec<(usize, f32)>) -> Vec<usize> {
+        hits.sort_by_key(|&(idx, _)| idx);
+        hits.into_iter().map(|(idx, _)| idx).collect()
+    }
+
+    #[test]
+    fn test_query_matches_brute_force() {
+        let mut tree = Octree::new();
+        let mut points = Vec::new();
+        let mut seed: u32 = 12345;
+        for idx in 0..500 {
+            let mut next = || {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                (seed >> 8) as f32 / (1 << 24) as f32 * 20000.0 - 10000.0
+            };
+            let p = (next(), next(), next());
+            tree.insert(idx, p.0, p.1, p.2);
+            points.push(p);
+        }
+        assert_eq!(tree.len(), 500);
+
+        let (qx, qy, qz, range) = (1000.0, -500.0, 250.0, 4000.0);
+        let expected: Vec<usize> = points
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| (p.0 - qx).powi(2) + (p.1 - qy).powi(2) + (p.2 - qz).powi(2) <= range * range)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        assert!(!expected.is_empty());
+        assert_eq!(sorted(tree.query_range(qx, qy, qz, range)), expected);
+    }
+
+    #[test]
+    fn test_remove_and_clear() {
+        let mut tree = Octree::new();
+        tree.insert(0, 0.0, 0.0, 0.0);
+        tree.insert(1, 10.0, 0.0, 0.0);
+        tree.insert(2, 50000.0, 0.0, 0.0); // forces the root to grow
+
+        assert_eq!(sorted(tree.query_range(0.0, 0.0, 0.0, 20.0)), vec![0, 1]);
+        assert_eq!(sorted(tree.query_range(50000.0, 0.0, 0.0, 1.0)), vec![2]);
+
+        tree.remove(1, 10.0, 0.0, 0.0);
+        assert_eq!(sorted(tree.query_range(0.0, 0.0, 0.0, 20.0)), vec![0]);
+        assert_eq!(tree.len(), 2);
+
+        tree.clear();
+        assert!(tree.is_empty());
+        assert!(tree.query_range(0.0, 0.0, 0.0, 1e9).is_empty());
+    }
+
+    #[test]
+    fn test_coincident_units_stop_at_max_depth() {
+        let mut tree = Octree::new();
+        for idx in 0..50 {
+            tree.insert(idx, 1.0, 1.0, 1.0);
+        }
+        assert_eq!(tree.query_range(1.0, 1.0, 1.0, 0.5).len(), 50);
+    }
+}
@@ -0,0 +1,289 @@
+// battle-core/src/profiling.rs
+//
+// Per-phase tick timing (BattleConfig.profiling). Each simulate_tick phase is
+// timed with a PhaseTimer lap - performance.now() in the browser / Node,
+// Instant natively - and PhaseProfiler keeps all-time totals plus the last
+// profile_window samples for averages and maxima (get_profile_stats).
+//
+// With profiling off a PhaseTimer holds None and never reads the clock, so
+// the cost is one branch per phase. Idle ticks aren't sampled.
+//
+// Whole-tick compute time is measured every tick regardless
+// (TickResult.tick_duration_us) - TickDurations keeps the last few for
+// get_average_tick_duration_us.
+
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// Milliseconds on a monotonic clock (arbitrary origin)
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    performance_now()
+}
+
+/// Milliseconds on a monotonic clock (arbitrary origin)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    ORIGIN.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+/// Parts of a tick, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema), schemars(rename = "ProfilePhase"))]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Column sync, grid tuning and refill
+    Grid,
+    /// Target validation and acquisition
+    Targeting,
+    /// Simulator-driven movement and separation
+    Movement,
+    /// Collecting and resolving shots, status effects
+    Combat,
+    /// Applying the damage queue
+    Damage,
+    /// Repairs, morale, disables, surrenders, retreats, objectives
+    Aftermath,
+    /// Shield and capacitor regen
+    ShieldRegen,
+    /// Encoding the TickResult for JS (WASM calls only)
+    Serialize,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 8] = [
+        Phase::Grid, Phase::Targeting, Phase::Movement, Phase::Combat,
+        Phase::Damage, Phase::Aftermath, Phase::ShieldRegen, Phase::Serialize,
+    ];
+}
+
+/// Default number of recent ticks averages and maxima cover
+pub const DEFAULT_PROFILE_WINDOW: usize = 100;
+
+/// Ticks get_average_tick_duration_us averages over
+pub const TICK_DURATION_WINDOW: usize = 20;
+
+/// Ticks that take longer than this (microseconds) log a warning - a whole
+/// tick at 20 Hz
+pub const SLOW_TICK_US: u32 = 50_000;
+
+/// Compute time of the last TICK_DURATION_WINDOW ticks, in microseconds
+#[derive(Debug, Clone, Default)]
+pub struct TickDurations {
+    recent: [u32; TICK_DURATION_WINDOW],
+    /// Slot the next sample goes in
+    next: usize,
+    len: usize,
+}
+
+impl TickDurations {
+    pub fn record(&mut self, us: u32) {
+        self.recent[self.next] = us;
+        self.next = (self.next + 1) % TICK_DURATION_WINDOW;
+        self.len = (self.len + 1).min(TICK_DURATION_WINDOW);
+    }
+
+    /// Mean of the recorded samples (0 before the first tick)
+    pub fn average_us(&self) -> u32 {
+        if self.len == 0 {
+            return 0;
+        }
+        let total: u64 = self.recent[..self.len].iter().map(|&us| us as u64).sum();
+        (total / self.len as u64) as u32
+    }
+}
+
+/// Timing of one phase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct PhaseStats {
+    pub phase: Phase,
+    /// Time spent since profiling started
+    pub total_ms: f64,
+    /// Samples since profiling started
+    pub samples: u64,
+    /// Over the last `window` samples
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+/// get_profile_stats JSON - phases in tick order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct ProfileStats {
+    /// Ticks profiled
+    pub ticks: u64,
+    pub window: usize,
+    pub phases: Vec<PhaseStats>,
+}
+
+/// Accumulated phase timings
+#[derive(Debug, Clone, Default)]
+pub struct PhaseProfiler {
+    ticks: u64,
+    totals: [f64; Phase::ALL.len()],
+    samples: [u64; Phase::ALL.len()],
+    /// Most recent samples per phase, newest last
+    recent: [VecDeque<f64>; Phase::ALL.len()],
+}
+
+impl PhaseProfiler {
+    pub fn record(&mut self, phase: Phase, ms: f64, window: usize) {
+        let i = phase as usize;
+        self.totals[i] += ms;
+        self.samples[i] += 1;
+        let recent = &mut self.recent[i];
+        while recent.len() >= window.max(1) {
+            recent.pop_front();
+        }
+        recent.push_back(ms);
+    }
+
+    pub fn end_tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    pub fn stats(&self, window: usize) -> ProfileStats {
+        let phases = Phase::ALL.iter()
+            .map(|&phase| {
+                let i = phase as usize;
+                let recent = &self.recent[i];
+                let avg_ms = if recent.is_empty() { 0.0 } else { recent.iter().sum::<f64>() / recent.len() as f64 };
+                let max_ms = recent.iter().copied().fold(0.0, f64::max);
+                PhaseStats { phase, total_ms: self.totals[i], samples: self.samples[i], avg_ms, max_ms }
+            })
+            .collect();
+        ProfileStats { ticks: self.ticks, window, phases }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Stopwatch for one tick's phases - inert when profiling is off
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseTimer {
+    last: Option<f64>,
+}
+
+impl PhaseTimer {
+    #[inline]
+    pub fn start(enabled: bool) -> Self {
+        PhaseTimer { last: enabled.then(now_ms) }
+    }
+
+    /// Record the time since the previous lap (or start) as `phase`
+    #[inline]
+    pub fn lap(&mut self, profiler: &mut PhaseProfiler, phase: Phase, window: usize) {
+        if let Some(last) = self.last {
+            let now = now_ms();
+            profiler.record(phase, now - last, window);
+            self.last = Some(now);
+        }
+    }
+
+    /// Count the tick - call once after its last lap
+    #[inline]
+    pub fn finish(self, profiler: &mut PhaseProfiler) {
+        if self.last.is_some() {
+            profiler.end_tick();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::{BattleUnit, Weapon};
+    use crate::config::BattleConfig;
+    use crate::simulator::{BattleSimulator, TICK_RESULT_VERSION};
+
+    fn ship(id: u32, faction: u32, x: f32) -> BattleUnit {
+        BattleUnit::builder()
+            .id(id)
+            .faction(faction)
+            .position(x, 0.0, 0.0)
+            .hp(500.0)
+            .max_speed(50.0)
+            .as_ship()
+            .weapons(vec![Weapon::builder().tag("LASER").dps(10.0).range(80.0, 100.0).build()])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tick_durations_average_the_last_window() {
+        let mut durations = TickDurations::default();
+        assert_eq!(durations.average_us(), 0);
+        durations.record(30);
+        durations.record(10);
+        assert_eq!(durations.average_us(), 20);
+        for _ in 0..TICK_DURATION_WINDOW {
+            durations.record(100);
+        }
+        assert_eq!(durations.average_us(), 100);
+    }
+
+    #[test]
+    fn test_window_keeps_recent_samples() {
+        let mut profiler = PhaseProfiler::default();
+        for ms in [5.0, 1.0, 2.0, 3.0] {
+            profiler.record(Phase::Combat, ms, 3);
+        }
+        let stats = profiler.stats(3);
+        let combat = &stats.phases[Phase::Combat as usize];
+        assert_eq!((combat.total_ms, combat.samples), (11.0, 4));
+        assert_eq!((combat.avg_ms, combat.max_ms), (2.0, 3.0));
+    }
+
+    #[test]
+    fn test_profile_stats_populated_and_monotonic() {
+        let units: Vec<BattleUnit> = (0..20).map(|i| ship(i + 1, 1 + i % 2, (i % 2) as f32 * 60.0)).collect();
+
+        // Off by default - nothing recorded
+        let mut sim = BattleSimulator::new(units.clone(), 1000.0);
+        let result = sim.simulate_tick(0.05, 1000.05);
+        assert_eq!(sim.profile_stats().ticks, 0);
+        // Whole-tick timing runs without profiling
+        assert_eq!(result.version, TICK_RESULT_VERSION);
+        assert_eq!(sim.average_tick_duration_us(), result.tick_duration_us);
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!((json["version"].as_u64(), json["tickDurationUs"].as_u64()), (Some(1), Some(result.tick_duration_us as u64)));
+
+        let config = BattleConfig { profiling: true, profile_window: 10, ..Default::default() };
+        let mut sim = BattleSimulator::new_with_config(units, 1000.0, config);
+        let mut previous = sim.profile_stats();
+        for i in 1..=30 {
+            sim.simulate_tick(0.05, 1000.0 + i as f64 * 0.05);
+            let stats = sim.profile_stats();
+            assert_eq!(stats.ticks, i);
+            assert_eq!(stats.phases.len(), Phase::ALL.len());
+            for (now, before) in stats.phases.iter().zip(&previous.phases) {
+                if now.phase == Phase::Serialize {
+                    assert_eq!(now.samples, 0);
+                    continue;
+                }
+                assert_eq!(now.samples, i, "{:?}", now.phase);
+                assert!(now.total_ms >= before.total_ms && now.max_ms >= now.avg_ms && now.avg_ms >= 0.0);
+            }
+            previous = stats;
+        }
+        let json = serde_json::to_value(&previous).unwrap();
+        assert_eq!(json["window"], 10);
+        assert_eq!(json["phases"][0]["phase"], "grid");
+    }
+}
@@ -0,0 +1,248 @@
+// battle-core/src/replay.rs
+//
+// Input recording for deterministic playback. A replay is the unit snapshot
+// taken when recording started plus everything fed in from outside before
+// each tick. Re-feeding it to a fresh simulator reproduces the same
+// TickResults - weapon cooldown randomization happens in normalize(), so the
+// snapshot and added units are stored already normalized.
+//
+// Only a deterministic battle can be recorded - hit rolls and the cooldowns
+// of scheduled reinforcements (stored un-normalized, they normalize on
+// arrival) come from the seed, so they match on playback.
+//
+// Times are simulated time, and paused ticks aren't recorded, so a battle
+// that was paused plays back straight through.
+//
+// The config (and with it the seed) and alliances in place when recording
+// started are stored with the snapshot, and every other host input that
+// changes the battle - target locks, move orders, waypoints, groups,
+// removals, alliance changes, config / faction config / objective changes,
+// resource nodes, hangar launches / docks, power splits, retreat and orbit
+// settings, forced retargets, weapon restrictions and tag categories - is recorded as a
+// ReplayCommand in the order it was issued, so the battle plays back
+// exactly. BattleConfig.record_replay starts recording at construction.
+
+use serde::{Deserialize, Serialize};
+use crate::battle_unit::{BattleUnit, ShipClass, Stance};
+use crate::config::BattleConfig;
+use crate::factions::FactionConfig;
+use crate::objectives::BattleObjective;
+use crate::resources::ResourceNode;
+use crate::simulator::ReinforcementWave;
+use crate::weapons::WeaponCategory;
+use crate::PositionUpdate;
+
+/// A manually_fire call that fired
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManualFireInput {
+    pub attacker_id: u32,
+    pub target_id: u32,
+    pub weapon_tag: String,
+    pub time: f64,
+}
+
+/// A player command that changed the battle, as it was issued
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayCommand {
+    LockTarget { unit_id: u32, target_id: u32 },
+    UnlockTarget { unit_id: u32 },
+    /// The point as given - playback clamps it to the bounds again
+    MoveOrder { unit_id: u32, x: f32, y: f32, z: f32 },
+    ClearOrders { unit_id: u32 },
+    SetWaypoints { unit_id: u32, waypoints: Vec<(f32, f32, f32)> },
+    ClearWaypoints { unit_id: u32 },
+    AssignGroup { unit_id: u32, group_id: Option<u32> },
+    GroupTarget { group_id: u32, target_id: u32 },
+    GroupStance { group_id: u32, stance: Stance },
+    ClearGroup { group_id: u32 },
+    RemoveUnit { unit_id: u32 },
+    SurrenderFaction { faction_id: u32 },
+    SetAllied { a: u32, b: u32, allied: bool },
+    /// The whole config after set_config (or a setter that changes part of it)
+    SetConfig { config: Box<BattleConfig> },
+    SetFactionConfig { config: FactionConfig },
+    SetObjectives { objectives: Vec<(u32, BattleObjective)> },
+    AddResourceNode { node: ResourceNode },
+    RemoveResourceNode { node_id: u32 },
+    LaunchFighter { carrier_id: u32, fighter_id: u32, time: f64 },
+    DockFighter { carrier_id: u32, fighter_id: u32 },
+    SetPower { unit_id: u32, shield: f32, weapons: f32, engines: f32 },
+    SetOrbitMode { unit_id: u32, enabled: bool },
+    SetRetreatThreshold { unit_id: u32, fraction: f32 },
+    SetRetreatTarget { unit_id: u32, x: f32, y: f32, z: f32 },
+    /// force_retarget_unit, or force_retarget_all for None
+    ForceRetarget { unit_id: Option<u32> },
+    ClassWeaponRestrictions { class: ShipClass, allowed_tags: Option<Vec<String>> },
+    WeaponTagPrefix { prefix: String, category: WeaponCategory },
+}
+
+/// Everything fed in before one simulate_tick call
+///
+/// Playback schedules reinforcements, applies added units, then position
+/// updates, then commands, then manual fires, then self-destruct triggers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TickInput {
+    pub dt: f32,
+    pub current_time: f64,
+    pub position_updates: Vec<PositionUpdate>,
+    pub added_units: Vec<BattleUnit>,
+    pub manual_fires: Vec<ManualFireInput>,
+    pub reinforcements: Vec<ReinforcementWave>,
+    /// In the order they were issued
+    pub commands: Vec<ReplayCommand>,
+    /// Units trigger_self_destruct was called for
+    pub self_destructs: Vec<u32>,
+}
+
+/// Recorded battle - export() encodes everything but the recording state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayRecorder {
+    #[serde(skip)]
+    pub enabled: bool,
+    /// current_time the snapshot was taken at
+    pub start_time: f64,
+    pub initial_state: Vec<BattleUnit>,
+    /// Config the battle was built with (recording off)
+    pub config: BattleConfig,
+    /// Allied faction pairs when recording started
+    pub alliances: Vec<(u32, u32)>,
+    pub tick_inputs: Vec<TickInput>,
+    /// Inputs since the last tick
+    #[serde(skip)]
+    pending: TickInput,
+}
+
+impl ReplayRecorder {
+    /// Start a new recording from this unit snapshot (drops any previous one)
+    pub fn start(&mut self, units: &[BattleUnit], current_time: f64, config: &BattleConfig, alliances: Vec<(u32, u32)>) {
+        self.enabled = true;
+        self.start_time = current_time;
+        self.initial_state = units.to_vec();
+        self.config = BattleConfig { record_replay: false, ..config.clone() };
+        self.alliances = alliances;
+        self.tick_inputs.clear();
+        self.pending = TickInput::default();
+    }
+
+    /// Stop recording - inputs since the last tick are dropped
+    pub fn stop(&mut self) {
+        self.enabled = false;
+        self.pending = TickInput::default();
+    }
+
+    pub fn record_positions(&mut self, updates: &[PositionUpdate]) {
+        self.pending.position_updates.extend_from_slice(updates);
+    }
+
+    pub fn record_units(&mut self, units: &[BattleUnit]) {
+        self.pending.added_units.extend_from_slice(units);
+    }
+
+    pub fn record_manual_fire(&mut self, attacker_id: u32, target_id: u32, weapon_tag: &str, time: f64) {
+        self.pending.manual_fires.push(ManualFireInput {
+            attacker_id,
+            target_id,
+            weapon_tag: weapon_tag.to_string(),
+            time,
+        });
+    }
+
+    pub fn record_self_destruct(&mut self, unit_id: u32) {
+        self.pending.self_destructs.push(unit_id);
+    }
+
+    pub fn record_command(&mut self, command: ReplayCommand) {
+        self.pending.commands.push(command);
+    }
+
+    pub fn record_reinforcements(&mut self, wave: &ReinforcementWave) {
+        self.pending.reinforcements.push(wave.clone());
+    }
+
+    /// Close the current input bundle for a simulate_tick(dt, current_time) call
+    pub fn end_tick(&mut self, dt: f32, current_time: f64) {
+        let mut input = std::mem::take(&mut self.pending);
+        input.dt = dt;
+        input.current_time = current_time;
+        self.tick_inputs.push(input);
+    }
+
+    /// Encode with bincode
+    pub fn export(&self) -> Result<Vec<u8>, bincode::Error> {
+        bincode::serialize(self)
+    }
+
+    /// Decode an export() - the result isn't recording
+    pub fn import(data: &[u8]) -> Result<Self, bincode::Error> {
+        bincode::deserialize(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SimulationMode;
+    use crate::simulator::BattleBounds;
+
+    #[test]
+    fn test_export_roundtrip() {
+        let mut recorder = ReplayRecorder::default();
+        let unit = |id| BattleUnit::builder().id(id).faction(1).build().unwrap();
+        let config = BattleConfig { record_replay: true, mode: SimulationMode::Deterministic { seed: 3 }, ..Default::default() };
+        recorder.start(&[unit(7)], 1000.0, &config, vec![(2, 3)]);
+        recorder.record_positions(&[PositionUpdate { id: 7, x: 1.0, y: 2.0, z: 3.0, clear_target: true }]);
+        recorder.record_command(ReplayCommand::LockTarget { unit_id: 7, target_id: 8 });
+        recorder.record_command(ReplayCommand::GroupStance { group_id: 1, stance: Stance::Defensive });
+        let zone = BattleObjective::ControlZone { x: 0.0, y: 0.0, z: 0.0, radius: 50.0, min_ticks: 20 };
+        recorder.record_command(ReplayCommand::SetObjectives { objectives: vec![(1, zone), (2, BattleObjective::EliminateAll)] });
+        recorder.record_command(ReplayCommand::SetConfig { config: Box::new(BattleConfig { tick_rate: 30.0, ..config.clone() }) });
+        recorder.record_manual_fire(7, 8, "LASER", 1000.01);
+        recorder.end_tick(0.05, 1000.05);
+        recorder.end_tick(0.05, 1000.1);
+        recorder.record_units(&[unit(9)]);
+
+        let decoded = ReplayRecorder::import(&recorder.export().unwrap()).unwrap();
+        assert!(!decoded.enabled);
+        assert_eq!(decoded.start_time, 1000.0);
+        assert_eq!(decoded.initial_state[0].id, 7);
+        assert_eq!(decoded.config.mode, SimulationMode::Deterministic { seed: 3 });
+        assert!(!decoded.config.record_replay);
+        assert_eq!(decoded.alliances, vec![(2, 3)]);
+        assert_eq!(decoded.tick_inputs.len(), 2);
+        let first = &decoded.tick_inputs[0];
+        assert_eq!((first.dt, first.current_time), (0.05, 1000.05));
+        assert_eq!(first.position_updates[0].y, 2.0);
+        assert_eq!(first.manual_fires[0].weapon_tag, "LASER");
+        assert_eq!(first.commands[1], ReplayCommand::GroupStance { group_id: 1, stance: Stance::Defensive });
+        assert!(matches!(&first.commands[2], ReplayCommand::SetObjectives { objectives } if objectives.len() == 2));
+        assert!(matches!(&first.commands[3], ReplayCommand::SetConfig { config } if config.tick_rate == 30.0));
+        // Pending inputs aren't part of the export
+        assert!(decoded.tick_inputs[1].added_units.is_empty());
+        assert!(ReplayRecorder::import(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_export_roundtrip_with_bounds() {
+        let unit = BattleUnit::builder().id(7).faction(1).build().unwrap();
+        for bounds in [
+            BattleBounds::Box { min: (-10.0, -20.0, -30.0), max: (10.0, 20.0, 30.0) },
+            BattleBounds::Sphere { center: (1.0, 2.0, 3.0), radius: 500.0 },
+        ] {
+            let mut recorder = ReplayRecorder::default();
+            let config = BattleConfig { bounds: Some(bounds), ..Default::default() };
+            recorder.start(std::slice::from_ref(&unit), 1000.0, &config, Vec::new());
+            recorder.record_command(ReplayCommand::MoveOrder { unit_id: 7, x: 900.0, y: 0.0, z: 0.0 });
+            recorder.end_tick(0.05, 1000.05);
+
+            let decoded = ReplayRecorder::import(&recorder.export().unwrap()).unwrap();
+            assert_eq!(decoded.config.bounds, Some(bounds));
+            assert_eq!(decoded.tick_inputs[0].commands.len(), 1);
+        }
+
+        // JSON keeps the "type" tag
+        let json = serde_json::to_value(BattleBounds::Sphere { center: (0.0, 0.0, 0.0), radius: 5.0 }).unwrap();
+        assert_eq!(json["type"], "sphere");
+        assert_eq!(serde_json::from_value::<BattleBounds>(json).unwrap(), BattleBounds::Sphere { center: (0.0, 0.0, 0.0), radius: 5.0 });
+    }
+}
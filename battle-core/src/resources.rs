@@ -0,0 +1,108 @@
+// battle-core/src/resources.rs
+//
+// Capturable resource nodes. Each tick the faction with the most units in the
+// fight inside a node's control_radius takes it; the owner earns
+// resource_per_tick for as long as it holds it. An empty node stays with its
+// owner, a tied one is contested - it keeps its owner but produces nothing.
+
+use serde::{Deserialize, Serialize};
+use crate::battle_unit::BattleUnit;
+
+/// A point on the battlefield that produces resources for whoever holds it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceNode {
+    pub id: u32,
+    pub pos_x: f32,
+    pub pos_y: f32,
+    pub pos_z: f32,
+    pub control_radius: f32,
+    pub resource_per_tick: f32,
+    /// Faction holding the node (None until someone captures it)
+    #[serde(default)]
+    pub controlled_by: Option<u32>,
+}
+
+/// What a node did this tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeControl {
+    /// Nobody inside - the owner (if any) keeps it
+    Empty,
+    /// Two or more factions tied for the most units inside
+    Contested,
+    /// This faction has the most units inside
+    Held(u32),
+}
+
+impl ResourceNode {
+    /// Who dominates the node right now
+    pub fn control(&self, units: &[BattleUnit]) -> NodeControl {
+        let radius_sq = self.control_radius * self.control_radius;
+        // Few factions per node - a small vec beats a map
+        let mut counts: Vec<(u32, u32)> = Vec::new();
+        for unit in units.iter().filter(|u| u.in_battle()) {
+            let (dx, dy, dz) = (unit.pos_x - self.pos_x, unit.pos_y - self.pos_y, unit.pos_z - self.pos_z);
+            if dx * dx + dy * dy + dz * dz > radius_sq {
+                continue;
+            }
+            match counts.iter_mut().find(|(faction, _)| *faction == unit.faction_id) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((unit.faction_id, 1)),
+            }
+        }
+
+        let Some(&(leader, most)) = counts.iter().max_by_key(|(_, count)| *count) else {
+            return NodeControl::Empty;
+        };
+        if counts.iter().filter(|(_, count)| *count == most).count() > 1 {
+            NodeControl::Contested
+        } else {
+            NodeControl::Held(leader)
+        }
+    }
+
+    /// Update controlled_by from `units` - returns (faction, amount) earned
+    /// this tick, None when contested or unowned
+    pub fn update(&mut self, units: &[BattleUnit]) -> Option<(u32, f32)> {
+        match self.control(units) {
+            NodeControl::Contested => return None,
+            NodeControl::Held(faction) => self.controlled_by = Some(faction),
+            NodeControl::Empty => {}
+        }
+        self.controlled_by.map(|faction| (faction, self.resource_per_tick))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(id: u32, faction_id: u32, x: f32) -> BattleUnit {
+        BattleUnit::builder().id(id).faction(faction_id).position(x, 0.0, 0.0).build().unwrap()
+    }
+
+    #[test]
+    fn test_capture_hold_and_contest() {
+        let mut node: ResourceNode = serde_json::from_str(
+            r#"{"id": 1, "pos_x": 0, "pos_y": 0, "pos_z": 0, "control_radius": 50, "resource_per_tick": 2.5}"#
+        ).unwrap();
+        assert_eq!(node.controlled_by, None);
+        assert_eq!(node.update(&[]), None);
+
+        // Most units inside wins; units outside the radius don't count
+        let mut units = vec![unit(1, 1, 10.0), unit(2, 1, 20.0), unit(3, 2, 30.0), unit(4, 2, 100.0), unit(5, 2, 120.0)];
+        assert_eq!(node.control(&units), NodeControl::Held(1));
+        assert_eq!(node.update(&units), Some((1, 2.5)));
+
+        // Tied - no income, owner kept
+        units[4].pos_x = 40.0;
+        assert_eq!(node.update(&units), None);
+        assert_eq!(node.controlled_by, Some(1));
+
+        // Left empty - still held
+        assert_eq!(node.update(&[]), Some((1, 2.5)));
+
+        units[0].state = crate::battle_unit::UnitState::Destroyed;
+        assert_eq!(node.update(&units), Some((2, 2.5)));
+        assert_eq!(node.controlled_by, Some(2));
+    }
+}
@@ -0,0 +1,141 @@
+// battle-core/src/schema.rs
+//
+// Units JSON from the host is checked against an embedded JSON Schema before
+// serde sees it, so a bad payload comes back as a list of every field at
+// fault ("/3/weapons/0/dps: ...") instead of serde's first error. The
+// payload-wide rules a schema can't express (unique ids, two factions) run
+// after it.
+//
+// validation.rs still checks the parsed units (non-finite, reserved ids, ...)
+// when they join the battle.
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::OnceLock;
+use jsonschema::Validator;
+use serde_json::Value;
+use crate::battle_unit::BattleUnit;
+use crate::error::BattleError;
+
+/// JSON Schema for a units array - generated from BattleUnit's JsonSchema
+/// derive (see typegen.rs), so it requires exactly the fields serde does
+/// and checks the type of every field it knows, plus dps > 0
+pub const UNITS_SCHEMA: &str = include_str!("../types/battle-units.schema.json");
+
+fn units_validator() -> &'static Validator {
+    static VALIDATOR: OnceLock<Validator> = OnceLock::new();
+    VALIDATOR.get_or_init(|| {
+        let schema: Value = serde_json::from_str(UNITS_SCHEMA).expect("UNITS_SCHEMA is JSON");
+        jsonschema::validator_for(&schema).expect("UNITS_SCHEMA is a valid schema")
+    })
+}
+
+/// Everything wrong with a units payload, as "path: message" - empty when it
+/// parses into units from at least two factions with unique ids
+pub fn units_payload_errors(units: &Value) -> Vec<String> {
+    let mut errors: Vec<String> = units_validator()
+        .iter_errors(units)
+        .map(|e| {
+            let path = e.instance_path().as_str();
+            format!("{}: {}", if path.is_empty() { "/" } else { path }, e)
+        })
+        .collect();
+    // The rules below read ids the schema has vouched for
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    let units = units.as_array().map(Vec::as_slice).unwrap_or_default();
+    let mut ids = HashSet::new();
+    for (i, unit) in units.iter().enumerate() {
+        let id = &unit["id"];
+        if !ids.insert(id.as_u64()) {
+            errors.push(format!("/{}/id: duplicate id {}", i, id));
+        }
+    }
+    let factions: BTreeSet<u64> = units.iter().filter_map(|unit| unit["faction_id"].as_u64()).collect();
+    if factions.len() < 2 {
+        errors.push(format!("/: need units from at least 2 factions, found {:?}", factions));
+    }
+    errors
+}
+
+/// Parse the units argument of a constructor - JSON errors, then schema and
+/// payload rules (BattleError::SchemaValidation), then serde
+pub fn parse_units(units_json: &str) -> Result<Vec<BattleUnit>, BattleError> {
+    let value: Value = serde_json::from_str(units_json)
+        .map_err(BattleError::parse("units"))?;
+    let errors = units_payload_errors(&value);
+    if !errors.is_empty() {
+        return Err(BattleError::SchemaValidation { errors });
+    }
+    serde_json::from_value(value)
+        .map_err(BattleError::parse("units"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::Weapon;
+
+    fn unit_json(id: u32, faction: u32) -> Value {
+        let unit = BattleUnit::builder()
+            .id(id)
+            .faction(faction)
+            .weapons(vec![Weapon::builder().tag("LASER").dps(10.0).build()])
+            .build()
+            .unwrap();
+        serde_json::to_value(unit).unwrap()
+    }
+
+    #[test]
+    fn test_valid_payload_parses() {
+        let json = Value::Array(vec![unit_json(1, 1), unit_json(2, 2)]).to_string();
+        assert!(units_payload_errors(&serde_json::from_str(&json).unwrap()).is_empty());
+        assert_eq!(parse_units(&json).unwrap().len(), 2);
+
+        // Only the required fields
+        let minimal = r#"[
+            { "id": 1, "faction_id": 1, "max_hp": 100, "hp": 100, "max_shield": 0, "shield": 0, "armor": "light",
+              "shield_regen": 0, "pos_x": 0, "pos_y": 0, "pos_z": 0, "vel_x": 0, "vel_y": 0, "vel_z": 0, "max_speed": 10,
+              "weapons": [{ "tag": "LASER", "dps": 5, "fire_rate": 1, "cooldown": 1, "max_range": 100,
+                            "optimal_range": 80, "target_armor_max": 4, "last_fired": 0 }],
+              "max_weapon_range": 100, "damage_dealt": 0, "damage_taken": 0 },
+            { "id": 2, "faction_id": 2, "max_hp": 100, "hp": 100, "max_shield": 0, "shield": 0, "armor": 0,
+              "shield_regen": 0, "pos_x": 50, "pos_y": 0, "pos_z": 0, "vel_x": 0, "vel_y": 0, "vel_z": 0, "max_speed": 10,
+              "weapons": [], "max_weapon_range": 0, "damage_dealt": 0, "damage_taken": 0 }
+        ]"#;
+        assert_eq!(parse_units(minimal).unwrap()[0].weapons[0].tag, "LASER");
+    }
+
+    #[test]
+    fn test_every_invalid_field_listed() {
+        let mut bad = unit_json(1, 1);
+        bad["hp"] = Value::from("full");
+        bad["weapons"][0]["dps"] = Value::from(0.0);
+        bad.as_object_mut().unwrap().remove("pos_z");
+        let payload = Value::Array(vec![bad, unit_json(2, 2)]);
+
+        let errors = units_payload_errors(&payload);
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        for path in ["/0/hp: ", "/0/weapons/0/dps: ", "/0: "] {
+            assert!(errors.iter().any(|e| e.starts_with(path)), "no {} in {:?}", path, errors);
+        }
+        assert!(errors.iter().any(|e| e.contains("pos_z")));
+
+        match parse_units(&payload.to_string()) {
+            Err(BattleError::SchemaValidation { errors: listed }) => assert_eq!(listed, errors),
+            other => panic!("expected SchemaValidation, got {:?}", other),
+        }
+        assert!(matches!(parse_units("[1,"), Err(BattleError::JsonParse { .. })));
+        assert_eq!(units_payload_errors(&Value::from(5)).len(), 1);
+    }
+
+    #[test]
+    fn test_payload_rules() {
+        let errors = units_payload_errors(&Value::Array(vec![unit_json(1, 1), unit_json(1, 2), unit_json(3, 2)]));
+        assert_eq!(errors, vec!["/1/id: duplicate id 1".to_string()]);
+
+        let errors = units_payload_errors(&Value::Array(vec![unit_json(1, 1), unit_json(2, 1)]));
+        assert_eq!(errors, vec!["/: need units from at least 2 factions, found {1}".to_string()]);
+    }
+}
@@ -14,49 +14,293 @@
 //     - Tracks last_movement_tick and next_weapon_ready_time
 //     - When idle: only does shield regen, skips targeting/weapons/spatial grid
 //     - Wakes automatically when movement received or weapon cooldown expires
+// 11. Added retreat behavior - units below retreat_hp_fraction disengage and withdraw
+//     - BattleConfig.ai_movement lets the simulator move retreating units itself
+//     - Withdrawn units leave the battle without counting as destroyed
+// 12. Added waypoint navigation - units with waypoints are moved by the simulator
+// 13. Added opt-in AI movement - ai_controlled units close to optimal range
+//     of their target each tick and are reported in TickResult.moved
+// 14. Retreating units fire defensively at enemies in weapon range, flee to
+//     retreat_target when set, and rejoin the fight if hull recovers
+// 15. Added collision separation - simulator-moved units are pushed apart by
+//     radius after movement; stations and player-synced units never move
+// 17. Added ally separation steering (collision avoidance) for simulator-moved units
+// 18. Added retarget hysteresis - periodic re-evaluation keeps a valid target
+//     unless a candidate is higher priority or retarget_switch_margin closer
+// 19. Spatial grid cell size auto-tuned to unit density at construction,
+//     optionally re-tuned every config.grid_tune_interval ticks (auto_tune_grid)
+// 16. Added weapon ammo and magazine reloads - shots spend ammo when committed
+// 20. Generic over SpatialIndex (SpatialGrid by default, Octree for sparse fields)
+// 21. Target lock - weapons hold fire for lock_time after target_id changes
+// 22. Target validity and fallback search use max_offensive_range (ignores point defense)
+// 23. Status effects - weapon hits attach debuffs, burns tick through the damage
+//     queue, changed effect lists are reported in TickResult.effects
+// 24. `parallel` feature - weapon-fire collection and damage application run on
+//     rayon; damage is summed into dense per-unit vecs (results in unit order)
+// 25. Optional battlefield bounds (BattleConfig.bounds) - external positions
+//     are clamped, simulator moves stay inside, retreating into the edge withdraws
+// 26. Targeting split into a read phase (choose_target, parallel under the
+//     `parallel` feature) and a serial write phase
+// 27. Per-weapon independent targeting - flagged weapons shoot the nearest valid
+//     enemy in their own range instead of the unit's primary target
+// 28. Per-tick vectors live in TickBuffers and are reused; output vectors come
+//     back through recycle_result() so steady-state ticks don't allocate
+// 29. Repair weapons - heal the most-damaged ally in range via a repair queue
+//     applied after damage; reported in TickResult.repaired
+// 30. Added add_units() batch insert and remove_unit() admin removal
+// 31. Added pause() / resume() - paused ticks return empty results and don't
+//     advance the tick counter
+// 32. MovedUnit carries velocity (vx/vy/vz); optional periodic position
+//     keyframes via emit_all_positions_every_n_ticks
+// 33. Added get_unit() / get_units_by_faction() borrowing lookups
+// 34. Faction alliances (FactionRelations) - allied factions don't target each
+//     other and the battle ends when only allies remain
+// 35. Added query_units_in_radius() for AoE / proximity queries
+// 36. Added run_to_completion() - auto-resolve in one call, reporting winner,
+//     reason, survivors and per-faction losses (faction_summary())
+// 37. Added manually_fire() - host-triggered shots bypass targeting and are
+//     committed (damage, on-hit effects, weaponsFired) in the next tick
+// 38. FIXED: overkill - attackers are credited in queue order only for damage the
+//     target actually absorbed; the surplus is reported in TickResult.overkill
+// 39. Added lock_target() / unlock_target() - locked units keep their target
+//     through retargeting, position updates and force_retarget until it dies
+// 40. Energy - shots are paid from the unit's capacitor (cheapest weapons first
+//     when it can't cover them all), capacitors recharge each tick, optional
+//     shield_regen_uses_energy; changed levels reported in TickResult.energy
+// 41. Damage from an allied source (e.g. a burn applied before the alliance) is
+//     dropped from the queue; added are_allies()
+// 42. Per-unit shots_fired / shots_hit / kills; get_unit_stats() / get_faction_stats()
+// 43. Weapon fire (not only landed damage) resets the stalemate timer; added
+//     ticks_since_combat() / stalemate_threshold()
+// 44. Combat log - typed events (fires, damage, destroys, target changes, shield
+//     breaks) kept in a bounded ring buffer (combat_log.rs)
+// 45. Class-based target priorities (BattleConfig.priority_table)
+// 46. Replay recording (replay.rs) - start_recording() / export_replay(), and
+//     from_replay() / step_replay() to play it back tick for tick
+// 47. BattleConfig.mode - deterministic battles reseed a ChaCha8 RNG from
+//     seed ^ tick each tick (rng.rs); with_config() applies it before normalize
+// 48. Damage entries carry the weapon's shield_pierce / shield_damage_bonus and
+//     are summed per target as a DamageSplit
+// 49. Scheduled reinforcements - waves join at their arrival tick, nearby
+//     enemies re-evaluate targets straight away, ids reported in TickResult.spawned;
+//     the battle doesn't end while waves are still on the way
+// 50. Added simulate_tick_delta() - drops moves under config.position_epsilon
+//     and damage that left hp/shield as last reported (DeltaTickResult)
+// 51. Hit chance (weapons::hit_chance) rolled from the simulator RNG when shots
+//     are committed - misses spend the shot and are reported with hit: false;
+//     external position updates record the speed they imply (external_speed)
+// 52. Morale - allied deaths nearby drain it, commanders and regen restore it;
+//     changes reported in TickResult.morale_events
+// 53. Simulated clock - weapon timing runs on host time minus the time spent
+//     paused, so resuming doesn't bring every weapon off cooldown at once;
+//     added step() (one config.fixed_dt tick, paused or not)
+// 54. Veterancy - attackers earn experience for damage credited and kills;
+//     level-ups reported in TickResult.level_ups
+// 55. Per-weapon stats (shots, hits, damage, kills) - get_weapon_stats(),
+//     weapon_totals() and CompletionReport.weapons; DamageEntry.is_shot
+//     replaced by weapon_idx
+// 56. Carriers - launch_fighter() / dock_fighter(); destroyed or removed
+//     carriers launch everything docked; TickResult.launched / docked
+// 57. Liveness goes through BattleUnit.state - removed units are withdrawn
+//     rather than dead; TickResult.withdrawn lists every unit that left
+//     without being destroyed (retreats and removals)
+// 58. Resupply - supply units top up the most depleted ally in range each
+//     tick (process_resupply); get_weapon_ammo()
+// 59. Unit validation (validation.rs) - try_new / try_with_config, and
+//     add_unit / add_units / schedule_reinforcements reject bad units
+// 60. Surrender - units under their surrender_hp_threshold stop, drop their
+//     target and leave the fight (no longer targeted or counted as active);
+//     reported in TickResult.surrendered, listed by get_surrendered_units()
+// 61. Disabled units - below disable_threshold a unit shuts down (only siege
+//     weapons may still shoot it) and self-repairs once no enemy ship has been
+//     within config.disabled_repair_radius for disabled_repair_delay_ticks;
+//     TickResult.disabled / reactivated. The grid indexes every unit on the
+//     battlefield, disabled and surrendered ones included
+// 62. Per-class weapon restrictions (set_class_weapon_restrictions) - weapons
+//     a unit's ShipClass may not carry are disabled (and logged) when it's
+//     added, arrives as a reinforcement, or the restriction is set
+// 63. Drone hangars - carriers with a DroneHangar build AI drones from its
+//     template (ids from DRONE_ID_BASE up) while enemies are in view and
+//     recall them after recover_after_ticks without; new drones are reported
+//     in TickResult.spawned, relaunches / recalls in launched / docked
+// 64. Objectives (objectives.rs) - set_objectives() gives factions win
+//     conditions checked each tick after combat; the first completed one ends
+//     the battle and decides the winner (TickResult.objectives_met,
+//     completed_objectives()). Without objectives elimination still applies
+// 65. Retreating units pick return-fire targets with find_best_target's
+//     priorities (targeting::find_enemy_in_range); the unscored nearest-enemy
+//     fallback after find_best_target is gone - it only ever added targets
+//     the priority rules reject
+// 66. Resource nodes (resources.rs) - each tick the faction with the most units
+//     inside a node's radius takes it and earns resource_per_tick into
+//     faction_resources; TickResult.resources_gained
+// 67. Move orders - issue_move_order() / issue_move_orders() send a unit to a
+//     point at max_speed (inside bounds, reported in TickResult.moved);
+//     cleared on arrival, by clear_orders() or by an external position update
+// 68. Reinforcement waves can name the faction they fight for and a spawn
+//     point their units are spread around (schedule_wave());
+//     TickResult.reinforcements_arrived groups arrivals by faction
+// 69. Groups (BattleUnit.group_id) - assign_group(), set_group_target() /
+//     set_group_stance() / clear_group(); members take the group's focus
+//     target whenever it's in range. Indexed per group, dead members pruned
+//     each tick. Stances: defensive units aren't steered at their target,
+//     hold_fire units only fire manually
+// 70. Stalemate threshold is config.stalemate_ticks (set_stalemate_threshold());
+//     TickResult.stalemate_warning once half of it has passed without combat
+// 71. grid_stats() / debug_query_nearby() - spatial grid diagnostics
+// 72. WeaponFired carries attacker / target positions at fire time (ax..tz)
+//     and, for misses, missOffset - config.include_fire_positions
+// 73. Hot per-unit fields mirrored in UnitColumns (struct of arrays) - the
+//     grid rebuild and target lookups by id read those instead of the records
+// 74. Tunables live in config::BattleConfig (was SimulatorConfig) - the old
+//     consts are its defaults; new_with_config(). fixed_dt became tick_rate.
+//     config.target_spread_factor spreads fire over similar targets,
+//     config.parallel_threshold keeps small battles serial
+// 75. surrender_faction() - a whole faction withdraws, its pending
+//     reinforcements are dropped (CombatLogEntry::FactionSurrendered)
+// 76. Per-faction FactionConfig (set_faction_config) - damage, shield regen
+//     and speed multipliers, faction-exclusive special weapons
+// 77. Targets stay valid out to view_range (the radius find_best_target
+//     searches), not just weapon range - a target acquired at view range was
+//     dropped again the next tick and re-acquired forever
+// 78. Unit templates (templates.rs) - register_template() once, then
+//     add_unit_from_template() by id, faction and position
+// 79. Per-phase tick timing with config.profiling (profiling.rs,
+//     profile_stats())
+// 80. render_ascii() - X/Z debug map of the battlefield (debug builds only)
+// 81. config.victory (objectives::VictoryCondition) - checked each tick after
+//     damage alongside objectives; once met the battle ends and it decides
+//     get_winner() (victory_state(), CompletionReason::Victory)
+// 82. Weapon categories (WeaponTagRegistry) are set as units join;
+//     register_weapon_tag_prefix() re-categorizes units already in the battle
+// 83. Grid queries in the tick write into TickBuffers scratch (in_range,
+//     nearby, movable) via query_range_into / get_nearby_into; pending
+//     fires no longer carry a copy of the weapon tag
+// 84. Reactor power - set_unit_power(); ai_controlled units rebalance
+//     (auto_balance_power) each tick after targeting, favouring weapons
+//     once their target is in weapon range
+// 85. Guided shots whose target dies before they land turn on the nearest
+//     enemy within config.missile_reacquire_radius of it (landing next
+//     tick) or fizzle (TickResult.missiles_retargeted / missiles_fizzled);
+//     config.refund_fizzled_ammo gives the ammo back
+// 86. DamagedUnit breaks the tick's damage down - hull_damage / shield_damage,
+//     primary_attacker_id (most damage this tick) and damage_type (weapon
+//     category of most hits, "burn" for status effects)
+// 87. Durations in BattleConfig are seconds (retarget_interval_secs,
+//     stalemate_secs, ...) turned into ticks for config.tick_rate whenever the
+//     config is applied, so a 10 or 30 Hz battle keeps the same timings; the
+//     *_ticks fields became optional exact overrides. The once-a-second debug
+//     logs follow the tick rate too. Counts the host gives in ticks
+//     (objectives, hangar recover_after_ticks) stay ticks
+// 88. TickResult.version (TICK_RESULT_VERSION) and tick_duration_us - compute
+//     time of the tick, logged as a warning past SLOW_TICK_US;
+//     average_tick_duration_us() over the last TICK_DURATION_WINDOW ticks
+// 89. Self-destructs - trigger_self_destruct() or an enemy inside a charged
+//     unit's self_destruct_trigger_radius blows it up at the start of the
+//     combat phase; the blast damages everything in self_destruct_radius
+//     (allies too unless config.self_destruct_friendly_fire is off) with
+//     kills credited to the unit, and sets off charged units it catches up
+//     to config.self_destruct_chain_limit links (TickResult.self_destructed)
+// 90. simulate_ticks() / simulate_until_end() - fast-forward on the host
+//     clock, capped at config.max_fast_forward_ticks; the latter reports a
+//     compact BattleOutcome
+// 91. Detection - units only target enemies their faction has on sensors
+//     (sensor_range() scaled by the target's stealth_modifier, or a contact
+//     that keeps firing), worked out at the start of targeting; is_target_valid,
+//     find_best_target, the retreat search and independent weapons all check
+//     it. TickResult.contacts_detected / contacts_lost
+// 92. snapshot() / restore() - roll the battle back to a saved BattleSnapshot
+//     (lag compensation); initial_snapshot() is taken at construction
+// 93. Replays carry the config (seed included) and alliances, and record player
+//     commands (ReplayCommand) - locks, orders, groups, removals, alliance
+//     changes; config.record_replay records from construction
+// 94. Point defense intercepts missiles - guided shots fired at a unit in the
+//     same tick arrive together, and each rolls against the best covering
+//     own / allied PD weapon's intercept_chance for that many; intercepted
+//     shots are reported with hit: false, intercepted: true (manual shots
+//     aren't intercepted)
+// 95. Recalled drones are despawned - withdrawn, listed in TickResult.destroyed
+//     and added back to their hangar's count - instead of docked
+// 96. UnitColumns own target ids, positions and alive flags during the tick:
+//     targeting, movement and damage write the columns, which are flushed to
+//     the records after movement (positions) and damage (targets) instead of
+//     being re-synced from them four times a tick
+// 97. BattleBounds is externally tagged in binary formats, so a replay
+//     recorded with bounds imports again
+// 98. config.victory is checked by the objectives pass - its leaves are
+//     BattleObjectives, and the first objective or condition met is the one
+//     VictoryState that ends the battle and names the winner (reported as
+//     CompletionReason::Objective; CompletionReason::Victory is gone)
+// 99. set_config() refuses a config whose tick_rate isn't finite and above 0
+// 100. run_to_completion() and simulate_until_end() share one loop
+//      (run_until_end) - only their clocks differ
+// 101. BattleSnapshot also keeps the idle counters, objective progress and
+//      completions, victory state and group targets, so restore() can roll
+//      back past the end of the battle
+// 102. Recovered drones are listed in TickResult.withdrawn, not destroyed,
+//      and the next launch reuses their records instead of adding a unit
+// 103. Replays record every host input that changes the battle (config,
+//      faction config, objectives, resource nodes, hangar launches / docks,
+//      power, retreat / orbit settings, retargets, weapon restrictions and
+//      tag prefixes), and only a deterministic battle can be recorded
+// 104. UnitColumns are kept in step by the entry points that change units
+//      instead of being re-synced from every record at the start of each
+//      tick; `units` is crate-private, edit_units() changes records directly
+// 105. UnitColumns are gone - the tick loop reads and writes the records
+//      again (the column copy saved ~0.13 ms of a 3.5 ms tick at 5000 units),
+//      and `units` is public again
+// 106. BattleSnapshot also keeps pending reinforcement waves, resource node
+//      owners and faction_resources, so restore() replays waves and income
+// 107. BattleUnit.alive / withdrawn are derived from state when units are
+//      written as JSON, not stored (see battle_unit.rs)
 
-use crate::spatial_grid::SpatialGrid;
-use crate::battle_unit::BattleUnit;
-use crate::targeting::find_best_target;
-use crate::weapons::{try_fire_weapon, is_point_defense};
-use crate::log;
-use crate::PositionUpdate;
-use std::collections::HashMap;
+use crate::spatial_grid::{GridStats, SpatialGrid, DEFAULT_CELL_SIZE};
+use crate::config::{BattleConfig, ConfigError, TickCounts};
+use crate::spatial_index::SpatialIndex;
+use crate::factions::{self, FactionConfig, FactionConfigs, FactionRelations};
+use crate::detection::{Detection, SensorContact};
+use crate::objectives::{BattleObjective, ObjectiveProgress, VictoryCondition, VictoryState};
+use crate::resources::ResourceNode;
+use crate::templates::{TemplateError, UnitTemplate};
+use crate::profiling::{now_ms, Phase, PhaseProfiler, PhaseTimer, ProfileStats, TickDurations, SLOW_TICK_US};
+#[cfg(debug_assertions)]
+use crate::debug_map::{self, DEFAULT_DEBUG_MAP_SCALE};
+use crate::combat_log::{CombatLog, CombatLogEntry};
+use crate::replay::{ReplayCommand, ReplayRecorder, TickInput};
+use crate::rng::{BattleRng, SimulationMode};
+use crate::battle_unit::{BattleUnit, CombatStats, DamageSplit, ShipClass, Stance, UnitState, DRONE_ID_BASE, Veterancy, Weapon, WeaponAmmo, WeaponStats, MAX_MORALE, MORALE_BROKEN, PowerError};
+use crate::targeting::{find_best_repair_target, find_best_target, find_enemy_in_range, find_resupply_target, find_weapon_target, PriorityTable};
+use crate::weapons::{
+    try_fire_weapon, try_repair, shot_damage, hit_chance, intercept_chance, is_guided, tag_contains, tag_starts_with,
+    disable_restricted_weapons, WeaponClassRestrictions, WeaponCategory, WeaponTagRegistry,
+};
+use crate::movement::{separation_force, update_movement, update_retreat};
+use crate::status_effect::{StatusEffect, StatusEffectKind};
+use crate::validation::{validate_units, ValidationError};
+use crate::log_at;
+use crate::{GroupAssignment, MoveOrder, PositionUpdate};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
 use serde::{Deserialize, Serialize};
 
-/// How often to re-evaluate targets (in ticks)
-/// 20 ticks = 1 second at 20 ticks/sec
-const RETARGET_INTERVAL: u64 = 20;
-
-/// Distance threshold for considering a position change "significant"
-/// If a unit moves more than this, clear its target to re-evaluate
-const SIGNIFICANT_MOVEMENT_THRESHOLD: f32 = 10.0;
-
-/// How many ticks without combat before declaring stalemate
-/// 1200 ticks = 60 seconds at 20 ticks/sec
-const STALEMATE_TICKS: u64 = 1200;
-
-/// ✅ NEW: How many ticks after movement before entering idle mode
-/// 40 ticks = 2 seconds buffer after last movement
-const IDLE_MOVEMENT_THRESHOLD: u64 = 40;
-
 /// Get projectile speed for a weapon type (units per second)
-fn get_projectile_speed(weapon_tag: &str) -> f32 {
-    let tag_lower = weapon_tag.to_lowercase();
-    
-    if tag_lower.contains("laser") || tag_lower.contains("ion") || tag_lower.contains("beam") {
+fn get_projectile_speed(tag: &str) -> f32 {
+    if tag_contains(tag, "laser") || tag_contains(tag, "ion") || tag_contains(tag, "beam") {
         return f32::INFINITY;
     }
     
-    if tag_lower.contains("missile") || tag_lower.starts_with("hm") || tag_lower.starts_with("sm") {
+    if tag_contains(tag, "missile") || tag_starts_with(tag, "hm") || tag_starts_with(tag, "sm") {
         return 50.0;
     }
     
-    if tag_lower.contains("rocket") || tag_lower.starts_with("pr") || tag_lower.starts_with("cr") {
+    if tag_contains(tag, "rocket") || tag_starts_with(tag, "pr") || tag_starts_with(tag, "cr") {
         return 80.0;
     }
     
-    if tag_lower.contains("nuke") || tag_lower.starts_with("nm") {
+    if tag_contains(tag, "nuke") || tag_starts_with(tag, "nm") {
         return 30.0;
     }
     
@@ -73,12 +317,420 @@ fn calculate_impact_time(distance: f32, weapon_tag: &str) -> u32 {
     }
 }
 
+/// Battlefield bounds - an axis-aligned box or a sphere
+///
+/// JSON: { "type": "box", "min": [x, y, z], "max": [x, y, z] }
+///    or { "type": "sphere", "center": [x, y, z], "radius": r }
+/// Binary formats (replays) write the variant index instead - bincode can't
+/// read an internal tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BattleBounds {
+    Box { min: (f32, f32, f32), max: (f32, f32, f32) },
+    Sphere { center: (f32, f32, f32), radius: f32 },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "BattleBounds", tag = "type", rename_all = "snake_case")]
+enum TaggedBounds {
+    Box { min: (f32, f32, f32), max: (f32, f32, f32) },
+    Sphere { center: (f32, f32, f32), radius: f32 },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "BattleBounds")]
+enum IndexedBounds {
+    Box { min: (f32, f32, f32), max: (f32, f32, f32) },
+    Sphere { center: (f32, f32, f32), radius: f32 },
+}
+
+impl Serialize for BattleBounds {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            TaggedBounds::serialize(self, serializer)
+        } else {
+            IndexedBounds::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BattleBounds {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            TaggedBounds::deserialize(deserializer)
+        } else {
+            IndexedBounds::deserialize(deserializer)
+        }
+    }
+}
+
+impl BattleBounds {
+    pub fn contains(&self, x: f32, y: f32, z: f32) -> bool {
+        match *self {
+            BattleBounds::Box { min, max } => {
+                (min.0..=max.0).contains(&x) && (min.1..=max.1).contains(&y) && (min.2..=max.2).contains(&z)
+            }
+            BattleBounds::Sphere { center, radius } => {
+                let (dx, dy, dz) = (x - center.0, y - center.1, z - center.2);
+                dx * dx + dy * dy + dz * dz <= radius * radius
+            }
+        }
+    }
+
+    /// Nearest point inside the bounds (non-finite coordinates snap to the center)
+    pub fn clamp(&self, x: f32, y: f32, z: f32) -> (f32, f32, f32) {
+        match *self {
+            BattleBounds::Box { min, max } => {
+                let axis = |v: f32, lo: f32, hi: f32| if v.is_nan() { (lo + hi) * 0.5 } else { v.clamp(lo, hi) };
+                (axis(x, min.0, max.0), axis(y, min.1, max.1), axis(z, min.2, max.2))
+            }
+            BattleBounds::Sphere { center, radius } => {
+                let finite = |v: f32, c: f32| if v.is_finite() { v } else { c };
+                let (x, y, z) = (finite(x, center.0), finite(y, center.1), finite(z, center.2));
+                // f64 so 1e30-ish coordinates don't overflow when squared
+                let (dx, dy, dz) = ((x - center.0) as f64, (y - center.1) as f64, (z - center.2) as f64);
+                let dist = (dx * dx + dy * dy + dz * dz).sqrt();
+                if dist <= radius as f64 {
+                    return (x, y, z);
+                }
+                let scale = radius as f64 / dist;
+                (
+                    center.0 + (dx * scale) as f32,
+                    center.1 + (dy * scale) as f32,
+                    center.2 + (dz * scale) as f32,
+                )
+            }
+        }
+    }
+}
+
+/// Unit indices per group_id
+fn group_index(units: &[BattleUnit]) -> HashMap<u32, Vec<usize>> {
+    let mut groups: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (idx, unit) in units.iter().enumerate() {
+        if let Some(group_id) = unit.group_id {
+            groups.entry(group_id).or_default().push(idx);
+        }
+    }
+    groups
+}
+
+/// Radius the spatial grid is tuned for - config.battlefield_radius, or the
+/// units' actual spread when that's 0
+fn tuning_radius(config: &BattleConfig, units: &[BattleUnit]) -> f32 {
+    if config.battlefield_radius > 0.0 {
+        config.battlefield_radius
+    } else {
+        battlefield_radius(units)
+    }
+}
+
+/// Radius of the sphere around the centroid that contains every unit in battle
+fn battlefield_radius(units: &[BattleUnit]) -> f32 {
+    let present = units.iter().filter(|u| u.in_battle());
+    let count = present.clone().count();
+    if count == 0 {
+        return 0.0;
+    }
+
+    let (sx, sy, sz) = present.clone()
+        .fold((0.0f32, 0.0f32, 0.0f32), |(x, y, z), u| (x + u.pos_x, y + u.pos_y, z + u.pos_z));
+    let (cx, cy, cz) = (sx / count as f32, sy / count as f32, sz / count as f32);
+
+    present
+        .map(|u| {
+            let dx = u.pos_x - cx;
+            let dy = u.pos_y - cy;
+            let dz = u.pos_z - cz;
+            (dx * dx + dy * dy + dz * dz).sqrt()
+        })
+        .fold(0.0f32, f32::max)
+}
+
+/// A shot that will be committed this tick:
+/// (attacker_idx, target_idx, damage, weapon_idx, distance, hit_chance)
+/// For repair weapons target_idx is an ally and damage is the repair amount.
+type PendingFire = (usize, usize, f32, usize, f32, f32);
+
+/// Bookkeeping from the read-only fire collection for one attacker
+#[derive(Debug, Clone, Copy, Default)]
+struct AttackerFires {
+    has_target: bool,
+    target_lost: bool,      // Target dead or gone - clear it so the unit can retarget
+    weapons_checked: usize,
+}
+
+/// Weapons of one attacker that can fire at its current target this tick
+///
+/// Read-only over the unit list so it can run on many attackers at once.
+/// Shots are appended to `fires`; `in_range` is scratch for grid queries.
+#[allow(clippy::too_many_arguments)]
+fn collect_weapon_fires(
+    units: &[BattleUnit],
+    grid: &impl SpatialIndex,
+    detection: &Detection,
+    relations: &FactionRelations,
+    priorities: &PriorityTable,
+    restrictions: &WeaponClassRestrictions,
+    faction_configs: &FactionConfigs,
+    attacker_idx: usize,
+    current_time: f64,
+    tick: u64,
+    fires: &mut Vec<PendingFire>,
+    in_range: &mut Vec<(usize, f32)>,
+) -> AttackerFires {
+    let mut collected = AttackerFires::default();
+    let attacker = &units[attacker_idx];
+    if !attacker.in_battle() || !attacker.has_weapons {
+        return collected;
+    }
+
+    // Resolve the unit's primary target (used by every weapon without independent_targeting)
+    let primary_idx = match attacker.target_id {
+        Some(target_id) => {
+            collected.has_target = true;
+            let found = units.iter().position(|u| u.id == target_id && u.is_alive());
+            collected.target_lost = found.is_none();
+            found
+        }
+        None => None,
+    };
+
+    let has_independent = attacker.weapons.iter().any(|w| w.independent_targeting || w.is_repair);
+    if primary_idx.is_none() && !has_independent {
+        return collected;
+    }
+    let first_fire = fires.len();
+
+    // Check each weapon
+    for (weapon_idx, weapon) in attacker.weapons.iter().enumerate() {
+        collected.weapons_checked += 1;
+
+        if weapon.category == WeaponCategory::PointDefense {
+            continue;
+        }
+
+        if weapon.is_repair {
+            if weapon.ready_time() > current_time {
+                continue;
+            }
+            let Some(ally_idx) = find_best_repair_target(attacker, weapon, units, grid, relations, in_range) else {
+                continue;
+            };
+            let ally = &units[ally_idx];
+            if let Some(amount) = try_repair(attacker, ally, weapon, current_time, tick) {
+                fires.push((
+                    attacker_idx,
+                    ally_idx,
+                    amount,
+                    weapon_idx,
+                    attacker.distance(ally),
+                    1.0
+                ));
+            }
+            continue;
+        }
+
+        if attacker.stance == Stance::HoldFire {
+            continue;
+        }
+
+        let target_idx = if weapon.independent_targeting {
+            // Only search when the weapon could actually fire this tick
+            if weapon.ready_time() > current_time {
+                continue;
+            }
+            find_weapon_target(attacker, weapon, units, grid, detection.detected_by(attacker.faction_id), relations, priorities, in_range)
+        } else {
+            primary_idx
+        };
+        let Some(target_idx) = target_idx else {
+            continue;
+        };
+        let target = &units[target_idx];
+
+        if let Some((damage, hit_chance)) = try_fire_weapon(attacker, target, weapon, current_time, tick, restrictions, faction_configs) {
+            let distance = attacker.distance(target);
+            fires.push((
+                attacker_idx,
+                target_idx,
+                damage,
+                weapon_idx,
+                distance,
+                hit_chance
+            ));
+        }
+    }
+
+    // Each shot was checked against the full capacitor - if it can't pay for all
+    // of them, fire the cheapest first and drop what no longer fits
+    if attacker.max_energy > 0.0 {
+        let cost = |fire: &PendingFire| attacker.weapons[fire.3].energy_cost;
+        let shots = &mut fires[first_fire..];
+        if shots.iter().map(cost).sum::<f32>() > attacker.energy {
+            shots.sort_unstable_by(|a, b| cost(a).total_cmp(&cost(b)).then(a.3.cmp(&b.3)));
+            let mut budget = attacker.energy;
+            let affordable = shots.iter()
+                .take_while(|fire| {
+                    budget -= cost(fire);
+                    budget >= 0.0
+                })
+                .count();
+            fires.truncate(first_fire + affordable);
+        }
+    }
+
+    collected
+}
+
+/// Vectors reused across ticks so a steady-state tick doesn't allocate
+///
+/// Scratch vectors are cleared (capacity kept) as they're used. The output
+/// vectors are handed out in TickResult and come back through
+/// BattleSimulator::recycle_result.
+#[derive(Debug, Default)]
+struct TickBuffers {
+    damage_entries: Vec<DamageEntry>,
+    repair_entries: Vec<RepairEntry>,
+    weapon_fires: Vec<PendingFire>,
+    weapons_fired: Vec<WeaponFired>,
+    moved: Vec<MovedUnit>,
+    damaged: Vec<DamagedUnit>,
+    destroyed: Vec<u32>,
+    overkill: Vec<DestroyedUnit>,
+    repaired: Vec<RepairedUnit>,
+    energy: Vec<UnitEnergy>,
+    spawned: Vec<u32>,
+    arrived: Vec<(u32, Vec<u32>)>,
+    morale_events: Vec<(u32, f32)>,
+    level_ups: Vec<(u32, u8)>,
+    contacts_detected: Vec<SensorContact>,
+    contacts_lost: Vec<SensorContact>,
+    /// (faction, enemies not yet spotted) during update_detection
+    unseen_enemies: Vec<(u32, usize)>,
+    /// Capacitor level last reported per unit (kept across ticks)
+    last_energy: Vec<f32>,
+    // Scratch only
+    repaired_idx: Vec<usize>,
+    moved_ids: Vec<u32>,
+    retargets: Vec<(usize, Option<u32>)>,
+    steered: Vec<(usize, f32, f32, f32)>,
+    fire_stats: Vec<AttackerFires>,
+    effects_changed: Vec<usize>,
+    hits_by_target: Vec<DamageSplit>,
+    damage_by_target: Vec<f32>,
+    dealt_by_attacker: Vec<f32>,
+    outcomes: Vec<DamageOutcome>,
+    /// (target_idx, attacker id, category - None for burns, damage) per entry
+    hits: Vec<(usize, Option<u32>, Option<WeaponCategory>, f32)>,
+    /// (primary attacker id, damage type) per unit, from `hits`
+    attribution: Vec<(Option<u32>, &'static str)>,
+    /// Guided shots that landed on a target already dead
+    stray_shots: Vec<DamageEntry>,
+    /// Grid query results (query_range_into / get_nearby_into)
+    in_range: Vec<(usize, f32)>,
+    nearby: Vec<usize>,
+    movable: Vec<bool>,
+    /// Guided shots at each unit this tick, while any point defense is up
+    inbound_missiles: Vec<u32>,
+}
+
+/// Take a reused output vector back if it has more room than the current one
+fn reclaim<T>(slot: &mut Vec<T>, mut returned: Vec<T>) {
+    if returned.capacity() > slot.capacity() {
+        returned.clear();
+        *slot = returned;
+    }
+}
+
+/// What happened to a unit that took damage this tick
+#[derive(Debug)]
+struct DamageOutcome {
+    idx: usize,
+    id: u32,
+    destroyed: bool,
+    shield_broken: bool,    // Shield was up before this tick's damage and is down now
+    overkill: f32,
+    hp: f32,
+    shield: f32,
+    hull_damage: f32,
+    shield_damage: f32,
+}
+
+/// Apply a tick's summed damage to one unit
+///
+/// `absorbed` is set to the part the unit actually absorbed (less than the
+/// sum when it died part-way through).
+#[inline]
+fn apply_damage(idx: usize, unit: &mut BattleUnit, hit: &DamageSplit, absorbed: &mut f32) -> Option<DamageOutcome> {
+    *absorbed = 0.0;
+    let damage = hit.total();
+    // Units that blew themselves up earlier in the tick are past caring
+    if damage <= 0.0 || !unit.is_alive() {
+        return None;
+    }
+    let (hp_before, shield_before) = (unit.hp, unit.shield);
+    *absorbed = unit.take_damage_split(*hit);
+    let overkill = damage - *absorbed;
+    Some(DamageOutcome {
+        idx,
+        id: unit.id,
+        destroyed: !unit.is_alive(),
+        shield_broken: shield_before > 0.0 && unit.shield <= 0.0,
+        overkill,
+        hp: unit.hp,
+        shield: unit.shield,
+        hull_damage: (hp_before - unit.hp).max(0.0),
+        shield_damage: (shield_before - unit.shield).max(0.0),
+    })
+}
+
+/// Who hit each target hardest and with what, from a tick's hits
+///
+/// `hits` is sorted in place. The primary attacker is the one whose hits add
+/// up to the most damage; the damage type is the category with the most hits
+/// (ties go to the one that did more damage), "burn" for status effects.
+fn attribute_hits(hits: &mut [(usize, Option<u32>, Option<WeaponCategory>, f32)], attribution: &mut [(Option<u32>, &'static str)]) {
+    const BURN: usize = WeaponCategory::ALL.len();
+    hits.sort_unstable_by_key(|&(target_idx, attacker_id, _, _)| (target_idx, attacker_id));
+    for target_hits in hits.chunk_by(|a, b| a.0 == b.0) {
+        let mut primary: Option<(Option<u32>, f32)> = None;
+        for attacker_hits in target_hits.chunk_by(|a, b| a.1 == b.1) {
+            let dealt: f32 = attacker_hits.iter().map(|hit| hit.3).sum();
+            if primary.is_none_or(|(_, most)| dealt > most) {
+                primary = Some((attacker_hits[0].1, dealt));
+            }
+        }
+
+        let mut tally = [(0u32, 0.0f32); BURN + 1];
+        for &(_, _, category, damage) in target_hits {
+            let slot = &mut tally[category.map_or(BURN, |c| c as usize)];
+            slot.0 += 1;
+            slot.1 += damage;
+        }
+        let kind = (0..tally.len())
+            .max_by(|&a, &b| tally[a].0.cmp(&tally[b].0).then(tally[a].1.total_cmp(&tally[b].1)))
+            .unwrap_or(BURN);
+        let damage_type = WeaponCategory::ALL.get(kind).map_or("burn", |c| c.name());
+
+        attribution[target_hits[0].0] = (primary.and_then(|(attacker_id, _)| attacker_id), damage_type);
+    }
+}
+
 /// Main battle simulator
-pub struct BattleSimulator {
+///
+/// Generic over the spatial index used for neighbour queries.
+pub struct BattleSimulator<I: SpatialIndex = SpatialGrid> {
     pub units: Vec<BattleUnit>,
-    grid: SpatialGrid,
+    config: BattleConfig,
+    /// config's durations in ticks - re-derived whenever the config changes
+    ticks: TickCounts,
+    grid: I,
+    /// Which factions fight each other (default: all hostile)
+    relations: FactionRelations,
+    /// Enemies each faction can see (updated at the start of targeting)
+    detection: Detection,
     tick: u64,
-    damage_queue: Vec<DamageEntry>,
+    buffers: TickBuffers,
     /// Track last tick when damage was dealt (for stalemate detection)
     last_combat_tick: u64,
     
@@ -91,29 +743,276 @@ pub struct BattleSimulator {
     is_idle: bool,
     /// Count of idle ticks (for logging)
     idle_tick_count: u64,
+
+    // Pause
+    /// While paused simulate_tick does nothing and the tick counter stays put
+    paused: bool,
+    /// Tick the current (or last) pause started at
+    paused_at_tick: u64,
+    /// Simulated time of the last tick (construction time before that)
+    last_time: f64,
+    /// Host time minus simulated time - grows by however long the battle was
+    /// paused, so cooldowns don't run out while nothing is simulated
+    time_offset: f64,
+    /// Re-derive time_offset on the next simulate_tick (after resume / step)
+    resync_clock: bool,
+    /// Shots from manually_fire waiting for the next tick's combat phase
+    manual_shots: Vec<ManualShot>,
+    /// Guided shots that turned on a new target, landing next tick
+    retargeted_shots: Vec<DamageEntry>,
+    /// Units trigger_self_destruct was called for, blowing up next tick
+    pending_self_destructs: Vec<u32>,
+    /// Recent battle events (BattleConfig.combat_log_capacity)
+    combat_log: CombatLog,
+    /// External inputs per tick while recording
+    recorder: ReplayRecorder,
+    /// Recorded inputs not yet played back (see from_replay)
+    playback: VecDeque<TickInput>,
+    /// Source of every random roll (BattleConfig.mode)
+    rng: BattleRng,
+    /// Waves not yet arrived, in arrival order
+    reinforcements: Vec<ReinforcementWave>,
+    /// Units that re-evaluate their target this tick regardless of the
+    /// retarget interval (enemies near a reinforcement spawn)
+    retarget_now: Vec<bool>,
+    /// Hangar launches / docks and removals waiting for the next TickResult
+    launched: Vec<u32>,
+    docked: Vec<u32>,
+    removed: Vec<u32>,
+    /// Last position / (hp, shield) simulate_tick_delta reported per unit
+    prev_positions: HashMap<u32, (f32, f32, f32)>,
+    prev_vitals: HashMap<u32, (f32, f32)>,
+    /// Weapon tag prefixes each ShipClass may carry (unlisted classes: anything)
+    weapon_class_restrictions: WeaponClassRestrictions,
+    /// Tag prefix -> category, applied to weapons as their unit joins
+    weapon_tags: WeaponTagRegistry,
+    /// Next id to try for a carrier drone (see allocate_drone_id)
+    next_drone_id: u32,
+    /// (faction_id, objective) win conditions - empty means elimination
+    objectives: Vec<(u32, BattleObjective)>,
+    /// Progress per entry of `objectives`
+    objective_progress: Vec<ObjectiveProgress>,
+    /// (faction_id, objective) for every objective completed, in order
+    completed_objectives: Vec<(u32, String)>,
+    /// The first objective or config.victory met (never plain elimination)
+    victory: Option<VictoryState>,
+    /// Capturable nodes, in the order they were added
+    resource_nodes: Vec<ResourceNode>,
+    /// Resources earned from nodes per faction
+    faction_resources: HashMap<u32, f32>,
+    /// Unit indices per group_id - members still in the battle (or docked)
+    groups: HashMap<u32, Vec<usize>>,
+    /// Focus target per group (set_group_target)
+    group_targets: HashMap<u32, u32>,
+    /// Stance per group, given to units as they join it
+    group_stances: HashMap<u32, Stance>,
+    /// Balance multipliers and special weapons per faction (unlisted: 1x)
+    faction_configs: FactionConfigs,
+    /// Unit types for add_unit_from_template, by template_id
+    templates: HashMap<String, UnitTemplate>,
+    /// Phase timings while config.profiling is on
+    profiler: PhaseProfiler,
+    /// Compute time of the last few ticks (always on)
+    tick_durations: TickDurations,
+    /// The battle as constructed (see initial_snapshot)
+    initial_snapshot: BattleSnapshot,
+    /// World units per cell of the debug map when none is given
+    #[cfg(debug_assertions)]
+    debug_map_scale: f32,
 }
 
 #[derive(Debug, Clone)]
 struct DamageEntry {
     target_idx: usize,
     damage: f32,
+    attacker_idx: Option<usize>,  // None when the source has left the unit list
+    weapon_idx: Option<usize>,    // Weapon that fired it (counts for shots_hit); None for burns
+    shield_pierce: f32,           // Weapon modifiers (0 / 1 for burns)
+    shield_damage_bonus: f32,
+    retargeted: bool,             // Guided shot already turned once - fizzles if this target dies too
+    blast: bool,                  // Self-destruct blast - not aimed, so alliances and disables don't stop it
+}
+
+impl DamageEntry {
+    #[inline]
+    fn split(&self) -> DamageSplit {
+        DamageSplit::new(self.damage, self.shield_pierce, self.shield_damage_bonus)
+    }
+}
+
+/// Shot fired through manually_fire - already paid for (cooldown / ammo),
+/// damage is queued with the next tick's weapon fires
+#[derive(Debug, Clone)]
+struct ManualShot {
     attacker_idx: usize,
+    target_idx: usize,
+    weapon_idx: usize,
+    damage: f32,
+    fired: WeaponFired,
+}
+
+/// Repair applied after the damage queue
+#[derive(Debug, Clone)]
+struct RepairEntry {
+    target_idx: usize,
+    amount: f32,
+    healer_idx: usize,
+    repairs_shield: bool,
 }
 
+/// TickResult.version - bumped when the result's shape changes in a way a
+/// client has to handle differently
+pub const TICK_RESULT_VERSION: u32 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
 pub struct TickResult {
+    /// TICK_RESULT_VERSION of the simulator that produced it (0: from before
+    /// results were versioned)
+    #[serde(default)]
+    pub version: u32,
     pub moved: Vec<MovedUnit>,
     pub damaged: Vec<DamagedUnit>,
+    /// Units destroyed this tick - losses (recovered drones are in `withdrawn`)
     pub destroyed: Vec<u32>,
+    /// Damage wasted on each unit in `destroyed` past what killed it
+    #[serde(default)]
+    pub overkill: Vec<DestroyedUnit>,
+    /// Units that withdrew from the battle this tick (retreated out of reach)
+    pub retreated: Vec<u32>,
+    /// Units that left the battle without being destroyed since the last
+    /// tick - `retreated`, units removed with remove_unit and drones
+    /// recovered by their carrier
+    #[serde(default)]
+    pub withdrawn: Vec<u32>,
+    /// Units that surrendered this tick
+    #[serde(default)]
+    pub surrendered: Vec<u32>,
+    /// Units that shut down this tick (below their disable_threshold)
+    #[serde(default)]
+    pub disabled: Vec<u32>,
+    /// Disabled units that repaired themselves back online this tick
+    #[serde(default)]
+    pub reactivated: Vec<u32>,
+    /// Units currently retreating (still on the battlefield)
+    #[serde(rename = "retreatingUnits")]
+    pub retreating_units: Vec<u32>,
     pub tick: u64,
     #[serde(rename = "weaponsFired")]
     pub weapons_fired: Vec<WeaponFired>,
+    /// Guided shots whose target died before they landed and that turned on
+    /// another enemy - they land next tick
+    #[serde(rename = "missilesRetargeted", default)]
+    pub missiles_retargeted: Vec<MissileRetargeted>,
+    /// Guided shots whose target died before they landed, with nothing left
+    /// in reach
+    #[serde(rename = "missilesFizzled", default)]
+    pub missiles_fizzled: Vec<MissileFizzled>,
+    /// Units that blew themselves up this tick (also in `destroyed`)
+    #[serde(rename = "selfDestructed", default)]
+    pub self_destructed: Vec<SelfDestructed>,
+    /// Enemies a faction's sensors picked up this tick
+    #[serde(rename = "contactsDetected", default)]
+    pub contacts_detected: Vec<SensorContact>,
+    /// Enemies a faction lost sight of this tick (still on the battlefield)
+    #[serde(rename = "contactsLost", default)]
+    pub contacts_lost: Vec<SensorContact>,
+    /// Units whose status effects changed this tick (applied or expired)
+    pub effects: Vec<UnitEffects>,
+    /// Units restored by repair weapons this tick (values after repair)
+    pub repaired: Vec<RepairedUnit>,
+    /// Capacitor levels that changed since the last tick (units with max_energy only)
+    #[serde(default)]
+    pub energy: Vec<UnitEnergy>,
+    /// Reinforcements that arrived and carrier drones built this tick
+    #[serde(default)]
+    pub spawned: Vec<u32>,
+    /// (faction_id, unit ids) for reinforcements that arrived this tick
+    #[serde(rename = "reinforcementsArrived", default)]
+    pub reinforcements_arrived: Vec<(u32, Vec<u32>)>,
+    /// (unit id, morale) for units whose morale changed this tick
+    #[serde(rename = "moraleEvents", default)]
+    pub morale_events: Vec<(u32, f32)>,
+    /// (unit id, new veterancy level) for units that levelled up this tick
+    #[serde(rename = "levelUps", default)]
+    pub level_ups: Vec<(u32, u8)>,
+    /// Units that left a hangar since the last tick (launch_fighter, or their
+    /// carrier died)
+    #[serde(default)]
+    pub launched: Vec<u32>,
+    /// Units docked with dock_fighter since the last tick
+    #[serde(default)]
+    pub docked: Vec<u32>,
+    /// Objectives completed this tick, as "faction_id:objective"
+    /// (e.g. "1:kill_unit(42)")
+    #[serde(rename = "objectivesMet", default)]
+    pub objectives_met: Vec<String>,
+    /// (faction_id, amount) earned from resource nodes this tick
+    #[serde(rename = "resourcesGained", default)]
+    pub resources_gained: Vec<(u32, f32)>,
+    /// More than half the stalemate threshold has passed without combat
+    #[serde(rename = "stalemateWarning", default)]
+    pub stalemate_warning: bool,
     /// ✅ NEW: Whether this was an idle tick (minimal processing)
     #[serde(rename = "isIdle")]
     pub is_idle: bool,
+    /// `moved` lists every unit on the battlefield (see emit_all_positions_every_n_ticks)
+    #[serde(rename = "isKeyframe", default)]
+    pub is_keyframe: bool,
+    /// Compute time of the tick in microseconds (0 for paused ticks)
+    #[serde(rename = "tickDurationUs", default)]
+    pub tick_duration_us: u32,
+}
+
+impl TickResult {
+    /// Result with nothing in it (idle and paused ticks)
+    pub fn empty(tick: u64, is_idle: bool) -> Self {
+        TickResult {
+            version: TICK_RESULT_VERSION,
+            moved: vec![],
+            damaged: vec![],
+            destroyed: vec![],
+            overkill: vec![],
+            retreated: vec![],
+            withdrawn: vec![],
+            surrendered: vec![],
+            disabled: vec![],
+            reactivated: vec![],
+            retreating_units: vec![],
+            tick,
+            weapons_fired: vec![],
+            missiles_retargeted: vec![],
+            missiles_fizzled: vec![],
+            self_destructed: vec![],
+            contacts_detected: vec![],
+            contacts_lost: vec![],
+            effects: vec![],
+            repaired: vec![],
+            energy: vec![],
+            spawned: vec![],
+            reinforcements_arrived: vec![],
+            morale_events: vec![],
+            level_ups: vec![],
+            launched: vec![],
+            docked: vec![],
+            objectives_met: vec![],
+            resources_gained: vec![],
+            stalemate_warning: false,
+            is_idle,
+            is_keyframe: false,
+            tick_duration_us: 0,
+        }
+    }
 }
 
+/// A TickResult from simulate_tick_delta - same shape, but `moved` and
+/// `damaged` only hold units whose state changed since they were last reported
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DeltaTickResult(pub TickResult);
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
 pub struct WeaponFired {
     #[serde(rename = "attackerId")]
     pub attacker_id: u32,
@@ -123,21 +1022,472 @@ pub struct WeaponFired {
     pub weapon_type: String,
     #[serde(rename = "impactTime")]
     pub impact_time: u32,
+    /// Rounds left after this shot (only for weapons with limited ammo)
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(rename = "ammoRemaining", default)]
+    pub ammo_remaining: Option<u32>,
+    /// False for a miss - the shot was spent but does no damage
+    #[serde(default = "hit_default")]
+    pub hit: bool,
+    /// Missile shot down by point defense (hit is false)
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "std::ops::Not::not"))]
+    #[serde(default)]
+    pub intercepted: bool,
+    /// Attacker and target positions when the shot was fired - all six set,
+    /// or none when config.include_fire_positions is off
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(default)]
+    pub ax: Option<f32>,
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(default)]
+    pub ay: Option<f32>,
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(default)]
+    pub az: Option<f32>,
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(default)]
+    pub tx: Option<f32>,
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(default)]
+    pub ty: Option<f32>,
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(default)]
+    pub tz: Option<f32>,
+    /// Misses only: where the shot ends, relative to (tx, ty, tz)
+    #[cfg_attr(feature = "typegen", schemars(skip_serializing_if = "Option::is_none"))]
+    #[serde(rename = "missOffset", default)]
+    pub miss_offset: Option<(f32, f32, f32)>,
+}
+
+/// Point defense that's online and can shoot missiles down
+#[inline]
+fn intercepts_missiles(weapon: &Weapon) -> bool {
+    weapon.category == WeaponCategory::PointDefense && !weapon.is_disabled && weapon.am_intercept_chance > 0.0
+}
+
+fn hit_default() -> bool {
+    true
+}
+
+impl WeaponFired {
+    /// Fill in ax..tz (and missOffset for a miss) from the units as they
+    /// stand - `seed` picks which side a miss goes past
+    fn with_positions(mut self, attacker: &BattleUnit, target: &BattleUnit, seed: u32) -> Self {
+        (self.ax, self.ay, self.az) = (Some(attacker.pos_x), Some(attacker.pos_y), Some(attacker.pos_z));
+        (self.tx, self.ty, self.tz) = (Some(target.pos_x), Some(target.pos_y), Some(target.pos_z));
+        if !self.hit {
+            self.miss_offset = Some(miss_offset(attacker, target, seed));
+        }
+        self
+    }
+}
+
+/// Per-shot seed for miss_offset - no rng draw, so turning fire positions
+/// on or off doesn't change a seeded battle
+fn shot_seed(tick: u64, attacker_id: u32, weapon_idx: usize) -> u32 {
+    (tick as u32).wrapping_mul(31).wrapping_add(attacker_id).wrapping_mul(31).wrapping_add(weapon_idx as u32)
+}
+
+/// Sideways offset from the target for a missed shot to end at - a couple of
+/// hull radii off the line of fire, at an angle picked by `seed`
+fn miss_offset(attacker: &BattleUnit, target: &BattleUnit, seed: u32) -> (f32, f32, f32) {
+    let (dx, dy, dz) = (target.pos_x - attacker.pos_x, target.pos_y - attacker.pos_y, target.pos_z - attacker.pos_z);
+    let len = (dx * dx + dy * dy + dz * dz).sqrt();
+    let dir = if len > f32::EPSILON { (dx / len, dy / len, dz / len) } else { (1.0, 0.0, 0.0) };
+
+    let cross = |a: (f32, f32, f32), b: (f32, f32, f32)| {
+        (a.1 * b.2 - a.2 * b.1, a.2 * b.0 - a.0 * b.2, a.0 * b.1 - a.1 * b.0)
+    };
+    // Two axes perpendicular to the line of fire
+    let up = if dir.1.abs() < 0.9 { (0.0, 1.0, 0.0) } else { (1.0, 0.0, 0.0) };
+    let side = cross(dir, up);
+    let side_len = (side.0 * side.0 + side.1 * side.1 + side.2 * side.2).sqrt();
+    let side = (side.0 / side_len, side.1 / side_len, side.2 / side_len);
+    let lift = cross(dir, side);
+
+    let angle = (seed.wrapping_mul(0x9E37_79B9) >> 8) as f32 / (1u32 << 24) as f32 * std::f32::consts::TAU;
+    let distance = 2.0 * target.radius.max(target.signature_radius).max(1.0);
+    let (sin, cos) = angle.sin_cos();
+    (
+        (side.0 * cos + lift.0 * sin) * distance,
+        (side.1 * cos + lift.1 * sin) * distance,
+        (side.2 * cos + lift.2 * sin) * distance,
+    )
+}
+
+/// JSON leaves ammoRemaining, intercepted, positions and missOffset out when unset;
+/// binary formats always write them (bincode has no field names, so nothing
+/// can be skipped)
+impl Serialize for WeaponFired {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let binary = !serializer.is_human_readable();
+        let with_ammo = self.ammo_remaining.is_some() || binary;
+        let with_positions = self.ax.is_some() || binary;
+        let with_intercepted = self.intercepted || binary;
+        let with_miss = self.miss_offset.is_some() || binary;
+        let len = 5 + with_ammo as usize + with_intercepted as usize + 6 * with_positions as usize + with_miss as usize;
+        let mut state = serializer.serialize_struct("WeaponFired", len)?;
+        state.serialize_field("attackerId", &self.attacker_id)?;
+        state.serialize_field("targetId", &self.target_id)?;
+        state.serialize_field("weaponType", &self.weapon_type)?;
+        state.serialize_field("impactTime", &self.impact_time)?;
+        if with_ammo {
+            state.serialize_field("ammoRemaining", &self.ammo_remaining)?;
+        } else {
+            state.skip_field("ammoRemaining")?;
+        }
+        state.serialize_field("hit", &self.hit)?;
+        if with_intercepted {
+            state.serialize_field("intercepted", &self.intercepted)?;
+        } else {
+            state.skip_field("intercepted")?;
+        }
+        let positions = [
+            ("ax", self.ax), ("ay", self.ay), ("az", self.az),
+            ("tx", self.tx), ("ty", self.ty), ("tz", self.tz),
+        ];
+        for (name, value) in positions {
+            if with_positions {
+                state.serialize_field(name, &value)?;
+            } else {
+                state.skip_field(name)?;
+            }
+        }
+        if with_miss {
+            state.serialize_field("missOffset", &self.miss_offset)?;
+        } else {
+            state.skip_field("missOffset")?;
+        }
+        state.end()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
 pub struct MovedUnit {
     pub id: u32,
     pub x: f32,
     pub y: f32,
     pub z: f32,
+    /// Velocity over the last tick (units/sec) for client-side extrapolation
+    #[serde(default)]
+    pub vx: f32,
+    #[serde(default)]
+    pub vy: f32,
+    #[serde(default)]
+    pub vz: f32,
+}
+
+impl MovedUnit {
+    /// Entry for a unit that moved from `old` this tick (velocity = displacement / dt)
+    fn from_move(unit: &BattleUnit, old: (f32, f32, f32), dt: f32) -> Self {
+        let inv_dt = if dt > 0.0 { 1.0 / dt } else { 0.0 };
+        MovedUnit {
+            id: unit.id,
+            x: unit.pos_x,
+            y: unit.pos_y,
+            z: unit.pos_z,
+            vx: (unit.pos_x - old.0) * inv_dt,
+            vy: (unit.pos_y - old.1) * inv_dt,
+            vz: (unit.pos_z - old.2) * inv_dt,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
 pub struct DamagedUnit {
     pub id: u32,
     pub hp: f32,
     pub shield: f32,
+    /// Hull / shield lost this tick
+    #[serde(default)]
+    pub hull_damage: f32,
+    #[serde(default)]
+    pub shield_damage: f32,
+    /// Unit that dealt the most damage this tick (None: burns only, or the
+    /// attacker has left the battle)
+    #[serde(default)]
+    pub primary_attacker_id: Option<u32>,
+    /// Weapon category of most of this tick's hits ("kinetic", "energy", ...),
+    /// "burn" for status effects, "explosive" for self-destruct blasts
+    #[serde(default)]
+    pub damage_type: Cow<'static, str>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct DestroyedUnit {
+    pub id: u32,
+    pub overkill: f32,
+}
+
+/// A unit that blew itself up (TickResult.self_destructed) - position and
+/// radius of the blast
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct SelfDestructed {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub radius: f32,
+    /// 0 for a triggered unit, n for one set off by a blast n links down a chain
+    pub depth: u32,
+}
+
+/// Guided shot that turned on a new target (TickResult.missiles_retargeted)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct MissileRetargeted {
+    #[serde(rename = "attackerId")]
+    pub attacker_id: u32,
+    /// The target that died before the shot landed
+    #[serde(rename = "fromId")]
+    pub from_id: u32,
+    #[serde(rename = "toId")]
+    pub to_id: u32,
+    #[serde(rename = "weaponType")]
+    pub weapon_type: String,
+}
+
+/// Guided shot that lost its target and found no other (TickResult.missiles_fizzled)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct MissileFizzled {
+    #[serde(rename = "attackerId")]
+    pub attacker_id: u32,
+    #[serde(rename = "targetId")]
+    pub target_id: u32,
+    #[serde(rename = "weaponType")]
+    pub weapon_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct RepairedUnit {
+    pub id: u32,
+    pub hp: f32,
+    pub shield: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct UnitEnergy {
+    pub id: u32,
+    pub energy: f32,
+}
+
+/// One unit found by query_units_in_radius
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitInRadius {
+    pub id: u32,
+    pub faction_id: u32,
+    pub hp: f32,
+    pub shield: f32,
+    pub pos_x: f32,
+    pub pos_y: f32,
+    pub pos_z: f32,
+    pub distance: f32,
+}
+
+/// Full current effect list for a unit (empty = all effects cleared)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct UnitEffects {
+    pub id: u32,
+    pub effects: Vec<StatusEffect>,
+}
+
+/// Units that join the battle at a given tick (see schedule_wave)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct ReinforcementWave {
+    pub arrival_tick: u64,
+    pub units: Vec<BattleUnit>,
+    /// Faction the units fight for (None keeps each unit's faction_id)
+    #[serde(default)]
+    pub faction_id: Option<u32>,
+    /// Point the units arrive around (None keeps their positions)
+    #[serde(default)]
+    pub spawn: Option<(f32, f32, f32)>,
+}
+
+impl ReinforcementWave {
+    /// Apply faction_id and spawn to the units - the first lands on the spawn
+    /// point, the rest on a sunflower spiral around it in the x/z plane
+    fn place_units(&mut self, spacing: f32) {
+        if let Some(faction_id) = self.faction_id {
+            for unit in self.units.iter_mut() {
+                unit.faction_id = faction_id;
+            }
+        }
+        let Some((x, y, z)) = self.spawn else {
+            return;
+        };
+        const GOLDEN_ANGLE: f32 = 2.399_963;
+        for (i, unit) in self.units.iter_mut().enumerate() {
+            let r = spacing * (i as f32).sqrt();
+            let angle = i as f32 * GOLDEN_ANGLE;
+            unit.pos_x = x + r * angle.cos();
+            unit.pos_y = y;
+            unit.pos_z = z + r * angle.sin();
+        }
+    }
+}
+
+/// Why launch_fighter / dock_fighter refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HangarError {
+    /// Carrier missing, dead, withdrawn or docked itself
+    NoCarrier(u32),
+    /// Fighter missing, dead or withdrawn, or not the carrier's faction
+    NoFighter(u32),
+    /// Launch of a unit that isn't in this carrier's hangar
+    NotDocked(u32),
+    /// Dock of a unit that's already in a hangar
+    AlreadyDocked(u32),
+    /// Carrier has no space left
+    HangarFull(u32),
+}
+
+impl fmt::Display for HangarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HangarError::NoCarrier(id) => write!(f, "carrier {} not found or not on the battlefield", id),
+            HangarError::NoFighter(id) => write!(f, "unit {} not found, not on the battlefield or not the carrier's faction", id),
+            HangarError::NotDocked(id) => write!(f, "unit {} is not in the carrier's hangar", id),
+            HangarError::AlreadyDocked(id) => write!(f, "unit {} is already in a hangar", id),
+            HangarError::HangarFull(id) => write!(f, "carrier {} hangar is full", id),
+        }
+    }
+}
+
+impl std::error::Error for HangarError {}
+
+/// Why run_to_completion stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionReason {
+    /// No hostile factions left on the battlefield
+    Elimination,
+    /// A faction completed its objective (see set_objectives) or
+    /// config.victory was met - see victory_state
+    Objective,
+    Stalemate,
+    /// Hit max_ticks with the battle still going
+    TickCap,
+}
+
+/// Per-faction head count (see faction_summary)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct FactionSummary {
+    pub faction_id: u32,
+    /// Still on the battlefield
+    pub active: u32,
+    pub destroyed: u32,
+    /// Retreated out of the battle - not a loss
+    pub withdrawn: u32,
+    /// Alive but out of the fight
+    #[serde(default)]
+    pub surrendered: u32,
+    /// Shut down (may repair back online)
+    #[serde(default)]
+    pub disabled: u32,
+}
+
+/// Surviving unit in a CompletionReport
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct SurvivingUnit {
+    pub id: u32,
+    pub faction_id: u32,
+    pub hp: f32,
+    pub shield: f32,
+}
+
+/// Final report from run_to_completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct CompletionReport {
+    pub winner: Option<u32>,
+    /// Ticks simulated by this call
+    pub ticks: u64,
+    pub reason: CompletionReason,
+    /// Simulator tick at the end of the run
+    pub tick: u64,
+    #[serde(rename = "ticksSinceLastCombat")]
+    pub ticks_since_last_combat: u64,
+    pub survivors: Vec<SurvivingUnit>,
+    pub factions: Vec<FactionSummary>,
+    /// Battle-wide weapon stats by tag (see weapon_totals)
+    #[serde(default)]
+    pub weapons: BTreeMap<String, WeaponStats>,
+}
+
+/// Result of simulate_until_end - CompletionReport without the per-unit detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct BattleOutcome {
+    pub winner: Option<u32>,
+    pub reason: CompletionReason,
+    /// Ticks simulated by this call
+    pub ticks: u64,
+    /// Simulator tick at the end of the run
+    pub tick: u64,
+    /// Units still in the battle
+    pub survivors: u32,
+}
+
+/// Battle state saved by snapshot() for restore()
+///
+/// Units as they were, with the tick counters, alliances, grid cell size,
+/// reinforcements still to arrive, resource income and battle outcome so far
+/// that go with them. The objectives and resource nodes themselves aren't
+/// part of it - only their progress and owners.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct BattleSnapshot {
+    pub units: Vec<BattleUnit>,
+    pub tick: u64,
+    pub last_combat_tick: u64,
+    /// Allied faction pairs as (low, high)
+    pub alliances: Vec<(u32, u32)>,
+    /// Spatial grid cell size (None for an octree)
+    #[serde(default)]
+    pub cell_size: Option<f32>,
+    /// Simulated time of the tick (weapon cooldowns count from it)
+    #[serde(default)]
+    pub time: f64,
+    /// Idle tracking - last tick movement came in, and idle ticks so far
+    #[serde(default)]
+    pub last_movement_tick: u64,
+    #[serde(default)]
+    pub idle_tick_count: u64,
+    /// Progress per objective (ControlZone hold streaks)
+    #[serde(default)]
+    pub objective_progress: Vec<ObjectiveProgress>,
+    /// (faction_id, objective) completed so far, in order
+    #[serde(default)]
+    pub completed_objectives: Vec<(u32, String)>,
+    /// The objective or config.victory that had decided the battle
+    #[serde(default)]
+    pub victory: Option<VictoryState>,
+    /// Focus target per group
+    #[serde(default)]
+    pub group_targets: HashMap<u32, u32>,
+    /// Waves scheduled but not arrived yet, in arrival order
+    #[serde(default)]
+    pub reinforcements: Vec<ReinforcementWave>,
+    /// Faction holding each resource node, by node id (unowned nodes left out)
+    #[serde(default)]
+    pub node_owners: HashMap<u32, u32>,
+    /// Resources each faction had earned from nodes
+    #[serde(default)]
+    pub faction_resources: HashMap<u32, f32>,
 }
 
 /// ✅ NEW: Idle state info for JS side
@@ -154,49 +1504,206 @@ pub struct IdleInfo {
 }
 
 impl BattleSimulator {
-    pub fn new(mut units: Vec<BattleUnit>, current_time: f64) -> Self {
+    /// Trusts its input - use try_new for data from outside
+    pub fn new(units: Vec<BattleUnit>, current_time: f64) -> Self {
+        Self::with_index(units, current_time, SpatialGrid::new(DEFAULT_CELL_SIZE))
+    }
+
+    /// new() that rejects duplicate ids and bad stats (see validation.rs)
+    pub fn try_new(units: Vec<BattleUnit>, current_time: f64) -> Result<Self, ValidationError> {
+        Self::try_with_config(units, current_time, SpatialGrid::new(DEFAULT_CELL_SIZE), BattleConfig::default())
+    }
+
+    /// new() with every tunable from `config` - the spatial grid uses
+    /// config.grid_cell_size when it's set
+    pub fn new_with_config(units: Vec<BattleUnit>, current_time: f64, config: BattleConfig) -> Self {
+        let grid = config.spatial_grid();
+        Self::with_config(units, current_time, grid, config)
+    }
+}
+
+impl<I: SpatialIndex> BattleSimulator<I> {
+    /// Create a simulator using the given (empty) spatial index
+    pub fn with_index(units: Vec<BattleUnit>, current_time: f64, grid: I) -> Self {
+        Self::with_config(units, current_time, grid, BattleConfig::default())
+    }
+
+    /// Create a simulator with a config in place from the start - needed for
+    /// SimulationMode::Deterministic, which also seeds the initial cooldown jitter
+    pub fn with_config(mut units: Vec<BattleUnit>, current_time: f64, mut grid: I, config: BattleConfig) -> Self {
+        if let Some(level) = config.log_level {
+            crate::logging::set_log_level(level);
+        }
+        let mut rng = BattleRng::new(config.mode, 0);
+        let weapon_tags = WeaponTagRegistry::default();
         // Normalize all units to compute derived fields and randomize weapon cooldowns
         for unit in units.iter_mut() {
-            unit.normalize(current_time);
+            unit.normalize(current_time, &mut rng);
+            weapon_tags.categorize_weapons(unit);
         }
 
         let ships = units.iter().filter(|u| u.is_ship).count();
         let stations = units.iter().filter(|u| u.is_station).count();
         let armed = units.iter().filter(|u| u.has_weapons).count();
         let max_range = units.iter().map(|u| u.max_weapon_range).fold(0.0f32, |a, b| a.max(b));
-        log(&format!(
+        log_at!(Info,
             "[Simulator] Created with {} units: {} ships, {} stations, {} armed, max_range={:.0}",
             units.len(), ships, stations, armed, max_range
-        ));
+        );
+
+        // A fixed grid_cell_size keeps the grid as given
+        if config.grid_cell_size <= 0.0 {
+            grid.tune(units.len(), tuning_radius(&config, &units));
+        }
+
+        let mut combat_log = CombatLog::default();
+        combat_log.set_capacity(config.combat_log_capacity);
 
-        Self {
+        let groups = group_index(&units);
+        let initial_snapshot = BattleSnapshot {
+            units: units.clone(),
+            cell_size: grid.grid_stats().map(|stats| stats.cell_size),
+            time: current_time,
+            ..Default::default()
+        };
+
+        let mut sim = Self {
             units,
-            grid: SpatialGrid::new(100.0),
+            ticks: config.tick_counts(),
+            config,
+            grid,
+            relations: FactionRelations::default(),
+            detection: Detection::default(),
             tick: 0,
-            damage_queue: Vec::new(),
+            buffers: TickBuffers::default(),
             last_combat_tick: 0,
             // ✅ NEW: Initialize idle tracking
             last_movement_tick: 0,
             next_weapon_ready_time: 0.0,
             is_idle: false,
             idle_tick_count: 0,
+            paused: false,
+            paused_at_tick: 0,
+            last_time: current_time,
+            time_offset: 0.0,
+            resync_clock: false,
+            manual_shots: Vec::new(),
+            retargeted_shots: Vec::new(),
+            pending_self_destructs: Vec::new(),
+            combat_log,
+            recorder: ReplayRecorder::default(),
+            playback: VecDeque::new(),
+            rng,
+            reinforcements: Vec::new(),
+            retarget_now: Vec::new(),
+            launched: Vec::new(),
+            docked: Vec::new(),
+            removed: Vec::new(),
+            prev_positions: HashMap::new(),
+            prev_vitals: HashMap::new(),
+            weapon_class_restrictions: HashMap::new(),
+            weapon_tags,
+            next_drone_id: DRONE_ID_BASE,
+            objectives: Vec::new(),
+            objective_progress: Vec::new(),
+            completed_objectives: Vec::new(),
+            victory: None,
+            resource_nodes: Vec::new(),
+            faction_resources: HashMap::new(),
+            groups,
+            group_targets: HashMap::new(),
+            group_stances: HashMap::new(),
+            faction_configs: HashMap::new(),
+            templates: HashMap::new(),
+            profiler: PhaseProfiler::default(),
+            tick_durations: TickDurations::default(),
+            initial_snapshot,
+            #[cfg(debug_assertions)]
+            debug_map_scale: DEFAULT_DEBUG_MAP_SCALE,
+        };
+        if sim.config.record_replay {
+            sim.start_recording();
         }
+        sim
     }
 
-    // =========================================================================
+    /// with_config() that rejects duplicate ids and bad stats - the first
+    /// problem found is returned
+    pub fn try_with_config(units: Vec<BattleUnit>, current_time: f64, grid: I, config: BattleConfig) -> Result<Self, ValidationError> {
+        if let Some(problem) = validate_units(&units, []).into_iter().next() {
+            return Err(problem);
+        }
+        Ok(Self::with_config(units, current_time, grid, config))
+    }
+
+    /// Simulator at the start of a recorded battle, with its config and
+    /// alliances, ready for step_replay()
+    pub fn from_replay(replay: ReplayRecorder, grid: I) -> Self {
+        let mut sim = Self::with_config(replay.initial_state, replay.start_time, grid, replay.config);
+        for (a, b) in replay.alliances {
+            sim.relations.set_allied(a, b, true);
+        }
+        sim.playback = replay.tick_inputs.into();
+        sim
+    }
+
+    /// Replace the simulator config - a config that fails validate() is
+    /// refused and the current one kept
+    pub fn set_config(&mut self, config: BattleConfig) -> Result<(), ConfigError> {
+        config.validate()?;
+        if self.recorder.enabled && config.mode == SimulationMode::Stochastic {
+            return Err(ConfigError::StochasticWhileRecording);
+        }
+        self.combat_log.set_capacity(config.combat_log_capacity);
+        if let Some(level) = config.log_level {
+            crate::logging::set_log_level(level);
+        }
+        if config.mode != self.config.mode {
+            self.rng = BattleRng::new(config.mode, self.tick);
+        }
+        if config.victory != self.config.victory {
+            self.victory = None;
+        }
+        self.ticks = config.tick_counts();
+        self.config = config;
+        self.is_idle = false;
+        self.record_config();
+        Ok(())
+    }
+
+    /// Replace the class-based target priority table
+    pub fn set_priority_table(&mut self, table: PriorityTable) {
+        self.config.priority_table = table;
+        self.is_idle = false;
+        self.record_config();
+    }
+
+    /// Configure ally separation steering for simulator-moved units
+    pub fn set_collision_avoidance(&mut self, enabled: bool, radius: f32, strength: f32) {
+        self.config.collision_avoidance = enabled;
+        self.config.separation_radius = radius.max(0.0);
+        self.config.separation_strength = strength.max(0.0);
+        self.record_config();
+    }
+
+    /// Get the current simulator config
+    pub fn config(&self) -> &BattleConfig {
+        &self.config
+    }
+
+    // =========================================================================
     // ✅ NEW: Idle mode methods
     // =========================================================================
 
     /// Check if any weapon is ready to fire
     fn any_weapon_ready(&self, current_time: f64) -> bool {
         for unit in &self.units {
-            if !unit.alive || !unit.has_weapons || unit.target_id.is_none() {
+            if !unit.is_alive() || !unit.has_weapons || unit.target_id.is_none() {
                 continue;
             }
             
             for weapon in &unit.weapons {
-                let time_since_fired = current_time - weapon.last_fired;
-                if time_since_fired >= weapon.cooldown as f64 {
+                if current_time >= weapon.ready_time() {
                     return true;
                 }
             }
@@ -209,12 +1716,12 @@ impl BattleSimulator {
         let mut earliest: f64 = f64::MAX;
         
         for unit in &self.units {
-            if !unit.alive || !unit.has_weapons || unit.target_id.is_none() {
+            if !unit.is_alive() || !unit.has_weapons || unit.target_id.is_none() {
                 continue;
             }
             
             for weapon in &unit.weapons {
-                let ready_time = weapon.last_fired + weapon.cooldown as f64;
+                let ready_time = weapon.ready_time();
                 if ready_time < earliest {
                     earliest = ready_time;
                 }
@@ -230,9 +1737,15 @@ impl BattleSimulator {
 
     /// Check if battle should be in idle mode
     fn should_be_idle(&self, current_time: f64) -> bool {
+        // Manual and retargeted shots and self-destructs are committed in the
+        // combat phase
+        if !self.manual_shots.is_empty() || !self.retargeted_shots.is_empty() || !self.pending_self_destructs.is_empty() {
+            return false;
+        }
+
         // Not idle if recent movement
         let ticks_since_movement = self.tick.saturating_sub(self.last_movement_tick);
-        if ticks_since_movement < IDLE_MOVEMENT_THRESHOLD {
+        if ticks_since_movement < self.ticks.idle_movement {
             return false;
         }
         
@@ -241,9 +1754,40 @@ impl BattleSimulator {
             return false;
         }
         
+        // Not idle if the simulator is moving units itself
+        if self.has_simulated_movement() {
+            return false;
+        }
+        
+        // Not idle while capacitors recharge (levels are reported per tick)
+        if self.units.iter().any(|u| u.is_alive() && u.energy < u.max_energy && u.energy_regen > 0.0) {
+            return false;
+        }
+
+        // Not idle while a supply unit may have someone to top up
+        if self.units.iter().any(|u| u.in_battle() && u.resupply_rate > 0.0)
+            && self.units.iter().any(|u| u.in_battle() && u.supply_need().is_some()) {
+            return false;
+        }
+
+        // Not idle while a disabled unit waits to self-repair
+        if self.units.iter().any(|u| u.is_disabled && u.on_battlefield() && u.repair_rate > 0.0 && u.hp < u.max_hp) {
+            return false;
+        }
+
+        // Not idle while morale recovers (changes are reported per tick)
+        if self.units.iter().any(|u| u.in_battle() && u.morale < MAX_MORALE && u.morale_regen_rate > 0.0) {
+            return false;
+        }
+
+        // Not idle while status effects are ticking (burns, expiries)
+        if self.units.iter().any(|u| u.is_alive() && !u.effects.is_empty()) {
+            return false;
+        }
+        
         // Not idle if no units have targets (need to do targeting)
         let units_with_targets = self.units.iter()
-            .filter(|u| u.alive && u.has_weapons && u.target_id.is_some())
+            .filter(|u| u.is_alive() && u.has_weapons && u.target_id.is_some())
             .count();
         if units_with_targets == 0 {
             // Need to do targeting - not idle
@@ -253,20 +1797,33 @@ impl BattleSimulator {
         true
     }
 
+    /// Check if the simulator may steer this unit itself
+    fn is_sim_moved(&self, unit: &BattleUnit) -> bool {
+        self.config.ai_movement || unit.ai_controlled
+    }
+
+    /// Check if the simulator is moving any units (AI, retreats, waypoints)
+    fn has_simulated_movement(&self) -> bool {
+        self.units.iter().any(|u| {
+            u.in_battle() &&
+                (u.ai_controlled || (u.retreating && self.is_sim_moved(u)) || !u.waypoints.is_empty() || u.move_order.is_some())
+        })
+    }
+
     /// Perform minimal idle tick - only shield regen
     fn do_idle_tick(&mut self, dt: f32) {
         self.idle_tick_count += 1;
         
         // Only do shield regen
         for unit in self.units.iter_mut() {
-            if unit.alive {
-                unit.regen_shield(dt);
+            if unit.is_alive() {
+                unit.regen_shield(dt * factions::shield_regen_multiplier(&self.faction_configs, unit.faction_id));
             }
         }
     }
 
     /// Get current idle state info
-    pub fn get_idle_info(&self, current_time: f64) -> IdleInfo {
+    pub fn get_idle_info(&self, _current_time: f64) -> IdleInfo {
         IdleInfo {
             is_idle: self.is_idle,
             ticks_since_movement: self.tick.saturating_sub(self.last_movement_tick),
@@ -285,6 +1842,71 @@ impl BattleSimulator {
         self.next_weapon_ready_time
     }
 
+    // =========================================================================
+    // Pause
+    // =========================================================================
+
+    /// Pause the battle - simulate_tick returns empty results until resumed
+    /// Position updates still apply (and keep the spatial grid current)
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.paused = true;
+            self.paused_at_tick = self.tick;
+            log_at!(Info, "[Simulator] Paused at tick {}", self.tick);
+        }
+    }
+
+    /// Resume a paused battle
+    ///
+    /// The next simulate_tick continues one dt after the last simulated tick,
+    /// however much host time passed in between.
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.resync_clock = true;
+            // Positions may have changed while paused - re-check everything next tick
+            self.is_idle = false;
+            log_at!(Info, "[Simulator] Resumed at tick {}", self.tick);
+        }
+    }
+
+    /// Check if the battle is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Tick the current (or last) pause started at
+    pub fn paused_at_tick(&self) -> u64 {
+        self.paused_at_tick
+    }
+
+    /// Run exactly one tick of 1 / config.tick_rate, paused or not
+    ///
+    /// Stays paused if it was. Simulated time moves on without the host
+    /// clock, so the next simulate_tick re-syncs like after resume().
+    pub fn step(&mut self) -> TickResult {
+        let dt = self.config.fixed_dt();
+        let result = self.advance(dt, self.last_time + dt as f64);
+        self.resync_clock = true;
+        result
+    }
+
+    /// Simulated time for a host timestamp passed to the public API
+    ///
+    /// While paused (or before the clock re-syncs) that's the last tick's time.
+    fn sim_time(&self, host_time: f64) -> f64 {
+        if self.paused || self.resync_clock {
+            self.last_time
+        } else {
+            host_time - self.time_offset
+        }
+    }
+
+    /// Simulated time of the last tick - host time minus the time spent paused
+    pub fn simulated_time(&self) -> f64 {
+        self.last_time
+    }
+
     // =========================================================================
     // External position update methods
     // =========================================================================
@@ -292,10 +1914,13 @@ impl BattleSimulator {
     /// Update multiple unit positions from external source (player movement)
     /// Returns the number of units successfully updated
     pub fn update_positions(&mut self, updates: &[PositionUpdate]) -> u32 {
+        if self.recorder.enabled {
+            self.recorder.record_positions(updates);
+        }
         let mut count = 0;
         
         for update in updates {
-            if self.update_single_position(update.id, update.x, update.y, update.z, update.clear_target) {
+            if self.set_unit_position(update.id, update.x, update.y, update.z).is_some() {
                 count += 1;
             }
         }
@@ -308,10 +1933,10 @@ impl BattleSimulator {
             self.is_idle = false;
             
             if self.idle_tick_count > 0 {
-                log(&format!(
+                log_at!(Info,
                     "[Idle] WAKING from idle after {} idle ticks - {} positions updated",
                     self.idle_tick_count, count
-                ));
+                );
             }
         }
         
@@ -319,10 +1944,34 @@ impl BattleSimulator {
     }
 
     /// Update a single unit's position
-    /// Returns true if unit was found and updated
+    /// Returns None if the unit wasn't found, otherwise Some(clamped) - whether
+    /// the position had to be pulled back inside the configured bounds
     /// NOTE: External position updates ALWAYS clear target - unit will re-evaluate at new position
-    pub fn update_single_position(&mut self, unit_id: u32, x: f32, y: f32, z: f32, _clear_target: bool) -> bool {
-        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.alive) {
+    pub fn update_single_position(&mut self, unit_id: u32, x: f32, y: f32, z: f32, clear_target: bool) -> Option<bool> {
+        if self.recorder.enabled {
+            self.recorder.record_positions(&[PositionUpdate { id: unit_id, x, y, z, clear_target }]);
+        }
+        self.set_unit_position(unit_id, x, y, z)
+    }
+
+    fn set_unit_position(&mut self, unit_id: u32, x: f32, y: f32, z: f32) -> Option<bool> {
+        let (x, y, z, clamped) = match self.config.bounds {
+            Some(bounds) if !bounds.contains(x, y, z) => {
+                let (cx, cy, cz) = bounds.clamp(x, y, z);
+                log_at!(Debug,
+                    "[Position] Unit {} position ({:.0}, {:.0}, {:.0}) out of bounds, clamped to ({:.0}, {:.0}, {:.0})",
+                    unit_id, x, y, z, cx, cy, cz
+                );
+                (cx, cy, cz, true)
+            }
+            _ => (x, y, z, false),
+        };
+
+        let now = self.last_time;
+        let significant_movement = self.config.significant_movement_threshold;
+        // Surrendered units hold where they gave up
+        if let Some(idx) = self.units.iter().position(|u| u.id == unit_id && u.is_alive() && !u.is_surrendered) {
+            let unit = &mut self.units[idx];
             let old_x = unit.pos_x;
             let old_y = unit.pos_y;
             let old_z = unit.pos_z;
@@ -336,34 +1985,205 @@ impl BattleSimulator {
             unit.vel_x = 0.0;
             unit.vel_y = 0.0;
             unit.vel_z = 0.0;
+            // The host has taken over this unit's movement
+            unit.move_order = None;
             
             // Calculate movement distance for logging
             let dx = x - old_x;
             let dy = y - old_y;
             let dz = z - old_z;
             let move_dist = (dx * dx + dy * dy + dz * dz).sqrt();
+
+            // Speed for hit chance - further updates before the next tick
+            // don't change the estimate
+            let elapsed = now - unit.external_move_time;
+            if elapsed > 0.0 {
+                unit.external_speed = move_dist / elapsed as f32;
+                unit.external_move_time = now;
+            }
             
             // ALWAYS clear target on external position update
             // Unit will re-acquire nearest target in range on next tick
-            if unit.target_id.is_some() && move_dist > 0.1 {
-                log(&format!(
+            if unit.target_id.is_some() && !unit.target_locked && move_dist > significant_movement {
+                log_at!(Debug,
                     "[Position] Unit {} moved {:.1} units, clearing target for re-evaluation",
                     unit_id, move_dist
-                ));
+                );
                 unit.target_id = None;
             }
             
+            Some(clamped)
+        } else {
+            None
+        }
+    }
+
+    /// Pull a unit back inside the configured bounds. Returns true if it was outside.
+    fn clamp_to_bounds(&mut self, idx: usize) -> bool {
+        let Some(bounds) = self.config.bounds else {
+            return false;
+        };
+        let unit = &mut self.units[idx];
+        if bounds.contains(unit.pos_x, unit.pos_y, unit.pos_z) {
+            return false;
+        }
+        (unit.pos_x, unit.pos_y, unit.pos_z) = bounds.clamp(unit.pos_x, unit.pos_y, unit.pos_z);
+        true
+    }
+
+    /// Ids of units on the battlefield that sit outside the configured bounds (debug helper)
+    pub fn get_out_of_bounds_units(&self) -> Vec<u32> {
+        let Some(bounds) = self.config.bounds else {
+            return Vec::new();
+        };
+        self.units.iter()
+            .filter(|u| u.in_battle() && !bounds.contains(u.pos_x, u.pos_y, u.pos_z))
+            .map(|u| u.id)
+            .collect()
+    }
+
+    /// Set waypoints for a unit, restarting from the first one
+    /// Returns true if unit was found
+    pub fn set_unit_waypoints(&mut self, unit_id: u32, waypoints: Vec<(f32, f32, f32)>) -> bool {
+        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive()) {
+            unit.waypoints = waypoints.clone();
+            unit.current_waypoint = 0;
+            self.is_idle = false;
+            self.record(ReplayCommand::SetWaypoints { unit_id, waypoints });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Clear a unit's waypoints and stop it
+    /// Returns true if unit was found
+    pub fn clear_unit_waypoints(&mut self, unit_id: u32) -> bool {
+        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive()) {
+            unit.waypoints.clear();
+            unit.current_waypoint = 0;
+            unit.stop();
+            self.record(ReplayCommand::ClearWaypoints { unit_id });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Order a unit to move to a point - the simulator moves it there at
+    /// max_speed each tick (the point is clamped to the configured bounds).
+    /// Replaces any earlier order. Returns true if the unit was found (false
+    /// for a non-finite point)
+    pub fn issue_move_order(&mut self, unit_id: u32, x: f32, y: f32, z: f32) -> bool {
+        if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+            return false;
+        }
+        let (cx, cy, cz) = match self.config.bounds {
+            Some(bounds) => bounds.clamp(x, y, z),
+            None => (x, y, z),
+        };
+        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive() && !u.is_surrendered) {
+            unit.move_order = Some((cx, cy, cz));
+            self.is_idle = false;
+            self.record(ReplayCommand::MoveOrder { unit_id, x, y, z });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// issue_move_order for several units - returns how many were found
+    pub fn issue_move_orders(&mut self, orders: &[MoveOrder]) -> u32 {
+        orders.iter()
+            .filter(|order| self.issue_move_order(order.id, order.x, order.y, order.z))
+            .count() as u32
+    }
+
+    /// Cancel a unit's move order and stop it
+    /// Returns true if unit was found
+    pub fn clear_orders(&mut self, unit_id: u32) -> bool {
+        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive()) {
+            unit.move_order = None;
+            unit.stop();
+            self.record(ReplayCommand::ClearOrders { unit_id });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Toggle orbit movement (circle target at optimal range) for a unit
+    /// Returns true if unit was found
+    pub fn set_unit_orbit_mode(&mut self, unit_id: u32, enabled: bool) -> bool {
+        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive()) {
+            unit.orbit_mode = enabled;
+            self.record(ReplayCommand::SetOrbitMode { unit_id, enabled });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Split a unit's reactor power between shields, weapons and engines
+    /// (see BattleUnit::set_power). ai_controlled units rebalance every tick,
+    /// so this only sticks for host-controlled ones.
+    /// Returns Ok(true) if unit was found
+    pub fn set_unit_power(&mut self, unit_id: u32, shield: f32, weapons: f32, engines: f32) -> Result<bool, PowerError> {
+        let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive()) else {
+            return Ok(false);
+        };
+        unit.set_power(shield, weapons, engines)?;
+        self.record(ReplayCommand::SetPower { unit_id, shield, weapons, engines });
+        Ok(true)
+    }
+
+    /// Set the hull fraction below which a unit retreats (0 = never)
+    /// Returns true if unit was found
+    pub fn set_retreat_threshold(&mut self, unit_id: u32, fraction: f32) -> bool {
+        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive()) {
+            unit.retreat_hp_fraction = fraction.clamp(0.0, 1.0);
+            self.is_idle = false;
+            self.record(ReplayCommand::SetRetreatThreshold { unit_id, fraction });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set the rally point a unit retreats towards
+    /// Returns true if unit was found
+    pub fn set_retreat_target(&mut self, unit_id: u32, x: f32, y: f32, z: f32) -> bool {
+        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.is_alive()) {
+            unit.retreat_target = Some((x, y, z));
+            self.record(ReplayCommand::SetRetreatTarget { unit_id, x, y, z });
             true
         } else {
             false
         }
     }
 
+    /// Re-tune the spatial index to the current unit count and spread (not
+    /// with a fixed config.grid_cell_size)
+    fn tune_grid(&mut self) {
+        if self.config.grid_cell_size > 0.0 {
+            return;
+        }
+        let count = self.units.iter().filter(|u| u.in_battle()).count();
+        self.grid.tune(count, tuning_radius(&self.config, &self.units));
+    }
+
+    /// Swap in a different spatial index, tuned and filled from current positions
+    pub fn set_spatial_index(&mut self, index: I) {
+        self.grid = index;
+        self.tune_grid();
+        self.rebuild_spatial_grid();
+    }
+
     /// Rebuild spatial grid from current positions
     fn rebuild_spatial_grid(&mut self) {
         self.grid.clear();
         for (idx, unit) in self.units.iter().enumerate() {
-            if unit.alive {
+            if unit.on_battlefield() {
                 self.grid.insert(idx, unit.pos_x, unit.pos_y, unit.pos_z);
             }
         }
@@ -374,15 +2194,16 @@ impl BattleSimulator {
     pub fn force_retarget_all(&mut self) -> u32 {
         let mut changed = 0;
         
-        // First pass: clear all targets
+        // First pass: clear all targets (locked targets stay)
         for unit in self.units.iter_mut() {
-            if unit.alive && unit.target_id.is_some() {
+            if unit.is_alive() && unit.target_id.is_some() && !unit.target_locked {
                 unit.target_id = None;
                 changed += 1;
             }
         }
         
-        log(&format!("[Retarget] Cleared {} unit targets, will re-acquire next tick", changed));
+        log_at!(Info, "[Retarget] Cleared {} unit targets, will re-acquire next tick", changed);
+        self.record(ReplayCommand::ForceRetarget { unit_id: None });
         
         // ✅ NEW: Wake from idle when forcing retarget
         self.is_idle = false;
@@ -390,43 +2211,118 @@ impl BattleSimulator {
         changed
     }
 
-    /// Force a specific unit to re-evaluate its target
+    /// Force a specific unit to re-evaluate its target (false if missing or locked)
     pub fn force_retarget_unit(&mut self, unit_id: u32) -> bool {
-        if let Some(unit) = self.units.iter_mut().find(|u| u.id == unit_id && u.alive) {
-            unit.target_id = None;
+        if let Some(idx) = self.units.iter().position(|u| u.id == unit_id && u.is_alive() && !u.target_locked) {
+            self.units[idx].target_id = None;
             // ✅ NEW: Wake from idle
             self.is_idle = false;
+            self.record(ReplayCommand::ForceRetarget { unit_id: Some(unit_id) });
             true
         } else {
             false
         }
     }
 
-    /// Check if a target is still valid (alive, in range)
+    /// Pin a unit's target - the AI won't change it until the target dies
+    ///
+    /// The target must be a hostile unit still on the battlefield. Range isn't
+    /// checked; an out-of-range locked target just isn't fired at.
+    pub fn lock_target(&mut self, unit_id: u32, target_id: u32) -> bool {
+        let Some(target_faction) = self.units.iter()
+            .find(|t| t.id == target_id && t.is_valid_target())
+            .map(|t| t.faction_id) else {
+            return false;
+        };
+        let Some(idx) = self.units.iter().position(|u| u.id == unit_id && u.in_battle()) else {
+            return false;
+        };
+        if !self.relations.is_hostile(self.units[idx].faction_id, target_faction) {
+            return false;
+        }
+
+        let now = self.last_time;
+        let unit = &mut self.units[idx];
+        unit.set_target(Some(target_id), now);
+        unit.target_locked = true;
+        self.is_idle = false;
+        self.record(ReplayCommand::LockTarget { unit_id, target_id });
+        log_at!(Info, "[Target] Unit {} locked onto {}", unit_id, target_id);
+        true
+    }
+
+    /// Release a locked target - the unit keeps it until normal retargeting
+    /// replaces it (false if the unit isn't found or wasn't locked)
+    pub fn unlock_target(&mut self, unit_id: u32) -> bool {
+        match self.units.iter_mut().find(|u| u.id == unit_id && u.target_locked) {
+            Some(unit) => {
+                unit.target_locked = false;
+                self.is_idle = false;
+                self.record(ReplayCommand::UnlockTarget { unit_id });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Best chance any point defense covering the target (its own, or an
+    /// ally's within weapon range) has to stop one of `inbound` missiles
+    /// arriving together - `reach` is the longest PD range in the battle
+    fn point_defense_chance(&self, target_idx: usize, inbound: u32, reach: f32, nearby: &mut Vec<usize>) -> f32 {
+        let target = &self.units[target_idx];
+        self.grid.get_nearby_into(target.pos_x, target.pos_y, target.pos_z, reach, nearby);
+        nearby.iter()
+            .map(|&idx| &self.units[idx])
+            .filter(|d| d.in_battle() && !d.is_disabled && !self.relations.is_hostile(d.faction_id, target.faction_id))
+            .flat_map(|defender| {
+                let dist = defender.distance(target);
+                defender.weapons.iter().filter(move |w| intercepts_missiles(w) && dist <= w.max_range)
+            })
+            .map(|w| intercept_chance(w, inbound))
+            .fold(0.0, f32::max)
+    }
+
+    /// Check if a target is still valid (alive, hostile, detected, within reach)
+    ///
+    /// Reach is the same radius find_best_target searches - weapon range or
+    /// view_range, whichever is larger - so a target picked up at view range
+    /// stays valid while the unit closes in. Firing is still range-gated per
+    /// weapon (try_fire_weapon).
     fn is_target_valid(&self, attacker_idx: usize, target_id: u32) -> bool {
         let attacker = &self.units[attacker_idx];
         
         // Find target
-        if let Some(target) = self.units.iter().find(|u| u.id == target_id) {
-            // Must be alive
-            if !target.alive {
+        if let Some(target_idx) = self.units.iter().position(|u| u.id == target_id) {
+            let target = &self.units[target_idx];
+            // Must be alive and still on the battlefield (disabled ones for
+            // siege weapons only - see the range check)
+            if !target.is_siege_target() {
                 return false;
             }
             
-            // Must be enemy
-            if target.faction_id == attacker.faction_id {
+            // Must be enemy, and on the faction's sensors
+            if !self.relations.is_hostile(attacker.faction_id, target.faction_id)
+                || !self.detection.is_detected(attacker.faction_id, target_idx)
+            {
                 return false;
             }
             
-            // Must be within weapon range - NO buffer, strict check
+            // Needs a weapon that can hit it (point defense doesn't count,
+            // siege only against stations)...
             let dist_sq = attacker.distance_sq(target);
-            let max_range = attacker.max_weapon_range;
+            let max_range = if target.is_disabled {
+                if target.is_station { attacker.max_siege_range() } else { 0.0 }
+            } else {
+                attacker.max_offensive_range(target.is_station)
+            };
             
             if max_range <= 0.0 {
-                return false; // No weapons = can't attack
+                return false; // No weapons that can hit this target
             }
             
-            if dist_sq > max_range * max_range {
+            // ...and has to be in weapon or view range - NO buffer, strict check
+            let reach = max_range.max(attacker.view_range);
+            if dist_sq > reach * reach {
                 return false;
             }
             
@@ -436,49 +2332,206 @@ impl BattleSimulator {
         }
     }
 
-    /// Find enemy within weapon range (fallback when spatial grid finds nothing)
-    /// Returns the index of the nearest enemy unit WITHIN WEAPON RANGE ONLY
-    fn find_any_enemy(&self, attacker_idx: usize) -> Option<usize> {
+    /// Work out which enemies every faction can see this tick (detection.rs)
+    ///
+    /// A unit detects enemies within its sensor_range() times their
+    /// stealth_modifier. Expects the grid to be current;
+    /// `unseen` is scratch.
+    fn update_detection(&mut self, in_range: &mut Vec<(usize, f32)>, unseen: &mut Vec<(u32, usize)>) {
+        self.detection.begin_tick(self.units.len());
+        let max_stealth = self.units.iter()
+            .filter(|u| u.on_battlefield())
+            .fold(0.0f32, |max, u| max.max(u.stealth_modifier));
+
+        // (faction, enemies it hasn't spotted yet) - once a faction sees
+        // every enemy its other units needn't look
+        unseen.clear();
+        for unit in self.units.iter().filter(|u| u.in_battle()) {
+            if !unseen.iter().any(|&(f, _)| f == unit.faction_id) {
+                unseen.push((unit.faction_id, 0));
+            }
+        }
+        for unit in self.units.iter().filter(|u| u.on_battlefield()) {
+            for (faction_id, enemies) in unseen.iter_mut() {
+                if self.relations.is_hostile(*faction_id, unit.faction_id) {
+                    *enemies += 1;
+                }
+            }
+        }
+
+        for spotter in self.units.iter().filter(|u| u.in_battle()) {
+            let range = spotter.sensor_range();
+            let Some(remaining) = unseen.iter_mut().find(|(f, _)| *f == spotter.faction_id).map(|(_, n)| n) else {
+                continue;
+            };
+            if range <= 0.0 || max_stealth <= 0.0 || *remaining == 0 {
+                continue;
+            }
+            self.grid.query_range_into(spotter.pos_x, spotter.pos_y, spotter.pos_z, range * max_stealth, in_range);
+            let detected = self.detection.flags_mut(spotter.faction_id);
+            for &(idx, dist_sq) in in_range.iter() {
+                if detected.get(idx).is_none_or(|&seen| seen) {
+                    continue;
+                }
+                let other = &self.units[idx];
+                let reach = range * other.stealth_modifier;
+                if dist_sq <= reach * reach && other.on_battlefield() && self.relations.is_hostile(spotter.faction_id, other.faction_id) {
+                    detected[idx] = true;
+                    *remaining = remaining.saturating_sub(1);
+                }
+            }
+        }
+
+        let units = &self.units;
+        self.detection.finish(
+            |id| units.iter().position(|u| u.id == id).filter(|&idx| units[idx].on_battlefield()),
+            |idx| units[idx].id,
+        );
+    }
+
+    /// Targeting decision for one unit - read-only, see simulate_tick step 2
+    ///
+    /// Returns None to keep the current target, or Some(new_target) (which may
+    /// be the same target, or None when nothing is in range).
+    fn choose_target(&self, idx: usize, in_range: &mut Vec<(usize, f32)>) -> Option<Option<u32>> {
+        let unit = &self.units[idx];
+        if !unit.in_battle() || !unit.has_weapons {
+            return None;
+        }
+
+        let current_target = unit.target_id;
+        // Locked targets are kept even out of range (they're cleared when they die)
+        if unit.target_locked && current_target.is_some() {
+            return None;
+        }
+        // Group focus target whenever it's in range, automatic targeting until then
+        if let Some(focus) = self.group_target(unit) {
+            if self.is_target_valid(idx, focus) {
+                return (current_target != Some(focus)).then_some(Some(focus));
+            }
+        }
+        let target_valid = current_target.is_some_and(|tid| self.is_target_valid(idx, tid));
+        let should_retarget = 
+            // No target / current target is no longer valid
+            !target_valid ||
+            // Periodic re-evaluation (every config.retarget_interval_secs)
+            self.tick.is_multiple_of(self.ticks.retarget_interval.max(1)) ||
+            // Reinforcements just arrived nearby
+            self.retarget_now.get(idx).copied().unwrap_or(false);
+        if !should_retarget {
+            return None;
+        }
+
+        // A still-valid target gets a bias during periodic re-evaluation
+        let incumbent = if target_valid {
+            current_target.and_then(|tid| self.units.iter().position(|u| u.id == tid))
+        } else {
+            None
+        };
+
+        let new_target = if unit.retreating {
+            // Retreating units only return fire at enemies already in weapon range
+            self.find_any_enemy(idx, in_range).map(|enemy_idx| self.units[enemy_idx].id)
+        } else if let Some(enemy_idx) = find_best_target(
+            unit,
+            &self.units,
+            &self.grid,
+            self.detection.detected_by(unit.faction_id),
+            &self.relations,
+            &self.config.priority_table,
+            incumbent,
+            self.config.retarget_switch_margin,
+            self.config.target_spread_factor,
+            in_range,
+        ) {
+            // Found new target using spatial grid
+            let new_target = self.units[enemy_idx].id;
+
+            // Log target changes
+            if current_target.is_some() && current_target != Some(new_target) && unit.id.is_multiple_of(50) {
+                log_at!(Info,
+                    "[Target] Unit {} retargeted: {:?} -> {}",
+                    unit.id, current_target, new_target
+                );
+            }
+            Some(new_target)
+        } else {
+            // Nothing in view range - the unit sits idle until something comes along
+            None
+        };
+
+        Some(new_target)
+    }
+
+    /// Best enemy already within weapon range (targets for retreating units)
+    ///
+    /// Scored by targeting::find_enemy_in_range, so the priority rules are
+    /// the same as find_best_target's. Expects the spatial grid to be current.
+    fn find_any_enemy(&self, attacker_idx: usize, in_range: &mut Vec<(usize, f32)>) -> Option<usize> {
         let attacker = &self.units[attacker_idx];
-        let max_range = attacker.max_weapon_range;
+        let max_range = attacker.max_offensive_range(true);
         
-        // No weapons = can't target anything
+        // No offensive weapons = can't target anything
         if max_range <= 0.0 {
             return None;
         }
         
-        let max_range_sq = max_range * max_range;
-        let mut best_idx: Option<usize> = None;
-        let mut best_dist_sq = f32::MAX;
-        
-        for (idx, other) in self.units.iter().enumerate() {
-            // Skip self, dead, allies
-            if idx == attacker_idx || !other.alive || other.faction_id == attacker.faction_id {
-                continue;
-            }
-            
-            let dist_sq = attacker.distance_sq(other);
-            
-            // ✅ ONLY target enemies within weapon range
-            if dist_sq <= max_range_sq && dist_sq < best_dist_sq {
-                best_dist_sq = dist_sq;
-                best_idx = Some(idx);
-            }
-        }
+        // ✅ ONLY target enemies within weapon range - query_range is exact
+        self.grid.query_range_into(attacker.pos_x, attacker.pos_y, attacker.pos_z, max_range, in_range);
+        let detected = self.detection.detected_by(attacker.faction_id);
+        let best_idx = find_enemy_in_range(attacker, &self.units, in_range.iter().copied(), detected, &self.relations, &self.config.priority_table);
         
-        if best_idx.is_some() {
-            log(&format!(
+        if let Some(idx) = best_idx {
+            log_at!(Debug,
                 "[Targeting] Unit {} found enemy in range at distance {:.1} (max_range={:.1})",
-                attacker.id, best_dist_sq.sqrt(), max_range
-            ));
+                attacker.id, attacker.distance_sq(&self.units[idx]).sqrt(), max_range
+            );
         }
         
         best_idx
     }
 
     /// Main simulation tick
+    ///
+    /// current_time is the host clock; weapon timing uses simulated time,
+    /// which doesn't move while paused.
     pub fn simulate_tick(&mut self, dt: f32, current_time: f64) -> TickResult {
+        if self.paused {
+            return TickResult::empty(self.tick, false);
+        }
+        if self.resync_clock {
+            self.time_offset = current_time - (self.last_time + dt as f64);
+            self.resync_clock = false;
+            log_at!(Debug, "[Simulator] Clock re-synced, {:.2}s behind host time", self.time_offset);
+        }
+        self.advance(dt, current_time - self.time_offset)
+    }
+
+    /// One tick at simulated time current_time, timed
+    fn advance(&mut self, dt: f32, current_time: f64) -> TickResult {
+        let started = now_ms();
+        let mut result = self.run_tick(dt, current_time);
+        let us = ((now_ms() - started) * 1000.0).round().min(u32::MAX as f64) as u32;
+        if us > SLOW_TICK_US {
+            log_at!(Warn, "[Simulator] Tick {} took {:.1}ms ({} units)", self.tick, us as f64 / 1000.0, self.units.len());
+        }
+        self.tick_durations.record(us);
+        result.tick_duration_us = us;
+        result
+    }
+
+    fn run_tick(&mut self, dt: f32, current_time: f64) -> TickResult {
+        if self.recorder.enabled {
+            self.recorder.end_tick(dt, current_time);
+        }
+
         self.tick += 1;
+        self.last_time = current_time;
+        self.rng.begin_tick(self.config.mode, self.tick);
+        self.buffers.spawned.clear();
+        self.buffers.arrived.clear();
+        self.spawn_reinforcements(current_time);
+        self.process_drone_hangars(current_time);
 
         // ✅ NEW: Check if we should be in idle mode
         let should_idle = self.should_be_idle(current_time);
@@ -489,394 +2542,6095 @@ impl BattleSimulator {
                 // Just entered idle mode
                 self.is_idle = true;
                 self.idle_tick_count = 0;
-                log(&format!(
+                log_at!(Info,
                     "[Idle] ENTERING idle mode at tick {} - no movement for {} ticks, next weapon ready at {:.2}",
                     self.tick, 
                     self.tick.saturating_sub(self.last_movement_tick),
                     self.next_weapon_ready_time
-                ));
+                );
             }
             
             self.do_idle_tick(dt);
             
-            // Log idle status periodically (every 5 seconds = 100 ticks)
-            if self.tick % 100 == 0 {
-                log(&format!(
+            // Log idle status periodically (every 5 seconds)
+            if self.tick.is_multiple_of(self.ticks.second * 5) {
+                log_at!(Debug,
                     "[Idle] Tick {}: idle for {} ticks, next weapon ready in {:.1}s",
                     self.tick,
                     self.idle_tick_count,
                     (self.next_weapon_ready_time - current_time).max(0.0)
-                ));
+                );
             }
             
-            return TickResult {
-                moved: vec![],
-                damaged: vec![],
-                destroyed: vec![],
-                tick: self.tick,
-                weapons_fired: vec![],
-                is_idle: true,
-            };
+            let mut result = TickResult::empty(self.tick, true);
+            result.launched = std::mem::take(&mut self.launched);
+            result.docked = std::mem::take(&mut self.docked);
+            result.withdrawn = std::mem::take(&mut self.removed);
+            result.resources_gained = self.process_resource_nodes();
+            result.objectives_met = self.evaluate_objectives();
+            result.stalemate_warning = self.stalemate_warning();
+            if self.is_keyframe_tick() {
+                self.append_keyframe(&mut result.moved, &mut Vec::new());
+                result.is_keyframe = true;
+            }
+            return result;
         }
 
         // ✅ NEW: Exiting idle mode
         if self.is_idle {
-            log(&format!(
+            log_at!(Info,
                 "[Idle] EXITING idle mode at tick {} after {} idle ticks",
                 self.tick, self.idle_tick_count
-            ));
+            );
             self.is_idle = false;
             self.idle_tick_count = 0;
         }
 
-        // DEBUG: Log tick start (every 20 ticks = ~1 second)
-        if self.tick % 20 == 0 {
-            let alive_count = self.units.iter().filter(|u| u.alive).count();
-            let with_targets = self.units.iter().filter(|u| u.alive && u.target_id.is_some()).count();
-            let with_weapons = self.units.iter().filter(|u| u.alive && u.has_weapons).count();
-            log(&format!(
+        // DEBUG: Log tick start (once a second)
+        if self.tick.is_multiple_of(self.ticks.second) {
+            let alive_count = self.units.iter().filter(|u| u.is_alive()).count();
+            let with_targets = self.units.iter().filter(|u| u.is_alive() && u.target_id.is_some()).count();
+            let with_weapons = self.units.iter().filter(|u| u.is_alive() && u.has_weapons).count();
+            log_at!(Debug,
                 "[Simulator] Tick {}: alive={}, with_targets={}, with_weapons={}, dt={:.3}s",
                 self.tick, alive_count, with_targets, with_weapons, dt
-            ));
+            );
         }
 
+        let mut timer = PhaseTimer::start(self.config.profiling);
+        let window = self.config.profile_window;
+
         // 1. Update spatial grid - O(n)
-        self.grid.clear();
-        for (idx, unit) in self.units.iter().enumerate() {
-            if unit.alive {
-                self.grid.insert(idx, unit.pos_x, unit.pos_y, unit.pos_z);
-            }
+        if self.config.auto_tune_grid && self.tick.is_multiple_of(self.ticks.grid_tune_interval.max(1)) {
+            self.tune_grid();
         }
+        self.rebuild_spatial_grid();
+        timer.lap(&mut self.profiler, Phase::Grid, window);
 
         // 2. Target acquisition and validation - O(k) per unit
         // Now validates existing targets and periodically re-evaluates
-        for idx in 0..self.units.len() {
-            if !self.units[idx].alive || !self.units[idx].has_weapons {
-                continue;
-            }
-
-            let current_target = self.units[idx].target_id;
-            let should_retarget = 
-                // No target
-                current_target.is_none() ||
-                // Periodic re-evaluation (every RETARGET_INTERVAL ticks)
-                (self.tick % RETARGET_INTERVAL == 0) ||
-                // Current target is no longer valid
-                (current_target.is_some() && !self.is_target_valid(idx, current_target.unwrap()));
-
-            if should_retarget {
-                // Clear old target
-                self.units[idx].target_id = None;
-                
-                // Find new target using spatial grid
-                if let Some(enemy_idx) = find_best_target(&self.units[idx], &self.units, &self.grid) {
-                    let old_target = current_target;
-                    let new_target = self.units[enemy_idx].id;
-                    self.units[idx].target_id = Some(new_target);
-                    
-                    // Log target changes
-                    if old_target.is_some() && old_target != Some(new_target) && self.units[idx].id % 50 == 0 {
-                        log(&format!(
-                            "[Target] Unit {} retargeted: {:?} -> {}",
-                            self.units[idx].id, old_target, new_target
-                        ));
-                    }
-                } else {
-                    // Spatial grid found nothing nearby - search all units within weapon range
-                    if let Some(enemy_idx) = self.find_any_enemy(idx) {
-                        let new_target = self.units[enemy_idx].id;
-                        self.units[idx].target_id = Some(new_target);
-                    }
-                    // If still no target, unit has no enemies in weapon range - it will sit idle
-                }
-            }
-        }
+        //
+        // Read phase: decide every unit's new target against an unchanged world.
+        // Decisions only read positions/factions of other units (never their
+        // target_id), so splitting read and write gives the same result as the
+        // old interleaved loop.
+        //
+        // Buffers are taken out of self for the rest of the tick so they can be
+        // filled while self is borrowed, and put back before returning.
+        let mut buffers = std::mem::take(&mut self.buffers);
+        // Small battles aren't worth rayon's overhead
+        let parallel = cfg!(feature = "parallel") && self.units.len() >= self.config.parallel_threshold;
 
-        // 3. Movement - USER INPUT ONLY
-        // Simulator does NOT auto-move units. All movement comes from player input
-        // via the position sync system (update_positions / update_single_position)
-        let moved: Vec<MovedUnit> = Vec::new();
+        self.update_detection(&mut buffers.in_range, &mut buffers.unseen_enemies);
+        let (mut contacts_detected, mut contacts_lost) = (std::mem::take(&mut buffers.contacts_detected), std::mem::take(&mut buffers.contacts_lost));
+        let units = &self.units;
+        self.detection.changes(&mut contacts_detected, &mut contacts_lost, |id| units.iter().position(|u| u.id == id).is_some_and(|idx| units[idx].is_alive()));
 
-        // 4. Combat - O(n) weapons
-        self.damage_queue.clear();
-
-        let mut weapon_fires: Vec<(usize, usize, f32, usize, f32, String)> = Vec::new();
-        let mut units_with_target = 0;
-        let mut units_checked_weapons = 0;
+        buffers.retargets.clear();
+        if parallel {
+            // One scratch buffer per rayon job rather than per unit
+            #[cfg(feature = "parallel")]
+            buffers.retargets.par_extend((0..self.units.len())
+                .into_par_iter()
+                .map_init(Vec::new, |in_range, idx| self.choose_target(idx, in_range).map(|target| (idx, target)))
+                .flatten());
+        } else {
+            let in_range = &mut buffers.in_range;
+            buffers.retargets.extend((0..self.units.len())
+                .filter_map(|idx| self.choose_target(idx, in_range).map(|target| (idx, target))));
+        }
 
-        for attacker_idx in 0..self.units.len() {
-            if !self.units[attacker_idx].alive || !self.units[attacker_idx].has_weapons {
-                continue;
+        // Write phase: serial. Safe to have computed in parallel above because
+        // the read phase only took &self, and each idx appears once here - no
+        // two tasks ever decide for (or write to) the same unit.
+        for &(idx, new_target) in &buffers.retargets {
+            if let Some(target_id) = new_target.filter(|&t| self.units[idx].target_id != Some(t)) {
+                self.combat_log.push(self.tick, CombatLogEntry::TargetAcquired { unit_id: self.units[idx].id, target_id });
             }
+            // Keeping the same target keeps the lock
+            self.units[idx].set_target(new_target, current_time);
+        }
+        self.retarget_now.clear();
 
-            let attacker_target_id = self.units[attacker_idx].target_id;
-            if attacker_target_id.is_none() {
+        // 2a. AI units shift reactor power for the fight they're in - before
+        // movement and combat read it. In combat = target within weapon range
+        for idx in 0..self.units.len() {
+            let unit = &self.units[idx];
+            if !(unit.ai_controlled && unit.in_battle()) {
                 continue;
             }
-            units_with_target += 1;
-
-            let target_id = attacker_target_id.unwrap();
+            let in_combat = unit.target_id
+                .and_then(|id| self.units.iter().find(|t| t.id == id))
+                .is_some_and(|target| unit.distance_sq(target) <= unit.max_weapon_range * unit.max_weapon_range);
+            self.units[idx].auto_balance_power(in_combat);
+        }
+        timer.lap(&mut self.profiler, Phase::Targeting, window);
 
-            // Find target index
-            let target_idx_opt = self.units.iter().position(|u| u.id == target_id && u.alive);
-            if target_idx_opt.is_none() {
-                // Clear dead target so unit can acquire new one next tick
-                self.units[attacker_idx].target_id = None;
-                continue;
+        // 3. Movement - player units move via the position sync system
+        // (update_positions / update_single_position). The simulator only moves
+        // ai_controlled units, units with waypoints (offline players) or a move
+        // order, and retreating units it is allowed to steer (see step 6)
+        let mut moved = std::mem::take(&mut buffers.moved);
+        let steered = &mut buffers.steered;
+        moved.clear();
+        steered.clear();
+        for idx in 0..self.units.len() {
+            let unit = &self.units[idx];
+            if unit.is_engaged() && (unit.ai_controlled || !unit.waypoints.is_empty() || unit.move_order.is_some()) {
+                steered.push((idx, unit.pos_x, unit.pos_y, unit.pos_z));
+                self.move_unit(idx, dt, &mut buffers.nearby);
+                self.clamp_to_bounds(idx);
+            }
+        }
+        if !steered.is_empty() {
+            // Keep grid entries in sync with simulator-driven moves
+            self.rebuild_spatial_grid();
+            self.separate_units(steered, &mut buffers.nearby, &mut buffers.movable);
+            for &(idx, ..) in steered.iter() {
+                self.clamp_to_bounds(idx);
             }
-            let target_idx = target_idx_opt.unwrap();
+            self.rebuild_spatial_grid();
 
-            // Check each weapon
-            for (weapon_idx, weapon) in self.units[attacker_idx].weapons.iter().enumerate() {
-                units_checked_weapons += 1;
-                
-                if is_point_defense(weapon) {
-                    continue;
+            for &(idx, old_x, old_y, old_z) in steered.iter() {
+                let unit = &self.units[idx];
+                if unit.pos_x != old_x || unit.pos_y != old_y || unit.pos_z != old_z {
+                    moved.push(MovedUnit::from_move(unit, (old_x, old_y, old_z), dt));
                 }
+            }
+        }
+        timer.lap(&mut self.profiler, Phase::Movement, window);
 
-                let attacker = &self.units[attacker_idx];
-                let target = &self.units[target_idx];
+        // 4. Combat - O(n) weapons
+        buffers.damage_entries.clear();
+        buffers.repair_entries.clear();
 
-                if let Some(damage) = try_fire_weapon(attacker, target, weapon, current_time, self.tick) {
-                    let distance = attacker.distance(target);
-                    weapon_fires.push((
-                        attacker_idx,
-                        target_idx,
-                        damage,
-                        weapon_idx,
-                        distance,
-                        weapon.tag.clone()
-                    ));
-                }
+        // 4a. Self-destructs - before anything fires, so nothing shoots at (or
+        // from) a unit that just blew up
+        let self_destructed = self.detonate_self_destructs(&mut buffers.damage_entries, &mut buffers.in_range);
+
+        // Refill magazines whose reload has finished
+        for unit in self.units.iter_mut() {
+            for weapon in unit.weapons.iter_mut() {
+                weapon.update_reload(current_time);
+            }
+        }
+
+        // Collect fires - read-only per attacker, so it can run in parallel
+        let (units, grid, detection, relations, tick) = (&self.units, &self.grid, &self.detection, &self.relations, self.tick);
+        let priorities = &self.config.priority_table;
+        let restrictions = &self.weapon_class_restrictions;
+        let faction_configs = &self.faction_configs;
+        let weapon_fires = &mut buffers.weapon_fires;
+        let fire_stats = &mut buffers.fire_stats;
+        weapon_fires.clear();
+        fire_stats.clear();
+        if parallel {
+            #[cfg(feature = "parallel")]
+            let per_attacker: Vec<(AttackerFires, Vec<PendingFire>)> = (0..units.len())
+                .into_par_iter()
+                .map_init(Vec::new, |in_range, attacker_idx| {
+                    let mut fires = Vec::new();
+                    let stats = collect_weapon_fires(units, grid, detection, relations, priorities, restrictions, faction_configs, attacker_idx, current_time, tick, &mut fires, in_range);
+                    (stats, fires)
+                })
+                .collect();
+            #[cfg(feature = "parallel")]
+            for (stats, fires) in per_attacker {
+                fire_stats.push(stats);
+                weapon_fires.extend(fires);
+            }
+        } else {
+            let in_range = &mut buffers.in_range;
+            fire_stats.extend((0..units.len())
+                .map(|attacker_idx| collect_weapon_fires(units, grid, detection, relations, priorities, restrictions, faction_configs, attacker_idx, current_time, tick, weapon_fires, in_range)));
+        }
+
+        let mut units_with_target = 0;
+        let mut units_checked_weapons = 0;
+
+        for (attacker_idx, collected) in fire_stats.iter().enumerate() {
+            if collected.has_target {
+                units_with_target += 1;
+            }
+            if collected.target_lost {
+                // Clear dead target so unit can acquire new one next tick
+                self.units[attacker_idx].clear_target();
             }
+            units_checked_weapons += collected.weapons_checked;
         }
 
         // DEBUG: Log combat summary
-        if self.tick % 20 == 0 {
-            log(&format!(
+        if self.tick.is_multiple_of(self.ticks.second) {
+            log_at!(Debug,
                 "[Combat] Tick {}: units_with_target={}, weapons_checked={}, weapons_fired={}",
                 self.tick, units_with_target, units_checked_weapons, weapon_fires.len()
-            ));
+            );
         }
 
         // Process weapon fires
-        let mut weapons_fired: Vec<WeaponFired> = Vec::new();
+        let mut weapons_fired = std::mem::take(&mut buffers.weapons_fired);
+        weapons_fired.clear();
+
+        let effects_changed = &mut buffers.effects_changed;
+        effects_changed.clear();
+
+        // Any shot at an enemy counts as combat for stalemate detection, even if
+        // no damage lands this tick
+        let mut hostile_fire = !self.manual_shots.is_empty() || !self.retargeted_shots.is_empty() || !self_destructed.is_empty();
+
+        // Guided shots that turned on a new target last tick land now
+        buffers.damage_entries.append(&mut self.retargeted_shots);
+
+        // Manual shots first - cooldown, ammo and the hit roll were spent when
+        // they were fired
+        let mut manual_shots = std::mem::take(&mut self.manual_shots);
+        for shot in manual_shots.drain(..) {
+            let attacker = &mut self.units[shot.attacker_idx];
+            attacker.shots_fired += 1;
+            attacker.weapon_stats_mut(shot.weapon_idx).shots_fired += 1;
+            if !shot.fired.hit {
+                weapons_fired.push(shot.fired);
+                continue;
+            }
+            let weapon = self.units[shot.attacker_idx].weapons.get(shot.weapon_idx);
+            buffers.damage_entries.push(DamageEntry {
+                target_idx: shot.target_idx,
+                damage: shot.damage,
+                attacker_idx: Some(shot.attacker_idx),
+                weapon_idx: Some(shot.weapon_idx),
+                shield_pierce: weapon.map_or(0.0, |w| w.shield_pierce),
+                shield_damage_bonus: weapon.map_or(1.0, |w| w.shield_damage_bonus),
+                retargeted: false,
+                blast: false,
+            });
+            let on_hit = weapon.and_then(|w| w.applies_effect.clone());
+            if let Some(spec) = on_hit {
+                let effect = spec.instantiate(self.tick, dt, self.units[shot.attacker_idx].id);
+                self.units[shot.target_idx].apply_effect(effect);
+                effects_changed.push(shot.target_idx);
+            }
+            weapons_fired.push(shot.fired);
+        }
+        self.manual_shots = manual_shots;
+
+        // Missiles fired at the same unit this tick arrive together - a volley
+        // saturates the point defense covering it
+        let inbound = &mut buffers.inbound_missiles;
+        inbound.clear();
+        let pd_reach = self.units.iter()
+            .filter(|u| u.in_battle() && !u.is_disabled)
+            .flat_map(|u| u.weapons.iter().filter(|w| intercepts_missiles(w)))
+            .map(|w| w.max_range)
+            .fold(0.0f32, f32::max);
+        if pd_reach > 0.0 {
+            inbound.resize(self.units.len(), 0);
+            for &(attacker_idx, target_idx, _, weapon_idx, _, _) in weapon_fires.iter() {
+                if self.units[attacker_idx].weapons.get(weapon_idx).is_some_and(|w| is_guided(w) && !w.is_repair) {
+                    inbound[target_idx] += 1;
+                }
+            }
+        }
 
-        for (attacker_idx, target_idx, damage, weapon_idx, distance, weapon_tag) in weapon_fires {
+        for (attacker_idx, target_idx, damage, weapon_idx, distance, chance) in weapon_fires.drain(..) {
+            let mut ammo_remaining = None;
+            let mut on_hit = None;
+            let mut repair = None;
+            let (mut shield_pierce, mut shield_damage_bonus) = (0.0, 1.0);
             if weapon_idx < self.units[attacker_idx].weapons.len() {
-                self.units[attacker_idx].weapons[weapon_idx].last_fired = current_time;
+                let weapon = &mut self.units[attacker_idx].weapons[weapon_idx];
+                weapon.last_fired = current_time;
+                weapon.consume_ammo(current_time);
+                ammo_remaining = weapon.ammo;
+                (shield_pierce, shield_damage_bonus) = (weapon.shield_pierce, weapon.shield_damage_bonus);
+                if weapon.is_repair {
+                    repair = Some(weapon.repairs_shield);
+                } else {
+                    on_hit = weapon.applies_effect.clone();
+                }
+                let cost = weapon.energy_cost;
+                self.units[attacker_idx].spend_energy(cost);
             }
 
-            self.damage_queue.push(DamageEntry {
-                target_idx,
-                damage,
-                attacker_idx,
-            });
+            // Only roll when it can miss, so always-hit battles draw nothing
+            let rolled_hit = chance >= 1.0 || self.rng.next_f64() < chance as f64;
+            let inbound = buffers.inbound_missiles.get(target_idx).copied().unwrap_or(0);
+            let intercepted = rolled_hit && repair.is_none() && inbound > 0
+                && self.units[attacker_idx].weapons.get(weapon_idx).is_some_and(is_guided)
+                && {
+                    let intercept = self.point_defense_chance(target_idx, inbound, pd_reach, &mut buffers.nearby);
+                    intercept > 0.0 && self.rng.next_f64() < intercept as f64
+                };
+            if intercepted {
+                log_at!(Debug,
+                    "[AM] Missile from {} shot down before reaching {} ({} inbound)",
+                    self.units[attacker_idx].id, self.units[target_idx].id, inbound
+                );
+            }
+            let hit = rolled_hit && !intercepted;
+            if let Some(repairs_shield) = repair {
+                buffers.repair_entries.push(RepairEntry {
+                    target_idx,
+                    amount: damage,
+                    healer_idx: attacker_idx,
+                    repairs_shield,
+                });
+            } else {
+                if hit {
+                    buffers.damage_entries.push(DamageEntry {
+                        target_idx,
+                        damage,
+                        attacker_idx: Some(attacker_idx),
+                        weapon_idx: Some(weapon_idx),
+                        shield_pierce,
+                        shield_damage_bonus,
+                        retargeted: false,
+                        blast: false,
+                    });
+                }
+                let attacker = &mut self.units[attacker_idx];
+                attacker.shots_fired += 1;
+                if weapon_idx < attacker.weapons.len() {
+                    attacker.weapon_stats_mut(weapon_idx).shots_fired += 1;
+                }
+                hostile_fire = true;
+            }
 
-            weapons_fired.push(WeaponFired {
-                attacker_id: self.units[attacker_idx].id,
-                target_id: self.units[target_idx].id,
-                impact_time: calculate_impact_time(distance, &weapon_tag),
-                weapon_type: weapon_tag,
+            if let Some(spec) = on_hit.filter(|_| hit) {
+                let effect = spec.instantiate(self.tick, dt, self.units[attacker_idx].id);
+                self.units[target_idx].apply_effect(effect);
+                effects_changed.push(target_idx);
+            }
+
+            let (attacker, target) = (&self.units[attacker_idx], &self.units[target_idx]);
+            let weapon_tag = attacker.weapons.get(weapon_idx).map_or("", |w| w.tag.as_str());
+            let fired = WeaponFired {
+                attacker_id: attacker.id,
+                target_id: target.id,
+                impact_time: calculate_impact_time(distance, weapon_tag),
+                weapon_type: weapon_tag.to_string(),
+                ammo_remaining,
+                hit,
+                intercepted,
+                ..Default::default()
+            };
+            weapons_fired.push(if self.config.include_fire_positions {
+                fired.with_positions(attacker, target, shot_seed(self.tick, attacker.id, weapon_idx))
+            } else {
+                fired
             });
         }
 
+        // Firing keeps a unit on the sensors of factions that already see it
+        self.detection.set_firing(weapons_fired.iter().map(|fired| fired.attacker_id));
+
+        if self.combat_log.is_enabled() {
+            for fired in &weapons_fired {
+                self.combat_log.push(self.tick, CombatLogEntry::WeaponFired(fired.clone()));
+            }
+        }
+
+        // 4b. Status effects - queue burn damage, drop expired effects
+        self.process_effects(effects_changed, &mut buffers.damage_entries);
+        timer.lap(&mut self.profiler, Phase::Combat, window);
+
         // 5. Process damage queue
-        // FIXED: Restructured to avoid double mutable borrow
-        let mut damage_by_target: HashMap<usize, f32> = HashMap::new();
-        for entry in &self.damage_queue {
-            *damage_by_target.entry(entry.target_idx).or_insert(0.0) += entry.damage;
-        }
-
-        let mut destroyed: Vec<u32> = Vec::new();
-        let mut damaged: Vec<DamagedUnit> = Vec::new();
-        let mut destroyed_unit_ids: Vec<u32> = Vec::new(); // Collect destroyed IDs separately
-
-        for (&target_idx, &total_damage) in &damage_by_target {
-            // Extract all needed values BEFORE any nested iteration
-            let unit_id: u32;
-            let unit_hp: f32;
-            let unit_shield: f32;
-            let was_destroyed: bool;
-            
-            {
-                // Scoped mutable borrow
-                let unit = &mut self.units[target_idx];
-                let was_alive = unit.alive;
-                
-                unit.take_damage(total_damage);
-                
-                unit_id = unit.id;
-                unit_hp = unit.hp;
-                unit_shield = unit.shield;
-                was_destroyed = was_alive && !unit.alive;
-            } // Mutable borrow ends here
-            
-            if was_destroyed {
-                destroyed.push(unit_id);
-                destroyed_unit_ids.push(unit_id);
-                log(&format!("[Damage] Unit {} DESTROYED!", unit_id));
-            } else if total_damage > 0.0 {
-                damaged.push(DamagedUnit {
-                    id: unit_id,
-                    hp: unit_hp,
-                    shield: unit_shield,
+        // Allies never hurt each other - catches burns applied before an alliance
+        if self.relations.has_alliances() {
+            let (units, relations) = (&self.units, &self.relations);
+            buffers.damage_entries.retain(|entry| entry.blast || entry.attacker_idx.is_none_or(|attacker_idx| {
+                relations.is_hostile(units[attacker_idx].faction_id, units[entry.target_idx].faction_id)
+            }));
+        }
+        // Disabled units only take siege fire - catches burns and shots queued
+        // before they shut down
+        if self.units.iter().any(|u| u.is_disabled) {
+            let units = &self.units;
+            buffers.damage_entries.retain(|entry| {
+                !units[entry.target_idx].is_disabled
+                    || entry.blast
+                    || entry.attacker_idx.zip(entry.weapon_idx)
+                        .is_some_and(|(a, w)| units[a].weapons[w].category == WeaponCategory::Siege)
+            });
+        }
+
+        // Sum per target into a dense vec indexed like units
+        let hits_by_target = &mut buffers.hits_by_target;
+        let damage_by_target = &mut buffers.damage_by_target;
+        let dealt_by_attacker = &mut buffers.dealt_by_attacker;
+        hits_by_target.clear();
+        hits_by_target.resize(self.units.len(), DamageSplit::default());
+        damage_by_target.clear();
+        damage_by_target.resize(self.units.len(), 0.0);
+        dealt_by_attacker.clear();
+        dealt_by_attacker.resize(self.units.len(), 0.0);
+        for entry in &buffers.damage_entries {
+            hits_by_target[entry.target_idx] += entry.split();
+        }
+
+        // Apply - each unit only touches itself, so the slice can be split across threads
+        let outcomes = &mut buffers.outcomes;
+        outcomes.clear();
+        if parallel {
+            #[cfg(feature = "parallel")]
+            outcomes.par_extend(self.units
+                .par_iter_mut()
+                .zip(hits_by_target.par_iter())
+                .zip(damage_by_target.par_iter_mut())
+                .enumerate()
+                .filter_map(|(idx, ((unit, hit), absorbed))| apply_damage(idx, unit, hit, absorbed)));
+        } else {
+            outcomes.extend(self.units
+                .iter_mut()
+                .zip(hits_by_target.iter())
+                .zip(damage_by_target.iter_mut())
+                .enumerate()
+                .filter_map(|(idx, ((unit, hit), absorbed))| apply_damage(idx, unit, hit, absorbed)));
+        }
+
+        // Credit attackers in queue order until the absorbed damage runs out -
+        // shots landing after the kill still spent their cooldown but earn nothing
+        // The entry that used up the last of a dying target's absorb gets the kill
+        for entry in &buffers.damage_entries {
+            let left = &mut damage_by_target[entry.target_idx];
+            let credited = entry.damage.min(*left);
+            *left -= credited;
+            if credited > 0.0 && self.combat_log.is_enabled() {
+                self.combat_log.push(self.tick, CombatLogEntry::DamageDealt {
+                    attacker: entry.attacker_idx.map(|idx| self.units[idx].id),
+                    target: self.units[entry.target_idx].id,
+                    damage: credited,
                 });
             }
+            let Some(attacker_idx) = entry.attacker_idx else {
+                continue;
+            };
+            dealt_by_attacker[attacker_idx] += credited;
+            // A guided shot landing on a target that's already dead is still
+            // in the air - it looks for another below
+            if credited <= 0.0 && !self.units[entry.target_idx].is_alive() {
+                if entry.weapon_idx.and_then(|w| self.units[attacker_idx].weapons.get(w)).is_some_and(is_guided) {
+                    buffers.stray_shots.push(entry.clone());
+                }
+            } else if credited > 0.0 {
+                let killed = *left <= 0.0 && !self.units[entry.target_idx].is_alive();
+                let attacker = &mut self.units[attacker_idx];
+                if entry.weapon_idx.is_some() {
+                    attacker.shots_hit += 1;
+                }
+                if killed {
+                    attacker.kills += 1;
+                    attacker.experience += attacker.xp_per_kill;
+                }
+                if let Some(weapon_idx) = entry.weapon_idx {
+                    let stats = attacker.weapon_stats_mut(weapon_idx);
+                    stats.shots_hit += 1;
+                    stats.damage_dealt += credited;
+                    stats.kills += killed as u32;
+                }
+            }
+        }
 
-            // Update attacker damage dealt stats
-            for entry in &self.damage_queue {
-                if entry.target_idx == target_idx {
-                    self.units[entry.attacker_idx].damage_dealt += entry.damage;
+        // Update attacker damage dealt stats and experience (kill bonuses were
+        // added above, so every killer is checked for a level-up here)
+        let mut level_ups = std::mem::take(&mut buffers.level_ups);
+        level_ups.clear();
+        for (unit, &dealt) in self.units.iter_mut().zip(dealt_by_attacker.iter()) {
+            unit.damage_dealt += dealt;
+            if dealt > 0.0 {
+                if let Some(level) = unit.gain_experience(dealt * unit.xp_per_damage) {
+                    log_at!(Info, "[Veterancy] Unit {} reached level {} ({:.0} xp)", unit.id, level, unit.experience);
+                    level_ups.push((unit.id, level));
                 }
             }
         }
 
-        // Clear targets pointing to destroyed units (separate pass to avoid borrow conflicts)
-        for destroyed_id in &destroyed_unit_ids {
+        // Who hit each damaged unit hardest, and with what
+        let hits = &mut buffers.hits;
+        hits.clear();
+        hits.extend(buffers.damage_entries.iter().map(|entry| {
+            let attacker = entry.attacker_idx.map(|idx| &self.units[idx]);
+            let category = if entry.blast {
+                Some(WeaponCategory::Explosive)
+            } else {
+                attacker.zip(entry.weapon_idx)
+                    .and_then(|(unit, w)| unit.weapons.get(w))
+                    .map(|weapon| weapon.category)
+            };
+            (entry.target_idx, attacker.map(|unit| unit.id), category, entry.damage)
+        }));
+        buffers.attribution.clear();
+        buffers.attribution.resize(self.units.len(), (None, "burn"));
+        attribute_hits(hits, &mut buffers.attribution);
+
+        let mut destroyed = std::mem::take(&mut buffers.destroyed);
+        let mut overkill = std::mem::take(&mut buffers.overkill);
+        let mut damaged = std::mem::take(&mut buffers.damaged);
+        destroyed.clear();
+        overkill.clear();
+        damaged.clear();
+
+        for outcome in outcomes.drain(..) {
+            if outcome.shield_broken {
+                self.combat_log.push(self.tick, CombatLogEntry::ShieldBroken(outcome.id));
+            }
+            if outcome.destroyed {
+                self.combat_log.push(self.tick, CombatLogEntry::UnitDestroyed(outcome.id));
+                destroyed.push(outcome.id);
+                overkill.push(DestroyedUnit { id: outcome.id, overkill: outcome.overkill });
+                log_at!(Info, "[Damage] Unit {} DESTROYED! (overkill {:.1})", outcome.id, outcome.overkill);
+            } else {
+                let (primary_attacker_id, damage_type) = buffers.attribution[outcome.idx];
+                damaged.push(DamagedUnit {
+                    id: outcome.id,
+                    hp: outcome.hp,
+                    shield: outcome.shield,
+                    hull_damage: outcome.hull_damage,
+                    shield_damage: outcome.shield_damage,
+                    primary_attacker_id,
+                    damage_type: Cow::Borrowed(damage_type),
+                });
+            }
+        }
+
+        for blast in &self_destructed {
+            self.combat_log.push(self.tick, CombatLogEntry::UnitDestroyed(blast.id));
+            destroyed.push(blast.id);
+            overkill.push(DestroyedUnit { id: blast.id, overkill: 0.0 });
+        }
+
+        // Clear targets pointing to destroyed units
+        for destroyed_id in &destroyed {
             for unit in self.units.iter_mut() {
                 if unit.target_id == Some(*destroyed_id) {
-                    unit.target_id = None;
+                    unit.clear_target();
                 }
             }
         }
 
-        // 6. Shield regen
+        // Guided shots whose target died first turn on another or fizzle
+        let (missiles_retargeted, missiles_fizzled) = self.redirect_stray_shots(&mut buffers.stray_shots, &mut buffers.in_range);
+        timer.lap(&mut self.profiler, Phase::Damage, window);
+
+        // 5a. Carriers that died launch everything docked (remove_unit does its own)
+        for idx in 0..self.units.len() {
+            if !self.units[idx].is_alive() && !self.units[idx].hangar_contents.is_empty() {
+                self.empty_hangar(idx, current_time);
+            }
+        }
+
+        // 5b. Repairs - after damage, so a unit killed this tick stays dead
+        let mut repaired = std::mem::take(&mut buffers.repaired);
+        let repaired_idx = &mut buffers.repaired_idx;
+        repaired.clear();
+        repaired_idx.clear();
+        for entry in &buffers.repair_entries {
+            let restored = self.units[entry.target_idx].repair(entry.amount, entry.repairs_shield);
+            if restored > 0.0 {
+                self.units[entry.healer_idx].healing_done += restored;
+                repaired_idx.push(entry.target_idx);
+            }
+        }
+        repaired_idx.sort_unstable();
+        repaired_idx.dedup();
+        repaired.extend(repaired_idx.iter().map(|&idx| RepairedUnit {
+            id: self.units[idx].id,
+            hp: self.units[idx].hp,
+            shield: self.units[idx].shield,
+        }));
+
+        // Resupply - after this tick's shots have drawn their supply
+        self.process_resupply(dt);
+
+        // 5c. Morale - before retreats so a unit that breaks runs this tick
+        let mut morale_events = std::mem::take(&mut buffers.morale_events);
+        morale_events.clear();
+        self.update_morale(&destroyed, dt, &mut morale_events);
+
+        // 5d. Disabled units - damaged ones shut down, ones left alone self-repair
+        let (disabled, reactivated) = self.process_disabled(dt, &mut repaired);
+
+        // 5e. Surrenders - before retreats, a unit that gives up doesn't flee
+        let surrendered = self.process_surrenders();
+
+        // 6. Retreats - flag damaged units, move them away, withdraw when clear
+        let retreated = self.process_retreats(dt, &mut moved);
+        self.prune_groups();
+
+        // 6a. Resource nodes and objectives - after everything that can kill
+        // or move a unit
+        let resources_gained = self.process_resource_nodes();
+        let objectives_met = self.evaluate_objectives();
+
+        // 6b. Position keyframe - every unit on the battlefield, moved or not
+        let is_keyframe = self.is_keyframe_tick();
+        if is_keyframe {
+            self.append_keyframe(&mut moved, &mut buffers.moved_ids);
+        }
+        let retreating_units: Vec<u32> = self.units.iter()
+            .filter(|u| u.retreating && u.in_battle())
+            .map(|u| u.id)
+            .collect();
+        timer.lap(&mut self.profiler, Phase::Aftermath, window);
+
+        // 7. Shield and capacitor regen
+        let shield_uses_energy = self.config.shield_regen_uses_energy;
         for unit in self.units.iter_mut() {
-            if unit.alive {
-                unit.regen_shield(dt);
+            if unit.is_alive() {
+                unit.regen_energy(dt);
+                // Faction bonus: the shield regens as if more time had passed
+                let shield_dt = dt * factions::shield_regen_multiplier(&self.faction_configs, unit.faction_id);
+                if shield_uses_energy {
+                    unit.regen_shield_from_energy(shield_dt);
+                } else {
+                    unit.regen_shield(shield_dt);
+                }
+            }
+        }
+        timer.lap(&mut self.profiler, Phase::ShieldRegen, window);
+
+        // Capacitor levels that changed since the last report (spent, recharged,
+        // or paid by manually_fire between ticks)
+        let mut energy = std::mem::take(&mut buffers.energy);
+        energy.clear();
+        buffers.last_energy.resize(self.units.len(), f32::NAN);
+        for (unit, last) in self.units.iter().zip(buffers.last_energy.iter_mut()) {
+            if unit.max_energy > 0.0 && unit.energy != *last {
+                *last = unit.energy;
+                energy.push(UnitEnergy { id: unit.id, energy: unit.energy });
             }
         }
 
-        // 7. Update stalemate tracking - if any damage was dealt, reset counter
-        if !damaged.is_empty() || !destroyed.is_empty() {
+        // 8. Update stalemate tracking - if anything fired or took damage, reset counter
+        if hostile_fire || !damaged.is_empty() || !destroyed.is_empty() {
             self.last_combat_tick = self.tick;
         }
 
         // ✅ NEW: Update next weapon ready time for idle mode calculation
         self.next_weapon_ready_time = self.calculate_next_weapon_ready_time(current_time);
 
-        // 8. Build result
+        // 9. Build result
+        let mut withdrawn = std::mem::take(&mut self.removed);
+        withdrawn.extend_from_slice(&retreated);
+        let spawned = std::mem::take(&mut buffers.spawned);
+        let reinforcements_arrived = std::mem::take(&mut buffers.arrived);
+        effects_changed.sort_unstable();
+        effects_changed.dedup();
+        let effects = effects_changed.iter()
+            .map(|&idx| UnitEffects { id: self.units[idx].id, effects: self.units[idx].effects.clone() })
+            .collect();
+
+        self.buffers = buffers;
+        timer.finish(&mut self.profiler);
+
         TickResult {
+            version: TICK_RESULT_VERSION,
             moved,
             damaged,
             destroyed,
+            overkill,
+            withdrawn,
+            retreated,
+            surrendered,
+            disabled,
+            reactivated,
+            retreating_units,
             tick: self.tick,
             weapons_fired,
+            missiles_retargeted,
+            missiles_fizzled,
+            self_destructed,
+            contacts_detected,
+            contacts_lost,
+            effects,
+            repaired,
+            energy,
+            spawned,
+            reinforcements_arrived,
+            morale_events,
+            level_ups,
+            launched: std::mem::take(&mut self.launched),
+            docked: std::mem::take(&mut self.docked),
+            objectives_met,
+            resources_gained,
+            stalemate_warning: self.stalemate_warning(),
             is_idle: false,
+            is_keyframe,
+            tick_duration_us: 0,
         }
     }
 
-    // =========================================================================
-    // Existing methods (required by lib.rs)
-    // =========================================================================
+    /// Blow up charged units that were triggered or have an enemy inside their
+    /// trigger radius, then the charged units their blasts catch, up to
+    /// config.self_destruct_chain_limit links. Blast damage is queued with
+    /// the unit as attacker, so it earns the kills.
+    fn detonate_self_destructs(&mut self, damage_entries: &mut Vec<DamageEntry>, in_range: &mut Vec<(usize, f32)>) -> Vec<SelfDestructed> {
+        // (unit index, links down the chain)
+        let mut queue: Vec<(usize, u32)> = Vec::new();
+        for unit_id in self.pending_self_destructs.drain(..) {
+            if let Some(idx) = self.units.iter().position(|u| u.id == unit_id && u.in_battle() && u.can_self_destruct()) {
+                queue.push((idx, 0));
+            }
+        }
+        for (idx, unit) in self.units.iter().enumerate() {
+            if !unit.in_battle() || !unit.can_self_destruct() || unit.self_destruct_trigger_radius <= 0.0 {
+                continue;
+            }
+            self.grid.query_range_into(unit.pos_x, unit.pos_y, unit.pos_z, unit.self_destruct_trigger_radius, in_range);
+            let enemy_near = in_range.iter().any(|&(other, _)| {
+                self.units[other].is_valid_target() && self.relations.is_hostile(unit.faction_id, self.units[other].faction_id)
+            });
+            if enemy_near {
+                queue.push((idx, 0));
+            }
+        }
 
-    pub fn add_unit(&mut self, mut unit: BattleUnit, current_time: f64) {
-        // Normalize unit data and randomize weapon cooldowns
-        unit.normalize(current_time);
-        log(&format!(
-            "[Simulator] Adding unit {} (faction={}, ship={}, station={}, has_weapons={}, max_range={:.0})",
-            unit.id, unit.faction_id, unit.is_ship, unit.is_station, unit.has_weapons, unit.max_weapon_range
-        ));
-        self.units.push(unit);
-        // ✅ NEW: Wake from idle when adding units
-        self.is_idle = false;
+        let friendly_fire = self.config.self_destruct_friendly_fire;
+        let chain_limit = self.config.self_destruct_chain_limit;
+        let mut detonated = Vec::new();
+        let mut next = 0;
+        while let Some(&(idx, depth)) = queue.get(next) {
+            next += 1;
+            // Caught in more than one blast, or triggered twice
+            if !self.units[idx].is_alive() {
+                continue;
+            }
+            self.units[idx].detonate();
+            let unit = &self.units[idx];
+            let (damage, radius, faction_id) = (unit.self_destruct_damage, unit.self_destruct_radius, unit.faction_id);
+            log_at!(Info, "[Combat] Unit {} self-destructed (radius {:.0}, chain depth {})", unit.id, radius, depth);
+            detonated.push(SelfDestructed { id: unit.id, x: unit.pos_x, y: unit.pos_y, z: unit.pos_z, radius, depth });
+
+            self.grid.query_range_into(unit.pos_x, unit.pos_y, unit.pos_z, radius, in_range);
+            for &(target_idx, _) in in_range.iter() {
+                let target = &self.units[target_idx];
+                if !target.on_battlefield() || (!friendly_fire && !self.relations.is_hostile(faction_id, target.faction_id)) {
+                    continue;
+                }
+                if target.can_self_destruct() && depth < chain_limit {
+                    queue.push((target_idx, depth + 1));
+                    continue;
+                }
+                damage_entries.push(DamageEntry {
+                    target_idx,
+                    damage,
+                    attacker_idx: Some(idx),
+                    weapon_idx: None,
+                    shield_pierce: 0.0,
+                    shield_damage_bonus: 1.0,
+                    retargeted: false,
+                    blast: true,
+                });
+            }
+        }
+        detonated
     }
 
-    pub fn get_active_factions(&self) -> Vec<u32> {
-        let mut factions: Vec<u32> = self.units
-            .iter()
-            .filter(|u| u.alive)
-            .map(|u| u.faction_id)
-            .collect();
+    /// Guided shots whose target died before they landed turn on the nearest
+    /// enemy within config.missile_reacquire_radius of it and land next tick;
+    /// the rest fizzle (giving their ammo back with config.refund_fizzled_ammo).
+    /// A shot only turns once. The shot was counted in shots_fired when it
+    /// was launched; a fizzle adds no damage or hit.
+    fn redirect_stray_shots(&mut self, strays: &mut Vec<DamageEntry>, in_range: &mut Vec<(usize, f32)>) -> (Vec<MissileRetargeted>, Vec<MissileFizzled>) {
+        let mut retargeted = Vec::new();
+        let mut fizzled = Vec::new();
+        for mut entry in strays.drain(..) {
+            let (Some(attacker_idx), Some(weapon_idx)) = (entry.attacker_idx, entry.weapon_idx) else {
+                continue;
+            };
+            let lost_id = self.units[entry.target_idx].id;
+            let new_target = if entry.retargeted {
+                None
+            } else {
+                self.reacquire_target(attacker_idx, weapon_idx, entry.target_idx, in_range)
+            };
+            let attacker_id = self.units[attacker_idx].id;
+            let weapon = &mut self.units[attacker_idx].weapons[weapon_idx];
+            let weapon_type = weapon.tag.clone();
+            match new_target {
+                Some(target_idx) => {
+                    log_at!(Debug, "[Combat] Unit {} {} lost target {}, turning on {}", attacker_id, weapon_type, lost_id, self.units[target_idx].id);
+                    retargeted.push(MissileRetargeted { attacker_id, from_id: lost_id, to_id: self.units[target_idx].id, weapon_type });
+                    entry.target_idx = target_idx;
+                    entry.retargeted = true;
+                    self.retargeted_shots.push(entry);
+                }
+                None => {
+                    if self.config.refund_fizzled_ammo {
+                        weapon.refund_ammo();
+                    }
+                    log_at!(Debug, "[Combat] Unit {} {} fizzled - target {} died before impact", attacker_id, weapon_type, lost_id);
+                    fizzled.push(MissileFizzled { attacker_id, target_id: lost_id, weapon_type });
+                }
+            }
+        }
+        (retargeted, fizzled)
+    }
 
-        factions.sort();
-        factions.dedup();
-        factions
+    /// Nearest enemy a guided shot from this weapon can turn on within
+    /// config.missile_reacquire_radius of the unit it lost - siege weapons
+    /// only take stations, like when they fire
+    fn reacquire_target(&self, attacker_idx: usize, weapon_idx: usize, lost_idx: usize, in_range: &mut Vec<(usize, f32)>) -> Option<usize> {
+        let radius = self.config.missile_reacquire_radius;
+        if radius <= 0.0 {
+            return None;
+        }
+        let attacker = &self.units[attacker_idx];
+        let siege = attacker.weapons[weapon_idx].category == WeaponCategory::Siege;
+        let lost = &self.units[lost_idx];
+        self.grid.query_range_into(lost.pos_x, lost.pos_y, lost.pos_z, radius, in_range);
+        in_range.iter()
+            .filter(|&&(idx, _)| {
+                let unit = &self.units[idx];
+                self.relations.is_hostile(attacker.faction_id, unit.faction_id)
+                    && if siege { unit.is_station && unit.is_siege_target() } else { unit.is_valid_target() }
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+            .map(|&(idx, _)| idx)
     }
 
-    /// Check if battle is in stalemate (no combat for STALEMATE_TICKS)
-    pub fn is_stalemate(&self) -> bool {
-        // Need at least some ticks to have passed
-        if self.tick < STALEMATE_TICKS {
-            return false;
+    /// Shut down units below their disable_threshold, and tick self-repair on
+    /// disabled ones - repair starts once no hostile ship has been within
+    /// config.disabled_repair_radius for disabled_repair_delay_ticks, and the
+    /// unit comes back online at can_reactivate(). Self-repairs are reported
+    /// in `repaired`. Returns (disabled, reactivated) ids.
+    fn process_disabled(&mut self, dt: f32, repaired: &mut Vec<RepairedUnit>) -> (Vec<u32>, Vec<u32>) {
+        let mut disabled: Vec<u32> = Vec::new();
+        let mut reactivated: Vec<u32> = Vec::new();
+        let radius = self.config.disabled_repair_radius;
+
+        for idx in 0..self.units.len() {
+            let unit = &self.units[idx];
+            if unit.in_battle() && unit.should_disable() {
+                let unit = &mut self.units[idx];
+                unit.is_disabled = true;
+                unit.quiet_ticks = 0;
+                unit.retreating = false;
+                unit.clear_target();
+                unit.stop();
+                disabled.push(unit.id);
+                log_at!(Info, "[Disabled] Unit {} DISABLED at {:.0}/{:.0} hp", unit.id, unit.hp, unit.max_hp);
+                continue;
+            }
+            if !unit.is_disabled || !unit.on_battlefield() {
+                continue;
+            }
+
+            let threatened = self.grid.query_range(unit.pos_x, unit.pos_y, unit.pos_z, radius)
+                .into_iter()
+                .any(|(other_idx, _)| {
+                    let other = &self.units[other_idx];
+                    other.is_ship && other.in_battle() && self.relations.is_hostile(unit.faction_id, other.faction_id)
+                });
+            let delay = self.ticks.disabled_repair_delay;
+            let unit = &mut self.units[idx];
+            if threatened {
+                unit.quiet_ticks = 0;
+                continue;
+            }
+            unit.quiet_ticks = unit.quiet_ticks.saturating_add(1);
+            if unit.quiet_ticks < delay || unit.repair_rate <= 0.0 || unit.hp >= unit.max_hp {
+                continue;
+            }
+
+            unit.hp = (unit.hp + unit.repair_rate * dt).min(unit.max_hp);
+            repaired.push(RepairedUnit { id: unit.id, hp: unit.hp, shield: unit.shield });
+            if unit.can_reactivate() {
+                unit.is_disabled = false;
+                unit.quiet_ticks = 0;
+                reactivated.push(unit.id);
+                log_at!(Info, "[Disabled] Unit {} back online at {:.0}/{:.0} hp", unit.id, unit.hp, unit.max_hp);
+            }
         }
-        
-        // If multiple factions exist but no combat for a while, it's a stalemate
-        let factions = self.get_active_factions();
-        if factions.len() > 1 && (self.tick - self.last_combat_tick) >= STALEMATE_TICKS {
-            log(&format!(
-                "[Simulator] Stalemate detected! {} ticks since last combat (threshold: {})",
-                self.tick - self.last_combat_tick, STALEMATE_TICKS
-            ));
-            return true;
+
+        // Units without siege weapons let go of what just shut down (locks included)
+        if !disabled.is_empty() {
+            for unit in self.units.iter_mut() {
+                if unit.target_id.is_some_and(|tid| disabled.contains(&tid)) && unit.max_siege_range() <= 0.0 {
+                    unit.clear_target();
+                }
+            }
         }
-        
-        false
+        (disabled, reactivated)
     }
 
-    pub fn is_battle_ended(&self) -> bool {
-        // Battle ends if: only one faction remains OR stalemate detected
-        let factions = self.get_active_factions();
-        
-        if factions.len() <= 1 {
-            return true;
+    /// Units below their surrender threshold give up - they stop, drop their
+    /// target, and anything targeting them lets go (locked targets included)
+    fn process_surrenders(&mut self) -> Vec<u32> {
+        let mut surrendered: Vec<u32> = Vec::new();
+        for unit in self.units.iter_mut().filter(|u| u.in_battle() && u.should_surrender()) {
+            unit.is_surrendered = true;
+            unit.retreating = false;
+            unit.clear_target();
+            unit.stop();
+            surrendered.push(unit.id);
+            log_at!(Info, "[Surrender] Unit {} SURRENDERED at {:.0}/{:.0} hp", unit.id, unit.hp, unit.max_hp);
         }
-        
-        // Check for stalemate
-        self.is_stalemate()
+        if !surrendered.is_empty() {
+            for unit in self.units.iter_mut() {
+                if unit.target_id.is_some_and(|tid| surrendered.contains(&tid)) {
+                    unit.clear_target();
+                }
+            }
+        }
+        surrendered
     }
 
-    pub fn get_results(&self) -> Vec<BattleUnit> {
-        self.units.clone()
+    /// Each supply unit hands resupply_rate * dt to its most depleted ally in
+    /// resupply_range (see find_resupply_target)
+    fn process_resupply(&mut self, dt: f32) {
+        for idx in 0..self.units.len() {
+            let rate = self.units[idx].resupply_rate;
+            if rate <= 0.0 {
+                continue;
+            }
+            let Some(target_idx) = find_resupply_target(&self.units[idx], &self.units, &self.grid, &self.relations) else {
+                continue;
+            };
+            let given = self.units[target_idx].resupply(rate * dt);
+            log_at!(Trace, "[Resupply] Unit {} -> {}: {:.2}", self.units[idx].id, self.units[target_idx].id, given);
+        }
     }
 
-    pub fn get_units(&self) -> &[BattleUnit] {
-        &self.units
+    /// Apply this tick's morale changes, reporting units whose morale moved
+    ///
+    /// Each allied death within a unit's morale_radius costs it
+    /// morale_loss_per_death. Morale then regenerates at morale_regen_rate per
+    /// second plus config.commander_morale_regen for every allied commander within
+    /// morale_radius.
+    fn update_morale(&mut self, destroyed: &[u32], dt: f32, events: &mut Vec<(u32, f32)>) {
+        let deaths: Vec<(u32, f32, f32, f32)> = self.units.iter()
+            .filter(|u| !u.is_alive() && destroyed.contains(&u.id))
+            .map(|u| (u.faction_id, u.pos_x, u.pos_y, u.pos_z))
+            .collect();
+        let commanders: Vec<(u32, u32, f32, f32, f32)> = self.units.iter()
+            .filter(|u| u.is_commander && u.in_battle())
+            .map(|u| (u.id, u.faction_id, u.pos_x, u.pos_y, u.pos_z))
+            .collect();
+
+        let relations = &self.relations;
+        for unit in self.units.iter_mut().filter(|u| u.in_battle()) {
+            let radius_sq = unit.morale_radius * unit.morale_radius;
+            let within = |x: f32, y: f32, z: f32| {
+                let (dx, dy, dz) = (unit.pos_x - x, unit.pos_y - y, unit.pos_z - z);
+                dx * dx + dy * dy + dz * dz <= radius_sq
+            };
+            let friendly = |faction_id: u32| !relations.is_hostile(unit.faction_id, faction_id);
+
+            let mut loss = 0.0;
+            if unit.morale_loss_per_death > 0.0 {
+                let nearby = deaths.iter()
+                    .filter(|&&(faction_id, x, y, z)| friendly(faction_id) && within(x, y, z))
+                    .count();
+                loss = nearby as f32 * unit.morale_loss_per_death;
+            }
+            let mut regen = unit.morale_regen_rate;
+            if unit.morale_radius > 0.0 {
+                let nearby = commanders.iter()
+                    .filter(|&&(id, faction_id, x, y, z)| id != unit.id && friendly(faction_id) && within(x, y, z))
+                    .count();
+                regen += nearby as f32 * self.config.commander_morale_regen;
+            }
+
+            let morale = (unit.morale - loss + regen * dt).clamp(0.0, MAX_MORALE);
+            if morale != unit.morale {
+                if morale < MORALE_BROKEN && unit.morale >= MORALE_BROKEN {
+                    log_at!(Info, "[Morale] Unit {} broke ({:.0} -> {:.0})", unit.id, unit.morale, morale);
+                }
+                unit.morale = morale;
+                events.push((unit.id, morale));
+            }
+        }
     }
 
-    pub fn get_faction_counts(&self) -> HashMap<u32, usize> {
-        let mut counts: HashMap<u32, usize> = HashMap::new();
-        for unit in &self.units {
-            if unit.alive {
-                *counts.entry(unit.faction_id).or_insert(0) += 1;
+    /// simulate_tick, reporting only state the client doesn't already have
+    ///
+    /// `moved` leaves out units less than config.position_epsilon from the
+    /// position last reported for them (keyframes still list everyone), and
+    /// `damaged` leaves out units whose hp and shield are what was last
+    /// reported. Velocity isn't compared, so clients extrapolating with it
+    /// should resync on keyframes. Don't mix with plain simulate_tick calls -
+    /// the last-reported state only tracks delta results.
+    pub fn simulate_tick_delta(&mut self, dt: f32, current_time: f64) -> DeltaTickResult {
+        let mut result = self.simulate_tick(dt, current_time);
+
+        let epsilon_sq = self.config.position_epsilon * self.config.position_epsilon;
+        let keyframe = result.is_keyframe;
+        let positions = &mut self.prev_positions;
+        result.moved.retain(|m| {
+            let pos = (m.x, m.y, m.z);
+            if let Some(&(x, y, z)) = positions.get(&m.id) {
+                let (dx, dy, dz) = (m.x - x, m.y - y, m.z - z);
+                if !keyframe && dx * dx + dy * dy + dz * dz < epsilon_sq {
+                    return false;
+                }
             }
+            positions.insert(m.id, pos);
+            true
+        });
+
+        let vitals = &mut self.prev_vitals;
+        result.damaged.retain(|d| vitals.insert(d.id, (d.hp, d.shield)) != Some((d.hp, d.shield)));
+        for repaired in &result.repaired {
+            vitals.insert(repaired.id, (repaired.hp, repaired.shield));
         }
-        counts
+
+        for id in result.destroyed.iter().chain(&result.withdrawn) {
+            self.prev_positions.remove(id);
+            self.prev_vitals.remove(id);
+        }
+        DeltaTickResult(result)
     }
 
-    pub fn is_battle_over(&self) -> bool {
-        self.is_battle_ended()
+    /// Check if this tick's result should carry a full position keyframe
+    fn is_keyframe_tick(&self) -> bool {
+        let every = self.config.emit_all_positions_every_n_ticks as u64;
+        every > 0 && self.tick.is_multiple_of(every)
     }
 
-    pub fn get_winner(&self) -> Option<u32> {
-        let factions = self.get_active_factions();
-        
-        if factions.len() == 1 {
-            // Clear winner - only one faction remains
-            Some(factions[0])
-        } else if factions.len() > 1 && self.is_stalemate() {
-            // Stalemate - faction with most units wins
-            let counts = self.get_faction_counts();
-            let mut best_faction: Option<u32> = None;
-            let mut best_count: usize = 0;
-            
-            for (faction, count) in counts {
-                if count > best_count {
-                    best_count = count;
-                    best_faction = Some(faction);
-                }
+    /// Add every unit on the battlefield that isn't in `moved` yet (zero velocity)
+    fn append_keyframe(&self, moved: &mut Vec<MovedUnit>, moved_ids: &mut Vec<u32>) {
+        moved_ids.clear();
+        moved_ids.extend(moved.iter().map(|m| m.id));
+        moved_ids.sort_unstable();
+        for unit in self.units.iter().filter(|u| u.in_battle()) {
+            if moved_ids.binary_search(&unit.id).is_err() {
+                moved.push(MovedUnit::from_move(unit, (unit.pos_x, unit.pos_y, unit.pos_z), 1.0));
             }
-            
-            log(&format!(
-                "[Simulator] Stalemate winner: faction {:?} with {} units",
-                best_faction, best_count
-            ));
-            
-            best_faction
-        } else {
-            // Battle ongoing, no winner yet
-            None
         }
     }
-}
\ No newline at end of file
+
+    /// Hand a TickResult's vectors back so the next ticks can reuse them
+    ///
+    /// Optional - without it every tick allocates its output vectors, which is
+    /// how it always worked. Call after the result has been serialized.
+    pub fn recycle_result(&mut self, result: TickResult) {
+        reclaim(&mut self.buffers.moved, result.moved);
+        reclaim(&mut self.buffers.damaged, result.damaged);
+        reclaim(&mut self.buffers.destroyed, result.destroyed);
+        reclaim(&mut self.buffers.overkill, result.overkill);
+        reclaim(&mut self.buffers.weapons_fired, result.weapons_fired);
+        reclaim(&mut self.buffers.repaired, result.repaired);
+        reclaim(&mut self.buffers.energy, result.energy);
+        reclaim(&mut self.buffers.spawned, result.spawned);
+        reclaim(&mut self.buffers.morale_events, result.morale_events);
+        reclaim(&mut self.buffers.level_ups, result.level_ups);
+        reclaim(&mut self.buffers.contacts_detected, result.contacts_detected);
+        reclaim(&mut self.buffers.contacts_lost, result.contacts_lost);
+    }
+
+    /// Per-tick status effect pass
+    ///
+    /// Active burns add damage to the queue (credited to the applier when it's
+    /// still around), then expired effects are removed. Units whose effect list
+    /// changed are appended to `changed`.
+    fn process_effects(&mut self, changed: &mut Vec<usize>, damage_entries: &mut Vec<DamageEntry>) {
+        for idx in 0..self.units.len() {
+            if self.units[idx].effects.is_empty() {
+                continue;
+            }
+            if !self.units[idx].is_alive() {
+                self.units[idx].effects.clear();
+                changed.push(idx);
+                continue;
+            }
+
+            for effect in &self.units[idx].effects {
+                if effect.kind == StatusEffectKind::Burn && effect.is_active(self.tick) && effect.magnitude > 0.0 {
+                    let attacker_idx = effect.source_id
+                        .and_then(|sid| self.units.iter().position(|u| u.id == sid));
+                    damage_entries.push(DamageEntry {
+                        target_idx: idx,
+                        damage: effect.magnitude,
+                        attacker_idx,
+                        weapon_idx: None,
+                        shield_pierce: 0.0,
+                        shield_damage_bonus: 1.0,
+                        retargeted: false,
+                        blast: false,
+                    });
+                }
+            }
+
+            // Expire after this tick's burn so an effect lasting N ticks burns N times
+            if self.units[idx].expire_effects(self.tick + 1) {
+                changed.push(idx);
+            }
+        }
+    }
+
+    /// Run update_movement for one unit against its current target (if any)
+    ///
+    /// Group members close on the group's focus target rather than their own;
+    /// defensive units aren't steered at either.
+    fn move_unit(&mut self, idx: usize, dt: f32, nearby: &mut Vec<usize>) {
+        let unit = &self.units[idx];
+        let chase = match unit.stance {
+            Stance::Defensive => None,
+            _ => self.group_target(unit).or(unit.target_id),
+        };
+        let target_idx = chase
+            .and_then(|tid| self.units.iter().position(|u| u.id == tid && u.is_alive()))
+            .filter(|&t| t != idx);
+
+        let separation = if self.config.collision_avoidance {
+            separation_force(
+                &self.units[idx],
+                &self.units,
+                &self.grid,
+                self.config.separation_radius,
+                self.config.separation_strength,
+                nearby,
+            )
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+
+        let base_speed = self.apply_faction_speed(idx);
+        match target_idx {
+            Some(t) if t < idx => {
+                let (head, tail) = self.units.split_at_mut(idx);
+                update_movement(&mut tail[0], Some(&head[t]), separation, dt);
+            }
+            Some(t) => {
+                let (head, tail) = self.units.split_at_mut(t);
+                update_movement(&mut head[idx], Some(&tail[0]), separation, dt);
+            }
+            None => update_movement(&mut self.units[idx], None, separation, dt),
+        }
+        self.units[idx].max_speed = base_speed;
+    }
+
+    /// Scale a unit's max_speed by its faction speed_multiplier for one
+    /// movement update - returns the unscaled value to put back afterwards
+    fn apply_faction_speed(&mut self, idx: usize) -> f32 {
+        let unit = &mut self.units[idx];
+        let base_speed = unit.max_speed;
+        unit.max_speed *= factions::speed_multiplier(&self.faction_configs, unit.faction_id);
+        base_speed
+    }
+
+    /// Push simulator-moved units out of anything they overlap
+    ///
+    /// Two steered units share the correction; a steered unit overlapping a
+    /// station or a player-synced unit takes all of it (those never move).
+    /// Expects the spatial grid to be current.
+    fn separate_units(&mut self, steered: &[(usize, f32, f32, f32)], nearby: &mut Vec<usize>, movable: &mut Vec<bool>) {
+        let max_radius = self.units.iter()
+            .filter(|u| u.in_battle())
+            .map(|u| u.radius)
+            .fold(0.0f32, |a, b| a.max(b));
+
+        movable.clear();
+        movable.resize(self.units.len(), false);
+        for &(idx, ..) in steered {
+            movable[idx] = !self.units[idx].is_station;
+        }
+
+        for _ in 0..self.config.separation_iterations {
+            let mut any_overlap = false;
+
+            for &(idx, ..) in steered {
+                if !movable[idx] {
+                    continue;
+                }
+
+                let unit = &self.units[idx];
+                self.grid.get_nearby_into(unit.pos_x, unit.pos_y, unit.pos_z, unit.radius + max_radius, nearby);
+
+                for &other_idx in nearby.iter() {
+                    if other_idx == idx || !self.units[other_idx].in_battle() {
+                        continue;
+                    }
+
+                    let unit = &self.units[idx];
+                    let other = &self.units[other_idx];
+                    let min_dist = unit.radius + other.radius;
+                    let dist_sq = unit.distance_sq(other);
+                    if dist_sq >= min_dist * min_dist {
+                        continue;
+                    }
+                    any_overlap = true;
+
+                    // Separation direction points from other to this unit
+                    let dist = dist_sq.sqrt();
+                    let (nx, ny, nz) = if dist > 1e-4 {
+                        (
+                            (unit.pos_x - other.pos_x) / dist,
+                            (unit.pos_y - other.pos_y) / dist,
+                            (unit.pos_z - other.pos_z) / dist,
+                        )
+                    } else if idx > other_idx {
+                        (1.0, 0.0, 0.0) // Exactly stacked - split along x by index
+                    } else {
+                        (-1.0, 0.0, 0.0)
+                    };
+
+                    let overlap = min_dist - dist;
+                    let share = if movable[other_idx] { 0.5 } else { 1.0 };
+
+                    let unit = &mut self.units[idx];
+                    unit.pos_x += nx * overlap * share;
+                    unit.pos_y += ny * overlap * share;
+                    unit.pos_z += nz * overlap * share;
+
+                    if movable[other_idx] {
+                        let other = &mut self.units[other_idx];
+                        other.pos_x -= nx * overlap * share;
+                        other.pos_y -= ny * overlap * share;
+                        other.pos_z -= nz * overlap * share;
+                    }
+                }
+            }
+
+            if !any_overlap {
+                break;
+            }
+        }
+    }
+
+    /// Handle retreating units
+    ///
+    /// Units below their retreat threshold drop their target (they keep firing
+    /// defensively at enemies in range). If the simulator may move them, they
+    /// flee to their retreat_target or directly away from the nearest enemy.
+    /// Once farther than retreat_disengage_distance from every enemy the unit
+    /// is withdrawn; if its hull recovers above the threshold it rejoins.
+    /// Returns ids of units that withdrew this tick.
+    fn process_retreats(&mut self, dt: f32, moved: &mut Vec<MovedUnit>) -> Vec<u32> {
+        let mut withdrawn: Vec<u32> = Vec::new();
+        let disengage_sq = self.config.retreat_disengage_distance * self.config.retreat_disengage_distance;
+
+        for idx in 0..self.units.len() {
+            if !self.units[idx].in_battle() {
+                continue;
+            }
+
+            if self.units[idx].retreating && !self.units[idx].should_retreat() {
+                let unit = &mut self.units[idx];
+                unit.retreating = false;
+                unit.clear_target();
+                unit.stop();
+                log_at!(Info, "[Retreat] Unit {} recovered, rejoining battle", unit.id);
+                continue;
+            }
+
+            if !self.units[idx].retreating {
+                if !self.units[idx].should_retreat() {
+                    continue;
+                }
+                let unit = &mut self.units[idx];
+                unit.retreating = true;
+                unit.clear_target();
+                log_at!(Info,
+                    "[Retreat] Unit {} retreating at {:.0}/{:.0} hp",
+                    unit.id, unit.hp, unit.max_hp
+                );
+            }
+
+            // Find nearest enemy still on the battlefield
+            let unit = &self.units[idx];
+            let nearest = self.units.iter()
+                .filter(|o| o.is_valid_target() && self.relations.is_hostile(unit.faction_id, o.faction_id))
+                .map(|o| (unit.distance_sq(o), o.pos_x, o.pos_y, o.pos_z))
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+
+            let in_contact = matches!(nearest, Some((dist_sq, ..)) if dist_sq <= disengage_sq);
+            let mut reached_edge = false;
+            if let Some((_, ex, ey, ez)) = nearest.filter(|_| in_contact && self.is_sim_moved(&self.units[idx])) {
+                let old = (unit.pos_x, unit.pos_y, unit.pos_z);
+                let base_speed = self.apply_faction_speed(idx);
+                update_retreat(&mut self.units[idx], (ex, ey, ez), dt);
+                self.units[idx].max_speed = base_speed;
+                // Fleeing into the arena edge counts as leaving the battlefield
+                reached_edge = self.clamp_to_bounds(idx);
+                let unit = &self.units[idx];
+                if (unit.pos_x, unit.pos_y, unit.pos_z) != old {
+                    moved.push(MovedUnit::from_move(unit, old, dt));
+                }
+            }
+
+            if !in_contact || reached_edge {
+                let unit = &mut self.units[idx];
+                unit.set_state(UnitState::Withdrawn);
+                unit.stop();
+                withdrawn.push(unit.id);
+                log_at!(Info, "[Retreat] Unit {} WITHDRAWN from battle", unit.id);
+            }
+        }
+
+        // Nobody should keep a withdrawn unit as target
+        for withdrawn_id in &withdrawn {
+            for unit in self.units.iter_mut() {
+                if unit.target_id == Some(*withdrawn_id) {
+                    unit.clear_target();
+                }
+            }
+        }
+
+        withdrawn
+    }
+
+    // =========================================================================
+    // Existing methods (required by lib.rs)
+    // =========================================================================
+
+    /// Add one unit mid-battle - rejected if its id is taken or its stats are bad
+    pub fn add_unit(&mut self, mut unit: BattleUnit, current_time: f64) -> Result<(), ValidationError> {
+        self.check_new_units(std::slice::from_ref(&unit))?;
+        // Normalize unit data and randomize weapon cooldowns
+        let current_time = self.sim_time(current_time);
+        unit.normalize(current_time, &mut self.rng);
+        self.weapon_tags.categorize_weapons(&mut unit);
+        disable_restricted_weapons(&mut unit, &self.weapon_class_restrictions);
+        if self.recorder.enabled {
+            self.recorder.record_units(std::slice::from_ref(&unit));
+        }
+        log_at!(Info,
+            "[Simulator] Adding unit {} (faction={}, ship={}, station={}, has_weapons={}, max_range={:.0})",
+            unit.id, unit.faction_id, unit.is_ship, unit.is_station, unit.has_weapons, unit.max_weapon_range
+        );
+        self.units.push(unit);
+        self.index_groups(self.units.len() - 1);
+        // ✅ NEW: Wake from idle when adding units
+        self.is_idle = false;
+        Ok(())
+    }
+
+    /// Store a unit type for add_unit_from_template (replaces one with the
+    /// same template_id)
+    pub fn register_template(&mut self, template: UnitTemplate) {
+        log_at!(Info, "[Simulator] Registered template '{}'", template.template_id);
+        self.templates.insert(template.template_id.clone(), template);
+    }
+
+    pub fn template(&self, template_id: &str) -> Option<&UnitTemplate> {
+        self.templates.get(template_id)
+    }
+
+    /// add_unit for a copy of a registered template with this id and faction,
+    /// at (x, y, z) - joins at the current simulated time
+    pub fn add_unit_from_template(&mut self, template_id: &str, id: u32, faction_id: u32, x: f32, y: f32, z: f32) -> Result<(), TemplateError> {
+        let template = self.templates.get(template_id)
+            .ok_or_else(|| TemplateError::UnknownTemplate(template_id.to_string()))?;
+        let unit = template.instantiate(id, faction_id, x, y, z);
+        // Host time that maps back to the last tick's simulated time
+        self.add_unit(unit, self.last_time + self.time_offset)?;
+        Ok(())
+    }
+
+    /// Add many units at once - normalizes each, then rebuilds the spatial grid once
+    /// Returns the number of units added; one bad unit rejects the whole batch
+    pub fn add_units(&mut self, units: Vec<BattleUnit>, current_time: f64) -> Result<u32, ValidationError> {
+        self.check_new_units(&units)?;
+        let count = units.len();
+        let current_time = self.sim_time(current_time);
+        self.units.reserve(count);
+        for mut unit in units {
+            unit.normalize(current_time, &mut self.rng);
+            self.weapon_tags.categorize_weapons(&mut unit);
+            disable_restricted_weapons(&mut unit, &self.weapon_class_restrictions);
+            self.units.push(unit);
+        }
+        self.index_groups(self.units.len() - count);
+        if self.recorder.enabled {
+            self.recorder.record_units(&self.units[self.units.len() - count..]);
+        }
+        log_at!(Info, "[Simulator] Added {} units in batch ({} total)", count, self.units.len());
+
+        self.rebuild_spatial_grid();
+        self.is_idle = false;
+        Ok(count as u32)
+    }
+
+    /// Queue units to join the battle at `arrival_tick`
+    ///
+    /// They're normalized and inserted at the start of that tick and listed in
+    /// TickResult.spawned; a tick that has already passed means the next one.
+    /// Validated now, against the battle and every wave still pending.
+    pub fn schedule_reinforcements(&mut self, arrival_tick: u64, units: Vec<BattleUnit>) -> Result<(), ValidationError> {
+        self.schedule_wave(ReinforcementWave { arrival_tick, units, faction_id: None, spawn: None })
+    }
+
+    /// schedule_reinforcements for a whole wave - its faction_id and spawn
+    /// point are applied to the units straight away (spawn positions are
+    /// clamped to the configured bounds). Arrivals are listed per faction in
+    /// TickResult.reinforcements_arrived.
+    pub fn schedule_wave(&mut self, mut wave: ReinforcementWave) -> Result<(), ValidationError> {
+        if wave.units.is_empty() {
+            return Ok(());
+        }
+        if let (Some(bounds), Some((x, y, z))) = (self.config.bounds, wave.spawn) {
+            wave.spawn = Some(bounds.clamp(x, y, z));
+        }
+        wave.place_units(self.config.reinforcement_spacing);
+        self.check_new_units(&wave.units)?;
+        let arrival_tick = wave.arrival_tick;
+        if self.recorder.enabled {
+            self.recorder.record_reinforcements(&wave);
+        }
+        log_at!(Info, "[Reinforcements] {} units scheduled for tick {}", wave.units.len(), arrival_tick);
+        // After waves with the same tick so they arrive in scheduling order
+        let at = self.reinforcements.partition_point(|w| w.arrival_tick <= arrival_tick);
+        self.reinforcements.insert(at, wave);
+        self.is_idle = false;
+        Ok(())
+    }
+
+    /// First problem with adding these units (ids checked against every unit
+    /// in the battle and every pending reinforcement)
+    fn check_new_units(&self, units: &[BattleUnit]) -> Result<(), ValidationError> {
+        let existing = self.units.iter()
+            .chain(self.reinforcements.iter().flat_map(|w| &w.units))
+            .map(|u| u.id);
+        match validate_units(units, existing).into_iter().next() {
+            Some(problem) => {
+                log_at!(Error, "[Simulator] Rejected units: {}", problem);
+                Err(problem)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Limit a ship class to weapons whose tags start with one of
+    /// `allowed_tags` (None lifts the restriction)
+    ///
+    /// Weapons already in the battle that break it are disabled, and so are
+    /// those of units added later. Returns how many were disabled now.
+    pub fn set_class_weapon_restrictions(&mut self, class: ShipClass, allowed_tags: Option<Vec<String>>) -> u32 {
+        if self.recorder.enabled {
+            self.recorder.record_command(ReplayCommand::ClassWeaponRestrictions { class, allowed_tags: allowed_tags.clone() });
+        }
+        match allowed_tags {
+            Some(tags) => {
+                self.weapon_class_restrictions.insert(class, tags);
+            }
+            None => {
+                self.weapon_class_restrictions.remove(&class);
+                return 0;
+            }
+        }
+        let restrictions = &self.weapon_class_restrictions;
+        let disabled = self.units.iter_mut()
+            .filter(|u| u.ship_class == class)
+            .map(|u| disable_restricted_weapons(u, restrictions))
+            .sum();
+        disabled
+    }
+
+    pub fn weapon_class_restrictions(&self) -> &WeaponClassRestrictions {
+        &self.weapon_class_restrictions
+    }
+
+    /// Map weapon tags starting with `prefix` to `category` - units already in
+    /// the battle are re-categorized, later ones pick it up as they join
+    pub fn register_weapon_tag_prefix(&mut self, prefix: &str, category: WeaponCategory) {
+        log_at!(Info, "[Simulator] Weapon tag prefix '{}' is {:?}", prefix, category);
+        self.weapon_tags.register(prefix, category);
+        self.record(ReplayCommand::WeaponTagPrefix { prefix: prefix.to_string(), category });
+        // Categories decide which weapons count towards offensive range
+        for unit in self.units.iter_mut() {
+            self.weapon_tags.categorize_weapons(unit);
+        }
+        self.is_idle = false;
+    }
+
+    pub fn weapon_tags(&self) -> &WeaponTagRegistry {
+        &self.weapon_tags
+    }
+
+    /// Units scheduled but not arrived yet
+    pub fn pending_reinforcements(&self) -> usize {
+        self.reinforcements.iter().map(|w| w.units.len()).sum()
+    }
+
+    /// Insert every wave due by this tick
+    ///
+    /// Living enemies with a new arrival inside their search range (weapon or
+    /// view range) re-evaluate their target this tick. Ids go to buffers.spawned
+    /// and, grouped by faction, buffers.arrived.
+    fn spawn_reinforcements(&mut self, current_time: f64) {
+        let due = self.reinforcements.partition_point(|w| w.arrival_tick <= self.tick);
+        if due == 0 {
+            return;
+        }
+
+        let first_new = self.units.len();
+        for wave in self.reinforcements.drain(..due) {
+            for mut unit in wave.units {
+                unit.normalize(current_time, &mut self.rng);
+                self.weapon_tags.categorize_weapons(&mut unit);
+                disable_restricted_weapons(&mut unit, &self.weapon_class_restrictions);
+                self.buffers.spawned.push(unit.id);
+                match self.buffers.arrived.iter_mut().find(|(f, _)| *f == unit.faction_id) {
+                    Some((_, ids)) => ids.push(unit.id),
+                    None => self.buffers.arrived.push((unit.faction_id, vec![unit.id])),
+                }
+                self.units.push(unit);
+            }
+        }
+
+        self.index_groups(first_new);
+        self.retarget_now.clear();
+        self.retarget_now.resize(self.units.len(), false);
+        let (existing, arrivals) = self.units.split_at(first_new);
+        for (idx, unit) in existing.iter().enumerate() {
+            if !unit.in_battle() || !unit.has_weapons {
+                continue;
+            }
+            let reach = unit.max_offensive_range(true).max(unit.view_range);
+            self.retarget_now[idx] = arrivals.iter().any(|arrival| {
+                self.relations.is_hostile(unit.faction_id, arrival.faction_id)
+                    && unit.distance_sq(arrival) <= reach * reach
+            });
+        }
+
+        log_at!(Info,
+            "[Reinforcements] Tick {}: {} units arrived, {} enemies re-targeting",
+            self.tick, self.units.len() - first_new, self.retarget_now.iter().filter(|&&r| r).count()
+        );
+
+        // Arrivals count as activity for idle and stalemate tracking
+        self.last_movement_tick = self.tick;
+        self.last_combat_tick = self.tick;
+        self.is_idle = false;
+    }
+
+    /// Launch a docked unit from a carrier
+    ///
+    /// The unit is placed just outside the carrier's radius, starts with no
+    /// target (so lock_time counts from the launch) and is listed in the next
+    /// TickResult.launched.
+    pub fn launch_fighter(&mut self, carrier_id: u32, fighter_id: u32, current_time: f64) -> Result<(), HangarError> {
+        let current_time = self.sim_time(current_time);
+        let carrier_idx = self.units.iter()
+            .position(|u| u.id == carrier_id && u.in_battle())
+            .ok_or(HangarError::NoCarrier(carrier_id))?;
+        if !self.units[carrier_idx].hangar_contents.contains(&fighter_id) {
+            return Err(HangarError::NotDocked(fighter_id));
+        }
+        let fighter_idx = self.units.iter()
+            .position(|u| u.id == fighter_id && u.is_alive() && !u.is_withdrawn())
+            .ok_or(HangarError::NoFighter(fighter_id))?;
+
+        self.units[carrier_idx].hangar_contents.retain(|&id| id != fighter_id);
+        self.place_launched(carrier_idx, fighter_idx, current_time);
+        self.launched.push(fighter_id);
+        self.rebuild_spatial_grid();
+        self.is_idle = false;
+        self.record(ReplayCommand::LaunchFighter { carrier_id, fighter_id, time: current_time });
+        log_at!(Info, "[Hangar] Carrier {} launched unit {}", carrier_id, fighter_id);
+        Ok(())
+    }
+
+    /// Dock a unit in a carrier of its own faction
+    ///
+    /// Docked units can't move, fire or be targeted; anything targeting the
+    /// unit drops it. Listed in the next TickResult.docked.
+    pub fn dock_fighter(&mut self, carrier_id: u32, fighter_id: u32) -> Result<(), HangarError> {
+        let carrier_idx = self.units.iter()
+            .position(|u| u.id == carrier_id && u.in_battle())
+            .ok_or(HangarError::NoCarrier(carrier_id))?;
+        let faction_id = self.units[carrier_idx].faction_id;
+        let fighter_idx = self.units.iter()
+            .position(|u| u.id == fighter_id && u.id != carrier_id && u.is_alive() && !u.is_withdrawn() && u.faction_id == faction_id)
+            .ok_or(HangarError::NoFighter(fighter_id))?;
+        if self.units[fighter_idx].is_in_hangar {
+            return Err(HangarError::AlreadyDocked(fighter_id));
+        }
+        if !self.units[carrier_idx].has_hangar_space() {
+            return Err(HangarError::HangarFull(carrier_id));
+        }
+
+        self.stow(carrier_idx, fighter_idx);
+        self.rebuild_spatial_grid();
+        self.is_idle = false;
+        self.record(ReplayCommand::DockFighter { carrier_id, fighter_id });
+        log_at!(Info, "[Hangar] Unit {} docked in carrier {}", fighter_id, carrier_id);
+        Ok(())
+    }
+
+    /// Put a unit in a carrier's hangar and list it in the next TickResult.docked
+    ///
+    /// Doesn't check capacity or rebuild the spatial index.
+    fn stow(&mut self, carrier_idx: usize, fighter_idx: usize) {
+        let fighter_id = self.units[fighter_idx].id;
+        let carrier = &mut self.units[carrier_idx];
+        carrier.hangar_contents.push(fighter_id);
+        let (x, y, z) = (carrier.pos_x, carrier.pos_y, carrier.pos_z);
+        let fighter = &mut self.units[fighter_idx];
+        fighter.is_in_hangar = true;
+        fighter.retreating = false;
+        fighter.clear_target();
+        (fighter.pos_x, fighter.pos_y, fighter.pos_z) = (x, y, z);
+        (fighter.vel_x, fighter.vel_y, fighter.vel_z) = (0.0, 0.0, 0.0);
+        self.clear_targets_on(&[fighter_id]);
+        self.docked.push(fighter_id);
+    }
+
+    /// Carrier drone hangars
+    ///
+    /// While an enemy is in a carrier's view_range (or it has a target) it
+    /// launches one drone per launch_interval - docked drones first, then new
+    /// ones built from the template - aimed at the carrier's target. After
+    /// recover_after_ticks in a row with no enemy in view every launched
+    /// drone is recovered (see recall_drones).
+    fn process_drone_hangars(&mut self, current_time: f64) {
+        let mut changed = false;
+        for carrier_idx in 0..self.units.len() {
+            let carrier = &self.units[carrier_idx];
+            if carrier.hangar.is_none() || !carrier.in_battle() {
+                continue;
+            }
+            let view_sq = carrier.view_range * carrier.view_range;
+            let enemy_in_view = carrier.target_id.is_some() || self.units.iter().any(|o| {
+                o.in_battle() && self.relations.is_hostile(carrier.faction_id, o.faction_id) && carrier.distance_sq(o) <= view_sq
+            });
+            let carrier_id = carrier.id;
+
+            let hangar = self.units[carrier_idx].hangar.as_mut().unwrap();
+            if !enemy_in_view {
+                hangar.idle_ticks = hangar.idle_ticks.saturating_add(1);
+                if hangar.idle_ticks >= hangar.recover_after_ticks {
+                    hangar.idle_ticks = 0;
+                    changed |= self.recall_drones(carrier_idx);
+                }
+                continue;
+            }
+            hangar.idle_ticks = 0;
+            if current_time - hangar.last_launch < hangar.launch_interval as f64 {
+                continue;
+            }
+
+            let carrier = &self.units[carrier_idx];
+            let docked = carrier.hangar_contents.iter()
+                .find_map(|&id| self.units.iter().position(|u| u.id == id && u.carrier_id == Some(carrier_id)));
+            let drone_idx = match docked {
+                Some(drone_idx) => {
+                    let drone_id = self.units[drone_idx].id;
+                    self.units[carrier_idx].hangar_contents.retain(|&id| id != drone_id);
+                    self.launched.push(drone_id);
+                    drone_idx
+                }
+                None if carrier.hangar.as_ref().is_some_and(|h| h.count > 0) => self.build_drone(carrier_idx, current_time),
+                None => continue,
+            };
+
+            self.place_launched(carrier_idx, drone_idx, current_time);
+            let target = self.units[carrier_idx].target_id;
+            self.units[drone_idx].set_target(target, current_time);
+            if let Some(hangar) = self.units[carrier_idx].hangar.as_mut() {
+                hangar.last_launch = current_time;
+            }
+            log_at!(Info, "[Hangar] Carrier {} launched drone {}", carrier_id, self.units[drone_idx].id);
+            changed = true;
+        }
+
+        if changed {
+            self.rebuild_spatial_grid();
+            self.last_movement_tick = self.tick;
+            self.is_idle = false;
+        }
+    }
+
+    /// New drone from a carrier's template, listed in TickResult.spawned -
+    /// returns its index. It takes over the record (and id) of a drone the
+    /// carrier recovered earlier, if there is one, so recall / relaunch
+    /// cycles don't grow the unit list.
+    fn build_drone(&mut self, carrier_idx: usize, current_time: f64) -> usize {
+        let carrier_id = self.units[carrier_idx].id;
+        let stowed = self.units.iter()
+            .position(|u| u.carrier_id == Some(carrier_id) && u.is_withdrawn() && u.is_in_hangar);
+        let id = match stowed {
+            Some(idx) => self.units[idx].id,
+            None => self.allocate_drone_id(),
+        };
+        let carrier = &mut self.units[carrier_idx];
+        let (carrier_id, faction_id, player_id, view_range) = (carrier.id, carrier.faction_id, carrier.player_id, carrier.view_range);
+        let hangar = carrier.hangar.as_mut().expect("build_drone on a unit without a hangar");
+        hangar.count -= 1;
+
+        let mut drone = (*hangar.template).clone();
+        drone.id = id;
+        drone.faction_id = faction_id;
+        drone.player_id = player_id;
+        drone.carrier_id = Some(carrier_id);
+        drone.ai_controlled = true;
+        // Drones hunt whatever their carrier can see
+        drone.view_range = drone.view_range.max(view_range);
+        drone.hangar = None;
+        drone.is_in_hangar = false;
+        drone.set_state(UnitState::Active);
+        drone.normalize(current_time, &mut self.rng);
+        self.weapon_tags.categorize_weapons(&mut drone);
+        disable_restricted_weapons(&mut drone, &self.weapon_class_restrictions);
+        let idx = match stowed {
+            Some(idx) => {
+                self.units[idx] = drone;
+                idx
+            }
+            None => {
+                self.units.push(drone);
+                self.units.len() - 1
+            }
+        };
+        self.buffers.spawned.push(id);
+        self.index_groups_at(idx);
+        idx
+    }
+
+    /// First free id from DRONE_ID_BASE up
+    fn allocate_drone_id(&mut self) -> u32 {
+        loop {
+            let id = self.next_drone_id;
+            self.next_drone_id = self.next_drone_id.checked_add(1).unwrap_or(DRONE_ID_BASE);
+            let taken = self.units.iter()
+                .chain(self.reinforcements.iter().flat_map(|w| &w.units))
+                .any(|u| u.id == id);
+            if !taken {
+                return id;
+            }
+        }
+    }
+
+    /// Recover every launched drone of a carrier - each is despawned (left
+    /// in the unit list withdrawn and in the hangar, for build_drone to reuse,
+    /// and listed in the next TickResult.withdrawn so the client drops it
+    /// without counting a loss) and goes back into the hangar's count. Units
+    /// docked with dock_fighter stay docked. Returns whether there were any
+    fn recall_drones(&mut self, carrier_idx: usize) -> bool {
+        let carrier_id = self.units[carrier_idx].id;
+        let first = self.removed.len();
+        for drone in self.units.iter_mut() {
+            if drone.carrier_id == Some(carrier_id) && drone.in_battle() && !drone.is_in_hangar {
+                drone.set_state(UnitState::Withdrawn);
+                drone.is_in_hangar = true;
+                drone.clear_target();
+                self.removed.push(drone.id);
+            }
+        }
+        let recovered = self.removed.split_off(first);
+        if recovered.is_empty() {
+            return false;
+        }
+        self.clear_targets_on(&recovered);
+        let count = recovered.len() as u32;
+        self.removed.extend(recovered);
+        if let Some(hangar) = self.units[carrier_idx].hangar.as_mut() {
+            hangar.count += count;
+        }
+        self.prune_groups();
+        log_at!(Info, "[Hangar] Carrier {} recovered {} drones", carrier_id, count);
+        true
+    }
+
+    /// Launch every unit still docked in a carrier (it died or was removed)
+    fn empty_hangar(&mut self, carrier_idx: usize, current_time: f64) {
+        let contents = std::mem::take(&mut self.units[carrier_idx].hangar_contents);
+        for fighter_id in contents {
+            let Some(fighter_idx) = self.units.iter().position(|u| u.id == fighter_id && u.state == UnitState::Active && u.is_in_hangar) else {
+                continue;
+            };
+            self.place_launched(carrier_idx, fighter_idx, current_time);
+            self.launched.push(fighter_id);
+        }
+        log_at!(Info, "[Hangar] Carrier {} lost - hangar emptied", self.units[carrier_idx].id);
+    }
+
+    /// Take a unit out of a hangar, just outside the carrier's radius
+    ///
+    /// The direction comes from the unit id (golden angle steps) so units
+    /// launched together fan out instead of stacking.
+    fn place_launched(&mut self, carrier_idx: usize, fighter_idx: usize, current_time: f64) {
+        const GOLDEN_ANGLE: f32 = 2.399_963;
+        let carrier = &self.units[carrier_idx];
+        let (x, y, z, carrier_radius) = (carrier.pos_x, carrier.pos_y, carrier.pos_z, carrier.radius);
+        let fighter = &mut self.units[fighter_idx];
+        let angle = (fighter.id % 1024) as f32 * GOLDEN_ANGLE;
+        let dist = carrier_radius + fighter.radius;
+        fighter.pos_x = x + angle.cos() * dist;
+        fighter.pos_y = y;
+        fighter.pos_z = z + angle.sin() * dist;
+        (fighter.vel_x, fighter.vel_y, fighter.vel_z) = (0.0, 0.0, 0.0);
+        fighter.is_in_hangar = false;
+        fighter.clear_target();
+        fighter.target_acquired_time = current_time;
+        self.clamp_to_bounds(fighter_idx);
+    }
+
+    /// Remove a unit outright (admin removal, not a combat death)
+    ///
+    /// The unit is withdrawn - listed in the next TickResult.withdrawn, not
+    /// counted as a loss - drops out of the spatial index and every target
+    /// pointing at it is cleared. A removed carrier launches what it had
+    /// docked. Returns false if no unit on the battlefield has this id.
+    pub fn remove_unit(&mut self, unit_id: u32) -> bool {
+        let Some(idx) = self.units.iter().position(|u| u.id == unit_id && u.state == UnitState::Active) else {
+            return false;
+        };
+        let unit = &mut self.units[idx];
+        unit.set_state(UnitState::Withdrawn);
+        unit.clear_target();
+        self.removed.push(unit_id);
+        if !self.units[idx].hangar_contents.is_empty() {
+            self.empty_hangar(idx, self.last_time);
+        }
+
+        self.clear_targets_on(&[unit_id]);
+
+        self.prune_groups();
+
+        // Full rebuild rather than grid.remove() - single position updates don't
+        // touch the index, so the unit's cell may not match its position
+        self.rebuild_spatial_grid();
+        self.is_idle = false;
+        self.record(ReplayCommand::RemoveUnit { unit_id });
+        log_at!(Info, "[Simulator] Removed unit {}", unit_id);
+        true
+    }
+
+    /// Drop every target pointing at one of `ids`, locked ones included
+    fn clear_targets_on(&mut self, ids: &[u32]) {
+        for other in self.units.iter_mut() {
+            if other.target_id.is_some_and(|tid| ids.contains(&tid)) {
+                other.clear_target();
+            }
+        }
+    }
+
+    /// Concede the battle for a whole faction
+    ///
+    /// Every unit of the faction still on the books (including disabled,
+    /// surrendered and docked ones) is withdrawn like remove_unit() - listed
+    /// in the next TickResult.withdrawn, not counted as losses - and every
+    /// target pointing at them is cleared. Its pending reinforcements are
+    /// dropped, so the end of battle and winner reflect the remaining
+    /// factions straight away. Returns false if the faction has no unit left
+    /// in the battle.
+    pub fn surrender_faction(&mut self, faction_id: u32) -> bool {
+        if !self.units.iter().any(|u| u.faction_id == faction_id && u.in_battle()) {
+            return false;
+        }
+
+        let mut count = 0;
+        for unit in self.units.iter_mut().filter(|u| u.faction_id == faction_id && u.state == UnitState::Active) {
+            unit.set_state(UnitState::Withdrawn);
+            unit.clear_target();
+            self.removed.push(unit.id);
+            count += 1;
+        }
+        for other in self.units.iter_mut() {
+            if other.target_id.is_some_and(|tid| self.removed.contains(&tid)) {
+                other.clear_target();
+            }
+        }
+
+        for wave in self.reinforcements.iter_mut() {
+            if wave.faction_id == Some(faction_id) {
+                wave.units.clear();
+            } else if wave.faction_id.is_none() {
+                wave.units.retain(|u| u.faction_id != faction_id);
+            }
+        }
+        self.reinforcements.retain(|w| !w.units.is_empty());
+
+        self.prune_groups();
+        self.rebuild_spatial_grid();
+        self.combat_log.push(self.tick, CombatLogEntry::FactionSurrendered(faction_id));
+        self.is_idle = false;
+        self.record(ReplayCommand::SurrenderFaction { faction_id });
+        log_at!(Info, "[Simulator] Faction {} surrendered ({} units withdrawn)", faction_id, count);
+        true
+    }
+
+    /// Put a unit in a group (None takes it out) - it takes on the group's
+    /// stance if one was set. Returns true if the unit was found
+    pub fn assign_group(&mut self, unit_id: u32, group_id: Option<u32>) -> bool {
+        let Some(idx) = self.units.iter().position(|u| u.id == unit_id && u.is_alive() && !u.is_withdrawn()) else {
+            return false;
+        };
+        if let Some(old) = self.units[idx].group_id {
+            if let Some(members) = self.groups.get_mut(&old) {
+                members.retain(|&m| m != idx);
+            }
+        }
+        self.units[idx].group_id = group_id;
+        self.index_groups_at(idx);
+        self.is_idle = false;
+        self.record(ReplayCommand::AssignGroup { unit_id, group_id });
+        true
+    }
+
+    /// assign_group for several units - returns how many were found
+    pub fn assign_groups(&mut self, assignments: &[GroupAssignment]) -> u32 {
+        assignments.iter()
+            .filter(|a| self.assign_group(a.unit_id, a.group_id))
+            .count() as u32
+    }
+
+    /// Give a group a focus target - every member in the fight takes it now
+    /// (members with a locked target keep theirs) and comes back to it
+    /// whenever it's in range. Returns how many members were retargeted;
+    /// 0 (and nothing changes) if the target isn't a valid enemy of theirs
+    pub fn set_group_target(&mut self, group_id: u32, target_id: u32) -> u32 {
+        let Some(target_faction) = self.units.iter()
+            .find(|t| t.id == target_id && t.is_valid_target())
+            .map(|t| t.faction_id) else {
+            return 0;
+        };
+        let Some(members) = self.groups.get(&group_id) else {
+            return 0;
+        };
+
+        let now = self.last_time;
+        let mut count = 0;
+        for &idx in members {
+            let unit = &mut self.units[idx];
+            if unit.in_battle() && !unit.target_locked && self.relations.is_hostile(unit.faction_id, target_faction) {
+                unit.set_target(Some(target_id), now);
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return 0; // Not an enemy of anyone in the group
+        }
+        self.group_targets.insert(group_id, target_id);
+        self.is_idle = false;
+        self.record(ReplayCommand::GroupTarget { group_id, target_id });
+        log_at!(Info, "[Group] Group {} focusing {} ({} members)", group_id, target_id, count);
+        count
+    }
+
+    /// Set every member's stance (and that of units joining later) - returns
+    /// how many members there were
+    pub fn set_group_stance(&mut self, group_id: u32, stance: Stance) -> u32 {
+        self.group_stances.insert(group_id, stance);
+        self.record(ReplayCommand::GroupStance { group_id, stance });
+        let members = self.groups.get(&group_id).map_or(&[][..], |m| m.as_slice());
+        for &idx in members {
+            self.units[idx].stance = stance;
+        }
+        self.is_idle = false;
+        members.len() as u32
+    }
+
+    /// Disband a group - members keep their target and stance but leave it,
+    /// and its focus target and stance are dropped. Returns how many members
+    /// there were
+    pub fn clear_group(&mut self, group_id: u32) -> u32 {
+        self.record(ReplayCommand::ClearGroup { group_id });
+        self.group_targets.remove(&group_id);
+        self.group_stances.remove(&group_id);
+        let members = self.groups.remove(&group_id).unwrap_or_default();
+        for &idx in &members {
+            self.units[idx].group_id = None;
+        }
+        members.len() as u32
+    }
+
+    /// Ids of a group's members (empty for an unknown group)
+    pub fn group_members(&self, group_id: u32) -> Vec<u32> {
+        self.groups.get(&group_id)
+            .map(|members| members.iter().map(|&idx| self.units[idx].id).collect())
+            .unwrap_or_default()
+    }
+
+    /// Focus target of a unit's group, if it has one
+    #[inline]
+    fn group_target(&self, unit: &BattleUnit) -> Option<u32> {
+        if self.group_targets.is_empty() {
+            return None;
+        }
+        unit.group_id.and_then(|group_id| self.group_targets.get(&group_id).copied())
+    }
+
+    /// Index units[from..] that arrived with a group_id
+    fn index_groups(&mut self, from: usize) {
+        for idx in from..self.units.len() {
+            self.index_groups_at(idx);
+        }
+    }
+
+    /// Add one unit to its group's index and give it the group's stance
+    fn index_groups_at(&mut self, idx: usize) {
+        let unit = &mut self.units[idx];
+        let Some(group_id) = unit.group_id else {
+            return;
+        };
+        if let Some(&stance) = self.group_stances.get(&group_id) {
+            unit.stance = stance;
+        }
+        let members = self.groups.entry(group_id).or_default();
+        if !members.contains(&idx) {
+            members.push(idx);
+        }
+    }
+
+    /// Drop members that are out of the battle for good (destroyed, withdrawn,
+    /// surrendered) and focus targets that are no longer valid
+    fn prune_groups(&mut self) {
+        if self.groups.is_empty() {
+            return;
+        }
+        let units = &self.units;
+        self.groups.retain(|_, members| {
+            members.retain(|&idx| {
+                let unit = &units[idx];
+                unit.is_alive() && !unit.is_withdrawn() && !unit.is_surrendered
+            });
+            !members.is_empty()
+        });
+        self.group_targets.retain(|_, target_id| {
+            units.iter().any(|u| u.id == *target_id && u.is_siege_target())
+        });
+    }
+
+    /// Replace the battle's win conditions - (faction_id, objective) pairs,
+    /// a faction may have several. Progress and completions start over.
+    /// An empty list goes back to elimination.
+    pub fn set_objectives(&mut self, objectives: Vec<(u32, BattleObjective)>) {
+        log_at!(Info, "[Simulator] {} objectives set", objectives.len());
+        if self.recorder.enabled {
+            self.recorder.record_command(ReplayCommand::SetObjectives { objectives: objectives.clone() });
+        }
+        self.objective_progress = vec![ObjectiveProgress::default(); objectives.len()];
+        self.objectives = objectives;
+        self.completed_objectives.clear();
+        self.victory = None;
+    }
+
+    pub fn objectives(&self) -> &[(u32, BattleObjective)] {
+        &self.objectives
+    }
+
+    /// (faction_id, objective) for each objective completed so far
+    pub fn completed_objectives(&self) -> &[(u32, String)] {
+        &self.completed_objectives
+    }
+
+    /// Check every objective not yet completed, then config.victory - returns
+    /// the objectives completed this tick as "faction_id:objective". The
+    /// first one met (objectives in order, then the condition) decides the
+    /// battle.
+    fn evaluate_objectives(&mut self) -> Vec<String> {
+        let mut arriving: Vec<u32> = self.reinforcements.iter()
+            .flat_map(|w| w.units.iter().map(|u| w.faction_id.unwrap_or(u.faction_id)))
+            .collect();
+        arriving.sort_unstable();
+        arriving.dedup();
+
+        let mut met: Vec<String> = Vec::new();
+        for ((faction_id, objective), progress) in self.objectives.iter().zip(self.objective_progress.iter_mut()) {
+            if progress.completed {
+                continue;
+            }
+            objective.update(*faction_id, &self.units, &self.relations, progress);
+            if !objective.is_met(*faction_id, &self.units, &self.relations, &arriving, self.tick, progress) {
+                continue;
+            }
+            progress.completed = true;
+            let name = objective.to_string();
+            log_at!(Info, "[Simulator] Faction {} completed objective {} at tick {}", faction_id, name, self.tick);
+            met.push(format!("{}:{}", faction_id, name));
+            if self.victory.is_none() {
+                self.victory = Some(VictoryState { winner: Some(*faction_id), condition: name.clone(), tick: self.tick });
+            }
+            self.completed_objectives.push((*faction_id, name));
+        }
+
+        // Elimination alone is left to end_reason()
+        if self.victory.is_none() && self.config.victory != VictoryCondition::Elimination {
+            if let Some((winner, condition)) = self.config.victory.check(&self.units, &self.relations, &arriving, self.tick) {
+                log_at!(Info, "[Simulator] Victory condition {} met at tick {} - winner {:?}", condition, self.tick, winner);
+                self.victory = Some(VictoryState { winner, condition, tick: self.tick });
+            }
+        }
+        met
+    }
+
+    /// How the battle was decided by an objective or config.victory - None
+    /// until then, and always for plain elimination
+    pub fn victory_state(&self) -> Option<&VictoryState> {
+        self.victory.as_ref()
+    }
+
+    /// Add a capturable resource node - false if a node with its id exists
+    pub fn add_resource_node(&mut self, node: ResourceNode) -> bool {
+        if self.resource_nodes.iter().any(|n| n.id == node.id) {
+            return false;
+        }
+        log_at!(Info, "[Simulator] Added resource node {} ({} per tick)", node.id, node.resource_per_tick);
+        if self.recorder.enabled {
+            self.recorder.record_command(ReplayCommand::AddResourceNode { node: node.clone() });
+        }
+        self.resource_nodes.push(node);
+        true
+    }
+
+    /// Remove a resource node - resources already earned are kept
+    pub fn remove_resource_node(&mut self, node_id: u32) -> bool {
+        let before = self.resource_nodes.len();
+        self.resource_nodes.retain(|n| n.id != node_id);
+        if self.resource_nodes.len() == before {
+            return false;
+        }
+        self.record(ReplayCommand::RemoveResourceNode { node_id });
+        true
+    }
+
+    pub fn resource_nodes(&self) -> &[ResourceNode] {
+        &self.resource_nodes
+    }
+
+    /// Total resources each faction has earned from nodes
+    pub fn faction_resources(&self) -> &HashMap<u32, f32> {
+        &self.faction_resources
+    }
+
+    /// Update node control and pay the holders - returns (faction_id, amount)
+    /// earned this tick, by faction id
+    fn process_resource_nodes(&mut self) -> Vec<(u32, f32)> {
+        let mut gained: Vec<(u32, f32)> = Vec::new();
+        for node in self.resource_nodes.iter_mut() {
+            let before = node.controlled_by;
+            let Some((faction_id, amount)) = node.update(&self.units) else {
+                continue;
+            };
+            if before != Some(faction_id) {
+                log_at!(Info, "[Simulator] Faction {} captured resource node {} at tick {}", faction_id, node.id, self.tick);
+            }
+            match gained.iter_mut().find(|(f, _)| *f == faction_id) {
+                Some((_, total)) => *total += amount,
+                None => gained.push((faction_id, amount)),
+            }
+        }
+        gained.sort_unstable_by_key(|&(faction_id, _)| faction_id);
+        for &(faction_id, amount) in &gained {
+            *self.faction_resources.entry(faction_id).or_insert(0.0) += amount;
+        }
+        gained
+    }
+
+    /// Ids of surrendered units that are still alive
+    pub fn get_surrendered_units(&self) -> Vec<u32> {
+        self.units.iter()
+            .filter(|u| u.is_surrendered && u.is_alive())
+            .map(|u| u.id)
+            .collect()
+    }
+
+    pub fn get_active_factions(&self) -> Vec<u32> {
+        let mut factions: Vec<u32> = self.units
+            .iter()
+            .filter(|u| u.in_battle())
+            .map(|u| u.faction_id)
+            .collect();
+
+        factions.sort();
+        factions.dedup();
+        factions
+    }
+
+    /// Recorded battle events
+    pub fn combat_log(&self) -> &CombatLog {
+        &self.combat_log
+    }
+
+    pub fn clear_combat_log(&mut self) {
+        self.combat_log.clear();
+    }
+
+    /// Current tick number (ticks simulated so far)
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Ticks since a weapon last fired at an enemy or anything took damage
+    pub fn ticks_since_combat(&self) -> u64 {
+        self.tick - self.last_combat_tick
+    }
+
+    /// Ticks without combat after which the battle ends as a stalemate
+    pub fn stalemate_threshold(&self) -> u64 {
+        self.ticks.stalemate
+    }
+
+    /// Change the stalemate threshold (config.stalemate_ticks) mid-battle
+    pub fn set_stalemate_threshold(&mut self, ticks: u64) {
+        log_at!(Info, "[Simulator] Stalemate threshold set to {} ticks", ticks);
+        self.config.stalemate_ticks = Some(ticks);
+        self.ticks.stalemate = ticks;
+        self.record_config();
+    }
+
+    /// Over half the stalemate threshold has passed without combat
+    pub fn stalemate_warning(&self) -> bool {
+        self.ticks_since_combat() > self.ticks.stalemate / 2
+    }
+
+    /// Check if battle is in stalemate (no combat for config.stalemate_secs)
+    pub fn is_stalemate(&self) -> bool {
+        let threshold = self.ticks.stalemate;
+        // Need at least some ticks to have passed
+        if self.tick < threshold {
+            return false;
+        }
+        
+        // If multiple factions exist but no combat for a while, it's a stalemate
+        let factions = self.get_active_factions();
+        if factions.len() > 1 && (self.tick - self.last_combat_tick) >= threshold {
+            log_at!(Info,
+                "[Simulator] Stalemate detected! {} ticks since last combat (threshold: {})",
+                self.tick - self.last_combat_tick, threshold
+            );
+            return true;
+        }
+        
+        false
+    }
+
+    pub fn is_battle_ended(&self) -> bool {
+        // Battle ends if: only one faction (or only allied factions) remain OR stalemate detected
+        self.end_reason().is_some()
+    }
+
+    /// Fire one of a unit's weapons at a chosen enemy, ignoring its target
+    ///
+    /// Uses the first weapon tagged weapon_tag that is enabled, loaded, off
+    /// cooldown and in range (sequence and target lock don't apply). Only that
+    /// weapon's last_fired / ammo change. Damage lands, and the shot is listed in
+    /// weaponsFired, on the next simulate_tick. None if nothing could fire.
+    pub fn manually_fire(&mut self, attacker_id: u32, target_id: u32, weapon_tag: &str, current_time: f64) -> Option<WeaponFired> {
+        let current_time = self.sim_time(current_time);
+        let attacker_idx = self.units.iter().position(|u| u.id == attacker_id && u.in_battle())?;
+        let target_idx = self.units.iter().position(|u| u.id == target_id && u.in_battle())?;
+        if !self.relations.is_hostile(self.units[attacker_idx].faction_id, self.units[target_idx].faction_id) {
+            return None;
+        }
+
+        for weapon in self.units[attacker_idx].weapons.iter_mut().filter(|w| w.tag == weapon_tag) {
+            weapon.update_reload(current_time);
+        }
+
+        let attacker = &self.units[attacker_idx];
+        let target = &self.units[target_idx];
+        let distance = attacker.distance(target);
+        let weapon_idx = attacker.weapons.iter().position(|w| {
+            w.tag == weapon_tag
+                && !w.is_disabled
+                && !w.is_repair
+                && w.category != WeaponCategory::PointDefense
+                && factions::may_fire(&self.faction_configs, attacker.faction_id, &w.tag)
+                && w.has_ammo()
+                && attacker.has_energy_for(w.energy_cost)
+                && current_time - w.last_fired >= w.cooldown as f64
+                && distance <= w.max_range
+        });
+        let Some(weapon_idx) = weapon_idx else {
+            log_at!(Debug,
+                "[Manual] Unit {} can't fire {} at {} (dist={:.1})",
+                attacker_id, weapon_tag, target_id, distance
+            );
+            return None;
+        };
+        let damage = shot_damage(attacker, target, &attacker.weapons[weapon_idx], distance)
+            * factions::damage_multiplier(&self.faction_configs, attacker.faction_id)
+            * attacker.effective_damage_multiplier();
+        let chance = hit_chance(&attacker.weapons[weapon_idx], target, target.speed(current_time), distance)
+            * attacker.accuracy_multiplier();
+        let hit = chance >= 1.0 || self.rng.next_f64() < chance as f64;
+
+        let cost = self.units[attacker_idx].weapons[weapon_idx].energy_cost;
+        self.units[attacker_idx].spend_energy(cost);
+        let weapon = &mut self.units[attacker_idx].weapons[weapon_idx];
+        weapon.last_fired = current_time;
+        weapon.consume_ammo(current_time);
+        let mut fired = WeaponFired {
+            attacker_id,
+            target_id,
+            weapon_type: weapon.tag.clone(),
+            impact_time: calculate_impact_time(distance, &weapon.tag),
+            ammo_remaining: weapon.ammo,
+            hit,
+            ..Default::default()
+        };
+        if self.config.include_fire_positions {
+            let (attacker, target) = (&self.units[attacker_idx], &self.units[target_idx]);
+            fired = fired.with_positions(attacker, target, shot_seed(self.tick, attacker_id, weapon_idx));
+        }
+
+        self.manual_shots.push(ManualShot {
+            attacker_idx,
+            target_idx,
+            weapon_idx,
+            damage,
+            fired: fired.clone(),
+        });
+        self.is_idle = false;
+        if self.recorder.enabled {
+            self.recorder.record_manual_fire(attacker_id, target_id, weapon_tag, current_time);
+        }
+        log_at!(Debug,
+            "[Manual] Unit {} fired {} at {} dmg={:.1}",
+            attacker_id, weapon_tag, target_id, damage
+        );
+        Some(fired)
+    }
+
+    /// Start recording a replay from the current units
+    ///
+    /// Only before the first tick - tick counters and idle state aren't part of
+    /// the snapshot - and only in SimulationMode::Deterministic, since random
+    /// rolls wouldn't play back the same. Returns false otherwise.
+    pub fn start_recording(&mut self) -> bool {
+        if self.tick > 0 {
+            return false;
+        }
+        if self.config.mode == SimulationMode::Stochastic {
+            log_at!(Warn, "[Replay] Not recording - a replay needs SimulationMode::Deterministic");
+            return false;
+        }
+        self.recorder.start(&self.units, self.last_time, &self.config, self.relations.alliances());
+        log_at!(Info, "[Replay] Recording started with {} units", self.units.len());
+        true
+    }
+
+    /// Note a player command for the replay, if one is being recorded
+    fn record(&mut self, command: ReplayCommand) {
+        if self.recorder.enabled {
+            self.recorder.record_command(command);
+        }
+    }
+
+    /// Note the whole config after it changed, if a replay is being recorded
+    fn record_config(&mut self) {
+        if self.recorder.enabled {
+            let config = BattleConfig { record_replay: false, ..self.config.clone() };
+            self.recorder.record_command(ReplayCommand::SetConfig { config: Box::new(config) });
+        }
+    }
+
+    /// Stop recording - the recorded ticks stay available to export_replay
+    pub fn stop_recording(&mut self) {
+        self.recorder.stop();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.enabled
+    }
+
+    /// bincode-encoded replay of the ticks recorded so far
+    pub fn export_replay(&self) -> Result<Vec<u8>, bincode::Error> {
+        self.recorder.export()
+    }
+
+    /// Feed the next recorded tick's inputs and simulate it - None when the
+    /// replay is done (or this simulator wasn't built with from_replay)
+    pub fn step_replay(&mut self) -> Option<TickResult> {
+        let input = self.playback.pop_front()?;
+        // Recorded inputs already passed validation when they were first fed in
+        for wave in input.reinforcements {
+            self.schedule_wave(wave).ok();
+        }
+        if !input.added_units.is_empty() {
+            self.add_units(input.added_units, input.current_time).ok();
+        }
+        if !input.position_updates.is_empty() {
+            self.update_positions(&input.position_updates);
+        }
+        for command in input.commands {
+            self.apply_command(command);
+        }
+        for fire in &input.manual_fires {
+            self.manually_fire(fire.attacker_id, fire.target_id, &fire.weapon_tag, fire.time);
+        }
+        for &unit_id in &input.self_destructs {
+            self.trigger_self_destruct(unit_id);
+        }
+        Some(self.simulate_tick(input.dt, input.current_time))
+    }
+
+    /// Re-issue a recorded command
+    fn apply_command(&mut self, command: ReplayCommand) {
+        match command {
+            ReplayCommand::LockTarget { unit_id, target_id } => { self.lock_target(unit_id, target_id); }
+            ReplayCommand::UnlockTarget { unit_id } => { self.unlock_target(unit_id); }
+            ReplayCommand::MoveOrder { unit_id, x, y, z } => { self.issue_move_order(unit_id, x, y, z); }
+            ReplayCommand::ClearOrders { unit_id } => { self.clear_orders(unit_id); }
+            ReplayCommand::SetWaypoints { unit_id, waypoints } => { self.set_unit_waypoints(unit_id, waypoints); }
+            ReplayCommand::ClearWaypoints { unit_id } => { self.clear_unit_waypoints(unit_id); }
+            ReplayCommand::AssignGroup { unit_id, group_id } => { self.assign_group(unit_id, group_id); }
+            ReplayCommand::GroupTarget { group_id, target_id } => { self.set_group_target(group_id, target_id); }
+            ReplayCommand::GroupStance { group_id, stance } => { self.set_group_stance(group_id, stance); }
+            ReplayCommand::ClearGroup { group_id } => { self.clear_group(group_id); }
+            ReplayCommand::RemoveUnit { unit_id } => { self.remove_unit(unit_id); }
+            ReplayCommand::SurrenderFaction { faction_id } => { self.surrender_faction(faction_id); }
+            ReplayCommand::SetAllied { a, b, allied } => self.set_factions_allied(a, b, allied),
+            ReplayCommand::SetConfig { config } => { self.set_config(*config).ok(); }
+            ReplayCommand::SetFactionConfig { config } => self.set_faction_config(config),
+            ReplayCommand::SetObjectives { objectives } => self.set_objectives(objectives),
+            ReplayCommand::AddResourceNode { node } => { self.add_resource_node(node); }
+            ReplayCommand::RemoveResourceNode { node_id } => { self.remove_resource_node(node_id); }
+            ReplayCommand::LaunchFighter { carrier_id, fighter_id, time } => { self.launch_fighter(carrier_id, fighter_id, time).ok(); }
+            ReplayCommand::DockFighter { carrier_id, fighter_id } => { self.dock_fighter(carrier_id, fighter_id).ok(); }
+            ReplayCommand::SetPower { unit_id, shield, weapons, engines } => { self.set_unit_power(unit_id, shield, weapons, engines).ok(); }
+            ReplayCommand::SetOrbitMode { unit_id, enabled } => { self.set_unit_orbit_mode(unit_id, enabled); }
+            ReplayCommand::SetRetreatThreshold { unit_id, fraction } => { self.set_retreat_threshold(unit_id, fraction); }
+            ReplayCommand::SetRetreatTarget { unit_id, x, y, z } => { self.set_retreat_target(unit_id, x, y, z); }
+            ReplayCommand::ForceRetarget { unit_id: Some(unit_id) } => { self.force_retarget_unit(unit_id); }
+            ReplayCommand::ForceRetarget { unit_id: None } => { self.force_retarget_all(); }
+            ReplayCommand::ClassWeaponRestrictions { class, allowed_tags } => { self.set_class_weapon_restrictions(class, allowed_tags); }
+            ReplayCommand::WeaponTagPrefix { prefix, category } => self.register_weapon_tag_prefix(&prefix, category),
+        }
+    }
+
+    /// Blow a unit up at the start of the next tick's combat phase - its
+    /// blast hits everything within self_destruct_radius (see
+    /// detonate_self_destructs). False if the unit isn't in the battle or
+    /// carries no charge.
+    pub fn trigger_self_destruct(&mut self, unit_id: u32) -> bool {
+        if !self.units.iter().any(|u| u.id == unit_id && u.in_battle() && u.can_self_destruct()) {
+            return false;
+        }
+        if self.recorder.enabled {
+            self.recorder.record_self_destruct(unit_id);
+        }
+        self.pending_self_destructs.push(unit_id);
+        self.is_idle = false;
+        true
+    }
+
+    /// Recorded ticks left to play back
+    pub fn replay_ticks_remaining(&self) -> usize {
+        self.playback.len()
+    }
+
+    /// Why the battle has ended, None while it's still going
+    fn end_reason(&self) -> Option<CompletionReason> {
+        if self.victory.is_some() {
+            return Some(CompletionReason::Objective);
+        }
+        // Not over while reinforcements are still on the way
+        if !self.reinforcements.is_empty() {
+            return None;
+        }
+        let factions = self.get_active_factions();
+        // With objectives a faction left alone keeps going until it completes
+        // one (or nobody is left at all)
+        let eliminated = if self.objectives.is_empty() {
+            !self.relations.any_hostile(&factions)
+        } else {
+            factions.is_empty()
+        };
+        if eliminated {
+            Some(CompletionReason::Elimination)
+        } else if self.is_stalemate() {
+            Some(CompletionReason::Stalemate)
+        } else {
+            None
+        }
+    }
+
+    /// Tick until the battle ends or max_ticks have run - the clock is the
+    /// caller's: tick(sim, i) runs the i-th tick. A paused simulator isn't
+    /// stepped. None if it's still going.
+    fn run_until_end(&mut self, max_ticks: u32, mut tick: impl FnMut(&mut Self, u32) -> TickResult) -> Option<CompletionReason> {
+        let mut reason = self.end_reason();
+        if self.paused {
+            return reason;
+        }
+        let mut i = 0;
+        while reason.is_none() && i < max_ticks {
+            let result = tick(self, i);
+            self.recycle_result(result);
+            reason = self.end_reason();
+            i += 1;
+        }
+        reason
+    }
+
+    /// Step the battle until it ends or max_ticks have run, then report
+    ///
+    /// Each tick advances the clock by dt from the last simulate_tick time.
+    /// Tick results are recycled straight away rather than collected. A paused
+    /// simulator isn't stepped (reported as TickCap after 0 ticks).
+    pub fn run_to_completion(&mut self, dt: f32, max_ticks: u32) -> CompletionReport {
+        let start_tick = self.tick;
+        let reason = self.run_until_end(max_ticks, |sim, _| sim.advance(dt, sim.last_time + dt as f64));
+        // Ran ahead of the host clock
+        self.resync_clock |= self.tick > start_tick;
+
+        let report = CompletionReport {
+            winner: reason.and_then(|_| self.get_winner()),
+            ticks: self.tick - start_tick,
+            reason: reason.unwrap_or(CompletionReason::TickCap),
+            tick: self.tick,
+            ticks_since_last_combat: self.ticks_since_combat(),
+            survivors: self.units
+                .iter()
+                .filter(|u| u.in_battle())
+                .map(|u| SurvivingUnit { id: u.id, faction_id: u.faction_id, hp: u.hp, shield: u.shield })
+                .collect(),
+            factions: self.faction_summary(),
+            weapons: self.weapon_totals(),
+        };
+        log_at!(Info,
+            "[Simulator] Ran to completion: {:?} after {} ticks, winner {:?}, {} survivors",
+            report.reason, report.ticks, report.winner, report.survivors.len()
+        );
+        report
+    }
+
+    /// n (at most config.max_fast_forward_ticks), warning when cut short
+    fn fast_forward_ticks(&self, n: u32) -> u32 {
+        let cap = self.config.max_fast_forward_ticks;
+        if n > cap {
+            log_at!(Warn, "[Simulator] Fast-forward of {} ticks truncated to {}", n, cap);
+        }
+        n.min(cap)
+    }
+
+    /// n consecutive simulate_tick calls, the i-th at start_time + i * dt
+    ///
+    /// n is capped at config.max_fast_forward_ticks. Hand the results back
+    /// with recycle_result once done with them.
+    pub fn simulate_ticks(&mut self, n: u32, dt: f32, start_time: f64) -> Vec<TickResult> {
+        (0..self.fast_forward_ticks(n))
+            .map(|i| self.simulate_tick(dt, start_time + i as f64 * dt as f64))
+            .collect()
+    }
+
+    /// simulate_tick from start_time in steps of dt until the battle ends or
+    /// max_ticks (capped at config.max_fast_forward_ticks) have run
+    ///
+    /// run_to_completion on the host clock - a paused simulator isn't stepped
+    /// (TickCap after 0 ticks).
+    pub fn simulate_until_end(&mut self, max_ticks: u32, dt: f32, start_time: f64) -> BattleOutcome {
+        let max_ticks = self.fast_forward_ticks(max_ticks);
+        let start_tick = self.tick;
+        let reason = self.run_until_end(max_ticks, |sim, i| sim.simulate_tick(dt, start_time + i as f64 * dt as f64));
+
+        BattleOutcome {
+            winner: reason.and_then(|_| self.get_winner()),
+            reason: reason.unwrap_or(CompletionReason::TickCap),
+            ticks: self.tick - start_tick,
+            tick: self.tick,
+            survivors: self.units.iter().filter(|u| u.in_battle()).count() as u32,
+        }
+    }
+
+    // =========================================================================
+    // Snapshots
+    // =========================================================================
+
+    /// Current state, for restore() - a full copy of the units
+    pub fn snapshot(&self) -> BattleSnapshot {
+        BattleSnapshot {
+            units: self.units.clone(),
+            tick: self.tick,
+            last_combat_tick: self.last_combat_tick,
+            alliances: self.relations.alliances(),
+            cell_size: self.grid.grid_stats().map(|stats| stats.cell_size),
+            time: self.last_time,
+            last_movement_tick: self.last_movement_tick,
+            idle_tick_count: self.idle_tick_count,
+            objective_progress: self.objective_progress.clone(),
+            completed_objectives: self.completed_objectives.clone(),
+            victory: self.victory.clone(),
+            group_targets: self.group_targets.clone(),
+            reinforcements: self.reinforcements.clone(),
+            node_owners: self.resource_nodes.iter()
+                .filter_map(|node| node.controlled_by.map(|faction_id| (node.id, faction_id)))
+                .collect(),
+            faction_resources: self.faction_resources.clone(),
+        }
+    }
+
+    /// State at construction (tick 0), before any unit was added or tick run
+    pub fn initial_snapshot(&self) -> &BattleSnapshot {
+        &self.initial_snapshot
+    }
+
+    /// Roll back to a snapshot - the units, tick counters, alliances, group
+    /// targets, pending reinforcements, resource node owners and income, and
+    /// objective progress (with whatever had decided the battle) are replaced
+    /// and the spatial grid is rebuilt from the snapshot's units
+    ///
+    /// Trusts its input like new(). Anything queued for the next tick (manual
+    /// shots, retargeted missiles, self-destructs) and the position / vitals
+    /// delta_tick compares against are dropped, and sensor contacts are worked
+    /// out afresh; the config, objectives, resource nodes and replay recording
+    /// are left alone - progress saved for a different set of objectives
+    /// starts over, and nodes the snapshot has no owner for are unowned.
+    /// Re-simulate from snapshot.time to catch up.
+    pub fn restore(&mut self, snapshot: BattleSnapshot) {
+        let BattleSnapshot {
+            units, tick, last_combat_tick, alliances, cell_size, time, last_movement_tick, idle_tick_count,
+            objective_progress, completed_objectives, victory, group_targets,
+            reinforcements, node_owners, faction_resources,
+        } = snapshot;
+        log_at!(Info, "[Simulator] Restoring tick {} ({} units) at tick {}", tick, units.len(), self.tick);
+        self.units = units;
+        self.tick = tick;
+        self.last_combat_tick = last_combat_tick;
+        self.last_movement_tick = last_movement_tick;
+        self.idle_tick_count = idle_tick_count;
+        self.last_time = time;
+
+        self.objective_progress = if objective_progress.len() == self.objectives.len() {
+            objective_progress
+        } else {
+            vec![ObjectiveProgress::default(); self.objectives.len()]
+        };
+        self.completed_objectives = completed_objectives;
+        self.victory = victory;
+        self.group_targets = group_targets;
+        self.reinforcements = reinforcements;
+        for node in self.resource_nodes.iter_mut() {
+            node.controlled_by = node_owners.get(&node.id).copied();
+        }
+        self.faction_resources = faction_resources;
+
+        self.relations = FactionRelations::default();
+        for (a, b) in alliances {
+            self.relations.set_allied(a, b, true);
+        }
+        if let Some(cell_size) = cell_size {
+            self.grid.set_cell_size(cell_size);
+        }
+
+        self.manual_shots.clear();
+        self.retargeted_shots.clear();
+        self.pending_self_destructs.clear();
+        self.retarget_now.clear();
+        self.launched.clear();
+        self.docked.clear();
+        self.removed.clear();
+        self.prev_positions.clear();
+        self.prev_vitals.clear();
+        self.buffers.last_energy.clear();
+        self.detection = Detection::default();
+
+        self.groups = group_index(&self.units);
+        self.rebuild_spatial_grid();
+        self.next_weapon_ready_time = 0.0;
+        self.is_idle = false;
+    }
+
+    pub fn get_results(&self) -> Vec<BattleUnit> {
+        self.units.clone()
+    }
+
+    pub fn get_units(&self) -> &[BattleUnit] {
+        &self.units
+    }
+
+    // =========================================================================
+    // Alliances
+    // =========================================================================
+
+    /// Mark two factions allied (or hostile again)
+    /// Targets between the pair are cleared so units re-acquire next tick
+    pub fn set_factions_allied(&mut self, a: u32, b: u32, allied: bool) {
+        if !self.relations.set_allied(a, b, allied) {
+            return;
+        }
+        self.record(ReplayCommand::SetAllied { a, b, allied });
+        log_at!(Info,
+            "[Simulator] Factions {} and {} now {}",
+            a, b, if allied { "allied" } else { "hostile" }
+        );
+
+        let faction_of: HashMap<u32, u32> = self.units.iter().map(|u| (u.id, u.faction_id)).collect();
+        for unit in self.units.iter_mut() {
+            let Some(target_faction) = unit.target_id.and_then(|t| faction_of.get(&t).copied()) else {
+                continue;
+            };
+            if (unit.faction_id, target_faction) == (a, b) || (unit.faction_id, target_faction) == (b, a) {
+                unit.clear_target();
+            }
+        }
+        self.is_idle = false;
+    }
+
+    /// Replace every alliance with exactly these pairs
+    pub fn set_alliances(&mut self, pairs: &[(u32, u32)]) {
+        for (a, b) in self.relations.alliances() {
+            if !pairs.iter().any(|&(x, y)| (x, y) == (a, b) || (y, x) == (a, b)) {
+                self.set_factions_allied(a, b, false);
+            }
+        }
+        for &(a, b) in pairs {
+            self.set_factions_allied(a, b, true);
+        }
+    }
+
+    /// Set (or replace) a faction's balance multipliers and special weapons
+    pub fn set_faction_config(&mut self, config: FactionConfig) {
+        log_at!(Info,
+            "[Faction] {} config: damage x{} shield regen x{} speed x{}, special weapons {:?}",
+            config.faction_id, config.damage_multiplier, config.shield_regen_multiplier,
+            config.speed_multiplier, config.special_weapon_tags
+        );
+        if self.recorder.enabled {
+            self.recorder.record_command(ReplayCommand::SetFactionConfig { config: config.clone() });
+        }
+        self.faction_configs.insert(config.faction_id, config);
+        self.is_idle = false;
+    }
+
+    /// Phase timings since profiling was switched on (see profiling.rs)
+    pub fn profile_stats(&self) -> ProfileStats {
+        self.profiler.stats(self.config.profile_window)
+    }
+
+    /// Mean compute time of the last TICK_DURATION_WINDOW ticks, in microseconds
+    pub fn average_tick_duration_us(&self) -> u32 {
+        self.tick_durations.average_us()
+    }
+
+    pub fn reset_profile(&mut self) {
+        self.profiler.reset();
+    }
+
+    /// Record the time since the timer's last lap as `phase` - for work done
+    /// outside simulate_tick (serializing its result)
+    pub fn lap_phase(&mut self, timer: &mut PhaseTimer, phase: Phase) {
+        timer.lap(&mut self.profiler, phase, self.config.profile_window);
+    }
+
+    /// A faction's config (None = everything at 1x)
+    pub fn faction_config(&self, faction_id: u32) -> Option<&FactionConfig> {
+        self.faction_configs.get(&faction_id)
+    }
+
+    /// Check if two factions are on the same side (true for a == b)
+    pub fn are_allies(&self, a: u32, b: u32) -> bool {
+        self.relations.is_allied(a, b)
+    }
+
+    /// Check if units of these factions fight each other
+    pub fn is_hostile(&self, a: u32, b: u32) -> bool {
+        self.relations.is_hostile(a, b)
+    }
+
+    /// Current faction relations
+    pub fn relations(&self) -> &FactionRelations {
+        &self.relations
+    }
+
+    /// Units on the battlefield within radius of a point (boundary included), nearest first
+    ///
+    /// Goes through the spatial index, so positions are as of the last grid
+    /// rebuild (every tick and after update_positions).
+    pub fn query_units_in_radius(&self, x: f32, y: f32, z: f32, radius: f32, faction: Option<u32>) -> Vec<UnitInRadius> {
+        let mut found: Vec<UnitInRadius> = self.grid.query_range(x, y, z, radius)
+            .into_iter()
+            .filter_map(|(idx, dist_sq)| {
+                let unit = self.units.get(idx)?;
+                if !unit.in_battle() || faction.is_some_and(|f| f != unit.faction_id) {
+                    return None;
+                }
+                Some(UnitInRadius {
+                    id: unit.id,
+                    faction_id: unit.faction_id,
+                    hp: unit.hp,
+                    shield: unit.shield,
+                    pos_x: unit.pos_x,
+                    pos_y: unit.pos_y,
+                    pos_z: unit.pos_z,
+                    distance: dist_sq.sqrt(),
+                })
+            })
+            .collect();
+        found.sort_by(|a, b| a.distance.total_cmp(&b.distance).then(a.id.cmp(&b.id)));
+        found
+    }
+
+    /// Spatial grid occupancy, plus how many units in the battle out-range
+    /// the largest query the grid can answer. None for non-grid indexes.
+    ///
+    /// Reflects the last grid rebuild (every tick and after update_positions).
+    pub fn grid_stats(&self) -> Option<GridStats> {
+        let mut stats = self.grid.grid_stats()?;
+        stats.units_beyond_query_range = self.units.iter()
+            .filter(|u| u.in_battle() && u.max_weapon_range > stats.max_query_range)
+            .count();
+        Some(stats)
+    }
+
+    /// Ids of the units the spatial index hands back as candidates near a
+    /// point, unfiltered - the grid returns whole cells, so this can include
+    /// units beyond range. For checking the index against a brute-force scan.
+    pub fn debug_query_nearby(&self, x: f32, y: f32, z: f32, range: f32) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.grid.get_nearby(x, y, z, range)
+            .into_iter()
+            .filter_map(|idx| self.units.get(idx).map(|u| u.id))
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Top-down ASCII map of the units on the battlefield, `scale` world
+    /// units per character (see debug_map.rs for the legend)
+    #[cfg(debug_assertions)]
+    pub fn render_ascii(&self, width: usize, height: usize, scale_units_per_cell: f32) -> String {
+        debug_map::render_ascii(&self.units, width, height, scale_units_per_cell)
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn set_debug_map_scale(&mut self, units_per_cell: f32) {
+        self.debug_map_scale = units_per_cell;
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn debug_map_scale(&self) -> f32 {
+        self.debug_map_scale
+    }
+
+    /// Look up one unit by id (alive or dead)
+    pub fn get_unit(&self, unit_id: u32) -> Option<&BattleUnit> {
+        self.units.iter().find(|u| u.id == unit_id)
+    }
+
+    /// All units of one faction (alive or dead), borrowed in unit order
+    pub fn get_units_by_faction(&self, faction_id: u32) -> impl Iterator<Item = &BattleUnit> {
+        self.units.iter().filter(move |u| u.faction_id == faction_id)
+    }
+
+    /// Combat statistics for one unit (alive or dead)
+    pub fn get_unit_stats(&self, unit_id: u32) -> Option<CombatStats> {
+        self.get_unit(unit_id).map(|u| u.stats())
+    }
+
+    /// Magazine and supply for each of a unit's weapons, in weapon order
+    pub fn get_weapon_ammo(&self, unit_id: u32) -> Option<Vec<WeaponAmmo>> {
+        self.get_unit(unit_id).map(|u| u.weapons.iter().map(|w| w.ammo_status()).collect())
+    }
+
+    /// Veterancy level and experience for one unit (alive or dead)
+    pub fn get_unit_veterancy(&self, unit_id: u32) -> Option<Veterancy> {
+        self.get_unit(unit_id).map(|u| u.veterancy())
+    }
+
+    /// Combat statistics summed over every unit of a faction (alive or dead)
+    /// Per-weapon stats for every unit that has fired: unit id -> tag -> stats
+    pub fn get_weapon_stats(&self) -> BTreeMap<u32, BTreeMap<String, WeaponStats>> {
+        self.units
+            .iter()
+            .filter(|u| !u.weapon_stats.is_empty())
+            .map(|u| (u.id, u.weapon_stats_by_tag()))
+            .collect()
+    }
+
+    /// Weapon stats by tag summed over every unit
+    pub fn weapon_totals(&self) -> BTreeMap<String, WeaponStats> {
+        let mut totals: BTreeMap<String, WeaponStats> = BTreeMap::new();
+        for unit in &self.units {
+            for (weapon, stats) in unit.weapons.iter().zip(&unit.weapon_stats) {
+                totals.entry(weapon.tag.clone()).or_default().add(stats);
+            }
+        }
+        totals
+    }
+
+    pub fn get_faction_stats(&self, faction_id: u32) -> CombatStats {
+        let mut stats = CombatStats::default();
+        for unit in self.get_units_by_faction(faction_id) {
+            stats.add(&unit.stats());
+        }
+        stats
+    }
+
+    pub fn get_faction_counts(&self) -> HashMap<u32, usize> {
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for unit in &self.units {
+            if unit.in_battle() {
+                *counts.entry(unit.faction_id).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Active / destroyed / withdrawn / surrendered / disabled counts per faction, sorted by faction id
+    pub fn faction_summary(&self) -> Vec<FactionSummary> {
+        let mut summary: Vec<FactionSummary> = Vec::new();
+        for unit in &self.units {
+            let entry = match summary.iter_mut().position(|f| f.faction_id == unit.faction_id) {
+                Some(i) => &mut summary[i],
+                None => {
+                    summary.push(FactionSummary { faction_id: unit.faction_id, active: 0, destroyed: 0, withdrawn: 0, surrendered: 0, disabled: 0 });
+                    summary.last_mut().unwrap()
+                }
+            };
+            match unit.state {
+                UnitState::Active if unit.is_surrendered => entry.surrendered += 1,
+                UnitState::Active if unit.is_disabled => entry.disabled += 1,
+                UnitState::Active => entry.active += 1,
+                UnitState::Destroyed => entry.destroyed += 1,
+                UnitState::Withdrawn => entry.withdrawn += 1,
+            }
+        }
+        summary.sort_by_key(|f| f.faction_id);
+        summary
+    }
+
+    pub fn is_battle_over(&self) -> bool {
+        self.is_battle_ended()
+    }
+
+    pub fn get_winner(&self) -> Option<u32> {
+        // First objective or victory condition met
+        if let Some(victory) = &self.victory {
+            return victory.winner;
+        }
+        let factions = self.get_active_factions();
+        
+        if factions.len() == 1 {
+            // Clear winner - only one faction remains
+            Some(factions[0])
+        } else if factions.len() > 1 && (self.is_stalemate() || !self.relations.any_hostile(&factions)) {
+            // Stalemate (or only allies left) - faction with most units wins
+            let counts = self.get_faction_counts();
+            let mut best_faction: Option<u32> = None;
+            let mut best_count: usize = 0;
+            
+            for (faction, count) in counts {
+                if count > best_count {
+                    best_count = count;
+                    best_faction = Some(faction);
+                }
+            }
+            
+            log_at!(Info,
+                "[Simulator] Stalemate winner: faction {:?} with {} units",
+                best_faction, best_count
+            );
+            
+            best_faction
+        } else {
+            // Battle ongoing, no winner yet
+            None
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::{ArmorClass, DroneHangar, Weapon};
+    use crate::config::*;
+    use crate::movement::MOVE_ORDER_EPSILON;
+
+    const DT: f32 = 0.05;
+    /// The default retarget interval / stalemate threshold at DT
+    const RETARGET_INTERVAL: u64 = 20;
+    const STALEMATE_TICKS: u64 = 1200;
+
+    /// Result JSON without the wall-clock tick duration, for comparing runs
+    fn untimed_json(result: &TickResult) -> String {
+        serde_json::to_string(&TickResult { tick_duration_us: 0, ..result.clone() }).unwrap()
+    }
+
+    fn make_ship(id: u32, faction: u32, x: f32, dps: f32) -> BattleUnit {
+        BattleUnit::builder()
+            .id(id)
+            .faction(faction)
+            .position(x, 0.0, 0.0)
+            .max_speed(50.0)
+            .as_ship()
+            .weapons(vec![Weapon::builder().tag("LASER").dps(dps).range(80.0, 100.0).build()])
+            .build()
+            .unwrap()
+    }
+
+    /// make_ship without weapons
+    fn make_hull(id: u32, faction: u32, x: f32) -> BattleUnit {
+        BattleUnit::builder().id(id).faction(faction).position(x, 0.0, 0.0).max_speed(50.0).as_ship().build().unwrap()
+    }
+
+    /// Run ticks until the battle ends or max_ticks elapse, collecting results
+    fn run<I: SpatialIndex>(sim: &mut BattleSimulator<I>, max_ticks: u32) -> Vec<TickResult> {
+        let mut results = Vec::new();
+        for i in 0..max_ticks {
+            results.push(sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64));
+            if sim.is_battle_ended() {
+                break;
+            }
+        }
+        results
+    }
+
+    #[test]
+    fn test_weaker_ship_retreats_instead_of_dying() {
+        let strong = make_ship(1, 1, 0.0, 20.0);
+        let mut weak = make_ship(2, 2, 50.0, 5.0);
+        weak.retreat_hp_fraction = 0.5;
+
+        let mut sim = BattleSimulator::new(vec![strong, weak], 1000.0);
+        sim.set_config(BattleConfig {
+            ai_movement: true,
+            retreat_disengage_distance: 300.0,
+            ..Default::default()
+        }).unwrap();
+
+        let results = run(&mut sim, 2000);
+
+        assert!(results.iter().all(|r| r.destroyed.is_empty()));
+        assert!(results.iter().any(|r| r.retreated.contains(&2)));
+        assert!(sim.is_battle_ended());
+        assert_eq!(sim.get_active_factions(), vec![1]);
+
+        let weak = sim.get_results().into_iter().find(|u| u.id == 2).unwrap();
+        assert!(weak.is_alive());
+        assert!(weak.is_withdrawn());
+        assert!(weak.hp > 0.0);
+    }
+
+    #[test]
+    fn test_ai_ship_closes_to_optimal_range_and_fires() {
+        let mut ship = make_ship(1, 1, 0.0, 20.0);
+        ship.ai_controlled = true;
+        ship.view_range = 600.0;
+        let station = BattleUnit::builder().id(2).faction(2).position(500.0, 0.0, 0.0).hp(100000.0).as_station().build().unwrap();
+
+        let mut sim = BattleSimulator::new(vec![ship, station], 1000.0);
+        let results = run(&mut sim, 250);
+
+        assert!(results.iter().any(|r| r.moved.iter().any(|m| m.id == 1)));
+        assert!(results.iter().all(|r| r.moved.iter().all(|m| m.id != 2)));
+        assert!(results.iter().any(|r| r.weapons_fired.iter().any(|w| w.attacker_id == 1)));
+
+        let ship = &sim.get_units()[0];
+        let dist = 500.0 - ship.pos_x;
+        assert!((64.0..=80.0).contains(&dist), "settled at {}", dist);
+    }
+
+    #[test]
+    fn test_ships_ordered_to_same_point_stay_apart() {
+        let mut a = make_hull(1, 1, -100.0);
+        a.waypoints = vec![(0.0, 0.0, 0.0)];
+        let mut b = make_hull(2, 1, 100.0);
+        b.waypoints = vec![(0.0, 0.0, 0.0)];
+
+        let mut sim = BattleSimulator::new(vec![a, b], 1000.0);
+        for i in 0..200 {
+            sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+        }
+
+        let units = sim.get_units();
+        let min_dist = units[0].radius + units[1].radius;
+        assert_eq!(min_dist, 20.0);
+        assert!(units[0].distance(&units[1]) >= min_dist - 0.01);
+    }
+
+    #[test]
+    fn test_ship_cannot_be_pushed_inside_station() {
+        let station = BattleUnit::builder().id(1).faction(1).as_station().build().unwrap();
+        let mut ship = make_hull(2, 1, 200.0);
+        ship.waypoints = vec![(0.0, 0.0, 0.0)];
+
+        let mut sim = BattleSimulator::new(vec![station, ship], 1000.0);
+        let mut ship_moved = false;
+        for i in 0..200 {
+            let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            ship_moved |= result.moved.iter().any(|m| m.id == 2);
+            assert!(result.moved.iter().all(|m| m.id != 1));
+        }
+
+        let units = sim.get_units();
+        assert!(ship_moved);
+        assert_eq!((units[0].pos_x, units[0].pos_y, units[0].pos_z), (0.0, 0.0, 0.0));
+        assert!(units[0].distance(&units[1]) >= 60.0 - 0.01);
+    }
+
+    /// Two missile boats that fire together at a 1 hp unit 100 out - the
+    /// second missile arrives after the first has killed it
+    fn overkill_salvo(tag: &str, lost: BattleUnit, others: Vec<BattleUnit>) -> BattleSimulator {
+        let boat = |id: u32, x: f32| {
+            let mut boat = make_ship(id, 1, x, 10.0);
+            boat.weapons[0] = Weapon { ammo: Some(4), magazine_size: 4, ..Weapon::builder().tag(tag).dps(50.0).range(150.0, 200.0).last_fired(999.0).build() };
+            boat
+        };
+        let mut units = vec![boat(1, 0.0), boat(2, 10.0), BattleUnit { hp: 1.0, ..lost }];
+        units.extend(others);
+        BattleSimulator::new(units, 1000.0)
+    }
+
+    #[test]
+    fn test_missile_reacquires_when_target_dies_in_flight() {
+        let lost = make_target_dummy(10, 100.0);
+        let mut sim = overkill_salvo("HM", lost, vec![make_target_dummy(11, 150.0)]);
+
+        let first = sim.simulate_tick(DT, 1000.0);
+        assert_eq!(first.destroyed, vec![10]);
+        assert_eq!(first.missiles_retargeted, vec![MissileRetargeted { attacker_id: 2, from_id: 10, to_id: 11, weapon_type: "HM".to_string() }]);
+        assert!(first.missiles_fizzled.is_empty());
+        assert_eq!(sim.get_unit(11).unwrap().damage_taken, 0.0);
+
+        // It lands next tick and counts as a hit for the boat that fired it
+        let second = sim.simulate_tick(DT, 1000.0 + DT as f64);
+        assert!(second.damaged.iter().any(|d| d.id == 11));
+        let boat = sim.get_unit(2).unwrap();
+        assert_eq!((boat.shots_fired, boat.shots_hit), (1, 1));
+        assert!(boat.damage_dealt > 0.0);
+
+        // Nukes only turn on stations - the nearer ship is passed over
+        let lost = make_station(10, 100.0);
+        let mut sim = overkill_salvo("NM", lost, vec![make_target_dummy(11, 150.0), make_station(12, 190.0)]);
+        let first = sim.simulate_tick(DT, 1000.0);
+        assert_eq!(first.missiles_retargeted.iter().map(|m| m.to_id).collect::<Vec<_>>(), vec![12]);
+    }
+
+    #[test]
+    fn test_missile_fizzles_with_nothing_in_reach() {
+        // The only other enemy is far beyond the reacquire radius
+        let lost = make_target_dummy(10, 100.0);
+        let mut sim = overkill_salvo("HM", lost.clone(), vec![make_target_dummy(11, 5000.0)]);
+        let result = sim.simulate_tick(DT, 1000.0);
+        assert_eq!(result.missiles_fizzled, vec![MissileFizzled { attacker_id: 2, target_id: 10, weapon_type: "HM".to_string() }]);
+        assert!(result.missiles_retargeted.is_empty());
+
+        // Still a shot fired, but no damage or hit - and no ammo back by default
+        let boat = sim.get_unit(2).unwrap();
+        assert_eq!((boat.shots_fired, boat.shots_hit, boat.damage_dealt), (1, 0, 0.0));
+        assert_eq!(boat.weapons[0].ammo, Some(3));
+
+        let mut sim = overkill_salvo("HM", lost.clone(), vec![make_target_dummy(11, 5000.0)]);
+        sim.set_config(BattleConfig { refund_fizzled_ammo: true, ..Default::default() }).unwrap();
+        assert_eq!(sim.simulate_tick(DT, 1000.0).missiles_fizzled.len(), 1);
+        assert_eq!(sim.get_unit(2).unwrap().weapons[0].ammo, Some(4));
+        assert_eq!(sim.get_unit(1).unwrap().weapons[0].ammo, Some(3));
+
+        // Radius 0 turns reacquiring off; lasers never fizzle
+        let mut sim = overkill_salvo("HM", lost.clone(), vec![make_target_dummy(11, 150.0)]);
+        sim.set_config(BattleConfig { missile_reacquire_radius: 0.0, ..Default::default() }).unwrap();
+        assert_eq!(sim.simulate_tick(DT, 1000.0).missiles_fizzled.len(), 1);
+        let mut sim = overkill_salvo("LASER", lost, vec![make_target_dummy(11, 150.0)]);
+        let result = sim.simulate_tick(DT, 1000.0);
+        assert_eq!(result.destroyed, vec![10]);
+        assert!(result.missiles_fizzled.is_empty() && result.missiles_retargeted.is_empty());
+    }
+
+    #[test]
+    fn test_damaged_unit_breakdown() {
+        // One heavy laser and two light kinetic guns fire together - the
+        // laser did the most damage, but most hits were kinetic
+        let gun = |id: u32, x: f32, tag: &str, dps: f32| {
+            let mut ship = make_ship(id, 1, x, dps);
+            ship.weapons[0] = Weapon::builder().tag(tag).dps(dps).range(80.0, 100.0).last_fired(999.0).build();
+            ship
+        };
+        let dummy = BattleUnit::builder().id(10).faction(2).position(60.0, 0.0, 0.0).hp(100000.0).shield(30.0, 30.0).as_ship().build().unwrap();
+        let mut sim = BattleSimulator::new(
+            vec![gun(1, 0.0, "LASER", 500.0), gun(2, 5.0, "GAUSS", 50.0), gun(3, 10.0, "GAUSS", 50.0), dummy],
+            1000.0,
+        );
+
+        let result = sim.simulate_tick(DT, 1000.0);
+        let hit = result.damaged.iter().find(|d| d.id == 10).unwrap();
+        assert_eq!(hit.primary_attacker_id, Some(1));
+        assert_eq!(hit.damage_type, "kinetic");
+        assert_eq!(hit.shield_damage, 30.0);
+        assert!((hit.hull_damage - (100000.0 - hit.hp)).abs() < 0.01);
+        let dealt: f32 = sim.get_units()[..3].iter().map(|u| u.damage_dealt).sum();
+        assert!((hit.hull_damage + hit.shield_damage - dealt).abs() < 0.01);
+
+        // Burns have no attacker
+        let mut hits = vec![(0, None, None, 5.0), (1, Some(4), Some(WeaponCategory::Ion), 1.0), (1, Some(4), Some(WeaponCategory::Ion), 1.0), (1, Some(3), None, 1.5)];
+        let mut attribution = vec![(None, ""); 2];
+        attribute_hits(&mut hits, &mut attribution);
+        assert_eq!(attribution, vec![(None, "burn"), (Some(4), "ion")]);
+    }
+
+    /// Unarmed hull with a self-destruct charge
+    fn make_fire_ship(id: u32, faction: u32, x: f32, damage: f32, radius: f32, trigger_radius: f32) -> BattleUnit {
+        BattleUnit::builder().id(id).faction(faction).position(x, 0.0, 0.0).hp(500.0).as_ship()
+            .self_destruct(damage, radius, trigger_radius)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_self_destruct_chain_stops_at_limit() {
+        // A line of charged ships 30 apart, blasts reach 40 - each sets off the next
+        let mut units: Vec<BattleUnit> = (1..=6).map(|id| make_fire_ship(id, 1, (id - 1) as f32 * 30.0, 50.0, 40.0, 0.0)).collect();
+        units.push(make_target_dummy(10, -30.0));
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        sim.set_config(BattleConfig { self_destruct_chain_limit: 3, ..Default::default() }).unwrap();
+        assert!(sim.trigger_self_destruct(1));
+        assert!(!sim.trigger_self_destruct(10), "no charge");
+
+        let result = sim.simulate_tick(DT, 1000.0);
+        let chain: Vec<(u32, u32)> = result.self_destructed.iter().map(|b| (b.id, b.depth)).collect();
+        assert_eq!(chain, vec![(1, 0), (2, 1), (3, 2), (4, 3)]);
+        assert_eq!(result.destroyed, vec![1, 2, 3, 4]);
+        assert_eq!(result.self_destructed[3].x, 90.0);
+        // Past the limit a charged ship only takes the damage, and stays armed
+        let fifth = result.damaged.iter().find(|d| d.id == 5).unwrap();
+        assert_eq!((fifth.hull_damage, fifth.primary_attacker_id, fifth.damage_type.as_ref()), (50.0, Some(4), "explosive"));
+        assert!(sim.get_unit(5).unwrap().is_alive() && sim.get_unit(6).unwrap().damage_taken == 0.0);
+        // The first blast is credited to the ship that set it off
+        assert_eq!(sim.get_unit(10).unwrap().damage_taken, 50.0);
+        assert_eq!(sim.get_unit(1).unwrap().damage_dealt, 50.0);
+    }
+
+    #[test]
+    fn test_self_destruct_friendly_fire() {
+        // The enemy coming within 20 sets it off; the blast reaches the escort too
+        let units = || vec![
+            make_fire_ship(1, 1, 0.0, 100.0, 50.0, 20.0),
+            make_fire_ship(2, 1, 30.0, 100.0, 50.0, 0.0),
+            BattleUnit::builder().id(3).faction(1).position(-10.0, 0.0, 0.0).hp(500.0).as_ship().build().unwrap(),
+            BattleUnit::builder().id(4).faction(2).position(15.0, 0.0, 0.0).hp(100.0).as_ship().build().unwrap(),
+            make_target_dummy(5, 5000.0),
+        ];
+
+        let mut sim = BattleSimulator::new(units(), 1000.0);
+        let result = sim.simulate_tick(DT, 1000.0);
+        assert_eq!(result.self_destructed.iter().map(|b| b.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(sim.get_unit(3).unwrap().hp, 300.0);
+        assert!(!sim.get_unit(4).unwrap().is_alive());
+        assert_eq!(sim.get_unit(1).unwrap().kills, 1);
+
+        // Enemies only - the escort is spared and the allied charge stays put
+        let mut sim = BattleSimulator::new(units(), 1000.0);
+        sim.set_config(BattleConfig { self_destruct_friendly_fire: false, ..Default::default() }).unwrap();
+        let result = sim.simulate_tick(DT, 1000.0);
+        assert_eq!(result.self_destructed.iter().map(|b| b.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(sim.get_unit(3).unwrap().hp, 500.0);
+        assert!(sim.get_unit(2).unwrap().is_alive() && !sim.get_unit(4).unwrap().is_alive());
+    }
+
+    #[test]
+    fn test_missile_launcher_reloads_after_magazine_empties() {
+        let mut boat = make_ship(1, 1, 0.0, 10.0);
+        boat.weapons[0] = Weapon {
+            ammo: Some(4),
+            magazine_size: 4,
+            reload_time: 5.0,
+            ..Weapon::builder().tag("HM-4").range(80.0, 100.0).last_fired(999.0).build()
+        };
+        let target = BattleUnit::builder().id(2).faction(2).position(50.0, 0.0, 0.0).hp(100000.0).as_station().build().unwrap();
+
+        let mut sim = BattleSimulator::new(vec![boat, target], 1000.0);
+        let mut fire_times: Vec<f64> = Vec::new();
+        let mut ammo_seen: Vec<Option<u32>> = Vec::new();
+        for i in 0..240 {
+            let now = 1000.0 + i as f64 * DT as f64;
+            for shot in sim.simulate_tick(DT, now).weapons_fired {
+                fire_times.push(now);
+                ammo_seen.push(shot.ammo_remaining);
+            }
+        }
+
+        // Four shots one cooldown apart, then silence until the 5s reload ends
+        assert!(fire_times.len() >= 5);
+        assert_eq!(&ammo_seen[..5], &[Some(3), Some(2), Some(1), Some(0), Some(3)]);
+        let gap = fire_times[4] - fire_times[3];
+        assert!((5.0..5.2).contains(&gap), "reload gap {}", gap);
+        assert!(fire_times[3] - fire_times[0] < 3.2);
+
+        // An instant reload that finishes at time 0 still refills
+        let mut weapon = Weapon { ammo: Some(1), magazine_size: 2, reload_time: 0.0, ..Weapon::builder().tag("HM-2").build() };
+        weapon.consume_ammo(0.0);
+        assert_eq!((weapon.ammo, weapon.reloading_until), (Some(0), Some(0.0)));
+        assert!(weapon.ready_time() < f64::MAX);
+        weapon.update_reload(0.0);
+        assert_eq!((weapon.ammo, weapon.reloading_until), (Some(2), None));
+    }
+
+    fn make_target_dummy(id: u32, x: f32) -> BattleUnit {
+        BattleUnit::builder().id(id).faction(2).position(x, 0.0, 0.0).hp(100000.0).as_ship().build().unwrap()
+    }
+
+    /// Faction 1 ship firing a missile every tick
+    fn make_launcher(id: u32, y: f32) -> BattleUnit {
+        let missile = Weapon::builder().tag("MISSILE").dps(20.0).fire_rate(20.0).cooldown(0.04).range(150.0, 200.0).build();
+        BattleUnit::builder().id(id).faction(1).position(0.0, y, 0.0).as_ship().weapons(vec![missile]).build().unwrap()
+    }
+
+    /// Faction 2 dummy with a point-defense turret
+    fn make_pd_dummy(id: u32, x: f32, chance: f32, saturation_factor: f32) -> BattleUnit {
+        let pd = Weapon::builder().tag("PD").range(20.0, 30.0).intercept(chance, saturation_factor).as_point_defense().build();
+        BattleUnit { weapons: vec![pd], ..make_target_dummy(id, x) }
+    }
+
+    #[test]
+    fn test_point_defense_shoots_down_missiles() {
+        // The freighter has no PD of its own - the escort's covers it
+        let units = vec![make_launcher(1, 0.0), make_target_dummy(10, 100.0), make_pd_dummy(11, 110.0, 1.0, 0.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let fired: Vec<WeaponFired> = run(&mut sim, 40).into_iter().flat_map(|r| r.weapons_fired).collect();
+        assert!(fired.len() > 10);
+        assert!(fired.iter().all(|f| f.intercepted && !f.hit));
+        assert!(sim.get_units()[1..].iter().all(|u| u.hp == u.max_hp));
+        assert_eq!(sim.get_unit(1).unwrap().shots_fired, fired.len() as u32);
+        assert!(serde_json::to_string(&fired[0]).unwrap().contains("\"intercepted\":true"));
+
+        // Escort pulled out of range - missiles get through
+        sim.update_single_position(11, 400.0, 0.0, 0.0, false);
+        let fired: Vec<WeaponFired> = (40..80).flat_map(|i| sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64).weapons_fired).collect();
+        assert!(fired.iter().all(|f| f.hit && !f.intercepted));
+        assert!(sim.get_unit(10).unwrap().hp < 100000.0);
+        assert!(!serde_json::to_string(&fired[0]).unwrap().contains("intercepted"));
+    }
+
+    #[test]
+    fn test_missile_volley_saturates_point_defense() {
+        // Share of missiles shot down with `launchers` firing at once
+        let intercept_rate = |launchers: u32| {
+            let mut units: Vec<BattleUnit> = (1..=launchers).map(|id| make_launcher(id, id as f32 * 8.0)).collect();
+            units.push(make_pd_dummy(20, 100.0, 0.8, 0.2));
+            let config = BattleConfig { mode: SimulationMode::Deterministic { seed: 5 }, ..Default::default() };
+            let mut sim = BattleSimulator::new_with_config(units, 1000.0, config);
+            let fired: Vec<WeaponFired> = run(&mut sim, 200).into_iter().flat_map(|r| r.weapons_fired).collect();
+            assert!(fired.len() as u32 >= 150 * launchers);
+            fired.iter().filter(|f| f.intercepted).count() as f32 / fired.len() as f32
+        };
+        let single = intercept_rate(1);
+        let volley = intercept_rate(5);
+        assert!((single - 0.8).abs() < 0.08, "single missile intercepted {}", single);
+        // 0.8 / (1 + 4 * 0.2) = 0.444
+        assert!((volley - 0.444).abs() < 0.05, "volley intercepted {}", volley);
+    }
+
+    #[test]
+    fn test_group_target_retargets_every_member() {
+        // Dummies can't shoot back; unit 5 is the nearest for everyone
+        let mut units: Vec<BattleUnit> = (1..=3).map(|id| BattleUnit { group_id: Some(7), ..make_ship(id, 1, id as f32 * 10.0, 1.0) }).collect();
+        units.push(make_ship(4, 1, 0.0, 1.0));
+        units.push(make_target_dummy(5, 60.0));
+        units.push(make_target_dummy(6, 95.0));
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        sim.simulate_tick(DT, 1000.0);
+        assert!(sim.get_units()[..4].iter().all(|u| u.target_id == Some(5)));
+        assert_eq!(sim.group_members(7), vec![1, 2, 3]);
+
+        assert_eq!(sim.set_group_target(7, 6), 3);
+        assert!(sim.get_units()[..3].iter().all(|u| u.target_id == Some(6)));
+        assert_eq!(sim.get_unit(4).unwrap().target_id, Some(5));
+        assert_eq!(sim.set_group_target(7, 2), 0);
+        assert_eq!(sim.set_group_target(99, 6), 0);
+
+        // Unit 1 stays on the focus target, unit 3 is moved out of its range
+        // and falls back to automatic targeting
+        sim.update_single_position(3, -10.0, 0.0, 0.0, false);
+        sim.simulate_tick(DT, 1000.05);
+        assert_eq!(sim.get_unit(1).unwrap().target_id, Some(6));
+        assert_eq!(sim.get_unit(3).unwrap().target_id, Some(5));
+        // ...and comes back to it once it closes
+        sim.update_single_position(3, 30.0, 0.0, 0.0, false);
+        sim.simulate_tick(DT, 1000.1);
+        assert_eq!(sim.get_unit(3).unwrap().target_id, Some(6));
+
+        // Stance carries over to units that join
+        assert_eq!(sim.set_group_stance(7, Stance::HoldFire), 3);
+        let joiner = GroupAssignment { unit_id: 4, group_id: Some(7) };
+        assert_eq!(sim.assign_groups(&[joiner, GroupAssignment { unit_id: 42, group_id: Some(7) }]), 1);
+        assert_eq!(sim.get_unit(4).unwrap().stance, Stance::HoldFire);
+        let results = run(&mut sim, 100);
+        assert!(results.iter().all(|r| r.weapons_fired.is_empty()));
+
+        assert_eq!(sim.clear_group(7), 4);
+        assert!(sim.group_members(7).is_empty());
+        assert!(sim.get_units()[..4].iter().all(|u| u.group_id.is_none()));
+    }
+
+    #[test]
+    fn test_dead_group_members_are_pruned() {
+        let mut weak = make_ship(2, 1, 50.0, 1.0);
+        weak.max_hp = 10.0;
+        weak.hp = 10.0;
+        let tough = |id, x| BattleUnit { max_hp: 100000.0, hp: 100000.0, ..make_ship(id, 1, x, 1.0) };
+        let units = vec![tough(1, 0.0), weak, tough(3, 10.0), make_ship(4, 2, 60.0, 100.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let assignments: Vec<GroupAssignment> = (1..=3).map(|id| GroupAssignment { unit_id: id, group_id: Some(1) }).collect();
+        assert_eq!(sim.assign_groups(&assignments), 3);
+        assert_eq!(sim.set_group_target(1, 4), 3);
+
+        for i in 0..200 {
+            sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            if !sim.get_unit(2).unwrap().is_alive() {
+                break;
+            }
+        }
+        assert!(!sim.get_unit(2).unwrap().is_alive());
+        assert!(!sim.group_members(1).contains(&2));
+
+        assert!(sim.remove_unit(3));
+        assert_eq!(sim.group_members(1), vec![1]);
+        assert!(sim.assign_group(1, None));
+        assert!(sim.group_members(1).is_empty());
+    }
+
+    #[test]
+    fn test_sticky_target_survives_retarget_intervals() {
+        // Unit 3 is 4% closer than the current target - within the 20% margin
+        let mut attacker = make_ship(1, 1, 0.0, 1.0);
+        attacker.target_id = Some(2);
+        let units = vec![attacker, make_target_dummy(2, 50.0), make_target_dummy(3, -48.0)];
+
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        for i in 0..(RETARGET_INTERVAL * 10) {
+            sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            assert_eq!(sim.get_units()[0].target_id, Some(2));
+        }
+    }
+
+    #[test]
+    fn test_sticky_target_switches_to_clearly_closer_enemy() {
+        let mut attacker = make_ship(1, 1, 0.0, 1.0);
+        attacker.target_id = Some(2);
+        let units = vec![attacker, make_target_dummy(2, 50.0), make_target_dummy(3, -30.0)];
+
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        for i in 0..RETARGET_INTERVAL {
+            sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+        }
+        assert_eq!(sim.get_units()[0].target_id, Some(3));
+    }
+
+    #[test]
+    fn test_stealth_frigate_closes_unseen() {
+        // Station sensors reach 300; the frigate is only seen at a quarter of
+        // that, about as close as it gets to the station's hull
+        let station = BattleUnit::builder().id(1).faction(1).hp(5000.0).view_range(300.0).as_station()
+            .weapon(Weapon::builder().tag("LASER").dps(10.0).range(250.0, 300.0).build())
+            .build()
+            .unwrap();
+        let frigate = |stealth: f32| BattleUnit {
+            ai_controlled: true,
+            ..BattleUnit::builder().id(2).faction(2).position(400.0, 0.0, 0.0).hp(5000.0).max_speed(50.0).view_range(500.0).as_ship()
+                .stealth(stealth)
+                .weapon(Weapon::builder().tag("LASER").dps(10.0).range(50.0, 70.0).build())
+                .build()
+                .unwrap()
+        };
+        // Distance the station first targets the frigate from
+        let acquired_at = |stealth: f32| {
+            let mut sim = BattleSimulator::new(vec![station.clone(), frigate(stealth)], 1000.0);
+            for i in 0..400 {
+                let distance = sim.get_units()[0].distance(&sim.get_units()[1]);
+                let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+                if i == 0 {
+                    assert_eq!(result.contacts_detected, vec![SensorContact { faction_id: 2, unit_id: 1 }]);
+                }
+                if sim.get_units()[0].target_id == Some(2) {
+                    assert!(result.contacts_detected.contains(&SensorContact { faction_id: 1, unit_id: 2 }));
+                    return distance;
+                }
+                assert!(result.weapons_fired.iter().all(|w| w.attacker_id != 1));
+            }
+            panic!("station never acquired the frigate");
+        };
+        assert!(acquired_at(0.25) <= 75.0);
+        assert!(acquired_at(1.0) > 290.0);
+    }
+
+    #[test]
+    fn test_contact_lost_out_of_sensor_range() {
+        let mut scout = make_hull(1, 1, 0.0);
+        scout.view_range = 200.0;
+        let mut sim = BattleSimulator::new(vec![scout, make_ship(2, 1, -500.0, 10.0), make_target_dummy(3, 150.0)], 1000.0);
+        let result = sim.simulate_tick(DT, 1000.0);
+        assert!(result.contacts_detected.contains(&SensorContact { faction_id: 1, unit_id: 3 }));
+        assert!(sim.get_units()[1].target_id.is_none(), "out of its own sensor range, but the scout shares what it sees");
+
+        // Spotted by the scout, so the ship far behind it can target the dummy
+        sim.units[1].pos_x = 50.0;
+        sim.simulate_tick(DT, 1000.0 + DT as f64);
+        assert_eq!(sim.get_units()[1].target_id, Some(3));
+
+        // Everyone pulls back out of sensor range - contact lost, target dropped
+        sim.units[0].pos_x = -1000.0;
+        sim.units[1].pos_x = -1000.0;
+        let result = sim.simulate_tick(DT, 1000.0 + 2.0 * DT as f64);
+        assert!(result.contacts_lost.contains(&SensorContact { faction_id: 1, unit_id: 3 }));
+        assert_eq!(sim.get_units()[1].target_id, None);
+    }
+
+    #[test]
+    fn test_target_at_view_range_is_kept_until_in_weapon_range() {
+        // Enemies between weapon range (100) and view range (300) - unit 3 is
+        // 5% closer than unit 2, within the retarget margin
+        let mut attacker = make_ship(1, 1, 0.0, 10.0);
+        attacker.view_range = 300.0;
+        let units = vec![attacker.clone(), make_target_dummy(2, 200.0), make_target_dummy(3, -190.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        sim.units[0].target_id = Some(2);
+        for i in 0..(RETARGET_INTERVAL * 3) {
+            let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            assert_eq!(sim.get_units()[0].target_id, Some(2), "target changed on tick {}", i);
+            assert!(result.weapons_fired.is_empty());
+            // Still valid - no fresh search outside the retarget interval
+            if !sim.tick().is_multiple_of(RETARGET_INTERVAL) {
+                assert_eq!(sim.choose_target(0, &mut Vec::new()), None);
+            }
+        }
+
+        // An AI-moved unit closes in on it and opens fire
+        attacker.ai_controlled = true;
+        let mut sim = BattleSimulator::new(vec![attacker, make_target_dummy(2, 200.0)], 1000.0);
+        let mut fired = false;
+        for i in 0..100 {
+            let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            assert_eq!(sim.get_units()[0].target_id, Some(2), "target dropped on tick {}", i);
+            fired |= result.weapons_fired.iter().any(|w| w.attacker_id == 1);
+        }
+        assert!(fired);
+        assert!(sim.get_units()[0].pos_x > 100.0);
+    }
+
+    #[test]
+    fn test_retreat_without_ai_movement_stays_put() {
+        let strong = make_ship(1, 1, 0.0, 20.0);
+        let mut weak = make_ship(2, 2, 50.0, 5.0);
+        weak.retreat_hp_fraction = 0.9;
+
+        let mut sim = BattleSimulator::new(vec![strong, weak], 1000.0);
+        run(&mut sim, 100);
+
+        let weak = &sim.get_units()[1];
+        assert!(weak.retreating);
+        assert_eq!(weak.pos_x, 50.0);
+    }
+
+    #[test]
+    fn test_retreating_unit_fires_defensively_and_recovers() {
+        let mut strong = make_ship(1, 1, 0.0, 20.0);
+        strong.weapons[0].last_fired = 999.0;
+        let mut weak = make_ship(2, 2, 50.0, 5.0);
+        weak.weapons[0].last_fired = 1000.5;
+        weak.retreat_hp_fraction = 0.9;
+
+        // Strong ship hits first, weak ship's weapon comes off cooldown after
+        let mut sim = BattleSimulator::new(vec![strong, weak], 1000.0);
+        let results = run(&mut sim, 40);
+
+        assert!(results.iter().any(|r| r.retreating_units.contains(&2)));
+        let fired_while_retreating = results.iter()
+            .filter(|r| r.retreating_units.contains(&2))
+            .any(|r| r.weapons_fired.iter().any(|w| w.attacker_id == 2));
+        assert!(fired_while_retreating);
+
+        // Lowering the threshold below current hull ends the retreat
+        assert!(sim.set_retreat_threshold(2, 0.1));
+        let result = sim.simulate_tick(DT, 1010.0);
+        assert!(!result.retreating_units.contains(&2));
+        assert!(!sim.get_units()[1].retreating);
+    }
+
+    #[test]
+    fn test_retreating_unit_returns_fire_at_the_armed_ship() {
+        let mut runner = make_ship(1, 1, 0.0, 5.0);
+        runner.hp = 10.0;
+        runner.max_hp = 100.0;
+        runner.retreat_hp_fraction = 0.5;
+        let freighter = make_hull(2, 2, 40.0);
+        let mut frigate = make_ship(3, 2, 45.0, 1.0);
+        frigate.weapons[0].last_fired = 1000.5;
+
+        let mut sim = BattleSimulator::new(vec![runner, freighter, frigate], 1000.0);
+        sim.simulate_tick(DT, 1000.0);
+        assert!(sim.get_unit(1).unwrap().retreating);
+
+        // Re-picked through the retreat path - the nearer freighter isn't a threat
+        assert!(sim.force_retarget_unit(1));
+        sim.simulate_tick(DT, 1000.05);
+        assert_eq!(sim.get_unit(1).unwrap().target_id, Some(3));
+    }
+
+    #[test]
+    fn test_move_order_arrives_without_overshooting() {
+        let mut unit = make_ship(1, 1, 0.0, 1.0);
+        unit.max_speed = 20.0;
+        let units = vec![unit, make_ship(2, 2, 5000.0, 1.0), make_ship(3, 2, 9000.0, 1.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        assert!(sim.issue_move_order(1, 100.0, 0.0, 0.0));
+        assert!(!sim.issue_move_order(1, f32::NAN, 0.0, 0.0));
+        assert!(!sim.issue_move_order(99, 0.0, 0.0, 0.0));
+
+        // 100 units at 20/s - 5 seconds of 0.05s ticks
+        let mut arrived_at = None;
+        for i in 0..200 {
+            let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            let unit = sim.get_unit(1).unwrap();
+            assert!(unit.pos_x <= 100.0 + MOVE_ORDER_EPSILON, "overshot to {}", unit.pos_x);
+            if arrived_at.is_none() {
+                assert!(result.moved.iter().any(|m| m.id == 1));
+                if unit.move_order.is_none() {
+                    arrived_at = Some(i + 1);
+                }
+            }
+        }
+        let ticks = arrived_at.expect("never arrived");
+        assert!((99..=101).contains(&ticks), "took {} ticks", ticks);
+        assert!((sim.get_unit(1).unwrap().pos_x - 100.0).abs() <= MOVE_ORDER_EPSILON);
+
+        // Batch orders; a position update or clear_orders cancels them
+        let orders = [
+            MoveOrder { id: 2, x: 0.0, y: 0.0, z: 0.0 },
+            MoveOrder { id: 3, x: 0.0, y: 0.0, z: 0.0 },
+            MoveOrder { id: 99, x: 0.0, y: 0.0, z: 0.0 },
+        ];
+        assert_eq!(sim.issue_move_orders(&orders), 2);
+        sim.update_single_position(2, 4000.0, 0.0, 0.0, false);
+        assert!(sim.clear_orders(3));
+        sim.simulate_tick(DT, 1011.0);
+        assert_eq!(sim.get_unit(2).unwrap().pos_x, 4000.0);
+        assert_eq!(sim.get_unit(3).unwrap().pos_x, 9000.0);
+
+        // Destinations outside the bounds are pulled in
+        sim.set_config(BattleConfig {
+            bounds: Some(BattleBounds::Box { min: (-200.0, -200.0, -200.0), max: (200.0, 200.0, 200.0) }),
+            ..Default::default()
+        }).unwrap();
+        assert!(sim.issue_move_order(1, 500.0, 0.0, 0.0));
+        assert_eq!(sim.get_unit(1).unwrap().move_order, Some((200.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_octree_index_runs_battle_to_completion() {
+        use crate::octree::Octree;
+        use crate::spatial_index::AnySpatialIndex;
+
+        let units = vec![make_ship(1, 1, 0.0, 200.0), make_ship(2, 2, 90.0, 1.0)];
+        let mut sim = BattleSimulator::with_index(units.clone(), 1000.0, Octree::new());
+        run(&mut sim, 400);
+        assert_eq!(sim.get_winner(), Some(1));
+
+        // Switching index mid-battle keeps targeting working
+        let mut sim = BattleSimulator::with_index(units, 1000.0, AnySpatialIndex::default());
+        run(&mut sim, 5);
+        sim.set_spatial_index(AnySpatialIndex::from_name("octree").unwrap());
+        run(&mut sim, 400);
+        assert_eq!(sim.get_winner(), Some(1));
+    }
+
+    #[test]
+    fn test_target_lock_delays_first_shot() {
+        let mut attacker = make_ship(1, 1, 0.0, 1.0);
+        attacker.weapons[0].last_fired = 900.0;
+
+        let first_shot = |lock_time: f32| {
+            let mut attacker = attacker.clone();
+            attacker.lock_time = lock_time;
+            // 20 ticks/sec - target acquired on the first tick at t=1000
+            let mut sim = BattleSimulator::new(vec![attacker, make_target_dummy(2, 50.0)], 1000.0);
+            run(&mut sim, 60).iter()
+                .position(|r| r.weapons_fired.iter().any(|w| w.attacker_id == 1))
+                .expect("attacker never fired")
+        };
+
+        assert!(first_shot(0.0) < 5);
+        let locked = first_shot(2.0);
+        assert!(locked >= 40, "fired at tick {} before lock completed", locked);
+    }
+
+    #[test]
+    fn test_point_defense_only_escort_never_acquires_ships() {
+        let mut escort = make_ship(1, 1, 0.0, 50.0);
+        escort.weapons[0].tag = "AM1".to_string();
+        escort.view_range = 500.0;
+        let mut enemy = make_target_dummy(2, 50.0);
+        enemy.view_range = 500.0;
+
+        let mut sim = BattleSimulator::new(vec![escort, enemy], 1000.0);
+        let results = run(&mut sim, 100);
+
+        assert_eq!(sim.get_units()[0].target_id, None);
+        assert!(results.iter().all(|r| r.weapons_fired.iter().all(|w| w.attacker_id != 1)));
+    }
+
+    #[test]
+    fn test_registered_prefix_recategorizes_weapons() {
+        let mut escort = make_ship(1, 1, 0.0, 50.0);
+        escort.weapons[0].tag = "FLAK-2".to_string();
+        let mut sim = BattleSimulator::new(vec![escort, make_target_dummy(2, 50.0)], 1000.0);
+        assert_eq!(sim.get_units()[0].weapons[0].category, WeaponCategory::Kinetic);
+
+        // Flak is point defense from now on - it stops shooting at ships
+        sim.register_weapon_tag_prefix("FLAK", WeaponCategory::PointDefense);
+        assert_eq!(sim.get_units()[0].weapons[0].category, WeaponCategory::PointDefense);
+        assert_eq!(sim.weapon_tags().categorize("flak-9"), WeaponCategory::PointDefense);
+        let results = run(&mut sim, 60);
+        assert!(results.iter().all(|r| r.weapons_fired.iter().all(|w| w.attacker_id != 1)));
+
+        // ...and so are the weapons of units that join later
+        let mut late = make_ship(3, 1, 10.0, 50.0);
+        late.weapons[0].tag = "FLAK-3".to_string();
+        sim.add_unit(late, 1010.0).unwrap();
+        assert_eq!(sim.get_unit(3).unwrap().weapons[0].category, WeaponCategory::PointDefense);
+    }
+
+    #[test]
+    fn test_burn_ticks_for_duration_and_credits_applier() {
+        use crate::status_effect::EffectSpec;
+
+        let mut attacker = make_ship(1, 1, 0.0, 10.0);
+        attacker.weapons[0].last_fired = 900.0;
+        attacker.weapons[0].ammo = Some(1);
+        attacker.weapons[0].applies_effect = Some(EffectSpec {
+            kind: StatusEffectKind::Burn,
+            magnitude: 2.0,
+            duration: 1.0,
+        });
+
+        let mut sim = BattleSimulator::new(vec![attacker, make_target_dummy(2, 50.0)], 1000.0);
+        let results = run(&mut sim, 60);
+
+        // One hit, then 1s of burn at 20 ticks/sec (the hit tick burns too)
+        let damaged_ticks = results.iter().filter(|r| r.damaged.iter().any(|d| d.id == 2)).count();
+        assert_eq!(damaged_ticks, 20);
+
+        let applied = results.iter().position(|r| r.effects.iter().any(|e| e.id == 2 && e.effects.len() == 1));
+        let cleared = results.iter().position(|r| r.effects.iter().any(|e| e.id == 2 && e.effects.is_empty()));
+        assert_eq!(cleared.unwrap() - applied.unwrap(), 19);
+
+        let units = sim.get_units();
+        assert!(units[1].effects.is_empty());
+        assert!((units[0].damage_dealt - (units[1].max_hp - units[1].hp)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_out_of_bounds_position_is_clamped_and_still_targetable() {
+        let mut sim = BattleSimulator::new(
+            vec![make_ship(1, 1, 9950.0, 10.0), make_target_dummy(2, 0.0)],
+            1000.0,
+        );
+        sim.set_config(BattleConfig {
+            bounds: Some(BattleBounds::Box { min: (-10000.0, -10000.0, -10000.0), max: (10000.0, 10000.0, 10000.0) }),
+            ..Default::default()
+        }).unwrap();
+
+        let updates = [PositionUpdate { id: 2, x: 1.0e9, y: 0.0, z: 0.0, clear_target: false }];
+        assert_eq!(sim.update_positions(&updates), 1);
+        assert_eq!(sim.update_single_position(2, 1.0e9, 0.0, 0.0, false), Some(true));
+        assert_eq!(sim.update_single_position(2, 10000.0, 0.0, 0.0, false), Some(false));
+        assert_eq!(sim.update_single_position(99, 0.0, 0.0, 0.0, false), None);
+        assert_eq!(sim.get_units()[1].pos_x, 10000.0);
+        assert!(sim.get_out_of_bounds_units().is_empty());
+
+        // The clamped unit sits in a normal grid cell and gets targeted
+        sim.simulate_tick(DT, 1000.0);
+        assert_eq!(sim.get_units()[0].target_id, Some(2));
+    }
+
+    #[test]
+    fn test_sphere_bounds_clamp() {
+        let bounds = BattleBounds::Sphere { center: (0.0, 0.0, 0.0), radius: 100.0 };
+        assert_eq!(bounds.clamp(1.0e30, 0.0, 0.0), (100.0, 0.0, 0.0));
+        assert_eq!(bounds.clamp(f32::NAN, 0.0, 0.0), (0.0, 0.0, 0.0));
+        assert!(bounds.contains(0.0, 99.0, 0.0));
+        assert!(!bounds.contains(0.0, 101.0, 0.0));
+    }
+
+    #[test]
+    fn test_station_turret_engages_second_attacker() {
+        let mut station = make_ship(1, 1, 0.0, 10.0);
+        station.is_ship = false;
+        station.is_station = true;
+        station.max_speed = 0.0;
+        station.view_range = 500.0;
+        station.weapons[0].max_range = 300.0;
+        station.max_weapon_range = 300.0;
+        station.weapons.push(Weapon {
+            independent_targeting: true,
+            ..Weapon::builder().tag("TURRET").dps(5.0).range(80.0, 100.0).build()
+        });
+        for weapon in &mut station.weapons {
+            weapon.last_fired = 900.0;
+        }
+        station.target_id = Some(3);
+
+        let near = make_ship(2, 2, 50.0, 1.0);
+        let far = make_ship(3, 2, 250.0, 1.0);
+
+        let mut sim = BattleSimulator::new(vec![station, near, far], 1000.0);
+        let result = sim.simulate_tick(DT, 1000.0);
+
+        let mut targets: Vec<u32> = result.weapons_fired.iter()
+            .filter(|w| w.attacker_id == 1)
+            .map(|w| w.target_id)
+            .collect();
+        targets.sort_unstable();
+        assert_eq!(targets, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_recycled_buffers_give_same_results() {
+        let mut units = vec![
+            make_ship(1, 1, 0.0, 10.0),
+            make_ship(2, 2, 50.0, 10.0),
+            make_ship(3, 2, 60.0, 10.0),
+        ];
+        // Fixed cooldown phase - normalize() randomizes last_fired == 0
+        for unit in &mut units {
+            unit.weapons[0].last_fired = 900.0;
+        }
+        let mut plain = BattleSimulator::new(units.clone(), 1000.0);
+        let mut recycled = BattleSimulator::new(units, 1000.0);
+
+        for i in 0..200 {
+            let t = 1000.0 + i as f64 * DT as f64;
+            let a = untimed_json(&plain.simulate_tick(DT, t));
+            let result = recycled.simulate_tick(DT, t);
+            assert_eq!(a, untimed_json(&result));
+            recycled.recycle_result(result);
+        }
+    }
+
+    /// Unarmed frigate at x=50, logistics ship behind it at x=120 (out of the
+    /// attackers' reach), attackers at x=0 each hitting for 10 hull/sec
+    fn logistics_battle(attackers: u32) -> BattleSimulator {
+        let frigate = make_hull(10, 1, 50.0);
+        let mut logi = make_ship(11, 1, 120.0, 0.0);
+        logi.weapons = vec![Weapon {
+            is_repair: true,
+            ..Weapon::builder().tag("REPAIR").dps(15.0).range(50.0, 100.0).last_fired(900.0).build()
+        }];
+
+        let mut units = vec![frigate, logi];
+        for i in 0..attackers {
+            let mut attacker = make_ship(20 + i, 2, 0.0, 10.0);
+            attacker.pos_y = i as f32 * 5.0;
+            attacker.weapons[0].last_fired = 900.0;
+            units.push(attacker);
+        }
+        BattleSimulator::new(units, 1000.0)
+    }
+
+    #[test]
+    fn test_logistics_keeps_frigate_alive_against_one_attacker() {
+        let mut sim = logistics_battle(1);
+        let results = run(&mut sim, 600);
+
+        let units = sim.get_units();
+        assert!(units[0].is_alive());
+        assert!(units[0].damage_taken > 100.0);
+        assert!(units[1].healing_done > 100.0);
+        assert!(units[1].damage_dealt == 0.0 && units[1].target_id.is_none());
+        assert!(results.iter().any(|r| r.repaired.iter().any(|u| u.id == 10)));
+    }
+
+    #[test]
+    fn test_logistics_loses_race_against_two_attackers() {
+        let mut sim = logistics_battle(2);
+        let results = run(&mut sim, 1200);
+
+        let died = results.iter().position(|r| r.destroyed.contains(&10));
+        assert!(died.is_some());
+        // Never repaired back to life
+        let units = sim.get_units();
+        assert!(!units[0].is_alive() && units[0].hp == 0.0);
+        assert!(results[died.unwrap()..].iter().all(|r| r.repaired.iter().all(|u| u.id != 10)));
+    }
+
+    /// Every unit on the battlefield is indexed at its position, nothing else is indexed
+    fn assert_grid_consistent<I: SpatialIndex>(sim: &BattleSimulator<I>) {
+        let mut indexed: Vec<usize> = sim.grid.query_range(0.0, 0.0, 0.0, 1.0e7)
+            .into_iter()
+            .map(|(idx, _)| idx)
+            .collect();
+        indexed.sort_unstable();
+        let expected: Vec<usize> = (0..sim.units.len()).filter(|&i| sim.units[i].on_battlefield()).collect();
+        assert_eq!(indexed, expected);
+
+        for &idx in &expected {
+            let u = &sim.units[idx];
+            assert!(sim.grid.query_range(u.pos_x, u.pos_y, u.pos_z, 0.5).iter().any(|&(i, _)| i == idx));
+        }
+    }
+
+    fn line_of_ships(first_id: u32, count: u32, faction: u32, x: f32) -> Vec<BattleUnit> {
+        (0..count)
+            .map(|i| {
+                let mut ship = make_ship(first_id + i, faction, x, 1.0);
+                ship.pos_y = (i % 25) as f32 * 8.0;
+                ship.pos_z = (i / 25) as f32 * 8.0;
+                ship
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_add_units_batch_indexes_every_unit() {
+        let mut sim = BattleSimulator::new(line_of_ships(1, 10, 1, 0.0), 1000.0);
+        sim.simulate_tick(DT, 1000.0);
+
+        assert_eq!(sim.add_units(line_of_ships(1000, 500, 2, 60.0), 1000.0).unwrap(), 500);
+        assert_eq!(sim.units.len(), 510);
+        assert_grid_consistent(&sim);
+
+        assert_eq!(sim.add_units(line_of_ships(2000, 500, 1, -60.0), 1000.0).unwrap(), 500);
+        assert_grid_consistent(&sim);
+
+        sim.simulate_tick(DT, 1000.0 + DT as f64);
+        assert_grid_consistent(&sim);
+        assert!(sim.units[10..510].iter().any(|u| u.target_id.is_some()));
+    }
+
+    #[test]
+    fn test_remove_unit_mid_battle_is_not_a_death() {
+        let mut sim = BattleSimulator::new(line_of_ships(1, 500, 1, 0.0), 1000.0);
+        sim.add_units(line_of_ships(1000, 500, 2, 60.0), 1000.0).unwrap();
+        run(&mut sim, 40);
+
+        // Remove every enemy someone is currently shooting at
+        let mut targeted: Vec<u32> = sim.units.iter().filter_map(|u| u.target_id).collect();
+        targeted.sort_unstable();
+        targeted.dedup();
+        for &id in &targeted {
+            assert!(sim.remove_unit(id));
+        }
+        assert!(!sim.remove_unit(targeted[0]));
+        assert!(!sim.remove_unit(99999));
+
+        assert!(sim.units.iter().all(|u| u.target_id.is_none_or(|t| !targeted.contains(&t))));
+        assert_grid_consistent(&sim);
+
+        for result in run(&mut sim, 40) {
+            assert!(result.destroyed.iter().all(|id| !targeted.contains(id)));
+            assert!(result.weapons_fired.iter().all(|w| !targeted.contains(&w.target_id)));
+            assert!(result.damaged.iter().all(|d| !targeted.contains(&d.id)));
+        }
+        assert_grid_consistent(&sim);
+    }
+
+    #[test]
+    fn test_surrender_faction_in_three_way_battle() {
+        // Three factions in a triangle, everyone in range of everyone
+        let mut units = line_of_ships(1, 5, 1, 0.0);
+        units.extend(line_of_ships(100, 5, 2, 60.0));
+        units.extend(line_of_ships(200, 5, 3, 30.0).into_iter().map(|mut u| { u.pos_z += 50.0; u }));
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        run(&mut sim, 5);
+        assert!(sim.units.iter().any(|u| u.target_id.is_some_and(|t| (200..205).contains(&t))));
+
+        assert!(sim.surrender_faction(3));
+        assert!(sim.units.iter().filter(|u| u.faction_id == 3).all(|u| u.state == UnitState::Withdrawn));
+        assert!(sim.units.iter().all(|u| u.target_id.is_none_or(|t| !(200..205).contains(&t))));
+        assert!(matches!(
+            sim.combat_log().since(sim.tick(), 10).last().map(|e| &e.entry),
+            Some(CombatLogEntry::FactionSurrendered(3))
+        ));
+        assert_grid_consistent(&sim);
+        assert!(!sim.is_battle_ended());
+        assert_eq!(sim.get_winner(), None);
+
+        // Already gone, or never there
+        assert!(!sim.surrender_faction(3));
+        assert!(!sim.surrender_faction(9));
+
+        // The other two keep fighting; faction 3 leaves, it isn't destroyed
+        let results = run(&mut sim, 20);
+        let withdrawn: Vec<u32> = results[0].withdrawn.clone();
+        assert_eq!(withdrawn, (200..205).collect::<Vec<u32>>());
+        assert!(results.iter().all(|r| r.destroyed.iter().all(|id| !(200..205).contains(id))));
+        assert!(results.iter().any(|r| r.weapons_fired.iter().any(|w| (100..105).contains(&w.target_id))));
+        assert!(results.iter().all(|r| r.weapons_fired.iter().all(|w| !(200..205).contains(&w.target_id))));
+        assert_eq!(sim.get_active_factions(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_surrender_of_last_opponent_beats_stalemate() {
+        // Too far apart to fight - the stalemate timer runs
+        let mut units = line_of_ships(1, 5, 1, 0.0);
+        units.extend(line_of_ships(100, 8, 2, 5000.0));
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        sim.set_stalemate_threshold(100);
+        run(&mut sim, 90);
+        assert!(!sim.is_battle_ended());
+
+        // Reinforcements for the loser don't hold the battle open
+        sim.schedule_wave(ReinforcementWave {
+            arrival_tick: sim.tick() + 500,
+            units: line_of_ships(300, 3, 1, 0.0),
+            faction_id: None,
+            spawn: None,
+        }).unwrap();
+        assert!(sim.surrender_faction(1));
+        assert!(sim.is_battle_ended());
+        assert_eq!(sim.end_reason(), Some(CompletionReason::Elimination));
+        assert_eq!(sim.get_winner(), Some(2));
+        assert_eq!(sim.pending_reinforcements(), 0);
+    }
+
+    #[test]
+    fn test_faction_damage_bonus_decides_equal_fight() {
+        // Mirror-image fleets, seeded - only the faction config differs
+        let battle = |favoured: Option<u32>| {
+            let mut units: Vec<BattleUnit> = (0..5).map(|i| make_ship(1 + i, 1, 0.0, 10.0)).collect();
+            units.extend((0..5).map(|i| make_ship(100 + i, 2, 60.0, 10.0)));
+            // 105 hp: 11 shots of 10 damage to kill, 10 with the bonus
+            for (i, unit) in units.iter_mut().enumerate() {
+                unit.pos_z = (i % 5) as f32 * 8.0;
+                (unit.hp, unit.max_hp) = (105.0, 105.0);
+            }
+            let config = BattleConfig { mode: SimulationMode::Deterministic { seed: 4 }, ..Default::default() };
+            let mut sim = BattleSimulator::new_with_config(units, 1000.0, config);
+            if let Some(faction_id) = favoured {
+                sim.set_faction_config(FactionConfig { damage_multiplier: 1.1, ..FactionConfig::new(faction_id) });
+            }
+            let report = sim.run_to_completion(DT, 20000);
+            assert_eq!(report.reason, CompletionReason::Elimination);
+            (report.winner, report.survivors.len())
+        };
+
+        let (even_winner, even_survivors) = battle(None);
+        assert!(even_winner.is_some());
+        // A 10% edge wins the fight for whichever side has it, with more left standing
+        for favoured in [1, 2] {
+            let (winner, survivors) = battle(Some(favoured));
+            assert_eq!(winner, Some(favoured));
+            if Some(favoured) == even_winner {
+                assert!(survivors >= even_survivors);
+            }
+        }
+    }
+
+    #[test]
+    fn test_special_weapons_and_faction_multipliers() {
+        let mut plasma = make_ship(1, 1, 0.0, 10.0);
+        plasma.weapons[0].tag = "PLASMA".to_string();
+        let mut captured = make_ship(2, 2, 60.0, 10.0);
+        captured.weapons[0].tag = "PLASMA".to_string();
+        let mut sim = BattleSimulator::new(vec![plasma, captured], 1000.0);
+        sim.set_faction_config(FactionConfig { special_weapon_tags: vec!["plasma".to_string()], ..FactionConfig::new(1) });
+
+        // Faction 2 can't fire faction 1's special weapon, manually or otherwise
+        assert!(sim.manually_fire(2, 1, "PLASMA", 1000.0).is_none());
+        let results = run(&mut sim, 40);
+        assert!(results.iter().any(|r| r.weapons_fired.iter().any(|w| w.attacker_id == 1)));
+        assert!(results.iter().all(|r| r.weapons_fired.iter().all(|w| w.attacker_id != 2)));
+
+        // Shield regen and speed scale with the faction's multipliers
+        let mut shielded = make_hull(1, 1, 0.0);
+        shielded.max_shield = 100.0;
+        shielded.shield_regen = 10.0;
+        let mut sim = BattleSimulator::new(vec![shielded, make_hull(2, 2, 5000.0)], 1000.0);
+        sim.set_faction_config(FactionConfig { shield_regen_multiplier: 2.0, ..FactionConfig::new(1) });
+        sim.simulate_tick(1.0, 1001.0);
+        assert_eq!(sim.get_unit(1).unwrap().shield, 20.0);
+
+        let mut sim = BattleSimulator::new(vec![make_hull(1, 1, 0.0), make_hull(2, 2, 5000.0)], 1000.0);
+        sim.set_faction_config(FactionConfig { speed_multiplier: 1.5, ..FactionConfig::new(1) });
+        sim.issue_move_order(1, 1000.0, 0.0, 0.0);
+        sim.simulate_tick(1.0, 1001.0);
+        let unit = sim.get_unit(1).unwrap();
+        assert_eq!(unit.pos_x, 75.0);
+        assert_eq!(unit.max_speed, 50.0);
+    }
+
+    #[test]
+    fn test_power_allocation() {
+        // All power to shields and engines: 1.5x regen and speed
+        let mut shielded = make_hull(1, 1, 0.0);
+        shielded.max_shield = 100.0;
+        shielded.shield_regen = 10.0;
+        let mut sim = BattleSimulator::new(vec![shielded, make_hull(2, 2, 5000.0)], 1000.0);
+        assert_eq!(sim.set_unit_power(1, 0.5, 0.0, 0.5), Ok(true));
+        sim.issue_move_order(1, 1000.0, 0.0, 0.0);
+        sim.simulate_tick(1.0, 1001.0);
+        let unit = sim.get_unit(1).unwrap();
+        assert_eq!((unit.shield, unit.pos_x), (15.0, 75.0));
+
+        // Splits that don't add up are refused and change nothing
+        assert_eq!(sim.set_unit_power(1, 0.5, 0.5, 0.5), Err(PowerError::BadSum(1.5)));
+        assert_eq!(sim.set_unit_power(1, 1.2, -0.2, 0.0), Err(PowerError::OutOfRange(1.2)));
+        assert_eq!(sim.get_unit(1).unwrap().power_weapons, 0.0);
+        assert_eq!(sim.set_unit_power(1, 0.333, 0.333, 0.333), Ok(true));
+        assert_eq!(sim.set_unit_power(99, 0.2, 0.4, 0.4), Ok(false));
+
+        // Two thirds to weapons doubles the damage of a stock ship's shots -
+        // same seed both times, so the cooldown jitter (and with it how many
+        // shots land in 40 ticks) is the same
+        let damage_taken = |power: Option<(f32, f32, f32)>| {
+            let config = BattleConfig { mode: SimulationMode::Deterministic { seed: 9 }, ..Default::default() };
+            let mut sim = BattleSimulator::new_with_config(vec![make_ship(1, 1, 0.0, 10.0), make_target_dummy(2, 50.0)], 1000.0, config);
+            if let Some((shield, weapons, engines)) = power {
+                sim.set_unit_power(1, shield, weapons, engines).unwrap();
+            }
+            run(&mut sim, 40);
+            sim.get_unit(2).unwrap().damage_taken
+        };
+        let stock = damage_taken(None);
+        assert!(stock > 0.0);
+        assert_eq!(damage_taken(Some((1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0))), stock * 2.0);
+    }
+
+    #[test]
+    fn test_ai_units_balance_power() {
+        let mut captain = make_ship(1, 1, 0.0, 10.0);
+        captain.ai_controlled = true;
+        captain.max_shield = 50.0;
+        let mut sim = BattleSimulator::new(vec![captain, make_target_dummy(2, 50.0)], 1000.0);
+
+        // Host splits don't stick on AI units - with a target in range they favour weapons
+        sim.set_unit_power(1, 0.1, 0.1, 0.8).unwrap();
+        sim.simulate_tick(DT, 1000.0);
+        let unit = sim.get_unit(1).unwrap();
+        assert_eq!((unit.power_shield, unit.power_weapons, unit.power_engines), (0.25, 0.5, 0.25));
+
+        // Low hull feeds the shields
+        sim.units[0].hp = sim.units[0].max_hp * 0.2;
+        sim.simulate_tick(DT, 1000.0 + DT as f64);
+        assert_eq!(sim.get_unit(1).unwrap().power_shield, 0.5);
+    }
+
+    #[test]
+    fn test_paused_battle_does_not_advance() {
+        let mut sim = BattleSimulator::new(
+            vec![make_ship(1, 1, 0.0, 10.0), make_target_dummy(2, 50.0)],
+            1000.0,
+        );
+        run(&mut sim, 10);
+        let hp = sim.get_units()[1].hp;
+
+        sim.pause();
+        assert!(sim.is_paused());
+        assert_eq!(sim.paused_at_tick(), 10);
+        for i in 0..100 {
+            let result = sim.simulate_tick(DT, 1001.0 + i as f64 * DT as f64);
+            assert_eq!(result.tick, 10);
+            assert!(result.weapons_fired.is_empty() && result.damaged.is_empty());
+        }
+        assert_eq!(sim.tick, 10);
+        assert_eq!(sim.get_units()[1].hp, hp);
+
+        // Moves still land in the grid while paused
+        let updates = [PositionUpdate { id: 2, x: 500.0, y: 0.0, z: 0.0, clear_target: false }];
+        assert_eq!(sim.update_positions(&updates), 1);
+        assert_eq!(sim.grid.query_range(500.0, 0.0, 0.0, 1.0), vec![(1, 0.0)]);
+
+        sim.resume();
+        assert_eq!(sim.simulate_tick(DT, 1006.0).tick, 11);
+    }
+
+    /// Stationary enemy station with an AI ship closing in from 400 units out
+    fn approach_battle() -> BattleSimulator {
+        let mut station = make_ship(1, 1, 0.0, 1.0);
+        station.is_ship = false;
+        station.is_station = true;
+        station.max_hp = 1.0e6;
+        station.hp = 1.0e6;
+        let mut ship = make_ship(2, 2, 400.0, 1.0);
+        ship.ai_controlled = true;
+        ship.view_range = 1000.0;
+        BattleSimulator::new(vec![station, ship], 1000.0)
+    }
+
+    #[test]
+    fn test_moved_lists_only_units_that_moved_with_velocity() {
+        let mut sim = approach_battle();
+        let results = run(&mut sim, 60);
+
+        // Ship needs ~6s to close from 400 to optimal range 80 at 50/s
+        for result in &results[1..] {
+            assert!(result.moved.iter().all(|m| m.id != 1));
+            assert!(!result.is_keyframe);
+            let ship = result.moved.iter().find(|m| m.id == 2).expect("ship moves every tick");
+            assert!((ship.vx + 50.0).abs() < 0.5, "vx={}", ship.vx);
+        }
+    }
+
+    #[test]
+    fn test_keyframe_tick_includes_every_unit() {
+        let mut sim = approach_battle();
+        sim.add_unit(make_target_dummy(3, 2000.0), 1000.0).unwrap();
+        sim.set_config(BattleConfig {
+            emit_all_positions_every_n_ticks: 10,
+            ..Default::default()
+        }).unwrap();
+        let results = run(&mut sim, 20);
+
+        let keyframe = &results[9];
+        assert_eq!(keyframe.tick, 10);
+        assert!(keyframe.is_keyframe);
+        let mut ids: Vec<u32> = keyframe.moved.iter().map(|m| m.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert!(keyframe.moved.iter().find(|m| m.id == 1).is_some_and(|m| m.vx == 0.0));
+        assert!(!results[10].is_keyframe);
+        assert!(results[10].moved.iter().all(|m| m.id == 2));
+    }
+
+    #[test]
+    fn test_delta_result_skips_unchanged_units() {
+        let mut sim = approach_battle();
+        sim.set_config(BattleConfig {
+            position_epsilon: 10.0,
+            emit_all_positions_every_n_ticks: 20,
+            ..Default::default()
+        }).unwrap();
+        // Shield refills every tick, so each hit leaves the same reported state
+        sim.units[0].max_shield = 100.0;
+        sim.units[0].shield = 100.0;
+        sim.units[0].shield_regen = 1000.0;
+
+        let mut full_moves = 0;
+        let mut delta_moves = 0;
+        let mut last_sent: Option<f32> = None;
+        let mut station_hits = Vec::new();
+        for i in 0..200 {
+            let DeltaTickResult(result) = sim.simulate_tick_delta(DT, 1000.0 + i as f64 * DT as f64);
+            let ship = &sim.get_units()[1];
+            full_moves += usize::from(ship.vel_x != 0.0);
+            if let Some(m) = result.moved.iter().find(|m| m.id == 2) {
+                // Far enough from the last report, or a keyframe
+                assert!(result.is_keyframe || last_sent.is_none_or(|x| (x - m.x).abs() >= 10.0));
+                last_sent = Some(m.x);
+                delta_moves += 1;
+            }
+            if result.is_keyframe {
+                assert_eq!(result.moved.len(), 2);
+            }
+            station_hits.extend(result.damaged.iter().filter(|d| d.id == 1).map(|d| (d.hp, d.shield)));
+        }
+        assert!(delta_moves > 0 && delta_moves < full_moves / 2, "{} of {}", delta_moves, full_moves);
+        // The first shot lands the tick the ship closes into range; after that
+        // it puts half its power into weapons (1.5 damage)
+        assert_eq!(station_hits, vec![(1.0e6, 99.0), (1.0e6, 98.5)]);
+        assert!(sim.get_units()[1].shots_fired > 1);
+    }
+
+    #[test]
+    fn test_unit_lookups_include_dead_units() {
+        let mut sim = BattleSimulator::new(
+            vec![make_ship(1, 1, 0.0, 10.0), make_ship(2, 2, 50.0, 10.0), make_ship(3, 2, 60.0, 10.0)],
+            1000.0,
+        );
+        assert!(sim.remove_unit(3));
+
+        assert_eq!(sim.get_unit(3).map(|u| u.state), Some(UnitState::Withdrawn));
+        assert!(sim.get_unit(4).is_none());
+        let ids: Vec<u32> = sim.get_units_by_faction(2).map(|u| u.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(sim.get_units_by_faction(9).count(), 0);
+    }
+
+    #[test]
+    fn test_withdrawn_units_are_not_losses() {
+        // JSON from before state existed - derived from alive / withdrawn
+        let legacy = |id: u32, alive: bool, withdrawn: bool| {
+            let mut json = serde_json::to_value(make_ship(id, 2, 50.0, 10.0)).unwrap();
+            let fields = json.as_object_mut().unwrap();
+            fields.remove("state");
+            fields.insert("alive".into(), alive.into());
+            fields.insert("withdrawn".into(), withdrawn.into());
+            serde_json::from_value::<BattleUnit>(json).unwrap()
+        };
+        let units = vec![make_ship(1, 1, 0.0, 10.0), make_ship(2, 2, 60.0, 10.0), legacy(3, false, false), legacy(4, true, true)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let states: Vec<UnitState> = sim.get_units().iter().map(|u| u.state).collect();
+        assert_eq!(states, vec![UnitState::Active, UnitState::Active, UnitState::Destroyed, UnitState::Withdrawn]);
+        let json = serde_json::to_value(sim.get_unit(4).unwrap()).unwrap();
+        assert_eq!((&json["state"], &json["alive"], &json["withdrawn"]), (&"withdrawn".into(), &true.into(), &true.into()));
+        // Worked out from state however it was set - not stored
+        let mut unit = make_ship(5, 1, 0.0, 10.0);
+        unit.state = UnitState::Destroyed;
+        let json = serde_json::to_value(&unit).unwrap();
+        assert_eq!((&json["alive"], &json["withdrawn"]), (&false.into(), &false.into()));
+        assert_eq!(serde_json::from_value::<BattleUnit>(json).unwrap().state, UnitState::Destroyed);
+
+        assert!(sim.remove_unit(2));
+        assert!(!sim.remove_unit(2));
+        let result = sim.simulate_tick(DT, 1000.0);
+        assert_eq!(result.withdrawn, vec![2]);
+        assert!(result.destroyed.is_empty());
+        let results = run(&mut sim, 40);
+        assert!(results.iter().all(|r| r.weapons_fired.is_empty() && r.withdrawn.is_empty()));
+
+        assert_eq!(sim.get_active_factions(), vec![1]);
+        let losses = &sim.faction_summary()[1];
+        assert_eq!((losses.active, losses.destroyed, losses.withdrawn), (0, 1, 2));
+    }
+
+    #[test]
+    fn test_unit_builder_checks_and_derives() {
+        use crate::battle_unit::BuildError;
+
+        assert_eq!(BattleUnit::builder().faction(1).build().unwrap_err(), BuildError::MissingId);
+        assert_eq!(BattleUnit::builder().id(1).build().unwrap_err(), BuildError::MissingFaction);
+        assert_eq!(BattleUnit::builder().id(1).faction(1).hp(0.0).build().unwrap_err(), BuildError::InvalidMaxHp(0.0));
+
+        let ship = make_ship(1, 1, 0.0, 1.0);
+        assert!(ship.has_weapons && ship.is_ship);
+        assert_eq!(ship.max_weapon_range, 100.0);
+        let ranged = BattleUnit::builder().id(1).faction(1).weapons(ship.weapons).max_weapon_range(250.0).build().unwrap();
+        assert_eq!(ranged.max_weapon_range, 250.0);
+
+        // weapon() adds to the list - the range follows the longest
+        let station = crate::fixtures::station(2, 1).weapon(Weapon::laser(5.0)).build().unwrap();
+        assert_eq!((station.weapons.len(), station.max_weapon_range), (3, 200.0));
+        assert!(station.has_weapons && station.is_station);
+    }
+
+    #[test]
+    fn test_armor_class_names_and_legacy_tiers() {
+        let with_armor = |armor: serde_json::Value| {
+            let mut json = serde_json::to_value(make_target_dummy(2, 50.0)).unwrap();
+            json["armor"] = armor;
+            serde_json::from_value::<BattleUnit>(json).unwrap()
+        };
+        assert_eq!(with_armor(3.into()).armor, ArmorClass::Heavy);
+        assert_eq!(with_armor("Heavy".into()).armor, ArmorClass::Heavy);
+        // Out of range clamps instead of failing
+        assert_eq!(with_armor(99.into()).armor, ArmorClass::Super);
+        assert_eq!(with_armor(2.4.into()).armor, ArmorClass::Medium);
+        assert_eq!(with_armor((-1).into()).armor, ArmorClass::None);
+        assert!(serde_json::from_value::<BattleUnit>({
+            let mut json = serde_json::to_value(make_target_dummy(2, 50.0)).unwrap();
+            json["armor"] = "plated".into();
+            json
+        }).is_err());
+
+        // Written back as the name; bincode (replays) keeps the tier
+        let heavy = with_armor(3.into());
+        assert_eq!(serde_json::to_value(&heavy).unwrap()["armor"], "heavy");
+        let decoded: BattleUnit = bincode::deserialize(&bincode::serialize(&heavy).unwrap()).unwrap();
+        assert_eq!(decoded.armor, ArmorClass::Heavy);
+
+        // Heavy takes 1.5 off each hull hit unless flat_armor says otherwise
+        let mut unit = heavy;
+        unit.take_damage(10.0);
+        assert_eq!(unit.max_hp - unit.hp, 8.5);
+        unit.flat_armor = Some(0.0);
+        unit.take_damage(10.0);
+        assert_eq!(unit.max_hp - unit.hp, 18.5);
+    }
+
+    #[test]
+    fn test_surrendered_unit_leaves_the_fight_alive() {
+        let mut beaten = make_ship(2, 2, 60.0, 1.0);
+        beaten.can_surrender = true;
+        beaten.surrender_hp_threshold = 0.5;
+        let mut sim = BattleSimulator::new(vec![make_ship(1, 1, 0.0, 30.0), beaten], 1000.0);
+        assert!(sim.lock_target(1, 2));
+        let results = run(&mut sim, 200);
+
+        let at = results.iter().position(|r| r.surrendered == vec![2]).expect("unit 2 never surrendered");
+        assert_eq!(results.iter().filter(|r| !r.surrendered.is_empty()).count(), 1);
+        assert!(results[at + 1..].iter().all(|r| r.weapons_fired.is_empty()));
+        assert!(sim.is_battle_ended());
+        assert_eq!(sim.get_active_factions(), vec![1]);
+        assert_eq!(sim.get_winner(), Some(1));
+
+        let unit = sim.get_unit(2).unwrap();
+        assert!(unit.is_alive() && unit.is_surrendered);
+        assert!(unit.hp > 0.0 && unit.hp < 50.0);
+        assert_eq!(sim.get_unit(1).unwrap().target_id, None);
+        assert_eq!(sim.get_surrendered_units(), vec![2]);
+        assert!(sim.get_results().iter().any(|u| u.id == 2));
+        let summary = &sim.faction_summary()[1];
+        assert_eq!((summary.active, summary.destroyed, summary.surrendered), (0, 0, 1));
+
+        // Holds where it gave up
+        assert_eq!(sim.update_single_position(2, 500.0, 0.0, 0.0, false), None);
+    }
+
+    fn make_station(id: u32, x: f32) -> BattleUnit {
+        BattleUnit::builder().id(id).faction(2).position(x, 0.0, 0.0).hp(1000.0).as_station().build().unwrap()
+    }
+
+    #[test]
+    fn test_station_disables_then_repairs_once_left_alone() {
+        let mut station = make_station(10, 0.0);
+        station.disable_threshold = 0.3;
+        station.repair_rate = 200.0;
+        let mut sim = BattleSimulator::new(
+            vec![make_ship(1, 1, -50.0, 200.0), station, make_station(11, -140.0)],
+            1000.0,
+        );
+        sim.set_config(BattleConfig { disabled_repair_delay_secs: 1.0, ..Default::default() }).unwrap();
+
+        let mut time = 1000.0;
+        let mut tick = |sim: &mut BattleSimulator| {
+            time += DT as f64;
+            sim.simulate_tick(DT, time)
+        };
+        let mut results = Vec::new();
+        while results.len() < 200 && !sim.get_unit(10).unwrap().is_disabled {
+            results.push(tick(&mut sim));
+        }
+        assert_eq!(results.last().unwrap().disabled, vec![10]);
+        let hp = sim.get_unit(10).unwrap().hp;
+        assert!(hp > 0.0 && hp < 300.0);
+        assert_eq!(sim.faction_summary()[1].disabled, 1);
+
+        // Lasers move on to the other station and leave the disabled one be
+        for _ in 0..40 {
+            tick(&mut sim);
+        }
+        assert_eq!(sim.get_unit(1).unwrap().target_id, Some(11));
+        assert!(sim.get_unit(11).unwrap().hp < 1000.0);
+        assert_eq!(sim.get_unit(10).unwrap().hp, hp);
+        assert!(!sim.is_battle_ended());
+
+        // No repairs while the ship is close, back online once it has gone
+        sim.remove_unit(1);
+        let results: Vec<TickResult> = (0..60).map(|_| tick(&mut sim)).collect();
+        let back = results.iter().position(|r| r.reactivated == vec![10]).expect("station never reactivated");
+        assert!(back >= 20);
+        assert!(results[..back].iter().any(|r| r.repaired.iter().any(|u| u.id == 10)));
+        let station = sim.get_unit(10).unwrap();
+        assert!(!station.is_disabled && station.hp >= 600.0);
+        assert_eq!(sim.get_active_factions(), vec![2]);
+    }
+
+    #[test]
+    fn test_disabled_station_counts_as_eliminated_but_siege_finishes_it() {
+        let mut station = make_station(10, 0.0);
+        station.disable_threshold = 0.5;
+        let mut sim = BattleSimulator::new(vec![make_ship(1, 1, -50.0, 200.0), station.clone()], 1000.0);
+        let results = run(&mut sim, 200);
+        assert!(results.iter().any(|r| r.disabled == vec![10]));
+        assert!(sim.is_battle_ended());
+        assert_eq!(sim.get_winner(), Some(1));
+        assert!(sim.get_unit(10).unwrap().is_alive());
+
+        // A siege carrier keeps shooting - only the nuke still does anything
+        let mut carrier = make_ship(1, 1, -50.0, 200.0);
+        carrier.weapons.push(Weapon::builder().tag("1").dps(50.0).range(50.0, 100.0).as_siege().build());
+        // Out of everyone's sight, keeps faction 2 in the battle
+        let far = make_station(11, 5000.0);
+        let mut sim = BattleSimulator::new(vec![carrier, station, far], 1000.0);
+        let results = run(&mut sim, 1000);
+        let disabled_at = results.iter().position(|r| r.disabled == vec![10]).unwrap();
+        assert!(results.iter().any(|r| r.destroyed == vec![10]));
+        assert!(results[disabled_at + 1..].iter()
+            .flat_map(|r| &r.weapons_fired)
+            .all(|f| f.weapon_type == "NM-1"));
+    }
+
+    #[test]
+    fn test_class_weapon_restrictions_disable_weapons() {
+        let mut fighter = make_ship(1, 1, 0.0, 20.0);
+        fighter.ship_class = "Fighter".parse().unwrap();
+        fighter.weapons.push(Weapon::builder().tag("1").dps(500.0).range(50.0, 100.0).as_siege().build());
+        let mut station = make_station(2, 50.0);
+        station.ship_class = ShipClass::Fighter;
+        let mut sim = BattleSimulator::new(vec![fighter, station, make_ship(3, 2, 60.0, 0.0)], 1000.0);
+        assert_eq!(sim.get_unit(2).unwrap().ship_class, ShipClass::Station);
+        assert!("dreadnought".parse::<ShipClass>().is_err());
+
+        assert_eq!(sim.set_class_weapon_restrictions(ShipClass::Fighter, Some(vec!["laser".to_string()])), 1);
+        let weapons = &sim.get_unit(1).unwrap().weapons;
+        assert!(!weapons[0].is_disabled && weapons[1].is_disabled);
+
+        let mut late = make_ship(4, 1, -10.0, 20.0);
+        late.ship_class = ShipClass::Fighter;
+        late.weapons[0].tag = "RAIL".to_string();
+        sim.add_unit(late, 1000.0).unwrap();
+        let mut destroyer = make_ship(5, 1, -10.0, 20.0);
+        destroyer.ship_class = ShipClass::Destroyer;
+        destroyer.weapons[0].tag = "RAIL".to_string();
+        sim.add_units(vec![destroyer], 1000.0).unwrap();
+        assert!(sim.get_unit(4).unwrap().weapons[0].is_disabled);
+        assert!(!sim.get_unit(5).unwrap().weapons[0].is_disabled);
+
+        let fired: Vec<WeaponFired> = run(&mut sim, 100).into_iter().flat_map(|r| r.weapons_fired).collect();
+        assert!(fired.iter().any(|f| f.attacker_id == 1 && f.weapon_type == "LASER"));
+        assert!(fired.iter().all(|f| f.weapon_type != "NM-1" && f.attacker_id != 4));
+
+        // Lifting it doesn't re-enable anything
+        assert_eq!(sim.set_class_weapon_restrictions(ShipClass::Fighter, None), 0);
+        assert!(sim.weapon_class_restrictions().is_empty());
+        assert!(sim.get_unit(4).unwrap().weapons[0].is_disabled);
+    }
+
+    #[test]
+    fn test_drones_recalled_straight_away_with_no_recover_delay() {
+        let drone = BattleUnit { max_hp: 40.0, hp: 40.0, is_ship: true, ..Default::default() };
+        let mut carrier = make_ship(1, 1, 0.0, 500.0);
+        carrier.view_range = 500.0;
+        carrier.hangar = Some(DroneHangar { template: Box::new(drone), count: 1, recover_after_ticks: 0, ..Default::default() });
+        let mut sim = BattleSimulator::try_new(vec![carrier, make_ship(2, 2, 300.0, 20.0)], 1000.0).unwrap();
+        let launched = sim.simulate_tick(DT, 1000.0).spawned;
+        assert_eq!(launched.len(), 1);
+
+        assert!(sim.remove_unit(2));
+        let result = sim.simulate_tick(DT, 1000.0 + DT as f64);
+        assert!(result.withdrawn.contains(&launched[0]));
+        assert_eq!(sim.get_unit(1).unwrap().hangar.as_ref().unwrap().count, 1);
+    }
+
+    #[test]
+    fn test_carrier_wins_with_drones_and_recalls_them() {
+        // A template - the carrier gives each drone its id and faction
+        let drone = BattleUnit {
+            max_hp: 40.0,
+            hp: 40.0,
+            max_speed: 60.0,
+            is_ship: true,
+            weapons: vec![Weapon::builder().tag("LASER").dps(25.0).range(60.0, 100.0).build()],
+            ..Default::default()
+        };
+        let mut carrier = make_ship(1, 1, 0.0, 500.0);
+        carrier.weapons[0].max_range = 50.0;
+        carrier.max_weapon_range = 50.0;
+        carrier.view_range = 500.0;
+        carrier.player_id = Some(77);
+        carrier.hangar = Some(DroneHangar {
+            template: Box::new(drone),
+            count: 6,
+            launch_interval: 0.5,
+            recover_after_ticks: 20,
+            ..Default::default()
+        });
+        let mut sim = BattleSimulator::try_new(vec![carrier, make_ship(2, 2, 300.0, 20.0)], 1000.0).unwrap();
+        let results = run(&mut sim, 2000);
+
+        assert!(sim.is_battle_ended());
+        assert_eq!(sim.get_winner(), Some(1));
+        assert!(!sim.get_unit(2).unwrap().is_alive());
+        assert!(results.iter().flat_map(|r| &r.weapons_fired).all(|f| f.attacker_id != 1));
+        let spawned: Vec<u32> = results.iter().flat_map(|r| r.spawned.iter().copied()).collect();
+        assert!(!spawned.is_empty() && spawned.iter().all(|&id| id >= DRONE_ID_BASE));
+        let drone = sim.get_unit(spawned[0]).unwrap();
+        assert_eq!((drone.faction_id, drone.player_id, drone.carrier_id), (1, Some(77), Some(1)));
+        assert!(drone.ai_controlled);
+        let lost = results.iter().flat_map(|r| &r.destroyed).filter(|&&id| id >= DRONE_ID_BASE).count();
+        let built = 6 - sim.get_unit(1).unwrap().hangar.as_ref().unwrap().count as usize;
+        assert_eq!(built, spawned.len());
+
+        // Nothing left in view - survivors are recovered (despawned, back in
+        // the hangar count), lost drones stay lost
+        let start = 1000.0 + results.len() as f64 * DT as f64;
+        let later: Vec<TickResult> = (0..30).map(|i| sim.simulate_tick(DT, start + i as f64 * DT as f64)).collect();
+        let recovered: Vec<u32> = later.iter().flat_map(|r| r.withdrawn.iter().copied()).collect();
+        assert!(!recovered.is_empty());
+        assert_eq!(recovered.len(), built - lost);
+        assert!(later.iter().all(|r| r.docked.is_empty() && r.destroyed.is_empty()));
+        assert!(recovered.iter().all(|&id| sim.get_unit(id).unwrap().is_withdrawn()));
+        let losses = sim.faction_summary().into_iter().find(|f| f.faction_id == 1).unwrap();
+        assert_eq!(losses.destroyed as usize, lost);
+        let carrier = sim.get_unit(1).unwrap();
+        assert_eq!(carrier.hangar.as_ref().unwrap().count as usize, 6 - lost);
+        assert!(carrier.hangar_contents.is_empty());
+
+        // Hangar state survives a snapshot
+        let snapshot: Vec<BattleUnit> = serde_json::from_str(&serde_json::to_string(&sim.get_results()).unwrap()).unwrap();
+        let restored = BattleSimulator::try_new(snapshot, 2000.0).unwrap();
+        let carrier = restored.get_unit(1).unwrap();
+        assert_eq!(carrier.hangar.as_ref().unwrap().count as usize, 6 - lost);
+        assert!(carrier.hangar_contents.is_empty());
+
+        // A new enemy - the relaunch reuses the recovered drones' records
+        // before building any more
+        let units_before = sim.units.len();
+        sim.add_units(vec![make_ship(3, 2, 300.0, 20.0)], start + 1.5).unwrap();
+        let mut relaunched: Vec<u32> = (30..30 + 5 * recovered.len() as u32 * 20)
+            .flat_map(|i| sim.simulate_tick(DT, start + i as f64 * DT as f64).spawned)
+            .take(recovered.len())
+            .collect();
+        let mut recovered = recovered;
+        relaunched.sort_unstable();
+        recovered.sort_unstable();
+        assert_eq!(relaunched, recovered);
+        assert_eq!(sim.units.len(), units_before + 1);
+        assert!(relaunched.iter().all(|&id| sim.get_unit(id).is_some_and(|u| !u.is_withdrawn() && !u.is_in_hangar)));
+    }
+
+    #[test]
+    fn test_allied_pair_wipes_third_faction_and_battle_ends() {
+        let mut lone = make_ship(1, 1, 0.0, 10.0);
+        lone.max_hp = 60.0;
+        lone.hp = 60.0;
+        let mut sim = BattleSimulator::new(
+            vec![lone, make_ship(2, 2, 40.0, 10.0), make_ship(3, 3, -40.0, 10.0)],
+            1000.0,
+        );
+        sim.set_alliances(&[(3, 2)]);
+        assert!(!sim.is_hostile(2, 3) && sim.is_hostile(1, 2));
+
+        let results = run(&mut sim, 400);
+
+        assert!(sim.is_battle_ended());
+        assert!(!sim.is_stalemate());
+        assert!(!sim.get_units()[0].is_alive());
+        assert!(sim.get_units()[1].is_alive() && sim.get_units()[2].is_alive());
+        assert_eq!(sim.get_active_factions(), vec![2, 3]);
+        assert!(matches!(sim.get_winner(), Some(2 | 3)));
+        // Allies never shot each other
+        assert!(results.iter().all(|r| r.weapons_fired.iter().all(|w| w.attacker_id == 1 || w.target_id == 1)));
+    }
+
+    #[test]
+    fn test_alliance_mid_battle_clears_targets_between_pair() {
+        let mut sim = BattleSimulator::new(
+            vec![make_ship(1, 1, 0.0, 1.0), make_ship(2, 2, 40.0, 1.0)],
+            1000.0,
+        );
+        run(&mut sim, 5);
+        assert_eq!(sim.get_units()[0].target_id, Some(2));
+
+        sim.set_factions_allied(1, 2, true);
+        assert!(sim.get_units().iter().all(|u| u.target_id.is_none()));
+        assert!(sim.is_battle_ended());
+        run(&mut sim, 5);
+        assert!(sim.get_units().iter().all(|u| u.target_id.is_none()));
+
+        sim.set_alliances(&[]);
+        assert!(!sim.is_battle_ended());
+        run(&mut sim, 5);
+        assert_eq!(sim.get_units()[0].target_id, Some(2));
+    }
+
+    #[test]
+    fn test_query_units_in_radius_includes_boundary() {
+        let mut at_edge = make_target_dummy(2, 3.0);
+        at_edge.pos_y = 4.0;
+        let mut sim = BattleSimulator::new(
+            vec![make_ship(1, 1, 0.0, 1.0), at_edge, make_target_dummy(3, 5.5)],
+            1000.0,
+        );
+        sim.simulate_tick(DT, 1000.0);
+
+        let found = sim.query_units_in_radius(0.0, 0.0, 0.0, 5.0, None);
+        let ids: Vec<u32> = found.iter().map(|u| u.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(found[1].distance, 5.0);
+
+        let enemies = sim.query_units_in_radius(0.0, 0.0, 0.0, 10.0, Some(2));
+        assert_eq!(enemies.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_run_to_completion_lopsided_battle() {
+        let mut units: Vec<BattleUnit> = (1..=50).map(|id| make_ship(id, 1, id as f32, 20.0)).collect();
+        units.extend((51..=55).map(|id| make_ship(id, 2, id as f32 + 10.0, 20.0)));
+
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let report = sim.run_to_completion(DT, 5000);
+
+        assert_eq!(report.reason, CompletionReason::Elimination);
+        assert_eq!(report.winner, Some(1));
+        assert!(report.ticks > 0 && report.ticks < 5000);
+        assert!(report.survivors.iter().all(|u| u.faction_id == 1));
+
+        let losers = report.factions.iter().find(|f| f.faction_id == 2).unwrap();
+        assert_eq!((losers.active, losers.destroyed), (0, 5));
+        let winners = report.factions.iter().find(|f| f.faction_id == 1).unwrap();
+        assert_eq!(winners.active as usize, report.survivors.len());
+        assert_eq!(winners.active + winners.destroyed + winners.withdrawn, 50);
+    }
+
+    #[test]
+    fn test_restore_rolls_back_to_snapshot() {
+        let config = BattleConfig { mode: SimulationMode::Deterministic { seed: 7 }, ..Default::default() };
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 50.0, 20.0), make_ship(3, 3, 60.0, 20.0)];
+        let mut sim = BattleSimulator::new_with_config(units, 1000.0, config);
+        sim.set_factions_allied(2, 3, true);
+        let units_json = |sim: &BattleSimulator| serde_json::to_string(sim.get_units()).unwrap();
+        let tick_at = |i: u64| 1000.0 + i as f64 * DT as f64;
+
+        sim.simulate_ticks(10, DT, tick_at(0));
+        let snapshot = sim.snapshot();
+        let at_10 = units_json(&sim);
+        assert_eq!(snapshot.tick, 10);
+
+        sim.simulate_ticks(10, DT, tick_at(10));
+        let at_20 = units_json(&sim);
+        sim.set_factions_allied(2, 3, false);
+        assert_ne!(at_10, at_20);
+
+        let parsed: BattleSnapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        assert_eq!((parsed.tick, parsed.units.len(), parsed.alliances.as_slice()), (10, 3, &[(2, 3)][..]));
+
+        sim.restore(snapshot);
+        assert_eq!(sim.tick(), 10);
+        assert_eq!(units_json(&sim), at_10);
+        assert!(sim.are_allies(2, 3));
+        assert_grid_consistent(&sim);
+
+        // Re-simulating from there plays out the same
+        sim.simulate_ticks(10, DT, tick_at(10));
+        assert_eq!(units_json(&sim), at_20);
+        assert_eq!(sim.tick(), 20);
+
+        let initial = sim.initial_snapshot().clone();
+        assert_eq!((initial.tick, initial.units.len(), initial.time), (0, 3, 1000.0));
+        sim.restore(initial);
+        assert!(sim.get_units().iter().all(|u| u.hp == u.max_hp) && !sim.are_allies(2, 3));
+    }
+
+    #[test]
+    fn test_restore_rolls_back_past_an_objective_win() {
+        // Out of range and nobody moves - faction 1 sits in the zone while
+        // faction 2 only has to hold out
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 10000.0, 20.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let zone = BattleObjective::ControlZone { x: 0.0, y: 0.0, z: 0.0, radius: 100.0, min_ticks: 1000 };
+        sim.set_objectives(vec![(1, zone), (2, BattleObjective::SurviveFor(30))]);
+
+        sim.simulate_ticks(20, DT, 1000.0);
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.objective_progress[0].held_ticks, 20);
+
+        let report = sim.run_to_completion(DT, 100);
+        assert_eq!((report.reason, report.winner, report.tick), (CompletionReason::Objective, Some(2), 30));
+
+        sim.restore(snapshot);
+        assert!(!sim.is_battle_ended());
+        assert!(sim.victory_state().is_none() && sim.completed_objectives().is_empty());
+        assert_eq!(sim.get_winner(), None);
+        assert_eq!(sim.objective_progress[0].held_ticks, 20);
+
+        // ...and the win comes round again at the same tick
+        let report = sim.run_to_completion(DT, 100);
+        assert_eq!((report.reason, report.winner, report.tick), (CompletionReason::Objective, Some(2), 30));
+        assert_eq!(sim.objective_progress[0].held_ticks, 30);
+    }
+
+    #[test]
+    fn test_restore_brings_back_pending_waves_and_resources() {
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 10000.0, 20.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let node = ResourceNode { id: 1, pos_x: 50.0, pos_y: 0.0, pos_z: 0.0, control_radius: 100.0, resource_per_tick: 1.5, controlled_by: None };
+        assert!(sim.add_resource_node(node));
+        sim.schedule_reinforcements(10, vec![make_ship(3, 2, 9000.0, 20.0)]).unwrap();
+        let snapshot = sim.snapshot();
+        assert_eq!(snapshot.reinforcements.len(), 1);
+
+        let arrivals = |results: &[TickResult]| results.iter()
+            .filter(|r| r.spawned.contains(&3))
+            .map(|r| r.tick)
+            .collect::<Vec<_>>();
+        let results = sim.simulate_ticks(20, DT, 1000.0);
+        assert_eq!(arrivals(&results), vec![10]);
+        assert_eq!(sim.faction_resources().get(&1), Some(&30.0));
+        assert_eq!(sim.resource_nodes()[0].controlled_by, Some(1));
+
+        let parsed: BattleSnapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        sim.restore(parsed);
+        assert_eq!(sim.pending_reinforcements(), 1);
+        assert!(sim.get_unit(3).is_none());
+        assert!(sim.faction_resources().is_empty());
+        assert_eq!(sim.resource_nodes()[0].controlled_by, None);
+
+        // The wave arrives again, and income isn't paid twice
+        let results = sim.simulate_ticks(20, DT, 1000.0);
+        assert_eq!(arrivals(&results), vec![10]);
+        assert_eq!(sim.faction_resources().get(&1), Some(&30.0));
+    }
+
+    #[test]
+    fn test_simulate_ticks_fast_forwards() {
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_target_dummy(2, 50.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let results = sim.simulate_ticks(40, DT, 1000.0);
+        assert_eq!(results.iter().map(|r| r.tick).collect::<Vec<_>>(), (1..=40).collect::<Vec<_>>());
+        assert!((sim.simulated_time() - (1000.0 + 39.0 * DT as f64)).abs() < 1e-9);
+        assert!(results.iter().any(|r| !r.weapons_fired.is_empty()));
+
+        sim.set_config(BattleConfig { max_fast_forward_ticks: 25, ..Default::default() }).unwrap();
+        assert_eq!(sim.simulate_ticks(100, DT, 1002.0).len(), 25);
+        assert_eq!(sim.tick(), 65);
+    }
+
+    #[test]
+    fn test_simulate_until_end() {
+        let mut units: Vec<BattleUnit> = (1..=50).map(|id| make_ship(id, 1, id as f32, 20.0)).collect();
+        units.extend((51..=55).map(|id| make_ship(id, 2, id as f32 + 10.0, 20.0)));
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let outcome = sim.simulate_until_end(5000, DT, 1000.0);
+        assert_eq!((outcome.reason, outcome.winner), (CompletionReason::Elimination, Some(1)));
+        assert!(outcome.ticks > 0 && outcome.ticks < 5000 && outcome.tick == outcome.ticks);
+        assert_eq!(outcome.survivors as usize, sim.get_units().iter().filter(|u| u.in_battle()).count());
+
+        // Already over - nothing more to run
+        assert_eq!(sim.simulate_until_end(5000, DT, 2000.0).ticks, 0);
+
+        // Out of range of each other - the cap stops it
+        let mut sim = BattleSimulator::new(vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 10000.0, 20.0)], 1000.0);
+        sim.set_config(BattleConfig { max_fast_forward_ticks: 30, ..Default::default() }).unwrap();
+        let outcome = sim.simulate_until_end(5000, DT, 1000.0);
+        assert_eq!((outcome.reason, outcome.ticks, outcome.winner, outcome.survivors), (CompletionReason::TickCap, 30, None, 2));
+    }
+
+    #[test]
+    fn test_objectives_end_the_battle() {
+        // Out of range and nobody moves - only the objective can end it
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 10000.0, 20.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        sim.set_objectives(vec![(2, BattleObjective::SurviveFor(30))]);
+
+        let results = run(&mut sim, 100);
+        assert_eq!(results.len(), 30);
+        assert_eq!(results[29].objectives_met, vec!["2:survive_for(30)".to_string()]);
+        assert!(results[..29].iter().all(|r| r.objectives_met.is_empty()));
+        assert_eq!(sim.get_winner(), Some(2));
+        assert_eq!(sim.run_to_completion(DT, 10).reason, CompletionReason::Objective);
+
+        // Faction 1 only has to kill unit 51, not the whole enemy fleet
+        let mut units: Vec<BattleUnit> = (1..=50).map(|id| make_ship(id, 1, id as f32, 20.0)).collect();
+        units.extend((51..=55).map(|id| make_ship(id, 2, id as f32 + 10.0, 20.0)));
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        sim.set_objectives(vec![(1, BattleObjective::KillUnit(51)), (2, BattleObjective::SurviveFor(5000))]);
+        let report = sim.run_to_completion(DT, 5000);
+        assert_eq!(report.reason, CompletionReason::Objective);
+        assert_eq!(report.winner, Some(1));
+        assert!(!sim.get_unit(51).unwrap().is_alive());
+        assert_eq!(sim.completed_objectives(), &[(1, "kill_unit(51)".to_string())]);
+        let state = sim.victory_state().unwrap();
+        assert_eq!((state.winner, state.condition.as_str()), (Some(1), "kill_unit(51)"));
+
+        // With objectives, wiping out the enemy doesn't end it on its own
+        let units = vec![make_ship(1, 1, 0.0, 200.0), make_ship(2, 2, 50.0, 1.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        sim.set_objectives(vec![(1, BattleObjective::ControlZone { x: 5000.0, y: 0.0, z: 0.0, radius: 100.0, min_ticks: 10 })]);
+        let report = sim.run_to_completion(DT, 200);
+        assert!(!sim.get_unit(2).unwrap().is_alive());
+        assert_eq!(report.reason, CompletionReason::TickCap);
+        // ...but holding the zone does
+        sim.update_single_position(1, 5000.0, 0.0, 0.0, true);
+        let report = sim.run_to_completion(DT, 200);
+        assert_eq!((report.reason, report.ticks, report.winner), (CompletionReason::Objective, 10, Some(1)));
+
+        sim.set_objectives(Vec::new());
+        assert_eq!(sim.run_to_completion(DT, 10).reason, CompletionReason::Elimination);
+    }
+
+    #[test]
+    fn test_victory_conditions() {
+        let tough = |id, faction, x| BattleUnit { hp: 5000.0, max_hp: 5000.0, ..make_ship(id, faction, x, 20.0) };
+
+        // Five attackers can't grind the defender down in 100 ticks - holding out wins
+        let mut units: Vec<BattleUnit> = (1..=5).map(|id| tough(id, 1, id as f32)).collect();
+        units.push(tough(10, 2, 60.0));
+        let victory = VictoryCondition::SurviveTicks { faction_id: 2, ticks: 100 };
+        let mut sim = BattleSimulator::new_with_config(units, 1000.0, BattleConfig { victory, ..Default::default() });
+        let report = sim.run_to_completion(DT, 1000);
+        assert_eq!((report.reason, report.winner, report.tick), (CompletionReason::Objective, Some(2), 100));
+        assert_eq!(sim.get_winner(), Some(2));
+        assert!(sim.get_unit(10).unwrap().is_alive());
+        let state = sim.victory_state().unwrap();
+        assert_eq!((state.winner, state.condition.as_str(), state.tick), (Some(2), "survive_ticks(2, 100)", 100));
+
+        // Even fleets, but faction 2's flagship is out in front
+        let mut units: Vec<BattleUnit> = (1..=3).map(|id| tough(id, 1, id as f32)).collect();
+        units.extend((4..=5).map(|id| tough(id, 2, 70.0 + id as f32)));
+        units.push(make_ship(6, 2, 60.0, 20.0));
+        let victory = VictoryCondition::Any(vec![VictoryCondition::DestroyUnit(6), VictoryCondition::DestroyAllStations(1)]);
+        let mut sim = BattleSimulator::new_with_config(units, 1000.0, BattleConfig { victory, ..Default::default() });
+        let report = sim.run_to_completion(DT, 1000);
+        assert_eq!((report.reason, report.winner), (CompletionReason::Objective, Some(1)));
+        assert!(!sim.get_unit(6).unwrap().is_alive());
+        assert!(sim.get_unit(4).unwrap().is_alive() && sim.get_unit(5).unwrap().is_alive());
+        assert_eq!(sim.victory_state().unwrap().condition, "destroy_unit(6)");
+
+        // Out of range and never met - stalemate still ends it
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 10000.0, 20.0)];
+        let config = BattleConfig { victory: VictoryCondition::DestroyUnit(2), stalemate_ticks: Some(50), ..Default::default() };
+        let mut sim = BattleSimulator::new_with_config(units, 1000.0, config);
+        assert_eq!(sim.run_to_completion(DT, 1000).reason, CompletionReason::Stalemate);
+        assert!(sim.victory_state().is_none());
+    }
+
+    #[test]
+    fn test_resource_nodes_pay_their_holder() {
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 10000.0, 20.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let node = |id, x| ResourceNode {
+            id,
+            pos_x: x,
+            pos_y: 0.0,
+            pos_z: 0.0,
+            control_radius: 100.0,
+            resource_per_tick: 1.5,
+            controlled_by: None,
+        };
+        assert!(sim.add_resource_node(node(1, 50.0)));
+        assert!(sim.add_resource_node(node(2, 5000.0)));
+        assert!(!sim.add_resource_node(node(1, 0.0)));
+
+        // Idle ticks pay out too
+        let results: Vec<TickResult> = (0..10).map(|i| sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64)).collect();
+        assert!(results.iter().all(|r| r.resources_gained == vec![(1, 1.5)]));
+        assert_eq!(sim.faction_resources().get(&1), Some(&15.0));
+        assert_eq!(sim.resource_nodes()[0].controlled_by, Some(1));
+        assert_eq!(sim.resource_nodes()[1].controlled_by, None);
+
+        assert!(sim.remove_resource_node(1));
+        assert!(!sim.remove_resource_node(1));
+        assert!(sim.simulate_tick(DT, 1001.0).resources_gained.is_empty());
+        assert_eq!(sim.faction_resources().get(&1), Some(&15.0));
+    }
+
+    #[test]
+    fn test_run_to_completion_respects_tick_cap() {
+        // Far out of range of each other and nobody moves - nothing can happen
+        let units = vec![make_ship(1, 1, 0.0, 20.0), make_ship(2, 2, 10000.0, 20.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+
+        let report = sim.run_to_completion(DT, 100);
+        assert_eq!(report.reason, CompletionReason::TickCap);
+        assert_eq!(report.ticks, 100);
+        assert_eq!(report.winner, None);
+        assert_eq!(report.survivors.len(), 2);
+
+        // Continues from where it stopped
+        let report = sim.run_to_completion(DT, 100);
+        assert_eq!(report.ticks, 100);
+        assert_eq!(sim.tick, 200);
+    }
+
+    #[test]
+    fn test_manual_fire_checks_range_and_disabled() {
+        let mut attacker = make_ship(1, 1, 0.0, 10.0);
+        attacker.weapons.push(Weapon { tag: "RAIL".to_string(), ..attacker.weapons[0].clone() });
+        attacker.weapons[0].last_fired = 900.0;
+        attacker.weapons[1].last_fired = 999.9;
+        let units = vec![attacker, make_target_dummy(2, 50.0), make_target_dummy(3, 500.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+
+        // Out of range
+        assert!(sim.manually_fire(1, 3, "LASER", 1000.0).is_none());
+
+        // Disabled
+        sim.units[0].weapons[0].is_disabled = true;
+        assert!(sim.manually_fire(1, 2, "LASER", 1000.0).is_none());
+        assert_eq!(sim.units[0].weapons[0].last_fired, 900.0);
+
+        sim.units[0].weapons[0].is_disabled = false;
+        let fired = sim.manually_fire(1, 2, "LASER", 1000.0).expect("in range and ready");
+        assert_eq!((fired.attacker_id, fired.target_id), (1, 2));
+        assert_eq!(sim.units[0].weapons[0].last_fired, 1000.0);
+        assert_eq!(sim.units[0].weapons[1].last_fired, 999.9);
+
+        // Now on cooldown
+        assert!(sim.manually_fire(1, 2, "LASER", 1000.0).is_none());
+
+        // Committed with the next tick
+        let result = sim.simulate_tick(DT, 1000.0 + DT as f64);
+        assert_eq!(result.weapons_fired.len(), 1);
+        assert_eq!(result.weapons_fired[0].weapon_type, "LASER");
+        assert!(sim.units[1].hp < 100000.0);
+        assert!(sim.units[1].hp >= 100000.0 - 10.0);
+    }
+
+    #[test]
+    fn test_overkill_not_credited() {
+        let attackers = (1..=3).map(|id| {
+            let mut ship = make_ship(id, 1, id as f32 * 10.0, 100.0);
+            ship.weapons[0].last_fired = 900.0;
+            ship
+        });
+        let mut victim = make_target_dummy(4, 0.0);
+        victim.max_hp = 150.0;
+        victim.hp = 150.0;
+        let mut units: Vec<BattleUnit> = attackers.collect();
+        units.push(victim);
+
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let results = run(&mut sim, 100);
+        let killing_tick = results.iter().find(|r| r.destroyed.contains(&4)).expect("victim destroyed");
+
+        // All three fired the same tick - queue order (unit order) decides credit
+        assert_eq!(killing_tick.weapons_fired.len(), 3);
+        let dealt: Vec<f32> = sim.get_units()[..3].iter().map(|u| u.damage_dealt).collect();
+        assert_eq!(dealt, vec![100.0, 50.0, 0.0]);
+        assert_eq!(sim.get_units()[3].damage_taken, 150.0);
+        assert_eq!(killing_tick.overkill.len(), 1);
+        assert_eq!(killing_tick.overkill[0].id, 4);
+        assert_eq!(killing_tick.overkill[0].overkill, 150.0);
+
+        // Every shot counts as fired; the wasted one isn't a hit; the kill goes
+        // to the shot that finished it
+        let stats: Vec<CombatStats> = (1..=3).map(|id| sim.get_unit_stats(id).unwrap()).collect();
+        assert!(stats.iter().all(|s| s.shots_fired == 1));
+        assert_eq!(stats.iter().map(|s| s.shots_hit).collect::<Vec<_>>(), vec![1, 1, 0]);
+        assert_eq!(stats.iter().map(|s| s.kills).collect::<Vec<_>>(), vec![0, 1, 0]);
+        assert_eq!(stats[2].accuracy, 0.0);
+
+        let faction = sim.get_faction_stats(1);
+        assert_eq!((faction.shots_fired, faction.shots_hit, faction.kills), (3, 2, 1));
+        assert!((faction.accuracy - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(faction.damage_dealt, 150.0);
+    }
+
+    #[test]
+    fn test_locked_target_survives_force_retarget() {
+        // Unit 2 is out of range and unit 3 is right next to the attacker
+        let mut attacker = make_ship(1, 1, 0.0, 1.0);
+        attacker.weapons[0].last_fired = 900.0;
+        let units = vec![attacker, make_target_dummy(2, 500.0), make_target_dummy(3, 10.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+
+        assert!(!sim.lock_target(1, 1));
+        assert!(sim.lock_target(1, 2));
+        assert_eq!(sim.force_retarget_all(), 0);
+        assert!(!sim.force_retarget_unit(1));
+        sim.update_single_position(1, 1.0, 0.0, 0.0, true);
+
+        for i in 0..(RETARGET_INTERVAL * 2) {
+            sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            assert_eq!(sim.get_units()[0].target_id, Some(2));
+        }
+
+        // Dies (or leaves) - lock released, normal targeting picks the nearby dummy
+        assert!(sim.remove_unit(2));
+        assert!(!sim.get_units()[0].target_locked);
+        sim.simulate_tick(DT, 1010.0);
+        assert_eq!(sim.get_units()[0].target_id, Some(3));
+
+        assert!(sim.lock_target(1, 3));
+        assert!(sim.unlock_target(1));
+        assert!(!sim.unlock_target(1));
+    }
+
+    #[test]
+    fn test_energy_limited_fire_settles_to_regen_rate() {
+        // Alpha strike costs 30, the capacitor holds 20 and refills 10/s
+        let mut ship = make_ship(1, 1, 0.0, 10.0);
+        let template = ship.weapons[0].clone();
+        ship.weapons = [("BIG", 15.0), ("MID", 10.0), ("SMALL", 5.0)]
+            .into_iter()
+            .map(|(tag, energy_cost)| Weapon {
+                tag: tag.to_string(),
+                energy_cost,
+                last_fired: 900.0,
+                ..template.clone()
+            })
+            .collect();
+        ship.max_energy = 20.0;
+        ship.energy = 20.0;
+        ship.energy_regen = 10.0;
+        let mut sim = BattleSimulator::new(vec![ship, make_target_dummy(2, 50.0)], 1000.0);
+
+        let cost = |tag: &str| match tag { "BIG" => 15.0, "MID" => 10.0, _ => 5.0 };
+        let mut spent_late = 0.0;
+        let mut small_late = 0;
+        for i in 0..400 {
+            let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            let energy = sim.get_units()[0].energy;
+            assert!((0.0..=20.0).contains(&energy));
+
+            if i == 0 {
+                // Can't afford everything - cheapest first
+                let mut tags: Vec<&str> = result.weapons_fired.iter().map(|f| f.weapon_type.as_str()).collect();
+                tags.sort();
+                assert_eq!(tags, vec!["MID", "SMALL"]);
+                assert!(result.energy.iter().any(|e| e.id == 1));
+            }
+            if i >= 200 {
+                spent_late += result.weapons_fired.iter().map(|f| cost(&f.weapon_type)).sum::<f32>();
+                small_late += result.weapons_fired.iter().filter(|f| f.weapon_type == "SMALL").count();
+            }
+        }
+
+        // Last 10s: spending tracks the 100 energy recharged, never stalls
+        assert!(spent_late <= 100.0 + 20.0, "spent {}", spent_late);
+        assert!(spent_late >= 100.0 - 20.0, "spent {}", spent_late);
+        assert!(small_late >= 9);
+    }
+
+    #[test]
+    fn test_allies_stop_attacking_immediately() {
+        use crate::status_effect::EffectSpec;
+
+        let mut burner = make_ship(1, 1, 0.0, 5.0);
+        burner.weapons[0].last_fired = 900.0;
+        burner.weapons[0].applies_effect = Some(EffectSpec {
+            kind: StatusEffectKind::Burn,
+            magnitude: 5.0,
+            duration: 30.0,
+        });
+        let mut third = make_target_dummy(3, -60.0);
+        third.faction_id = 3;
+        let mut sim = BattleSimulator::new(vec![burner, make_target_dummy(2, 40.0), third], 1000.0);
+
+        let mut time = 1000.0;
+        for _ in 0..5 {
+            sim.simulate_tick(DT, time);
+            time += DT as f64;
+        }
+        assert_eq!(sim.get_units()[0].target_id, Some(2));
+        assert!(sim.get_units()[1].has_effect(StatusEffectKind::Burn));
+
+        sim.set_factions_allied(1, 2, true);
+        assert!(sim.are_allies(2, 1));
+        assert!(!sim.are_allies(1, 3));
+
+        let hp = sim.get_units()[1].hp;
+        let mut hit_third = false;
+        for _ in 0..40 {
+            let result = sim.simulate_tick(DT, time);
+            time += DT as f64;
+            assert!(result.weapons_fired.iter().all(|f| f.target_id != 2));
+            hit_third |= result.weapons_fired.iter().any(|f| f.target_id == 3);
+            // The burn from before the alliance no longer lands
+            assert_eq!(sim.get_units()[1].hp, hp);
+        }
+        assert!(hit_third);
+    }
+
+    #[test]
+    fn test_identical_sequences_fire_interleaved() {
+        // Same alternate-tick pattern on both ships, no cooldown
+        let mut units: Vec<BattleUnit> = (1..=2).map(|id| {
+            let mut ship = make_ship(id, 1, 0.0, 1.0);
+            ship.weapons[0].cooldown = 0.0;
+            ship.weapons[0].sequence = vec![true, false];
+            ship
+        }).collect();
+        units.push(make_target_dummy(3, 50.0));
+        let mut sim = BattleSimulator::new(units, 1000.0);
+
+        let offsets: Vec<Option<u32>> = sim.get_units()[..2].iter().map(|u| u.weapons[0].sequence_offset).collect();
+        assert!(offsets.iter().all(|o| o.is_some()));
+
+        let mut shooters_per_tick = Vec::new();
+        for i in 0..10 {
+            let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            let mut shooters: Vec<u32> = result.weapons_fired.iter().map(|f| f.attacker_id).collect();
+            shooters.sort();
+            shooters_per_tick.push(shooters);
+        }
+        // Exactly one of the two fires each tick, alternating
+        assert!(shooters_per_tick.iter().all(|s| s.len() == 1));
+        assert!(shooters_per_tick.windows(2).all(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn test_stalemate_threshold_and_warning() {
+        // Out of range - nothing ever fires
+        let mut sim = BattleSimulator::new(vec![make_ship(1, 1, 0.0, 1.0), make_target_dummy(2, 5000.0)], 1000.0);
+        sim.set_stalemate_threshold(40);
+        assert_eq!(sim.stalemate_threshold(), 40);
+
+        let results = run(&mut sim, 100);
+        assert_eq!(results.len(), 40);
+        assert!(sim.is_stalemate());
+        // Warned from tick 21 on (more than half the threshold without combat)
+        assert!(results[..20].iter().all(|r| !r.stalemate_warning));
+        assert!(results[20..].iter().all(|r| r.stalemate_warning));
+        assert_eq!(sim.ticks_since_combat(), 40);
+
+        // Raising it un-ends the battle
+        sim.set_stalemate_threshold(STALEMATE_TICKS);
+        assert!(!sim.is_battle_ended());
+        assert!(!sim.stalemate_warning());
+    }
+
+    #[test]
+    fn test_stalemate_takes_the_same_time_at_any_tick_rate() {
+        // The same standoff stepped at 10 and 20 Hz ends after 3 simulated seconds
+        let seconds_to_stalemate = |tick_rate: f32| {
+            let config = BattleConfig { tick_rate, stalemate_secs: 3.0, ..Default::default() };
+            let mut sim = BattleSimulator::new_with_config(vec![make_ship(1, 1, 0.0, 1.0), make_target_dummy(2, 5000.0)], 1000.0, config);
+            while !sim.is_battle_ended() && sim.tick() < 1000 {
+                sim.step();
+            }
+            (sim.tick(), sim.tick() as f32 * sim.config().fixed_dt())
+        };
+        let (ticks_10, secs_10) = seconds_to_stalemate(10.0);
+        let (ticks_20, secs_20) = seconds_to_stalemate(20.0);
+        assert_eq!((ticks_10, ticks_20), (30, 60));
+        assert!((secs_10 - 3.0).abs() < 1e-4 && (secs_20 - 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_new_with_config() {
+        let units = vec![make_ship(1, 1, 0.0, 10.0), make_ship(2, 2, 50.0, 10.0)];
+        let config = BattleConfig { grid_cell_size: 250.0, tick_rate: 10.0, ..Default::default() };
+        let mut sim = BattleSimulator::new_with_config(units.clone(), 1000.0, config);
+        // A fixed cell size isn't re-tuned to the two units
+        assert_eq!(sim.grid_stats().unwrap().cell_size, 250.0);
+        sim.step();
+        assert!((sim.simulated_time() - 1000.1).abs() < 1e-6);
+
+        let sim = BattleSimulator::new_with_config(units, 1000.0, BattleConfig::default());
+        let tuned = sim.grid_stats().unwrap().cell_size;
+        assert_eq!(tuned, SpatialGrid::auto_cell_size(2, 25.0));
+    }
+
+    #[test]
+    fn test_target_spread_factor() {
+        // 30 attackers, two unarmed hulls 60 and 62 away - same priority
+        let mut units: Vec<BattleUnit> = (1..=30)
+            .map(|id| {
+                let mut ship = make_ship(id, 1, 0.0, 1.0);
+                ship.pos_z = id as f32 * 0.01;
+                ship
+            })
+            .collect();
+        units.push(make_hull(100, 2, 60.0));
+        units.push(make_hull(101, 2, 62.0));
+
+        let targets = |factor: f32| {
+            let config = BattleConfig { target_spread_factor: factor, ..Default::default() };
+            let mut sim = BattleSimulator::new_with_config(units.clone(), 1000.0, config);
+            sim.simulate_tick(DT, 1000.0 + DT as f64);
+            let mut counts = HashMap::new();
+            for unit in sim.get_units().iter().filter(|u| u.faction_id == 1) {
+                *counts.entry(unit.target_id.unwrap()).or_insert(0) += 1;
+            }
+            counts
+        };
+
+        assert_eq!(targets(0.0).get(&100), Some(&30));
+        let spread = targets(0.5);
+        assert!(spread[&100] > 0 && spread[&101] > 0, "{:?}", spread);
+
+        // The preference is fixed per pair, so a repeat run agrees
+        assert_eq!(targets(0.5), spread);
+    }
+
+    #[test]
+    fn test_grid_stats_and_debug_query() {
+        // Three ships in one cell, a dummy two cells away
+        let mut units: Vec<BattleUnit> = (1..=3).map(|id| make_ship(id, 1, (id - 1) as f32 * 10.0, 1.0)).collect();
+        units.push(make_target_dummy(4, 3000.0));
+        let cell_size = SpatialGrid::auto_cell_size(units.len(), battlefield_radius(&units));
+        let mut sim = BattleSimulator::new(units.clone(), 1000.0);
+        assert_eq!(sim.grid_stats().unwrap().total_entries, 0);
+        sim.simulate_tick(DT, 1000.0);
+
+        let stats = sim.grid_stats().unwrap();
+        assert_eq!(stats.cell_size, cell_size);
+        assert_eq!((stats.cell_count, stats.total_entries), (2, 4));
+        assert_eq!((stats.min_per_cell, stats.max_per_cell), (1, 3));
+        assert_eq!(stats.avg_per_cell, 2.0);
+        assert_eq!(stats.units_beyond_query_range, 0);
+
+        // Whole cells come back - 2 and 3 are candidates for a 5 unit query
+        assert_eq!(sim.debug_query_nearby(0.0, 0.0, 0.0, 5.0), vec![1, 2, 3]);
+        assert_eq!(sim.debug_query_nearby(3000.0, 0.0, 0.0, 5.0), vec![4]);
+
+        sim.units[0].max_weapon_range = stats.max_query_range * 2.0;
+        assert_eq!(sim.grid_stats().unwrap().units_beyond_query_range, 1);
+
+        // The octree's queries are exact and it has no cells
+        let mut sim = BattleSimulator::with_index(units, 1000.0, crate::octree::Octree::new());
+        sim.simulate_tick(DT, 1000.0);
+        assert!(sim.grid_stats().is_none());
+        assert_eq!(sim.debug_query_nearby(0.0, 0.0, 0.0, 5.0), vec![1]);
+    }
+
+    #[test]
+    fn test_weapon_fire_without_damage_counts_as_combat() {
+        let mut attacker = make_ship(1, 1, 0.0, 1.0);
+        attacker.weapons[0].last_fired = 900.0;
+        let mut sim = BattleSimulator::new(vec![attacker, make_target_dummy(2, 500.0)], 1000.0);
+        for i in 0..100 {
+            sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+        }
+        assert_eq!(sim.ticks_since_combat(), 100);
+        assert_eq!(sim.stalemate_threshold(), STALEMATE_TICKS);
+
+        // Shot in flight when the pair allied - it's reported but never lands
+        sim.update_single_position(2, 50.0, 0.0, 0.0, false);
+        assert!(sim.manually_fire(1, 2, "LASER", 1006.0).is_some());
+        sim.set_factions_allied(1, 2, true);
+        let result = sim.simulate_tick(DT, 1006.0);
+        assert_eq!(result.weapons_fired.len(), 1);
+        assert!(result.damaged.is_empty() && result.destroyed.is_empty());
+        assert_eq!(sim.ticks_since_combat(), 0);
+        assert_eq!(sim.tick(), 101);
+    }
+
+    #[test]
+    fn test_combat_log_records_battle_events() {
+        let mut attacker = make_ship(1, 1, 0.0, 100.0);
+        attacker.weapons[0].last_fired = 900.0;
+        let mut victim = make_target_dummy(2, 50.0);
+        victim.max_hp = 150.0;
+        victim.hp = 150.0;
+        victim.max_shield = 20.0;
+        victim.shield = 20.0;
+
+        let mut sim = BattleSimulator::new(vec![attacker, victim], 1000.0);
+        run(&mut sim, 100);
+
+        let events = sim.combat_log().since(0, usize::MAX);
+        let kinds: Vec<&str> = events.iter().map(|e| match e.entry {
+            CombatLogEntry::TargetAcquired { .. } => "target",
+            CombatLogEntry::WeaponFired(_) => "fired",
+            CombatLogEntry::DamageDealt { .. } => "damage",
+            CombatLogEntry::ShieldBroken(_) => "shield",
+            CombatLogEntry::UnitDestroyed(_) => "destroyed",
+            CombatLogEntry::FactionSurrendered(_) => "surrendered",
+        }).collect();
+        assert_eq!(kinds, vec!["target", "fired", "damage", "shield", "fired", "damage", "destroyed"]);
+        assert!(events.windows(2).all(|w| w[0].tick <= w[1].tick));
+
+        // Paging by tick
+        let last_tick = events.last().unwrap().tick;
+        assert_eq!(sim.combat_log().since(last_tick, 10).len(), 3);
+        assert_eq!(sim.combat_log().since(0, 2).len(), 2);
+
+        sim.clear_combat_log();
+        assert!(sim.combat_log().is_empty());
+    }
+
+    #[test]
+    fn test_priority_table_sends_bomber_past_frigate() {
+        let setup = || {
+            let mut bomber = make_ship(1, 1, 0.0, 10.0);
+            bomber.class = "bomber".to_string();
+            bomber.ai_controlled = true;
+            bomber.view_range = 1000.0;
+            bomber.max_hp = 100000.0;
+            bomber.hp = 100000.0;
+            bomber.weapons[0].last_fired = 900.0;
+            let mut frigate = make_ship(2, 2, 200.0, 1.0);
+            frigate.class = "frigate".to_string();
+            frigate.pos_y = 30.0; // Off the flight path
+            frigate.weapons[0].last_fired = 900.0;
+            let mut station = make_target_dummy(3, 400.0);
+            station.class = "station".to_string();
+            station.is_ship = false;
+            station.is_station = true;
+            vec![bomber, frigate, station]
+        };
+
+        let mut sim = BattleSimulator::new(setup(), 1000.0);
+        let mut table = PriorityTable::default();
+        table.set("bomber", "station", 200);
+        sim.set_priority_table(table);
+
+        let results = run(&mut sim, 200);
+        assert_eq!(sim.get_units()[0].target_id, Some(3));
+        let first_shot = results.iter().flat_map(|r| &r.weapons_fired).find(|f| f.attacker_id == 1).unwrap();
+        assert_eq!(first_shot.target_id, 3);
+
+        // Without the table the armed frigate wins
+        let mut sim = BattleSimulator::new(setup(), 1000.0);
+        sim.simulate_tick(DT, 1000.0);
+        assert_eq!(sim.get_units()[0].target_id, Some(2));
+    }
+
+    #[test]
+    fn test_replay_roundtrip_reproduces_results() {
+        use crate::replay::ReplayRecorder;
+
+        // last_fired 0 - cooldowns get randomized on construction / add
+        let mut attacker = make_ship(1, 1, 0.0, 20.0);
+        attacker.weapons.push(Weapon::builder().tag("TORPEDO").dps(30.0).cooldown(50.0).range(50.0, 100.0).last_fired(900.0).build());
+        let mut hunter = make_ship(2, 2, 300.0, 10.0);
+        hunter.ai_controlled = true;
+        hunter.view_range = 1000.0;
+        hunter.max_hp = 400.0;
+        hunter.hp = 400.0;
+        let units = vec![attacker, make_ship(3, 2, 50.0, 5.0), hunter];
+
+        // Random rolls wouldn't play back - only a deterministic battle records
+        let mut sim = BattleSimulator::new(units.clone(), 1000.0);
+        assert!(!sim.start_recording() && !sim.is_recording());
+        let config = BattleConfig { mode: SimulationMode::Deterministic { seed: 5 }, ..Default::default() };
+        let mut sim = BattleSimulator::new_with_config(units, 1000.0, config);
+        assert!(sim.start_recording());
+        assert_eq!(sim.set_config(BattleConfig::default()), Err(ConfigError::StochasticWhileRecording));
+        assert!(sim.manually_fire(1, 3, "TORPEDO", 1000.0).is_some());
+
+        let mut recorded = Vec::new();
+        for i in 0..150 {
+            let time = 1000.0 + i as f64 * DT as f64;
+            match i {
+                20 => sim.add_unit(make_ship(4, 1, -20.0, 15.0), time).unwrap(),
+                40 => { sim.update_positions(&[PositionUpdate { id: 1, x: 30.0, y: 10.0, z: 0.0, clear_target: false }]); }
+                60 => { sim.update_single_position(4, 60.0, -5.0, 0.0, false); }
+                _ => {}
+            }
+            let result = sim.simulate_tick(DT, time);
+            recorded.push(untimed_json(&result));
+        }
+        assert!(!sim.start_recording());
+        assert!(recorded.iter().any(|r| r.contains("\"damaged\":[{")));
+
+        let bytes = sim.export_replay().unwrap();
+        let mut replay = BattleSimulator::from_replay(ReplayRecorder::import(&bytes).unwrap(), SpatialGrid::new(DEFAULT_CELL_SIZE));
+        assert_eq!(replay.replay_ticks_remaining(), 150);
+
+        let mut played = Vec::new();
+        while let Some(result) = replay.step_replay() {
+            played.push(untimed_json(&result));
+        }
+        assert_eq!(played, recorded);
+        assert!(replay.step_replay().is_none());
+    }
+
+    #[test]
+    fn test_recorded_battle_replays_exactly() {
+        use crate::replay::ReplayRecorder;
+
+        let config = BattleConfig { mode: SimulationMode::Deterministic { seed: 11 }, record_replay: true, ..Default::default() };
+        let units = vec![
+            make_ship(1, 1, 0.0, 20.0), make_ship(2, 1, -30.0, 15.0),
+            make_ship(3, 2, 150.0, 20.0), make_ship(4, 2, 170.0, 10.0),
+            make_ship(5, 3, 60.0, 10.0),
+        ];
+        let mut sim = BattleSimulator::new_with_config(units, 1000.0, config);
+        assert!(sim.is_recording());
+
+        // A scripted session - every input kind the replay covers
+        let mut recorded = Vec::new();
+        for i in 0..200u64 {
+            let time = 1000.0 + i as f64 * DT as f64;
+            match i {
+                0 => sim.set_factions_allied(1, 3, true),
+                5 => assert_eq!(sim.issue_move_orders(&[
+                    MoveOrder { id: 3, x: 60.0, y: 0.0, z: 0.0 },
+                    MoveOrder { id: 4, x: 80.0, y: 10.0, z: 0.0 },
+                ]), 2),
+                10 => {
+                    assert!(sim.assign_group(1, Some(7)) && sim.assign_group(2, Some(7)));
+                    assert_eq!(sim.set_group_target(7, 3), 2);
+                }
+                20 => assert!(sim.lock_target(5, 4)),
+                30 => sim.add_unit(make_ship(6, 2, 200.0, 10.0), time).unwrap(),
+                40 => { sim.update_positions(&[PositionUpdate { id: 6, x: 90.0, y: 0.0, z: 0.0, clear_target: true }]); }
+                50 => {
+                    assert_eq!(sim.set_unit_power(1, 0.2, 0.6, 0.2), Ok(true));
+                    assert!(sim.set_retreat_threshold(4, 0.5));
+                    let mut faction = FactionConfig::new(2);
+                    faction.damage_multiplier = 1.5;
+                    sim.set_faction_config(faction);
+                }
+                60 => { sim.set_group_stance(7, Stance::Defensive); }
+                70 => {
+                    let config = BattleConfig { retarget_interval: Some(5), ..sim.config().clone() };
+                    sim.set_config(config).unwrap();
+                    sim.set_objectives(vec![(2, BattleObjective::SurviveFor(180))]);
+                    assert!(sim.add_resource_node(ResourceNode {
+                        id: 1, pos_x: 0.0, pos_y: 0.0, pos_z: 0.0, control_radius: 100.0, resource_per_tick: 1.0, controlled_by: None,
+                    }));
+                }
+                80 => assert!(sim.remove_unit(2)),
+                100 => sim.set_factions_allied(1, 3, false),
+                _ => {}
+            }
+            recorded.push(untimed_json(&sim.simulate_tick(DT, time)));
+        }
+        let events = |sim: &BattleSimulator| serde_json::to_string(&sim.combat_log().since(0, usize::MAX)).unwrap();
+        assert!(sim.combat_log().len() > 20);
+
+        let replay = ReplayRecorder::import(&sim.export_replay().unwrap()).unwrap();
+        assert!(!replay.config.record_replay && replay.alliances.is_empty());
+        let grid = replay.config.spatial_grid();
+        let mut playback = BattleSimulator::from_replay(replay, grid);
+        assert!(!playback.is_recording());
+
+        let mut played = Vec::new();
+        while let Some(result) = playback.step_replay() {
+            played.push(untimed_json(&result));
+        }
+        assert_eq!(played, recorded);
+        assert_eq!(events(&playback), events(&sim));
+        assert_eq!(serde_json::to_string(playback.get_units()).unwrap(), serde_json::to_string(sim.get_units()).unwrap());
+        assert!(playback.get_unit(2).unwrap().is_withdrawn());
+        // Mid-battle config, objectives and nodes came along too
+        assert_eq!(playback.config().retarget_interval, Some(5));
+        assert_eq!(playback.completed_objectives(), sim.completed_objectives());
+        assert!(!sim.completed_objectives().is_empty());
+        assert_eq!(playback.faction_resources(), sim.faction_resources());
+        assert!(!sim.faction_resources().is_empty());
+    }
+
+    #[test]
+    fn test_deterministic_mode_reproduces_battle() {
+        let battle = |seed: u64| {
+            let units: Vec<BattleUnit> = (1..=6).map(|id| {
+                let mut ship = make_ship(id, id % 2 + 1, id as f32 * 15.0, 10.0);
+                ship.weapons[0].cooldown = 2.0; // last_fired 0 - jittered
+                ship
+            }).collect();
+            let config = BattleConfig {
+                mode: SimulationMode::Deterministic { seed },
+                ..Default::default()
+            };
+            let mut sim = BattleSimulator::with_config(units, 1000.0, SpatialGrid::new(DEFAULT_CELL_SIZE), config);
+            let mut log = Vec::new();
+            for i in 0..60 {
+                let time = 1000.0 + i as f64 * DT as f64;
+                if i == 10 {
+                    let mut late = make_ship(7, 1, 40.0, 10.0);
+                    late.weapons[0].cooldown = 2.0;
+                    sim.add_unit(late, time).unwrap();
+                }
+                log.push(untimed_json(&sim.simulate_tick(DT, time)));
+            }
+            let jitter: Vec<f64> = sim.get_units().iter().map(|u| u.weapons[0].last_fired).collect();
+            (log, jitter)
+        };
+
+        let (log_a, jitter_a) = battle(42);
+        let (log_b, jitter_b) = battle(42);
+        assert_eq!(log_a, log_b);
+        assert_eq!(jitter_a, jitter_b);
+        let (_, jitter_c) = battle(7);
+        assert_ne!(jitter_a, jitter_c);
+    }
+
+    #[test]
+    fn test_shield_pierce_and_bonus() {
+        // One 100-damage shot the armor doesn't weaken, hull hits reduced by 5
+        let first_shot = |pierce: f32, bonus: f32, shield: f32| {
+            let mut attacker = make_ship(1, 1, 0.0, 100.0);
+            attacker.weapons[0].tag = "TORPEDO".to_string();
+            attacker.weapons[0].last_fired = 900.0;
+            attacker.weapons[0].target_armor_max = ArmorClass::Super;
+            attacker.weapons[0].shield_pierce = pierce;
+            attacker.weapons[0].shield_damage_bonus = bonus;
+            let mut target = make_target_dummy(2, 50.0);
+            target.armor = ArmorClass::Super;
+            target.flat_armor = Some(5.0);
+            target.max_shield = 1000.0;
+            target.shield = shield;
+            let mut sim = BattleSimulator::new(vec![attacker, target], 1000.0);
+            let result = sim.simulate_tick(DT, 1000.0);
+            assert_eq!(result.weapons_fired.len(), 1);
+            let target = &sim.get_units()[1];
+            assert_eq!(sim.get_units()[0].damage_dealt, 100.0);
+            (target.shield, target.max_hp - target.hp)
+        };
+
+        // Half goes straight to hull, armor only on that half
+        assert_eq!(first_shot(0.5, 1.0, 1000.0), (950.0, 45.0));
+        // Bonus doubles what the shield loses
+        assert_eq!(first_shot(0.0, 2.0, 1000.0), (800.0, 0.0));
+        // 30 shield costs 15 raw at 2x - the other 85 reaches the hull
+        assert_eq!(first_shot(0.0, 2.0, 30.0), (0.0, 80.0));
+        // No modifiers - unchanged shield-first behaviour
+        assert_eq!(first_shot(0.0, 1.0, 30.0), (0.0, 65.0));
+    }
+
+    #[test]
+    fn test_reinforcements_keep_battle_going() {
+        let battle = || {
+            let mut victim = make_ship(2, 2, 50.0, 0.0);
+            victim.max_hp = 190.0;
+            victim.hp = 190.0;
+            // Seeded so cooldown jitter can't push the end past tick 200
+            let config = BattleConfig {
+                mode: SimulationMode::Deterministic { seed: 11 },
+                ..Default::default()
+            };
+            BattleSimulator::with_config(vec![make_ship(1, 1, 0.0, 20.0), victim], 1000.0, SpatialGrid::new(DEFAULT_CELL_SIZE), config)
+        };
+
+        // On its own the battle is over before tick 200
+        let mut sim = battle();
+        let ended_at = run(&mut sim, 400).len();
+        assert!(ended_at > 150 && ended_at <= 200, "ended at {}", ended_at);
+
+        let mut sim = battle();
+        let mut wave = make_ship(3, 2, 60.0, 0.0);
+        wave.max_hp = 1000.0;
+        wave.hp = 1000.0;
+        sim.schedule_reinforcements(150, vec![wave]).unwrap();
+        assert_eq!(sim.pending_reinforcements(), 1);
+
+        let results = run(&mut sim, 250);
+        assert_eq!(results.len(), 250);
+        assert!(!sim.is_battle_ended());
+        assert_eq!(sim.pending_reinforcements(), 0);
+        assert_eq!(results[149].spawned, vec![3]);
+        assert!(results.iter().enumerate().all(|(i, r)| i == 149 || r.spawned.is_empty()));
+        assert!(sim.get_units()[2].damage_taken > 0.0);
+
+        // A tick that's already gone spawns on the next one
+        sim.schedule_reinforcements(5, vec![make_ship(4, 2, 70.0, 0.0)]).unwrap();
+        let result = sim.simulate_tick(DT, 1100.0);
+        assert_eq!(result.spawned, vec![4]);
+        assert_eq!(sim.get_units().len(), 4);
+    }
+
+    #[test]
+    fn test_reinforcement_wave_flips_a_losing_battle() {
+        let battle = || {
+            let config = BattleConfig {
+                mode: SimulationMode::Deterministic { seed: 5 },
+                ..Default::default()
+            };
+            let mut defender = make_ship(2, 2, 60.0, 5.0);
+            defender.max_hp = 300.0;
+            defender.hp = 300.0;
+            let mut attacker = make_ship(1, 1, 0.0, 30.0);
+            attacker.max_hp = 400.0;
+            attacker.hp = 400.0;
+            BattleSimulator::with_config(vec![attacker, defender], 1000.0, SpatialGrid::new(DEFAULT_CELL_SIZE), config)
+        };
+
+        let mut sim = battle();
+        run(&mut sim, 2000);
+        assert_eq!(sim.get_winner(), Some(1));
+
+        // Same battle, with help for faction 2 arriving around (40, 0, 40)
+        let mut sim = battle();
+        let wave: ReinforcementWave = serde_json::from_value(serde_json::json!({
+            "arrival_tick": 20,
+            "units": [make_ship(3, 9, 0.0, 30.0), make_ship(4, 9, 0.0, 30.0), make_ship(5, 9, 0.0, 30.0)],
+            "faction_id": 2,
+            "spawn": [40.0, 0.0, 40.0],
+        })).unwrap();
+        sim.schedule_wave(wave).unwrap();
+
+        let results = run(&mut sim, 2000);
+        assert_eq!(results[19].reinforcements_arrived, vec![(2, vec![3, 4, 5])]);
+        assert!(results.iter().enumerate().all(|(i, r)| i == 19 || r.reinforcements_arrived.is_empty()));
+        assert_eq!(sim.get_winner(), Some(2));
+
+        // Spread around the spawn point (nobody steers them afterwards)
+        let from_spawn = |id| {
+            let unit = sim.get_unit(id).unwrap();
+            assert_eq!(unit.faction_id, 2);
+            ((unit.pos_x - 40.0).powi(2) + unit.pos_y.powi(2) + (unit.pos_z - 40.0).powi(2)).sqrt()
+        };
+        assert!(from_spawn(3) < 1e-3);
+        assert!((from_spawn(4) - DEFAULT_REINFORCEMENT_SPACING).abs() < 1e-3);
+        assert!((from_spawn(5) - DEFAULT_REINFORCEMENT_SPACING * 2f32.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_reinforcements_trigger_local_retarget() {
+        // Locked on an unarmed dummy until an armed ship arrives next to it
+        let attacker = make_ship(1, 1, 0.0, 20.0);
+        let mut far_attacker = make_ship(2, 1, 5000.0, 20.0);
+        far_attacker.view_range = 100.0;
+        let mut dummy = make_target_dummy(3, 90.0);
+        dummy.max_hp = 10000.0;
+        dummy.hp = 10000.0;
+        let mut sim = BattleSimulator::new(vec![attacker, far_attacker, dummy], 1000.0);
+        sim.simulate_tick(DT, 1000.0);
+        assert_eq!(sim.get_units()[0].target_id, Some(3));
+
+        sim.schedule_reinforcements(7, vec![make_ship(4, 2, 50.0, 20.0)]).unwrap();
+        for i in 1..7 {
+            sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+        }
+        assert_eq!(sim.tick, 7);
+        // Switched on arrival, not at the next retarget_interval
+        assert_eq!(sim.get_units()[0].target_id, Some(4));
+        assert_ne!(sim.get_units()[1].target_id, Some(4));
+    }
+
+    #[test]
+    fn test_low_tracking_gun_misses_fast_frigate() {
+        // Hits out of shots from the battleship against a frigate orbiting at `speed`
+        let duel = |speed: f32| {
+            let mut battleship = make_ship(1, 1, 0.0, 100.0);
+            battleship.max_speed = 0.0;
+            battleship.weapons[0].cooldown = 0.1;
+            battleship.weapons[0].max_range = 1000.0;
+            battleship.max_weapon_range = 1000.0;
+            battleship.weapons[0].tracking = 0.2;
+            let mut frigate = make_ship(2, 2, 500.0, 0.0);
+            frigate.signature_radius = 20.0;
+            frigate.max_hp = 1.0e9;
+            frigate.hp = 1.0e9;
+            frigate.max_speed = speed;
+            frigate.ai_controlled = true;
+            frigate.orbit_mode = true;
+            frigate.view_range = 1000.0;
+            frigate.weapons[0].optimal_range = 500.0;
+            frigate.weapons[0].max_range = 600.0;
+            frigate.max_weapon_range = 600.0;
+
+            let mut sim = BattleSimulator::new(vec![battleship, frigate], 1000.0);
+            sim.set_config(BattleConfig {
+                mode: SimulationMode::Deterministic { seed: 3 },
+                ..Default::default()
+            }).unwrap();
+            let shots: Vec<WeaponFired> = run(&mut sim, 200).into_iter()
+                .flat_map(|r| r.weapons_fired)
+                .filter(|f| f.attacker_id == 1)
+                .collect();
+            let hits = shots.iter().filter(|f| f.hit).count();
+            let battleship = &sim.get_units()[0];
+            assert_eq!(battleship.shots_fired as usize, shots.len());
+            assert_eq!(battleship.shots_hit as usize, hits);
+            (hits, shots.len())
+        };
+
+        let (hits, shots) = duel(0.0);
+        assert!(shots > 50);
+        assert_eq!(hits, shots);
+
+        // 200/s at 500 is 0.4 rad/s - twice the tracking on half the signature
+        let (hits, shots) = duel(200.0);
+        assert!(shots > 50);
+        assert!(hits * 20 < shots, "{} of {} hit", hits, shots);
+    }
+
+    #[test]
+    fn test_weapon_fired_carries_positions() {
+        let mut gun = make_ship(1, 1, 0.0, 10.0);
+        gun.max_speed = 0.0;
+        gun.pos_y = 5.0;
+        let mut dummy = make_target_dummy(2, 60.0);
+        dummy.pos_z = -3.0;
+        let mut sim = BattleSimulator::new(vec![gun.clone(), dummy.clone()], 1000.0);
+        let fired = run(&mut sim, 100).into_iter().flat_map(|r| r.weapons_fired).next().unwrap();
+
+        let json = serde_json::to_value(&fired).unwrap();
+        assert_eq!(json["attackerId"], 1);
+        assert_eq!((json["ax"].as_f64(), json["ay"].as_f64(), json["az"].as_f64()), (Some(0.0), Some(5.0), Some(0.0)));
+        assert_eq!((json["tx"].as_f64(), json["ty"].as_f64(), json["tz"].as_f64()), (Some(60.0), Some(0.0), Some(-3.0)));
+        assert!(json.get("missOffset").is_none());
+
+        // A miss ends off to the side of the line of fire, not at the target
+        let units = sim.get_units();
+        let miss = WeaponFired { hit: false, ..fired.clone() }.with_positions(&units[0], &units[1], 7);
+        let (ox, oy, oz) = miss.miss_offset.unwrap();
+        let (dx, dy, dz) = (60.0, -5.0, -3.0);
+        assert!((ox * dx + oy * dy + oz * dz).abs() < 1e-3);
+        assert!((ox * ox + oy * oy + oz * oz).sqrt() >= 2.0);
+        let json = serde_json::to_value(&miss).unwrap();
+        assert_eq!(json["missOffset"][0].as_f64(), Some(ox as f64));
+        let decoded: WeaponFired = bincode::deserialize(&bincode::serialize(&miss).unwrap()).unwrap();
+        assert_eq!(decoded, miss);
+
+        // Turned off, the positions are left out of the JSON entirely
+        let mut sim = BattleSimulator::new(vec![gun, dummy], 1000.0);
+        sim.set_config(BattleConfig { include_fire_positions: false, ..Default::default() }).unwrap();
+        let fired = run(&mut sim, 100).into_iter().flat_map(|r| r.weapons_fired).next().unwrap();
+        assert_eq!(fired.ax, None);
+        let json = serde_json::to_value(&fired).unwrap();
+        assert!(json.get("ax").is_none() && json.get("tz").is_none());
+        let decoded: WeaponFired = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, fired);
+    }
+
+    #[test]
+    fn test_external_movement_counts_for_hit_chance() {
+        let mut gun = make_ship(1, 1, 0.0, 100.0);
+        gun.weapons[0].max_range = 1000.0;
+        gun.max_weapon_range = 1000.0;
+        gun.weapons[0].tracking = 0.2;
+        let mut frigate = make_target_dummy(2, 500.0);
+        frigate.signature_radius = 20.0;
+        let mut sim = BattleSimulator::new(vec![gun, frigate], 1000.0);
+        sim.simulate_tick(DT, 1000.0);
+
+        // Player moves the frigate 10 per tick (200/s) across the line of fire
+        let mut misses = 0;
+        for i in 1..=100 {
+            sim.update_single_position(2, 500.0, 0.0, i as f32 * 10.0, false);
+            let result = sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64);
+            misses += result.weapons_fired.iter().filter(|f| !f.hit).count();
+        }
+        assert!((sim.get_units()[1].external_speed - 200.0).abs() < 1.0);
+        assert!(misses > 0);
+
+        // Stopped updating - after EXTERNAL_SPEED_WINDOW it's stationary again
+        let now = 1000.0 + 101.0 * DT as f64;
+        assert!(sim.get_units()[1].speed(now) > 100.0);
+        assert_eq!(sim.get_units()[1].speed(now + 2.0), 0.0);
+    }
+
+    #[test]
+    fn test_allied_deaths_break_morale() {
+        let mut observer = make_ship(1, 1, 0.0, 0.0);
+        observer.max_hp = 1.0e6;
+        observer.hp = 1.0e6;
+        observer.morale_radius = 100.0;
+        observer.morale_loss_per_death = 30.0;
+        let mut units = vec![observer];
+        for id in 2..=4 {
+            let mut ally = make_target_dummy(id, id as f32 * 10.0);
+            ally.faction_id = 1;
+            ally.hp = 1.0;
+            units.push(ally);
+        }
+        // Too far away to matter
+        let mut distant = make_target_dummy(5, 500.0);
+        distant.faction_id = 1;
+        distant.hp = 1.0;
+        units.push(distant);
+        units.push(make_ship(6, 2, 90.0, 100.0));
+        units[5].weapons[0].max_range = 1000.0;
+        units[5].max_weapon_range = 1000.0;
+        let mut sim = BattleSimulator::new(units, 1000.0);
+
+        let mut time = 1000.0;
+        let mut kill = |sim: &mut BattleSimulator, id: u32| {
+            time += 2.0;
+            assert!(sim.manually_fire(6, id, "LASER", time).is_some());
+            let result = sim.simulate_tick(DT, time);
+            assert!(result.destroyed.contains(&id));
+            result
+        };
+
+        assert!(kill(&mut sim, 5).morale_events.is_empty());
+        assert_eq!(kill(&mut sim, 2).morale_events, vec![(1, 70.0)]);
+        assert_eq!(sim.get_units()[0].accuracy_multiplier(), 1.0);
+        kill(&mut sim, 3);
+        assert_eq!(sim.get_units()[0].morale, 40.0);
+        assert_eq!(sim.get_units()[0].accuracy_multiplier(), 0.5);
+        assert!(!sim.get_units()[0].retreating);
+
+        // Below 25 it retreats even without a retreat_hp_fraction
+        let result = kill(&mut sim, 4);
+        assert_eq!(result.morale_events, vec![(1, 10.0)]);
+        assert!(result.retreating_units.contains(&1));
+    }
+
+    #[test]
+    fn test_damage_and_kills_earn_veterancy() {
+        let mut veteran = make_ship(1, 1, 0.0, 100.0);
+        veteran.max_hp = 100.0;
+        veteran.hp = 50.0;
+        veteran.experience = 99.9;
+        let mut weak = make_target_dummy(3, -50.0);
+        weak.hp = 1.0;
+        weak.xp_per_kill = 0.0;
+        let units = vec![veteran, make_target_dummy(2, 50.0), weak];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let cooldown = sim.get_units()[0].weapons[0].cooldown;
+
+        assert!(sim.manually_fire(1, 2, "LASER", 1002.0).is_some());
+        let result = sim.simulate_tick(DT, 1002.05);
+        assert_eq!(result.level_ups, vec![(1, 1)]);
+        let unit = &sim.get_units()[0];
+        assert!((unit.experience - (99.9 + unit.damage_dealt * 0.1)).abs() < 1e-3);
+        assert!((unit.weapons[0].dps - 105.0).abs() < 1e-3);
+        assert!((unit.weapons[0].cooldown - cooldown * 0.97).abs() < 1e-6);
+        assert_eq!((unit.max_hp, unit.hp), (102.0, 52.0));
+
+        // A big kill bonus can jump several levels at once, capped at 5
+        sim.units[0].xp_per_kill = 5000.0;
+        assert!(sim.manually_fire(1, 3, "LASER", 1010.0).is_some());
+        let result = sim.simulate_tick(DT, 1010.05);
+        assert!(result.destroyed.contains(&3));
+        assert_eq!(result.level_ups, vec![(1, 5)]);
+        let veterancy = sim.get_unit_veterancy(1).unwrap();
+        assert_eq!((veterancy.level, veterancy.next_level_xp), (5, None));
+        assert!((sim.get_units()[0].weapons[0].dps - 100.0 * 1.05f32.powi(5)).abs() < 1e-2);
+        assert_eq!(sim.get_unit_veterancy(2).unwrap().next_level_xp, Some(100.0));
+        assert!(sim.get_unit_veterancy(99).is_none());
+    }
+
+    #[test]
+    fn test_weapon_stats_split_by_weapon() {
+        let mut ship = make_ship(1, 1, 0.0, 10.0);
+        ship.weapons[0].cooldown = 1.0;
+        ship.weapons.push(Weapon::builder().tag("RAIL").dps(40.0).cooldown(2.0).range(80.0, 100.0).build());
+        let mut target = make_target_dummy(2, 50.0);
+        target.max_hp = 500.0;
+        target.hp = 500.0;
+        let mut sim = BattleSimulator::new(vec![ship, target], 1000.0);
+
+        run(&mut sim, 60);
+        let stats = sim.get_weapon_stats();
+        assert_eq!(stats.keys().copied().collect::<Vec<_>>(), vec![1]);
+        let (laser, rail) = (stats[&1]["LASER"], stats[&1]["RAIL"]);
+        assert!(laser.shots_hit >= 2 && rail.shots_hit >= 1);
+        assert_eq!((laser.shots_fired, rail.shots_fired), (laser.shots_hit, rail.shots_hit));
+        assert_eq!(laser.damage_dealt, laser.shots_hit as f32 * 10.0);
+        assert_eq!(rail.damage_dealt, rail.shots_hit as f32 * 40.0);
+        let unit = &sim.get_units()[0];
+        assert_eq!(unit.shots_fired, laser.shots_fired + rail.shots_fired);
+        assert_eq!(unit.damage_dealt, laser.damage_dealt + rail.damage_dealt);
+
+        // The kill goes to whichever weapon landed the last of the target's hp
+        let report = sim.run_to_completion(DT, 1000);
+        assert_eq!(report.reason, CompletionReason::Elimination);
+        let totals = &report.weapons;
+        assert_eq!(totals["LASER"].kills + totals["RAIL"].kills, 1);
+        assert_eq!(totals["LASER"].damage_dealt + totals["RAIL"].damage_dealt, 500.0);
+        assert_eq!(totals["LASER"], sim.get_weapon_stats()[&1]["LASER"]);
+    }
+
+    #[test]
+    fn test_carrier_launch_and_dock() {
+        let mut carrier = make_target_dummy(1, 0.0);
+        carrier.faction_id = 1;
+        carrier.hangar_capacity = 1;
+        carrier.hangar_contents = vec![2];
+        let mut fighter = make_ship(2, 1, 0.0, 10.0);
+        fighter.is_in_hangar = true;
+        let units = vec![carrier, fighter, make_ship(3, 2, 60.0, 10.0)];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+
+        // Docked: never fires and never gets picked as a target
+        for result in run(&mut sim, 40) {
+            assert!(result.weapons_fired.iter().all(|f| f.attacker_id != 2 && f.target_id != 2));
+        }
+
+        assert_eq!(sim.launch_fighter(1, 3, 1002.0), Err(HangarError::NotDocked(3)));
+        assert_eq!(sim.launch_fighter(1, 2, 1002.0), Ok(()));
+        assert!(sim.get_units()[0].hangar_contents.is_empty());
+        let (carrier, fighter) = (&sim.get_units()[0], &sim.get_units()[1]);
+        assert!(!fighter.is_in_hangar);
+        assert!((carrier.distance_sq(fighter).sqrt() - (carrier.radius + fighter.radius)).abs() < 1e-3);
+        let results: Vec<TickResult> = (0..40).map(|i| sim.simulate_tick(DT, 1002.0 + i as f64 * DT as f64)).collect();
+        assert_eq!(results[0].launched, vec![2]);
+        assert!(results[1].launched.is_empty());
+        assert!(results.iter().any(|r| r.weapons_fired.iter().any(|f| f.attacker_id == 2)));
+
+        assert_eq!(sim.dock_fighter(1, 3), Err(HangarError::NoFighter(3)));
+        assert_eq!(sim.dock_fighter(1, 2), Ok(()));
+        assert_eq!(sim.dock_fighter(1, 2), Err(HangarError::AlreadyDocked(2)));
+        assert!(sim.get_units().iter().all(|u| u.target_id != Some(2)));
+        assert_eq!(sim.simulate_tick(DT, 1004.0).docked, vec![2]);
+        sim.units[1].is_in_hangar = false;
+        assert_eq!(sim.dock_fighter(1, 2), Err(HangarError::HangarFull(1)));
+        sim.units[1].is_in_hangar = true;
+
+        // Losing the carrier puts its fighters back on the battlefield
+        assert!(sim.remove_unit(1));
+        let result = sim.simulate_tick(DT, 1004.05);
+        assert_eq!(result.launched, vec![2]);
+        assert!(sim.get_units()[1].in_battle());
+        assert_eq!(sim.get_winner(), None);
+    }
+
+    #[test]
+    fn test_supply_limits_shots_until_resupplied() {
+        let mut gunship = make_ship(1, 1, 0.0, 10.0);
+        gunship.weapons[0].ammo_per_shot = 2.0;
+        gunship.weapons[0].ammo_remaining = 6.0;
+        let mut sim = BattleSimulator::new(vec![gunship, make_target_dummy(2, 50.0)], 1000.0);
+
+        let results = run(&mut sim, 200);
+        let shots = results.iter().map(|r| r.weapons_fired.len()).sum::<usize>();
+        assert_eq!(shots, 3);
+        assert!(results[100..].iter().all(|r| r.weapons_fired.is_empty()));
+        let ammo = &sim.get_weapon_ammo(1).unwrap()[0];
+        assert_eq!((ammo.ammo_remaining, ammo.ammo_capacity), (0.0, 6.0));
+
+        // A supply ship in range refills it (capacity caps the top-up)
+        let tender = BattleUnit {
+            resupply_rate: 4.0,
+            resupply_range: 50.0,
+            ..BattleUnit::builder().id(3).faction(1).position(-20.0, 0.0, 0.0).as_ship().build().unwrap()
+        };
+        sim.add_units(vec![tender], 1010.0).unwrap();
+        let fired = (0..100)
+            .map(|i| sim.simulate_tick(DT, 1010.0 + i as f64 * DT as f64))
+            .filter(|r| !r.weapons_fired.is_empty())
+            .count();
+        assert!(fired >= 3, "{}", fired);
+        assert!(sim.get_units()[0].weapons[0].ammo_remaining <= 6.0);
+        assert!(sim.get_weapon_ammo(99).is_none());
+    }
+
+    #[test]
+    fn test_commander_restores_morale() {
+        let shaken = |id: u32, faction: u32, x: f32| {
+            let mut unit = make_target_dummy(id, x);
+            unit.faction_id = faction;
+            unit.morale = 40.0;
+            unit.morale_radius = 100.0;
+            unit.morale_regen_rate = 1.0;
+            unit
+        };
+        let mut commander = make_target_dummy(1, 0.0);
+        commander.faction_id = 1;
+        commander.is_commander = true;
+        let units = vec![
+            commander,
+            shaken(2, 1, 50.0),
+            shaken(3, 1, 500.0),
+            shaken(4, 2, 60.0),
+        ];
+        let mut sim = BattleSimulator::new(units, 1000.0);
+        let results = run(&mut sim, 20);
+
+        // One second: regen alone, plus COMMANDER_MORALE_REGEN for the ally in range
+        let morale: Vec<f32> = sim.get_units().iter().map(|u| u.morale).collect();
+        assert!((morale[1] - 46.0).abs() < 1e-3);
+        assert!((morale[2] - 41.0).abs() < 1e-3);
+        assert!((morale[3] - 41.0).abs() < 1e-3);
+        assert!(results.iter().all(|r| r.morale_events.len() == 3));
+    }
+
+    #[test]
+    fn test_long_pause_does_not_alpha_strike() {
+        let mut attacker = make_ship(1, 1, 0.0, 10.0);
+        attacker.weapons = (0..10).map(|_| attacker.weapons[0].clone()).collect();
+        let mut target = make_target_dummy(2, 50.0);
+        target.max_hp = 1.0e6;
+        target.hp = 1.0e6;
+        let mut sim = BattleSimulator::new(vec![attacker, target], 1000.0);
+        // One weapon comes off its 5s cooldown every half second from 1000.5
+        for (i, weapon) in sim.units[0].weapons.iter_mut().enumerate() {
+            weapon.cooldown = 5.0;
+            weapon.last_fired = 995.5 + i as f64 * 0.5;
+        }
+        for i in 1..=5 {
+            assert!(sim.simulate_tick(DT, 1000.0 + i as f64 * DT as f64).weapons_fired.is_empty());
+        }
+
+        sim.pause();
+        for i in 0..10 {
+            sim.simulate_tick(DT, 1100.0 + i as f64 * 100.0);
+        }
+        sim.resume();
+
+        // 1000 host seconds later the battle carries on from 1000.25
+        let mut fired = Vec::new();
+        for i in 1..=10 {
+            let result = sim.simulate_tick(DT, 2000.0 + i as f64 * DT as f64);
+            fired.push(result.weapons_fired.len());
+        }
+        assert!((sim.simulated_time() - 1000.75).abs() < 1e-6);
+        // Only the weapon due at 1000.5
+        assert_eq!(fired.iter().sum::<usize>(), 1);
+        assert_eq!(fired[4], 1);
+
+        // Manual fire runs on simulated time too - 1000.85, next weapon due at 1001
+        assert!(sim.manually_fire(1, 2, "LASER", 2000.6).is_none());
+        assert!(sim.manually_fire(1, 2, "LASER", 2000.8).is_some());
+        assert_eq!(sim.get_units()[0].weapons[1].last_fired, 2000.8 - sim.time_offset);
+    }
+
+    #[test]
+    fn test_step_runs_one_tick_while_paused() {
+        let mut sim = BattleSimulator::new(
+            vec![make_ship(1, 1, 0.0, 10.0), make_target_dummy(2, 50.0)],
+            1000.0,
+        );
+        sim.set_config(BattleConfig { tick_rate: 10.0, ..Default::default() }).unwrap();
+        // A rate with no dt is refused and the old one kept
+        assert_eq!(sim.set_config(BattleConfig { tick_rate: 0.0, ..Default::default() }), Err(ConfigError::TickRate(0.0)));
+        assert_eq!(sim.config().tick_rate, 10.0);
+        sim.pause();
+
+        let result = sim.step();
+        assert_eq!((result.tick, sim.tick), (1, 1));
+        assert!(sim.is_paused());
+        assert!((sim.simulated_time() - 1000.1).abs() < 1e-6);
+        assert_eq!(sim.simulate_tick(DT, 5000.0).tick, 1);
+
+        sim.step();
+        assert!((sim.simulated_time() - 1000.2).abs() < 1e-6);
+        sim.resume();
+        sim.simulate_tick(DT, 9000.0);
+        assert!((sim.simulated_time() - 1000.25).abs() < 1e-6);
+        sim.simulate_tick(DT, 9000.05);
+        assert!((sim.simulated_time() - 1000.3).abs() < 1e-6);
+        assert_eq!(sim.tick, 4);
+    }
+
+    #[test]
+    fn test_duplicate_and_invalid_units_rejected() {
+        let dup = BattleSimulator::try_new(vec![make_ship(1, 1, 0.0, 10.0), make_ship(1, 2, 50.0, 10.0)], 1000.0);
+        assert_eq!(dup.err(), Some(ValidationError::DuplicateId { id: 1 }));
+
+        let mut sim = BattleSimulator::try_new(
+            vec![make_ship(1, 1, 0.0, 10.0), make_ship(2, 2, 50.0, 10.0)],
+            1000.0,
+        ).unwrap();
+        sim.remove_unit(2);
+        // Removed units keep their id
+        assert_eq!(sim.add_unit(make_ship(2, 2, 60.0, 10.0), 1000.0), Err(ValidationError::DuplicateId { id: 2 }));
+
+        sim.schedule_reinforcements(10, vec![make_ship(3, 2, 70.0, 10.0)]).unwrap();
+        assert_eq!(
+            sim.schedule_reinforcements(20, vec![make_ship(3, 2, 80.0, 10.0)]),
+            Err(ValidationError::DuplicateId { id: 3 })
+        );
+        let mut lost = make_ship(4, 2, 0.0, 10.0);
+        lost.pos_x = f32::NAN;
+        // One bad unit rejects the whole batch
+        assert_eq!(
+            sim.add_units(vec![make_ship(5, 2, 90.0, 10.0), lost], 1000.0),
+            Err(ValidationError::NonFinite { id: 4, field: "pos_x" })
+        );
+        assert_eq!(sim.get_units().len(), 2);
+        assert_eq!(sim.pending_reinforcements(), 1);
+    }
+}
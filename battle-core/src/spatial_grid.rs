@@ -1,4 +1,139 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use serde::Serialize;
+
+use crate::spatial_index::SpatialIndex;
+use crate::log_at;
+
+/// Default cell size used when there is nothing to tune against
+pub const DEFAULT_CELL_SIZE: f32 = 100.0;
+
+/// Cell coordinates are clamped to +/- this, so absurd positions (1e30, inf)
+/// land in edge cells instead of overflowing i32 key math
+const MAX_CELL_COORD: i32 = 1 << 24;
+
+/// Auto-tuned cell size bounds - tiny cells make range queries scan many cells
+const MIN_AUTO_CELL_SIZE: f32 = 50.0;
+const MAX_AUTO_CELL_SIZE: f32 = 10000.0;
+
+/// Fixed-key hasher so cell iteration (and so query result order and
+/// tie-breaks in targeting) is the same on every run - RandomState isn't
+type CellMap = HashMap<(i32, i32, i32), Cell, BuildHasherDefault<DefaultHasher>>;
+
+/// Units in one grid cell, positions stored SoA so distance checks can run
+/// four at a time (see the `simd` feature)
+#[derive(Debug, Clone, Default)]
+struct Cell {
+    indices: Vec<usize>,
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    zs: Vec<f32>,
+}
+
+impl Cell {
+    #[inline]
+    fn push(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        self.indices.push(index);
+        self.xs.push(x);
+        self.ys.push(y);
+        self.zs.push(z);
+    }
+
+    fn clear(&mut self) {
+        self.indices.clear();
+        self.xs.clear();
+        self.ys.clear();
+        self.zs.clear();
+    }
+
+    fn remove(&mut self, index: usize) {
+        if let Some(i) = self.indices.iter().position(|&idx| idx == index) {
+            self.indices.swap_remove(i);
+            self.xs.swap_remove(i);
+            self.ys.swap_remove(i);
+            self.zs.swap_remove(i);
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Scalar distance filter for entries [start..] - the whole cell without SIMD
+    #[inline]
+    fn collect_in_radius_from(&self, start: usize, x: f32, y: f32, z: f32, radius_sq: f32, out: &mut Vec<(usize, f32)>) {
+        for i in start..self.len() {
+            let dist_sq = (self.xs[i] - x).powi(2) + (self.ys[i] - y).powi(2) + (self.zs[i] - z).powi(2);
+            if dist_sq <= radius_sq {
+                out.push((self.indices[i], dist_sq));
+            }
+        }
+    }
+
+    /// Append (index, distance_sq) for every unit within radius
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    #[inline]
+    fn collect_in_radius(&self, x: f32, y: f32, z: f32, radius_sq: f32, out: &mut Vec<(usize, f32)>) {
+        self.collect_in_radius_from(0, x, y, z, radius_sq, out);
+    }
+
+    /// Append (index, distance_sq) for every unit within radius - SSE, 4 units per iteration
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn collect_in_radius(&self, x: f32, y: f32, z: f32, radius_sq: f32, out: &mut Vec<(usize, f32)>) {
+        use std::arch::x86_64::*;
+
+        let full = self.len() / 4 * 4;
+        // SAFETY: SSE is part of the x86_64 baseline, and every load reads
+        // lanes i..i+4 with i + 4 <= full <= len of each position vec.
+        unsafe {
+            let (px, py, pz) = (_mm_set1_ps(x), _mm_set1_ps(y), _mm_set1_ps(z));
+            let r = _mm_set1_ps(radius_sq);
+            let mut lanes = [0.0f32; 4];
+
+            for i in (0..full).step_by(4) {
+                let dx = _mm_sub_ps(_mm_loadu_ps(self.xs.as_ptr().add(i)), px);
+                let dy = _mm_sub_ps(_mm_loadu_ps(self.ys.as_ptr().add(i)), py);
+                let dz = _mm_sub_ps(_mm_loadu_ps(self.zs.as_ptr().add(i)), pz);
+                let dist_sq = _mm_add_ps(_mm_add_ps(_mm_mul_ps(dx, dx), _mm_mul_ps(dy, dy)), _mm_mul_ps(dz, dz));
+
+                let mask = _mm_movemask_ps(_mm_cmple_ps(dist_sq, r));
+                if mask != 0 {
+                    _mm_storeu_ps(lanes.as_mut_ptr(), dist_sq);
+                    for (lane, &d) in lanes.iter().enumerate() {
+                        if mask & (1 << lane) != 0 {
+                            out.push((self.indices[i + lane], d));
+                        }
+                    }
+                }
+            }
+        }
+
+        self.collect_in_radius_from(full, x, y, z, radius_sq, out);
+    }
+}
+
+/// Occupancy snapshot of a SpatialGrid, for diagnostics
+///
+/// Per-cell figures cover occupied cells only - empty cells kept around for
+/// reuse by clear() aren't counted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GridStats {
+    pub cell_count: usize,
+    pub total_entries: usize,
+    pub min_per_cell: usize,
+    pub avg_per_cell: f32,
+    pub max_per_cell: usize,
+    pub cell_size: f32,
+    /// Largest range a single query can cover (see max_query_range)
+    pub max_query_range: f32,
+    /// Units in the battle whose max_weapon_range is beyond max_query_range -
+    /// filled in by the simulator, the grid doesn't know about weapons
+    pub units_beyond_query_range: usize,
+}
 
 /// High-performance spatial grid for O(k) nearest-neighbor queries
 /// 
@@ -8,7 +143,7 @@ use std::collections::HashMap;
 pub struct SpatialGrid {
     cell_size: f32,
     inv_cell_size: f32,
-    cells: HashMap<(i32, i32, i32), Vec<usize>>, // Key: cell coords, Value: unit indices
+    cells: CellMap, // Key: cell coords, Value: units in cell
 }
 
 impl SpatialGrid {
@@ -16,63 +151,220 @@ impl SpatialGrid {
         Self {
             cell_size,
             inv_cell_size: 1.0 / cell_size,
-            cells: HashMap::new(),
+            cells: CellMap::default(),
+        }
+    }
+
+    /// Create a grid with cell size tuned to unit density
+    ///
+    /// cell_size = battlefield_radius / cbrt(unit_count), aiming for a handful
+    /// of units per occupied cell. Clamped to sane bounds.
+    pub fn with_auto_cell_size(unit_count: usize, battlefield_radius: f32) -> Self {
+        Self::new(Self::auto_cell_size(unit_count, battlefield_radius))
+    }
+
+    /// Cell size with_auto_cell_size would pick
+    pub fn auto_cell_size(unit_count: usize, battlefield_radius: f32) -> f32 {
+        if unit_count == 0 || !battlefield_radius.is_finite() || battlefield_radius <= 0.0 {
+            return DEFAULT_CELL_SIZE;
+        }
+        (battlefield_radius / (unit_count as f32).cbrt()).clamp(MIN_AUTO_CELL_SIZE, MAX_AUTO_CELL_SIZE)
+    }
+
+    /// Change cell size, re-inserting everything currently in the grid
+    pub fn resize(&mut self, new_cell_size: f32) {
+        self.cell_size = new_cell_size;
+        self.inv_cell_size = 1.0 / new_cell_size;
+
+        let old_cells = std::mem::take(&mut self.cells);
+        for cell in old_cells.into_values() {
+            for i in 0..cell.len() {
+                self.insert(cell.indices[i], cell.xs[i], cell.ys[i], cell.zs[i]);
+            }
         }
     }
 
     /// Get cell key for position - INLINE for speed
+    ///
+    /// NaN maps to cell 0; huge or infinite coordinates are clamped to the
+    /// outermost cells so neighbour offsets can't overflow.
     #[inline]
     fn get_key(&self, x: f32, y: f32, z: f32) -> (i32, i32, i32) {
-        (
-            (x * self.inv_cell_size).floor() as i32,
-            (y * self.inv_cell_size).floor() as i32,
-            (z * self.inv_cell_size).floor() as i32,
-        )
-    }
-
-    /// Insert unit into grid - O(1)
-    pub fn insert(&mut self, index: usize, x: f32, y: f32, z: f32) {
-        let key = self.get_key(x, y, z);
-        self.cells.entry(key).or_insert_with(Vec::new).push(index);
+        let coord = |v: f32| ((v * self.inv_cell_size).floor() as i32).clamp(-MAX_CELL_COORD, MAX_CELL_COORD);
+        (coord(x), coord(y), coord(z))
     }
 
-    /// Get nearby unit indices - O(k) where k = units in nearby cells
+    /// Call `visit` for every occupied cell within `range` cells of a position
     ///
-    /// Dynamically expands search radius based on range parameter
-    pub fn get_nearby(&self, x: f32, y: f32, z: f32, range: f32) -> Vec<usize> {
+    /// Walks the (2n+1)^3 neighbourhood, or just the occupied cells when that
+    /// is cheaper (huge ranges relative to cell size).
+    #[inline]
+    fn visit_cells(&self, x: f32, y: f32, z: f32, range: f32, mut visit: impl FnMut(&Cell)) {
         let (cx, cy, cz) = self.get_key(x, y, z);
-        let mut result = Vec::new();
 
         // Calculate how many cells to search based on range
         // Add 1 to ensure we cover edge cases
-        let cells_needed = ((range * self.inv_cell_size).ceil() as i32).max(1);
+        let cells_needed = ((range * self.inv_cell_size).ceil() as i32).clamp(1, MAX_CELL_COORD);
+        let span = 2 * cells_needed as u64 + 1;
+
+        if span.saturating_mul(span).saturating_mul(span) > self.cells.len() as u64 {
+            let within = |a: i32, b: i32| (a - b).abs() <= cells_needed;
+            for (&(kx, ky, kz), cell) in &self.cells {
+                if within(kx, cx) && within(ky, cy) && within(kz, cz) {
+                    visit(cell);
+                }
+            }
+            return;
+        }
 
         for dx in -cells_needed..=cells_needed {
             for dy in -cells_needed..=cells_needed {
                 for dz in -cells_needed..=cells_needed {
-                    let key = (cx + dx, cy + dy, cz + dz);
-
-                    if let Some(cell) = self.cells.get(&key) {
-                        for &idx in cell {
-                            result.push(idx);
-                        }
+                    if let Some(cell) = self.cells.get(&(cx + dx, cy + dy, cz + dz)) {
+                        visit(cell);
                     }
                 }
             }
         }
+    }
 
+    /// Insert unit into grid - O(1)
+    pub fn insert(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        let key = self.get_key(x, y, z);
+        self.cells.entry(key).or_default().push(index, x, y, z);
+    }
+
+    /// Remove unit from the cell its position maps to - O(k) in cell size
+    pub fn remove(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        let key = self.get_key(x, y, z);
+        if let Some(cell) = self.cells.get_mut(&key) {
+            cell.remove(index);
+            if cell.len() == 0 {
+                self.cells.remove(&key);
+            }
+        }
+    }
+
+    /// Get nearby unit indices - O(k) where k = units in nearby cells
+    ///
+    /// Dynamically expands search radius based on range parameter
+    pub fn get_nearby(&self, x: f32, y: f32, z: f32, range: f32) -> Vec<usize> {
+        let mut result = Vec::new();
+        self.get_nearby_into(x, y, z, range, &mut result);
+        result
+    }
+
+    /// get_nearby into `out` (cleared first), reusing its allocation
+    pub fn get_nearby_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<usize>) {
+        out.clear();
+        self.visit_cells(x, y, z, range, |cell| out.extend_from_slice(&cell.indices));
+    }
+
+    /// Units actually within a spherical radius, as (index, distance_sq) pairs
+    ///
+    /// get_nearby returns whole cells, so units in the corners of the searched
+    /// block can be up to cell_size * sqrt(3) beyond range. This filters them out.
+    pub fn get_in_radius(&self, x: f32, y: f32, z: f32, radius: f32) -> Vec<(usize, f32)> {
+        let mut result = Vec::new();
+        self.get_in_radius_into(x, y, z, radius, &mut result);
         result
     }
 
-    /// Clear all cells - O(1) (just creates new HashMap)
+    /// get_in_radius into `out` (cleared first), reusing its allocation
+    pub fn get_in_radius_into(&self, x: f32, y: f32, z: f32, radius: f32, out: &mut Vec<(usize, f32)>) {
+        let radius_sq = radius * radius;
+        out.clear();
+        self.visit_cells(x, y, z, radius, |cell| cell.collect_in_radius(x, y, z, radius_sq, out));
+    }
+
+    /// Edge length of a grid cell
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Clear all cells - O(cells)
+    ///
+    /// Cells occupied before the clear keep their (now empty) vectors so the
+    /// per-tick rebuild refills them without allocating; cells that stayed
+    /// empty through a whole rebuild are dropped here.
     pub fn clear(&mut self) {
-        self.cells.clear();
+        self.cells.retain(|_, cell| {
+            let occupied = cell.len() > 0;
+            cell.clear();
+            occupied
+        });
     }
 
-    /// Get statistics
+    /// Get statistics - (occupied cells, units)
     pub fn stats(&self) -> (usize, usize) {
-        let total_units: usize = self.cells.values().map(|v| v.len()).sum();
-        (self.cells.len(), total_units)
+        let occupied = self.cells.values().filter(|c| c.len() > 0).count();
+        let total_units: usize = self.cells.values().map(|c| c.len()).sum();
+        (occupied, total_units)
+    }
+
+    /// Largest range a query is guaranteed to cover - the searched block is
+    /// capped at MAX_CELL_COORD cells either side of the query cell
+    pub fn max_query_range(&self) -> f32 {
+        MAX_CELL_COORD as f32 * self.cell_size
+    }
+
+    /// Cell occupancy for diagnostics - O(cells)
+    pub fn occupancy(&self) -> GridStats {
+        let (cell_count, total_entries) = self.stats();
+        let occupied = || self.cells.values().map(|c| c.len()).filter(|&len| len > 0);
+        GridStats {
+            cell_count,
+            total_entries,
+            min_per_cell: occupied().min().unwrap_or(0),
+            avg_per_cell: if cell_count == 0 { 0.0 } else { total_entries as f32 / cell_count as f32 },
+            max_per_cell: occupied().max().unwrap_or(0),
+            cell_size: self.cell_size,
+            max_query_range: self.max_query_range(),
+            units_beyond_query_range: 0,
+        }
+    }
+}
+
+impl SpatialIndex for SpatialGrid {
+    fn insert(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        SpatialGrid::insert(self, index, x, y, z);
+    }
+
+    fn query_range_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<(usize, f32)>) {
+        self.get_in_radius_into(x, y, z, range, out);
+    }
+
+    fn remove(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        SpatialGrid::remove(self, index, x, y, z);
+    }
+
+    fn clear(&mut self) {
+        SpatialGrid::clear(self);
+    }
+
+    fn get_nearby_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<usize>) {
+        SpatialGrid::get_nearby_into(self, x, y, z, range, out);
+    }
+
+    fn grid_stats(&self) -> Option<GridStats> {
+        Some(self.occupancy())
+    }
+
+    fn tune(&mut self, unit_count: usize, battlefield_radius: f32) {
+        let cell_size = Self::auto_cell_size(unit_count, battlefield_radius);
+        if (cell_size - self.cell_size).abs() > 1.0 {
+            log_at!(Info,
+                "[Grid] Re-tuned cell size {:.0} -> {:.0} for {} units",
+                self.cell_size, cell_size, unit_count
+            );
+            self.resize(cell_size);
+        }
+    }
+
+    fn set_cell_size(&mut self, cell_size: f32) {
+        if cell_size > 0.0 && cell_size != self.cell_size {
+            self.resize(cell_size);
+        }
     }
 }
 
@@ -96,4 +388,115 @@ mod tests {
         assert!(nearby.contains(&1));
         assert!(!nearby.contains(&2));
     }
+
+    #[test]
+    fn test_auto_cell_size_and_resize() {
+        // 1000 units in a 5000 radius -> 5000 / 10
+        assert!((SpatialGrid::auto_cell_size(1000, 5000.0) - 500.0).abs() < 0.1);
+        assert_eq!(SpatialGrid::auto_cell_size(0, 5000.0), DEFAULT_CELL_SIZE);
+        assert_eq!(SpatialGrid::auto_cell_size(1000, 10.0), MIN_AUTO_CELL_SIZE);
+
+        let mut grid = SpatialGrid::with_auto_cell_size(1000, 5000.0);
+        grid.insert(0, 100.0, 0.0, 0.0);
+        grid.insert(1, 3000.0, 0.0, 0.0);
+
+        grid.resize(100.0);
+        assert_eq!(grid.cell_size(), 100.0);
+        assert_eq!(grid.stats(), (2, 2));
+        assert!(grid.get_nearby(100.0, 0.0, 0.0, 50.0).contains(&0));
+        assert!(!grid.get_nearby(100.0, 0.0, 0.0, 50.0).contains(&1));
+    }
+
+    #[test]
+    fn test_get_in_radius_matches_brute_force() {
+        // One big cell with a count that isn't a multiple of 4 exercises the SIMD tail
+        let mut grid = SpatialGrid::new(10000.0);
+        let points: Vec<(f32, f32, f32)> = (0..103)
+            .map(|i| ((i * 37 % 200) as f32, (i * 11 % 150) as f32, (i * 7 % 90) as f32))
+            .collect();
+        for (idx, &(x, y, z)) in points.iter().enumerate() {
+            grid.insert(idx, x, y, z);
+        }
+
+        let mut hits = grid.get_in_radius(100.0, 75.0, 45.0, 60.0);
+        hits.sort_by_key(|&(idx, _)| idx);
+
+        let expected: Vec<(usize, f32)> = points.iter().enumerate()
+            .map(|(idx, p)| (idx, (p.0 - 100.0).powi(2) + (p.1 - 75.0).powi(2) + (p.2 - 45.0).powi(2)))
+            .filter(|&(_, d)| d <= 3600.0)
+            .collect();
+        assert!(!expected.is_empty() && expected.len() < points.len());
+        assert_eq!(hits, expected);
+    }
+
+    #[test]
+    fn test_get_in_radius_and_remove() {
+        let mut grid = SpatialGrid::new(1000.0);
+        grid.insert(0, 0.0, 0.0, 0.0);
+        grid.insert(1, 300.0, 0.0, 0.0);
+        grid.insert(2, 0.0, 400.0, 0.0);
+
+        // Same cell, but 2 is outside the exact range
+        let mut hits = grid.get_in_radius(0.0, 0.0, 0.0, 350.0);
+        hits.sort_by_key(|&(idx, _)| idx);
+        assert_eq!(hits, vec![(0, 0.0), (1, 90000.0)]);
+
+        grid.remove(1, 300.0, 0.0, 0.0);
+        assert_eq!(grid.get_in_radius(0.0, 0.0, 0.0, 350.0), vec![(0, 0.0)]);
+        assert_eq!(grid.stats(), (1, 2));
+    }
+
+    #[test]
+    fn test_into_variants_reuse_buffer() {
+        let mut grid = SpatialGrid::new(1000.0);
+        grid.insert(0, 0.0, 0.0, 0.0);
+        grid.insert(1, 5000.0, 0.0, 0.0);
+
+        // Stale contents are cleared and the capacity kept
+        let mut nearby = Vec::with_capacity(16);
+        nearby.extend([7, 8, 9]);
+        grid.get_nearby_into(0.0, 0.0, 0.0, 100.0, &mut nearby);
+        assert_eq!(nearby, grid.get_nearby(0.0, 0.0, 0.0, 100.0));
+        assert_eq!(nearby.capacity(), 16);
+
+        let mut hits = vec![(9, 1.0)];
+        grid.get_in_radius_into(5000.0, 0.0, 0.0, 10.0, &mut hits);
+        assert_eq!(hits, vec![(1, 0.0)]);
+    }
+
+    #[test]
+    fn test_occupancy() {
+        let mut grid = SpatialGrid::new(100.0);
+        assert_eq!(grid.occupancy().min_per_cell, 0);
+
+        // Three units in one cell, one alone in another
+        for (idx, x) in [10.0, 20.0, 30.0, 250.0].into_iter().enumerate() {
+            grid.insert(idx, x, 0.0, 0.0);
+        }
+        let stats = grid.occupancy();
+        assert_eq!((stats.cell_count, stats.total_entries), (2, 4));
+        assert_eq!((stats.min_per_cell, stats.max_per_cell), (1, 3));
+        assert_eq!(stats.avg_per_cell, 2.0);
+        assert_eq!(stats.cell_size, 100.0);
+
+        // Cells emptied by clear() are kept but not counted
+        grid.clear();
+        grid.insert(0, 10.0, 0.0, 0.0);
+        assert_eq!(grid.occupancy().cell_count, 1);
+        assert_eq!(grid.occupancy().min_per_cell, 1);
+    }
+
+    #[test]
+    fn test_extreme_coordinates_do_not_overflow_keys() {
+        let mut grid = SpatialGrid::new(100.0);
+        grid.insert(0, 1.0e30, 0.0, 0.0);
+        grid.insert(1, f32::INFINITY, f32::NEG_INFINITY, 0.0);
+        grid.insert(2, f32::NAN, 0.0, 0.0);
+        grid.insert(3, 50.0, 50.0, 50.0);
+
+        assert!(grid.get_nearby(1.0e30, 0.0, 0.0, 100.0).contains(&0));
+        assert_eq!(grid.get_in_radius(50.0, 50.0, 50.0, 10.0), vec![(3, 0.0)]);
+        // A range far larger than the grid scans occupied cells instead of the cube
+        assert_eq!(grid.get_nearby(0.0, 0.0, 0.0, 1.0e12).len(), 4);
+    }
 }
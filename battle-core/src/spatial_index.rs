@@ -0,0 +1,158 @@
+// battle-core/src/spatial_index.rs
+//
+// Common interface over the spatial partitioning structures.
+// SpatialGrid suits dense fights; Octree suits sparse, very large battlefields.
+
+use crate::octree::Octree;
+use crate::spatial_grid::{GridStats, SpatialGrid, DEFAULT_CELL_SIZE};
+
+/// Indexes must be shareable across threads when the `parallel` feature
+/// runs targeting on rayon; single-threaded builds don't require it
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Sync> MaybeSync for T {}
+
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSync for T {}
+
+/// Spatial index over unit positions, keyed by index into the unit list
+pub trait SpatialIndex: MaybeSync {
+    /// Insert unit at position
+    fn insert(&mut self, index: usize, x: f32, y: f32, z: f32);
+
+    /// Units within range of a point, as (index, distance_sq) - exact, no false positives
+    fn query_range(&self, x: f32, y: f32, z: f32, range: f32) -> Vec<(usize, f32)> {
+        let mut result = Vec::new();
+        self.query_range_into(x, y, z, range, &mut result);
+        result
+    }
+
+    /// query_range into `out` (cleared first) - per-tick callers pass a
+    /// scratch buffer so repeated queries don't allocate
+    fn query_range_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<(usize, f32)>);
+
+    /// Remove unit previously inserted at this position
+    fn remove(&mut self, index: usize, x: f32, y: f32, z: f32);
+
+    /// Remove everything
+    fn clear(&mut self);
+
+    /// Candidate unit indices near a point
+    ///
+    /// May over-return (callers re-check distance); defaults to the exact query.
+    fn get_nearby(&self, x: f32, y: f32, z: f32, range: f32) -> Vec<usize> {
+        let mut result = Vec::new();
+        self.get_nearby_into(x, y, z, range, &mut result);
+        result
+    }
+
+    /// get_nearby into `out` (cleared first). The default goes through
+    /// query_range, so it still allocates - indexes override it.
+    fn get_nearby_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<usize>) {
+        out.clear();
+        out.extend(self.query_range(x, y, z, range).into_iter().map(|(idx, _)| idx));
+    }
+
+    /// Cell occupancy, for indexes that are uniform grids. None by default.
+    fn grid_stats(&self) -> Option<GridStats> {
+        None
+    }
+
+    /// Adapt internal parameters to unit count and spread. No-op by default.
+    fn tune(&mut self, _unit_count: usize, _battlefield_radius: f32) {}
+
+    /// Use cells of this size (grids only - see grid_stats). No-op by default.
+    fn set_cell_size(&mut self, _cell_size: f32) {}
+}
+
+/// Runtime-selectable spatial index, used where the index type can't be a
+/// compile-time choice (the WASM simulator)
+#[derive(Debug, Clone)]
+pub enum AnySpatialIndex {
+    Grid(SpatialGrid),
+    Octree(Octree),
+}
+
+impl AnySpatialIndex {
+    /// Build an empty index from its name ("grid" or "octree")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "grid" => Some(Self::Grid(SpatialGrid::new(DEFAULT_CELL_SIZE))),
+            "octree" => Some(Self::Octree(Octree::new())),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Grid(_) => "grid",
+            Self::Octree(_) => "octree",
+        }
+    }
+}
+
+impl Default for AnySpatialIndex {
+    fn default() -> Self {
+        Self::Grid(SpatialGrid::new(DEFAULT_CELL_SIZE))
+    }
+}
+
+impl SpatialIndex for AnySpatialIndex {
+    fn insert(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        match self {
+            Self::Grid(grid) => grid.insert(index, x, y, z),
+            Self::Octree(tree) => tree.insert(index, x, y, z),
+        }
+    }
+
+    fn query_range_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<(usize, f32)>) {
+        match self {
+            Self::Grid(grid) => SpatialIndex::query_range_into(grid, x, y, z, range, out),
+            Self::Octree(tree) => SpatialIndex::query_range_into(tree, x, y, z, range, out),
+        }
+    }
+
+    fn remove(&mut self, index: usize, x: f32, y: f32, z: f32) {
+        match self {
+            Self::Grid(grid) => grid.remove(index, x, y, z),
+            Self::Octree(tree) => tree.remove(index, x, y, z),
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Self::Grid(grid) => grid.clear(),
+            Self::Octree(tree) => tree.clear(),
+        }
+    }
+
+    fn get_nearby_into(&self, x: f32, y: f32, z: f32, range: f32, out: &mut Vec<usize>) {
+        match self {
+            Self::Grid(grid) => grid.get_nearby_into(x, y, z, range, out),
+            Self::Octree(tree) => tree.get_nearby_into(x, y, z, range, out),
+        }
+    }
+
+    fn grid_stats(&self) -> Option<GridStats> {
+        match self {
+            Self::Grid(grid) => grid.grid_stats(),
+            Self::Octree(tree) => tree.grid_stats(),
+        }
+    }
+
+    fn tune(&mut self, unit_count: usize, battlefield_radius: f32) {
+        match self {
+            Self::Grid(grid) => SpatialIndex::tune(grid, unit_count, battlefield_radius),
+            Self::Octree(tree) => SpatialIndex::tune(tree, unit_count, battlefield_radius),
+        }
+    }
+
+    fn set_cell_size(&mut self, cell_size: f32) {
+        if let Self::Grid(grid) = self {
+            SpatialIndex::set_cell_size(grid, cell_size);
+        }
+    }
+}
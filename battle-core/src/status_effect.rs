@@ -0,0 +1,117 @@
+// battle-core/src/status_effect.rs
+//
+// Timed debuffs applied by weapon hits (EMP, plasma, ...).
+// Effects are tick-based so they expire deterministically with the simulation.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StatusEffectKind {
+    /// Scales max_speed by (1 - magnitude)
+    SpeedSlow,
+    /// Pauses shield regen (magnitude unused)
+    ShieldDisrupt,
+    /// Deals magnitude damage every tick, credited to the applier
+    Burn,
+}
+
+/// Active effect on a unit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    pub magnitude: f32,
+    #[cfg_attr(feature = "typegen", schemars(description = "Active while the tick is below this"))]
+    pub expires_at_tick: u64,     // Effect is active while tick < expires_at_tick
+    #[serde(default)]
+    pub source_id: Option<u32>,   // Unit that applied it (for damage attribution)
+}
+
+impl StatusEffect {
+    #[inline]
+    pub fn is_active(&self, tick: u64) -> bool {
+        tick < self.expires_at_tick
+    }
+}
+
+/// Effect a weapon attaches to its target on hit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct EffectSpec {
+    pub kind: StatusEffectKind,
+    pub magnitude: f32,
+    #[cfg_attr(feature = "typegen", schemars(description = "Seconds"))]
+    pub duration: f32,            // Seconds
+}
+
+impl EffectSpec {
+    /// Instantiate on a target at `tick`, converting duration to ticks with `dt`
+    pub fn instantiate(&self, tick: u64, dt: f32, source_id: u32) -> StatusEffect {
+        let duration_ticks = if dt > 0.0 { (self.duration / dt).ceil().max(1.0) as u64 } else { 1 };
+        StatusEffect {
+            kind: self.kind,
+            magnitude: self.magnitude,
+            expires_at_tick: tick + duration_ticks,
+            source_id: Some(source_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::BattleUnit;
+
+    fn effect(kind: StatusEffectKind, magnitude: f32, expires_at_tick: u64, source_id: u32) -> StatusEffect {
+        StatusEffect { kind, magnitude, expires_at_tick, source_id: Some(source_id) }
+    }
+
+    #[test]
+    fn test_same_kind_refreshes_instead_of_stacking() {
+        let mut unit = BattleUnit::default();
+        unit.apply_effect(effect(StatusEffectKind::SpeedSlow, 0.5, 20, 1));
+        unit.apply_effect(effect(StatusEffectKind::SpeedSlow, 0.3, 30, 2));
+        unit.apply_effect(effect(StatusEffectKind::Burn, 5.0, 10, 1));
+
+        assert_eq!(unit.effects.len(), 2);
+        assert_eq!(unit.effects[0], effect(StatusEffectKind::SpeedSlow, 0.5, 30, 2));
+        assert!((unit.effective_max_speed() - unit.max_speed * 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_effects_expire_by_tick() {
+        let mut unit = BattleUnit::default();
+        unit.apply_effect(effect(StatusEffectKind::ShieldDisrupt, 0.0, 10, 1));
+        unit.apply_effect(effect(StatusEffectKind::Burn, 1.0, 20, 1));
+
+        assert!(!unit.expire_effects(9));
+        assert!(unit.expire_effects(10));
+        assert!(!unit.has_effect(StatusEffectKind::ShieldDisrupt));
+        assert!(unit.has_effect(StatusEffectKind::Burn));
+    }
+
+    #[test]
+    fn test_shield_disrupt_pauses_regen() {
+        let mut unit = BattleUnit {
+            shield_regen: 10.0,
+            ..BattleUnit::builder().id(1).faction(1).shield(50.0, 100.0).build().unwrap()
+        };
+        unit.apply_effect(effect(StatusEffectKind::ShieldDisrupt, 0.0, 10, 1));
+        unit.regen_shield(1.0);
+        assert_eq!(unit.shield, 50.0);
+
+        unit.expire_effects(10);
+        unit.regen_shield(1.0);
+        assert_eq!(unit.shield, 60.0);
+    }
+
+    #[test]
+    fn test_spec_duration_converts_to_ticks() {
+        let spec = EffectSpec { kind: StatusEffectKind::Burn, magnitude: 2.0, duration: 1.0 };
+        let applied = spec.instantiate(100, 0.05, 7);
+        assert_eq!(applied.expires_at_tick, 120);
+        assert_eq!(applied.source_id, Some(7));
+    }
+}
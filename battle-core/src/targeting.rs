@@ -5,10 +5,32 @@
 // 2. Stations only target ships (defensive)
 // 3. Support for siege weapons (nukes) that only target stations
 // 4. Unarmed ships/stations are lower priority targets
-
-use crate::battle_unit::BattleUnit;
-use crate::spatial_grid::SpatialGrid;
-use crate::log;
+// 5. Point-defense-only units count as unarmed; search radius ignores PD range
+// 6. find_weapon_target() for weapons with independent_targeting
+// 7. find_best_repair_target() - most-damaged ally in a repair weapon's range
+// 8. Hostility comes from FactionRelations (allied factions) instead of faction_id !=
+// 9. Data-driven PriorityTable (attacker class -> target class -> score) consulted
+//    before the ship/station heuristics
+// 10. find_resupply_target() - most depleted ally in a supply unit's range
+// 11. Disabled stations are the lowest priority, and only for units carrying
+//     siege weapons
+// 12. target_score() is the one scoring rule for every unit-target search;
+//     find_enemy_in_range() replaces the simulator's unscored nearest-enemy
+//     fallback (which let stations lock onto stations)
+// 13. find_best_target can scale same-priority distances by a fixed
+//     per-pair factor so a fleet spreads fire (config.target_spread_factor)
+// 14. Per-tick searches take a caller-owned `in_range` buffer for the grid
+//     query instead of allocating one per call
+// 15. Unit searches only consider enemies in the attacker faction's
+//     detected set (detection.rs)
+
+use crate::battle_unit::{BattleUnit, Weapon};
+use crate::weapons::WeaponCategory;
+use crate::spatial_index::SpatialIndex;
+use crate::factions::FactionRelations;
+use crate::log_at;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Target priority scores
 /// Higher = more priority
@@ -16,9 +38,63 @@ const PRIORITY_ARMED_SHIP: i32 = 100;
 const PRIORITY_UNARMED_SHIP: i32 = 50;
 const PRIORITY_ARMED_STATION: i32 = 30;
 const PRIORITY_UNARMED_STATION: i32 = 10;
+const PRIORITY_DISABLED_STATION: i32 = 1;
+
+/// Target scores by unit class: attacker_class -> target_class -> score
+///
+/// JSON is a plain nested map, e.g. {"bomber": {"capital": 150, "station": 200}}.
+/// A listed pair overrides the built-in heuristics (0 = never target); pairs
+/// not listed fall back to them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PriorityTable {
+    scores: HashMap<String, HashMap<String, i32>>,
+}
+
+impl PriorityTable {
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Set one attacker/target class score
+    pub fn set(&mut self, attacker_class: &str, target_class: &str, score: i32) {
+        self.scores
+            .entry(attacker_class.to_string())
+            .or_default()
+            .insert(target_class.to_string(), score);
+    }
+
+    /// Score for this pair, None if the table doesn't list it
+    #[inline]
+    pub fn score(&self, attacker_class: &str, target_class: &str) -> Option<i32> {
+        if self.scores.is_empty() {
+            return None;
+        }
+        self.scores.get(attacker_class)?.get(target_class).copied()
+    }
+}
+
+/// Distance multiplier spreading fire between similar targets: 1 plus up
+/// to `factor`, fixed per attacker / target pair so a unit's preference never
+/// flips between ticks, but different attackers rank the same pair of
+/// near-equidistant enemies differently
+#[inline]
+fn spread_weight(attacker_id: u32, target_id: u32, factor: f32) -> f32 {
+    if factor <= 0.0 {
+        return 1.0;
+    }
+    // splitmix-style mix of the pair into [0, 1)
+    let mut h = ((attacker_id as u64) << 32 | target_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    h ^= h >> 31;
+    h = h.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h ^= h >> 29;
+    1.0 + factor * (h >> 40) as f32 / (1u64 << 24) as f32
+}
 
 /// Calculate target priority score
 /// 
+/// A PriorityTable entry for the two classes wins; otherwise:
+///
 /// Ships should target:
 /// 1. Armed hostile ships (highest threat)
 /// 2. Unarmed hostile ships (support/logistics)
@@ -27,11 +103,22 @@ const PRIORITY_UNARMED_STATION: i32 = 10;
 /// 
 /// Stations should target:
 /// 1. Armed hostile ships only (defensive)
+///
+/// Disabled units come before all of that: only siege carriers may finish
+/// off disabled stations, and only once nothing else is left.
 #[inline]
-fn calculate_target_priority(attacker: &BattleUnit, target: &BattleUnit) -> i32 {
+fn calculate_target_priority(attacker: &BattleUnit, target: &BattleUnit, priorities: &PriorityTable) -> i32 {
+    if target.is_disabled {
+        return if target.is_station && attacker.max_siege_range() > 0.0 { PRIORITY_DISABLED_STATION } else { 0 };
+    }
+
+    if let Some(score) = priorities.score(&attacker.class, &target.class) {
+        return score.max(0);
+    }
+
     // Stations can only target ships
     if attacker.is_station {
-        if target.is_ship && target.has_weapons {
+        if target.is_ship && target.is_armed() {
             return PRIORITY_ARMED_SHIP;
         } else if target.is_ship {
             return PRIORITY_UNARMED_SHIP;
@@ -41,13 +128,13 @@ fn calculate_target_priority(attacker: &BattleUnit, target: &BattleUnit) -> i32
 
     // Ships target priority
     if target.is_ship {
-        if target.has_weapons {
+        if target.is_armed() {
             PRIORITY_ARMED_SHIP
         } else {
             PRIORITY_UNARMED_SHIP
         }
     } else if target.is_station {
-        if target.has_weapons {
+        if target.is_armed() {
             PRIORITY_ARMED_STATION
         } else {
             PRIORITY_UNARMED_STATION
@@ -58,72 +145,291 @@ fn calculate_target_priority(attacker: &BattleUnit, target: &BattleUnit) -> i32
     }
 }
 
+/// Priority of `other` as a target for `unit`, 0 when it isn't one
+///
+/// Skips self, units out of the fight (disabled ones are left to
+/// calculate_target_priority), allies, anything the priority rules exclude
+/// and anything none of the unit's weapons can hit.
+#[inline]
+pub fn target_score(unit: &BattleUnit, other: &BattleUnit, relations: &FactionRelations, priorities: &PriorityTable) -> i32 {
+    if other.id == unit.id || !other.is_siege_target() || !relations.is_hostile(unit.faction_id, other.faction_id) {
+        return 0;
+    }
+    let priority = calculate_target_priority(unit, other, priorities);
+    // e.g. ships when we only carry nukes
+    if priority == 0 || unit.max_offensive_range(other.is_station) <= 0.0 {
+        return 0;
+    }
+    priority
+}
+
+/// Highest-priority candidate, nearest on ties - (index, priority, dist_sq)
+///
+/// `candidates` are (index, dist_sq) pairs from whatever search found them;
+/// only those flagged in `detected` (by index) count. With `in_weapon_range`
+/// a candidate also has to be inside the range of the weapons that can hit
+/// it. The returned dist_sq is scaled by spread_weight.
+#[allow(clippy::too_many_arguments)]
+fn best_candidate(
+    unit: &BattleUnit,
+    all_units: &[BattleUnit],
+    candidates: impl IntoIterator<Item = (usize, f32)>,
+    detected: &[bool],
+    relations: &FactionRelations,
+    priorities: &PriorityTable,
+    in_weapon_range: bool,
+    spread_factor: f32,
+) -> Option<(usize, i32, f32)> {
+    let mut best: Option<(usize, i32, f32)> = None;
+    for (idx, dist_sq) in candidates {
+        if !detected.get(idx).copied().unwrap_or(false) {
+            continue;
+        }
+        let Some(other) = all_units.get(idx) else { continue };
+        let priority = target_score(unit, other, relations, priorities);
+        if priority == 0 {
+            continue;
+        }
+        if in_weapon_range {
+            let range = unit.max_offensive_range(other.is_station);
+            if dist_sq > range * range {
+                continue;
+            }
+        }
+        let weight = spread_weight(unit.id, other.id, spread_factor);
+        let dist_sq = dist_sq * weight * weight;
+        // Prefer: Higher priority, then closer distance
+        if best.is_none_or(|(_, p, d)| priority > p || (priority == p && dist_sq < d)) {
+            best = Some((idx, priority, dist_sq));
+        }
+    }
+    best
+}
+
 /// Find best target for a unit
 /// 
 /// Uses spatial grid for O(k) lookup instead of O(n)
 /// Applies priority scoring for ship-vs-station targeting
+///
+/// `incumbent` is the index of a still-valid current target. It is kept
+/// unless a candidate has strictly higher priority, or the same priority and
+/// is at least `switch_margin` (fraction, e.g. 0.2 = 20%) closer. This stops
+/// units flip-flopping between near-equidistant enemies. Distances are
+/// scaled by spread_weight (`spread_factor` 0 = plain nearest).
+///
+/// Only enemies flagged in `detected` (the unit's faction's sensor contacts,
+/// by index) are candidates - the incumbent included. `in_range` is scratch space for the
+/// grid query.
+#[allow(clippy::too_many_arguments)]
 pub fn find_best_target(
     unit: &BattleUnit,
     all_units: &[BattleUnit],
-    grid: &SpatialGrid,
+    grid: &impl SpatialIndex,
+    detected: &[bool],
+    relations: &FactionRelations,
+    priorities: &PriorityTable,
+    incumbent: Option<usize>,
+    switch_margin: f32,
+    spread_factor: f32,
+    in_range: &mut Vec<(usize, f32)>,
 ) -> Option<usize> {
-    if !unit.alive || !unit.can_attack() {
+    if !unit.is_alive() || !unit.can_attack() {
         return None;
     }
 
-    // Get nearby units using spatial grid
-    let search_range = unit.max_weapon_range.max(unit.view_range);
-    let nearby_indices = grid.get_nearby(
+    // Point defense can't shoot units - a PD-only unit never acquires targets
+    let offensive_range = unit.max_offensive_range(true);
+    if offensive_range <= 0.0 {
+        return None;
+    }
+
+    // Get units within search range using spatial grid (exact distance, no corner over-return)
+    let search_range = offensive_range.max(unit.view_range);
+    grid.query_range_into(
         unit.pos_x,
         unit.pos_y,
         unit.pos_z,
         search_range,
+        in_range,
     );
 
-    let mut best_target_idx: Option<usize> = None;
-    let mut best_priority: i32 = 0;
-    let mut best_dist_sq: f32 = f32::MAX;
+    let best = best_candidate(unit, all_units, in_range.iter().copied(), detected, relations, priorities, false, spread_factor);
+    let best_target_idx = best.map(|(idx, _, _)| idx);
+    let (best_priority, best_dist_sq) = best.map_or((0, f32::MAX), |(_, p, d)| (p, d));
+
+    // Sticky targeting - only switch away from the incumbent for a clearly better candidate
+    if let Some(current_idx) = incumbent.filter(|&i| i < all_units.len() && Some(i) != best_target_idx) {
+        let current = &all_units[current_idx];
+        let current_priority = calculate_target_priority(unit, current, priorities);
+        if current_priority > 0 && current.is_siege_target() && detected.get(current_idx).copied().unwrap_or(false) {
+            let keep_factor = (1.0 - switch_margin).max(0.0);
+            let weight = spread_weight(unit.id, current.id, spread_factor);
+            let current_dist_sq = unit.distance_sq(current) * weight * weight;
+            let clearly_closer = current_dist_sq * keep_factor * keep_factor > best_dist_sq;
+            if best_target_idx.is_none()
+                || best_priority < current_priority
+                || (best_priority == current_priority && !clearly_closer)
+            {
+                return Some(current_idx);
+            }
+        }
+    }
 
-    for &idx in &nearby_indices {
-        if idx >= all_units.len() {
+    // Debug log
+    if let Some(target_idx) = best_target_idx.filter(|_| unit.id.is_multiple_of(100)) {
+        let target = &all_units[target_idx];
+        log_at!(Debug,
+            "[Targeting] Unit {} (ship={}) -> Unit {} (ship={}, station={}) priority={} dist={:.1}",
+            unit.id, unit.is_ship, target.id, target.is_ship, target.is_station, 
+            best_priority, best_dist_sq.sqrt()
+        );
+    }
+
+    best_target_idx
+}
+
+/// Best enemy already inside weapon range among `candidates` ((index,
+/// dist_sq) pairs from any search - grid query or a scan of every unit)
+///
+/// Same priorities (and `detected` filter) as find_best_target, but only what
+/// the unit can shoot right now. Used for units that won't close in
+/// (retreating ones).
+pub fn find_enemy_in_range(
+    unit: &BattleUnit,
+    all_units: &[BattleUnit],
+    candidates: impl IntoIterator<Item = (usize, f32)>,
+    detected: &[bool],
+    relations: &FactionRelations,
+    priorities: &PriorityTable,
+) -> Option<usize> {
+    if !unit.is_alive() || !unit.can_attack() {
+        return None;
+    }
+    best_candidate(unit, all_units, candidates, detected, relations, priorities, true, 0.0).map(|(idx, _, _)| idx)
+}
+
+/// Find a target for one weapon with independent_targeting
+///
+/// Nearest valid enemy inside the weapon's own max_range that the weapon may
+/// shoot: never for point defense, stations only for siege weapons, and the
+/// attacker's usual priority rules (e.g. stations ignore stations). Only
+/// enemies flagged in `detected`.
+#[allow(clippy::too_many_arguments)]
+pub fn find_weapon_target(
+    unit: &BattleUnit,
+    weapon: &Weapon,
+    all_units: &[BattleUnit],
+    grid: &impl SpatialIndex,
+    detected: &[bool],
+    relations: &FactionRelations,
+    priorities: &PriorityTable,
+    in_range: &mut Vec<(usize, f32)>,
+) -> Option<usize> {
+    if !unit.is_alive() || weapon.category == WeaponCategory::PointDefense || weapon.is_repair || weapon.max_range <= 0.0 {
+        return None;
+    }
+    let siege = weapon.category == WeaponCategory::Siege;
+
+    grid.query_range_into(unit.pos_x, unit.pos_y, unit.pos_z, weapon.max_range, in_range);
+    in_range.iter()
+        .copied()
+        .filter(|&(idx, _)| {
+            detected.get(idx).copied().unwrap_or(false) && all_units.get(idx).is_some_and(|other| {
+                other.id != unit.id
+                    && other.is_siege_target()
+                    && relations.is_hostile(unit.faction_id, other.faction_id)
+                    && (!siege || other.is_station)
+                    && (siege || !other.is_disabled)
+                    && calculate_target_priority(unit, other, priorities) > 0
+            })
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(idx, _)| idx)
+}
+
+/// Find the ally a repair weapon should fix
+///
+/// Most-damaged (largest missing fraction) living ally (own or allied faction) inside the weapon's
+/// max_range, nearest first on ties. The healer never picks itself.
+pub fn find_best_repair_target(
+    unit: &BattleUnit,
+    weapon: &Weapon,
+    all_units: &[BattleUnit],
+    grid: &impl SpatialIndex,
+    relations: &FactionRelations,
+    in_range: &mut Vec<(usize, f32)>,
+) -> Option<usize> {
+    if !unit.is_alive() || !weapon.is_repair || weapon.max_range <= 0.0 {
+        return None;
+    }
+
+    let mut best: Option<(usize, f32, f32)> = None; // (idx, need, dist_sq)
+    grid.query_range_into(unit.pos_x, unit.pos_y, unit.pos_z, weapon.max_range, in_range);
+    for &(idx, dist_sq) in in_range.iter() {
+        let Some(other) = all_units.get(idx) else {
+            continue;
+        };
+        if other.id == unit.id || !other.is_valid_target() || !relations.is_allied(unit.faction_id, other.faction_id) {
             continue;
         }
 
-        let other = &all_units[idx];
-        
-        // Skip self, dead units, same faction
-        if other.id == unit.id || !other.alive || other.faction_id == unit.faction_id {
+        let need = other.repair_need(weapon.repairs_shield);
+        if need <= 0.0 {
             continue;
         }
 
-        // Calculate priority
-        let priority = calculate_target_priority(unit, other);
-        if priority == 0 {
-            continue; // Not a valid target for this attacker type
+        let better = match best {
+            None => true,
+            Some((_, best_need, best_dist_sq)) => {
+                need > best_need || (need == best_need && dist_sq < best_dist_sq)
+            }
+        };
+        if better {
+            best = Some((idx, need, dist_sq));
         }
+    }
 
-        let dist_sq = unit.distance_sq(other);
+    best.map(|(idx, ..)| idx)
+}
 
-        // Check if this is a better target
-        // Prefer: Higher priority, then closer distance
-        if priority > best_priority || (priority == best_priority && dist_sq < best_dist_sq) {
-            best_priority = priority;
-            best_dist_sq = dist_sq;
-            best_target_idx = Some(idx);
-        }
+/// Find the ally a supply unit should resupply
+///
+/// Lowest supply_need (emptiest weapon) among allies within resupply_range,
+/// nearest first on ties. Never itself.
+pub fn find_resupply_target(
+    unit: &BattleUnit,
+    all_units: &[BattleUnit],
+    grid: &impl SpatialIndex,
+    relations: &FactionRelations,
+) -> Option<usize> {
+    if !unit.in_battle() || unit.resupply_rate <= 0.0 || unit.resupply_range <= 0.0 {
+        return None;
     }
 
-    // Debug log
-    if best_target_idx.is_some() && unit.id % 100 == 0 {
-        let target = &all_units[best_target_idx.unwrap()];
-        log(&format!(
-            "[Targeting] Unit {} (ship={}) -> Unit {} (ship={}, station={}) priority={} dist={:.1}",
-            unit.id, unit.is_ship, target.id, target.is_ship, target.is_station, 
-            best_priority, best_dist_sq.sqrt()
-        ));
+    let mut best: Option<(usize, f32, f32)> = None; // (idx, supply fraction, dist_sq)
+    for (idx, dist_sq) in grid.query_range(unit.pos_x, unit.pos_y, unit.pos_z, unit.resupply_range) {
+        let Some(other) = all_units.get(idx) else {
+            continue;
+        };
+        if other.id == unit.id || !other.is_valid_target() || !relations.is_allied(unit.faction_id, other.faction_id) {
+            continue;
+        }
+        let Some(fraction) = other.supply_need() else {
+            continue;
+        };
+
+        let better = match best {
+            None => true,
+            Some((_, best_fraction, best_dist_sq)) => {
+                fraction < best_fraction || (fraction == best_fraction && dist_sq < best_dist_sq)
+            }
+        };
+        if better {
+            best = Some((idx, fraction, dist_sq));
+        }
     }
 
-    best_target_idx
+    best.map(|(idx, ..)| idx)
 }
 
 /// Find best station target for siege weapons (nukes)
@@ -132,10 +438,11 @@ pub fn find_best_target(
 pub fn find_siege_target(
     unit: &BattleUnit,
     all_units: &[BattleUnit],
-    grid: &SpatialGrid,
+    grid: &impl SpatialIndex,
+    relations: &FactionRelations,
     siege_range: f32,
 ) -> Option<usize> {
-    if !unit.alive {
+    if !unit.is_alive() {
         return None;
     }
 
@@ -156,8 +463,8 @@ pub fn find_siege_target(
 
         let other = &all_units[idx];
         
-        // Skip self, dead, same faction, and non-stations
-        if other.id == unit.id || !other.alive || other.faction_id == unit.faction_id {
+        // Skip self, dead/withdrawn, same faction / allies, and non-stations
+        if other.id == unit.id || !other.is_valid_target() || !relations.is_hostile(unit.faction_id, other.faction_id) {
             continue;
         }
 
@@ -182,19 +489,18 @@ pub fn find_siege_target(
 /// Note: This is called during the missile interception phase
 pub fn find_am_targets(
     all_units: &[BattleUnit],
-    grid: &SpatialGrid,
+    grid: &impl SpatialIndex,
+    relations: &FactionRelations,
 ) -> Vec<(usize, usize)> {
     let mut am_pairs = Vec::new();
 
     for (idx, unit) in all_units.iter().enumerate() {
-        if !unit.alive {
+        if !unit.is_alive() {
             continue;
         }
 
         // Check if unit has AM weapons
-        let has_am = unit.weapons.iter().any(|w| {
-            w.tag.starts_with("AM") || w.tag.to_lowercase().contains("anti-missile")
-        });
+        let has_am = unit.weapons.iter().any(|w| w.category == WeaponCategory::PointDefense);
 
         if !has_am {
             continue;
@@ -208,7 +514,7 @@ pub fn find_am_targets(
                 continue;
             }
             let enemy = &all_units[enemy_idx];
-            if enemy.faction_id != unit.faction_id && enemy.alive {
+            if relations.is_hostile(unit.faction_id, enemy.faction_id) && enemy.is_valid_target() {
                 am_pairs.push((idx, enemy_idx));
             }
         }
@@ -220,50 +526,97 @@ pub fn find_am_targets(
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    fn make_unit(id: u32, faction: u32, is_ship: bool, is_station: bool, has_weapons: bool) -> BattleUnit {
-        BattleUnit {
-            id,
-            faction_id: faction,
-            is_ship,
-            is_station,
-            has_weapons,
-            alive: true,
-            pos_x: 0.0,
-            pos_y: 0.0,
-            pos_z: 0.0,
-            max_weapon_range: 100.0,
-            view_range: 150.0,
-            ..Default::default()
-        }
-    }
+    use crate::battle_unit::Weapon;
+    use crate::fixtures::{freighter, ship, station};
 
     #[test]
     fn test_target_priority_ship_vs_ship() {
-        let attacker = make_unit(1, 1, true, false, true);
-        
-        let armed_ship = make_unit(2, 2, true, false, true);
-        let unarmed_ship = make_unit(3, 2, true, false, false);
-        let armed_station = make_unit(4, 2, false, true, true);
-        let unarmed_station = make_unit(5, 2, false, true, false);
-
-        assert_eq!(calculate_target_priority(&attacker, &armed_ship), PRIORITY_ARMED_SHIP);
-        assert_eq!(calculate_target_priority(&attacker, &unarmed_ship), PRIORITY_UNARMED_SHIP);
-        assert_eq!(calculate_target_priority(&attacker, &armed_station), PRIORITY_ARMED_STATION);
-        assert_eq!(calculate_target_priority(&attacker, &unarmed_station), PRIORITY_UNARMED_STATION);
+        let attacker = ship(1, 1).build().unwrap();
+
+        let armed_ship = ship(2, 2).build().unwrap();
+        let unarmed_ship = freighter(3, 2).build().unwrap();
+        let armed_station = station(4, 2).build().unwrap();
+        let unarmed_station = station(5, 2).weapons(Vec::new()).build().unwrap();
+
+        assert_eq!(calculate_target_priority(&attacker, &armed_ship, &PriorityTable::default()), PRIORITY_ARMED_SHIP);
+        assert_eq!(calculate_target_priority(&attacker, &unarmed_ship, &PriorityTable::default()), PRIORITY_UNARMED_SHIP);
+        assert_eq!(calculate_target_priority(&attacker, &armed_station, &PriorityTable::default()), PRIORITY_ARMED_STATION);
+        assert_eq!(calculate_target_priority(&attacker, &unarmed_station, &PriorityTable::default()), PRIORITY_UNARMED_STATION);
     }
 
     #[test]
     fn test_target_priority_station_defensive() {
-        let attacker = make_unit(1, 1, false, true, true);
-        
-        let armed_ship = make_unit(2, 2, true, false, true);
-        let enemy_station = make_unit(3, 2, false, true, true);
+        let attacker = station(1, 1).build().unwrap();
+
+        let armed_ship = ship(2, 2).build().unwrap();
+        let enemy_station = station(3, 2).build().unwrap();
 
         // Stations should target ships
-        assert_eq!(calculate_target_priority(&attacker, &armed_ship), PRIORITY_ARMED_SHIP);
-        
+        assert_eq!(calculate_target_priority(&attacker, &armed_ship, &PriorityTable::default()), PRIORITY_ARMED_SHIP);
+
         // Stations should NOT target other stations
-        assert_eq!(calculate_target_priority(&attacker, &enemy_station), 0);
+        assert_eq!(calculate_target_priority(&attacker, &enemy_station, &PriorityTable::default()), 0);
+    }
+
+    #[test]
+    fn test_point_defense_only_ship_is_unarmed_priority() {
+        let attacker = ship(1, 1).build().unwrap();
+        let escort = ship(2, 2).weapons(vec![Weapon::point_defense(10.0)]).build().unwrap();
+
+        assert!(escort.has_weapons);
+        assert_eq!(calculate_target_priority(&attacker, &escort, &PriorityTable::default()), PRIORITY_UNARMED_SHIP);
+        assert_eq!(escort.max_offensive_range(false), 0.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fallback_uses_the_same_priorities() {
+        let units = vec![
+            ship(1, 1).build().unwrap(),
+            freighter(2, 2).position(40.0, 0.0, 0.0).build().unwrap(),
+            ship(3, 2).position(45.0, 0.0, 0.0).build().unwrap(),
+            ship(4, 2).position(150.0, 0.0, 0.0).build().unwrap(),
+        ];
+        let relations = FactionRelations::default();
+        let table = PriorityTable::default();
+        let mut detected = vec![true; units.len()];
+
+        // Every unit as candidates, like a search that bypassed the grid
+        let scan = |attacker: &BattleUnit, units: &[BattleUnit]| {
+            units.iter().enumerate().map(|(i, u)| (i, attacker.distance_sq(u))).collect::<Vec<_>>()
+        };
+        let candidates = scan(&units[0], &units);
+        // The armed frigate beats the nearer freighter; the farther one is out of range
+        assert_eq!(find_enemy_in_range(&units[0], &units, candidates.clone(), &detected, &relations, &table), Some(2));
+        // ...unless nobody has it on sensors
+        detected[2] = false;
+        assert_eq!(find_enemy_in_range(&units[0], &units, candidates, &detected, &relations, &table), Some(1));
+
+        // Stations still ignore stations, even as the only enemy in range
+        let units = vec![station(5, 1).build().unwrap(), station(6, 2).position(20.0, 0.0, 0.0).build().unwrap()];
+        let candidates = scan(&units[0], &units);
+        assert_eq!(find_enemy_in_range(&units[0], &units, candidates, &[true, true], &relations, &table), None);
+        assert_eq!(target_score(&units[0], &units[1], &relations, &table), 0);
+    }
+
+    #[test]
+    fn test_priority_table_overrides_heuristics() {
+        let bomber = ship(1, 1).class("bomber").build().unwrap();
+        let frigate = ship(2, 2).class("frigate").build().unwrap();
+        let fortress = station(4, 1).class("station").build().unwrap();
+        let station = station(3, 2).class("station").build().unwrap();
+
+        let mut table = PriorityTable::default();
+        table.set("bomber", "station", 200);
+        table.set("station", "station", 5);
+
+        assert_eq!(calculate_target_priority(&bomber, &station, &table), 200);
+        // Not listed - heuristics
+        assert_eq!(calculate_target_priority(&bomber, &frigate, &table), PRIORITY_ARMED_SHIP);
+        // Explicit override of stations-ignore-stations
+        assert_eq!(calculate_target_priority(&fortress, &station, &table), 5);
+
+        let json: PriorityTable = serde_json::from_str(r#"{"bomber": {"station": 200}}"#).unwrap();
+        assert_eq!(json.score("bomber", "station"), Some(200));
+        assert_eq!(json.score("generic", "station"), None);
+    }
+}
@@ -0,0 +1,120 @@
+// battle-core/src/templates.rs
+//
+// Unit templates - register a unit type once, then stamp out copies by id,
+// faction and position. Spawning hundreds of identical fighters or drones
+// this way skips parsing a full unit JSON for each one.
+//
+//   sim.register_template(UnitTemplate { template_id: "interceptor".into(), base_unit });
+//   sim.add_unit_from_template("interceptor", 500, 2, 100.0, 0.0, 40.0)?;
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::battle_unit::BattleUnit;
+use crate::validation::ValidationError;
+
+/// A unit type to copy from - base_unit's id, faction and position are
+/// replaced on every copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+pub struct UnitTemplate {
+    pub template_id: String,
+    pub base_unit: BattleUnit,
+}
+
+impl UnitTemplate {
+    /// Fresh copy of base_unit with its own identity, at rest at (x, y, z)
+    pub fn instantiate(&self, id: u32, faction_id: u32, x: f32, y: f32, z: f32) -> BattleUnit {
+        BattleUnit {
+            id,
+            faction_id,
+            pos_x: x,
+            pos_y: y,
+            pos_z: z,
+            vel_x: 0.0,
+            vel_y: 0.0,
+            vel_z: 0.0,
+            target_id: None,
+            ..self.base_unit.clone()
+        }
+    }
+}
+
+/// Why add_unit_from_template refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    /// No template registered under this id
+    UnknownTemplate(String),
+    /// The copy isn't a unit the simulator accepts (see validation.rs)
+    InvalidUnit(ValidationError),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownTemplate(id) => write!(f, "no template '{}'", id),
+            TemplateError::InvalidUnit(e) => write!(f, "invalid unit: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<ValidationError> for TemplateError {
+    fn from(e: ValidationError) -> Self {
+        TemplateError::InvalidUnit(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::Weapon;
+    use crate::simulator::BattleSimulator;
+
+    fn fighter_template() -> UnitTemplate {
+        let base_unit = BattleUnit::builder()
+            .id(9999)
+            .faction(1)
+            .hp(40.0)
+            .max_speed(120.0)
+            .as_ship()
+            .weapons(vec![Weapon::builder().tag("LASER").dps(5.0).range(60.0, 80.0).build()])
+            .build()
+            .unwrap();
+        UnitTemplate { template_id: "fighter".to_string(), base_unit }
+    }
+
+    #[test]
+    fn test_fighters_from_template() {
+        let mut sim = BattleSimulator::new(Vec::new(), 1000.0);
+        sim.register_template(fighter_template());
+        for i in 0..100 {
+            sim.add_unit_from_template("fighter", 1 + i, 1 + i % 2, i as f32 * 10.0, 0.0, 0.0).unwrap();
+        }
+
+        assert_eq!(sim.get_units().len(), 100);
+        let fighter = sim.get_unit(42).unwrap();
+        assert_eq!((fighter.faction_id, fighter.pos_x), (2, 410.0));
+        assert_eq!(fighter.max_hp, 40.0);
+        assert_eq!(fighter.weapons[0].tag, "LASER");
+        assert_eq!(sim.get_active_factions(), vec![1, 2]);
+        // The template itself is untouched
+        assert_eq!(sim.template("fighter").unwrap().base_unit.id, 9999);
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let mut sim = BattleSimulator::new(Vec::new(), 1000.0);
+        assert_eq!(
+            sim.add_unit_from_template("fighter", 1, 1, 0.0, 0.0, 0.0),
+            Err(TemplateError::UnknownTemplate("fighter".to_string()))
+        );
+
+        sim.register_template(fighter_template());
+        sim.add_unit_from_template("fighter", 1, 1, 0.0, 0.0, 0.0).unwrap();
+        assert_eq!(
+            sim.add_unit_from_template("fighter", 1, 2, 0.0, 0.0, 0.0),
+            Err(TemplateError::InvalidUnit(ValidationError::DuplicateId { id: 1 }))
+        );
+    }
+}
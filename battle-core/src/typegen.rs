@@ -0,0 +1,217 @@
+// battle-core/src/typegen.rs
+//
+// TypeScript and JSON Schema for the JSON payloads, generated from the
+// JsonSchema derives (feature "typegen"). `cargo run --features typegen --bin
+// generate-types` writes them to types/; CI runs it with --check and fails
+// when a committed file is stale, so a renamed or retyped field shows up as
+// a diff before it reaches a client.
+//
+// The derives are the only description of the payloads: wasm-pack's .d.ts
+// embeds types/battle-core.d.ts (typescript.rs) and the units validator
+// embeds types/battle-units.schema.json (schema.rs), so neither needs
+// schemars at build time.
+//
+// The host's payloads (BattleUnit, PositionUpdate, ...) are described the
+// way serde reads them - defaulted fields are optional - and the results the
+// way serde writes them. A type used both ways (StatusEffect) keeps the
+// host-side shape.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use schemars::generate::SchemaSettings;
+use schemars::transform::RecursiveTransform;
+use schemars::Schema;
+use serde_json::Value;
+use crate::battle_unit::{BattleUnit, Weapon};
+use crate::error::BattleError;
+use crate::factions::FactionConfig;
+use crate::objectives::VictoryState;
+use crate::profiling::ProfileStats;
+use crate::simulator::{BattleOutcome, BattleSnapshot, CompletionReport, DamagedUnit, MovedUnit, ReinforcementWave, TickResult, WeaponFired};
+use crate::templates::UnitTemplate;
+use crate::PositionUpdate;
+
+/// Where generate-types writes, relative to the crate root
+pub const TYPES_PATH: &str = "types/battle-core.d.ts";
+pub const UNITS_SCHEMA_PATH: &str = "types/battle-units.schema.json";
+
+const HEADER: &str = "\
+// Generated by `cargo run --features typegen --bin generate-types` from the
+// battle-core JsonSchema derives - do not edit.
+";
+
+/// The whole .d.ts - one declaration per type, sorted by name
+pub fn generate() -> String {
+    let mut results = SchemaSettings::draft2020_12().for_serialize().into_generator();
+    results.subschema_for::<TickResult>();
+    results.subschema_for::<WeaponFired>();
+    results.subschema_for::<MovedUnit>();
+    results.subschema_for::<DamagedUnit>();
+    results.subschema_for::<BattleError>();
+    results.subschema_for::<CompletionReport>();
+    results.subschema_for::<BattleOutcome>();
+    results.subschema_for::<ProfileStats>();
+    results.subschema_for::<VictoryState>();
+
+    let mut payloads = SchemaSettings::draft2020_12().for_deserialize().into_generator();
+    payloads.subschema_for::<BattleUnit>();
+    payloads.subschema_for::<Weapon>();
+    payloads.subschema_for::<PositionUpdate>();
+    payloads.subschema_for::<FactionConfig>();
+    payloads.subschema_for::<ReinforcementWave>();
+    payloads.subschema_for::<UnitTemplate>();
+    payloads.subschema_for::<BattleSnapshot>();
+
+    let mut definitions: BTreeMap<String, Value> = results.take_definitions(true).into_iter().collect();
+    definitions.extend(payloads.take_definitions(true));
+
+    let mut out = String::from(HEADER);
+    for (name, schema) in &definitions {
+        out.push('\n');
+        write_declaration(&mut out, name, schema);
+    }
+    out
+}
+
+/// JSON Schema for the units array the constructors take, the way serde
+/// reads it
+pub fn units_schema() -> String {
+    // schemars leaves the upper bound of u32 fields to serde
+    let settings = SchemaSettings::draft2020_12().for_deserialize().with_transform(RecursiveTransform(|schema: &mut Schema| {
+        if schema.get("format").and_then(Value::as_str) == Some("uint32") {
+            schema.insert("maximum".to_string(), u32::MAX.into());
+        }
+    }));
+    let schema = settings.into_generator().into_root_schema_for::<Vec<BattleUnit>>();
+    serde_json::to_string_pretty(&schema).expect("schemas are JSON") + "\n"
+}
+
+/// Every generated file as (path relative to the crate root, contents)
+pub fn generated_files() -> [(&'static str, String); 2] {
+    [(TYPES_PATH, generate()), (UNITS_SCHEMA_PATH, units_schema())]
+}
+
+fn write_doc(out: &mut String, schema: &Value, indent: &str) {
+    let Some(description) = schema.get("description").and_then(Value::as_str) else {
+        return;
+    };
+    let lines: Vec<&str> = description.lines().collect();
+    if let [line] = lines.as_slice() {
+        writeln!(out, "{}/** {} */", indent, line).unwrap();
+        return;
+    }
+    writeln!(out, "{}/**", indent).unwrap();
+    for line in lines {
+        writeln!(out, "{}", format!("{} * {}", indent, line).trim_end()).unwrap();
+    }
+    writeln!(out, "{} */", indent).unwrap();
+}
+
+/// `export interface` for objects with properties, `export type` otherwise
+fn write_declaration(out: &mut String, name: &str, schema: &Value) {
+    write_doc(out, schema, "");
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        writeln!(out, "export type {} = {};", name, ts_type(schema)).unwrap();
+        return;
+    };
+    let required: Vec<&str> = schema.get("required")
+        .and_then(Value::as_array)
+        .map(|fields| fields.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    writeln!(out, "export interface {} {{", name).unwrap();
+    for (field, property) in properties {
+        write_doc(out, property, "    ");
+        let optional = if required.contains(&field.as_str()) { "" } else { "?" };
+        writeln!(out, "    {}{}: {};", field, optional, ts_type(property)).unwrap();
+    }
+    out.push_str("}\n");
+}
+
+/// Members joined with |, duplicates dropped
+fn union(members: impl Iterator<Item = String>) -> String {
+    let mut seen: Vec<String> = Vec::new();
+    for member in members {
+        if !seen.contains(&member) {
+            seen.push(member);
+        }
+    }
+    seen.join(" | ")
+}
+
+/// TypeScript type for a schema - the subset schemars emits for our types
+fn ts_type(schema: &Value) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or(reference).to_string();
+    }
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return union(values.iter().map(Value::to_string));
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(members) = schema.get(key).and_then(Value::as_array) {
+            return union(members.iter().map(ts_type));
+        }
+    }
+    match schema.get("type") {
+        Some(Value::String(type_name)) => primitive(type_name, schema),
+        Some(Value::Array(types)) => union(types.iter().filter_map(Value::as_str).map(|t| primitive(t, schema))),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn primitive(type_name: &str, schema: &Value) -> String {
+    match type_name {
+        "integer" | "number" => "number".to_string(),
+        "string" | "boolean" | "null" => type_name.to_string(),
+        "array" => match (schema.get("prefixItems"), schema.get("items")) {
+            (Some(Value::Array(items)), _) => {
+                format!("[{}]", items.iter().map(ts_type).collect::<Vec<_>>().join(", "))
+            }
+            (_, Some(items)) => match ts_type(items) {
+                item if item.contains(" | ") => format!("({})[]", item),
+                item => format!("{}[]", item),
+            },
+            _ => "unknown[]".to_string(),
+        },
+        "object" => match schema.get("additionalProperties") {
+            Some(value @ Value::Object(_)) => format!("Record<string, {}>", ts_type(value)),
+            _ => "Record<string, unknown>".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_to_typescript() {
+        assert_eq!(ts_type(&json!({ "type": ["integer", "null"], "format": "uint32" })), "number | null");
+        assert_eq!(ts_type(&json!({ "type": "array", "prefixItems": [{ "type": "number" }, { "type": "number" }] })), "[number, number]");
+        assert_eq!(ts_type(&json!({ "type": "array", "items": { "anyOf": [{ "$ref": "#/$defs/Weapon" }, { "type": "null" }] } })), "(Weapon | null)[]");
+        assert_eq!(ts_type(&json!({ "oneOf": [{ "const": "burn" }, { "const": "speed_slow" }] })), "\"burn\" | \"speed_slow\"");
+
+        let mut out = String::new();
+        write_declaration(&mut out, "Hit", &json!({
+            "description": "One hit",
+            "type": "object",
+            "required": ["id"],
+            "properties": { "id": { "type": "integer" }, "crit": { "type": "boolean", "description": "Doubled" } }
+        }));
+        assert_eq!(out, "/** One hit */\nexport interface Hit {\n    /** Doubled */\n    crit?: boolean;\n    id: number;\n}\n");
+    }
+
+    #[test]
+    fn test_committed_types_are_current() {
+        for (file, generated) in generated_files() {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+            let committed = std::fs::read_to_string(&path).unwrap_or_default();
+            assert!(committed == generated, "{} is stale - run cargo run --features typegen --bin generate-types", file);
+        }
+    }
+}
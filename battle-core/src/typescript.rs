@@ -0,0 +1,270 @@
+// battle-core/src/typescript.rs
+//
+// TypeScript for the JSON the WASM boundary carries - wasm-pack appends the
+// custom sections below to the generated .d.ts. The payload interfaces are
+// types/battle-core.d.ts, generated from the JsonSchema derives (typegen.rs),
+// so they aren't written out here. The JSON methods themselves stay `string`
+// in wasm-pack's output, so BattleSimulatorJson / SimulationBuilderJson list
+// them again with the payload each string carries (Json<T> is just a string -
+// the real classes satisfy these interfaces as-is).
+//
+// The tests at the bottom check the generated interfaces against what serde
+// actually writes and reads, and that every payload the methods name exists.
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_SECTION: &'static str = TS_PAYLOADS;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_METHODS_SECTION: &'static str = TS_METHODS;
+
+/// The payload interfaces - types/battle-core.d.ts as generated
+pub const TS_PAYLOADS: &str = include_str!("../types/battle-core.d.ts");
+
+/// Json<T> and the JSON methods of the WASM classes
+pub const TS_METHODS: &str = r#"
+/** JSON text carrying a T - parse it with JSON.parse, build it with JSON.stringify */
+export type Json<T> = string;
+
+/**
+ * The JSON methods of WasmBattleSimulator with their payloads. The
+ * constructors take units as Json<BattleUnit[]>.
+ */
+export interface BattleSimulatorJson {
+    add_unit(unit_json: Json<BattleUnit>, current_time: number): void;
+    add_units_batch(units_json: Json<BattleUnit[]>, current_time: number): number;
+    schedule_reinforcements(tick: number, units_json: Json<BattleUnit[]>): void;
+    schedule_reinforcement_wave(wave_json: Json<ReinforcementWave>): void;
+    update_unit_positions(positions_json: Json<PositionUpdate[]>): number;
+    set_faction_config(config_json: Json<FactionConfig>): void;
+    register_template(template_json: Json<UnitTemplate>): void;
+    simulate_tick(dt: number, current_time: number): Json<TickResult>;
+    simulate_tick_delta(dt: number, current_time: number): Json<TickResult>;
+    step(): Json<TickResult>;
+    step_replay(): Json<TickResult | null>;
+    replay_step(): Json<TickResult | null>;
+    manually_fire_weapon(attacker_id: number, target_id: number, weapon_tag: string, current_time: number): Json<WeaponFired>;
+    simulate_n_ticks(n: number, dt: number, start_time: number): Json<TickResult[]>;
+    simulate_until_end(max_ticks: number, dt: number, start_time: number): Json<BattleOutcome>;
+    run_to_completion(dt: number, max_ticks: number): Json<CompletionReport>;
+    snapshot_battle(): Json<BattleSnapshot>;
+    restore_battle(snapshot_json: Json<BattleSnapshot>): void;
+    get_initial_snapshot(): Json<BattleSnapshot>;
+    get_results(): Json<BattleUnit[]>;
+    get_unit(unit_id: number): Json<BattleUnit>;
+    get_units_by_faction(faction_id: number): Json<BattleUnit[]>;
+    get_unit_positions(): Json<PositionUpdate[]>;
+    get_profile_stats(): Json<ProfileStats>;
+    get_victory_state(): Json<VictoryState | null>;
+    /** Prefix -> category */
+    get_weapon_tags(): Json<Record<string, WeaponCategory>>;
+}
+
+/** The JSON methods of WasmSimulationBuilder with their payloads */
+export interface SimulationBuilderJson {
+    add_unit(unit_json: Json<BattleUnit>): void;
+    add_faction_units(faction_id: number, units_json: Json<BattleUnit[]>): void;
+    add_reinforcement_wave(wave_json: Json<ReinforcementWave>): void;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::Value;
+    use crate::battle_unit::*;
+    use crate::simulator::*;
+    use crate::status_effect::*;
+    use crate::PositionUpdate;
+
+    /// (name, optional) for each field of `export interface <name>`
+    fn ts_fields(name: &str) -> Vec<(String, bool)> {
+        let header = format!("export interface {} {{", name);
+        let start = TS_PAYLOADS.find(&header).unwrap_or_else(|| panic!("no interface {}", name)) + header.len();
+        let body = &TS_PAYLOADS[start..start + TS_PAYLOADS[start..].find("\n}").unwrap()];
+        body.lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("/**") && !line.starts_with('*'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(field, _)| match field.strip_suffix('?') {
+                Some(field) => (field.to_string(), true),
+                None => (field.to_string(), false),
+            })
+            .collect()
+    }
+
+    /// String members of `export type <name> = "a" | "b";` (ArmorClass also
+    /// takes a number)
+    fn ts_union(name: &str) -> BTreeSet<String> {
+        let header = format!("export type {} = ", name);
+        let start = TS_PAYLOADS.find(&header).unwrap() + header.len();
+        let line = &TS_PAYLOADS[start..start + TS_PAYLOADS[start..].find(';').unwrap()];
+        line.split('|')
+            .filter_map(|member| member.trim().strip_prefix('"')?.strip_suffix('"').map(str::to_string))
+            .collect()
+    }
+
+    fn json_object<T: Serialize>(value: &T) -> serde_json::Map<String, Value> {
+        match serde_json::to_value(value).unwrap() {
+            Value::Object(map) => map,
+            other => panic!("not an object: {}", other),
+        }
+    }
+
+    /// Every field the interface lists is written, and nothing else
+    fn assert_written<T: Serialize>(name: &str, value: &T) {
+        let written: BTreeSet<String> = json_object(value).keys().cloned().collect();
+        let listed: BTreeSet<String> = ts_fields(name).into_iter().map(|(field, _)| field).collect();
+        assert_eq!(written, listed, "{} fields differ from the TypeScript interface", name);
+    }
+
+    /// Also read back: `?` fields may be left out, the rest may not
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(name: &str, value: &T) {
+        assert_written(name, value);
+        let full = json_object(value);
+        let fields = ts_fields(name);
+
+        let mut minimal = full.clone();
+        minimal.retain(|key, _| fields.iter().any(|(field, optional)| field == key && !optional));
+        if let Err(e) = serde_json::from_value::<T>(Value::Object(minimal)) {
+            panic!("{} without its optional fields: {}", name, e);
+        }
+        for (field, _) in fields.iter().filter(|(_, optional)| !optional) {
+            let mut missing = full.clone();
+            missing.remove(field);
+            assert!(serde_json::from_value::<T>(Value::Object(missing)).is_err(), "{}.{} is optional - mark it with ?", name, field);
+        }
+    }
+
+    fn assert_enum<T: Serialize>(name: &str, variants: &[T]) {
+        let written: BTreeSet<String> = variants.iter()
+            .map(|variant| serde_json::to_value(variant).unwrap().as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(written, ts_union(name), "{} variants differ from the TypeScript union", name);
+    }
+
+    fn unit() -> BattleUnit {
+        BattleUnit::builder()
+            .id(1)
+            .faction(1)
+            .weapons(vec![Weapon::builder().tag("LASER").build()])
+            .build()
+            .unwrap()
+    }
+
+    fn shot() -> WeaponFired {
+        WeaponFired {
+            attacker_id: 1,
+            target_id: 2,
+            weapon_type: "LASER".to_string(),
+            impact_time: 3,
+            ammo_remaining: Some(4),
+            hit: false,
+            intercepted: true,
+            ax: Some(0.0),
+            ay: Some(0.0),
+            az: Some(0.0),
+            tx: Some(1.0),
+            ty: Some(0.0),
+            tz: Some(0.0),
+            miss_offset: Some((0.0, 2.0, 0.0)),
+        }
+    }
+
+    #[test]
+    fn test_host_payloads_match_typescript() {
+        let effect = EffectSpec { kind: StatusEffectKind::Burn, magnitude: 1.0, duration: 2.0 };
+        assert_round_trip("EffectSpec", &effect);
+        assert_round_trip("StatusEffect", &effect.instantiate(0, 0.05, 1));
+        assert_round_trip("WeaponStats", &WeaponStats::default());
+        assert_round_trip("Weapon", &Weapon::builder().tag("LASER").build());
+        assert_round_trip("BattleUnit", &unit());
+        assert_round_trip("DroneHangar", &DroneHangar { template: Box::new(unit()), count: 2, launch_interval: 1.0, ..Default::default() });
+        assert_round_trip("PositionUpdate", &PositionUpdate { id: 1, x: 0.0, y: 0.0, z: 0.0, clear_target: true });
+        assert_round_trip("FactionConfig", &crate::factions::FactionConfig::new(1));
+        assert_round_trip("UnitTemplate", &crate::templates::UnitTemplate { template_id: "fighter".to_string(), base_unit: unit() });
+        assert_round_trip("ReinforcementWave", &ReinforcementWave { arrival_tick: 1, units: vec![unit()], faction_id: Some(2), spawn: None });
+    }
+
+    #[test]
+    fn test_results_match_typescript() {
+        assert_written("TickResult", &TickResult::empty(1, false));
+        assert_written("WeaponFired", &shot());
+        // Without ammo, interception or positions only the required fields are written
+        let bare = WeaponFired { ammo_remaining: None, intercepted: false, ax: None, ay: None, az: None, tx: None, ty: None, tz: None, miss_offset: None, ..shot() };
+        let written: BTreeSet<String> = json_object(&bare).keys().cloned().collect();
+        let required: BTreeSet<String> = ts_fields("WeaponFired").into_iter().filter(|(_, optional)| !optional).map(|(field, _)| field).collect();
+        assert_eq!(written, required);
+
+        assert_written("MovedUnit", &MovedUnit { id: 1, x: 0.0, y: 0.0, z: 0.0, vx: 0.0, vy: 0.0, vz: 0.0 });
+        assert_written("DamagedUnit", &DamagedUnit {
+            id: 1,
+            hp: 1.0,
+            shield: 0.0,
+            hull_damage: 2.0,
+            shield_damage: 3.0,
+            primary_attacker_id: Some(7),
+            damage_type: "kinetic".into(),
+        });
+        assert_written("DestroyedUnit", &DestroyedUnit { id: 1, overkill: 1.0 });
+        assert_written("MissileRetargeted", &MissileRetargeted { attacker_id: 1, from_id: 2, to_id: 3, weapon_type: "HM".to_string() });
+        assert_written("MissileFizzled", &MissileFizzled { attacker_id: 1, target_id: 2, weapon_type: "HM".to_string() });
+        assert_written("SelfDestructed", &SelfDestructed { id: 1, x: 0.0, y: 1.0, z: 2.0, radius: 50.0, depth: 0 });
+        assert_written("SensorContact", &crate::detection::SensorContact { faction_id: 1, unit_id: 2 });
+        assert_written("RepairedUnit", &RepairedUnit { id: 1, hp: 1.0, shield: 0.0 });
+        assert_written("UnitEnergy", &UnitEnergy { id: 1, energy: 1.0 });
+        assert_written("UnitEffects", &UnitEffects { id: 1, effects: Vec::new() });
+
+        let mut sim = BattleSimulator::new(vec![unit(), BattleUnit { id: 2, faction_id: 2, pos_x: 50.0, ..unit() }], 0.0);
+        let report = sim.run_to_completion(0.05, 10);
+        assert_written("CompletionReport", &report);
+        assert_written("SurvivingUnit", &report.survivors[0]);
+        assert_written("FactionSummary", &report.factions[0]);
+        assert_written("BattleOutcome", &sim.simulate_until_end(10, 0.05, 1.0));
+        assert_written("BattleSnapshot", &sim.snapshot());
+
+        let stats = crate::profiling::PhaseProfiler::default().stats(10);
+        assert_written("ProfileStats", &stats);
+        assert_written("PhaseStats", &stats.phases[0]);
+        assert_written("VictoryState", &crate::objectives::VictoryState { winner: None, condition: "elimination".to_string(), tick: 1 });
+    }
+
+    #[test]
+    fn test_enums_match_typescript() {
+        use ArmorClass as A;
+        assert_enum("ArmorClass", &[A::None, A::Light, A::Medium, A::Heavy, A::Super]);
+        use ShipClass as S;
+        assert_enum("ShipClass", &[S::Fighter, S::Corvette, S::Frigate, S::Destroyer, S::Cruiser, S::Battleship, S::Carrier, S::Station]);
+        assert_enum("UnitState", &[UnitState::Active, UnitState::Destroyed, UnitState::Withdrawn]);
+        assert_enum("Stance", &[Stance::Aggressive, Stance::Defensive, Stance::HoldFire]);
+        use StatusEffectKind as K;
+        assert_enum("StatusEffectKind", &[K::SpeedSlow, K::ShieldDisrupt, K::Burn]);
+        use crate::weapons::WeaponCategory as W;
+        assert_enum("WeaponCategory", &[W::PointDefense, W::Siege, W::Kinetic, W::Energy, W::Explosive, W::Tractor, W::Ion, W::Emp]);
+        use CompletionReason as R;
+        assert_enum("CompletionReason", &[R::Elimination, R::Objective, R::Stalemate, R::TickCap]);
+        assert_enum("ProfilePhase", &crate::profiling::Phase::ALL);
+    }
+
+    #[test]
+    fn test_method_payloads_are_declared() {
+        let mut names = BTreeSet::new();
+        for rest in TS_METHODS.split("Json<").skip(1) {
+            let inner = &rest[..rest.find('>').unwrap()];
+            for word in inner.split(|c: char| !c.is_ascii_alphanumeric()) {
+                if word.starts_with(|c: char| c.is_ascii_uppercase()) && word != "T" && word != "Record" {
+                    names.insert(word);
+                }
+            }
+        }
+        assert!(names.contains("TickResult"));
+        for name in names {
+            let declared = [format!("export interface {} {{", name), format!("export type {} = ", name)];
+            assert!(declared.iter().any(|decl| TS_PAYLOADS.contains(decl.as_str())), "{} isn't in types/battle-core.d.ts", name);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// battle-core/src/validation.rs
+//
+// Checks on unit data from outside the simulator (constructor, add_unit,
+// reinforcements). Duplicate ids are the dangerous case - every lookup takes
+// the first unit with an id, so a duplicate silently splits one unit's
+// targeting, position sync and damage credit across two.
+
+use std::collections::HashSet;
+use std::fmt;
+use serde::Serialize;
+use crate::battle_unit::{check_power, BattleUnit, DRONE_ID_BASE};
+
+/// A unit the simulator won't accept
+///
+/// JSON: { "kind": "duplicate_id", "id": 4021 }, { "kind": "non_finite",
+/// "id": 7, "field": "pos_x" }, ...
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationError {
+    /// Another unit (in the batch or already in the battle) has this id
+    DuplicateId { id: u32 },
+    /// Id in the carrier drone range (DRONE_ID_BASE and up) on a unit that
+    /// isn't a drone
+    ReservedId { id: u32 },
+    /// NaN or infinite position / hp / shield
+    NonFinite { id: u32, field: &'static str },
+    /// A max_* stat or self-destruct value below zero
+    Negative { id: u32, field: &'static str },
+    /// Weapon that can't reach anything (max_range <= 0)
+    WeaponRange { id: u32, weapon: String },
+    /// power_shield / power_weapons / power_engines outside 0..=1 or not
+    /// summing to 1.0
+    PowerSplit { id: u32 },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::DuplicateId { id } => write!(f, "unit {}: duplicate id", id),
+            ValidationError::ReservedId { id } => write!(f, "unit {}: id is reserved for carrier drones", id),
+            ValidationError::NonFinite { id, field } => write!(f, "unit {}: {} is not a finite number", id, field),
+            ValidationError::Negative { id, field } => write!(f, "unit {}: {} is negative", id, field),
+            ValidationError::WeaponRange { id, weapon } => write!(f, "unit {}: weapon {} has max_range <= 0", id, weapon),
+            ValidationError::PowerSplit { id } => write!(f, "unit {}: power fractions must each be 0..=1 and sum to 1.0", id),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Problems with one unit on its own (ids aren't checked)
+pub fn validate_unit(unit: &BattleUnit, problems: &mut Vec<ValidationError>) {
+    let id = unit.id;
+    if id >= DRONE_ID_BASE && unit.carrier_id.is_none() {
+        problems.push(ValidationError::ReservedId { id });
+    }
+
+    let finite = [
+        ("pos_x", unit.pos_x),
+        ("pos_y", unit.pos_y),
+        ("pos_z", unit.pos_z),
+        ("hp", unit.hp),
+        ("max_hp", unit.max_hp),
+        ("shield", unit.shield),
+        ("max_shield", unit.max_shield),
+    ];
+    for (field, value) in finite {
+        if !value.is_finite() {
+            problems.push(ValidationError::NonFinite { id, field });
+        }
+    }
+
+    let non_negative = [
+        ("max_hp", unit.max_hp),
+        ("max_shield", unit.max_shield),
+        ("max_speed", unit.max_speed),
+        ("max_energy", unit.max_energy),
+        ("self_destruct_damage", unit.self_destruct_damage),
+        ("self_destruct_radius", unit.self_destruct_radius),
+        ("self_destruct_trigger_radius", unit.self_destruct_trigger_radius),
+        ("stealth_modifier", unit.stealth_modifier),
+    ];
+    for (field, value) in non_negative {
+        if value < 0.0 {
+            problems.push(ValidationError::Negative { id, field });
+        }
+    }
+
+    for weapon in &unit.weapons {
+        if weapon.max_range.is_nan() || weapon.max_range <= 0.0 {
+            problems.push(ValidationError::WeaponRange { id, weapon: weapon.tag.clone() });
+        }
+    }
+
+    if check_power(unit.power_shield, unit.power_weapons, unit.power_engines).is_err() {
+        problems.push(ValidationError::PowerSplit { id });
+    }
+}
+
+/// Every problem with `units` joining a battle whose units (dead, withdrawn
+/// and scheduled ones included) have `existing_ids`
+///
+/// Empty when they can all be added.
+pub fn validate_units(units: &[BattleUnit], existing_ids: impl IntoIterator<Item = u32>) -> Vec<ValidationError> {
+    let mut problems = Vec::new();
+    let mut seen: HashSet<u32> = existing_ids.into_iter().collect();
+    for unit in units {
+        if !seen.insert(unit.id) {
+            problems.push(ValidationError::DuplicateId { id: unit.id });
+        }
+        validate_unit(unit, &mut problems);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle_unit::Weapon;
+
+    fn unit(id: u32) -> BattleUnit {
+        BattleUnit::builder()
+            .id(id)
+            .faction(1)
+            .weapons(vec![Weapon::builder().tag("LASER").build()])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_each_rejection_reason() {
+        assert!(validate_units(&[unit(1), unit(2)], [3]).is_empty());
+
+        assert_eq!(validate_units(&[unit(1), unit(1)], []), vec![ValidationError::DuplicateId { id: 1 }]);
+        assert_eq!(validate_units(&[unit(3)], [3]), vec![ValidationError::DuplicateId { id: 3 }]);
+        assert_eq!(validate_units(&[unit(DRONE_ID_BASE)], []), vec![ValidationError::ReservedId { id: DRONE_ID_BASE }]);
+        let drone = BattleUnit { carrier_id: Some(1), ..unit(DRONE_ID_BASE) };
+        assert!(validate_units(&[drone], []).is_empty());
+
+        let mut lost = unit(4);
+        lost.pos_y = f32::NAN;
+        lost.hp = f32::INFINITY;
+        assert_eq!(validate_units(&[lost], []), vec![
+            ValidationError::NonFinite { id: 4, field: "pos_y" },
+            ValidationError::NonFinite { id: 4, field: "hp" },
+        ]);
+
+        let mut inverted = unit(5);
+        inverted.max_shield = -1.0;
+        inverted.max_speed = -5.0;
+        assert_eq!(validate_units(&[inverted], []), vec![
+            ValidationError::Negative { id: 5, field: "max_shield" },
+            ValidationError::Negative { id: 5, field: "max_speed" },
+        ]);
+
+        let mut blunt = unit(6);
+        blunt.weapons[0].max_range = 0.0;
+        blunt.weapons.push(Weapon::builder().tag("RAIL").range(50.0, f32::NAN).build());
+        let problems = validate_units(&[blunt], []);
+        assert_eq!(problems, vec![
+            ValidationError::WeaponRange { id: 6, weapon: "LASER".to_string() },
+            ValidationError::WeaponRange { id: 6, weapon: "RAIL".to_string() },
+        ]);
+        assert_eq!(problems[0].to_string(), "unit 6: weapon LASER has max_range <= 0");
+
+        let overdriven = BattleUnit { power_weapons: 0.9, ..unit(8) };
+        assert_eq!(validate_units(&[overdriven], []), vec![ValidationError::PowerSplit { id: 8 }]);
+        assert_eq!(
+            serde_json::to_string(&ValidationError::NonFinite { id: 7, field: "pos_x" }).unwrap(),
+            r#"{"kind":"non_finite","id":7,"field":"pos_x"}"#
+        );
+    }
+}
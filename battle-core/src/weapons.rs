@@ -4,22 +4,54 @@
 // 1. Proper armor effectiveness matrix (not just binary 50%)
 // 2. Added weapon category support for special targeting
 // 3. Improved logging for debugging
+// 4. Tag checks are ASCII case-insensitive without allocating (tag_contains / tag_starts_with)
+// 5. Added try_repair() for repair weapons (never fire through try_fire_weapon)
+// 6. Per-shot lines log at Debug, per-weapon detail at Trace (see logging.rs)
+// 7. Disabled weapons never fire; damage calculation split out into shot_damage()
+// 8. Weapons hold fire when the unit's capacitor can't pay energy_cost
+// 9. Fire sequences are indexed by tick + sequence_offset (per-weapon phase)
+// 10. Added hit_chance() - tracking vs target speed / distance / signature;
+//     try_fire_weapon returns it with the damage (the simulator rolls it)
+// 11. Shaken attackers (low morale) hit half as often
+// 12. Only siege weapons fire at disabled units
+// 13. Per-ShipClass weapon restrictions (is_weapon_allowed /
+//     disable_restricted_weapons); try_fire_weapon debug-asserts them
+// 14. Armor effectiveness compares ArmorClass tiers
+// 15. Added WeaponBuilder (Weapon::builder())
+// 16. try_fire_weapon applies the attacker's FactionConfig - damage_multiplier,
+//     and other factions' special weapons hold fire
+// 17. Canonical weapons: Weapon::laser() / missile() / point_defense()
+// 18. Weapon categories come from a tag prefix registry (WeaponTagRegistry) -
+//     weapon.category replaces the hardcoded "AM" / "NM" prefix checks
+// 19. try_fire_weapon scales damage by the attacker's weapon power
+// 20. Added is_guided() - missiles, rockets and nukes, which reacquire (or
+//     fizzle) when their target dies before they land
+// 21. WeaponCategory::ALL and name() - the serialized name, for tallies and
+//     DamagedUnit.damage_type
+// 22. Added intercept_chance() - point defense against missiles arriving
+//     together, diluted by saturation_factor
 
-use crate::battle_unit::{BattleUnit, Weapon};
-use crate::log;
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::battle_unit::{ArmorClass, BattleUnit, ShipClass, Weapon};
+use crate::factions::{self, FactionConfigs};
+use crate::log_at;
+
+/// Weapon tag prefixes each ship class may carry - classes not listed may
+/// carry anything
+pub type WeaponClassRestrictions = HashMap<ShipClass, Vec<String>>;
 
 /// Calculate armor effectiveness multiplier
 /// 
-/// Armor Types: None=0, Light=1, Medium=2, Heavy=3, Super=4
-/// 
-/// Damage reduction based on (target_armor - weapon.target_armor_max):
+/// Damage reduction based on how many tiers target_armor is above
+/// weapon.target_armor_max:
 /// - 0 or less: 100% damage (weapon can penetrate)
 /// - 1: 50% damage
 /// - 2: 25% damage  
 /// - 3+: 10% damage (heavily armored target)
 #[inline]
-fn calculate_armor_effectiveness(target_armor: f32, weapon_armor_max: f32) -> f32 {
-    let armor_diff = (target_armor - weapon_armor_max) as i32;
+fn calculate_armor_effectiveness(target_armor: ArmorClass, weapon_armor_max: ArmorClass) -> f32 {
+    let armor_diff = target_armor.tier() as i32 - weapon_armor_max.tier() as i32;
     
     match armor_diff {
         d if d <= 0 => 1.0,   // Full damage - weapon can handle this armor
@@ -48,18 +80,222 @@ fn calculate_range_falloff(distance: f32, optimal_range: f32, max_range: f32) ->
     }
 }
 
-/// Check if weapon is a point defense (Anti-Missile) weapon
+/// Signature radius the tracking formula is scaled to
+const REFERENCE_SIGNATURE: f32 = 40.0;
+
+/// Chance (0-1) that a shot from `weapon` connects
+///
+/// Simplified EVE turret formula:
+///   angular = target_speed / distance                                (rad/s)
+///   term    = (angular / tracking) * (REFERENCE_SIGNATURE / signature_radius)
+///   chance  = 0.5 ^ (term^2)
+///
+/// A stationary target is always hit. A 40-radius target crossing at exactly
+/// the weapon's tracking speed is hit half the time; smaller, faster or closer
+/// targets are harder to hit, bigger ones easier. Weapons without tracking
+/// and targets without signature_radius always hit.
+pub fn hit_chance(weapon: &Weapon, target: &BattleUnit, target_speed: f32, dist: f32) -> f32 {
+    if weapon.tracking <= 0.0 || target.signature_radius <= 0.0 || target_speed <= 0.0 {
+        return 1.0;
+    }
+    // Point blank would make the angular speed blow up
+    let angular = target_speed / dist.max(1.0);
+    let term = (angular / weapon.tracking) * (REFERENCE_SIGNATURE / target.signature_radius);
+    0.5f32.powf(term * term)
+}
+
+/// Case-insensitive (ASCII) substring check on a weapon tag - no allocation
 #[inline]
-pub fn is_point_defense(weapon: &Weapon) -> bool {
-    weapon.tag.starts_with("AM") || 
-    weapon.tag.to_lowercase().contains("anti-missile")
+pub fn tag_contains(tag: &str, needle: &str) -> bool {
+    let (tag, needle) = (tag.as_bytes(), needle.as_bytes());
+    needle.is_empty() || tag.windows(needle.len()).any(|w| w.eq_ignore_ascii_case(needle))
 }
 
-/// Check if weapon is a siege weapon (Nukes)
+/// Missiles, rockets and nukes - shots that can turn after another target when
+/// theirs dies before they land (see BattleConfig::missile_reacquire_radius)
 #[inline]
-pub fn is_siege_weapon(weapon: &Weapon) -> bool {
-    weapon.tag.starts_with("NM") || 
-    weapon.tag.to_lowercase().contains("nuke")
+pub fn is_guided(weapon: &Weapon) -> bool {
+    match weapon.category {
+        WeaponCategory::Explosive | WeaponCategory::Siege => true,
+        WeaponCategory::PointDefense => false,
+        _ => tag_contains(&weapon.tag, "missile") || tag_contains(&weapon.tag, "rocket"),
+    }
+}
+
+/// Chance a point-defense weapon shoots down each of `inbound` missiles
+/// arriving at once - the more there are, the more get through:
+///   am_intercept_chance / (1 + (inbound - 1) * saturation_factor)
+#[inline]
+pub fn intercept_chance(weapon: &Weapon, inbound: u32) -> f32 {
+    weapon.am_intercept_chance / (1.0 + inbound.saturating_sub(1) as f32 * weapon.saturation_factor)
+}
+
+/// Case-insensitive (ASCII) prefix check on a weapon tag - no allocation
+#[inline]
+pub fn tag_starts_with(tag: &str, prefix: &str) -> bool {
+    tag.as_bytes()
+        .get(..prefix.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(prefix.as_bytes()))
+}
+
+/// Check if a unit of this class may carry the weapon - its tag has to start
+/// with one of the class's allowed prefixes (case-insensitive)
+#[inline]
+pub fn is_weapon_allowed(restrictions: &WeaponClassRestrictions, class: ShipClass, weapon: &Weapon) -> bool {
+    restrictions.get(&class)
+        .is_none_or(|allowed| allowed.iter().any(|prefix| tag_starts_with(&weapon.tag, prefix)))
+}
+
+/// Disable every weapon the unit's class may not carry, logging each one
+///
+/// Returns how many were newly disabled.
+pub fn disable_restricted_weapons(unit: &mut BattleUnit, restrictions: &WeaponClassRestrictions) -> u32 {
+    let mut count = 0;
+    for weapon in unit.weapons.iter_mut() {
+        if !weapon.is_disabled && !is_weapon_allowed(restrictions, unit.ship_class, weapon) {
+            weapon.is_disabled = true;
+            count += 1;
+            log_at!(Error,
+                "[Weapon] Unit {} ({:?}) can't carry {} - weapon disabled",
+                unit.id, unit.ship_class, weapon.tag
+            );
+        }
+    }
+    count
+}
+
+/// What a weapon is, as far as targeting and interception care
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "typegen", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponCategory {
+    /// Shoots down missiles, never fires at units
+    PointDefense,
+    /// Only fires at stations (and finishes off disabled units)
+    Siege,
+    #[default]
+    Kinetic,
+    Energy,
+    Explosive,
+    Tractor,
+    Ion,
+    Emp,
+}
+
+impl WeaponCategory {
+    /// Every category, in declaration order - `category as usize` indexes it
+    pub const ALL: [WeaponCategory; 8] = [
+        WeaponCategory::PointDefense,
+        WeaponCategory::Siege,
+        WeaponCategory::Kinetic,
+        WeaponCategory::Energy,
+        WeaponCategory::Explosive,
+        WeaponCategory::Tractor,
+        WeaponCategory::Ion,
+        WeaponCategory::Emp,
+    ];
+
+    /// JSON name ("point_defense", "energy", ...)
+    pub fn name(self) -> &'static str {
+        match self {
+            WeaponCategory::PointDefense => "point_defense",
+            WeaponCategory::Siege => "siege",
+            WeaponCategory::Kinetic => "kinetic",
+            WeaponCategory::Energy => "energy",
+            WeaponCategory::Explosive => "explosive",
+            WeaponCategory::Tractor => "tractor",
+            WeaponCategory::Ion => "ion",
+            WeaponCategory::Emp => "emp",
+        }
+    }
+}
+
+impl std::str::FromStr for WeaponCategory {
+    type Err = String;
+
+    /// Category from its JSON name, case-insensitive ("point_defense", "EMP")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WeaponCategory::ALL.into_iter()
+            .find(|category| category.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown weapon category '{}'", s))
+    }
+}
+
+/// Tag prefixes every registry starts with
+pub const DEFAULT_WEAPON_TAG_PREFIXES: &[(&str, WeaponCategory)] = &[
+    ("AM", WeaponCategory::PointDefense),   // Anti-Missile
+    ("NM", WeaponCategory::Siege),          // Nukes
+    ("LASER", WeaponCategory::Energy),
+    ("HM", WeaponCategory::Explosive),      // Heavy Missiles
+    ("SM", WeaponCategory::Explosive),      // Small Missiles
+    ("CR", WeaponCategory::Explosive),      // Concussion Rockets
+    ("PR", WeaponCategory::Explosive),      // Proton Rockets
+    ("ION", WeaponCategory::Ion),
+    ("EMP", WeaponCategory::Emp),
+    ("TRACTOR", WeaponCategory::Tractor),
+];
+
+/// Category of the longest prefix the tag starts with (case-insensitive)
+fn longest_prefix<'a>(tag: &str, prefixes: impl Iterator<Item = (&'a str, WeaponCategory)>) -> Option<WeaponCategory> {
+    prefixes
+        .filter(|(prefix, _)| tag_starts_with(tag, prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, category)| category)
+}
+
+/// Category for a tag no prefix matched - spelled-out point defense and
+/// nukes still count, anything else is kinetic
+fn keyword_category(tag: &str) -> WeaponCategory {
+    if tag_contains(tag, "anti-missile") {
+        WeaponCategory::PointDefense
+    } else if tag_contains(tag, "nuke") {
+        WeaponCategory::Siege
+    } else {
+        WeaponCategory::Kinetic
+    }
+}
+
+/// Category a tag gets from the default registry - what weapons built outside
+/// a simulator start with
+pub fn default_category(tag: &str) -> WeaponCategory {
+    longest_prefix(tag, DEFAULT_WEAPON_TAG_PREFIXES.iter().copied())
+        .unwrap_or_else(|| keyword_category(tag))
+}
+
+/// Tag prefix -> category lookup. Units joining a simulator have each
+/// weapon's category set from its registry (longest matching prefix wins).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeaponTagRegistry {
+    pub prefixes: HashMap<String, WeaponCategory>,
+}
+
+impl Default for WeaponTagRegistry {
+    fn default() -> Self {
+        WeaponTagRegistry {
+            prefixes: DEFAULT_WEAPON_TAG_PREFIXES.iter()
+                .map(|&(prefix, category)| (prefix.to_string(), category))
+                .collect(),
+        }
+    }
+}
+
+impl WeaponTagRegistry {
+    /// Add or replace a prefix
+    pub fn register(&mut self, prefix: &str, category: WeaponCategory) {
+        self.prefixes.insert(prefix.to_string(), category);
+    }
+
+    pub fn categorize(&self, tag: &str) -> WeaponCategory {
+        longest_prefix(tag, self.prefixes.iter().map(|(prefix, &category)| (prefix.as_str(), category)))
+            .unwrap_or_else(|| keyword_category(tag))
+    }
+
+    /// Set the category of each of the unit's weapons
+    pub fn categorize_weapons(&self, unit: &mut BattleUnit) {
+        for weapon in unit.weapons.iter_mut() {
+            weapon.category = self.categorize(&weapon.tag);
+        }
+    }
 }
 
 /// Check if weapon fires projectiles that can be intercepted
@@ -67,51 +303,81 @@ pub fn is_siege_weapon(weapon: &Weapon) -> bool {
 pub fn is_interceptable(weapon: &Weapon) -> bool {
     // Missiles and torpedoes can be intercepted
     // Lasers, beams, and kinetics cannot
-    let tag_lower = weapon.tag.to_lowercase();
-    tag_lower.contains("missile") || 
-    tag_lower.contains("rocket") || 
-    tag_lower.contains("torpedo") ||
-    tag_lower.starts_with("nm") ||  // Nukes
-    tag_lower.starts_with("hm") ||  // Heavy Missiles
-    tag_lower.starts_with("sm") ||  // Small Missiles
-    tag_lower.starts_with("cr") ||  // Concussion Rockets
-    tag_lower.starts_with("pr")     // Proton Rockets
+    let tag = weapon.tag.as_str();
+    tag_contains(tag, "missile") || 
+    tag_contains(tag, "rocket") || 
+    tag_contains(tag, "torpedo") ||
+    tag_starts_with(tag, "nm") ||  // Nukes
+    tag_starts_with(tag, "hm") ||  // Heavy Missiles
+    tag_starts_with(tag, "sm") ||  // Small Missiles
+    tag_starts_with(tag, "cr") ||  // Concussion Rockets
+    tag_starts_with(tag, "pr")     // Proton Rockets
 }
 
 /// Check if weapon can fire this tick based on sequence
+///
+/// Indexed by tick + the weapon's sequence_offset so weapons sharing a pattern
+/// don't fire in lockstep.
 #[inline]
 pub fn can_fire_sequence(weapon: &Weapon, tick: u64) -> bool {
     if weapon.sequence.is_empty() {
         return true;  // No sequence = always fire (use cooldown only)
     }
-    let idx = (tick as usize) % weapon.sequence.len();
+    let phase = tick.wrapping_add(weapon.sequence_offset.unwrap_or(0) as u64);
+    let idx = (phase % weapon.sequence.len() as u64) as usize;
     weapon.sequence[idx]
 }
 
 /// Check if weapon can fire and calculate damage
 /// 
-/// Returns Some(damage) if weapon fires, None if on cooldown or out of range
+/// Returns Some((damage, hit_chance)) if weapon fires, None if on cooldown or
+/// out of range. The caller rolls hit_chance - a miss still spends the shot.
+/// Damage includes the attacker's faction damage_multiplier and weapon power.
 pub fn try_fire_weapon(
     attacker: &BattleUnit,
     target: &BattleUnit,
     weapon: &Weapon,
     current_time: f64,
     current_tick: u64,
-) -> Option<f32> {
+    restrictions: &WeaponClassRestrictions,
+    faction_configs: &FactionConfigs,
+) -> Option<(f32, f32)> {
     // Check sequence first (cheap check)
     if !can_fire_sequence(weapon, current_tick) {
         return None;
     }
 
+    // Out of ammo (or reloading), knocked out, or not enough energy
+    if !weapon.has_ammo() || weapon.is_disabled || !attacker.has_energy_for(weapon.energy_cost) {
+        return None;
+    }
+
+    // Another faction's special weapon
+    if !factions::may_fire(faction_configs, attacker.faction_id, &weapon.tag) {
+        return None;
+    }
+
+    // Restricted weapons are disabled when the unit joins or the restriction is set
+    debug_assert!(
+        is_weapon_allowed(restrictions, attacker.ship_class, weapon),
+        "unit {} ({:?}) firing restricted weapon {}", attacker.id, attacker.ship_class, weapon.tag
+    );
+
+    // Still locking on to a newly acquired target (independent turrets don't
+    // use the unit's target, so the unit lock doesn't apply to them)
+    if !weapon.independent_targeting && attacker.is_locking(current_time) {
+        return None;
+    }
+
     // Check cooldown
     let time_since_fired = current_time - weapon.last_fired;
     if time_since_fired < weapon.cooldown as f64 {
         // DEBUG: Log cooldown block (only occasionally to avoid spam)
-        if attacker.id % 100 == 0 && current_tick % 20 == 0 {
-            log(&format!(
+        if attacker.id.is_multiple_of(100) && current_tick.is_multiple_of(20) {
+            log_at!(Trace,
                 "[Weapon] Unit {} {} on cooldown: {:.2}s remaining",
                 attacker.id, weapon.tag, weapon.cooldown as f64 - time_since_fired
-            ));
+            );
         }
         return None;
     }
@@ -121,31 +387,52 @@ pub fn try_fire_weapon(
 
     // Check range
     if dist > weapon.max_range {
-        if attacker.id % 100 == 0 && current_tick % 20 == 0 {
-            log(&format!(
+        if attacker.id.is_multiple_of(100) && current_tick.is_multiple_of(20) {
+            log_at!(Trace,
                 "[Weapon] Unit {} {} out of range: dist={:.1} > max={:.1}",
                 attacker.id, weapon.tag, dist, weapon.max_range
-            ));
+            );
         }
         return None;
     }
 
     // ✅ Special: Siege weapons (Nukes) should only target stations
-    if is_siege_weapon(weapon) && !target.is_station {
-        if attacker.id % 100 == 0 && current_tick % 20 == 0 {
-            log(&format!(
+    if weapon.category == WeaponCategory::Siege && !target.is_station {
+        if attacker.id.is_multiple_of(100) && current_tick.is_multiple_of(20) {
+            log_at!(Trace,
                 "[Weapon] Unit {} {} is siege weapon, skipping non-station target {}",
                 attacker.id, weapon.tag, target.id
-            ));
+            );
         }
         return None;  // Don't fire nukes at ships
     }
 
+    // Disabled units are out of the fight - only siege weapons finish them off
+    if target.is_disabled && weapon.category != WeaponCategory::Siege {
+        return None;
+    }
+
     // ✅ Special: Point defense weapons should only target incoming missiles (handled elsewhere)
-    if is_point_defense(weapon) {
+    if weapon.category == WeaponCategory::PointDefense {
         return None;  // AM weapons handled in missile interception phase
     }
 
+    // Repair weapons only fire at allies (see try_repair)
+    if weapon.is_repair {
+        return None;
+    }
+
+    let chance = hit_chance(weapon, target, target.speed(current_time), dist) * attacker.accuracy_multiplier();
+    let damage = shot_damage(attacker, target, weapon, dist)
+        * factions::damage_multiplier(faction_configs, attacker.faction_id)
+        * attacker.effective_damage_multiplier();
+    Some((damage, chance))
+}
+
+/// Damage of one shot at this distance - range falloff and armor applied, minimum 1
+///
+/// No cooldown / range / target checks; callers decide whether the weapon fires.
+pub fn shot_damage(attacker: &BattleUnit, target: &BattleUnit, weapon: &Weapon, dist: f32) -> f32 {
     // Calculate base damage per shot
     // DPS is already per-second from battle-data.service.js
     // Damage per shot = DPS / fire_rate (shots per second)
@@ -162,10 +449,10 @@ pub fn try_fire_weapon(
     if range_mult < 1.0 {
         let old_damage = damage;
         damage *= range_mult;
-        log(&format!(
+        log_at!(Trace,
             "[Weapon] Unit {} {} range falloff: dist={:.1} optimal={:.1} max={:.1} mult={:.2} dmg {:.1}->{:.1}",
             attacker.id, weapon.tag, dist, weapon.optimal_range, weapon.max_range, range_mult, old_damage, damage
-        ));
+        );
     }
 
     // ✅ Apply armor effectiveness
@@ -173,21 +460,54 @@ pub fn try_fire_weapon(
     if armor_mult < 1.0 {
         let old_damage = damage;
         damage *= armor_mult;
-        log(&format!(
+        log_at!(Trace,
             "[Weapon] Unit {} {} armor penalty: target_armor={} weapon_max={} mult={:.2} dmg {:.1}->{:.1}",
-            attacker.id, weapon.tag, target.armor as i32, weapon.target_armor_max as i32, armor_mult, old_damage, damage
-        ));
+            attacker.id, weapon.tag, target.armor.name(), weapon.target_armor_max.name(), armor_mult, old_damage, damage
+        );
     }
 
     // Ensure minimum damage of 1
     damage = damage.max(1.0);
 
-    log(&format!(
+    log_at!(Debug,
         "[Weapon] Unit {} -> {} : {} dmg={:.1} (base={:.1} range_mult={:.2} armor_mult={:.2})",
         attacker.id, target.id, weapon.tag, damage, damage_per_shot, range_mult, armor_mult
-    ));
+    );
+
+    damage
+}
+
+/// Check if a repair weapon can fire at an ally and calculate the repair amount
+///
+/// Same sequence / ammo / energy / cooldown / range rules as try_fire_weapon, but no
+/// range falloff or armor - the full amount per shot is restored. Picking a
+/// friendly target is up to find_best_repair_target.
+pub fn try_repair(
+    healer: &BattleUnit,
+    target: &BattleUnit,
+    weapon: &Weapon,
+    current_time: f64,
+    current_tick: u64,
+) -> Option<f32> {
+    if !weapon.is_repair || weapon.is_disabled || !target.is_alive() {
+        return None;
+    }
+    if !can_fire_sequence(weapon, current_tick) || !weapon.has_ammo() || !healer.has_energy_for(weapon.energy_cost) {
+        return None;
+    }
+    if current_time - weapon.last_fired < weapon.cooldown as f64 {
+        return None;
+    }
+    if healer.distance(target) > weapon.max_range {
+        return None;
+    }
 
-    Some(damage)
+    let amount = if weapon.fire_rate > 0.0 {
+        weapon.dps / weapon.fire_rate
+    } else {
+        weapon.dps
+    };
+    Some(amount)
 }
 
 /// Try to intercept an incoming missile with point defense
@@ -200,7 +520,7 @@ pub fn try_intercept_missile(
     missile_pos_z: f32,
     current_time: f64,
 ) -> bool {
-    if !is_point_defense(weapon) {
+    if weapon.category != WeaponCategory::PointDefense {
         return false;
     }
 
@@ -221,32 +541,160 @@ pub fn try_intercept_missile(
     }
 
     // Successfully intercepted!
-    log(&format!(
+    log_at!(Debug,
         "[AM] Unit {} intercepted missile at dist={:.1}",
         defender.id, dist
-    ));
+    );
 
     true
 }
 
+impl Weapon {
+    pub fn builder() -> WeaponBuilder {
+        WeaponBuilder::default()
+    }
+
+    /// Hitscan beam - 80 optimal / 100 max, one shot a second
+    pub fn laser(dps: f32) -> Weapon {
+        Weapon::builder().tag("LASER").dps(dps).range(80.0, 100.0).build()
+    }
+
+    /// Long-range interceptable projectile - 150 optimal / 200 max, a
+    /// volley every 4 seconds
+    pub fn missile(dps: f32) -> Weapon {
+        Weapon::builder().tag("MISSILE").dps(dps).range(150.0, 200.0).fire_rate(0.25).cooldown(4.0).build()
+    }
+
+    /// Anti-missile turret (AM-PD) - 20 optimal / 30 max, four shots a second
+    pub fn point_defense(dps: f32) -> Weapon {
+        Weapon::builder().tag("PD").dps(dps).range(20.0, 30.0).fire_rate(4.0).cooldown(0.25).as_point_defense().build()
+    }
+}
+
+/// Chainable Weapon construction - anything not set keeps Weapon::default(),
+/// except target_armor_max, which starts at Super (penetrates any armor)
+///
+/// Point defense and siege are recognised by tag, so as_point_defense() /
+/// as_siege() prefix the tag (AM- / NM-) at build() time.
+#[derive(Debug, Clone)]
+pub struct WeaponBuilder {
+    weapon: Weapon,
+    point_defense: bool,
+    siege: bool,
+}
+
+impl Default for WeaponBuilder {
+    fn default() -> Self {
+        WeaponBuilder {
+            weapon: Weapon { target_armor_max: ArmorClass::Super, ..Default::default() },
+            point_defense: false,
+            siege: false,
+        }
+    }
+}
+
+impl WeaponBuilder {
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.weapon.tag = tag.to_string();
+        self
+    }
+
+    pub fn dps(mut self, dps: f32) -> Self {
+        self.weapon.dps = dps;
+        self
+    }
+
+    pub fn fire_rate(mut self, fire_rate: f32) -> Self {
+        self.weapon.fire_rate = fire_rate;
+        self
+    }
+
+    pub fn range(mut self, optimal: f32, max: f32) -> Self {
+        self.weapon.optimal_range = optimal;
+        self.weapon.max_range = max;
+        self
+    }
+
+    pub fn cooldown(mut self, cooldown: f32) -> Self {
+        self.weapon.cooldown = cooldown;
+        self
+    }
+
+    pub fn armor_max(mut self, armor: ArmorClass) -> Self {
+        self.weapon.target_armor_max = armor;
+        self
+    }
+
+    pub fn sequence(mut self, sequence: Vec<bool>) -> Self {
+        self.weapon.sequence = sequence;
+        self
+    }
+
+    pub fn tracking(mut self, tracking: f32) -> Self {
+        self.weapon.tracking = tracking;
+        self
+    }
+
+    /// Time of the last shot - anything a cooldown before the battle starts
+    /// makes the weapon ready on the first tick
+    pub fn last_fired(mut self, time: f64) -> Self {
+        self.weapon.last_fired = time;
+        self
+    }
+
+    pub fn as_point_defense(mut self) -> Self {
+        self.point_defense = true;
+        self
+    }
+
+    /// Point defense: per-missile intercept chance and how much a volley dilutes it
+    pub fn intercept(mut self, chance: f32, saturation_factor: f32) -> Self {
+        self.weapon.am_intercept_chance = chance;
+        self.weapon.saturation_factor = saturation_factor;
+        self
+    }
+
+    pub fn as_siege(mut self) -> Self {
+        self.siege = true;
+        self
+    }
+
+    pub fn build(self) -> Weapon {
+        let mut weapon = self.weapon;
+        weapon.category = default_category(&weapon.tag);
+        if self.point_defense && weapon.category != WeaponCategory::PointDefense {
+            weapon.tag = format!("AM-{}", weapon.tag);
+            weapon.category = WeaponCategory::PointDefense;
+        }
+        if self.siege && weapon.category != WeaponCategory::Siege {
+            weapon.tag = format!("NM-{}", weapon.tag);
+            weapon.category = WeaponCategory::Siege;
+        }
+        weapon
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_armor_effectiveness() {
+        use ArmorClass::*;
+
         // Weapon can handle armor
-        assert_eq!(calculate_armor_effectiveness(1.0, 2.0), 1.0);
-        assert_eq!(calculate_armor_effectiveness(2.0, 2.0), 1.0);
+        assert_eq!(calculate_armor_effectiveness(Light, Medium), 1.0);
+        assert_eq!(calculate_armor_effectiveness(Medium, Medium), 1.0);
         
         // One tier above
-        assert_eq!(calculate_armor_effectiveness(3.0, 2.0), 0.5);
+        assert_eq!(calculate_armor_effectiveness(Heavy, Medium), 0.5);
         
         // Two tiers above
-        assert_eq!(calculate_armor_effectiveness(4.0, 2.0), 0.25);
+        assert_eq!(calculate_armor_effectiveness(Super, Medium), 0.25);
         
         // Three+ tiers above
-        assert_eq!(calculate_armor_effectiveness(5.0, 2.0), 0.1);
+        assert_eq!(calculate_armor_effectiveness(Heavy, None), 0.1);
+        assert_eq!(calculate_armor_effectiveness(Super, None), 0.1);
     }
 
     #[test]
@@ -263,4 +711,114 @@ mod tests {
         // Beyond max range
         assert_eq!(calculate_range_falloff(150.0, 50.0, 100.0), 0.1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_tag_checks_ignore_case() {
+        assert!(tag_contains("Heavy-NUKE", "nuke"));
+        assert!(tag_contains("anything", ""));
+        assert!(!tag_contains("AM", "anti-missile"));
+        assert!(tag_starts_with("Hm2", "hm"));
+        assert!(!tag_starts_with("h", "hm"));
+        assert!(!tag_starts_with("éh", "hm"));
+    }
+
+    #[test]
+    fn test_weapon_builder() {
+        let gun = Weapon::builder().tag("LASER").dps(25.0).range(60.0, 90.0).build();
+        assert_eq!((gun.fire_rate, gun.cooldown), (1.0, 1.0));
+        assert_eq!((gun.optimal_range, gun.max_range), (60.0, 90.0));
+        assert_eq!(gun.target_armor_max, ArmorClass::Super);
+        assert!(gun.sequence.is_empty());
+
+        // Categories are tag based - the builder prefixes, order doesn't matter
+        let pd = Weapon::builder().as_point_defense().tag("2").build();
+        assert_eq!(pd.tag, "AM-2");
+        assert_eq!(pd.category, WeaponCategory::PointDefense);
+        assert_eq!(Weapon::builder().tag("AM1").as_point_defense().build().tag, "AM1");
+        let nuke = Weapon::builder().tag("1").as_siege().build();
+        assert_eq!((nuke.tag.as_str(), nuke.category), ("NM-1", WeaponCategory::Siege));
+
+        let laser = Weapon::laser(30.0);
+        assert_eq!((laser.dps, laser.max_range), (30.0, 100.0));
+        assert!(!is_interceptable(&laser));
+        let missile = Weapon::missile(40.0);
+        assert!(is_interceptable(&missile) && missile.max_range > laser.max_range);
+        let pd = Weapon::point_defense(5.0);
+        assert_eq!((pd.tag.as_str(), pd.category), ("AM-PD", WeaponCategory::PointDefense));
+        assert_eq!(laser.category, WeaponCategory::Energy);
+    }
+
+    #[test]
+    fn test_tag_registry() {
+        for category in WeaponCategory::ALL {
+            assert_eq!(serde_json::to_value(category).unwrap(), category.name());
+            assert_eq!(category.name().to_uppercase().parse::<WeaponCategory>(), Ok(category));
+        }
+        let mut registry = WeaponTagRegistry::default();
+        assert_eq!(registry.categorize("AM-1"), WeaponCategory::PointDefense);
+        assert_eq!(registry.categorize("nm-heavy"), WeaponCategory::Siege);
+        assert_eq!(registry.categorize("Flak anti-missile"), WeaponCategory::PointDefense);
+        assert_eq!(registry.categorize("RAILGUN"), WeaponCategory::Kinetic);
+        for tag in ["AM-1", "NM-2", "LASER", "Heavy-NUKE", "RAILGUN"] {
+            assert_eq!(registry.categorize(tag), default_category(tag));
+        }
+
+        // Longest prefix wins
+        registry.register("AMP", WeaponCategory::Energy);
+        assert_eq!(registry.categorize("AMP-CANNON"), WeaponCategory::Energy);
+        assert_eq!(registry.categorize("AM-1"), WeaponCategory::PointDefense);
+        registry.register("RAIL", WeaponCategory::Tractor);
+        let mut unit = BattleUnit::builder().id(1).faction(1)
+            .weapons(vec![Weapon::builder().tag("RAILGUN").build(), Weapon::builder().tag("AMP-1").build()])
+            .build()
+            .unwrap();
+        registry.categorize_weapons(&mut unit);
+        assert_eq!(unit.weapons[0].category, WeaponCategory::Tractor);
+        assert_eq!(unit.weapons[1].category, WeaponCategory::Energy);
+
+        assert_eq!("EMP".parse::<WeaponCategory>(), Ok(WeaponCategory::Emp));
+        assert_eq!("point_defense".parse::<WeaponCategory>(), Ok(WeaponCategory::PointDefense));
+        assert!("laser".parse::<WeaponCategory>().is_err());
+    }
+
+    #[test]
+    fn test_sequence_uses_offset() {
+        let mut weapon = Weapon::builder().sequence(vec![true, false, false]).build();
+        assert!(can_fire_sequence(&weapon, 3));
+        weapon.sequence_offset = Some(1);
+        assert!(!can_fire_sequence(&weapon, 3));
+        assert!(can_fire_sequence(&weapon, 2));
+    }
+
+    #[test]
+    fn test_hit_chance_boundaries() {
+        let weapon = Weapon::builder().tracking(0.1).build();
+        let unit = || BattleUnit::builder().id(2).faction(2).build().unwrap();
+        let target = BattleUnit { signature_radius: 40.0, ..unit() };
+
+        // Stationary, untracked weapon or no signature - always hits
+        assert_eq!(hit_chance(&weapon, &target, 0.0, 100.0), 1.0);
+        assert_eq!(hit_chance(&Weapon::default(), &target, 500.0, 100.0), 1.0);
+        assert_eq!(hit_chance(&weapon, &unit(), 500.0, 100.0), 1.0);
+
+        // Crossing at exactly the tracking speed with the reference signature
+        assert!((hit_chance(&weapon, &target, 10.0, 100.0) - 0.5).abs() < 1e-6);
+        // Half the signature at the same angular speed: 0.5^4
+        let small = BattleUnit { signature_radius: 20.0, ..unit() };
+        assert!((hit_chance(&weapon, &small, 10.0, 100.0) - 0.0625).abs() < 1e-6);
+        // Farther away is easier, point blank is clamped and near impossible
+        assert!(hit_chance(&weapon, &target, 10.0, 1000.0) > 0.99);
+        assert!(hit_chance(&weapon, &target, 10.0, 0.0) < 1e-6);
+    }
+
+    #[test]
+    fn test_intercept_chance_saturates() {
+        let pd = Weapon::builder().tag("PD").intercept(0.8, 0.2).as_point_defense().build();
+        assert!((intercept_chance(&pd, 1) - 0.8).abs() < 1e-6);
+        // 0.8 / (1 + 4 * 0.2)
+        assert!((intercept_chance(&pd, 5) - 0.444).abs() < 1e-3);
+        assert_eq!(intercept_chance(&pd, 0), intercept_chance(&pd, 1));
+        let unsaturated = Weapon { saturation_factor: 0.0, ..pd };
+        assert_eq!(intercept_chance(&unsaturated, 50), 0.8);
+    }
+}
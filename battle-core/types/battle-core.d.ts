@@ -0,0 +1,633 @@
+// Generated by `cargo run --features typegen --bin generate-types` from the
+// battle-core JsonSchema derives - do not edit.
+
+export type ArmorClass = "none" | "light" | "medium" | "heavy" | "super" | number;
+
+/** Thrown by the WASM methods - switch on code; the other fields depend on it */
+export interface BattleError {
+    code: "JSON_PARSE" | "UNIT_NOT_FOUND" | "INVALID_STATE" | "WEAPON_NOT_FOUND" | "OUT_OF_BOUNDS" | "INVALID_UNIT" | "INVALID_ARGUMENT" | "ENCODE" | "SCHEMA_VALIDATION";
+    /** SCHEMA_VALIDATION: "path: message" per problem */
+    errors?: string[];
+    /** JSON_PARSE, INVALID_ARGUMENT: the argument */
+    field?: string;
+    message: string;
+    /** INVALID_UNIT: { kind, id, ... } (see validation.rs) */
+    problem?: Record<string, unknown>;
+    /** WEAPON_NOT_FOUND */
+    tag?: string;
+    /** UNIT_NOT_FOUND, WEAPON_NOT_FOUND */
+    unit_id?: number;
+    /** ENCODE */
+    what?: string;
+    /** OUT_OF_BOUNDS */
+    x?: number;
+    /** OUT_OF_BOUNDS */
+    y?: number;
+    /** OUT_OF_BOUNDS */
+    z?: number;
+}
+
+/** Result of simulate_until_end - CompletionReport without the per-unit detail */
+export interface BattleOutcome {
+    reason: CompletionReason;
+    /** Units still in the battle */
+    survivors: number;
+    /** Simulator tick at the end of the run */
+    tick: number;
+    /** Ticks simulated by this call */
+    ticks: number;
+    winner: number | null;
+}
+
+/**
+ * Battle state saved by snapshot() for restore()
+ *
+ * Units as they were, with the tick counters, alliances, grid cell size,
+ * reinforcements still to arrive, resource income and battle outcome so far
+ * that go with them. The objectives and resource nodes themselves aren't
+ * part of it - only their progress and owners.
+ */
+export interface BattleSnapshot {
+    /** Allied faction pairs as (low, high) */
+    alliances: [number, number][];
+    /** Spatial grid cell size (None for an octree) */
+    cell_size?: number | null;
+    /** (faction_id, objective) completed so far, in order */
+    completed_objectives?: [number, string][];
+    /** Resources each faction had earned from nodes */
+    faction_resources?: Record<string, unknown>;
+    /** Focus target per group */
+    group_targets?: Record<string, unknown>;
+    idle_tick_count?: number;
+    last_combat_tick: number;
+    /** Idle tracking - last tick movement came in, and idle ticks so far */
+    last_movement_tick?: number;
+    /** Faction holding each resource node, by node id (unowned nodes left out) */
+    node_owners?: Record<string, unknown>;
+    /** Progress per objective (ControlZone hold streaks) */
+    objective_progress?: ObjectiveProgress[];
+    /** Waves scheduled but not arrived yet, in arrival order */
+    reinforcements?: ReinforcementWave[];
+    tick: number;
+    /** Simulated time of the tick (weapon cooldowns count from it) */
+    time?: number;
+    units: BattleUnit[];
+    /** The objective or config.victory that had decided the battle */
+    victory?: VictoryState | null;
+}
+
+/**
+ * Memory-optimized battle unit
+ *
+ * Uses flat primitives for cache efficiency
+ * ~250 bytes per unit in Rust
+ */
+export interface BattleUnit {
+    /** NPC / offline unit - the simulator moves it itself */
+    ai_controlled?: boolean;
+    /** state isn't destroyed - read for units without state */
+    alive?: boolean;
+    armor: ArmorClass;
+    can_surrender?: boolean;
+    carrier_id?: number | null;
+    /** Key into the targeting PriorityTable */
+    class?: string;
+    current_waypoint?: number;
+    damage_dealt: number;
+    damage_taken: number;
+    disable_threshold?: number;
+    effects?: StatusEffect[];
+    energy?: number;
+    energy_regen?: number;
+    experience?: number;
+    external_move_time?: number;
+    external_speed?: number;
+    /** Units of different factions fight unless allied */
+    faction_id: number;
+    /** Hull damage taken off every hit (None = 0.5 per armor tier) */
+    flat_armor?: number | null;
+    group_id?: number | null;
+    hangar?: DroneHangar | null;
+    hangar_capacity?: number;
+    hangar_contents?: number[];
+    has_weapons?: boolean;
+    healing_done?: number;
+    hp: number;
+    id: number;
+    is_commander?: boolean;
+    is_disabled?: boolean;
+    is_in_hangar?: boolean;
+    is_ship?: boolean;
+    is_station?: boolean;
+    is_surrendered?: boolean;
+    kills?: number;
+    lock_time?: number;
+    max_energy?: number;
+    max_hp: number;
+    max_shield: number;
+    max_speed: number;
+    /** Longest weapon max_range - derived from weapons when the simulator normalizes the unit */
+    max_weapon_range: number;
+    morale?: number;
+    morale_loss_per_death?: number;
+    morale_radius?: number;
+    morale_regen_rate?: number;
+    move_order?: [number, number, number] | null;
+    orbit_angle?: number;
+    orbit_mode?: boolean;
+    player_id?: number | null;
+    pos_x: number;
+    pos_y: number;
+    pos_z: number;
+    power_engines?: number;
+    power_shield?: number;
+    power_weapons?: number;
+    quiet_ticks?: number;
+    radius?: number;
+    repair_rate?: number;
+    resupply_range?: number;
+    resupply_rate?: number;
+    retreat_hp_fraction?: number;
+    retreat_target?: [number, number, number] | null;
+    retreating?: boolean;
+    self_destruct_damage?: number;
+    self_destruct_radius?: number;
+    self_destruct_trigger_radius?: number;
+    shield: number;
+    shield_regen: number;
+    ship_class?: ShipClass;
+    shots_fired?: number;
+    shots_hit?: number;
+    signature_radius?: number;
+    stance?: Stance;
+    state?: UnitState;
+    /** Scales the distance enemies detect this unit at (0.5 = half their sensor range) */
+    stealth_modifier?: number;
+    surrender_hp_threshold?: number;
+    target_acquired_time?: number;
+    target_id?: number | null;
+    target_locked?: boolean;
+    unit_type?: string;
+    vel_x: number;
+    vel_y: number;
+    vel_z: number;
+    veterancy_level?: number;
+    /** Sensor range (weapon range when that's longer) - enemies are detected and targets stay valid inside it */
+    view_range?: number;
+    waypoints?: [number, number, number][];
+    weapon_stats?: WeaponStats[];
+    weapons: Weapon[];
+    /** state is withdrawn - read for units without state */
+    withdrawn?: boolean;
+    xp_per_damage?: number;
+    xp_per_kill?: number;
+}
+
+/** Why run_to_completion stopped */
+export type CompletionReason = "stalemate" | "elimination" | "objective" | "tick_cap";
+
+/** Final report from run_to_completion */
+export interface CompletionReport {
+    factions: FactionSummary[];
+    reason: CompletionReason;
+    survivors: SurvivingUnit[];
+    /** Simulator tick at the end of the run */
+    tick: number;
+    /** Ticks simulated by this call */
+    ticks: number;
+    ticksSinceLastCombat: number;
+    /** Battle-wide weapon stats by tag (see weapon_totals) */
+    weapons: Record<string, WeaponStats>;
+    winner: number | null;
+}
+
+export interface DamagedUnit {
+    /**
+     * Weapon category of most of this tick's hits ("kinetic", "energy", ...),
+     * "burn" for status effects, "explosive" for self-destruct blasts
+     */
+    damage_type: string;
+    hp: number;
+    /** Hull / shield lost this tick */
+    hull_damage: number;
+    id: number;
+    /**
+     * Unit that dealt the most damage this tick (None: burns only, or the
+     * attacker has left the battle)
+     */
+    primary_attacker_id: number | null;
+    shield: number;
+    shield_damage: number;
+}
+
+export interface DestroyedUnit {
+    id: number;
+    overkill: number;
+}
+
+/**
+ * Drones a carrier builds and launches itself (see BattleSimulator's hangar
+ * pass)
+ *
+ * A store of its own, apart from hangar_capacity / hangar_contents: drones
+ * waiting in `count` (built or recovered) don't take up hangar_capacity,
+ * which only limits units docked with dock_fighter - a drone docked that
+ * way counts like any other unit.
+ *
+ * JSON: { "template": { ...BattleUnit... }, "count": 6, "launch_interval": 1.5 }
+ * - the rest is optional or kept by the simulator.
+ */
+export interface DroneHangar {
+    /**
+     * Drones ready to launch - recovered drones go back into it, a
+     * destroyed one is gone for good
+     */
+    count: number;
+    /** Ticks in a row with no enemy in view */
+    idle_ticks?: number;
+    /** Simulated time of the last launch */
+    last_launch?: number;
+    /** Seconds between launches */
+    launch_interval: number;
+    /**
+     * Ticks without an enemy in view before launched drones are recalled
+     * (0 = the first tick without one)
+     */
+    recover_after_ticks?: number;
+    /**
+     * Unit every drone is built from - faction, player, id and position are
+     * filled in at launch, view_range raised to the carrier's
+     */
+    template: BattleUnit;
+}
+
+/** Effect a weapon attaches to its target on hit */
+export interface EffectSpec {
+    /** Seconds */
+    duration: number;
+    kind: StatusEffectKind;
+    magnitude: number;
+}
+
+/**
+ * Per-faction balance - factions without one fight at 1x
+ *
+ * JSON: { "faction_id": 2, "damage_multiplier": 1.1, "special_weapon_tags": ["PLASMA"] }
+ * - the multipliers default to 1, the tags to none.
+ */
+export interface FactionConfig {
+    /** Scales the damage of every shot the faction's units fire */
+    damage_multiplier?: number;
+    faction_id: number;
+    shield_regen_multiplier?: number;
+    /**
+     * Weapon tag prefixes only this faction may fire - other factions'
+     * units carrying them hold fire with them
+     */
+    special_weapon_tags?: string[];
+    /** Scales max_speed of simulator-moved units */
+    speed_multiplier?: number;
+}
+
+/** Per-faction head count (see faction_summary) */
+export interface FactionSummary {
+    /** Still on the battlefield */
+    active: number;
+    destroyed: number;
+    /** Shut down (may repair back online) */
+    disabled: number;
+    faction_id: number;
+    /** Alive but out of the fight */
+    surrendered: number;
+    /** Retreated out of the battle - not a loss */
+    withdrawn: number;
+}
+
+/** Guided shot that lost its target and found no other (TickResult.missiles_fizzled) */
+export interface MissileFizzled {
+    attackerId: number;
+    targetId: number;
+    weaponType: string;
+}
+
+/** Guided shot that turned on a new target (TickResult.missiles_retargeted) */
+export interface MissileRetargeted {
+    attackerId: number;
+    /** The target that died before the shot landed */
+    fromId: number;
+    toId: number;
+    weaponType: string;
+}
+
+export interface MovedUnit {
+    id: number;
+    /** Velocity over the last tick (units/sec) for client-side extrapolation */
+    vx: number;
+    vy: number;
+    vz: number;
+    x: number;
+    y: number;
+    z: number;
+}
+
+/** Per-objective state kept by the simulator */
+export interface ObjectiveProgress {
+    completed: boolean;
+    /** Consecutive ticks the zone has been held (ControlZone only) */
+    held_ticks: number;
+}
+
+/** Timing of one phase */
+export interface PhaseStats {
+    /** Over the last `window` samples */
+    avg_ms: number;
+    max_ms: number;
+    phase: ProfilePhase;
+    /** Samples since profiling started */
+    samples: number;
+    /** Time spent since profiling started */
+    total_ms: number;
+}
+
+/** Position update for syncing external movement */
+export interface PositionUpdate {
+    clear_target?: boolean;
+    id: number;
+    x: number;
+    y: number;
+    z: number;
+}
+
+/** Parts of a tick, in the order they run */
+export type ProfilePhase = "grid" | "targeting" | "movement" | "combat" | "damage" | "aftermath" | "shield_regen" | "serialize";
+
+/** get_profile_stats JSON - phases in tick order */
+export interface ProfileStats {
+    phases: PhaseStats[];
+    /** Ticks profiled */
+    ticks: number;
+    window: number;
+}
+
+/** Units that join the battle at a given tick (see schedule_wave) */
+export interface ReinforcementWave {
+    arrival_tick: number;
+    /** Faction the units fight for (None keeps each unit's faction_id) */
+    faction_id?: number | null;
+    /** Point the units arrive around (None keeps their positions) */
+    spawn?: [number, number, number] | null;
+    units: BattleUnit[];
+}
+
+export interface RepairedUnit {
+    hp: number;
+    id: number;
+    shield: number;
+}
+
+/**
+ * A unit that blew itself up (TickResult.self_destructed) - position and
+ * radius of the blast
+ */
+export interface SelfDestructed {
+    /** 0 for a triggered unit, n for one set off by a blast n links down a chain */
+    depth: number;
+    id: number;
+    radius: number;
+    x: number;
+    y: number;
+    z: number;
+}
+
+/** A faction picking up or losing sight of an enemy unit */
+export interface SensorContact {
+    faction_id: number;
+    unit_id: number;
+}
+
+/**
+ * Hull class - the simulator can restrict which weapons each class carries
+ * (see weapons::is_weapon_allowed)
+ */
+export type ShipClass = "fighter" | "corvette" | "frigate" | "destroyer" | "cruiser" | "battleship" | "carrier" | "station";
+
+/** How freely a unit engages */
+export type Stance = "aggressive" | "defensive" | "hold_fire";
+
+/** Active effect on a unit */
+export interface StatusEffect {
+    /** Active while the tick is below this */
+    expires_at_tick: number;
+    kind: StatusEffectKind;
+    magnitude: number;
+    source_id?: number | null;
+}
+
+export type StatusEffectKind = "speed_slow" | "shield_disrupt" | "burn";
+
+/** Surviving unit in a CompletionReport */
+export interface SurvivingUnit {
+    faction_id: number;
+    hp: number;
+    id: number;
+    shield: number;
+}
+
+export interface TickResult {
+    /** Enemies a faction's sensors picked up this tick */
+    contactsDetected: SensorContact[];
+    /** Enemies a faction lost sight of this tick (still on the battlefield) */
+    contactsLost: SensorContact[];
+    damaged: DamagedUnit[];
+    /** Units destroyed this tick - losses (recovered drones are in `withdrawn`) */
+    destroyed: number[];
+    /** Units that shut down this tick (below their disable_threshold) */
+    disabled: number[];
+    /** Units docked with dock_fighter since the last tick */
+    docked: number[];
+    /** Units whose status effects changed this tick (applied or expired) */
+    effects: UnitEffects[];
+    /** Capacitor levels that changed since the last tick (units with max_energy only) */
+    energy: UnitEnergy[];
+    /** ✅ NEW: Whether this was an idle tick (minimal processing) */
+    isIdle: boolean;
+    /** `moved` lists every unit on the battlefield (see emit_all_positions_every_n_ticks) */
+    isKeyframe: boolean;
+    /**
+     * Units that left a hangar since the last tick (launch_fighter, or their
+     * carrier died)
+     */
+    launched: number[];
+    /** (unit id, new veterancy level) for units that levelled up this tick */
+    levelUps: [number, number][];
+    /**
+     * Guided shots whose target died before they landed, with nothing left
+     * in reach
+     */
+    missilesFizzled: MissileFizzled[];
+    /**
+     * Guided shots whose target died before they landed and that turned on
+     * another enemy - they land next tick
+     */
+    missilesRetargeted: MissileRetargeted[];
+    /** (unit id, morale) for units whose morale changed this tick */
+    moraleEvents: [number, number][];
+    moved: MovedUnit[];
+    /**
+     * Objectives completed this tick, as "faction_id:objective"
+     * (e.g. "1:kill_unit(42)")
+     */
+    objectivesMet: string[];
+    /** Damage wasted on each unit in `destroyed` past what killed it */
+    overkill: DestroyedUnit[];
+    /** Disabled units that repaired themselves back online this tick */
+    reactivated: number[];
+    /** (faction_id, unit ids) for reinforcements that arrived this tick */
+    reinforcementsArrived: [number, number[]][];
+    /** Units restored by repair weapons this tick (values after repair) */
+    repaired: RepairedUnit[];
+    /** (faction_id, amount) earned from resource nodes this tick */
+    resourcesGained: [number, number][];
+    /** Units that withdrew from the battle this tick (retreated out of reach) */
+    retreated: number[];
+    /** Units currently retreating (still on the battlefield) */
+    retreatingUnits: number[];
+    /** Units that blew themselves up this tick (also in `destroyed`) */
+    selfDestructed: SelfDestructed[];
+    /** Reinforcements that arrived and carrier drones built this tick */
+    spawned: number[];
+    /** More than half the stalemate threshold has passed without combat */
+    stalemateWarning: boolean;
+    /** Units that surrendered this tick */
+    surrendered: number[];
+    tick: number;
+    /** Compute time of the tick in microseconds (0 for paused ticks) */
+    tickDurationUs: number;
+    /**
+     * TICK_RESULT_VERSION of the simulator that produced it (0: from before
+     * results were versioned)
+     */
+    version: number;
+    weaponsFired: WeaponFired[];
+    /**
+     * Units that left the battle without being destroyed since the last
+     * tick - `retreated`, units removed with remove_unit and drones
+     * recovered by their carrier
+     */
+    withdrawn: number[];
+}
+
+/** Full current effect list for a unit (empty = all effects cleared) */
+export interface UnitEffects {
+    effects: StatusEffect[];
+    id: number;
+}
+
+export interface UnitEnergy {
+    energy: number;
+    id: number;
+}
+
+/**
+ * Whether a unit is still in the fight
+ *
+ * Withdrawn units left without being destroyed (retreat, removal) - they
+ * aren't targetable and don't count as losses.
+ */
+export type UnitState = "active" | "destroyed" | "withdrawn";
+
+/**
+ * A unit type to copy from - base_unit's id, faction and position are
+ * replaced on every copy
+ */
+export interface UnitTemplate {
+    base_unit: BattleUnit;
+    template_id: string;
+}
+
+/** How an objective or config.victory decided the battle (get_victory_state) */
+export interface VictoryState {
+    /** The objective or condition that was met, e.g. "destroy_unit(7)" */
+    condition: string;
+    tick: number;
+    /** None when nobody is left to claim it */
+    winner?: number | null;
+}
+
+export interface Weapon {
+    /** Point defense: chance (0-1) to shoot down an incoming missile */
+    am_intercept_chance?: number;
+    ammo?: number | null;
+    ammo_capacity?: number;
+    ammo_per_shot?: number;
+    ammo_remaining?: number;
+    applies_effect?: EffectSpec | null;
+    /** Set from the simulator's tag registry when the unit joins */
+    category?: WeaponCategory;
+    /** Seconds between shots */
+    cooldown: number;
+    /** Damage per second */
+    dps: number;
+    energy_cost?: number;
+    /** Shots per second */
+    fire_rate: number;
+    independent_targeting?: boolean;
+    is_disabled?: boolean;
+    is_repair?: boolean;
+    last_fired: number;
+    magazine_size?: number;
+    max_range: number;
+    optimal_range: number;
+    projectile_speed?: number;
+    reload_time?: number;
+    reloading_until?: number | null;
+    repairs_shield?: boolean;
+    /** Point defense: how much each extra missile arriving at once dilutes am_intercept_chance */
+    saturation_factor?: number;
+    sequence?: boolean[];
+    sequence_index?: number;
+    sequence_offset?: number | null;
+    shield_damage_bonus?: number;
+    shield_pierce?: number;
+    tag: string;
+    /** Heaviest armor class hit at full damage */
+    target_armor_max: ArmorClass;
+    tracking?: number;
+}
+
+/** What a weapon is, as far as targeting and interception care */
+export type WeaponCategory = "kinetic" | "energy" | "explosive" | "tractor" | "ion" | "emp" | "point_defense" | "siege";
+
+export interface WeaponFired {
+    /** Rounds left after this shot (only for weapons with limited ammo) */
+    ammoRemaining?: number | null;
+    attackerId: number;
+    /**
+     * Attacker and target positions when the shot was fired - all six set,
+     * or none when config.include_fire_positions is off
+     */
+    ax?: number | null;
+    ay?: number | null;
+    az?: number | null;
+    /** False for a miss - the shot was spent but does no damage */
+    hit: boolean;
+    impactTime: number;
+    /** Missile shot down by point defense (hit is false) */
+    intercepted?: boolean;
+    /** Misses only: where the shot ends, relative to (tx, ty, tz) */
+    missOffset?: [number, number, number] | null;
+    targetId: number;
+    tx?: number | null;
+    ty?: number | null;
+    tz?: number | null;
+    weaponType: string;
+}
+
+/** Counters for one weapon (or every weapon with one tag, once merged) */
+export interface WeaponStats {
+    damage_dealt: number;
+    kills: number;
+    shots_fired: number;
+    /** Shots that did damage */
+    shots_hit: number;
+}